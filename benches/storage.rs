@@ -0,0 +1,63 @@
+//! Benchmarks of the storage of the pallets with a large state, so performance regressions of the
+//! `BTreeMap`s the pallets keep their state in are visible. Run them with `cargo bench`.
+//!
+//! The execution of whole blocks is measured by the `benchmark` command of the node instead, since
+//! the runtime is only built into the binary.
+
+use pallets::balances;
+use pallets::benchmarking::{self, account, Rng};
+use pallets::crypto::AccountId32;
+use pallets::storage::InMemoryBackend;
+
+// The number of accounts the state is populated with.
+const ACCOUNTS: u32 = 1_000_000;
+// The number of samples of each benchmark.
+const SAMPLES: usize = 10;
+
+#[derive(Clone)]
+struct BenchConfig;
+
+impl pallets::system::Config for BenchConfig {
+    type AccountId = AccountId32;
+    type BlockNumber = u32;
+    type Nonce = u32;
+    type RuntimeEvent = ();
+    type Hashing = pallets::hashing::Sha256;
+}
+
+impl balances::Config for BenchConfig {
+    type Balance = u128;
+    const MINIMUM_TRANSFER: Self::Balance = 1;
+    const MAX_TRANSFERS_PER_BLOCK: u32 = u32::MAX;
+    const EXISTENTIAL_DEPOSIT: Self::Balance = 1;
+}
+
+fn main() {
+    let mut balances = balances::Pallet::<BenchConfig>::new();
+    benchmarking::populate_balances(&mut balances, ACCOUNTS, 1_000);
+    let mut rng = Rng(42);
+
+    // Transfers between random accounts, which look up and update two entries of the map each.
+    let transfers = benchmarking::bench(
+        "balances/transfer/1000",
+        SAMPLES,
+        || balances.clone(),
+        |mut balances| {
+            for _ in 0..1_000 {
+                let from = account(rng.below(ACCOUNTS.into()) as u32);
+                let to = account(rng.below(ACCOUNTS.into()) as u32);
+                balances.transfer(from, to, 1).expect("every account is funded");
+            }
+            balances
+        },
+    );
+    println!("{transfers}");
+
+    // Writing the whole state into a backend and computing its root, like every block does.
+    let state_root = benchmarking::bench("balances/store_state", SAMPLES, InMemoryBackend::new, |mut state| {
+        balances.store_state(&mut state);
+        let root = state.root();
+        (state, root)
+    });
+    println!("{state_root}");
+}
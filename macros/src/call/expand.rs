@@ -3,14 +3,31 @@ use quote::quote;
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-	let CallDef { pallet_struct, methods } = def;
+	let CallDef { pallet_struct, methods, context, instance } = def;
+
+	// These are the generics of the `Call` of the pallet: the parameters of its `impl`s, the
+	// arguments of its type, and the parameters of its declaration, where the instance defaults to
+	// `()`. An instantiable pallet has a `Call` for each of its instances.
+	let (impl_generics, type_generics, decl_generics) = match &instance {
+		Some(instance) => (
+			quote! { <T: Config<#instance>, #instance: crate::support::Instance> },
+			quote! { <T, #instance> },
+			quote! { <T: Config<#instance>, #instance: crate::support::Instance = ()> },
+		),
+		None => (quote! { <T: Config> }, quote! { <T> }, quote! { <T: Config> }),
+	};
+	// The type parameters the `Call` must use, even when no call has an argument depending on them.
+	let phantom = match &instance {
+		Some(instance) => quote! { (T, #instance) },
+		None => quote! { T },
+	};
 
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
-	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
-	// first two parameters to these calls.
+	// not include the `self` or `caller: T::AccountId` (or `origin: OriginFor<T>`) parameter, which
+	// we always assume are the first two parameters to these calls.
 	let args_name = methods
 		.iter()
 		.map(|method| method.args.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>())
@@ -23,42 +40,230 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// This is a nested vector of the names of the types in `args_type`, as written in the pallet,
+	// like `T::AccountId`.
+	let args_type_name = args_type
+		.iter()
+		.map(|types| types.iter().map(|type_| type_name(type_)).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// This is a vector of the dispatch logic for each of the functions in `fn_name`. Functions which
+	// return a value on success have it boxed into the `DispatchOutput`, the others output `None`.
+	let dispatch_body = methods
+		.iter()
+		.zip(&args_name)
+		.map(|(method, args_name)| {
+			let fn_name = &method.name;
+			// The context is passed as the last argument of the calls which take it.
+			let context_arg = method.takes_context.then(|| quote! { context });
+			// The first argument of every call is either its origin, or the account which signed it.
+			let origin_arg = if method.takes_origin {
+				quote! { origin }
+			} else {
+				quote! { crate::support::ensure_signed(origin)? }
+			};
+			let fn_call = quote! { self.#fn_name(#origin_arg, #( #args_name, )* #context_arg)? };
+			if method.returns_value {
+				quote! { Ok(crate::support::dispatch_output(#fn_call)) }
+			} else {
+				quote! {
+					#fn_call;
+					Ok(None)
+				}
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// This is a vector of the weight of each of the functions in `fn_name`.
+	let weight = methods.iter().map(|method| &method.weight).collect::<Vec<_>>();
+
+	// The body of the dispatch function, which is the same whether the calls need a context or not.
+	let dispatch_fn_body = quote! {
+		match call {
+			#(
+				Call::#fn_name { #( #args_name ),* } => {
+					#dispatch_body
+				},
+			)*
+			Call::__Ignore(_, never) => match never {},
+		}
+	};
+
+	// This is the index of each of the functions in `fn_name`, which is the first byte of the
+	// encoding of their call.
+	let call_index = methods.iter().map(|method| method.call_index).collect::<Vec<_>>();
+	// The type of every argument of every call must be encodable for the `Call` to be encodable.
+	let all_args_type = args_type.iter().flatten().collect::<Vec<_>>();
+
+	// The SCALE encoding of a call is the index of the call, followed by its arguments in order.
+	// See the `codec` module of the pallets.
+	let codec_impl = quote! {
+		impl #impl_generics crate::codec::Encode for Call #type_generics
+		where
+			#( #all_args_type: crate::codec::Encode, )*
+		{
+			fn encode_to(&self, dest: &mut crate::codec::Vec<u8>) {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							dest.push(#call_index);
+							#( crate::codec::Encode::encode_to(#args_name, dest); )*
+						},
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl #impl_generics crate::codec::Decode for Call #type_generics
+		where
+			#( #all_args_type: crate::codec::Decode, )*
+		{
+			fn decode(input: &mut &[u8]) -> Result<Self, crate::codec::Error> {
+				Self::decode_with_depth(input, crate::codec::MAX_CALL_DEPTH)
+			}
+
+			// The arguments of a call can hold other calls, like the call of a sudo call, so
+			// they are decoded with the depth left to the call.
+			fn decode_with_depth(input: &mut &[u8], depth: u32) -> Result<Self, crate::codec::Error> {
+				match <u8 as crate::codec::Decode>::decode(input)? {
+					#(
+						#call_index => Ok(Call::#fn_name {
+							#( #args_name: crate::codec::Decode::decode_with_depth(input, depth)?, )*
+						}),
+					)*
+					_ => Err(crate::codec::Error::Invalid),
+				}
+			}
+		}
+	};
+
+	// Pallets whose calls need a context implement `DispatchWithContext`, where the runtime
+	// provides that context. Every other pallet implements `Dispatch`.
+	let dispatch_trait_impl = match context {
+		Some(context) => quote! {
+			impl #impl_generics crate::support::DispatchWithContext<#context> for #pallet_struct #type_generics {
+				type Origin = crate::support::OriginFor<T>;
+				type Call = Call #type_generics;
+
+				fn dispatch(
+					&mut self,
+					context: &mut #context,
+					origin: Self::Origin,
+					call: Self::Call,
+				) -> crate::support::DispatchResultWithOutput {
+					#dispatch_fn_body
+				}
+			}
+		},
+		None => quote! {
+			impl #impl_generics crate::support::Dispatch for #pallet_struct #type_generics {
+				type Origin = crate::support::OriginFor<T>;
+				type Call = Call #type_generics;
+
+				fn dispatch(
+					&mut self,
+					origin: Self::Origin,
+					call: Self::Call,
+				) -> crate::support::DispatchResultWithOutput {
+					#dispatch_fn_body
+				}
+			}
+		},
+	};
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
-	// and the `Dispatch` trait logic to route a `caller` to access those functions.
+	// and the `Dispatch` trait logic to route an `origin` to access those functions.
 	let dispatch_impl = quote! {
 		// The callable functions exposed by this pallet.
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
-		pub enum Call<T: Config> {
+		#[derive(Clone)]
+		pub enum Call #decl_generics {
 			#(
 				#fn_name { #( #args_name: #args_type),* },
 			)*
+			// This variant can't be constructed. It makes sure `T` and the instance are used when no
+			// call has an argument depending on them.
+			#[doc(hidden)]
+			__Ignore(core::marker::PhantomData<#phantom>, core::convert::Infallible),
 		}
 
-		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
-		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
-			type Call = Call<T>;
+		impl #impl_generics Call #type_generics {
+			// The weight of this call, as declared by its `#[weight = ...]` attribute. The
+			// arguments of the call are in scope by reference, so the weight can depend on them.
+			#[allow(unused_variables)]
+			pub fn weight(&self) -> crate::support::Weight {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => #weight,
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
-				match call {
+			// The name of the function called by this call.
+			pub fn name(&self) -> &'static str {
+				match self {
 					#(
-						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
-								#( #args_name ),*
-							)?;
-						},
+						Call::#fn_name { .. } => stringify!(#fn_name),
 					)*
+					Call::__Ignore(_, never) => match *never {},
 				}
-				Ok(())
+			}
+
+			// The metadata of each function of this pallet, in the order they are declared.
+			pub fn metadata() -> crate::codec::Vec<crate::metadata::FunctionMetadata> {
+				crate::codec::Vec::from([
+					#(
+						crate::metadata::FunctionMetadata {
+							name: stringify!(#fn_name),
+							index: #call_index,
+							args: crate::codec::Vec::from([
+								#(
+									crate::metadata::ArgMetadata {
+										name: stringify!(#args_name),
+										ty: #args_type_name,
+									},
+								)*
+							]),
+						},
+					)*
+				])
 			}
 		}
+
+		// The runtime finds the calls of the pallet through this trait.
+		impl #impl_generics crate::support::Callable for #pallet_struct #type_generics {
+			type Call = Call #type_generics;
+		}
+
+		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
+		// appropriate function call with all arguments, including the `caller` or the `origin`.
+		#dispatch_trait_impl
+
+		#codec_impl
 	};
 
 	// Return the generated code.
 	dispatch_impl.into()
 }
+
+/// The name of `type_` as written in the pallet, like `Vec<(T::AccountId, u32)>`. The tokens of the
+/// type are separated by spaces, which are only kept between words and after commas.
+fn type_name(type_: &syn::Type) -> String {
+	let tokens = quote!(#type_).to_string();
+	let mut name = String::new();
+	let mut chars = tokens.chars().peekable();
+	while let Some(c) = chars.next() {
+		let is_word = |c: char| c.is_alphanumeric() || c == '_';
+		match c {
+			' ' if name.ends_with(',') => name.push(' '),
+			' ' if name.ends_with(is_word) && chars.peek().copied().is_some_and(is_word) => name.push(' '),
+			' ' => {},
+			c => name.push(c),
+		}
+	}
+	name
+}
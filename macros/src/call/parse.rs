@@ -5,8 +5,14 @@ use syn::spanned::Spanned;
 mod keyword {
 	syn::custom_keyword!(T);
 	syn::custom_keyword!(AccountId);
+	syn::custom_keyword!(OriginFor);
 }
 
+/// The name of the attribute used to declare the weight of a callable function.
+const WEIGHT_ATTR: &str = "weight";
+/// The name of the attribute used to declare the index of a callable function.
+const CALL_INDEX_ATTR: &str = "call_index";
+
 /// This object will collect all the information we need to keep while parsing the callable
 /// functions.
 #[derive(Debug)]
@@ -16,6 +22,12 @@ pub struct CallDef {
 	pub pallet_struct: syn::Ident,
 	/// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
 	pub methods: Vec<CallVariantDef>,
+	/// This is the type of the context needed to dispatch the callable functions, if any of them
+	/// takes a context argument. See `CallVariantDef`.
+	pub context: Option<Box<syn::Type>>,
+	/// This is the instance parameter of the pallet, when the pallet can be included several times
+	/// in a runtime, like `I` in `impl<T: Config<I>, I: Instance> Pallet<T, I>`.
+	pub instance: Option<syn::Ident>,
 }
 
 /// This is the metadata we keep about each callable function in our pallet.
@@ -25,6 +37,20 @@ pub struct CallVariantDef {
 	pub name: syn::Ident,
 	/// Information on args of the function: `(name, type)`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// Whether the function takes the origin of the call, as `origin: OriginFor<T>`, instead of
+	/// the account which signed it, as `caller: T::AccountId`.
+	pub takes_origin: bool,
+	/// Whether the function returns a value on success, i.e. it returns `Result<R, DispatchError>`
+	/// rather than `DispatchResult`.
+	pub returns_value: bool,
+	/// The weight of the function, declared with `#[weight = ...]`.
+	pub weight: syn::Expr,
+	/// The index of the function, which is the first byte of the encoding of its calls. It is
+	/// declared with `#[call_index = N]`, or else it is the position of the function in the `impl`.
+	pub call_index: u8,
+	/// Whether the function takes a context argument, like `balances: &mut balances::Pallet<T>`,
+	/// as its last argument. It is not part of the call, it is provided by the runtime instead.
+	pub takes_context: bool,
 }
 
 impl CallDef {
@@ -43,8 +69,12 @@ impl CallDef {
 			_ => panic!("not supported tokens"),
 		};
 
-		// Here is where we will store all the callable functions.
+		// The pallet is instantiable when the `impl` has a second type parameter after `T`.
+		let instance = item_impl.generics.type_params().nth(1).map(|param| param.ident.clone());
+
+		// Here is where we will store all the callable functions, and the context they need.
 		let mut methods = vec![];
+		let mut context: Option<Box<syn::Type>> = None;
 		for item in item_impl.items {
 			if let syn::ImplItem::Fn(method) = item {
 				// Here is where we will store all the args for each callable functions.
@@ -59,23 +89,27 @@ impl CallDef {
 					},
 				}
 
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
+				// The second argument should be the `caller: T::AccountId` or the
+				// `origin: OriginFor<T>` argument.
+				let takes_origin = match method.sig.inputs.iter().skip(1).next() {
 					Some(syn::FnArg::Typed(arg)) => {
 						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
+						// `caller: T::AccountId` or `origin: OriginFor<T>`.
+						check_caller_arg(arg)?
 					},
 					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+						let msg = "Invalid call, second argument should be `caller: T::AccountId` \
+							or `origin: OriginFor<T>`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
-				}
+				};
 
 				let fn_name = method.sig.ident.clone();
+				let mut takes_context = false;
 
 				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
-				for arg in method.sig.inputs.iter().skip(2) {
+				let last_arg = method.sig.inputs.len() - 1;
+				for (i, arg) in method.sig.inputs.iter().enumerate().skip(2) {
 					// All arguments should be typed.
 					let arg = if let syn::FnArg::Typed(arg) = arg {
 						arg
@@ -91,24 +125,193 @@ impl CallDef {
 						return Err(syn::Error::new(arg.pat.span(), msg))
 					};
 
+					// A mutable reference is the context argument, which must be the last one.
+					if let syn::Type::Reference(reference) = &*arg.ty {
+						if reference.mutability.is_none() || i != last_arg {
+							let msg = "Invalid call, only the last argument can be a reference, \
+								which must be `&mut` to the context of the call";
+							return Err(syn::Error::new(arg.ty.span(), msg))
+						}
+						check_context_arg(&mut context, &reference.elem)?;
+						takes_context = true;
+						continue
+					}
+
 					// Store the argument name and the argument type for generating code.
 					args.push((arg_ident, arg.ty.clone()));
 				}
 
+				let returns_value = check_returns_value(&method.sig.output);
+				let weight = parse_weight_attr(&method)?;
+				let call_index = parse_call_index_attr(&method, methods.len())?;
+				if let Some(other) = methods.iter().find(|other: &&CallVariantDef| other.call_index == call_index) {
+					let msg = format!("Invalid call, call index {call_index} is already used by `{}`", other.name);
+					return Err(syn::Error::new(method.sig.ident.span(), msg))
+				}
+
 				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+				methods.push(CallVariantDef {
+					name: fn_name,
+					args,
+					takes_origin,
+					returns_value,
+					weight,
+					call_index,
+					takes_context,
+				});
 			}
 		}
 
 		// Return all callable functions for this pallet.
-		Ok(Self { pallet_struct, methods })
+		Ok(Self { pallet_struct, methods, context, instance })
+	}
+}
+
+/// Check that the context argument of a callable function has the same type as the context of
+/// all the other callable functions of the pallet, since they are dispatched with the same context.
+pub fn check_context_arg(context: &mut Option<Box<syn::Type>>, ty: &syn::Type) -> syn::Result<()> {
+	match context {
+		Some(context) if context.to_token_stream().to_string() != ty.to_token_stream().to_string() => {
+			let msg = "Invalid call, all calls of a pallet must take the same context";
+			Err(syn::Error::new(ty.span(), msg))
+		},
+		Some(_) => Ok(()),
+		None => {
+			*context = Some(Box::new(ty.clone()));
+			Ok(())
+		},
+	}
+}
+
+/// Parse the weight of a callable function, which must be declared as `#[weight = ...]`.
+pub fn parse_weight_attr(method: &syn::ImplItemFn) -> syn::Result<syn::Expr> {
+	let mut weights = method.attrs.iter().filter(|attr| attr.path().is_ident(WEIGHT_ATTR));
+
+	let attr = match weights.next() {
+		Some(attr) => attr,
+		None => {
+			let msg = "Invalid call, missing weight: expected `#[weight = ...]`";
+			return Err(syn::Error::new(method.sig.span(), msg))
+		},
+	};
+	if let Some(duplicate) = weights.next() {
+		let msg = "Invalid call, weight must only be declared once";
+		return Err(syn::Error::new(duplicate.span(), msg))
+	}
+
+	match &attr.meta {
+		syn::Meta::NameValue(meta) => Ok(meta.value.clone()),
+		_ => {
+			let msg = "Invalid weight attribute: expected `#[weight = ...]`";
+			Err(syn::Error::new(attr.span(), msg))
+		},
 	}
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
+/// Parse the index of a callable function, declared as `#[call_index = N]`. Functions without it
+/// are indexed by `position`, their position in the `impl`.
+pub fn parse_call_index_attr(method: &syn::ImplItemFn, position: usize) -> syn::Result<u8> {
+	let mut indices = method.attrs.iter().filter(|attr| attr.path().is_ident(CALL_INDEX_ATTR));
+
+	let attr = match indices.next() {
+		Some(attr) => attr,
+		None => {
+			return u8::try_from(position).map_err(|_| {
+				let msg = "Invalid call, a pallet can't have more than 256 calls";
+				syn::Error::new(method.sig.span(), msg)
+			})
+		},
+	};
+	if let Some(duplicate) = indices.next() {
+		let msg = "Invalid call, call index must only be declared once";
+		return Err(syn::Error::new(duplicate.span(), msg))
+	}
+
+	match &attr.meta {
+		syn::Meta::NameValue(syn::MetaNameValue {
+			value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(index), .. }),
+			..
+		}) => index.base10_parse::<u8>(),
+		_ => {
+			let msg = "Invalid call index attribute: expected `#[call_index = N]`, with N from 0 to 255";
+			Err(syn::Error::new(attr.span(), msg))
+		},
+	}
+}
+
+/// Remove the `#[weight = ...]` and `#[call_index = N]` attributes from the callable functions,
+/// since they are not real attributes and are only used by this macro.
+pub fn strip_call_attrs(item: &mut syn::Item) {
+	if let syn::Item::Impl(item_impl) = item {
+		for item in item_impl.items.iter_mut() {
+			if let syn::ImplItem::Fn(method) = item {
+				method
+					.attrs
+					.retain(|attr| !attr.path().is_ident(WEIGHT_ATTR) && !attr.path().is_ident(CALL_INDEX_ATTR));
+			}
+		}
+	}
+}
+
+/// Check if a callable function returns a value on success.
+///
+/// Functions returning `DispatchResult` or `Result<(), _>` do not return a value, while functions
+/// returning `Result<R, _>` for any other `R` do.
+pub fn check_returns_value(output: &syn::ReturnType) -> bool {
+	let ty = match output {
+		syn::ReturnType::Type(_, ty) => ty,
+		syn::ReturnType::Default => return false,
+	};
+
+	let segment = match &**ty {
+		syn::Type::Path(tp) => tp.path.segments.last(),
+		_ => None,
+	};
+	let Some(segment) = segment.filter(|segment| segment.ident == "Result") else {
+		return false
+	};
+
+	// The first generic argument of the `Result` is the returned value.
+	match &segment.arguments {
+		syn::PathArguments::AngleBracketed(args) => match args.args.first() {
+			Some(syn::GenericArgument::Type(syn::Type::Tuple(tuple))) => !tuple.elems.is_empty(),
+			Some(syn::GenericArgument::Type(_)) => true,
+			_ => false,
+		},
+		_ => false,
+	}
+}
+
+/// Check caller arg is exactly: `caller: T::AccountId`, or `origin: OriginFor<T>`. Returns whether
+/// it is the origin.
 ///
 /// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
+pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<bool> {
+	pub struct CheckDispatchableOriginArg;
+	impl syn::parse::Parse for CheckDispatchableOriginArg {
+		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+			input.parse::<keyword::OriginFor>()?;
+			input.parse::<syn::Token![<]>()?;
+			input.parse::<keyword::T>()?;
+			input.parse::<syn::Token![>]>()?;
+			Ok(Self)
+		}
+	}
+
+	// An argument named `origin` or `_origin` must be the origin of the call.
+	if let syn::Pat::Ident(ident) = &*arg.pat {
+		if ident.ident == "origin" || ident.ident == "_origin" {
+			let ty = &arg.ty;
+			syn::parse2::<CheckDispatchableOriginArg>(ty.to_token_stream()).map_err(|e| {
+				let msg = "Invalid type for second parameter: expected `origin: OriginFor<T>`";
+				let mut err = syn::Error::new(ty.span(), msg);
+				err.combine(e);
+				err
+			})?;
+			return Ok(true)
+		}
+	}
+
 	pub struct CheckDispatchableFirstArg;
 	impl syn::parse::Parse for CheckDispatchableFirstArg {
 		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
@@ -123,7 +326,8 @@ pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
 	if let syn::Pat::Ident(ident) = &*arg.pat {
 		// We also support the name as `_caller` for when the variable is unused.
 		if &ident.ident != "caller" && &ident.ident != "_caller" {
-			let msg = "Invalid name for second parameter: expected `caller: T::AccountId`";
+			let msg = "Invalid name for second parameter: expected `caller: T::AccountId` or \
+				`origin: OriginFor<T>`";
 			return Err(syn::Error::new(ident.span(), msg))
 		}
 	}
@@ -137,5 +341,5 @@ pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
 		err
 	})?;
 
-	Ok(())
+	Ok(false)
 }
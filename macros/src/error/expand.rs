@@ -0,0 +1,28 @@
+use super::parse::ErrorDef;
+use quote::quote;
+
+/// See the `fn error` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_error(def: ErrorDef) -> proc_macro2::TokenStream {
+	let ErrorDef { error_enum, variants } = def;
+
+	// This is a vector of all the variant names.
+	let variant_name = variants.iter().map(|variant| &variant.name).collect::<Vec<_>>();
+	// This is a nested vector of the lines of the doc comment of each variant in `variant_name`.
+	let variant_docs = variants.iter().map(|variant| &variant.docs).collect::<Vec<_>>();
+
+	quote! {
+		impl #error_enum {
+			// The metadata of each variant of this error, in the order they are declared.
+			pub fn metadata() -> crate::codec::Vec<crate::metadata::ErrorMetadata> {
+				crate::codec::Vec::from([
+					#(
+						crate::metadata::ErrorMetadata {
+							name: stringify!(#variant_name),
+							docs: crate::codec::Vec::from([ #( #variant_docs ),* ]),
+						},
+					)*
+				])
+			}
+		}
+	}
+}
@@ -0,0 +1,24 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn error` docs at the `lib.rs` of this crate for a high level definition.
+pub fn error(
+	_attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let item_enum = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the variants of the error...
+	let generated: proc_macro::TokenStream = match parse::ErrorDef::try_from(item_enum.clone()) {
+		// ..then we generate our new code.
+		Ok(def) => expand::expand_error(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	};
+
+	// The final expanded code will be placed here. The error is left as it was declared.
+	let mut finished: proc_macro::TokenStream = quote::quote!(#item_enum).into();
+
+	// Add our generated code to the end, and return the final result.
+	finished.extend(generated);
+	return finished;
+}
@@ -0,0 +1,60 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the errors of a
+/// pallet.
+#[derive(Debug)]
+pub struct ErrorDef {
+	/// This is the name of the error enum. We mostly assume it is `Error`.
+	pub error_enum: syn::Ident,
+	/// This is a list of the variants of the error, in the order they are declared. See
+	/// `ErrorVariantDef`.
+	pub variants: Vec<ErrorVariantDef>,
+}
+
+/// This is the metadata we keep about each variant of the error.
+#[derive(Debug)]
+pub struct ErrorVariantDef {
+	/// The variant name.
+	pub name: syn::Ident,
+	/// The lines of the doc comment of the variant, without their leading space.
+	pub docs: Vec<String>,
+}
+
+impl ErrorDef {
+	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::error, expected item enum"))
+		};
+
+		let mut variants = vec![];
+		for variant in item_enum.variants {
+			// Errors are converted into a `DispatchError`, which only keeps the name of the variant,
+			// so the variants can't hold any data.
+			if !matches!(variant.fields, syn::Fields::Unit) {
+				let msg = "Invalid pallet::error, variants can't have fields";
+				return Err(syn::Error::new(variant.fields.span(), msg))
+			}
+			variants.push(ErrorVariantDef { name: variant.ident, docs: parse_docs(&variant.attrs) });
+		}
+
+		Ok(Self { error_enum: item_enum.ident, variants })
+	}
+}
+
+/// Parse the lines of the doc comment in `attrs`, which are `#[doc = "..."]` attributes.
+fn parse_docs(attrs: &[syn::Attribute]) -> Vec<String> {
+	attrs
+		.iter()
+		.filter(|attr| attr.path().is_ident("doc"))
+		.filter_map(|attr| match &attr.meta {
+			syn::Meta::NameValue(syn::MetaNameValue {
+				value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }),
+				..
+			}) => Some(doc.value().strip_prefix(' ').map(str::to_string).unwrap_or_else(|| doc.value())),
+			_ => None,
+		})
+		.collect()
+}
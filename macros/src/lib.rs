@@ -1,6 +1,41 @@
 mod call;
+mod error;
 mod runtime;
+mod storage;
 
+/// Expand the callable functions of a pallet.
+///
+/// This generates code needed for dispatching calls to the functions in the `impl` block:
+/// - `enum Call` - an enum with a variant for each callable function, containing all of its
+///   arguments except `self` and `caller: T::AccountId`, which must be the first two parameters.
+///   Calls taking a `caller` can only be made by a signed origin. A callable function can take
+///   `origin: support::OriginFor<T>` instead of the `caller`, to check the origin itself, like
+///   with `support::ensure_root`.
+///   Every callable function must declare its weight with a `#[weight = ...]` attribute, which is
+///   returned by `Call::weight()`. The weight expression can refer to the arguments of the call,
+///   which are borrowed, like `#[weight = 1_000 * targets.len() as u64]`. `Call::name()` returns
+///   the name of the function, and `Call::metadata()` returns the `metadata::FunctionMetadata` of
+///   every function, with the names and the types of its arguments as written in the pallet.
+///   A callable function can declare its index with a `#[call_index = N]` attribute, so its
+///   encoding doesn't change when the functions are reordered. Functions without it are indexed by
+///   their position in the `impl` block. Two functions with the same index are refused.
+/// - implements the traits `codec::Encode` and `codec::Decode` for `Call`, when the types of all the
+///   arguments implement them. A call is encoded as the index of its function, followed by its
+///   arguments.
+/// - implements the trait `support::Dispatch` to route each `Call` variant to its function.
+/// - implements the trait `support::Callable` for the pallet, so the runtime finds its `Call`.
+///   Callable functions return either `DispatchResult`, or `Result<R, DispatchError>` when they
+///   produce a value. That value, which must be `'static`, is boxed into the `DispatchOutput`
+///   returned by `dispatch`.
+///
+/// A callable function can take a mutable reference to another part of the runtime, like
+/// `balances: &mut balances::Pallet<T>`, as its last argument. This context is not part of the
+/// `Call`: the pallet then implements `support::DispatchWithContext` instead of
+/// `support::Dispatch`, and the runtime provides the context when dispatching.
+///
+/// A pallet which can be included several times in a runtime implements its calls in
+/// `impl<T: Config<I>, I: support::Instance> Pallet<T, I>`. Its `Call` is then `Call<T, I>`, where
+/// the instance defaults to `()`.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -9,22 +44,170 @@ pub fn call(
 	call::call(attr, item)
 }
 
+/// Expand the errors of a pallet, declared as an `enum` of unit variants, like `pub enum Error`.
+///
+/// This generates `Error::metadata()`, which returns the `metadata::ErrorMetadata` of each variant,
+/// with its name and the lines of its doc comment, in the order the variants are declared. The
+/// enum itself is left as it is declared, so the pallet still converts it into a `DispatchError`.
+#[proc_macro_attribute]
+pub fn error(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	error::error(attr, item)
+}
+
+/// Expand the storage fields of a pallet, declared on its struct, like `pub struct Pallet<T>`.
+///
+/// This generates a getter function for each field declared with a `#[getter(name)]` attribute,
+/// documented with the doc comment of the field:
+/// - a field holding a single value, or a `support::StorageValue<Value>`, gets
+///   `fn name(&self) -> Value`, returning a clone of it.
+/// - a `BTreeMap<Key, Value>` or `support::StorageMap<Key, Value>` field gets `fn name(&self, key: &Key) -> Option<&Value>`. With
+///   `#[getter(name, default)]` it gets `fn name(&self, key: &Key) -> Value` instead, returning a
+///   clone of the value, or `Default::default()` when the key is missing. Another default can be
+///   declared, like `#[getter(deposit, default = Zero::zero())]`.
+/// - a map declared with `#[getter(name, iter)]` also gets `fn iter_field(&self)`,
+///   named after the field, iterating over its entries sorted by key.
+///
+/// A field holding a `support::Events<Event>` gives the pallet `fn events(&self)`,
+/// `fn take_events(&mut self)` and `fn set_phase(&mut self, phase)`, which the runtime uses to
+/// collect the events, and `fn deposit_event(&mut self, event)`, through which the pallet emits
+/// them.
+///
+/// It also implements `support::Transactional` for the pallet, by starting, committing or undoing
+/// a transaction in each of its `support::StorageMap`, `support::StorageSet`, `support::StorageValue`
+/// and `support::Events` fields. The state a pallet changes in its calls must be kept in those fields, so a call which
+/// fails can be undone.
+///
+/// Pallets read their storage through these getters, so they only depend on how the storage is
+/// kept in the fields themselves. The attribute must be placed before the `#[derive]` of the
+/// struct, so the `#[getter]` attributes are removed before it runs.
+#[proc_macro_attribute]
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	storage::storage(attr, item)
+}
+
 /// Expand the `Runtime` definition.
 ///
+/// The `Runtime` must be a struct with named fields, whose first field is
+/// `system: system::Pallet<Runtime>`, and every field must be a pallet of the runtime, like
+/// `balances::Pallet<Runtime>`. Every pallet besides the system pallet must have callable
+/// functions, expanded by `#[macros::call]`, see `support::Callable`. A runtime which breaks these
+/// rules is refused with an error pointing at the offending field.
+///
+/// The runtime gets a `dispatching` field of its own, a `support::Dispatching`, so the attribute
+/// must be placed before the `#[derive]` of the struct, for the derived traits to include it.
+///
 /// This generates function implementations on `Runtime`:
 /// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
 ///   included in the runtime.
-/// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
-///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+/// - `fn execute_block()` - which handles basic logic for executing a borrowed block of extrinsics.
+///   It does basic actions like incrementing the block number and checking the block to be executed
+///   has a valid block number, a parent hash matching the hash of the best block, a matching
+///   extrinsics root and a total weight within the system pallet's `max_block_weight()`,
+///   then initializes the block through `support::InitializeBlock`. The inherents of the block
+///   are applied first, and the block is refused if any of them fails. After the extrinsics are
+///   applied, the block is finalized through `support::FinalizeBlock`. The runtime's
+///   `support::EnterPhase` implementation is told each phase of the block as it is entered. The
+///   root of the state left by the block, computed by the runtime's `support::StateRoot`
+///   implementation, must match the state root of the header. The hash of the block is then stored
+///   in the system pallet. Extrinsics which fail don't stop the block: the result of each one is
+///   returned in a `support::BlockExecutionReport`, and the system pallet emits `ExtrinsicSuccess`
+///   or `ExtrinsicFailed` for it.
+/// - `pub fn apply_extrinsic()` - which applies a single `types::SignedExtrinsic` on top of the
+///   current block. It rejects extrinsics whose signature doesn't match the public key of their
+///   caller in the system pallet, whose nonce isn't the current nonce of their caller, and expired
+///   extrinsics. It then increments the nonce of the caller, and charges the transaction fee
+///   through the runtime's `support::ChargeTransaction` implementation before dispatching the call
+///   in a transaction, see `support::with_transaction`, so a call which fails leaves no changes
+///   behind. Calls which return a `support::ActualWeight` lower than their declared weight get the
+///   fee for the unused weight refunded. The calls which became due in the pallets declared with
+///   `#[scheduler]` are then dispatched, see below.
+/// - `pub fn apply_inherent()` - which applies a single inherent call of a block, dispatching it
+///   with the none origin, without a nonce or fee. The calls which became due are then dispatched.
+/// - `pub fn check_signature()` - which checks the signature of a `types::SignedExtrinsic`, like
+///   `apply_extrinsic` does before applying it.
+/// - `fn execute_blocks()` - which executes a chain of blocks in sequence, stopping at the first
+///   block which fails to execute and reporting its block number.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
 /// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   all pallets, with a `weight()` function returning the weight of the underlying call, and a
+///   `name()` function returning the names of its pallet and function, which is also how it is
+///   formatted with `Debug` and exposed to pallets through `support::CallMetadata`. It implements
+///   `codec::Encode` and `codec::Decode`, encoding the index of the pallet before its call, and
+///   refusing to decode calls nested deeper than `codec::MAX_CALL_DEPTH`. A pallet can declare its
+///   index with a `#[pallet_index = N]` attribute on its field, or else it is indexed by its
+///   position in the runtime, and two pallets with the same index are refused.
+///   It also implements `support::TransactionVersion`, with the `transaction_version` of the
+///   `system::Config::VERSION` of the runtime. The system pallet is not included.
+/// - the same pallet can be included several times with different instances, like
+///   `tokens: balances::Pallet<Runtime, support::Instance1>` next to
+///   `balances: balances::Pallet<Runtime>`. Each instance has its own variant in `RuntimeCall` and
+///   `RuntimeEvent`, named after its field, and its own field in `RuntimeGenesisConfig`. The types of
+///   the pallet are taken from the module of its type, with the instance as their last generic
+///   argument, like `balances::Call<Runtime, support::Instance1>`. Including the same instance
+///   twice is refused.
+/// - `type RuntimeOrigin` - the `support::RawOrigin` of the accounts of the runtime, which calls
+///   are dispatched with. Extrinsics are dispatched with the signed origin of their caller.
+/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet, with a
+///   `RuntimeOrigin`. Calls from a signed origin whose caller does not exist in the system pallet
+///   are refused with `BadOrigin`. Calls which aren't from the root origin are then refused with
+///   `CallFiltered` unless the runtime's `support::CallFilter<RuntimeCall>` implementation allows
+///   them. Every call is passed to the runtime's `support::PreDispatch` implementation before it is
+///   dispatched, and its result after. The system pallet is not included.
+/// - pallets implementing `support::DispatchWithContext` must declare which field of the runtime
+///   is their context, with a `#[dispatch_context(field)]` attribute on the pallet field. Pallets
+///   declared with `#[dispatch_context(self)]` are dispatched with the runtime itself as their
+///   context, which implements `support::DispatchCall` so they can dispatch calls of the runtime,
+///   like a batch of calls. Such pallets must implement `Default`, which stands in for them while
+///   they are taken out of the runtime to dispatch their call. A call of such a pallet made while
+///   it dispatches its own call, like a batch in a sudo call in a batch, is refused with
+///   `DispatchError::Reentrancy`, see `support::Dispatching`.
+/// - `enum RuntimeEvent` - an "outer"-enum of the events of the system pallet and of the pallets
+///   declared with an `#[events]` attribute on their field. Those pallets must provide an
+///   `EventOf<Runtime>` type and a `take_events()` function. `execute_block` moves their events to
+///   the system pallet after each phase of the block, so the system pallet's `Config::RuntimeEvent`
+///   must be this enum. It also implements the trait `support::EnterPhase`, which calls
+///   `set_phase()` on those pallets, so their events are recorded with the phase of the block.
+/// - implements the trait `support::StateRoot` when some pallets are declared with a `#[state]`
+///   attribute on their field: the root commits to the state of the system pallet and of those
+///   pallets, which must provide a `store_state()` function, see `storage::InMemoryBackend::root`.
+///   A runtime without such pallets implements the trait itself.
+/// - `struct RuntimeGenesisConfig` - the initial state of the runtime, with the `GenesisConfig` of
+///   the system pallet and of the pallets declared with a `#[genesis]` attribute on their field.
+///   Its `build()` function builds the genesis config of each pallet into a runtime, with the
+///   `build()` function of that genesis config.
+/// - `pub fn metadata()` - the `metadata::RuntimeMetadata` of the runtime: its version, and the
+///   name, index, calls and errors of each pallet, out of `Call::metadata()` and `Error::metadata()`
+///   of the module of the pallet, so the errors of every pallet must be declared with
+///   `#[macros::error]`.
+/// - `trait RuntimeApi` - read-only queries about the state of the runtime, implemented for it:
+///   `block_number()` and `nonce_of()`, with `balance_of()` when the runtime has a `balances`
+///   pallet, and `claim_owner()` when it has a `proof_of_existence` pallet.
+/// - implements the trait `support::Transactional`, which starts, commits or undoes a transaction in
+///   the system pallet and in every pallet, so every pallet must implement it, usually through
+///   `#[macros::storage]`.
+/// - implements the traits `support::InitializeBlock` and `support::FinalizeBlock`, which call the
+///   `support::Hooks` of the pallets declared with a `#[hooks]` attribute on their field, with the
+///   number of the block, in the order the pallets are declared. Pallets whose hooks need another
+///   field of the runtime implement `support::HooksWithContext` instead, and declare that field
+///   with `#[hooks(field)]`.
+/// - `fn migrate()` - run by `execute_block()` before the block is initialized when the state was
+///   built by another `spec_version` of the runtime, see `system::RuntimeVersion`. It runs
+///   `migrations::migrate()` on each pallet declared with a `#[migrations]` attribute on their
+///   field, which must implement `migrations::OnRuntimeUpgrade`, then records the version of the
+///   runtime in the system pallet.
+/// - pallets declared with a `#[scheduler]` attribute on their field must provide a `take_due()`
+///   function, returning the tasks which are due with their `origin`, boxed `call`, `address` and
+///   `id`, and a `note_dispatched()` function. The call of each task is dispatched with its
+///   `origin` after the hooks in `support::InitializeBlock`, and after each extrinsic in
+///   `apply_extrinsic()`, and its result is passed to `note_dispatched()`.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
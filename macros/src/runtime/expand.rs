@@ -1,14 +1,157 @@
 use super::parse::RuntimeDef;
-use quote::quote;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
 
 /// See the `fn runtime` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	let RuntimeDef { runtime_struct, pallets } = def;
 
 	// This is a vector of all the pallet names, not including system.
-	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+	let pallet_names = pallets.iter().map(|pallet| pallet.name.clone()).collect::<Vec<_>>();
 	// This is a vector of all the pallet types, not including system.
-	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	let pallet_types = pallets.iter().map(|pallet| pallet.ty.clone()).collect::<Vec<_>>();
+	// This is a vector of the generic arguments of the types declared in the module of each pallet,
+	// like `<Runtime>`, or `<Runtime, support::Instance1>` for an instance of the pallet.
+	let pallet_generics = pallets
+		.iter()
+		.map(|pallet| match &pallet.instance {
+			Some(instance) => quote! { <#runtime_struct, #instance> },
+			None => quote! { <#runtime_struct> },
+		})
+		.collect::<Vec<_>>();
+	// This is a vector of the `Call` of each pallet, not including system. Each instance of a
+	// pallet has its own `Call`. It is found through `support::Callable`, so a pallet without a
+	// `#[macros::call]` impl is reported at its type.
+	let pallet_calls = pallets
+		.iter()
+		.map(|pallet| {
+			let ty = &pallet.ty;
+			quote_spanned! { ty.span() => <#ty as crate::support::Callable>::Call }
+		})
+		.collect::<Vec<_>>();
+	// This is a vector of the modules of the pallets, not including system.
+	let pallet_modules = pallets.iter().map(|pallet| pallet.module.clone()).collect::<Vec<_>>();
+	// This is a vector of how to dispatch a `call` to each of the pallets, not including system.
+	// Pallets with a `#[dispatch_context(field)]` are dispatched with that field as the context.
+	// Pallets with a `#[dispatch_context(self)]` are dispatched with the runtime as the context, so
+	// they are taken out of the runtime while their call is dispatched. The runtime records them
+	// meanwhile, and refuses the calls they make to themselves, see `support::Dispatching`.
+	let pallet_dispatch = pallets
+		.iter()
+		.map(|pallet| {
+			let name = &pallet.name;
+			let name_str = name.to_string();
+			match &pallet.context {
+				Some(context) if context == "self" => quote! {
+					match self.dispatching.enter(#name_str) {
+						Ok(()) => {
+							let mut pallet = core::mem::take(&mut self.#name);
+							let result = crate::support::DispatchWithContext::dispatch(
+								&mut pallet,
+								self,
+								origin,
+								call,
+							);
+							self.#name = pallet;
+							self.dispatching.exit(#name_str);
+							result
+						},
+						Err(error) => Err(error),
+					}
+				},
+				Some(context) => quote! {
+					crate::support::DispatchWithContext::dispatch(
+						&mut self.#name,
+						&mut self.#context,
+						origin,
+						call,
+					)
+				},
+				None => quote! { crate::support::Dispatch::dispatch(&mut self.#name, origin, call) },
+			}
+		})
+		.collect::<Vec<_>>();
+	// This is the index of each of the pallets, which is the first byte of the encoding of their
+	// calls in a `RuntimeCall`.
+	let pallet_index = pallets.iter().map(|pallet| pallet.index).collect::<Vec<_>>();
+	// This is a vector of the names of the pallets which emit events, declared with `#[events]`.
+	let event_pallet_names = pallets
+		.iter()
+		.filter(|pallet| pallet.has_events)
+		.map(|pallet| pallet.name.clone())
+		.collect::<Vec<_>>();
+	// This is a vector of the events of each pallet in `event_pallet_names`.
+	let event_pallet_types = pallets
+		.iter()
+		.zip(&pallet_generics)
+		.filter(|(pallet, _)| pallet.has_events)
+		.map(|(pallet, generics)| {
+			let module = &pallet.module;
+			quote! { #module::EventOf #generics }
+		})
+		.collect::<Vec<_>>();
+	// This is a vector of the names of the pallets with a genesis config, declared with
+	// `#[genesis]`.
+	let genesis_pallet_names = pallets
+		.iter()
+		.filter(|pallet| pallet.has_genesis)
+		.map(|pallet| pallet.name.clone())
+		.collect::<Vec<_>>();
+	// This is a vector of the genesis config of each pallet in `genesis_pallet_names`.
+	let genesis_pallet_types = pallets
+		.iter()
+		.zip(&pallet_generics)
+		.filter(|(pallet, _)| pallet.has_genesis)
+		.map(|(pallet, generics)| {
+			let module = &pallet.module;
+			quote! { #module::GenesisConfig #generics }
+		})
+		.collect::<Vec<_>>();
+	// This is a vector of how to call the hooks of each pallet declared with `#[hooks]`, as a pair
+	// of the call to `on_initialize` and the call to `on_finalize`. Pallets declared with
+	// `#[hooks(field)]` are called with that field as the context.
+	let (hooks_on_initialize, hooks_on_finalize): (Vec<_>, Vec<_>) = pallets
+		.iter()
+		.filter(|pallet| pallet.has_hooks)
+		.map(|pallet| {
+			let name = &pallet.name;
+			match &pallet.hooks_context {
+				Some(context) => (
+					quote! {
+						crate::support::HooksWithContext::on_initialize(&mut self.#name, &mut self.#context, block_number);
+					},
+					quote! {
+						crate::support::HooksWithContext::on_finalize(&mut self.#name, &mut self.#context, block_number);
+					},
+				),
+				None => (
+					quote! { crate::support::Hooks::on_initialize(&mut self.#name, block_number); },
+					quote! { crate::support::Hooks::on_finalize(&mut self.#name, block_number); },
+				),
+			}
+		})
+		.unzip();
+	// This is a vector of the names of the pallets with migrations, declared with `#[migrations]`.
+	let migration_pallet_names = pallets
+		.iter()
+		.filter(|pallet| pallet.has_migrations)
+		.map(|pallet| pallet.name.clone())
+		.collect::<Vec<_>>();
+	// This is a vector of the names of the pallets which schedule calls, declared with
+	// `#[scheduler]`.
+	let scheduler_pallet_names = pallets
+		.iter()
+		.filter(|pallet| pallet.is_scheduler)
+		.map(|pallet| pallet.name.clone())
+		.collect::<Vec<_>>();
+
+	// This is a vector of the names of the pallets the state root commits to, declared with
+	// `#[state]`.
+	let state_pallet_names = pallets
+		.iter()
+		.filter(|pallet| pallet.has_state)
+		.map(|pallet| pallet.name.clone())
+		.collect::<Vec<_>>();
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
@@ -19,25 +162,295 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 					// Since system is not included in the list of pallets, we manually add it here.
 					system: <system::Pallet::<Self>>::new(),
 					#(
-						#pallet_names: <#pallet_types>::new()
-					),*
+						#pallet_names: <#pallet_types>::new(),
+					)*
+					dispatching: crate::support::Dispatching::default(),
+				}
+			}
+
+			// Execute a block of extrinsics. Increments the block number, checks the header of the
+			// block follows the best block and commits to its extrinsics, and initializes the
+			// block using the `support::InitializeBlock` implementation generated below. Once the block
+			// is executed, its hash is stored in the system pallet, so the next block can be
+			// checked against it.
+			//
+			// Before the block is initialized, before each inherent and extrinsic and before the
+			// block is finalized, the runtime's `support::EnterPhase` implementation is told the new
+			// phase. Inherents come first in the block, so the phases of the extrinsics are numbered
+			// after them.
+			//
+			// Each inherent is applied with `apply_inherent`, and the block is refused with the error
+			// of the first one which fails. Each extrinsic is then applied with `apply_extrinsic`,
+			// then the block is finalized using the `support::FinalizeBlock` implementation generated
+			// below. An extrinsic which fails doesn't stop the block: its result is recorded in the
+			// `support::BlockExecutionReport` returned, in the order the extrinsics were included in
+			// the block.
+			//
+			// The events emitted in each phase are collected into the system pallet at the end of
+			// that phase. The system pallet then emits `ExtrinsicSuccess` or `ExtrinsicFailed` in
+			// the phase of each extrinsic.
+			//
+			// Once the block is finalized, the root of the state computed by the runtime's
+			// `support::StateRoot` implementation must match the state root of the header. The
+			// block is refused otherwise, but the runtime is left in the state after the block, so
+			// it must be discarded, like a runtime whose block number was incremented by a block
+			// with an invalid header.
+			//
+			// The block is borrowed, so the same block can be executed on several runtimes. Since
+			// pallet calls take their arguments by value, each extrinsic is cloned as it is applied.
+			fn execute_block(
+				&mut self,
+				block: &types::Block,
+			) -> Result<crate::support::BlockExecutionReport, crate::support::DispatchError> {
+				// The header is checked before anything is changed, so a block which is refused
+				// leaves the state as it was.
+				let parent_hash = self.system.best_block_hash();
+				if block.header.block_number != self.system.next_block_number()? {
+					return Err(crate::support::DispatchError::Other("block number does not match what is expected"))
+				}
+				if block.header.parent_hash != parent_hash {
+					return Err(crate::support::DispatchError::Other("parent hash does not match the best block"))
+				}
+				if block.header.extrinsics_root != crate::support::extrinsics_root(&block.inherents, &block.extrinsics) {
+					return Err(crate::support::DispatchError::Other("extrinsics root does not match the extrinsics"))
+				}
+				let block_weight = block
+					.inherents
+					.iter()
+					.chain(block.extrinsics.iter().map(|signed| &signed.extrinsic.call))
+					.try_fold(0 as crate::support::Weight, |weight, call| weight.checked_add(call.weight()));
+				if !matches!(block_weight, Some(weight) if weight <= self.system.max_block_weight()) {
+					return Err(crate::support::DispatchError::Other("block exceeds the maximum weight"))
+				}
+				self.system.inc_block_number()?;
+				crate::support::EnterPhase::enter_phase(self, crate::support::Phase::Initialization);
+				self.migrate()?;
+				crate::support::InitializeBlock::initialize_block(self);
+				self.collect_events();
+				for (i, inherent) in block.inherents.iter().enumerate() {
+					crate::support::EnterPhase::enter_phase(
+						self,
+						crate::support::Phase::ApplyExtrinsic(i as u32),
+					);
+					self.apply_inherent(inherent.clone())?;
+					self.collect_events();
+				}
+				let mut report = crate::support::BlockExecutionReport {
+					results: Vec::with_capacity(block.extrinsics.len()),
+				};
+				for (i, extrinsic) in block.extrinsics.iter().enumerate() {
+					let phase = crate::support::Phase::ApplyExtrinsic((block.inherents.len() + i) as u32);
+					crate::support::EnterPhase::enter_phase(self, phase);
+					let res = self.apply_extrinsic(extrinsic.clone());
+					self.collect_events();
+					let event = match &res {
+						Ok(_) => system::Event::ExtrinsicSuccess,
+						Err(error) => system::Event::ExtrinsicFailed { error: *error },
+					};
+					self.system.deposit_event(crate::support::EventRecord { phase, event: RuntimeEvent::system(event) });
+					report.results.push(res);
+				}
+				crate::support::EnterPhase::enter_phase(self, crate::support::Phase::Finalization);
+				crate::support::FinalizeBlock::finalize_block(self);
+				self.collect_events();
+				if crate::support::StateRoot::state_root(self) != block.header.state_root {
+					return Err(crate::support::DispatchError::Other("state root does not match the state"))
+				}
+				self.system.set_block_hash(block.header.block_number, block.header.hash());
+				Ok(report)
+			}
+
+			// Migrate the state when it was built by another spec version of the runtime, by
+			// running `migrations::migrate` on each pallet declared with `#[migrations]`, in the
+			// order the pallets are declared. The system pallet then records the version of the
+			// runtime, and emits `RuntimeUpgraded` with the total weight of the migrations.
+			//
+			// This fails with the error of the first check of a migration which fails, in which case
+			// the runtime must be discarded. `execute_block` migrates the state before the block
+			// is initialized, and so must a block author.
+			fn migrate(&mut self) -> crate::support::DispatchResult {
+				let version = <Self as system::Config>::VERSION;
+				let stored = self.system.version();
+				self.system.set_version(version);
+				if stored.spec_version == version.spec_version {
+					return Ok(())
+				}
+				let mut weight: crate::support::Weight = 0;
+				#(
+					weight = weight.saturating_add(crate::migrations::migrate(&mut self.#migration_pallet_names)?);
+				)*
+				self.system.deposit_event(crate::support::EventRecord {
+					phase: crate::support::Phase::Initialization,
+					event: RuntimeEvent::system(system::Event::RuntimeUpgraded {
+						spec_version: version.spec_version,
+						weight,
+					}),
+				});
+				Ok(())
+			}
+
+			// The metadata of the runtime, with the calls and the errors of each pallet, in the
+			// order the pallets are declared.
+			pub fn metadata() -> crate::metadata::RuntimeMetadata {
+				crate::metadata::RuntimeMetadata {
+					version: <Self as system::Config>::VERSION,
+					pallets: crate::codec::Vec::from([
+						#(
+							crate::metadata::PalletMetadata {
+								name: stringify!(#pallet_names),
+								index: #pallet_index,
+								calls: <#pallet_calls>::metadata(),
+								errors: #pallet_modules::Error::metadata(),
+							},
+						)*
+					]),
+				}
+			}
+
+			// Move the events emitted by the pallets to the system pallet, wrapped in a
+			// `RuntimeEvent`, keeping the phase they were emitted in.
+			fn collect_events(&mut self) {
+				#(
+					for record in self.#event_pallet_names.take_events() {
+						self.system.deposit_event(crate::support::EventRecord {
+							phase: record.phase,
+							event: RuntimeEvent::#event_pallet_names(record.event),
+						});
+					}
+				)*
+			}
+
+			// Apply a single extrinsic on top of the current block, without touching the block
+			// number. This is what `execute_block` does for each of its extrinsics.
+			//
+			// The signature of the extrinsic is checked first, against the public key of the caller
+			// registered in the system pallet, and the genesis hash of the chain. The nonce of the
+			// extrinsic must then be the current nonce of the caller: lower nonces were already
+			// used, so replayed extrinsics are refused, and higher nonces must wait for the
+			// extrinsics before them. Extrinsics which fail these checks, or which expired, are
+			// rejected without touching the state of the caller. Otherwise the transaction fee for
+			// the weight of the call (including the tip) is charged using the runtime's
+			// `support::ChargeTransaction` implementation. An extrinsic whose fee can't be paid is
+			// invalid too, and is rejected before the nonce of the caller is used. Then the nonce
+			// of the caller is incremented, and the call is dispatched with the signed origin of
+			// the caller. The call is
+			// dispatched in a transaction, see `support::with_transaction`: when it fails, its
+			// changes and the events it emitted are discarded, but the caller still pays the fee
+			// and uses up their nonce. Calls which return a `support::ActualWeight` lower than
+			// their declared weight get the fee for the unused weight refunded. Then the calls
+			// which became due are dispatched, see `dispatch_due`.
+			pub fn apply_extrinsic(
+				&mut self,
+				signed: types::SignedExtrinsic,
+			) -> crate::support::DispatchResultWithOutput {
+				self.check_signature(&signed)?;
+				let crate::support::SignedExtrinsic { extrinsic, nonce, .. } = signed;
+				let crate::support::Extrinsic { caller, call, valid_until, tip } = extrinsic;
+				let expected_nonce = self.system.get_nonce(&caller);
+				if nonce < expected_nonce {
+					return Err(crate::support::DispatchError::Other("stale nonce"))
+				}
+				if nonce > expected_nonce {
+					return Err(crate::support::DispatchError::Other("future nonce"))
+				}
+				if valid_until.is_some_and(|valid_until| self.system.block_number() > valid_until) {
+					return Err(crate::support::DispatchError::Other("extrinsic expired"))
+				}
+				let weight = call.weight();
+				crate::support::ChargeTransaction::charge_transaction(self, &caller, weight, tip)?;
+				self.system.inc_nonce(&caller);
+				let result = crate::support::with_transaction(self, |runtime| {
+					crate::support::Dispatch::dispatch(runtime, crate::support::RawOrigin::Signed(caller.clone()), call)
+				});
+				let actual_weight = match &result {
+					Ok(Some(output)) => output.downcast_ref::<crate::support::ActualWeight>(),
+					_ => None,
+				};
+				if let Some(&crate::support::ActualWeight(actual_weight)) = actual_weight {
+					if actual_weight < weight {
+						crate::support::ChargeTransaction::refund_transaction(
+							self,
+							&caller,
+							weight,
+							actual_weight,
+						);
+					}
+				}
+				self.dispatch_due();
+				result
+			}
+
+			// Apply an inherent of a block: its call is dispatched with the none origin, without
+			// any nonce or fee, since the block author provides it. Then the calls which became due
+			// are dispatched, see `dispatch_due`.
+			pub fn apply_inherent(&mut self, call: RuntimeCall) -> crate::support::DispatchResultWithOutput {
+				let result = crate::support::Dispatch::dispatch(self, crate::support::RawOrigin::None, call);
+				self.dispatch_due();
+				result
+			}
+
+			// Dispatch the calls which are due in each pallet declared with `#[scheduler]`, with
+			// the origin they were scheduled with, and report their result back to their pallet.
+			// This happens after the hooks at the start of every block, and after every extrinsic,
+			// which may have made a call due, like the last approval of a multisig call.
+			//
+			// The account of a signed origin is created in the system pallet if it doesn't exist,
+			// like the caller of an extrinsic, since it may not be able to sign extrinsics.
+			//
+			// A dispatched call can make calls due in any of those pallets, like a proxy call of
+			// the last approval of a multisig call, so they are dispatched until none is left.
+			fn dispatch_due(&mut self) {
+				loop {
+					#[allow(unused_mut)]
+					let mut dispatched = false;
+					#(
+						for task in self.#scheduler_pallet_names.take_due() {
+							dispatched = true;
+							if let crate::support::RawOrigin::Signed(who) = &task.origin {
+								self.system.create_account(who);
+							}
+							let result = crate::support::Dispatch::dispatch(self, task.origin, *task.call);
+							self.#scheduler_pallet_names.note_dispatched(task.address, task.id, result.map(|_| ()));
+						}
+					)*
+					if !dispatched {
+						break;
+					}
 				}
 			}
 
-			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
-				self.system.inc_block_number();
-				if block.header.block_number != self.system.block_number() {
-					return Err(&"block number does not match what is expected")
+			// Check the signature of an extrinsic against the public key of its caller registered
+			// in the system pallet, and the genesis hash of the chain.
+			pub fn check_signature(
+				&self,
+				signed: &types::SignedExtrinsic,
+			) -> crate::support::DispatchResult {
+				let public = self
+					.system
+					.public_key(&signed.extrinsic.caller)
+					.ok_or(crate::support::DispatchError::Other("unknown signer"))?;
+				let payload = crate::support::SignedExtrinsic::signing_payload(
+					&signed.extrinsic,
+					&signed.nonce,
+					&self.system.genesis_hash(),
+				);
+				if !public.verify(&payload, &signed.signature) {
+					return Err(crate::support::DispatchError::Other("invalid signature"))
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
-						)
-					});
+				Ok(())
+			}
+
+			// Execute a chain of blocks in sequence, stopping at the first block which fails.
+			//
+			// Each block must follow the previous one, which is enforced by `execute_block`.
+			// Returns the block number of the failed block together with its error.
+			fn execute_blocks(
+				&mut self,
+				blocks: &[types::Block],
+			) -> Result<(), (<Self as system::Config>::BlockNumber, crate::support::DispatchError)> {
+				for block in blocks {
+					let block_number = block.header.block_number;
+					self.execute_block(block).map_err(|e| (block_number, e))?;
 				}
 				Ok(())
 			}
@@ -51,41 +464,381 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
+		#[derive(Clone)]
 		pub enum RuntimeCall {
-			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
+			#( #pallet_names(#pallet_calls) ),*
+		}
+
+		impl RuntimeCall {
+			// The weight of the underlying pallet call.
+			pub fn weight(&self) -> crate::support::Weight {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => call.weight()
+					),*
+				}
+			}
+
+			// The name of the pallet and of the function called by the underlying pallet call.
+			pub fn name(&self) -> (&'static str, &'static str) {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => (stringify!(#pallet_names), call.name())
+					),*
+				}
+			}
+		}
+
+		// Pallets filter runtime calls by the names of their pallet and function, like the proxy
+		// pallet.
+		impl crate::support::CallMetadata for RuntimeCall {
+			fn pallet_name(&self) -> &'static str {
+				self.name().0
+			}
+
+			fn function_name(&self) -> &'static str {
+				self.name().1
+			}
+		}
+
+		// Runtime calls are shown as the names of their pallet and function, so pallets which
+		// store calls, like a scheduler, can be debugged.
+		impl core::fmt::Debug for RuntimeCall {
+			fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+				let (pallet, function) = self.name();
+				write!(f, "{}::{}", pallet, function)
+			}
+		}
+
+		// The SCALE encoding of a runtime call is the index of its pallet, followed by the encoding
+		// of the pallet call. See the `codec` module of the pallets.
+		impl crate::codec::Encode for RuntimeCall {
+			fn encode_to(&self, dest: &mut crate::codec::Vec<u8>) {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => {
+							dest.push(#pallet_index);
+							crate::codec::Encode::encode_to(call, dest);
+						}
+					),*
+				}
+			}
+		}
+
+		impl crate::codec::Decode for RuntimeCall {
+			fn decode(input: &mut &[u8]) -> Result<Self, crate::codec::Error> {
+				Self::decode_with_depth(input, crate::codec::MAX_CALL_DEPTH)
+			}
+
+			// Every runtime call takes one level of depth, so calls nested deeper than
+			// `codec::MAX_CALL_DEPTH` are refused before they overflow the stack.
+			fn decode_with_depth(input: &mut &[u8], depth: u32) -> Result<Self, crate::codec::Error> {
+				let depth = depth.checked_sub(1).ok_or(crate::codec::Error::Invalid)?;
+				match <u8 as crate::codec::Decode>::decode(input)? {
+					#(
+						#pallet_index => Ok(RuntimeCall::#pallet_names(crate::codec::Decode::decode_with_depth(input, depth)?)),
+					)*
+					_ => Err(crate::codec::Error::Invalid),
+				}
+			}
+		}
+
+		// Extrinsics encode the transaction version of the runtime with their call, so calls encoded
+		// for another version of the runtime are refused.
+		impl crate::support::TransactionVersion for RuntimeCall {
+			const TRANSACTION_VERSION: u32 = <#runtime_struct as system::Config>::VERSION.transaction_version;
+		}
+
+		// The origin of the calls dispatched by the runtime.
+		pub type RuntimeOrigin =
+			crate::support::RawOrigin<<#runtime_struct as system::Config>::AccountId>;
+
+		// These are all the events which can be emitted by the pallets of the runtime, which are
+		// collected into the system pallet. Only pallets declared with `#[events]` are included,
+		// besides the system pallet, which reports the outcome of each extrinsic.
+		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq)]
+		pub enum RuntimeEvent {
+			system(system::Event),
+			#( #event_pallet_names(#event_pallet_types) ),*
 		}
 
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+			type Origin = RuntimeOrigin;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call on behalf of an origin.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
-			// Note that we extract the `caller` from the extrinsic, and use that information
-			// to determine who we are executing the call on behalf of.
+			// Note that extrinsics are dispatched with the signed origin of their `caller`, while
+			// the runtime can dispatch calls with the root or none origin itself. Each pallet call
+			// checks the origin it is made with.
+			//
+			// The caller of a signed origin must already exist in the system pallet, otherwise the
+			// call fails with `DispatchError::BadOrigin`. Accounts with no prior state are created
+			// by `execute_block`, which increments their nonce before dispatching.
+			//
+			// The runtime's `support::PreDispatch` implementation observes each call before it is
+			// routed to its pallet, and its result afterwards.
 			fn dispatch(
 				&mut self,
-				caller: Self::Caller,
+				origin: Self::Origin,
 				runtime_call: Self::Call,
-			) -> crate::support::DispatchResult {
+			) -> crate::support::DispatchResultWithOutput {
+				if let crate::support::RawOrigin::Signed(caller) = &origin {
+					if !self.system.account_exists(caller) {
+						return Err(crate::support::DispatchError::BadOrigin)
+					}
+				}
+				// The root origin isn't filtered, so the calls lifting a filter can always be made.
+				if !matches!(origin, crate::support::RawOrigin::Root)
+					&& !crate::support::CallFilter::allows(self, &runtime_call)
+				{
+					return Err(crate::support::DispatchError::CallFiltered)
+				}
+
+				crate::support::PreDispatch::pre_dispatch(self, &origin, &runtime_call);
+
 				// This match statement will allow us to correctly route `RuntimeCall`s
-				// to the appropriate pallet level call.
-				match runtime_call {
+				// to the appropriate pallet level call. Since `RuntimeCall` is generated from the
+				// same list of pallets, every variant is always routed to its pallet.
+				let result = match runtime_call {
 					#(
 						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+							#pallet_dispatch
 						}
 					),*
+				};
+
+				crate::support::PreDispatch::post_dispatch(self, &result);
+				result
+			}
+		}
+	};
+
+	// This quote block calls the hooks of the pallets at the start and at the end of every block,
+	// and dispatches the scheduled calls which are due, through the traits used by
+	// `execute_block` and by the block builder.
+	let hooks_impl = quote! {
+		impl crate::support::InitializeBlock for #runtime_struct {
+			// Call `support::Hooks::on_initialize` on each pallet declared with `#[hooks]`, or
+			// `support::HooksWithContext::on_initialize` with its context on each pallet declared
+			// with `#[hooks(field)]`, in the order the pallets are declared. Then dispatch the
+			// calls which are due, see `dispatch_due`.
+			#[allow(unused_variables)]
+			fn initialize_block(&mut self) {
+				let block_number = self.system.block_number();
+				#( #hooks_on_initialize )*
+				self.dispatch_due();
+			}
+		}
+
+		impl crate::support::FinalizeBlock for #runtime_struct {
+			// Call `support::Hooks::on_finalize` or `support::HooksWithContext::on_finalize` on
+			// each pallet declared with `#[hooks]`, like `initialize_block`.
+			#[allow(unused_variables)]
+			fn finalize_block(&mut self) {
+				let block_number = self.system.block_number();
+				#( #hooks_on_finalize )*
+			}
+		}
+	};
+
+	// This quote block lets the pallets declared with `#[dispatch_context(self)]` dispatch calls of
+	// the runtime, when there are any.
+	let dispatch_call_impl = if pallets
+		.iter()
+		.any(|pallet| pallet.context.as_ref().is_some_and(|context| context == "self"))
+	{
+		quote! {
+			impl crate::support::DispatchCall<RuntimeOrigin, RuntimeCall> for #runtime_struct {
+				fn dispatch_call(
+					&mut self,
+					origin: RuntimeOrigin,
+					call: RuntimeCall,
+				) -> crate::support::DispatchResultWithOutput {
+					crate::support::Dispatch::dispatch(self, origin, call)
+				}
+
+				// The transaction is started in every pallet at once, see `support::Transactional`.
+				fn transactional(
+					&mut self,
+					f: &mut dyn FnMut(
+						&mut dyn crate::support::DispatchCall<RuntimeOrigin, RuntimeCall>,
+					) -> crate::support::DispatchResult,
+				) -> crate::support::DispatchResult {
+					crate::support::with_transaction(self, |runtime| f(runtime))
 				}
+			}
+		}
+	} else {
+		quote! {}
+	};
+
+	// This quote block tells the pallets which emit events the phase of the block being executed,
+	// so their events are recorded with it.
+	let enter_phase_impl = quote! {
+		impl crate::support::EnterPhase for #runtime_struct {
+			#[allow(unused_variables)]
+			fn enter_phase(&mut self, phase: crate::support::Phase) {
+				#( self.#event_pallet_names.set_phase(phase); )*
+			}
+		}
+	};
+
+	// This quote block commits to the state of the system pallet and of the pallets declared with
+	// `#[state]`, which write it into an in-memory backend to compute its root, when there are
+	// any. A runtime without such pallets implements `support::StateRoot` itself.
+	let state_root_impl = if state_pallet_names.is_empty() {
+		quote! {}
+	} else {
+		quote! {
+			impl crate::support::StateRoot for #runtime_struct {
+				fn state_root(&self) -> system::Hash {
+					let mut state = crate::storage::InMemoryBackend::new();
+					self.system.store_state(&mut state);
+					#( self.#state_pallet_names.store_state(&mut state); )*
+					state.root()
+				}
+			}
+		}
+	};
+
+	// This quote block starts, commits or undoes a transaction in the system pallet and in every
+	// pallet of the runtime, so the runtime can dispatch calls in a transaction, see
+	// `support::with_transaction`.
+	let transactional_impl = quote! {
+		impl crate::support::Transactional for #runtime_struct {
+			fn start_transaction(&mut self) {
+				crate::support::Transactional::start_transaction(&mut self.system);
+				#( crate::support::Transactional::start_transaction(&mut self.#pallet_names); )*
+			}
+
+			fn commit_transaction(&mut self) {
+				crate::support::Transactional::commit_transaction(&mut self.system);
+				#( crate::support::Transactional::commit_transaction(&mut self.#pallet_names); )*
+			}
+
+			fn rollback_transaction(&mut self) {
+				crate::support::Transactional::rollback_transaction(&mut self.system);
+				#( crate::support::Transactional::rollback_transaction(&mut self.#pallet_names); )*
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeGenesisConfig`, which sets the initial state of the
+	// runtime.
+	let genesis_impl = quote! {
+		// The initial state of the runtime, made of the genesis config of the system pallet and of
+		// each pallet declared with `#[genesis]`.
+		#[derive(Default)]
+		pub struct RuntimeGenesisConfig {
+			pub system: system::GenesisConfig<#runtime_struct>,
+			#( pub #genesis_pallet_names: #genesis_pallet_types ),*
+		}
+
+		impl RuntimeGenesisConfig {
+			// Build the genesis config of each pallet into `runtime`, in the order the pallets
+			// are declared, stopping at the first which fails.
+			pub fn build(&self, runtime: &mut #runtime_struct) -> crate::support::DispatchResult {
+				self.system.build(&mut runtime.system)?;
+				#( self.#genesis_pallet_names.build(&mut runtime.#genesis_pallet_names)?; )*
 				Ok(())
 			}
 		}
 	};
 
+	// This quote block implements the `RuntimeApi`, which answers queries about the state of the
+	// runtime. The queries of the balances and proof of existence pallets are only included when
+	// the runtime has a pallet with that name.
+	let has_pallet = |name: &str| pallets.iter().any(|pallet| pallet.name == name);
+	let (balance_of_decl, balance_of_impl) = if has_pallet("balances") {
+		(
+			quote! {
+				// The free balance of `who`.
+				fn balance_of(
+					&self,
+					who: &<#runtime_struct as system::Config>::AccountId,
+				) -> <#runtime_struct as balances::Config>::Balance;
+			},
+			quote! {
+				fn balance_of(
+					&self,
+					who: &<#runtime_struct as system::Config>::AccountId,
+				) -> <#runtime_struct as balances::Config>::Balance {
+					self.balances.balance(who)
+				}
+			},
+		)
+	} else {
+		(quote! {}, quote! {})
+	};
+	let (claim_owner_decl, claim_owner_impl) = if has_pallet("proof_of_existence") {
+		(
+			quote! {
+				// The owner of `claim`, if it is claimed.
+				fn claim_owner(
+					&self,
+					claim: &system::Hash,
+				) -> Option<<#runtime_struct as system::Config>::AccountId>;
+			},
+			quote! {
+				fn claim_owner(
+					&self,
+					claim: &system::Hash,
+				) -> Option<<#runtime_struct as system::Config>::AccountId> {
+					self.proof_of_existence.get_claim(claim).cloned()
+				}
+			},
+		)
+	} else {
+		(quote! {}, quote! {})
+	};
+	let runtime_api_impl = quote! {
+		// Read-only queries about the state of the runtime, for the code outside of it, like RPC
+		// servers and CLIs. They only borrow the runtime, and don't depend on how its pallets
+		// keep their state.
+		pub trait RuntimeApi {
+			// The number of the best block.
+			fn block_number(&self) -> <#runtime_struct as system::Config>::BlockNumber;
+
+			// The nonce the next extrinsic of `who` must have.
+			fn nonce_of(
+				&self,
+				who: &<#runtime_struct as system::Config>::AccountId,
+			) -> <#runtime_struct as system::Config>::Nonce;
+
+			#balance_of_decl
+			#claim_owner_decl
+		}
+
+		impl RuntimeApi for #runtime_struct {
+			fn block_number(&self) -> <#runtime_struct as system::Config>::BlockNumber {
+				self.system.block_number()
+			}
+
+			fn nonce_of(
+				&self,
+				who: &<#runtime_struct as system::Config>::AccountId,
+			) -> <#runtime_struct as system::Config>::Nonce {
+				self.system.get_nonce(who)
+			}
+
+			#balance_of_impl
+			#claim_owner_impl
+		}
+	};
+
 	// We combine and return all the generated code.
 	quote! {
 		#dispatch_impl
 		#runtime_impl
+		#hooks_impl
+		#enter_phase_impl
+		#state_root_impl
+		#dispatch_call_impl
+		#transactional_impl
+		#genesis_impl
+		#runtime_api_impl
 	}
 	.into()
 }
@@ -6,11 +6,7 @@ pub fn runtime(
 	_attr: proc_macro::TokenStream,
 	item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-	// The final expanded code will be placed here.
-	// Since our macro only adds new code, our final product will contain all of our old code too,
-	// hence we clone `item`.
-	let mut finished = item.clone();
-	let item_mod = syn::parse_macro_input!(item as syn::Item);
+	let mut item_mod = syn::parse_macro_input!(item as syn::Item);
 
 	// First we parse the `Runtime` struct...
 	let generated: proc_macro::TokenStream = match parse::RuntimeDef::try_from(item_mod.clone()) {
@@ -19,6 +15,16 @@ pub fn runtime(
 		Err(e) => e.to_compile_error().into(),
 	};
 
+	// The final expanded code will be placed here.
+	// Our final product will contain all of our old code too, except for the
+	// `#[dispatch_context]`, `#[events]`, `#[genesis]`, `#[hooks]`, `#[migrations]`,
+	// `#[pallet_index]`, `#[scheduler]` and `#[state]` attributes, which are only meaningful to
+	// this macro.
+	// The runtime also gets a field of its own, see `parse::add_dispatching_field`.
+	parse::strip_runtime_attrs(&mut item_mod);
+	parse::add_dispatching_field(&mut item_mod);
+	let mut finished: proc_macro::TokenStream = quote::quote!(#item_mod).into();
+
 	// Add our generated code to the end, and return the final result.
 	finished.extend(generated);
 	return finished;
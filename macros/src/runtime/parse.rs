@@ -1,3 +1,5 @@
+use quote::ToTokens;
+use syn::ext::IdentExt;
 use syn::spanned::Spanned;
 
 /// This object will collect all the information we need to keep while parsing the `Runtime` struct.
@@ -7,9 +9,74 @@ pub struct RuntimeDef {
 	pub runtime_struct: syn::Ident,
 	/// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
 	/// list, but during parsing we check that system exists.
-	pub pallets: Vec<(syn::Ident, syn::Type)>,
+	pub pallets: Vec<PalletDef>,
 }
 
+/// This is the metadata we keep about each pallet included in the `Runtime` struct.
+#[derive(Debug)]
+pub struct PalletDef {
+	/// The name of the field holding the pallet.
+	pub name: syn::Ident,
+	/// The type of the pallet.
+	pub ty: syn::Type,
+	/// The module of the pallet, where its `Call`, `Error`, `EventOf` and `GenesisConfig` are
+	/// declared, like `balances` for `balances::Pallet<Runtime>`. It is the name of the field when
+	/// the type of the pallet isn't a path to a module.
+	pub module: syn::Path,
+	/// The instance of the pallet, like `support::Instance1` for
+	/// `balances::Pallet<Runtime, support::Instance1>`, when the pallet can be included several
+	/// times. See `support::Instance`.
+	pub instance: Option<syn::Type>,
+	/// The index of the pallet, which is the first byte of the encoding of its calls in a
+	/// `RuntimeCall`. It is declared with `#[pallet_index = N]`, or else it is the position of the
+	/// pallet in the runtime, not counting the system pallet.
+	pub index: u8,
+	/// The name of the field holding the context the calls of this pallet are dispatched with,
+	/// declared with `#[dispatch_context(field)]`. See `support::DispatchWithContext`. Pallets
+	/// declared with `#[dispatch_context(self)]` are dispatched with the runtime itself as the
+	/// context, see `support::DispatchCall`.
+	pub context: Option<syn::Ident>,
+	/// Whether the pallet emits events, declared with `#[events]`. Its events are then collected
+	/// into the `RuntimeEvent` of the runtime.
+	pub has_events: bool,
+	/// Whether the pallet has a `GenesisConfig`, declared with `#[genesis]`. It is then included
+	/// in the `RuntimeGenesisConfig` of the runtime.
+	pub has_genesis: bool,
+	/// Whether the pallet implements `support::Hooks`, declared with `#[hooks]`. Its hooks are then
+	/// called at the start and at the end of every block.
+	pub has_hooks: bool,
+	/// The name of the field holding the context the hooks of this pallet are called with,
+	/// declared with `#[hooks(field)]`. See `support::HooksWithContext`.
+	pub hooks_context: Option<syn::Ident>,
+	/// Whether the pallet implements `migrations::OnRuntimeUpgrade`, declared with `#[migrations]`.
+	/// It is then migrated when the runtime executes a block on a state built by another spec
+	/// version.
+	pub has_migrations: bool,
+	/// Whether the pallet schedules calls, declared with `#[scheduler]`. The calls which are due
+	/// are then dispatched at the start of every block, after the hooks of the pallets.
+	pub is_scheduler: bool,
+	/// Whether the state root of the runtime commits to the state of the pallet, declared with
+	/// `#[state]`. The pallet must then provide a `store_state()` function.
+	pub has_state: bool,
+}
+
+/// The name of the attribute used to declare the context a pallet is dispatched with.
+const DISPATCH_CONTEXT_ATTR: &str = "dispatch_context";
+/// The name of the attribute used to declare that a pallet emits events.
+const EVENTS_ATTR: &str = "events";
+/// The name of the attribute used to declare that a pallet has a genesis config.
+const GENESIS_ATTR: &str = "genesis";
+/// The name of the attribute used to declare that a pallet has hooks.
+const HOOKS_ATTR: &str = "hooks";
+/// The name of the attribute used to declare that a pallet has migrations.
+const MIGRATIONS_ATTR: &str = "migrations";
+/// The name of the attribute used to declare that a pallet schedules calls.
+const SCHEDULER_ATTR: &str = "scheduler";
+/// The name of the attribute used to declare that the state root commits to the state of a pallet.
+const STATE_ATTR: &str = "state";
+/// The name of the attribute used to declare the index of a pallet.
+const PALLET_INDEX_ATTR: &str = "pallet_index";
+
 impl RuntimeDef {
 	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
 		// First we check that we are parsing a `struct`.
@@ -19,8 +86,12 @@ impl RuntimeDef {
 			return Err(syn::Error::new(item.span(), "Invalid runtime, expected item struct"))
 		};
 
-		// We check that the `Runtime` includes the `system` pallet as the first item.
+		// We check that the `Runtime` includes the `system` pallet as the first item, and that every
+		// field is a pallet of this runtime.
 		check_system(&item_struct)?;
+		for field in &item_struct.fields {
+			check_pallet_type(&item_struct.ident, &field.ty)?;
+		}
 
 		let runtime_struct = item_struct.ident;
 
@@ -28,8 +99,43 @@ impl RuntimeDef {
 		let mut pallets = vec![];
 		// We skip `system`, which we ensure is the first field in `check_system`.
 		for field in item_struct.fields.into_iter().skip(1) {
+			let context = parse_dispatch_context_attr(&field)?;
+			let has_events = field.attrs.iter().any(|attr| attr.path().is_ident(EVENTS_ATTR));
+			let has_genesis = field.attrs.iter().any(|attr| attr.path().is_ident(GENESIS_ATTR));
+			let has_hooks = field.attrs.iter().any(|attr| attr.path().is_ident(HOOKS_ATTR));
+			let hooks_context = parse_hooks_attr(&field)?;
+			let has_migrations = field.attrs.iter().any(|attr| attr.path().is_ident(MIGRATIONS_ATTR));
+			let is_scheduler = field.attrs.iter().any(|attr| attr.path().is_ident(SCHEDULER_ATTR));
+			let has_state = field.attrs.iter().any(|attr| attr.path().is_ident(STATE_ATTR));
+			let index = parse_pallet_index_attr(&field, pallets.len())?;
+			if let Some(other) = pallets.iter().find(|other: &&PalletDef| other.index == index) {
+				let msg = format!("pallet index {index} is already used by `{}`", other.name);
+				return Err(syn::Error::new(field.span(), msg))
+			}
+			// The same pallet can only be included several times with different instances, otherwise
+			// they would keep their state under the same keys.
+			let ty = field.ty.to_token_stream().to_string();
+			if let Some(other) = pallets.iter().find(|other: &&PalletDef| other.ty.to_token_stream().to_string() == ty) {
+				let msg = format!("pallet is already included as `{}`, include it with another instance", other.name);
+				return Err(syn::Error::new(field.ty.span(), msg))
+			}
 			if let Some(ident) = field.ident {
-				pallets.push((ident, field.ty))
+				let (module, instance) = parse_pallet_type(&ident, &field.ty);
+				pallets.push(PalletDef {
+					name: ident,
+					ty: field.ty,
+					module,
+					instance,
+					index,
+					context,
+					has_events,
+					has_genesis,
+					has_hooks,
+					hooks_context,
+					has_migrations,
+					is_scheduler,
+					has_state,
+				})
 			}
 		}
 
@@ -37,6 +143,116 @@ impl RuntimeDef {
 	}
 }
 
+/// Parse the module and the instance of a pallet out of its type, like `balances` and
+/// `support::Instance1` for `balances::Pallet<Runtime, support::Instance1>`. The instance is the
+/// second generic argument of the pallet, and the module is the field `name` when the type isn't
+/// a path to a module.
+fn parse_pallet_type(name: &syn::Ident, ty: &syn::Type) -> (syn::Path, Option<syn::Type>) {
+	let path = match ty {
+		syn::Type::Path(type_path) => &type_path.path,
+		_ => return (name.clone().into(), None),
+	};
+	let module = if path.segments.len() > 1 {
+		let mut module = path.clone();
+		module.segments.pop();
+		module.segments.pop_punct();
+		module
+	} else {
+		name.clone().into()
+	};
+	let instance = match path.segments.last().map(|segment| &segment.arguments) {
+		Some(syn::PathArguments::AngleBracketed(args)) => {
+			args.args.iter().nth(1).and_then(|arg| match arg {
+				syn::GenericArgument::Type(instance) => Some(instance.clone()),
+				_ => None,
+			})
+		},
+		_ => None,
+	};
+	(module, instance)
+}
+
+/// Parse the context a pallet is dispatched with, declared as `#[dispatch_context(field)]`, or
+/// `#[dispatch_context(self)]` for the runtime itself.
+fn parse_dispatch_context_attr(field: &syn::Field) -> syn::Result<Option<syn::Ident>> {
+	let mut attrs = field.attrs.iter().filter(|attr| attr.path().is_ident(DISPATCH_CONTEXT_ATTR));
+	let attr = match attrs.next() {
+		Some(attr) => attr,
+		None => return Ok(None),
+	};
+	if let Some(duplicate) = attrs.next() {
+		let msg = "dispatch context must only be declared once";
+		return Err(syn::Error::new(duplicate.span(), msg))
+	}
+	attr.parse_args_with(syn::Ident::parse_any).map(Some)
+}
+
+/// Parse the context the hooks of a pallet are called with, declared as `#[hooks(field)]`. Pallets
+/// declared with `#[hooks]` have no context.
+fn parse_hooks_attr(field: &syn::Field) -> syn::Result<Option<syn::Ident>> {
+	match field.attrs.iter().find(|attr| attr.path().is_ident(HOOKS_ATTR)).map(|attr| &attr.meta) {
+		Some(syn::Meta::List(list)) => list.parse_args::<syn::Ident>().map(Some),
+		_ => Ok(None),
+	}
+}
+
+/// Parse the index of a pallet, declared as `#[pallet_index = N]`. Pallets without it are indexed
+/// by `position`, their position in the runtime.
+fn parse_pallet_index_attr(field: &syn::Field, position: usize) -> syn::Result<u8> {
+	let mut attrs = field.attrs.iter().filter(|attr| attr.path().is_ident(PALLET_INDEX_ATTR));
+	let attr = match attrs.next() {
+		Some(attr) => attr,
+		None => {
+			return u8::try_from(position).map_err(|_| {
+				syn::Error::new(field.span(), "a runtime can't have more than 256 pallets")
+			})
+		},
+	};
+	if let Some(duplicate) = attrs.next() {
+		let msg = "pallet index must only be declared once";
+		return Err(syn::Error::new(duplicate.span(), msg))
+	}
+	match &attr.meta {
+		syn::Meta::NameValue(syn::MetaNameValue {
+			value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(index), .. }),
+			..
+		}) => index.base10_parse::<u8>(),
+		_ => {
+			let msg = "invalid pallet index attribute: expected `#[pallet_index = N]`, with N from 0 to 255";
+			Err(syn::Error::new(attr.span(), msg))
+		},
+	}
+}
+
+/// Remove the `#[dispatch_context(field)]`, `#[events]`, `#[genesis]`, `#[hooks]`, `#[migrations]`,
+/// `#[pallet_index = N]`, `#[scheduler]` and `#[state]` attributes from the `Runtime` struct, since they are not real attributes and are only used by this macro.
+pub fn strip_runtime_attrs(item: &mut syn::Item) {
+	if let syn::Item::Struct(item_struct) = item {
+		for field in item_struct.fields.iter_mut() {
+			field.attrs.retain(|attr| {
+				!attr.path().is_ident(DISPATCH_CONTEXT_ATTR) &&
+					!attr.path().is_ident(EVENTS_ATTR) &&
+					!attr.path().is_ident(GENESIS_ATTR) &&
+					!attr.path().is_ident(HOOKS_ATTR) &&
+					!attr.path().is_ident(MIGRATIONS_ATTR) &&
+					!attr.path().is_ident(PALLET_INDEX_ATTR) &&
+					!attr.path().is_ident(SCHEDULER_ATTR) &&
+					!attr.path().is_ident(STATE_ATTR)
+			});
+		}
+	}
+}
+
+/// Add the `dispatching` field to the `Runtime` struct, which records the pallets declared with
+/// `#[dispatch_context(self)]` while they dispatch a call, see `support::Dispatching`.
+pub fn add_dispatching_field(item: &mut syn::Item) {
+	if let syn::Item::Struct(item_struct) = item {
+		if let syn::Fields::Named(fields) = &mut item_struct.fields {
+			fields.named.push(syn::parse_quote! { dispatching: crate::support::Dispatching });
+		}
+	}
+}
+
 /// This function checks that the `system` pallet is the first pallet included in the `Runtime`
 /// struct. We make many assumptions about the `system` pallet in order to keep these macros simple.
 /// For example, we assume that the system pallet has no callable functions, and that it contains
@@ -44,24 +260,57 @@ impl RuntimeDef {
 ///
 /// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
 fn check_system(item_struct: &syn::ItemStruct) -> syn::Result<()> {
-	// Extract the name of the first field in the `Runtime` struct.
-	let first_field_name = if let Some(first_field) = item_struct.fields.iter().next() {
-		if let Some(field_name) = &first_field.ident {
-			field_name.to_string()
-		} else {
-			let msg = "first field is expected to have the name system";
-			return Err(syn::Error::new(item_struct.span(), msg))
-		}
-	} else {
-		let msg = "runtime struct is expected to have fields";
-		return Err(syn::Error::new(item_struct.span(), msg))
+	let runtime = &item_struct.ident;
+	let first_field = match &item_struct.fields {
+		syn::Fields::Named(fields) => fields.named.first(),
+		syn::Fields::Unnamed(fields) => {
+			let msg = "runtime struct is expected to have named fields, one for each pallet";
+			return Err(syn::Error::new(fields.span(), msg))
+		},
+		syn::Fields::Unit => None,
+	};
+	let first_field = match first_field {
+		Some(field) => field,
+		None => {
+			let msg = format!("runtime struct is expected to have fields, starting with `system: system::Pallet<{runtime}>`");
+			return Err(syn::Error::new(runtime.span(), msg))
+		},
 	};
 
-	// Check if the first field is named "system"
-	if first_field_name != "system" {
-		let msg = "first field is expected to be named system";
-		return Err(syn::Error::new(item_struct.span(), msg))
+	// Check if the first field is named "system", and point at the `system` field when it is
+	// declared after another pallet.
+	if first_field.ident.as_ref().is_some_and(|ident| ident == "system") {
+		return Ok(())
 	}
+	match item_struct.fields.iter().find(|field| field.ident.as_ref().is_some_and(|ident| ident == "system")) {
+		Some(system) => {
+			let msg = "the system pallet is expected to be the first field of the runtime";
+			Err(syn::Error::new(system.span(), msg))
+		},
+		None => {
+			let msg = format!("runtime is missing the system pallet, expected `system: system::Pallet<{runtime}>` as its first field");
+			Err(syn::Error::new(runtime.span(), msg))
+		},
+	}
+}
 
-	Ok(())
+/// This function checks that the type of a field of the `Runtime` struct is a pallet of this
+/// runtime, like `balances::Pallet<Runtime>`: its first generic argument must be the runtime.
+fn check_pallet_type(runtime: &syn::Ident, ty: &syn::Type) -> syn::Result<()> {
+	let first_arg = match ty {
+		syn::Type::Path(type_path) if type_path.qself.is_none() => {
+			match type_path.path.segments.last().map(|segment| &segment.arguments) {
+				Some(syn::PathArguments::AngleBracketed(args)) => args.args.first(),
+				_ => None,
+			}
+		},
+		_ => None,
+	};
+	match first_arg {
+		Some(syn::GenericArgument::Type(syn::Type::Path(arg))) if arg.path.is_ident(runtime) => Ok(()),
+		_ => {
+			let msg = format!("invalid pallet type, expected a pallet of `{runtime}`, like `balances::Pallet<{runtime}>`");
+			Err(syn::Error::new(ty.span(), msg))
+		},
+	}
 }
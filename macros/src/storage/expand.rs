@@ -0,0 +1,116 @@
+use super::parse::{StorageDef, StorageKind};
+use quote::{format_ident, quote};
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_storage(def: StorageDef) -> proc_macro2::TokenStream {
+	let StorageDef { pallet_struct, generics, getters, events, transactional } = def;
+	let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+	// This is a vector of the getter functions of the fields, with the iterators over the maps
+	// declared with `iter`.
+	let getter_fns = getters
+		.iter()
+		.map(|getter| {
+			let field = &getter.field;
+			let name = &getter.name;
+			let docs = &getter.docs;
+			let getter_fn = match (&getter.kind, &getter.default) {
+				// Values are returned by value, like the balances of the runtime.
+				(StorageKind::Value { ty, wrapped: false }, _) => quote! {
+					#( #docs )*
+					#[allow(clippy::clone_on_copy)]
+					pub fn #name(&self) -> #ty {
+						self.#field.clone()
+					}
+				},
+				(StorageKind::Value { ty, wrapped: true }, _) => quote! {
+					#( #docs )*
+					#[allow(clippy::clone_on_copy)]
+					pub fn #name(&self) -> #ty {
+						(*self.#field).clone()
+					}
+				},
+				// Maps with a default return it for the keys which are missing.
+				(StorageKind::Map { key, value }, Some(default)) => quote! {
+					#( #docs )*
+					pub fn #name(&self, key: &#key) -> #value {
+						self.#field.get(key).cloned().unwrap_or_else(|| #default)
+					}
+				},
+				(StorageKind::Map { key, value }, None) => quote! {
+					#( #docs )*
+					pub fn #name(&self, key: &#key) -> Option<&#value> {
+						self.#field.get(key)
+					}
+				},
+			};
+			let iter_fn = match &getter.kind {
+				StorageKind::Map { key, value } if getter.iter => {
+					let iter_name = format_ident!("iter_{}", field);
+					quote! {
+						// Iterate over the entries of the map, sorted by key.
+						pub fn #iter_name(&self) -> impl Iterator<Item = (&#key, &#value)> {
+							self.#field.iter()
+						}
+					}
+				},
+				_ => quote! {},
+			};
+			quote! {
+				#getter_fn
+				#iter_fn
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// These are the functions through which the runtime moves the events of the pallet to the
+	// system pallet, and tells it the phase they are emitted in.
+	let events_fns = match events {
+		Some((field, event)) => quote! {
+			/// Get the events emitted by this pallet with the phase they were emitted in, oldest
+			/// first. The runtime moves them to the system pallet with `take_events`.
+			pub fn events(&self) -> &[crate::support::EventRecord<#event>] {
+				self.#field.records()
+			}
+
+			/// Take the events emitted by this pallet since they were last taken.
+			pub fn take_events(&mut self) -> crate::codec::Vec<crate::support::EventRecord<#event>> {
+				self.#field.take()
+			}
+
+			/// Set the phase of the block being executed, which the next events are recorded with.
+			pub fn set_phase(&mut self, phase: crate::support::Phase) {
+				self.#field.set_phase(phase)
+			}
+
+			// Emit `event`, recorded with the phase of the block being executed.
+			fn deposit_event(&mut self, event: #event) {
+				self.#field.deposit(event)
+			}
+		},
+		None => quote! {},
+	};
+
+	quote! {
+		impl #impl_generics #pallet_struct #type_generics #where_clause {
+			#( #getter_fns )*
+			#events_fns
+		}
+
+		// A transaction is started, committed or undone in every field which records its writes,
+		// in the order of the fields.
+		impl #impl_generics crate::support::Transactional for #pallet_struct #type_generics #where_clause {
+			fn start_transaction(&mut self) {
+				#( crate::support::Transactional::start_transaction(&mut self.#transactional); )*
+			}
+
+			fn commit_transaction(&mut self) {
+				#( crate::support::Transactional::commit_transaction(&mut self.#transactional); )*
+			}
+
+			fn rollback_transaction(&mut self) {
+				#( crate::support::Transactional::rollback_transaction(&mut self.#transactional); )*
+			}
+		}
+	}
+}
@@ -0,0 +1,27 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn storage(
+	_attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let mut item_struct = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the storage fields of the pallet...
+	let generated: proc_macro::TokenStream = match parse::StorageDef::try_from(item_struct.clone()) {
+		// ..then we generate our new code.
+		Ok(def) => expand::expand_storage(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	};
+
+	// The final expanded code will be placed here.
+	// Our final product will contain all of our old code too, except for the `#[getter]`
+	// attributes, which are only meaningful to this macro.
+	parse::strip_getter_attrs(&mut item_struct);
+	let mut finished: proc_macro::TokenStream = quote::quote!(#item_struct).into();
+
+	// Add our generated code to the end, and return the final result.
+	finished.extend(generated);
+	return finished;
+}
@@ -0,0 +1,196 @@
+use syn::spanned::Spanned;
+
+/// The name of the attribute used to declare the getter of a storage field.
+const GETTER_ATTR: &str = "getter";
+/// The types of the fields which record their writes, so they can be changed in a transaction.
+const TRANSACTIONAL_TYPES: [&str; 4] = ["StorageMap", "StorageSet", "StorageValue", "Events"];
+
+/// This object will collect all the information we need to keep while parsing the storage fields
+/// of a pallet.
+#[derive(Debug)]
+pub struct StorageDef {
+	/// This is the name of the pallet struct. We mostly assume it is `Pallet`.
+	pub pallet_struct: syn::Ident,
+	/// These are the generics of the pallet struct, like `<T: Config>`.
+	pub generics: syn::Generics,
+	/// This is a list of the getters declared on the fields of the pallet. See `GetterDef`.
+	pub getters: Vec<GetterDef>,
+	/// The field holding the events of the pallet, a `support::Events<Event>`, with the type of
+	/// its events, if the pallet emits any.
+	pub events: Option<(syn::Ident, syn::Type)>,
+	/// The fields which can be changed in a transaction: the `support::StorageMap`,
+	/// `support::StorageSet`, `support::StorageValue` and `support::Events` fields.
+	pub transactional: Vec<syn::Ident>,
+}
+
+/// This is the metadata we keep about each getter of a storage field.
+#[derive(Debug)]
+pub struct GetterDef {
+	/// The name of the field.
+	pub field: syn::Ident,
+	/// The name of the getter function.
+	pub name: syn::Ident,
+	/// The kind of storage the field holds. See `StorageKind`.
+	pub kind: StorageKind,
+	/// The value returned for the keys of a map which are missing, declared with `default` or
+	/// `default = ...`. Getters of maps without it return an `Option`.
+	pub default: Option<syn::Expr>,
+	/// Whether an iterator over the entries of a map is generated too, declared with `iter`. It is
+	/// named after the field, like `iter_claims`.
+	pub iter: bool,
+	/// The doc comment of the field, which documents its getter.
+	pub docs: Vec<syn::Attribute>,
+}
+
+/// The kinds of storage a pallet field can hold.
+#[derive(Debug)]
+pub enum StorageKind {
+	/// A single value, like a counter. It is kept in a `StorageValue` when `wrapped` is set.
+	Value { ty: syn::Type, wrapped: bool },
+	/// A `BTreeMap` or a `StorageMap` from keys to values.
+	Map { key: syn::Type, value: syn::Type },
+}
+
+impl StorageDef {
+	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing a `struct`.
+		let item_struct = if let syn::Item::Struct(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::storage, expected item struct"))
+		};
+
+		let mut getters = vec![];
+		let mut events = None;
+		let mut transactional = vec![];
+		for field in &item_struct.fields {
+			if let (true, Some(field_name)) = (is_transactional(&field.ty), &field.ident) {
+				transactional.push(field_name.clone());
+			}
+			if let (Some(event), Some(field_name)) = (parse_events_type(&field.ty), &field.ident) {
+				if events.is_some() {
+					let msg = "Invalid pallet::storage, only one field can hold the events";
+					return Err(syn::Error::new(field.span(), msg))
+				}
+				events = Some((field_name.clone(), event));
+			}
+			let mut attrs = field.attrs.iter().filter(|attr| attr.path().is_ident(GETTER_ATTR));
+			let attr = match attrs.next() {
+				Some(attr) => attr,
+				None => continue,
+			};
+			if let Some(duplicate) = attrs.next() {
+				let msg = "Invalid pallet::storage, getter must only be declared once";
+				return Err(syn::Error::new(duplicate.span(), msg))
+			}
+			let field_name = match &field.ident {
+				Some(ident) => ident.clone(),
+				None => {
+					let msg = "Invalid pallet::storage, only named fields can have a getter";
+					return Err(syn::Error::new(field.span(), msg))
+				},
+			};
+			getters.push(parse_getter_attr(attr, field_name, field)?);
+		}
+
+		Ok(Self { pallet_struct: item_struct.ident, generics: item_struct.generics, getters, events, transactional })
+	}
+}
+
+/// Parse the getter of `field`, declared as `#[getter(name)]` for a value, or as
+/// `#[getter(name, default = ..., iter)]` for a map, where `default` and `iter` are optional.
+fn parse_getter_attr(attr: &syn::Attribute, field_name: syn::Ident, field: &syn::Field) -> syn::Result<GetterDef> {
+	let mut name = None;
+	let mut default = None;
+	let mut iter = false;
+	attr.parse_nested_meta(|meta| {
+		if meta.path.is_ident("default") {
+			default = Some(if meta.input.peek(syn::Token![=]) {
+				meta.value()?.parse()?
+			} else {
+				syn::parse_quote!(Default::default())
+			});
+		} else if meta.path.is_ident("iter") {
+			iter = true;
+		} else if let (None, Some(ident)) = (&name, meta.path.get_ident()) {
+			name = Some(ident.clone());
+		} else {
+			return Err(meta.error("invalid getter attribute: expected `#[getter(name, default = ..., iter)]`"))
+		}
+		Ok(())
+	})?;
+	let name = match name {
+		Some(name) => name,
+		None => {
+			let msg = "invalid getter attribute: missing the name of the getter";
+			return Err(syn::Error::new(attr.span(), msg))
+		},
+	};
+
+	let kind = parse_storage_kind(&field.ty);
+	if matches!(kind, StorageKind::Value { .. }) && (default.is_some() || iter) {
+		let msg = "invalid getter attribute: `default` and `iter` can only be declared on a map";
+		return Err(syn::Error::new(attr.span(), msg))
+	}
+	let docs = field.attrs.iter().filter(|attr| attr.path().is_ident("doc")).cloned().collect();
+	Ok(GetterDef { field: field_name, name, kind, default, iter, docs })
+}
+
+/// Parse the kind of storage held by a field of type `ty`: a `BTreeMap<K, V>` or a
+/// `StorageMap<K, V>` is a map, a `StorageValue<T>` is a value of type `T`, and any other type is a
+/// value.
+fn parse_storage_kind(ty: &syn::Type) -> StorageKind {
+	if let syn::Type::Path(type_path) = ty {
+		if let Some(segment) = type_path.path.segments.last() {
+			if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+				let types = args
+					.args
+					.iter()
+					.filter_map(|arg| match arg {
+						syn::GenericArgument::Type(ty) => Some(ty.clone()),
+						_ => None,
+					})
+					.collect::<Vec<_>>();
+				if (segment.ident == "BTreeMap" || segment.ident == "StorageMap") && types.len() == 2 {
+					return StorageKind::Map { key: types[0].clone(), value: types[1].clone() }
+				}
+				if segment.ident == "StorageValue" && types.len() == 1 {
+					return StorageKind::Value { ty: types[0].clone(), wrapped: true }
+				}
+			}
+		}
+	}
+	StorageKind::Value { ty: ty.clone(), wrapped: false }
+}
+
+/// Whether a field of type `ty` can be changed in a transaction, which is the case of the
+/// `StorageMap`, `StorageSet`, `StorageValue` and `Events` fields.
+fn is_transactional(ty: &syn::Type) -> bool {
+	let syn::Type::Path(type_path) = ty else { return false };
+	type_path
+		.path
+		.segments
+		.last()
+		.is_some_and(|segment| TRANSACTIONAL_TYPES.iter().any(|name| segment.ident == name))
+}
+
+/// Parse the type of the events held by a field of type `ty`, when it is an `Events<Event>`.
+fn parse_events_type(ty: &syn::Type) -> Option<syn::Type> {
+	let syn::Type::Path(type_path) = ty else { return None };
+	let segment = type_path.path.segments.last()?;
+	let syn::PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+	match (segment.ident == "Events", args.args.first()) {
+		(true, Some(syn::GenericArgument::Type(event))) if args.args.len() == 1 => Some(event.clone()),
+		_ => None,
+	}
+}
+
+/// Remove the `#[getter]` attributes from the fields of the pallet, since they are not real
+/// attributes and are only used by this macro.
+pub fn strip_getter_attrs(item: &mut syn::Item) {
+	if let syn::Item::Struct(item_struct) = item {
+		for field in item_struct.fields.iter_mut() {
+			field.attrs.retain(|attr| !attr.path().is_ident(GETTER_ATTR));
+		}
+	}
+}
@@ -0,0 +1,362 @@
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+
+use crate::codec::Encode;
+use crate::storage::{storage_double_map_key, storage_map_key, StorageBackend};
+use crate::support::{ArithmeticError, DispatchError, DispatchResult, Events, StorageMap};
+
+pub trait Config: crate::system::Config {
+	/// The identifier of an asset.
+	type AssetId: Ord + Copy + 'static;
+	/// The balance of an asset, which is distinct from the native balance of the balances pallet.
+	type AssetBalance: Zero + CheckedAdd + CheckedSub + Copy + PartialOrd + 'static;
+}
+
+/// An asset, which only its admin can mint, burn and freeze.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetDetails<AccountId, Balance> {
+	/// The account which created the asset.
+	pub owner: AccountId,
+	/// The account which can mint, burn and freeze the asset.
+	pub admin: AccountId,
+	/// The total balance of the asset held by all the accounts.
+	pub supply: Balance,
+}
+
+/// The balance of an account in an asset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AssetAccount<Balance> {
+	pub balance: Balance,
+	/// Whether the account can't transfer the asset.
+	pub is_frozen: bool,
+}
+
+/// The events emitted by the assets pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, AssetId, Balance> {
+	/// An asset was created.
+	Created { asset_id: AssetId, owner: AccountId },
+	/// An amount of an asset was minted to an account.
+	Issued { asset_id: AssetId, owner: AccountId, amount: Balance },
+	/// An amount of an asset was burnt from an account.
+	Burned { asset_id: AssetId, owner: AccountId, amount: Balance },
+	/// An amount of an asset was transferred.
+	Transferred { asset_id: AssetId, from: AccountId, to: AccountId, amount: Balance },
+	/// An account was frozen in an asset.
+	Frozen { asset_id: AssetId, who: AccountId },
+	/// An account was thawed in an asset.
+	Thawed { asset_id: AssetId, who: AccountId },
+}
+
+/// The errors returned by the assets pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The asset already exists.
+	InUse,
+	/// The asset does not exist.
+	Unknown,
+	/// The caller isn't the admin of the asset.
+	NoPermission,
+	/// The account doesn't have enough of the asset.
+	BalanceLow,
+	/// The account is frozen in the asset.
+	Frozen,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::InUse => "InUse",
+			Error::Unknown => "Unknown",
+			Error::NoPermission => "NoPermission",
+			Error::BalanceLow => "BalanceLow",
+			Error::Frozen => "Frozen",
+		};
+		DispatchError::Module { pallet: "assets", error }
+	}
+}
+
+/// The events of the assets pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> =
+	Event<<T as crate::system::Config>::AccountId, <T as Config>::AssetId, <T as Config>::AssetBalance>;
+
+/// This is the Assets Module.
+/// It allows accounts to create fungible assets besides the native balance, each with an admin
+/// which mints and burns it, and which can freeze the accounts holding it.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The assets, keyed by their id.
+	#[allow(clippy::type_complexity)]
+	assets: StorageMap<T::AssetId, AssetDetails<T::AccountId, T::AssetBalance>>,
+	/// The balance of each account in each asset, keyed by the asset and the account. Accounts
+	/// which hold none of an asset and aren't frozen in it have no entry.
+	#[allow(clippy::type_complexity)]
+	accounts: StorageMap<(T::AssetId, T::AccountId), AssetAccount<T::AssetBalance>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<EventOf<T>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Create the asset `asset_id`, owned by the `caller`, who is also its admin.
+	/// This function will return an error if the asset already exists.
+	#[call_index = 0]
+	#[weight = 5_000]
+	pub fn create(&mut self, caller: T::AccountId, asset_id: T::AssetId) -> DispatchResult {
+		if self.assets.contains_key(&asset_id) {
+			return Err(Error::InUse.into());
+		}
+
+		let details = AssetDetails { owner: caller.clone(), admin: caller.clone(), supply: T::AssetBalance::zero() };
+		self.assets.insert(asset_id, details);
+		self.deposit_event(Event::Created { asset_id, owner: caller });
+		Ok(())
+	}
+
+	/// Mint `amount` of `asset_id` to `beneficiary`. Only the admin of the asset can mint.
+	/// This function will return an error if the asset does not exist, if the caller isn't its
+	/// admin, or if its supply overflows.
+	#[call_index = 1]
+	#[weight = 5_000]
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		beneficiary: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let details = self.admin_of(&caller, asset_id)?;
+		let supply = details.supply.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+		// The balance of an account can't exceed the supply, so it can't overflow either.
+		let balance = self.balance(asset_id, &beneficiary).checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+
+		self.assets.entry(asset_id).and_modify(|details| details.supply = supply);
+		self.set_balance(asset_id, beneficiary.clone(), balance);
+		self.deposit_event(Event::Issued { asset_id, owner: beneficiary, amount });
+		Ok(())
+	}
+
+	/// Burn `amount` of `asset_id` from `who`. Only the admin of the asset can burn.
+	/// This function will return an error if the asset does not exist, if the caller isn't its
+	/// admin, or if `who` holds less than `amount`.
+	#[call_index = 2]
+	#[weight = 5_000]
+	pub fn burn(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		who: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let details = self.admin_of(&caller, asset_id)?;
+		let supply = details.supply.checked_sub(&amount).ok_or(Error::BalanceLow)?;
+		let balance = self.balance(asset_id, &who).checked_sub(&amount).ok_or(Error::BalanceLow)?;
+
+		self.assets.entry(asset_id).and_modify(|details| details.supply = supply);
+		self.set_balance(asset_id, who.clone(), balance);
+		self.deposit_event(Event::Burned { asset_id, owner: who, amount });
+		Ok(())
+	}
+
+	/// Transfer `amount` of `asset_id` from the `caller` to `to`.
+	/// This function will return an error if the asset does not exist, if the caller is frozen in
+	/// it, or if the caller holds less than `amount`.
+	#[call_index = 3]
+	#[weight = 5_000]
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		to: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		if !self.assets.contains_key(&asset_id) {
+			return Err(Error::Unknown.into());
+		}
+		if self.is_frozen(asset_id, &caller) {
+			return Err(Error::Frozen.into());
+		}
+		let from_balance = self.balance(asset_id, &caller).checked_sub(&amount).ok_or(Error::BalanceLow)?;
+
+		if caller != to {
+			let to_balance = self.balance(asset_id, &to).checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+			self.set_balance(asset_id, caller.clone(), from_balance);
+			self.set_balance(asset_id, to.clone(), to_balance);
+		}
+		self.deposit_event(Event::Transferred { asset_id, from: caller, to, amount });
+		Ok(())
+	}
+
+	/// Freeze `who` in `asset_id`, so it can't transfer the asset until it is thawed. Only the
+	/// admin of the asset can freeze.
+	/// This function will return an error if the asset does not exist, or if the caller isn't its
+	/// admin.
+	#[call_index = 4]
+	#[weight = 2_000]
+	pub fn freeze(&mut self, caller: T::AccountId, asset_id: T::AssetId, who: T::AccountId) -> DispatchResult {
+		self.admin_of(&caller, asset_id)?;
+
+		self.accounts
+			.entry((asset_id, who.clone()))
+			.or_insert(AssetAccount { balance: T::AssetBalance::zero(), is_frozen: false })
+			.is_frozen = true;
+		self.deposit_event(Event::Frozen { asset_id, who });
+		Ok(())
+	}
+
+	/// Thaw `who` in `asset_id`, so it can transfer the asset again. Only the admin of the asset
+	/// can thaw.
+	/// This function will return an error if the asset does not exist, or if the caller isn't its
+	/// admin.
+	#[call_index = 5]
+	#[weight = 2_000]
+	pub fn thaw(&mut self, caller: T::AccountId, asset_id: T::AssetId, who: T::AccountId) -> DispatchResult {
+		self.admin_of(&caller, asset_id)?;
+
+		if let Some(account) = self.accounts.get_mut(&(asset_id, who.clone())) {
+			account.is_frozen = false;
+			if account.balance.is_zero() {
+				self.accounts.remove(&(asset_id, who.clone()));
+			}
+		}
+		self.deposit_event(Event::Thawed { asset_id, who });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Assets Module.
+	pub fn new() -> Self {
+		Self { assets: StorageMap::new(), accounts: StorageMap::new(), events: Events::new() }
+	}
+
+	/// Get the asset `asset_id`, if it exists.
+	pub fn asset(&self, asset_id: T::AssetId) -> Option<&AssetDetails<T::AccountId, T::AssetBalance>> {
+		self.assets.get(&asset_id)
+	}
+
+	/// Get the balance of `who` in `asset_id`, which is zero if it holds none of it.
+	pub fn balance(&self, asset_id: T::AssetId, who: &T::AccountId) -> T::AssetBalance {
+		self.accounts
+			.get(&(asset_id, who.clone()))
+			.map(|account| account.balance)
+			.unwrap_or_else(T::AssetBalance::zero)
+	}
+
+	/// Whether `who` is frozen in `asset_id`.
+	pub fn is_frozen(&self, asset_id: T::AssetId, who: &T::AccountId) -> bool {
+		self.accounts.get(&(asset_id, who.clone())).is_some_and(|account| account.is_frozen)
+	}
+
+	/// Whether `who` owns or administers an asset, or has an account in one.
+	pub fn has_assets(&self, who: &T::AccountId) -> bool {
+		self.assets.values().any(|details| details.owner == *who || details.admin == *who)
+			|| self.accounts.keys().any(|(_, account)| account == who)
+	}
+
+	/// Get the asset `asset_id`, checking that `caller` is its admin.
+	fn admin_of(
+		&self,
+		caller: &T::AccountId,
+		asset_id: T::AssetId,
+	) -> Result<&AssetDetails<T::AccountId, T::AssetBalance>, Error> {
+		let details = self.assets.get(&asset_id).ok_or(Error::Unknown)?;
+		if details.admin != *caller {
+			return Err(Error::NoPermission);
+		}
+		Ok(details)
+	}
+
+	/// Set the balance of `who` in `asset_id`, removing its entry when it holds none of the asset
+	/// and isn't frozen in it.
+	fn set_balance(&mut self, asset_id: T::AssetId, who: T::AccountId, balance: T::AssetBalance) {
+		let is_frozen = self.is_frozen(asset_id, &who);
+		if balance.is_zero() && !is_frozen {
+			self.accounts.remove(&(asset_id, who));
+		} else {
+			self.accounts.insert((asset_id, who), AssetAccount { balance, is_frozen });
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::AssetId: Encode,
+	T::AssetBalance: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for (asset_id, details) in &self.assets {
+			let value = ((&details.owner, &details.admin), details.supply).encode();
+			state.insert(storage_map_key("assets", "assets", asset_id), value);
+		}
+		for ((asset_id, who), account) in &self.accounts {
+			let key = storage_double_map_key("assets", "accounts", asset_id, who);
+			state.insert(key, (account.balance, account.is_frozen).encode());
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::Error;
+	use crate::support::ArithmeticError;
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		type AssetId = u32;
+		type AssetBalance = u64;
+	}
+
+	#[test]
+	fn admins_mint_and_burn_assets() {
+		let mut assets = super::Pallet::<TestConfig>::new();
+		assert_eq!(assets.mint("alice", 0, "bob", 10), Err(Error::Unknown.into()));
+		assert_eq!(assets.create("alice", 0), Ok(()));
+		assert_eq!(assets.create("bob", 0), Err(Error::InUse.into()));
+
+		assert_eq!(assets.mint("bob", 0, "bob", 10), Err(Error::NoPermission.into()));
+		assert_eq!(assets.mint("alice", 0, "bob", 10), Ok(()));
+		assert_eq!(assets.mint("alice", 0, "charlie", u64::MAX), Err(ArithmeticError::Overflow.into()));
+		assert_eq!(assets.burn("alice", 0, "bob", 11), Err(Error::BalanceLow.into()));
+		assert_eq!(assets.burn("alice", 0, "bob", 4), Ok(()));
+		assert_eq!(assets.balance(0, &"bob"), 6);
+		assert_eq!(assets.asset(0).map(|details| details.supply), Some(6));
+
+		// The balances of an asset are separate from those of other assets.
+		assert_eq!(assets.create("bob", 1), Ok(()));
+		assert_eq!(assets.mint("bob", 1, "bob", 3), Ok(()));
+		assert_eq!(assets.balance(0, &"bob"), 6);
+		assert_eq!(assets.balance(1, &"bob"), 3);
+	}
+
+	#[test]
+	fn frozen_accounts_cannot_transfer() {
+		let mut assets = super::Pallet::<TestConfig>::new();
+		assert_eq!(assets.create("alice", 0), Ok(()));
+		assert_eq!(assets.mint("alice", 0, "bob", 10), Ok(()));
+
+		assert_eq!(assets.transfer("bob", 0, "charlie", 11), Err(Error::BalanceLow.into()));
+		assert_eq!(assets.transfer("bob", 0, "charlie", 4), Ok(()));
+		assert_eq!((assets.balance(0, &"bob"), assets.balance(0, &"charlie")), (6, 4));
+
+		assert_eq!(assets.freeze("bob", 0, "bob"), Err(Error::NoPermission.into()));
+		assert_eq!(assets.freeze("alice", 0, "bob"), Ok(()));
+		assert_eq!(assets.transfer("bob", 0, "charlie", 1), Err(Error::Frozen.into()));
+		// Frozen accounts can still receive the asset.
+		assert_eq!(assets.transfer("charlie", 0, "bob", 4), Ok(()));
+		assert_eq!(assets.thaw("alice", 0, "bob"), Ok(()));
+		assert_eq!(assets.transfer("bob", 0, "charlie", 10), Ok(()));
+		assert_eq!(assets.balance(0, &"bob"), 0);
+		assert!(!assets.is_frozen(0, &"bob"));
+	}
+}
@@ -0,0 +1,168 @@
+use alloc::vec::Vec;
+
+use crate::codec::Encode;
+use crate::storage::{storage_key, StorageBackend};
+use crate::support::{ensure_root, DispatchError, DispatchResult, Events, OriginFor, StorageValue};
+
+pub trait Config: crate::system::Config {
+	/// The maximum number of authorities.
+	const MAX_AUTHORITIES: u32;
+}
+
+/// The events emitted by the aura pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId> {
+	/// The authority set was replaced, from the next block on.
+	AuthoritiesChanged { authorities: Vec<AccountId> },
+}
+
+/// The errors returned by the aura pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The authority set is empty, so nobody could author blocks.
+	NoAuthorities,
+	/// There are more than `Config::MAX_AUTHORITIES` authorities.
+	TooManyAuthorities,
+	/// An account is in the authority set more than once.
+	DuplicateAuthority,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::NoAuthorities => "NoAuthorities",
+			Error::TooManyAuthorities => "TooManyAuthorities",
+			Error::DuplicateAuthority => "DuplicateAuthority",
+		};
+		DispatchError::Module { pallet: "aura", error }
+	}
+}
+
+/// The events of the aura pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// This is the Aura Module.
+/// It keeps the authority set of a proof of authority chain: the accounts which take turns to
+/// author the blocks, in order. The consensus engine of the node reads the authority set from the
+/// state of the parent of a block to know who must have authored it.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The authorities, in the order they author blocks.
+	authorities: StorageValue<Vec<T::AccountId>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Replace the authority set with `authorities`, which author the blocks after the current
+	/// block. This can only be called by the root origin.
+	/// This function will return an error if the authority set is empty, too large, or has
+	/// duplicates.
+	#[call_index = 0]
+	#[weight = 1_000u64.saturating_add(100u64.saturating_mul(authorities.len() as u64))]
+	pub fn set_authorities(&mut self, origin: OriginFor<T>, authorities: Vec<T::AccountId>) -> DispatchResult {
+		ensure_root(origin)?;
+		self.try_set_authorities(authorities.clone())?;
+		self.deposit_event(Event::AuthoritiesChanged { authorities });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Aura Module, without authorities.
+	pub fn new() -> Self {
+		Self { authorities: StorageValue::new(Vec::new()), events: Events::new() }
+	}
+
+	/// The authorities, in the order they author blocks.
+	pub fn authorities(&self) -> &[T::AccountId] {
+		&self.authorities
+	}
+
+	/// Check and set the authority set.
+	fn try_set_authorities(&mut self, authorities: Vec<T::AccountId>) -> DispatchResult {
+		if authorities.is_empty() {
+			return Err(Error::NoAuthorities.into());
+		}
+		if authorities.len() > T::MAX_AUTHORITIES as usize {
+			return Err(Error::TooManyAuthorities.into());
+		}
+		if authorities.iter().enumerate().any(|(i, authority)| authorities[..i].contains(authority)) {
+			return Err(Error::DuplicateAuthority.into());
+		}
+		self.authorities.set(authorities);
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		state.insert(storage_key("aura", "authorities"), self.authorities.encode());
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The initial state of the Aura Module, see `RuntimeGenesisConfig` of `#[macros::runtime]`.
+pub struct GenesisConfig<T: Config> {
+	/// The authorities which author the first blocks. Without them, no block can be authored.
+	pub authorities: Vec<T::AccountId>,
+}
+
+impl<T: Config> GenesisConfig<T> {
+	/// Set the authority set, if there is one. This fails if it is too large or has duplicates.
+	pub fn build(&self, aura: &mut Pallet<T>) -> DispatchResult {
+		if self.authorities.is_empty() {
+			return Ok(());
+		}
+		aura.try_set_authorities(self.authorities.clone())
+	}
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { authorities: Vec::new() }
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, Event, GenesisConfig};
+	use crate::support::DispatchError;
+	use crate::support::RawOrigin::{Root, Signed};
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		const MAX_AUTHORITIES: u32 = 3;
+	}
+
+	#[test]
+	fn root_replaces_the_authority_set() {
+		let mut aura = super::Pallet::<TestConfig>::new();
+		assert_eq!(GenesisConfig { authorities: vec!["alice", "bob"] }.build(&mut aura), Ok(()));
+		assert_eq!(aura.authorities(), ["alice", "bob"]);
+
+		assert_eq!(aura.set_authorities(Signed("alice"), vec!["alice"]), Err(DispatchError::BadOrigin));
+		assert_eq!(aura.set_authorities(Root, vec![]), Err(Error::NoAuthorities.into()));
+		assert_eq!(aura.set_authorities(Root, vec!["a", "b", "c", "d"]), Err(Error::TooManyAuthorities.into()));
+		assert_eq!(aura.set_authorities(Root, vec!["bob", "charlie", "bob"]), Err(Error::DuplicateAuthority.into()));
+		assert_eq!(aura.authorities(), ["alice", "bob"]);
+
+		assert_eq!(aura.set_authorities(Root, vec!["charlie", "alice"]), Ok(()));
+		assert_eq!(aura.authorities(), ["charlie", "alice"]);
+		let events = aura.take_events().into_iter().map(|record| record.event).collect::<Vec<_>>();
+		assert_eq!(events, [Event::AuthoritiesChanged { authorities: vec!["charlie", "alice"] }]);
+	}
+}
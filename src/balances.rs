@@ -1,77 +1,920 @@
-use std::collections::BTreeMap;
-use num::traits::{CheckedSub, CheckedAdd, Zero};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use num::traits::{CheckedSub, CheckedAdd, Saturating, Zero};
 
-pub trait Config: crate::system::Config {
-    type Balance: Zero + CheckedSub + CheckedAdd + Copy; 
+use crate::codec::{Decode, Encode};
+use crate::migrations::OnRuntimeUpgrade;
+use crate::storage::{storage_key, storage_map_key, StorageBackend};
+use crate::support::{
+    self, ensure_root, ActualWeight, ArithmeticError, Currency, DispatchError, DispatchResult, Events, Hooks,
+    Instance, OriginFor, StorageMap, StorageValue,
+};
+use crate::weights::balances as weights;
+
+// The configuration of the instance `I` of the balances pallet. A runtime with a single currency
+// implements `Config`, which is the configuration of the instance `()`, and one with several tokens
+// implements `Config<I>` once for each instance, like `Config<support::Instance1>`.
+pub trait Config<I: Instance = ()>: crate::system::Config {
+    /// The balance type. Besides the primitive integers, any custom type (like a newtype or a
+    /// fixed-point number) implementing these arithmetic traits can be used.
+    type Balance: Zero + CheckedSub + CheckedAdd + Saturating + Copy + PartialOrd + 'static;
+    /// The smallest amount which can be transferred, so the chain can't be spammed with tiny or
+    /// zero transfers.
+    const MINIMUM_TRANSFER: Self::Balance;
+    /// The number of transfers an account can make in a single block.
+    const MAX_TRANSFERS_PER_BLOCK: u32;
+    /// The smallest total balance an account can hold. An account whose balance drops below it
+    /// after a transfer is reaped, and what is left of its balance is lost as dust. The genesis
+    /// config of a chain can set another deposit, see `Pallet::existential_deposit`.
+    const EXISTENTIAL_DEPOSIT: Self::Balance;
+
+    /// The account receiving the dust of reaped accounts, like a treasury. By default there is
+    /// none, and the dust is burnt.
+    fn dust_destination() -> Option<Self::AccountId> {
+        None
+    }
 }
 
-#[derive(Debug)]
-pub struct Pallet<T: Config>{
-    balances: BTreeMap<T::AccountId, T::Balance>,
+/// The identifier of a lock, like `*b"staking "`, so the pallet which set it can update it.
+pub type LockIdentifier = [u8; 8];
+
+/// The reasons for which a lock forbids moving the locked funds. Reasons can be combined, like
+/// `WithdrawReasons::TRANSFER | WithdrawReasons::FEE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WithdrawReasons(u8);
+
+impl WithdrawReasons {
+    /// Transfers to other accounts, including funds reserved to be transferred later.
+    pub const TRANSFER: Self = Self(1 << 0);
+    /// Payment of transaction fees.
+    pub const FEE: Self = Self(1 << 1);
+
+    /// Whether these reasons have any reason in common with `other`.
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
 }
 
-#[macros::call]
-impl <T: Config> Pallet<T> {
+impl core::ops::BitOr for WithdrawReasons {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// A lock on the free balance of an account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BalanceLock<Balance> {
+    /// The amount which can't be moved.
+    pub amount: Balance,
+    /// The reasons for which the amount can't be moved.
+    pub reasons: WithdrawReasons,
+}
+
+/// The events emitted by the balances pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, Balance> {
+    /// A transfer succeeded. Includes the free balances of both parties after the transfer.
+    Transferred {
+        from: AccountId,
+        to: AccountId,
+        amount: Balance,
+        from_balance: Balance,
+        to_balance: Balance,
+    },
+    /// An account was reaped with a balance below `Config::EXISTENTIAL_DEPOSIT`, which was lost.
+    /// The dust is paid to `Config::dust_destination`, or burnt if there is none.
+    DustLost { account: AccountId, amount: Balance },
+    /// An account was removed from the pallet, because its balance dropped below
+    /// `Config::EXISTENTIAL_DEPOSIT`.
+    AccountReaped { account: AccountId },
+    /// New funds were created in the account, increasing the total issuance.
+    Minted { account: AccountId, amount: Balance },
+    /// Funds were destroyed from the account, decreasing the total issuance.
+    Burned { account: AccountId, amount: Balance },
+}
+
+/// The errors returned by the balances pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The amount is below `Config::MINIMUM_TRANSFER`.
+    BelowMinimumTransfer,
+    /// The account made `Config::MAX_TRANSFERS_PER_BLOCK` transfers in the current block already.
+    TransferRateLimitExceeded,
+    /// The free balance of the account is lower than the amount.
+    InsufficientBalance,
+    /// The amount would dip into funds which are locked for this kind of withdrawal.
+    BalanceLocked,
+    /// There is no escrow with this id.
+    EscrowDoesNotExist,
+    /// Only the account which created an escrow can cancel it.
+    NotEscrowCreator,
+    /// The account can't be reaped, because it still has a free or reserved balance.
+    AccountHasBalance,
+    /// The transfer would create an account with a balance below `Config::EXISTENTIAL_DEPOSIT`.
+    ExistentialDeposit,
+    /// The transfer would drop the sender below `Config::EXISTENTIAL_DEPOSIT`, and reap them.
+    KeepAlive,
+}
+
+impl From<Error> for DispatchError {
+    fn from(error: Error) -> Self {
+        let error = match error {
+            Error::BelowMinimumTransfer => "BelowMinimumTransfer",
+            Error::TransferRateLimitExceeded => "TransferRateLimitExceeded",
+            Error::InsufficientBalance => "InsufficientBalance",
+            Error::BalanceLocked => "BalanceLocked",
+            Error::EscrowDoesNotExist => "EscrowDoesNotExist",
+            Error::NotEscrowCreator => "NotEscrowCreator",
+            Error::AccountHasBalance => "AccountHasBalance",
+            Error::ExistentialDeposit => "ExistentialDeposit",
+            Error::KeepAlive => "KeepAlive",
+        };
+        DispatchError::Module { pallet: "balances", error }
+    }
+}
+
+/// The events of the instance `I` of the balances pallet for the runtime `T`, as collected by
+/// `#[macros::runtime]`.
+pub type EventOf<T, I = ()> = Event<<T as crate::system::Config>::AccountId, <T as Config<I>>::Balance>;
+
+/// The balances held by an account.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AccountData<Balance> {
+    /// The balance which can be transferred, as long as it is not locked.
+    pub free: Balance,
+    /// The balance which is held by the pallet, like for an escrow, and can't be transferred until
+    /// it is unreserved.
+    pub reserved: Balance,
+}
+
+/// Funds reserved from an account, which are paid to another account at some block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escrow<AccountId, Balance, BlockNumber> {
+    /// The account which created the escrow, and whose funds are reserved.
+    pub from: AccountId,
+    /// The account which receives the funds when they are released.
+    pub to: AccountId,
+    /// The amount reserved from `from`.
+    pub amount: Balance,
+    /// The funds are released at the end of this block.
+    pub release_block: BlockNumber,
+}
+
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config<I>, I: Instance = ()> {
+    // The free and reserved balances of each account, which are zero for the accounts which don't
+    // exist.
+    #[getter(account, default = Self::empty_account())]
+    accounts: StorageMap<T::AccountId, AccountData<T::Balance>>,
+    // The locks on the free balance of each account.
+    locks: StorageMap<T::AccountId, BTreeMap<LockIdentifier, BalanceLock<T::Balance>>>,
+    // The sum of the free and reserved balances of all the accounts.
+    #[getter(total_issuance)]
+    total_issuance: StorageValue<T::Balance>,
+    // The escrows which are waiting to be released, keyed by their id.
+    #[allow(clippy::type_complexity)]
+    #[getter(escrow)]
+    escrows: StorageMap<u32, Escrow<T::AccountId, T::Balance, T::BlockNumber>>,
+    // The id which will be assigned to the next escrow.
+    next_escrow_id: StorageValue<u32>,
+    // The number of transfers made by each account in the current block.
+    transfers_this_block: StorageMap<T::AccountId, u32>,
+    // The smallest total balance an account can hold, which is `Config::EXISTENTIAL_DEPOSIT`
+    // unless the genesis config sets another one.
+    #[getter(existential_deposit)]
+    existential_deposit: T::Balance,
+    events: Events<Event<T::AccountId, T::Balance>>,
+    _instance: PhantomData<I>,
+}
 
+#[macros::call]
+impl<T: Config<I>, I: Instance> Pallet<T, I> {
+    // Transfer `amount` from the caller to `to`. This counts towards the transfers the caller can
+    // make in the current block.
+    #[call_index = 0]
+    #[weight = weights::TRANSFER]
     pub fn transfer(
         &mut self,
         caller: T::AccountId,
         to: T::AccountId,
         amount: T::Balance,
-        ) -> Result<(), &'static str> {
+        ) -> DispatchResult {
+        self.ensure_transfer_allowed(&caller)?;
+        self.do_transfer(caller.clone(), to, amount)?;
+        self.note_transfer(caller);
+        Ok(())
+    }
+
+    // Transfer up to `amount`, moving all of the caller's balance if they don't have enough.
+    // Returns the amount which was actually transferred.
+    #[call_index = 1]
+    #[weight = weights::TRANSFER_SOME]
+    pub fn transfer_some(
+        &mut self,
+        caller: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+        ) -> Result<T::Balance, DispatchError> {
+        self.ensure_transfer_allowed(&caller)?;
+
+        // Only the balance which is not locked for transfers can be moved.
         let caller_balance: T::Balance = self.balance(&caller);
-        let to_balance:<T as Config>::Balance = self.balance(&to);
+        let transferable: T::Balance = caller_balance
+            .saturating_sub(self.locked_balance(&caller, WithdrawReasons::TRANSFER));
+        let transferred: T::Balance = transferable.saturating_sub(transferable.saturating_sub(amount));
 
-        let new_caller_balance: T::Balance = caller_balance 
-            .checked_sub(&amount)
-            .ok_or("Insufficient balance")?;
+        if transferred < T::MINIMUM_TRANSFER {
+            return Err(Error::BelowMinimumTransfer.into());
+        }
+
+        self.move_free(caller.clone(), to, transferred, WithdrawReasons::TRANSFER)?;
+        self.note_transfer(caller);
+
+        Ok(transferred)
+    }
+
+    // Transfer `amount` from the caller to `to`, like `transfer`, but refuse to drop the caller
+    // below the existential deposit, so their account is never reaped by the transfer.
+    #[call_index = 2]
+    #[weight = weights::TRANSFER_KEEP_ALIVE]
+    pub fn transfer_keep_alive(
+        &mut self,
+        caller: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+        ) -> DispatchResult {
+        self.ensure_transfer_allowed(&caller)?;
+        let balance: T::Balance = self.balance(&caller);
+        if balance >= amount && balance.saturating_sub(amount) < self.keep_alive_floor(&caller) {
+            return Err(Error::KeepAlive.into());
+        }
+        self.do_transfer(caller.clone(), to, amount)?;
+        self.note_transfer(caller);
+        Ok(())
+    }
+
+    // Transfer all of the caller's free balance which is not locked for transfers to `to`. With
+    // `keep_alive`, the existential deposit is left behind so the caller's account is not reaped.
+    // Returns the amount which was transferred.
+    #[call_index = 3]
+    #[weight = weights::TRANSFER_ALL]
+    pub fn transfer_all(
+        &mut self,
+        caller: T::AccountId,
+        to: T::AccountId,
+        keep_alive: bool,
+        ) -> Result<T::Balance, DispatchError> {
+        self.ensure_transfer_allowed(&caller)?;
+        let amount: T::Balance = self.reducible_balance(&caller, keep_alive);
+        self.do_transfer(caller.clone(), to, amount)?;
+        self.note_transfer(caller);
+        Ok(amount)
+    }
+
+    // Make a transfer from the caller to each of the `targets`, in order. The transfers are atomic:
+    // if any of them fails, none of them is made. Each transfer counts towards the transfers the
+    // caller can make in the current block. Transfers to the caller don't move any funds, so
+    // their weight is refunded.
+    #[call_index = 4]
+    #[weight = weights::TRANSFER_MULTI_PER_TARGET.saturating_mul(targets.len() as u64)]
+    pub fn transfer_multi(
+        &mut self,
+        caller: T::AccountId,
+        targets: Vec<(T::AccountId, T::Balance)>,
+        ) -> Result<ActualWeight, DispatchError> {
+        support::with_transaction(self, |balances| {
+            let mut moved: u64 = 0;
+            for (to, amount) in targets {
+                if to != caller {
+                    moved += 1;
+                }
+                balances.transfer(caller.clone(), to, amount)?;
+            }
+            Ok(ActualWeight(weights::TRANSFER_MULTI_PER_TARGET.saturating_mul(moved)))
+        })
+    }
+
+    // Reserve `amount` from the caller, to be paid to `to` at the end of `release_block`. Returns
+    // the id of the escrow, which the caller can use to cancel it until then.
+    #[call_index = 5]
+    #[weight = weights::CREATE_ESCROW]
+    pub fn create_escrow(
+        &mut self,
+        caller: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+        release_block: T::BlockNumber,
+        ) -> Result<u32, DispatchError> {
+        let escrow_id: u32 = *self.next_escrow_id;
+        let next_escrow_id: u32 = escrow_id.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+
+        self.reserve(&caller, amount)?;
+        self.escrows.insert(escrow_id, Escrow { from: caller, to, amount, release_block });
+        self.next_escrow_id.set(next_escrow_id);
 
-        let new_to_balance: T::Balance = to_balance 
+        Ok(escrow_id)
+    }
+
+    // Cancel an escrow which was not released yet, giving the reserved funds back to the caller.
+    // Only the account which created the escrow can cancel it.
+    #[call_index = 6]
+    #[weight = weights::CANCEL_ESCROW]
+    pub fn cancel_escrow(&mut self, caller: T::AccountId, escrow_id: u32) -> DispatchResult {
+        let escrow = self.escrows.get(&escrow_id).ok_or(Error::EscrowDoesNotExist)?;
+        if escrow.from != caller {
+            return Err(Error::NotEscrowCreator.into());
+        }
+
+        let amount: T::Balance = escrow.amount;
+        self.escrows.remove(&escrow_id);
+        self.unreserve(&caller, amount);
+        Ok(())
+    }
+    // Set the free balance of `who` to `amount`, adjusting the total issuance. This can only be
+    // called by the root origin.
+    #[call_index = 7]
+    #[weight = weights::FORCE_SET_BALANCE]
+    pub fn force_set_balance(
+        &mut self,
+        origin: OriginFor<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+        ) -> DispatchResult {
+        ensure_root(origin)?;
+        self.set_balance(who, amount);
+        Ok(())
+    }
+
+    // Create `amount` of new funds in the free balance of `who`, increasing the total issuance.
+    // This can only be called by the root origin. It fails if the balance or the total issuance
+    // would overflow, or if it would create an account below the existential deposit.
+    #[call_index = 8]
+    #[weight = weights::MINT_INTO]
+    pub fn mint_into(
+        &mut self,
+        origin: OriginFor<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+        ) -> DispatchResult {
+        ensure_root(origin)?;
+        let new_balance: T::Balance = self.balance(&who)
             .checked_add(&amount)
-            .ok_or("Overflow when adding to balance")?;
+            .ok_or(ArithmeticError::Overflow)?;
+        self.total_issuance.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+        self.ensure_existential_deposit(&who, new_balance)?;
 
-        self.set_balance(caller, new_caller_balance);
-        self.set_balance(to, new_to_balance);
+        self.set_balance(who.clone(), new_balance);
+        self.deposit_event(Event::Minted { account: who, amount });
+        Ok(())
+    }
 
+    // Destroy `amount` from the free balance of `who`, decreasing the total issuance. This can only
+    // be called by the root origin. Locked funds can't be burnt, and the account is reaped if it
+    // drops below the existential deposit.
+    #[call_index = 9]
+    #[weight = weights::BURN_FROM]
+    pub fn burn_from(
+        &mut self,
+        origin: OriginFor<T>,
+        who: T::AccountId,
+        amount: T::Balance,
+        ) -> DispatchResult {
+        ensure_root(origin)?;
+        let new_balance: T::Balance = self.ensure_can_withdraw(&who, amount, WithdrawReasons::TRANSFER)?;
+
+        self.set_balance(who.clone(), new_balance);
+        self.deposit_event(Event::Burned { account: who.clone(), amount });
+        self.reap_dust(&who);
         Ok(())
     }
 }
 
-
-impl <T: Config> Pallet<T> {
+impl<T: Config<I>, I: Instance> Pallet<T, I> {
     pub fn new() -> Self {
         Self {
-            balances: BTreeMap::new()
+            accounts: StorageMap::new(),
+            locks: StorageMap::new(),
+            total_issuance: StorageValue::new(T::Balance::zero()),
+            escrows: StorageMap::new(),
+            next_escrow_id: StorageValue::new(0),
+            transfers_this_block: StorageMap::new(),
+            existential_deposit: T::EXISTENTIAL_DEPOSIT,
+            events: Events::new(),
+            _instance: PhantomData,
+        }
+    }
+
+    fn ensure_transfer_allowed(&self, who: &T::AccountId) -> DispatchResult {
+        let transfers: u32 = *self.transfers_this_block.get(who).unwrap_or(&0);
+        if transfers >= T::MAX_TRANSFERS_PER_BLOCK {
+            return Err(Error::TransferRateLimitExceeded.into());
+        }
+        Ok(())
+    }
+
+    fn note_transfer(&mut self, who: T::AccountId) {
+        let transfers = self.transfers_this_block.entry(who).or_insert(0);
+        *transfers = transfers.saturating_add(1);
+    }
+
+    // Move `amount` from `from` to `to`, without counting towards the transfers `from` can make in
+    // the current block. This is used by the runtime and other pallets, like for paying claim fees.
+    pub fn do_transfer(
+        &mut self,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+        ) -> DispatchResult {
+        if amount < T::MINIMUM_TRANSFER {
+            return Err(Error::BelowMinimumTransfer.into());
         }
+        self.move_free(from, to, amount, WithdrawReasons::TRANSFER)
     }
 
+    // Pay a transaction fee of `amount` from `from` to `to`. Unlike transfers, this is only
+    // restricted by the locks on fee payments.
+    pub fn pay_fee(
+        &mut self,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+        ) -> DispatchResult {
+        self.move_free(from, to, amount, WithdrawReasons::FEE)
+    }
+
+    // Move `amount` from the free balance of `from` to the free balance of `to`, as long as it is
+    // not locked for `reasons`.
+    fn move_free(
+        &mut self,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+        reasons: WithdrawReasons,
+        ) -> DispatchResult {
+        let new_from_balance: T::Balance = self.ensure_can_withdraw(&from, amount, reasons)?;
+        // Moving funds to the same account leaves its balance as it is. Crediting its balance from
+        // before the withdrawal would create funds out of nothing.
+        if from == to {
+            let balance: T::Balance = self.balance(&from);
+            self.deposit_event(Event::Transferred {
+                from,
+                to,
+                amount,
+                from_balance: balance,
+                to_balance: balance,
+            });
+            return Ok(());
+        }
+        let new_to_balance: T::Balance = self.balance(&to)
+            .checked_add(&amount)
+            .ok_or(ArithmeticError::Overflow)?;
+        self.ensure_existential_deposit(&to, new_to_balance)?;
+
+        self.set_balance(from.clone(), new_from_balance);
+        self.set_balance(to.clone(), new_to_balance);
+        self.deposit_event(Event::Transferred {
+            from: from.clone(),
+            to,
+            amount,
+            from_balance: new_from_balance,
+            to_balance: new_to_balance,
+        });
+        self.reap_dust(&from);
+
+        Ok(())
+    }
+
+    // The part of the free balance of `who` which can be transferred, without going below the
+    // balance locked for transfers, and with `keep_alive` without reaping the account.
+    pub fn reducible_balance(&self, who: &T::AccountId, keep_alive: bool) -> T::Balance {
+        let locked: T::Balance = self.locked_balance(who, WithdrawReasons::TRANSFER);
+        let keep_alive_floor: T::Balance = self.keep_alive_floor(who);
+        let floor: T::Balance = if keep_alive && keep_alive_floor > locked { keep_alive_floor } else { locked };
+        self.balance(who).saturating_sub(floor)
+    }
+
+    // The free balance `who` must keep so their total balance stays at the existential deposit.
+    // Their reserved balance counts towards it.
+    fn keep_alive_floor(&self, who: &T::AccountId) -> T::Balance {
+        self.existential_deposit.saturating_sub(self.reserved_balance(who))
+    }
+
+    // Check that an account which doesn't exist yet would be created with at least the existential
+    // deposit, when its free balance becomes `new_balance`.
+    fn ensure_existential_deposit(&self, who: &T::AccountId, new_balance: T::Balance) -> DispatchResult {
+        if !self.account_exists(who) && new_balance < self.existential_deposit {
+            return Err(Error::ExistentialDeposit.into());
+        }
+        Ok(())
+    }
+
+    // Reap `who` if their total balance dropped below the existential deposit, paying what is left
+    // of their free balance to the dust destination. The dust destination itself is never reaped,
+    // so the dust can't be lost.
+    fn reap_dust(&mut self, who: &T::AccountId) {
+        if self.total_balance(who) >= self.existential_deposit {
+            return;
+        }
+        let destination: Option<T::AccountId> = T::dust_destination();
+        if destination.as_ref() == Some(who) {
+            return;
+        }
+
+        let dust: T::Balance = self.balance(who);
+        self.set_balance(who.clone(), T::Balance::zero());
+        self.set_reserved(who.clone(), T::Balance::zero());
+        if let Some(destination) = destination {
+            let new_balance: T::Balance = self.balance(&destination).saturating_add(dust);
+            self.set_balance(destination, new_balance);
+        }
+        self.remove_account(who);
+
+        if !dust.is_zero() {
+            self.deposit_event(Event::DustLost { account: who.clone(), amount: dust });
+        }
+        self.deposit_event(Event::AccountReaped { account: who.clone() });
+    }
+
+    // Every change of a balance goes through here, so the total issuance is kept up to date. This
+    // is not a call: extrinsics can only set a balance with `force_set_balance`, which requires the
+    // root origin, like the sudo account gets through the sudo pallet.
     pub fn set_balance(&mut self, who: T::AccountId, amount: T::Balance) {
-        self.balances.insert(who.clone(), amount);
+        let old_amount: T::Balance = self.balance(&who);
+        self.total_issuance.set(self.total_issuance.saturating_sub(old_amount).saturating_add(amount));
+        self.accounts.entry(who).or_insert_with(Self::empty_account).free = amount;
     }
 
     pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-        *self.balances.get(who).unwrap_or(&T::Balance::zero())
+        self.account(who).free
+    }
+
+    fn empty_account() -> AccountData<T::Balance> {
+        AccountData { free: T::Balance::zero(), reserved: T::Balance::zero() }
+    }
+
+    // An account exists from the first time its balance is set, until it is reaped.
+    pub fn account_exists(&self, who: &T::AccountId) -> bool {
+        self.accounts.contains_key(who)
+    }
+
+    fn set_reserved(&mut self, who: T::AccountId, amount: T::Balance) {
+        let old_amount: T::Balance = self.reserved_balance(&who);
+        self.total_issuance.set(self.total_issuance.saturating_sub(old_amount).saturating_add(amount));
+        self.accounts.entry(who).or_insert_with(Self::empty_account).reserved = amount;
+    }
+
+    pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.account(who).reserved
+    }
+
+    // The complete holdings of `who`, which is their free balance plus their reserved balance.
+    pub fn total_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.balance(who).saturating_add(self.reserved_balance(who))
+    }
+
+    // Move `amount` from the free balance of `who` to their reserved balance. Funds locked for
+    // transfers can't be reserved.
+    pub fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let new_free: T::Balance = self.ensure_can_withdraw(who, amount, WithdrawReasons::TRANSFER)?;
+        let new_reserved: T::Balance = self.reserved_balance(who)
+            .checked_add(&amount)
+            .ok_or(ArithmeticError::Overflow)?;
+
+        self.set_balance(who.clone(), new_free);
+        self.set_reserved(who.clone(), new_reserved);
+        Ok(())
+    }
+
+    // Move up to `amount` from the reserved balance of `who` back to their free balance. Returns
+    // the amount which was actually unreserved.
+    pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let reserved: T::Balance = self.reserved_balance(who);
+        let new_reserved: T::Balance = reserved.saturating_sub(amount);
+        let unreserved: T::Balance = reserved.saturating_sub(new_reserved);
+
+        self.set_reserved(who.clone(), new_reserved);
+        self.set_balance(who.clone(), self.balance(who).saturating_add(unreserved));
+        unreserved
+    }
+
+    // Move up to `amount` from the reserved balance of `from` to the free balance of `to`. Returns
+    // the amount which was actually moved.
+    pub fn repatriate_reserved(
+        &mut self,
+        from: &T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> T::Balance {
+        let reserved: T::Balance = self.reserved_balance(from);
+        let new_reserved: T::Balance = reserved.saturating_sub(amount);
+        let moved: T::Balance = reserved.saturating_sub(new_reserved);
+
+        self.set_reserved(from.clone(), new_reserved);
+        let new_to_balance: T::Balance = self.balance(&to).saturating_add(moved);
+        self.set_balance(to, new_to_balance);
+        moved
+    }
+
+    // Remove up to `amount` from the reserved balance of `who`, as a penalty. Unlike `slash`, the
+    // free balance is left untouched. Returns the amount which was actually slashed.
+    pub fn slash_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let reserved: T::Balance = self.reserved_balance(who);
+        let new_reserved: T::Balance = reserved.saturating_sub(amount);
+        self.set_reserved(who.clone(), new_reserved);
+        reserved.saturating_sub(new_reserved)
+    }
+
+    // Lock `amount` of the free balance of `who`, so it can't be moved for any of the `reasons`.
+    // This replaces the lock with the same `id`. When several locks overlap, the largest amount is
+    // locked, they don't add up.
+    pub fn set_lock(
+        &mut self,
+        id: LockIdentifier,
+        who: &T::AccountId,
+        amount: T::Balance,
+        reasons: WithdrawReasons,
+    ) {
+        self.locks.entry(who.clone()).or_default().insert(id, BalanceLock { amount, reasons });
+    }
+
+    // Extend the lock with the same `id`, so it locks at least `amount` for at least the `reasons`
+    // it already had, plus the new `reasons`. This sets a new lock if there is none with this `id`.
+    pub fn extend_lock(
+        &mut self,
+        id: LockIdentifier,
+        who: &T::AccountId,
+        amount: T::Balance,
+        reasons: WithdrawReasons,
+    ) {
+        let lock = match self.locks.get(who).and_then(|locks| locks.get(&id)) {
+            Some(lock) => BalanceLock {
+                amount: if lock.amount > amount { lock.amount } else { amount },
+                reasons: lock.reasons | reasons,
+            },
+            None => BalanceLock { amount, reasons },
+        };
+        self.locks.entry(who.clone()).or_default().insert(id, lock);
+    }
+
+    // The locks on the free balance of `who`, keyed by their identifier.
+    pub fn locks(&self, who: &T::AccountId) -> impl Iterator<Item = (&LockIdentifier, &BalanceLock<T::Balance>)> {
+        self.locks.get(who).into_iter().flatten()
+    }
+
+    pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+        if let Some(locks) = self.locks.get_mut(who) {
+            locks.remove(&id);
+            if locks.is_empty() {
+                self.locks.remove(who);
+            }
+        }
+    }
+
+    // The part of the free balance of `who` which can't be moved for any of the `reasons`.
+    pub fn locked_balance(&self, who: &T::AccountId, reasons: WithdrawReasons) -> T::Balance {
+        self.locks
+            .get(who)
+            .into_iter()
+            .flat_map(|locks| locks.values())
+            .filter(|lock| lock.reasons.intersects(reasons))
+            .fold(T::Balance::zero(), |locked, lock| if lock.amount > locked { lock.amount } else { locked })
+    }
+
+    // Check that `amount` can be withdrawn from the free balance of `who` for `reasons`, without
+    // going below the balance locked for those reasons. Returns the new free balance.
+    fn ensure_can_withdraw(
+        &self,
+        who: &T::AccountId,
+        amount: T::Balance,
+        reasons: WithdrawReasons,
+    ) -> Result<T::Balance, DispatchError> {
+        let new_balance: T::Balance = self.balance(who)
+            .checked_sub(&amount)
+            .ok_or(Error::InsufficientBalance)?;
+        if new_balance < self.locked_balance(who, reasons) {
+            return Err(Error::BalanceLocked.into());
+        }
+        Ok(new_balance)
+    }
+
+    // Remove up to `amount` from the balance of `who`, as a penalty. The balance never goes below
+    // zero. Returns the amount which was actually slashed.
+    pub fn slash(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let balance: T::Balance = self.balance(who);
+        let new_balance: T::Balance = balance.saturating_sub(amount);
+        self.set_balance(who.clone(), new_balance);
+        balance.saturating_sub(new_balance)
+    }
+
+    // Reset the total issuance to the sum of the free and reserved balances of all the accounts.
+    // This is meant to be called from a migration, for state which was built before the total
+    // issuance was tracked.
+    pub fn recompute_issuance(&mut self) {
+        self.total_issuance.set(self
+            .accounts
+            .values()
+            .fold(T::Balance::zero(), |total, account| {
+                total.saturating_add(account.free).saturating_add(account.reserved)
+            }));
+    }
+
+    // Iterate over all the accounts with a balance, sorted by account.
+    pub fn iter(&self) -> impl Iterator<Item = (&T::AccountId, &T::Balance)> {
+        self.accounts.iter().map(|(who, account)| (who, &account.free))
+    }
+
+    // Remove every trace of `who` from this pallet, like their locks. This fails if they still have
+    // a free or reserved balance, so no funds are ever lost when an account is reaped.
+    pub fn reap_account(&mut self, who: &T::AccountId) -> DispatchResult {
+        if !self.total_balance(who).is_zero() {
+            return Err(Error::AccountHasBalance.into());
+        }
+
+        self.remove_account(who);
+        Ok(())
+    }
+
+    fn remove_account(&mut self, who: &T::AccountId) {
+        self.accounts.remove(who);
+        self.locks.remove(who);
+        self.transfers_this_block.remove(who);
     }
 }
 
+impl<T: Config<I>, I: Instance> Pallet<T, I>
+where
+    T::AccountId: Encode,
+    T::Balance: Encode,
+    T::BlockNumber: Encode,
+{
+    // The name of the pallet in its storage keys, followed by the prefix of its instance, so the
+    // state of several instances doesn't collide.
+    fn storage_prefix() -> String {
+        alloc::format!("balances{}", I::PREFIX)
+    }
 
+    // Write the state of the pallet into `state`, so the runtime can commit to it. The phase and
+    // the events are not part of the state.
+    pub fn store_state(&self, state: &mut impl StorageBackend) {
+        let pallet = &Self::storage_prefix();
+        for (who, account) in &self.accounts {
+            state.insert(storage_map_key(pallet, "accounts", who), (account.free, account.reserved).encode());
+        }
+        for (who, locks) in &self.locks {
+            for (id, lock) in locks {
+                let key = storage_map_key(pallet, "locks", &(who, id));
+                state.insert(key, (lock.amount, lock.reasons.0).encode());
+            }
+        }
+        state.insert(storage_key(pallet, "total_issuance"), self.total_issuance.encode());
+        for (id, escrow) in &self.escrows {
+            let value = ((&escrow.from, &escrow.to), (escrow.amount, escrow.release_block)).encode();
+            state.insert(storage_map_key(pallet, "escrows", id), value);
+        }
+        state.insert(storage_key(pallet, "next_escrow_id"), self.next_escrow_id.encode());
+        state.insert(storage_key(pallet, "existential_deposit"), self.existential_deposit.encode());
+        for (who, transfers) in &self.transfers_this_block {
+            state.insert(storage_map_key(pallet, "transfers_this_block", who), transfers.encode());
+        }
+    }
+}
 
+impl<T: Config<I>, I: Instance> Hooks<T::BlockNumber> for Pallet<T, I> {
+    // Reset the number of transfers made by each account, at the start of every block.
+    fn on_initialize(&mut self, _block_number: T::BlockNumber) {
+        self.transfers_this_block.clear();
+    }
 
+    // Release the escrows which are due at the end of `block_number`, paying their reserved funds
+    // to their recipient.
+    fn on_finalize(&mut self, block_number: T::BlockNumber) {
+        let due: Vec<u32> = self.escrows
+            .iter()
+            .filter(|(_, escrow)| escrow.release_block <= block_number)
+            .map(|(escrow_id, _)| *escrow_id)
+            .collect();
 
-#[cfg(test)]
-mod tests {
-    use crate::system;
+        for escrow_id in due {
+            if let Some(escrow) = self.escrows.remove(&escrow_id) {
+                self.repatriate_reserved(&escrow.from, escrow.to, escrow.amount);
+            }
+        }
+    }
+}
+
+// The balances pallet has nothing to migrate yet, but checks that no upgrade of the runtime creates
+// or destroys funds: the total issuance must be left unchanged, and still be the sum of the free
+// and reserved balances of the accounts. A change of how balances are kept must preserve both.
+impl<T: Config<I>, I: Instance> OnRuntimeUpgrade for Pallet<T, I>
+where
+    T::Balance: Encode + Decode,
+{
+    fn pre_upgrade(&self) -> Result<Vec<u8>, DispatchError> {
+        Ok(self.total_issuance.encode())
+    }
+
+    fn post_upgrade(&self, state: Vec<u8>) -> Result<(), DispatchError> {
+        let total_issuance = T::Balance::decode_all(&state).map_err(|_| "invalid pre-upgrade state")?;
+        if *self.total_issuance != total_issuance {
+            return Err("the upgrade changed the total issuance".into());
+        }
+        let total_balance: T::Balance = self
+            .accounts
+            .values()
+            .fold(T::Balance::zero(), |total, account| total.saturating_add(account.free).saturating_add(account.reserved));
+        if total_balance != total_issuance {
+            return Err("the total issuance is not the sum of the balances".into());
+        }
+        Ok(())
+    }
+}
+
+// The balances pallet is the currency of the runtime, so pallets only taking deposits or fees
+// don't need to depend on it directly.
+impl<T: Config<I>, I: Instance> Currency<T::AccountId> for Pallet<T, I> {
+    type Balance = T::Balance;
 
+    fn free_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.balance(who)
+    }
+
+    fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+        Pallet::reserved_balance(self, who)
+    }
+
+    fn transfer(&mut self, from: &T::AccountId, to: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        self.do_transfer(from.clone(), to.clone(), amount)
+    }
+
+    fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        Pallet::reserve(self, who, amount)
+    }
+
+    fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        Pallet::unreserve(self, who, amount)
+    }
+}
 
-    struct  TestConfig;
-    impl system::Config for TestConfig {
-        type AccountId = String;    
-        type BlockNumber = u32;    
-        type Nonce = u32;    
-    }    
-    impl super::Config for TestConfig {
-        type Balance = u128;    
+impl<T: Config<I>, I: Instance> Default for Pallet<T, I> {
+    fn default() -> Self {
+        Self::new()
     }
+}
+
+// The initial state of the balances pallet, see `RuntimeGenesisConfig` of `#[macros::runtime]`.
+pub struct GenesisConfig<T: Config<I>, I: Instance = ()> {
+    // The free balance of each account at genesis, which makes up the initial total issuance.
+    pub balances: Vec<(T::AccountId, T::Balance)>,
+    // The existential deposit of this chain, in place of `Config::EXISTENTIAL_DEPOSIT`.
+    pub existential_deposit: Option<T::Balance>,
+    _instance: PhantomData<I>,
+}
+
+impl<T: Config<I>, I: Instance> GenesisConfig<T, I> {
+    // Set the existential deposit, then the balance of each account. This fails if a balance is
+    // below the existential deposit, since the account could not exist.
+    pub fn build(&self, balances: &mut Pallet<T, I>) -> DispatchResult {
+        if let Some(existential_deposit) = self.existential_deposit {
+            balances.existential_deposit = existential_deposit;
+        }
+        for (who, amount) in &self.balances {
+            if *amount < balances.existential_deposit {
+                return Err(Error::ExistentialDeposit.into());
+            }
+            balances.set_balance(who.clone(), *amount);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config<I>, I: Instance> Default for GenesisConfig<T, I> {
+    fn default() -> Self {
+        Self { balances: Vec::new(), existential_deposit: None, _instance: PhantomData }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::support::{ActualWeight, ArithmeticError, DispatchError, EventRecord, Hooks, Phase};
+    use crate::support::RawOrigin::{self, Root, Signed};
+    use super::Error;
+
+    crate::construct_mock_runtime!(struct TestConfig<AccountId = String>: balances);
+    crate::construct_mock_runtime!(struct MinimumTransferConfig<AccountId = String>: balances { MINIMUM_TRANSFER = 10 });
+    crate::construct_mock_runtime!(struct RateLimitConfig<AccountId = String>: balances { MAX_TRANSFERS_PER_BLOCK = 2 });
+    crate::construct_mock_runtime!(struct BurnDustConfig<AccountId = String>: balances { EXISTENTIAL_DEPOSIT = 10 });
+    crate::construct_mock_runtime!(struct ExistentialDepositConfig<AccountId = String>: balances {
+        EXISTENTIAL_DEPOSIT = 10;
+        fn dust_destination() -> Option<Self::AccountId> {
+            Some("treasury".to_string())
+        }
+    });
 
     #[test]
     fn init_balances() {
@@ -84,11 +927,10 @@ mod tests {
     }
 
     #[test]
-    fn transfer_balance(){
+    fn transfer_balance() {
         let alice: String = "alice".to_string();
         let bob: String = "bob".to_string();
 
-
         let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
 
         balances.set_balance("alice".to_string(), 100);
@@ -99,36 +941,737 @@ mod tests {
     }
 
     #[test]
-    pub fn transfer_insufficient() {
+    fn transfer_event_includes_resulting_balances() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        balances.set_balance(bob.clone(), 5);
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 30), Ok(()));
+
+        assert_eq!(
+            balances.events(),
+            [EventRecord {
+                phase: Phase::Initialization,
+                event: super::Event::Transferred {
+                    from: alice.clone(),
+                    to: bob.clone(),
+                    amount: 30,
+                    from_balance: balances.balance(&alice),
+                    to_balance: balances.balance(&bob),
+                },
+            }]
+        );
+        assert_eq!(balances.balance(&alice), 70);
+        assert_eq!(balances.balance(&bob), 35);
+
+        // Failed transfers do not emit events.
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 100), Err(Error::InsufficientBalance.into()));
+        assert_eq!(balances.events().len(), 1);
+    }
+
+    #[test]
+    fn transfer_insufficient() {
         let alice: String = "alice".to_string();
         let bob: String = "bob".to_string();
 
         let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
         balances.set_balance("alice".to_string(), 100);
-        let result = balances.transfer(alice.clone(), bob.clone(), 50);
+        let result = balances.transfer(alice.clone(), bob.clone(), 150);
 
-        assert_eq!(result, Err("Insufficient balance"));
+        assert_eq!(result, Err(Error::InsufficientBalance.into()));
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.balance(&bob), 0);
+    }
+
+    #[test]
+    fn transfer_overflow() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance("alice".to_string(), 1);
+        balances.set_balance("bob".to_string(), u128::MAX);
+
+        let result = balances.transfer(alice.clone(), bob.clone(), 1);
+
+        assert_eq!(result, Err(ArithmeticError::Overflow.into()));
+        assert_eq!(balances.balance(&alice), 1);
+        assert_eq!(balances.balance(&bob), u128::MAX);
+    }
+
+    #[test]
+    fn transfer_to_self_keeps_the_balance() {
+        let alice: String = "alice".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        assert_eq!(balances.transfer(alice.clone(), alice.clone(), 30), Ok(()));
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.total_issuance(), 100);
+
+        // The caller must still be able to afford the transfer.
+        assert_eq!(balances.transfer(alice.clone(), alice.clone(), 200), Err(Error::InsufficientBalance.into()));
+    }
+
+    #[test]
+    fn transfers_to_self_keep_the_balance_and_the_issuance() {
+        use super::WithdrawReasons;
+
+        let alice: String = "alice".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        balances.set_lock(*b"staking_", &alice, 20, WithdrawReasons::TRANSFER);
+
+        // Whether the transfers succeed or not, none of them changes what alice holds.
+        for amount in [0, 1, 50, 80, 81, 100, 101, u128::MAX] {
+            let _ = balances.transfer(alice.clone(), alice.clone(), amount);
+            let _ = balances.transfer_some(alice.clone(), alice.clone(), amount);
+            let _ = balances.transfer_keep_alive(alice.clone(), alice.clone(), amount);
+            let _ = balances.transfer_multi(alice.clone(), vec![(alice.clone(), amount), (alice.clone(), amount)]);
+            assert_eq!(balances.balance(&alice), 100, "amount {amount}");
+            assert_eq!(balances.total_issuance(), 100, "amount {amount}");
+        }
+        for keep_alive in [false, true] {
+            assert_eq!(balances.transfer_all(alice.clone(), alice.clone(), keep_alive), Ok(80));
+            assert_eq!(balances.balance(&alice), 100);
+            assert_eq!(balances.total_issuance(), 100);
+        }
+    }
+
+    #[test]
+    fn transfer_multi_is_all_or_nothing() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+        let charlie: String = "charlie".to_string();
+
+        let mut balances: super::Pallet<RateLimitConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        // The second transfer can't be afforded, so the first one is rolled back.
+        let targets = vec![(bob.clone(), 30), (charlie.clone(), 80)];
+        assert_eq!(balances.transfer_multi(alice.clone(), targets), Err(Error::InsufficientBalance.into()));
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.balance(&bob), 0);
+        assert!(!balances.account_exists(&bob));
+        assert!(balances.events().is_empty());
+
+        let targets = vec![(bob.clone(), 30), (charlie.clone(), 50)];
+        assert_eq!(balances.transfer_multi(alice.clone(), targets), Ok(ActualWeight(2 * super::weights::TRANSFER_MULTI_PER_TARGET)));
+        assert_eq!(balances.balance(&alice), 20);
+        assert_eq!(balances.balance(&bob), 30);
+        assert_eq!(balances.balance(&charlie), 50);
+        assert_eq!(balances.events().len(), 2);
+
+        // Each transfer counts towards the rate limit, which was reached by the two transfers.
+        balances.on_initialize(2);
+        let targets = vec![(bob.clone(), 5), (bob.clone(), 5), (charlie.clone(), 5)];
+        // The weight of the call grows with the number of transfers.
+        let call = super::Call::<RateLimitConfig>::transfer_multi { targets: targets.clone() };
+        assert_eq!(call.weight(), 3 * super::weights::TRANSFER_MULTI_PER_TARGET);
+        assert_eq!(balances.transfer_multi(alice.clone(), targets), Err(Error::TransferRateLimitExceeded.into()));
+        assert_eq!(balances.balance(&alice), 20);
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 5), Ok(()));
+    }
+
+    #[test]
+    fn transfer_below_minimum() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<MinimumTransferConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 5), Err(Error::BelowMinimumTransfer.into()));
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.balance(&bob), 0);
+
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 10), Ok(()));
+        assert_eq!(balances.balance(&alice), 90);
+        assert_eq!(balances.balance(&bob), 10);
+    }
+
+    #[test]
+    fn slash_saturates_at_zero() {
+        let alice: String = "alice".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.slash(&alice, 30), 30);
+        assert_eq!(balances.balance(&alice), 70);
+        assert_eq!(balances.slash(&alice, 100), 70);
         assert_eq!(balances.balance(&alice), 0);
+    }
+
+    #[test]
+    fn transfers_are_rate_limited_per_block() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<RateLimitConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 10), Ok(()));
+        assert_eq!(balances.transfer_some(alice.clone(), bob.clone(), 10), Ok(10));
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 10), Err(Error::TransferRateLimitExceeded.into()));
+        assert_eq!(balances.balance(&alice), 80);
+        // Other accounts have their own limit, and the runtime can still move funds, like fees.
+        assert_eq!(balances.transfer(bob.clone(), alice.clone(), 5), Ok(()));
+        assert_eq!(balances.do_transfer(alice.clone(), bob.clone(), 10), Ok(()));
+
+        balances.on_initialize(2);
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 10), Ok(()));
+        assert_eq!(balances.balance(&alice), 65);
+    }
+
+    #[test]
+    fn transfer_locks_do_not_restrict_fees() {
+        use super::WithdrawReasons;
+
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        balances.set_lock(*b"vesting ", &alice, 50, WithdrawReasons::TRANSFER);
+
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 60), Err(Error::BalanceLocked.into()));
+        assert_eq!(balances.reserve(&alice, 60), Err(Error::BalanceLocked.into()));
+        assert_eq!(balances.balance(&alice), 100);
+
+        assert_eq!(balances.pay_fee(alice.clone(), bob.clone(), 60), Ok(()));
+        assert_eq!(balances.balance(&alice), 40);
+        assert_eq!(balances.balance(&bob), 60);
+    }
+
+    #[test]
+    fn overlapping_locks_take_the_maximum() {
+        use super::WithdrawReasons;
+
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        balances.set_lock(*b"vesting ", &alice, 50, WithdrawReasons::TRANSFER);
+        balances.set_lock(*b"staking ", &alice, 30, WithdrawReasons::TRANSFER | WithdrawReasons::FEE);
+        assert_eq!(balances.locked_balance(&alice, WithdrawReasons::TRANSFER), 50);
+        assert_eq!(balances.locked_balance(&alice, WithdrawReasons::FEE), 30);
+
+        assert_eq!(balances.pay_fee(alice.clone(), bob.clone(), 80), Err(Error::BalanceLocked.into()));
+        // Only what is not locked for transfers is moved.
+        assert_eq!(balances.transfer_some(alice.clone(), bob.clone(), 80), Ok(50));
+
+        // Setting a lock again replaces it, and removed locks don't restrict anything.
+        balances.set_lock(*b"vesting ", &alice, 10, WithdrawReasons::TRANSFER);
+        assert_eq!(balances.locked_balance(&alice, WithdrawReasons::TRANSFER), 30);
+        balances.remove_lock(*b"staking ", &alice);
+        assert_eq!(balances.locked_balance(&alice, WithdrawReasons::FEE), 0);
+        assert_eq!(balances.pay_fee(alice.clone(), bob.clone(), 50), Ok(()));
+    }
+
+    #[test]
+    fn extend_lock_never_shrinks_a_lock() {
+        use super::{BalanceLock, WithdrawReasons};
+
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        balances.extend_lock(*b"staking ", &alice, 50, WithdrawReasons::TRANSFER);
+        balances.extend_lock(*b"staking ", &alice, 30, WithdrawReasons::FEE);
+
+        let locks = balances.locks(&alice).collect::<Vec<_>>();
+        let expected = BalanceLock { amount: 50, reasons: WithdrawReasons::TRANSFER | WithdrawReasons::FEE };
+        assert_eq!(locks, [(b"staking ", &expected)]);
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 60), Err(Error::BalanceLocked.into()));
+        assert_eq!(balances.pay_fee(alice.clone(), bob.clone(), 60), Err(Error::BalanceLocked.into()));
+
+        balances.extend_lock(*b"staking ", &alice, 80, WithdrawReasons::TRANSFER);
+        assert_eq!(balances.locked_balance(&alice, WithdrawReasons::FEE), 80);
+        balances.remove_lock(*b"staking ", &alice);
+        assert_eq!(balances.locks(&alice).count(), 0);
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 60), Ok(()));
+    }
+
+    #[test]
+    fn total_balance_includes_reserved_balance() {
+        let alice: String = "alice".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        assert_eq!(balances.reserve(&alice, 30), Ok(()));
+
+        assert_eq!(balances.balance(&alice), 70);
+        assert_eq!(balances.reserved_balance(&alice), 30);
+        assert_eq!(balances.total_balance(&alice), 100);
+
+        // The sum saturates instead of overflowing.
+        balances.set_balance(alice.clone(), u128::MAX);
+        assert_eq!(balances.total_balance(&alice), u128::MAX);
+    }
+
+    #[test]
+    fn reserved_balance_can_be_unreserved_slashed_or_repatriated() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        assert_eq!(balances.reserve(&alice, 150), Err(Error::InsufficientBalance.into()));
+        assert_eq!(balances.reserve(&alice, 60), Ok(()));
+        assert_eq!(balances.account(&alice), super::AccountData { free: 40, reserved: 60 });
+
+        assert_eq!(balances.unreserve(&alice, 10), 10);
+        assert_eq!(balances.slash_reserved(&alice, 20), 20);
+        assert_eq!(balances.account(&alice), super::AccountData { free: 50, reserved: 30 });
+        assert_eq!(balances.total_issuance(), 80);
+
+        // Each operation moves at most what is reserved.
+        assert_eq!(balances.repatriate_reserved(&alice, bob.clone(), 25), 25);
+        assert_eq!(balances.slash_reserved(&alice, 10), 5);
+        assert_eq!(balances.unreserve(&alice, 10), 0);
+        assert_eq!(balances.account(&alice), super::AccountData { free: 50, reserved: 0 });
+        assert_eq!(balances.account(&bob), super::AccountData { free: 25, reserved: 0 });
+        assert_eq!(balances.total_issuance(), 75);
+    }
+
+    #[test]
+    fn escrow_is_released_at_its_block() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.create_escrow(alice.clone(), bob.clone(), 150, 3), Err(Error::InsufficientBalance.into()));
+        assert_eq!(balances.create_escrow(alice.clone(), bob.clone(), 40, 3), Ok(0));
+        assert_eq!(balances.balance(&alice), 60);
+        assert_eq!(balances.reserved_balance(&alice), 40);
+        assert_eq!(balances.total_issuance(), 100);
+        // Reserved funds can't be transferred.
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 70), Err(Error::InsufficientBalance.into()));
+
+        balances.on_finalize(2);
         assert_eq!(balances.balance(&bob), 0);
+        assert!(balances.escrow(&0).is_some());
+
+        balances.on_finalize(3);
+        assert_eq!(balances.balance(&bob), 40);
+        assert_eq!(balances.reserved_balance(&alice), 0);
+        assert_eq!(balances.balance(&alice), 60);
+        assert_eq!(balances.escrow(&0), None);
+        assert_eq!(balances.total_issuance(), 100);
+    }
 
+    #[test]
+    fn escrow_can_be_cancelled_before_release() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
 
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        assert_eq!(balances.create_escrow(alice.clone(), bob.clone(), 40, 3), Ok(0));
+
+        assert_eq!(balances.cancel_escrow(bob.clone(), 0), Err(Error::NotEscrowCreator.into()));
+        assert_eq!(balances.cancel_escrow(alice.clone(), 0), Ok(()));
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.reserved_balance(&alice), 0);
+
+        balances.on_finalize(3);
+        assert_eq!(balances.balance(&bob), 0);
+        assert_eq!(balances.balance(&alice), 100);
     }
 
     #[test]
-    pub fn transfer_overfload() {
+    fn escrow_cannot_be_cancelled_after_release() {
         let alice: String = "alice".to_string();
         let bob: String = "bob".to_string();
 
         let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
-        balances.set_balance("alice".to_string(), u128::MAX);
-        
-        let result = balances
-        .transfer(alice.clone(), bob.clone(), 1);
+        balances.set_balance(alice.clone(), 100);
+        assert_eq!(balances.create_escrow(alice.clone(), bob.clone(), 40, 3), Ok(0));
+        assert_eq!(balances.create_escrow(alice.clone(), bob.clone(), 10, 5), Ok(1));
 
-        assert_eq!(result, Err("Overflow"));
-        assert_eq!(balances.balance(&alice), u128::MAX);
-        assert_eq!(balances.balance(&bob), u128::MAX);
+        balances.on_finalize(4);
+        assert_eq!(balances.cancel_escrow(alice.clone(), 0), Err(Error::EscrowDoesNotExist.into()));
+        assert_eq!(balances.balance(&bob), 40);
+
+        // The escrow which is not due yet can still be cancelled.
+        assert_eq!(balances.cancel_escrow(alice.clone(), 1), Ok(()));
+        assert_eq!(balances.balance(&alice), 60);
+    }
+
+    #[test]
+    fn recompute_issuance_sums_all_balances() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        balances.slash(&alice, 30);
+        assert_eq!(balances.total_issuance(), 70);
+
+        // Balances written without going through `set_balance`, like in an old genesis.
+        let account = |free, reserved| super::AccountData { free, reserved };
+        balances.accounts.insert(bob.clone(), account(50, 0));
+        balances.accounts.insert("charlie".to_string(), account(u128::MAX, 0));
+        assert_eq!(balances.total_issuance(), 70);
+
+        balances.recompute_issuance();
+        assert_eq!(balances.total_issuance(), u128::MAX);
+
+        balances.accounts.remove("charlie");
+        balances.recompute_issuance();
+        assert_eq!(balances.total_issuance(), 120);
+
+        // Reserved balances are part of the issuance.
+        balances.accounts.insert(alice.clone(), account(70, 5));
+        balances.recompute_issuance();
+        assert_eq!(balances.total_issuance(), 125);
+    }
+
+    #[test]
+    fn mint_and_burn_change_the_total_issuance() {
+        use super::WithdrawReasons;
+
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        // Only the root origin can create or destroy funds.
+        assert_eq!(balances.mint_into(Signed(alice.clone()), alice.clone(), 100), Err(DispatchError::BadOrigin));
+        assert_eq!(balances.burn_from(RawOrigin::None, alice.clone(), 0), Err(DispatchError::BadOrigin));
+        assert_eq!(balances.mint_into(Root, alice.clone(), 100), Ok(()));
+        assert_eq!(balances.mint_into(Root, bob.clone(), 50), Ok(()));
+        assert_eq!(balances.total_issuance(), 150);
+        assert_eq!(balances.mint_into(Root, bob.clone(), u128::MAX), Err(ArithmeticError::Overflow.into()));
+
+        balances.set_lock(*b"vesting ", &alice, 80, WithdrawReasons::TRANSFER);
+        assert_eq!(balances.burn_from(Root, alice.clone(), 30), Err(Error::BalanceLocked.into()));
+        assert_eq!(balances.burn_from(Root, bob.clone(), 60), Err(Error::InsufficientBalance.into()));
+        assert_eq!(balances.burn_from(Root, alice.clone(), 20), Ok(()));
+        assert_eq!(balances.balance(&alice), 80);
+
+        // Setting a balance directly is also reflected in the total issuance.
+        assert_eq!(balances.force_set_balance(Signed(bob.clone()), bob.clone(), 10), Err(DispatchError::BadOrigin));
+        assert_eq!(balances.force_set_balance(Root, bob.clone(), 10), Ok(()));
+        assert_eq!(balances.total_issuance(), 90);
+        let held = balances.iter().map(|(_, free)| free).sum::<u128>();
+        assert_eq!(held, balances.total_issuance());
+
+        let events = balances.events().iter().map(|record| record.event.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                super::Event::Minted { account: alice.clone(), amount: 100 },
+                super::Event::Minted { account: bob.clone(), amount: 50 },
+                super::Event::Burned { account: alice.clone(), amount: 20 },
+            ]
+        );
+    }
+
+    #[test]
+    fn burn_from_reaps_account_below_existential_deposit() {
+        let alice: String = "alice".to_string();
+
+        let mut balances: super::Pallet<BurnDustConfig> = super::Pallet::new();
+        assert_eq!(balances.mint_into(Root, alice.clone(), 5), Err(Error::ExistentialDeposit.into()));
+        assert_eq!(balances.mint_into(Root, alice.clone(), 100), Ok(()));
+
+        assert_eq!(balances.burn_from(Root, alice.clone(), 95), Ok(()));
+        assert!(!balances.account_exists(&alice));
+        assert_eq!(balances.total_issuance(), 0);
+    }
+
+    #[test]
+    fn transfer_some_moves_what_is_available() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.transfer_some(alice.clone(), bob.clone(), 60), Ok(60));
+        assert_eq!(balances.transfer_some(alice.clone(), bob.clone(), 60), Ok(40));
+        assert_eq!(balances.balance(&alice), 0);
+        assert_eq!(balances.balance(&bob), 100);
+    }
+
+    #[test]
+    fn transfer_some_to_self_keeps_the_balance() {
+        let alice: String = "alice".to_string();
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.transfer_some(alice.clone(), alice.clone(), 60), Ok(60));
+        assert_eq!(balances.transfer_some(alice.clone(), alice.clone(), 200), Ok(100));
+        assert_eq!(balances.balance(&alice), 100);
+        assert_eq!(balances.total_issuance(), 100);
+    }
+
+    #[test]
+    fn transfer_reaps_account_below_existential_deposit() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+        let treasury: String = "treasury".to_string();
+
+        let mut balances: super::Pallet<ExistentialDepositConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        assert!(balances.account_exists(&alice));
+        assert!(!balances.account_exists(&bob));
+
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 95), Ok(()));
+        assert!(!balances.account_exists(&alice));
+        assert_eq!(balances.balance(&alice), 0);
+        assert_eq!(balances.balance(&bob), 95);
+        // The dust is paid to the dust destination, so nothing is lost from the issuance.
+        assert_eq!(balances.balance(&treasury), 5);
+        assert_eq!(balances.total_issuance(), 100);
+
+        let events = balances.events().iter().map(|record| record.event.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            events[1..],
+            [
+                super::Event::DustLost { account: alice.clone(), amount: 5 },
+                super::Event::AccountReaped { account: alice.clone() },
+            ]
+        );
+    }
+
+    #[test]
+    fn transfer_cannot_create_account_below_existential_deposit() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<ExistentialDepositConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 5), Err(Error::ExistentialDeposit.into()));
+        assert_eq!(balances.transfer_some(alice.clone(), bob.clone(), 5), Err(Error::ExistentialDeposit.into()));
+        assert_eq!(balances.balance(&alice), 100);
+
+        // Once the account exists, it can receive any amount.
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 10), Ok(()));
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 5), Ok(()));
+        assert_eq!(balances.balance(&bob), 15);
+    }
+
+    #[test]
+    fn transfer_keep_alive_stops_at_the_existential_deposit() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<ExistentialDepositConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.transfer_keep_alive(alice.clone(), bob.clone(), 91), Err(Error::KeepAlive.into()));
+        assert_eq!(balances.transfer_keep_alive(alice.clone(), bob.clone(), 100), Err(Error::KeepAlive.into()));
+        assert_eq!(balances.transfer_keep_alive(alice.clone(), bob.clone(), 101), Err(Error::InsufficientBalance.into()));
+        assert_eq!(balances.balance(&alice), 100);
+
+        assert_eq!(balances.transfer_keep_alive(alice.clone(), bob.clone(), 90), Ok(()));
+        assert!(balances.account_exists(&alice));
+        assert_eq!(balances.balance(&alice), 10);
+        assert_eq!(balances.balance(&bob), 90);
+    }
+
+    #[test]
+    fn transfer_all_with_and_without_keep_alive() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<ExistentialDepositConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.transfer_all(alice.clone(), bob.clone(), true), Ok(90));
+        assert!(balances.account_exists(&alice));
+        assert_eq!(balances.balance(&alice), 10);
+        // Nothing is left to transfer without reaping the account.
+        assert_eq!(balances.transfer_all(alice.clone(), bob.clone(), true), Ok(0));
+        assert_eq!(balances.balance(&alice), 10);
+
+        assert_eq!(balances.transfer_all(alice.clone(), bob.clone(), false), Ok(10));
+        assert!(!balances.account_exists(&alice));
+        assert_eq!(balances.balance(&bob), 100);
+        assert_eq!(balances.balance(&"treasury".to_string()), 0);
+    }
+
+    #[test]
+    fn transfer_all_keeps_locked_and_reserved_funds() {
+        use super::WithdrawReasons;
+
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<ExistentialDepositConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        balances.set_lock(*b"vesting ", &alice, 30, WithdrawReasons::TRANSFER);
+        assert_eq!(balances.reducible_balance(&alice, true), 70);
+        assert_eq!(balances.transfer_all(alice.clone(), bob.clone(), false), Ok(70));
+        assert_eq!(balances.balance(&alice), 30);
+
+        // The reserved balance keeps the account alive, so all the free balance can be moved.
+        balances.remove_lock(*b"vesting ", &alice);
+        assert_eq!(balances.reserve(&alice, 10), Ok(()));
+        assert_eq!(balances.reducible_balance(&alice, true), 20);
+        assert_eq!(balances.transfer_keep_alive(alice.clone(), bob.clone(), 20), Ok(()));
+        assert_eq!(balances.account(&alice), super::AccountData { free: 0, reserved: 10 });
+        assert!(balances.account_exists(&alice));
+    }
+
+    #[test]
+    fn dust_is_burnt_without_a_destination() {
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+
+        let mut balances: super::Pallet<BurnDustConfig> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+
+        assert_eq!(balances.transfer_some(alice.clone(), bob.clone(), 93), Ok(93));
+        assert!(!balances.account_exists(&alice));
+        assert_eq!(balances.total_issuance(), 93);
+        assert_eq!(balances.iter().map(|(who, _)| who.clone()).collect::<Vec<_>>(), [bob]);
+    }
+
+    #[test]
+    fn upgrades_must_preserve_the_total_issuance() {
+        use crate::migrations::{migrate, OnRuntimeUpgrade};
+
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        balances.set_balance("alice".to_string(), 100);
+        assert_eq!(migrate(&mut balances), Ok(0));
+
+        // A migration which loses track of a balance is caught by the checks.
+        let state = balances.pre_upgrade().unwrap();
+        balances.accounts.clear();
+        assert_eq!(balances.post_upgrade(state.clone()), Err("the total issuance is not the sum of the balances".into()));
+        balances.total_issuance.set(0);
+        assert_eq!(balances.post_upgrade(state), Err("the upgrade changed the total issuance".into()));
+    }
+
+    mod custom_balance {
+        use core::ops::{Add, Sub};
+        use num::traits::{CheckedAdd, CheckedSub, Saturating, Zero};
+
+        use crate::system;
+
+        #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+        struct MyBalance(u128);
+
+        impl Add for MyBalance {
+            type Output = Self;
+            fn add(self, other: Self) -> Self {
+                MyBalance(self.0 + other.0)
+            }
+        }
+
+        impl Sub for MyBalance {
+            type Output = Self;
+            fn sub(self, other: Self) -> Self {
+                MyBalance(self.0 - other.0)
+            }
+        }
+
+        impl Zero for MyBalance {
+            fn zero() -> Self {
+                MyBalance(0)
+            }
+            fn is_zero(&self) -> bool {
+                self.0 == 0
+            }
+        }
+
+        impl CheckedAdd for MyBalance {
+            fn checked_add(&self, other: &Self) -> Option<Self> {
+                self.0.checked_add(other.0).map(MyBalance)
+            }
+        }
+
+        impl CheckedSub for MyBalance {
+            fn checked_sub(&self, other: &Self) -> Option<Self> {
+                self.0.checked_sub(other.0).map(MyBalance)
+            }
+        }
+
+        impl Saturating for MyBalance {
+            fn saturating_add(self, other: Self) -> Self {
+                MyBalance(self.0.saturating_add(other.0))
+            }
+            fn saturating_sub(self, other: Self) -> Self {
+                MyBalance(self.0.saturating_sub(other.0))
+            }
+        }
+
+        struct CustomBalanceConfig;
+        impl system::Config for CustomBalanceConfig {
+            type AccountId = String;
+            type BlockNumber = u32;
+            type Nonce = u32;
+            type RuntimeEvent = ();
+            type Hashing = crate::hashing::Sha256;
+        }
+        impl super::super::Config for CustomBalanceConfig {
+            type Balance = MyBalance;
+            const MINIMUM_TRANSFER: Self::Balance = MyBalance(1);
+            const MAX_TRANSFERS_PER_BLOCK: u32 = u32::MAX;
+            const EXISTENTIAL_DEPOSIT: Self::Balance = MyBalance(0);
+        }
+
+        #[test]
+        fn transfer_custom_balance() {
+            let alice: String = "alice".to_string();
+            let bob: String = "bob".to_string();
+
+            let mut balances: super::super::Pallet<CustomBalanceConfig> = super::super::Pallet::new();
+            balances.set_balance(alice.clone(), MyBalance(100));
+
+            assert_eq!(balances.transfer(alice.clone(), bob.clone(), MyBalance(30)), Ok(()));
+            assert_eq!(balances.transfer(alice.clone(), bob.clone(), MyBalance(100)), Err(super::Error::InsufficientBalance.into()));
+            assert_eq!(balances.transfer_some(alice.clone(), bob.clone(), MyBalance(100)), Ok(MyBalance(70)));
+            assert_eq!(balances.slash(&bob, MyBalance(10)), MyBalance(10));
+
+            assert_eq!(balances.balance(&alice), MyBalance(0));
+            assert_eq!(balances.balance(&bob), MyBalance(90));
+        }
+    }
+
+    // The second instance of the balances pallet for `TestConfig`, like a second token.
+    impl super::Config<crate::support::Instance1> for TestConfig {
+        type Balance = u64;
+        const MINIMUM_TRANSFER: Self::Balance = 5;
+        const MAX_TRANSFERS_PER_BLOCK: u32 = u32::MAX;
+        const EXISTENTIAL_DEPOSIT: Self::Balance = 0;
+    }
+
+    #[test]
+    fn instances_keep_their_own_config_and_state() {
+        use crate::codec::Encode;
+        use crate::storage::{storage_key, InMemoryBackend, StorageBackend};
+        use crate::support::Instance1;
+
+        let alice: String = "alice".to_string();
+        let bob: String = "bob".to_string();
+        let mut balances: super::Pallet<TestConfig> = super::Pallet::new();
+        let mut tokens: super::Pallet<TestConfig, Instance1> = super::Pallet::new();
+        balances.set_balance(alice.clone(), 100);
+        tokens.set_balance(alice.clone(), 50);
 
+        assert_eq!(balances.transfer(alice.clone(), bob.clone(), 1), Ok(()));
+        assert_eq!(tokens.transfer(alice.clone(), bob.clone(), 1), Err(Error::BelowMinimumTransfer.into()));
+        assert_eq!(tokens.transfer(alice.clone(), bob.clone(), 20), Ok(()));
+        assert_eq!((balances.balance(&bob), tokens.balance(&bob)), (1, 20));
 
+        // The state of the instance `()` keeps its keys, and the other instances have their own.
+        let mut state = InMemoryBackend::new();
+        balances.store_state(&mut state);
+        tokens.store_state(&mut state);
+        assert_eq!(state.get(&storage_key("balances", "total_issuance")), Some(100u128.encode()));
+        assert_eq!(state.get(&storage_key("balancesInstance1", "total_issuance")), Some(50u64.encode()));
     }
 }
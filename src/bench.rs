@@ -0,0 +1,557 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::benchmarking::{self, Rng};
+use crate::block_builder::BlockBuilder;
+use crate::keyring::{dev_pair, Keystore};
+use crate::support::{Dispatch, RawOrigin, Weight, WEIGHT_PER_NANOS};
+use crate::{balances, proof_of_existence, treasury, types, Runtime, RuntimeCall, RuntimeGenesisConfig, RuntimeOrigin};
+
+/// The number of samples the execution of a block is timed over.
+const SAMPLES: usize = 10;
+
+/// The number of samples each call is timed over by the weight benchmarks. Calls are much faster
+/// than blocks, so they take more samples to be measured reliably.
+const CALL_SAMPLES: usize = 200;
+
+/// The balance of each of the accounts the state is populated with, which is enough for every
+/// signer to pay the fees, deposits and transfers of a block.
+const FUNDS: types::Balance = 1_000_000_000;
+
+/// The kind of extrinsics the blocks of a benchmark are made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+	/// Transfers of random amounts to random accounts of the state.
+	Transfers,
+	/// Claims on random contents.
+	Claims,
+}
+
+impl FromStr for BlockKind {
+	type Err = String;
+
+	fn from_str(kind: &str) -> Result<Self, Self::Err> {
+		match kind {
+			"transfers" => Ok(BlockKind::Transfers),
+			"claims" => Ok(BlockKind::Claims),
+			_ => Err(format!("invalid kind of block {kind}, expected transfers or claims")),
+		}
+	}
+}
+
+impl fmt::Display for BlockKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			BlockKind::Transfers => "transfers",
+			BlockKind::Claims => "claims",
+		})
+	}
+}
+
+/// The state and the block of a benchmark: a runtime with many funded accounts, and the next block
+/// on top of it.
+pub struct Benchmark {
+	pub runtime: Runtime,
+	pub block: types::Block,
+}
+
+impl Benchmark {
+	/// Build `genesis` and populate it with `accounts` funded accounts, then build a block of
+	/// `extrinsics` random extrinsics of `kind`, reproducible from `seed`. The maximum weight of the
+	/// blocks is lifted, so the execution of blocks larger than the chain allows can be measured.
+	///
+	/// The benchmark accounts hold no keys, so the extrinsics are signed by accounts of their own,
+	/// which are funded too. There are enough of them for none to make more transfers than the
+	/// balances pallet allows in a block, so every extrinsic of the block succeeds.
+	pub fn new(
+		mut genesis: RuntimeGenesisConfig,
+		kind: BlockKind,
+		extrinsics: u32,
+		accounts: u32,
+		seed: u64,
+	) -> Result<Self, String> {
+		genesis.system.max_block_weight = Some(Weight::MAX);
+		let mut runtime = Runtime::new();
+		genesis.build(&mut runtime).map_err(|error| format!("invalid genesis: {error}"))?;
+		benchmarking::populate_balances(&mut runtime.balances, accounts, FUNDS);
+
+		let mut keystore = Keystore::new();
+		let signers = (0..extrinsics.div_ceil(<Runtime as balances::Config>::MAX_TRANSFERS_PER_BLOCK))
+			.map(|index| keystore.insert(dev_pair(&format!("bench-signer-{index}"))))
+			.collect::<Vec<_>>();
+		for signer in &signers {
+			runtime.balances.set_balance(signer.clone(), FUNDS);
+		}
+
+		let mut rng = Rng(seed);
+		let mut nonces = BTreeMap::new();
+		let genesis_hash = runtime.system.genesis_hash();
+		let signed = (0..extrinsics)
+			.map(|index| {
+				let caller = signers[index as usize % signers.len()].clone();
+				let call = match kind {
+					BlockKind::Transfers => {
+						let to = benchmarking::account(rng.below(accounts.max(1).into()) as u32);
+						let amount = (rng.below(1_000) + 1).into();
+						RuntimeCall::balances(balances::Call::transfer { to, amount })
+					},
+					BlockKind::Claims => {
+						let content = rng.next_u64().to_le_bytes().to_vec();
+						RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { content })
+					},
+				};
+				let nonce = nonces.entry(caller.clone()).or_insert_with(|| runtime.system.get_nonce(&caller));
+				let extrinsic = types::ExtrinsicBuilder::new(caller).call(call).build();
+				let signed = keystore.sign_extrinsic(extrinsic, *nonce, &genesis_hash).expect("the signer is in the keystore");
+				*nonce += 1;
+				signed
+			})
+			.collect();
+		let block = BlockBuilder::new(&runtime, runtime.system.max_block_weight())
+			.seal(signed)
+			.map_err(|error| format!("the block can't be built: {error}"))?;
+		Ok(Self { runtime, block })
+	}
+
+	/// Time the execution of the block on copies of the state, after checking that every extrinsic
+	/// succeeds. The copies are made between the samples, so only the execution is timed.
+	pub fn run(&self, name: &str, samples: usize) -> Result<benchmarking::Measurement, String> {
+		let report = self.runtime.clone().execute_block(&self.block).map_err(|error| error.to_string())?;
+		if report.failed() > 0 {
+			return Err(format!("{} extrinsics of the block failed", report.failed()));
+		}
+		Ok(benchmarking::bench(name, samples, || self.runtime.clone(), |mut runtime| {
+			let report = runtime.execute_block(&self.block);
+			(runtime, report)
+		}))
+	}
+}
+
+/// Run the `benchmark` command of the node on `genesis`, and print its measurement.
+pub fn run(genesis: RuntimeGenesisConfig, kind: BlockKind, extrinsics: u32, accounts: u32) -> Result<(), String> {
+	println!("Populating the state with {accounts} accounts...");
+	let benchmark = Benchmark::new(genesis, kind, extrinsics, accounts, 42)?;
+	let measurement = benchmark.run(&format!("execute_block/{kind}/{extrinsics}"), SAMPLES)?;
+	println!("{measurement}");
+	Ok(())
+}
+
+/// The benchmark of a call of a pallet, which measures the weight of the call in its worst case.
+pub struct CallBenchmark {
+	/// The name of the pallet of the call, as in the metadata of the runtime.
+	pub pallet: &'static str,
+	/// The name of the function of the call, as in the metadata of the runtime.
+	pub function: &'static str,
+	/// The name of the constant of `weights.rs` the weight of the call is written to.
+	pub constant: &'static str,
+	/// The number of units the call is made for, like the targets of `transfer_multi`, when its
+	/// weight is declared per unit. The weight is the time of the call divided by this number.
+	pub units: u32,
+	/// Set up the worst case of the call on `runtime`, and return the call with its origin.
+	pub setup: fn(&mut Runtime) -> (RuntimeOrigin, RuntimeCall),
+}
+
+impl CallBenchmark {
+	/// Set up the worst case of the call on a copy of `runtime`, check that the call succeeds, then
+	/// time it over `samples` samples, and return its weight, which is the median time of the call
+	/// per unit, converted with `support::WEIGHT_PER_NANOS`.
+	pub fn measure(&self, runtime: &Runtime, samples: usize) -> Result<(benchmarking::Measurement, Weight), String> {
+		let mut runtime = runtime.clone();
+		let (origin, call) = (self.setup)(&mut runtime);
+		if let Err(error) = runtime.clone().dispatch(origin.clone(), call.clone()) {
+			return Err(format!("the worst case of {}::{} fails: {error}", self.pallet, self.function));
+		}
+
+		let name = format!("{}::{}", self.pallet, self.function);
+		let measurement = benchmarking::bench(&name, samples, || (runtime.clone(), origin.clone(), call.clone()), |(mut runtime, origin, call)| {
+			let result = runtime.dispatch(origin, call);
+			(runtime, result)
+		});
+		let nanos = measurement.median().as_nanos() as Weight;
+		let weight = (nanos.saturating_mul(WEIGHT_PER_NANOS) / Weight::from(self.units)).max(1);
+		Ok((measurement, weight))
+	}
+}
+
+// A benchmark account with a nonce, so it can make calls, and enough funds for their fees and
+// deposits.
+fn funded(runtime: &mut Runtime, index: u32) -> types::AccountId {
+	let who = benchmarking::account(index);
+	runtime.system.create_account(&who);
+	runtime.balances.set_balance(who.clone(), FUNDS);
+	who
+}
+
+// A benchmark account which doesn't exist yet, so the call creates it.
+fn fresh(index: u32) -> types::AccountId {
+	benchmarking::account(1_000 + index)
+}
+
+// Dispatch `call` during the setup of a benchmark, like the creation of a claim the benchmarked
+// call revokes.
+fn prepare(runtime: &mut Runtime, origin: RuntimeOrigin, call: RuntimeCall) {
+	runtime.dispatch(origin, call).expect("the setup of a benchmark must succeed");
+}
+
+// The claim of the content of the benchmarks, which is empty, since the weight of the length of
+// the content is declared per byte.
+fn claim() -> crate::system::Hash {
+	proof_of_existence::Pallet::<Runtime>::claim_of(&[])
+}
+
+fn create_claim(runtime: &mut Runtime, owner: &types::AccountId) {
+	let call = proof_of_existence::Call::create_claim { content: Vec::new() };
+	prepare(runtime, RawOrigin::Signed(owner.clone()), RuntimeCall::proof_of_existence(call));
+}
+
+// The owners of the shared claim of the benchmarks, which are as many as a claim can have.
+fn claim_owners(runtime: &mut Runtime) -> Vec<types::AccountId> {
+	let owners = <Runtime as proof_of_existence::Config>::MAX_CLAIM_OWNERS;
+	(0..owners).map(|index| funded(runtime, index)).collect()
+}
+
+// Propose a spend of the treasury pot during the setup of a benchmark, with the id 0.
+fn propose_spend(runtime: &mut Runtime) {
+	let proposer = funded(runtime, 0);
+	let call = treasury::Call::propose_spend { beneficiary: fresh(0), amount: FUNDS };
+	prepare(runtime, RawOrigin::Signed(proposer), RuntimeCall::treasury(call));
+}
+
+/// The benchmarks of the calls whose weights are generated into `weights.rs`, which are every call
+/// of the balances, proof of existence and treasury pallets, in the order they are declared.
+pub fn call_benchmarks() -> Vec<CallBenchmark> {
+	let max_transfers = <Runtime as balances::Config>::MAX_TRANSFERS_PER_BLOCK;
+	vec![
+		// Transfers moving the whole balance of the caller to an account they create, so the caller
+		// is reaped.
+		CallBenchmark { pallet: "balances", function: "transfer", constant: "TRANSFER", units: 1, setup: |runtime| {
+			let caller = funded(runtime, 0);
+			(RawOrigin::Signed(caller), RuntimeCall::balances(balances::Call::transfer { to: fresh(0), amount: FUNDS }))
+		} },
+		CallBenchmark { pallet: "balances", function: "transfer_some", constant: "TRANSFER_SOME", units: 1, setup: |runtime| {
+			let caller = funded(runtime, 0);
+			let call = balances::Call::transfer_some { to: fresh(0), amount: 2 * FUNDS };
+			(RawOrigin::Signed(caller), RuntimeCall::balances(call))
+		} },
+		CallBenchmark {
+			pallet: "balances",
+			function: "transfer_keep_alive",
+			constant: "TRANSFER_KEEP_ALIVE",
+			units: 1,
+			setup: |runtime| {
+				let caller = funded(runtime, 0);
+				let call = balances::Call::transfer_keep_alive { to: fresh(0), amount: FUNDS / 2 };
+				(RawOrigin::Signed(caller), RuntimeCall::balances(call))
+			},
+		},
+		CallBenchmark { pallet: "balances", function: "transfer_all", constant: "TRANSFER_ALL", units: 1, setup: |runtime| {
+			let caller = funded(runtime, 0);
+			let call = balances::Call::transfer_all { to: fresh(0), keep_alive: false };
+			(RawOrigin::Signed(caller), RuntimeCall::balances(call))
+		} },
+		// As many targets as the caller can transfer to in a block, which are all created.
+		CallBenchmark {
+			pallet: "balances",
+			function: "transfer_multi",
+			constant: "TRANSFER_MULTI_PER_TARGET",
+			units: max_transfers,
+			setup: |runtime| {
+				let caller = funded(runtime, 0);
+				let max_transfers = <Runtime as balances::Config>::MAX_TRANSFERS_PER_BLOCK;
+				let targets = (0..max_transfers).map(|index| (fresh(index), 10)).collect();
+				(RawOrigin::Signed(caller), RuntimeCall::balances(balances::Call::transfer_multi { targets }))
+			},
+		},
+		CallBenchmark { pallet: "balances", function: "create_escrow", constant: "CREATE_ESCROW", units: 1, setup: |runtime| {
+			let caller = funded(runtime, 0);
+			let call = balances::Call::create_escrow { to: fresh(0), amount: FUNDS / 2, release_block: 10 };
+			(RawOrigin::Signed(caller), RuntimeCall::balances(call))
+		} },
+		CallBenchmark { pallet: "balances", function: "cancel_escrow", constant: "CANCEL_ESCROW", units: 1, setup: |runtime| {
+			let caller = funded(runtime, 0);
+			let create = balances::Call::create_escrow { to: fresh(0), amount: FUNDS / 2, release_block: 10 };
+			prepare(runtime, RawOrigin::Signed(caller.clone()), RuntimeCall::balances(create));
+			(RawOrigin::Signed(caller), RuntimeCall::balances(balances::Call::cancel_escrow { escrow_id: 0 }))
+		} },
+		CallBenchmark {
+			pallet: "balances",
+			function: "force_set_balance",
+			constant: "FORCE_SET_BALANCE",
+			units: 1,
+			setup: |_| {
+				(RawOrigin::Root, RuntimeCall::balances(balances::Call::force_set_balance { who: fresh(0), amount: FUNDS }))
+			},
+		},
+		CallBenchmark { pallet: "balances", function: "mint_into", constant: "MINT_INTO", units: 1, setup: |_| {
+			(RawOrigin::Root, RuntimeCall::balances(balances::Call::mint_into { who: fresh(0), amount: FUNDS }))
+		} },
+		// Burning the whole balance of an account, which is reaped.
+		CallBenchmark { pallet: "balances", function: "burn_from", constant: "BURN_FROM", units: 1, setup: |runtime| {
+			let who = funded(runtime, 0);
+			(RawOrigin::Root, RuntimeCall::balances(balances::Call::burn_from { who, amount: FUNDS }))
+		} },
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "create_claim",
+			constant: "CREATE_CLAIM",
+			units: 1,
+			setup: |runtime| {
+				let caller = funded(runtime, 0);
+				let call = proof_of_existence::Call::create_claim { content: Vec::new() };
+				(RawOrigin::Signed(caller), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "create_claim_with_expiry",
+			constant: "CREATE_CLAIM_WITH_EXPIRY",
+			units: 1,
+			setup: |runtime| {
+				let caller = funded(runtime, 0);
+				let call = proof_of_existence::Call::create_claim_with_expiry { content: Vec::new(), ttl_blocks: 10 };
+				(RawOrigin::Signed(caller), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "revoke_claim",
+			constant: "REVOKE_CLAIM",
+			units: 1,
+			setup: |runtime| {
+				let caller = funded(runtime, 0);
+				create_claim(runtime, &caller);
+				let call = proof_of_existence::Call::revoke_claim { claim: claim() };
+				(RawOrigin::Signed(caller), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "transfer_claim",
+			constant: "TRANSFER_CLAIM",
+			units: 1,
+			setup: |runtime| {
+				let (caller, new_owner) = (funded(runtime, 0), funded(runtime, 1));
+				create_claim(runtime, &caller);
+				let call = proof_of_existence::Call::transfer_claim { claim: claim(), new_owner };
+				(RawOrigin::Signed(caller), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "renounce_claim",
+			constant: "RENOUNCE_CLAIM",
+			units: 1,
+			setup: |runtime| {
+				let caller = funded(runtime, 0);
+				create_claim(runtime, &caller);
+				let call = proof_of_existence::Call::renounce_claim { claim: claim() };
+				(RawOrigin::Signed(caller), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		// Metadata as long as a claim can hold.
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "set_claim_metadata",
+			constant: "SET_CLAIM_METADATA",
+			units: 1,
+			setup: |runtime| {
+				let caller = funded(runtime, 0);
+				create_claim(runtime, &caller);
+				let metadata = vec![0; <Runtime as proof_of_existence::Config>::MAX_METADATA_LENGTH];
+				let call = proof_of_existence::Call::set_claim_metadata { claim: claim(), metadata };
+				(RawOrigin::Signed(caller), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		// As many owners as a claim can have.
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "create_shared_claim",
+			constant: "CREATE_SHARED_CLAIM",
+			units: 1,
+			setup: |runtime| {
+				let owners = claim_owners(runtime);
+				let (caller, threshold) = (owners[0].clone(), owners.len() as u32);
+				let call = proof_of_existence::Call::create_shared_claim { content: Vec::new(), owners, threshold };
+				(RawOrigin::Signed(caller), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		// The last approval of a claim shared by as many owners as a claim can have, which revokes
+		// it.
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "approve_revoke",
+			constant: "APPROVE_REVOKE",
+			units: 1,
+			setup: |runtime| {
+				let owners = claim_owners(runtime);
+				let threshold = owners.len() as u32;
+				let create =
+					proof_of_existence::Call::create_shared_claim { content: Vec::new(), owners: owners.clone(), threshold };
+				prepare(runtime, RawOrigin::Signed(owners[0].clone()), RuntimeCall::proof_of_existence(create));
+				for owner in &owners[1..] {
+					let approve = proof_of_existence::Call::approve_revoke { claim: claim() };
+					prepare(runtime, RawOrigin::Signed(owner.clone()), RuntimeCall::proof_of_existence(approve));
+				}
+				let call = proof_of_existence::Call::approve_revoke { claim: claim() };
+				(RawOrigin::Signed(owners[0].clone()), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		CallBenchmark {
+			pallet: "proof_of_existence",
+			function: "query_claim",
+			constant: "QUERY_CLAIM",
+			units: 1,
+			setup: |runtime| {
+				let caller = funded(runtime, 0);
+				create_claim(runtime, &caller);
+				let call = proof_of_existence::Call::query_claim { claim: claim() };
+				(RawOrigin::Signed(caller), RuntimeCall::proof_of_existence(call))
+			},
+		},
+		CallBenchmark { pallet: "treasury", function: "propose_spend", constant: "PROPOSE_SPEND", units: 1, setup: |runtime| {
+			let caller = funded(runtime, 0);
+			let call = treasury::Call::propose_spend { beneficiary: fresh(0), amount: FUNDS };
+			(RawOrigin::Signed(caller), RuntimeCall::treasury(call))
+		} },
+		CallBenchmark {
+			pallet: "treasury",
+			function: "approve_proposal",
+			constant: "APPROVE_PROPOSAL",
+			units: 1,
+			setup: |runtime| {
+				propose_spend(runtime);
+				(RawOrigin::Root, RuntimeCall::treasury(treasury::Call::approve_proposal { proposal_id: 0 }))
+			},
+		},
+		// The bond of the proposer is slashed into the pot.
+		CallBenchmark {
+			pallet: "treasury",
+			function: "reject_proposal",
+			constant: "REJECT_PROPOSAL",
+			units: 1,
+			setup: |runtime| {
+				propose_spend(runtime);
+				(RawOrigin::Root, RuntimeCall::treasury(treasury::Call::reject_proposal { proposal_id: 0 }))
+			},
+		},
+	]
+}
+
+/// Render the `weights.rs` of the lib out of the weight of each benchmark, with a module of
+/// constants for each pallet.
+pub fn render_weights(weights: &[(CallBenchmark, Weight)]) -> String {
+	let mut file = String::from(WEIGHTS_HEADER);
+	let mut pallet = None;
+	for (benchmark, weight) in weights {
+		if pallet != Some(benchmark.pallet) {
+			if pallet.is_some() {
+				file.push_str("}\n");
+			}
+			let name = benchmark.pallet;
+			file.push_str(&format!("\n/// The weights of the calls of the `{name}` pallet.\npub mod {name} {{\n\tuse super::Weight;\n"));
+			pallet = Some(benchmark.pallet);
+		}
+		let per_unit = if benchmark.units > 1 { ", per unit" } else { "" };
+		file.push_str(&format!(
+			"\n\t/// The weight of `{}`{per_unit}.\n\tpub const {}: Weight = {};\n",
+			benchmark.function,
+			benchmark.constant,
+			fmt_weight(*weight)
+		));
+	}
+	if pallet.is_some() {
+		file.push_str("}\n");
+	}
+	file
+}
+
+/// The header of `weights.rs`, before the modules of the pallets.
+const WEIGHTS_HEADER: &str = "\
+//! The weights of the calls of the pallets, which are the median time of their worst case as
+//! measured on the runtime of the node, converted with `support::WEIGHT_PER_NANOS`. The calls
+//! declare them with their `#[weight = ...]` attribute, so the fees follow what the calls cost.
+//!
+//! This file is generated by the `benchmark weights` command of the node, regenerate it with
+//! `cargo run --release -- benchmark weights` instead of editing it.
+
+use crate::support::Weight;
+";
+
+// Format a weight with an underscore between each group of three digits, like `12_345`.
+fn fmt_weight(weight: Weight) -> String {
+	let digits = weight.to_string();
+	let mut formatted = String::new();
+	for (i, digit) in digits.chars().enumerate() {
+		if i > 0 && (digits.len() - i).is_multiple_of(3) {
+			formatted.push('_');
+		}
+		formatted.push(digit);
+	}
+	formatted
+}
+
+/// Run the `benchmark weights` command of the node on `genesis`: measure the weight of each call
+/// of `call_benchmarks`, and write the `weights.rs` of the lib to `output`.
+pub fn run_weights(genesis: RuntimeGenesisConfig, output: &Path) -> Result<(), String> {
+	let mut runtime = Runtime::new();
+	genesis.build(&mut runtime).map_err(|error| format!("invalid genesis: {error}"))?;
+	let mut weights = Vec::new();
+	for benchmark in call_benchmarks() {
+		let (measurement, weight) = benchmark.measure(&runtime, CALL_SAMPLES)?;
+		println!("{measurement} weight: {weight}");
+		weights.push((benchmark, weight));
+	}
+	std::fs::write(output, render_weights(&weights)).map_err(|error| format!("{}: {error}", output.display()))?;
+	println!("Weights written to {}", output.display());
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::{call_benchmarks, render_weights, Benchmark, BlockKind};
+	use crate::{development_genesis, Runtime};
+
+	#[test]
+	fn benchmark_blocks_only_hold_successful_extrinsics() {
+		for kind in [BlockKind::Transfers, BlockKind::Claims] {
+			// More transfers than a single account can make in a block.
+			let benchmark = Benchmark::new(development_genesis(), kind, 25, 100, 7).unwrap();
+			assert_eq!(benchmark.block.extrinsics.len(), 25);
+			let measurement = benchmark.run("execute_block", 2).unwrap();
+			assert_eq!(measurement.samples.len(), 2);
+		}
+		assert_eq!("claims".parse(), Ok(BlockKind::Claims));
+		assert_eq!(BlockKind::Transfers.to_string(), "transfers");
+	}
+
+	#[test]
+	fn every_call_of_the_weighted_pallets_is_benchmarked() {
+		let benchmarks = call_benchmarks();
+		let metadata = Runtime::metadata();
+		for pallet in ["balances", "proof_of_existence", "treasury"] {
+			let calls = metadata.pallet(pallet).unwrap().calls.iter().map(|call| call.name).collect::<Vec<_>>();
+			let benchmarked =
+				benchmarks.iter().filter(|benchmark| benchmark.pallet == pallet).map(|benchmark| benchmark.function).collect::<Vec<_>>();
+			assert_eq!(benchmarked, calls);
+		}
+
+		// The worst case of every call succeeds, and is rendered into the constant of the call.
+		let mut runtime = Runtime::new();
+		development_genesis().build(&mut runtime).unwrap();
+		let weights = benchmarks
+			.into_iter()
+			.map(|benchmark| {
+				let (_, weight) = benchmark.measure(&runtime, 1).unwrap();
+				assert!(weight > 0);
+				(benchmark, weight)
+			})
+			.collect::<Vec<_>>();
+		let file = render_weights(&weights);
+		assert!(file.contains("pub mod balances {") && file.contains("pub mod proof_of_existence {"));
+		assert!(file.contains("pub mod treasury {"));
+		assert!(file.contains("\tpub const TRANSFER: Weight = ") && file.contains("\tpub const QUERY_CLAIM: Weight = "));
+		assert!(file.contains("/// The weight of `transfer_multi`, per unit."));
+	}
+}
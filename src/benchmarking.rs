@@ -0,0 +1,152 @@
+//! Helpers to benchmark the runtime and its pallets: a reproducible random generator, accounts to
+//! populate a large state with, and a harness which times a routine over several samples.
+//!
+//! The `benches/` of the crate use them to measure the storage of the pallets with a million
+//! accounts, and the `benchmark` command of the node to measure `execute_block` on blocks of random
+//! transfers or claims. Both must be built with optimizations, like `cargo bench` and
+//! `cargo run --release` do, for their timings to mean anything.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::balances;
+use crate::crypto::AccountId32;
+
+/// A small xorshift generator, so benchmarks are random but reproducible from their seed, which
+/// must not be zero.
+#[derive(Debug, Clone)]
+pub struct Rng(pub u64);
+
+impl Rng {
+	/// The next random number.
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	/// A random number lower than `bound`.
+	pub fn below(&mut self, bound: u64) -> u64 {
+		self.next_u64() % bound
+	}
+}
+
+/// The benchmark account numbered `index`. Nobody holds a key for it, so it can receive funds but
+/// can't sign extrinsics.
+pub fn account(index: u32) -> AccountId32 {
+	let mut account = [0u8; 32];
+	account[..5].copy_from_slice(b"bench");
+	account[28..].copy_from_slice(&index.to_be_bytes());
+	AccountId32(account)
+}
+
+/// Give `balance` to each of the benchmark accounts numbered below `accounts`.
+pub fn populate_balances<T>(balances: &mut balances::Pallet<T>, accounts: u32, balance: T::Balance)
+where
+	T: balances::Config<AccountId = AccountId32>,
+{
+	for index in 0..accounts {
+		balances.set_balance(account(index), balance);
+	}
+}
+
+/// The timings of the samples of a benchmark, fastest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+	/// The name of the benchmark, like `execute_block/transfers/100`.
+	pub name: String,
+	/// The time each sample took, fastest first.
+	pub samples: Vec<Duration>,
+}
+
+impl Measurement {
+	/// The time of the fastest sample.
+	pub fn min(&self) -> Duration {
+		self.samples[0]
+	}
+
+	/// The time of the median sample.
+	pub fn median(&self) -> Duration {
+		self.samples[self.samples.len() / 2]
+	}
+
+	/// The time of the slowest sample.
+	pub fn max(&self) -> Duration {
+		self.samples[self.samples.len() - 1]
+	}
+}
+
+// A measurement is shown as its name followed by the times of its fastest, median and slowest
+// samples.
+impl fmt::Display for Measurement {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:<40} time: [{:?} {:?} {:?}]", self.name, self.min(), self.median(), self.max())
+	}
+}
+
+/// Time `routine` over `samples` samples, each on a new input built by `setup`, which isn't timed,
+/// like a copy of the state the routine modifies. The routine returns what it modified, so dropping
+/// it isn't timed either.
+///
+/// # Panics
+///
+/// Panics if `samples` is zero.
+pub fn bench<I, O>(
+	name: &str,
+	samples: usize,
+	mut setup: impl FnMut() -> I,
+	mut routine: impl FnMut(I) -> O,
+) -> Measurement {
+	assert!(samples > 0, "a benchmark needs at least one sample");
+	let mut timings = Vec::with_capacity(samples);
+	for _ in 0..samples {
+		let input = setup();
+		let start = Instant::now();
+		let output = std::hint::black_box(routine(std::hint::black_box(input)));
+		timings.push(start.elapsed());
+		drop(output);
+	}
+	timings.sort();
+	Measurement { name: name.to_string(), samples: timings }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{account, bench, populate_balances, Rng};
+
+	crate::construct_mock_runtime!(struct TestConfig<AccountId = crate::crypto::AccountId32>);
+
+	impl crate::balances::Config for TestConfig {
+		type Balance = u128;
+		const MINIMUM_TRANSFER: Self::Balance = 1;
+		const MAX_TRANSFERS_PER_BLOCK: u32 = u32::MAX;
+		const EXISTENTIAL_DEPOSIT: Self::Balance = 1;
+	}
+
+	#[test]
+	fn benchmarks_time_each_sample_on_a_fresh_input() {
+		let mut balances = crate::balances::Pallet::<TestConfig>::new();
+		populate_balances(&mut balances, 1_000, 10);
+		assert_eq!(balances.total_issuance(), 10_000);
+		assert_ne!(account(0), account(1));
+
+		let mut rng = Rng(42);
+		let mut setups = 0;
+		let measurement = bench(
+			"balances/transfer",
+			5,
+			|| {
+				setups += 1;
+				balances.clone()
+			},
+			|mut balances| {
+				let (from, to) = (account(rng.below(1_000) as u32), account(rng.below(1_000) as u32));
+				balances.transfer(from, to, 5).map(|_| balances)
+			},
+		);
+		assert_eq!((measurement.samples.len(), setups), (5, 5));
+		assert!(measurement.min() <= measurement.median() && measurement.median() <= measurement.max());
+		assert!(measurement.to_string().starts_with("balances/transfer"));
+	}
+}
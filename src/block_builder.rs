@@ -0,0 +1,253 @@
+use crate::support::{self, DispatchError, Weight};
+use crate::tx_pool::TransactionPool;
+use crate::{types, Runtime, RuntimeCall};
+
+/// Builds the next block on top of the current state of the runtime, out of a pool of candidate
+/// extrinsics. This is the block author's counterpart to `Runtime::execute_block`.
+pub struct BlockBuilder<'a> {
+	/// The runtime the block will be executed on. It is never modified by the builder.
+	runtime: &'a Runtime,
+	/// The maximum total weight of the inherents and extrinsics included in the block.
+	max_weight: Weight,
+	/// The inherents of the block, which the block author provides, like the time of the block.
+	inherents: Vec<RuntimeCall>,
+}
+
+impl<'a> BlockBuilder<'a> {
+	/// Create a new block builder on top of the current state of `runtime`.
+	pub fn new(runtime: &'a Runtime, max_weight: Weight) -> Self {
+		Self { runtime, max_weight, inherents: Vec::new() }
+	}
+
+	/// Include `inherents` in the blocks built, before their extrinsics.
+	pub fn with_inherents(mut self, inherents: Vec<RuntimeCall>) -> Self {
+		self.inherents = inherents;
+		self
+	}
+
+	/// Build the next block out of the extrinsics waiting in `pool`.
+	///
+	/// Candidates are tried in the order they are drained from the pool, which is their priority
+	/// order. Each candidate is applied in a transaction on top of the state left by the candidates
+	/// included before it, and it is dropped, with its changes undone, if it fails, or if it
+	/// doesn't fit in the remaining weight of the block.
+	///
+	/// The extrinsics which don't fit in the remaining weight of the block, and the later
+	/// extrinsics of their callers, are submitted back to the pool against the state after the
+	/// block, so they can be included in a later block. Extrinsics which fail are dropped.
+	///
+	/// The header of the block is complete: it is built on the best block of the runtime, and
+	/// commits to the extrinsics which were included and to the state they leave.
+	///
+	/// This fails if the block number of the next block can't be computed, or if an inherent
+	/// fails.
+	pub fn build_from_pool(&self, pool: &mut TransactionPool) -> Result<types::Block, DispatchError> {
+		let Authored { block, state, deferred } = self.author(pool.drain())?;
+		for signed in deferred {
+			// Extrinsics which are no longer valid on top of the block are dropped.
+			let _ = pool.submit_extrinsic(&state, signed);
+		}
+		Ok(block)
+	}
+
+	/// Build the next block with exactly `extrinsics`, in the given order, with the header
+	/// `Runtime::execute_block` expects. Unlike `build_from_pool`, extrinsics which fail are kept in the
+	/// block, and the weight of the block is not checked.
+	///
+	/// This fails if the block number of the next block can't be computed, or if an inherent
+	/// fails.
+	pub fn seal(&self, extrinsics: Vec<types::SignedExtrinsic>) -> Result<types::Block, DispatchError> {
+		let mut state = self.initialize()?;
+		for signed in &extrinsics {
+			// Failed extrinsics are kept in the block, like `execute_block` does.
+			let _ = state.apply_extrinsic(signed.clone());
+		}
+		Ok(self.finalize(&mut state, extrinsics))
+	}
+
+	/// A copy of the state of the runtime, migrated to the version of the runtime if needed, with
+	/// the next block initialized and its inherents applied.
+	fn initialize(&self) -> Result<Runtime, DispatchError> {
+		let mut state = self.runtime.clone();
+		state.system.inc_block_number()?;
+		state.migrate()?;
+		support::InitializeBlock::initialize_block(&mut state);
+		for inherent in &self.inherents {
+			state.apply_inherent(inherent.clone())?;
+		}
+		Ok(state)
+	}
+
+	/// Finalize the block in `state`, where `extrinsics` were applied, and build the block with the
+	/// root of the state it leaves.
+	fn finalize(&self, state: &mut Runtime, extrinsics: Vec<types::SignedExtrinsic>) -> types::Block {
+		support::FinalizeBlock::finalize_block(state);
+		let mut block = types::Block::new(self.runtime.system.best_block_hash(), state.system.block_number(), extrinsics)
+			.with_inherents(self.inherents.clone());
+		block.header.state_root = support::StateRoot::state_root(state);
+		block
+	}
+
+	/// Build the next block out of `candidates`, keeping the state left by the block and the
+	/// candidates deferred to a later block.
+	fn author(&self, candidates: Vec<types::SignedExtrinsic>) -> Result<Authored, DispatchError> {
+		let mut state = self.initialize()?;
+		let mut block_weight = self.inherents.iter().map(RuntimeCall::weight).sum::<Weight>();
+		let mut extrinsics = Vec::new();
+		let mut deferred = Vec::new();
+		for signed in candidates {
+			// Once an extrinsic of a caller is deferred, their later extrinsics can't be applied
+			// before it.
+			let caller = &signed.extrinsic.caller;
+			if deferred.iter().any(|other: &types::SignedExtrinsic| &other.extrinsic.caller == caller) {
+				deferred.push(signed);
+				continue;
+			}
+			let weight = match block_weight.checked_add(signed.extrinsic.call.weight()) {
+				Some(weight) if weight <= self.max_weight => weight,
+				_ => {
+					deferred.push(signed);
+					continue;
+				},
+			};
+
+			// A candidate which fails is left out of the block, so none of its changes are kept,
+			// not even its nonce and its fee.
+			let applied = support::with_transaction(&mut state, |state| state.apply_extrinsic(signed.clone()));
+			if applied.is_ok() {
+				block_weight = weight;
+				extrinsics.push(signed);
+			}
+		}
+
+		let block = self.finalize(&mut state, extrinsics);
+		Ok(Authored { block, state, deferred })
+	}
+}
+
+/// A block authored by a `BlockBuilder`.
+struct Authored {
+	block: types::Block,
+	/// The state of the runtime after the block.
+	state: Runtime,
+	/// The candidates which didn't fit in the block, in the order they were given.
+	deferred: Vec<types::SignedExtrinsic>,
+}
+
+#[cfg(test)]
+mod test {
+	use super::{BlockBuilder, TransactionPool};
+	use pallets::weights::balances::TRANSFER;
+	use pallets::weights::proof_of_existence::CREATE_CLAIM;
+	use crate::{balances, dev_account, proof_of_existence, support, types, Runtime, RuntimeCall};
+
+	fn transfer(caller: &str, to: &str, amount: types::Balance, tip: types::Balance) -> types::Extrinsic {
+		support::Extrinsic {
+			caller: dev_account(caller),
+			call: RuntimeCall::balances(balances::Call::transfer { to: dev_account(to), amount }),
+			valid_until: None,
+			tip,
+		}
+	}
+
+	#[test]
+	fn build_block_packs_valid_extrinsics_by_tip() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(dev_account("alice"), 100);
+		runtime.balances.set_balance(dev_account("bob"), 100);
+
+		let claim = support::Extrinsic {
+			caller: dev_account("bob"),
+			call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				content: "my_document".into(),
+			}),
+			valid_until: None,
+			tip: 3,
+		};
+		let mut pool = TransactionPool::new();
+		for signed in [
+			runtime.sign(transfer("alice", "bob", 10, 1), 0),
+			runtime.sign(transfer("bob", "charlie", 20, 5), 0),
+			runtime.sign(claim, 1),
+			// Alice's second transfer doesn't fit in the weight left by the others.
+			runtime.sign(transfer("alice", "bob", 10, 0), 1),
+		] {
+			pool.submit_extrinsic(&runtime, signed).expect("valid extrinsic");
+		}
+		// Charlie has no funds to pay for the transfer or the fee. The pool would refuse his
+		// extrinsic, but the block builder also drops it when it is given directly.
+		let mut candidates = pool.drain();
+		candidates.insert(0, runtime.sign(transfer("charlie", "alice", 10, 10), 0));
+
+		// The claim weighs a byte of weight per byte of content on top of the weight of its call.
+		let max_weight = 2 * TRANSFER + CREATE_CLAIM + 11;
+		let block = BlockBuilder::new(&runtime, max_weight).author(candidates).expect("wrong block building").block;
+		assert_eq!(block.header.block_number, 1);
+		let included = block
+			.extrinsics
+			.iter()
+			.map(|signed| (signed.extrinsic.caller.clone(), signed.extrinsic.tip))
+			.collect::<Vec<_>>();
+		assert_eq!(included, vec![(dev_account("bob"), 5), (dev_account("bob"), 3), (dev_account("alice"), 1)]);
+		let weight = block.extrinsics.iter().map(|signed| signed.extrinsic.call.weight()).sum::<u64>();
+		assert_eq!(weight, max_weight);
+
+		// Building a block does not modify the runtime, and the block can be executed on it.
+		assert_eq!(runtime.system.block_number(), 0);
+		let report = runtime.execute_block(&block).expect("wrong block execution");
+		assert!(report.all_succeeded());
+	}
+
+	#[test]
+	fn build_block_respects_weight_limit() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(dev_account("alice"), 100);
+
+		let candidates = vec![
+			runtime.sign(transfer("alice", "bob", 10, 3), 0),
+			runtime.sign(transfer("alice", "bob", 10, 2), 1),
+			runtime.sign(transfer("alice", "bob", 10, 1), 2),
+		];
+
+		let block = BlockBuilder::new(&runtime, 2 * TRANSFER).author(candidates).expect("wrong block building").block;
+		let tips = block.extrinsics.iter().map(|signed| signed.extrinsic.tip).collect::<Vec<_>>();
+		assert_eq!(tips, vec![3, 2]);
+	}
+
+	#[test]
+	fn build_from_pool_keeps_extrinsics_which_do_not_fit() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(dev_account("alice"), 100);
+		runtime.balances.set_balance(dev_account("bob"), 100);
+
+		// Only two transfers fit in a block, so Alice's last transfer and Bob's transfer are left
+		// for the next block.
+		let mut pool = TransactionPool::new();
+		for (tip, nonce) in [(3, 0), (2, 1), (1, 2)] {
+			pool.submit_extrinsic(&runtime, runtime.sign(transfer("alice", "bob", 10, tip), nonce)).unwrap();
+		}
+		pool.submit_extrinsic(&runtime, runtime.sign(transfer("bob", "alice", 10, 0), 0)).unwrap();
+
+		let block_1 = BlockBuilder::new(&runtime, 2 * TRANSFER)
+			.build_from_pool(&mut pool)
+			.expect("wrong block building");
+		let tips = block_1.extrinsics.iter().map(|signed| signed.extrinsic.tip).collect::<Vec<_>>();
+		assert_eq!(tips, vec![3, 2]);
+		assert_eq!(pool.len(), 2);
+		runtime.execute_block(&block_1).expect("wrong block execution");
+
+		let block_2 = BlockBuilder::new(&runtime, 2 * TRANSFER)
+			.build_from_pool(&mut pool)
+			.expect("wrong block building");
+		assert_eq!(block_2.header.parent_hash, block_1.header.hash());
+		let included = block_2
+			.extrinsics
+			.iter()
+			.map(|signed| (signed.extrinsic.caller.clone(), signed.nonce))
+			.collect::<Vec<_>>();
+		assert_eq!(included, vec![(dev_account("alice"), 2), (dev_account("bob"), 0)]);
+		assert!(pool.is_empty());
+		let report = runtime.execute_block(&block_2).expect("wrong block execution");
+		assert!(report.all_succeeded());
+	}
+}
@@ -0,0 +1,460 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::chain_store::ChainStore;
+use crate::finality::FinalityError;
+#[cfg(any(test, feature = "network"))]
+use crate::import_queue::BlockImport;
+use crate::import_queue::{execute_verified, ImportError};
+use crate::storage::StorageBackend;
+use crate::support::{BlockExecutionReport, DispatchError};
+use crate::{system, types, Runtime};
+
+/// The blocks on the way from one block to another through their common ancestor, like when the
+/// best block moves to another fork.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeRoute {
+	/// The blocks rolled back, from the block the route starts at down to the common ancestor,
+	/// which is excluded.
+	pub retracted: Vec<system::Hash>,
+	/// The common ancestor of both blocks.
+	pub common_ancestor: system::Hash,
+	/// The blocks applied, from the child of the common ancestor up to the block the route ends at.
+	pub enacted: Vec<system::Hash>,
+}
+
+/// The chain of a node: every block imported since the finalized block, kept in a tree so several
+/// forks can compete to be the best chain, on top of the best chain kept in a `ChainStore`.
+///
+/// The best block is chosen by the longest chain rule: a block becomes the best block when it is
+/// higher than the best block. When two forks are as long, the one whose block was imported first
+/// stays the best chain, so the best block only moves when another fork gets longer. The store
+/// always holds the blocks of the best chain, so a node finds it again when it restarts.
+///
+/// Only the states after the finalized block and after the best block are kept. The state of any
+/// other block is rebuilt by executing the blocks from the finalized block up to it again, so a
+/// block on another fork is executed on the state of its parent. When the best block moves to
+/// another fork, the state of the chain rolls back to the common ancestor of both forks, and the
+/// blocks of the new fork are executed again on top of it, see `TreeRoute`.
+///
+/// A block finalized by the finality gadget, see `finality`, is never rolled back: the forks which
+/// don't include it are dropped, and blocks can only be imported on top of it.
+pub struct Chain<B> {
+	/// The hash of the genesis, which is the parent of the first block.
+	genesis_hash: system::Hash,
+	/// The blocks of the best chain.
+	store: ChainStore<B>,
+	/// The finalized block and the blocks imported after it, on every fork, keyed by hash.
+	blocks: BTreeMap<system::Hash, types::Block>,
+	/// The children of the finalized block and of the blocks after it, in the order they were
+	/// imported.
+	children: BTreeMap<system::Hash, Vec<system::Hash>>,
+	/// The state left by the finalized block, which is the genesis state until a block is
+	/// finalized.
+	finalized: Runtime,
+	/// The state left by the best block.
+	best: Runtime,
+}
+
+impl<B: StorageBackend> Chain<B>
+where
+	B::Error: fmt::Debug,
+{
+	/// Open the chain whose best chain is kept in `store`, on top of the `genesis` state, which
+	/// executes every block stored again. The last block finalized is finalized again, so only the
+	/// blocks after it can be rolled back.
+	///
+	/// This fails if a block is missing from the store or can't be decoded, or if a block doesn't
+	/// execute.
+	pub fn new(genesis: Runtime, store: ChainStore<B>) -> Result<Self, DispatchError> {
+		let genesis_hash = genesis.system.best_block_hash();
+		let mut chain = Self {
+			genesis_hash,
+			store,
+			blocks: BTreeMap::new(),
+			children: BTreeMap::new(),
+			finalized: genesis.clone(),
+			best: genesis,
+		};
+		let finalized_number = chain.store.finalized_block_number();
+		for block_number in 1..=chain.store.best_block_number() {
+			let block = chain
+				.store
+				.block(block_number)
+				.ok_or(DispatchError::Other("missing or corrupt block in the chain store"))?;
+			chain.best.execute_block(&block)?;
+			match block_number.cmp(&finalized_number) {
+				Ordering::Less => {},
+				Ordering::Equal => {
+					chain.finalized = chain.best.clone();
+					chain.blocks.insert(block.header.hash(), block);
+				},
+				Ordering::Greater => chain.insert(block),
+			}
+		}
+		Ok(chain)
+	}
+
+	/// The number and hash of the best block.
+	pub fn best_block(&self) -> (types::BlockNumber, system::Hash) {
+		(self.best.system.block_number(), self.best.system.best_block_hash())
+	}
+
+	/// The number and hash of the last block finalized, which is the genesis until a block is
+	/// finalized.
+	pub fn finalized_head(&self) -> (types::BlockNumber, system::Hash) {
+		(self.finalized.system.block_number(), self.finalized.system.best_block_hash())
+	}
+
+	/// The state of the chain, after its best block.
+	pub fn runtime(&self) -> &Runtime {
+		&self.best
+	}
+
+	/// The state of the chain after its finalized block.
+	pub fn finalized_runtime(&self) -> &Runtime {
+		&self.finalized
+	}
+
+	/// The block with `hash`, on any fork, if it is the finalized block or was imported after it.
+	pub fn block(&self, hash: &system::Hash) -> Option<&types::Block> {
+		self.blocks.get(hash)
+	}
+
+	/// The block with `block_number` on the best chain.
+	pub fn canonical_block(&self, block_number: types::BlockNumber) -> Option<types::Block> {
+		self.store.block(block_number)
+	}
+
+	/// The hash of the block with `block_number` on the best chain.
+	pub fn canonical_hash(&self, block_number: types::BlockNumber) -> Option<system::Hash> {
+		match block_number {
+			0 => Some(self.genesis_hash),
+			_ => self.best.system.block_hash(block_number),
+		}
+	}
+
+	/// Whether blocks can be imported on top of the block with `hash`, which is either the finalized
+	/// block or a block imported after it.
+	pub fn is_known(&self, hash: &system::Hash) -> bool {
+		self.blocks.contains_key(hash) || *hash == self.finalized_head().1
+	}
+
+	/// The state left by the block with `hash`, on any fork, if it is known. It is rebuilt by
+	/// executing the blocks from the finalized block up to it again, unless it is the best block.
+	pub fn state_at(&self, hash: &system::Hash) -> Option<Runtime> {
+		if *hash == self.best_block().1 {
+			return Some(self.best.clone());
+		}
+		let route = self.tree_route(self.finalized_head().1, *hash)?;
+		let mut state = self.finalized.clone();
+		for block in &route.enacted {
+			state.execute_block(&self.blocks[block]).expect("the blocks imported execute again");
+		}
+		Some(state)
+	}
+
+	/// The route from the block with hash `from` to the block with hash `to`, if both are known.
+	pub fn tree_route(&self, from: system::Hash, to: system::Hash) -> Option<TreeRoute> {
+		let (mut from_number, mut to_number) = (self.number_of(&from)?, self.number_of(&to)?);
+		let (mut from, mut to) = (from, to);
+		let (mut retracted, mut enacted) = (Vec::new(), Vec::new());
+		while from != to {
+			if from_number >= to_number {
+				retracted.push(from);
+				from = self.blocks[&from].header.parent_hash;
+				from_number -= 1;
+			} else {
+				enacted.push(to);
+				to = self.blocks[&to].header.parent_hash;
+				to_number -= 1;
+			}
+		}
+		enacted.reverse();
+		Some(TreeRoute { retracted, common_ancestor: from, enacted })
+	}
+
+	/// Whether the block with `hash` is `ancestor` or one of its descendants.
+	pub fn is_descendant(&self, hash: system::Hash, ancestor: system::Hash) -> bool {
+		self.tree_route(ancestor, hash).is_some_and(|route| route.retracted.is_empty())
+	}
+
+	/// Import `block` on top of its parent, which can be any known block, see `is_known`, and make
+	/// it the best block if its fork is now the longest. The blocks of the new best chain are
+	/// stored before the block is imported.
+	///
+	/// The block goes through the checks of the import queue, see `execute_verified`, against the
+	/// state of its parent. It is refused if it was imported already, or if it isn't built on top
+	/// of the finalized block.
+	pub fn import_block(&mut self, block: types::Block) -> Result<BlockExecutionReport, ImportError> {
+		let hash = block.header.hash();
+		let (finalized_number, _) = self.finalized_head();
+		if self.is_known(&hash) || self.canonical_hash(block.header.block_number) == Some(hash) {
+			return Err(ImportError::AlreadyKnown);
+		}
+		if block.header.block_number <= finalized_number {
+			return Err(ImportError::ConflictsWithFinalized);
+		}
+		let parent_hash = block.header.parent_hash;
+		let parent = self.state_at(&parent_hash).ok_or(ImportError::UnknownParent { parent_hash })?;
+		let (state, report) = execute_verified(parent, &block)?;
+
+		if state.system.block_number() > self.best.system.block_number() {
+			let route = self.tree_route(self.best_block().1, parent_hash).expect("the parent is known");
+			let ancestor = self.number_of(&route.common_ancestor).expect("the ancestor is known");
+			let enacted = route.enacted.iter().map(|enacted| &self.blocks[enacted]).chain([&block]);
+			self.store.set_best_chain(ancestor, enacted).map_err(|error| ImportError::Storage(format!("{error:?}")))?;
+			self.best = state;
+		}
+		self.insert(block);
+		Ok(report)
+	}
+
+	/// Finalize the block with `hash`, which must be built on top of the finalized block, and
+	/// drop every fork which doesn't include it.
+	///
+	/// When the best block was on one of those forks, the longest fork built on the finalized block
+	/// becomes the best chain, and the route to its head is returned.
+	pub fn finalize_block(&mut self, hash: system::Hash) -> Result<Option<TreeRoute>, FinalityError> {
+		let (finalized_number, finalized_hash) = self.finalized_head();
+		if hash == finalized_hash {
+			return Ok(None);
+		}
+		if !self.blocks.contains_key(&hash) {
+			// Only the ancestors of the finalized block are known outside of the tree.
+			let is_ancestor = (0..finalized_number).any(|block_number| self.canonical_hash(block_number) == Some(hash));
+			return Err(if is_ancestor { FinalityError::ConflictsWithFinalized } else { FinalityError::UnknownBlock(hash) });
+		}
+
+		// The store is written first, so nothing changes if writing it fails.
+		let head = (!self.is_descendant(self.best_block().1, hash)).then(|| self.longest_fork_from(hash));
+		let new_best = match head {
+			Some(head) => {
+				let route = self.tree_route(self.best_block().1, head).expect("both blocks are known");
+				let ancestor = self.number_of(&route.common_ancestor).expect("the ancestor is known");
+				let enacted = route.enacted.iter().map(|enacted| &self.blocks[enacted]);
+				self.store.set_best_chain(ancestor, enacted).map_err(|error| FinalityError::Storage(format!("{error:?}")))?;
+				Some(route)
+			},
+			None => None,
+		};
+		let number = self.blocks[&hash].header.block_number;
+		self.store.set_finalized(number).map_err(|error| FinalityError::Storage(format!("{error:?}")))?;
+		if let Some(head) = head {
+			self.best = self.state_at(&head).expect("the head is known");
+		}
+		self.finalized = self.state_at(&hash).expect("the block is known");
+
+		// Only the finalized block and its descendants are kept, its ancestors being stored.
+		let kept = self.blocks.keys().filter(|block| self.is_descendant(**block, hash)).copied().collect::<Vec<_>>();
+		self.blocks.retain(|block, _| kept.contains(block));
+		self.children.retain(|parent, _| kept.contains(parent));
+		Ok(new_best)
+	}
+
+	/// Add `block`, which was executed on top of its parent, to the tree.
+	fn insert(&mut self, block: types::Block) {
+		let hash = block.header.hash();
+		self.children.entry(block.header.parent_hash).or_default().push(hash);
+		self.blocks.insert(hash, block);
+	}
+
+	/// The number of the block with `hash`, if it is known.
+	fn number_of(&self, hash: &system::Hash) -> Option<types::BlockNumber> {
+		match self.blocks.get(hash) {
+			Some(block) => Some(block.header.block_number),
+			None => (*hash == self.finalized_head().1).then_some(self.finalized_head().0),
+		}
+	}
+
+	/// The head of the longest fork built on the block with `hash`. When several forks are as long,
+	/// it is the head of the one whose blocks were imported first.
+	fn longest_fork_from(&self, hash: system::Hash) -> system::Hash {
+		let number_of = |hash: &system::Hash| self.number_of(hash).expect("the blocks of the tree are known");
+		let (mut head, mut pending) = (hash, vec![hash]);
+		while let Some(block) = pending.pop() {
+			if number_of(&block) > number_of(&head) {
+				head = block;
+			}
+			pending.extend(self.children.get(&block).into_iter().flatten().rev());
+		}
+		head
+	}
+}
+
+#[cfg(any(test, feature = "network"))]
+impl<B: StorageBackend> BlockImport for Chain<B>
+where
+	B::Error: fmt::Debug,
+{
+	fn is_known(&self, hash: &system::Hash) -> bool {
+		Chain::is_known(self, hash)
+	}
+
+	fn finalized_number(&self) -> types::BlockNumber {
+		self.finalized_head().0
+	}
+
+	fn import_block(&mut self, block: &types::Block) -> Result<BlockExecutionReport, ImportError> {
+		Chain::import_block(self, block.clone())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Chain, TreeRoute};
+	use crate::block_builder::BlockBuilder;
+	use crate::chain_store::ChainStore;
+	use crate::consensus;
+	use crate::finality::FinalityError;
+	use crate::import_queue::ImportError;
+	use crate::storage::{FileBackend, InMemoryBackend};
+	use crate::{balances, dev_account, development_genesis, system, types, Runtime, RuntimeApi, RuntimeCall, DEV_KEYSTORE};
+
+	fn genesis() -> Runtime {
+		let mut genesis = Runtime::new();
+		development_genesis().build(&mut genesis).unwrap();
+		genesis
+	}
+
+	fn new_chain() -> Chain<InMemoryBackend> {
+		Chain::new(genesis(), ChainStore::new(InMemoryBackend::new())).unwrap()
+	}
+
+	// Build and seal a block on top of `parent` in `chain`, with a transfer from Alice to `to`.
+	fn block_on<B>(chain: &Chain<B>, parent: system::Hash, to: &str, amount: types::Balance) -> types::Block
+	where
+		B: crate::storage::StorageBackend,
+		B::Error: std::fmt::Debug,
+	{
+		let state = chain.state_at(&parent).unwrap();
+		let call = RuntimeCall::balances(balances::Call::transfer { to: dev_account(to), amount });
+		let extrinsic = types::ExtrinsicBuilder::new(dev_account("alice")).call(call).build();
+		let signed = state.sign(extrinsic, state.nonce_of(&dev_account("alice")));
+		let block = BlockBuilder::new(&state, state.system.max_block_weight()).seal(vec![signed]).unwrap();
+		sealed(&state, block)
+	}
+
+	// Seal `block` again, after its header was changed.
+	fn sealed(parent: &Runtime, mut block: types::Block) -> types::Block {
+		block.header.digest.clear();
+		DEV_KEYSTORE.with(|keystore| consensus::seal_block(parent, &mut block, keystore)).unwrap();
+		block
+	}
+
+	// Import a block built with `block_on`, returning its hash and the route from the previous best
+	// block to the best block.
+	fn extend<B>(chain: &mut Chain<B>, parent: system::Hash, to: &str, amount: types::Balance) -> (system::Hash, TreeRoute)
+	where
+		B: crate::storage::StorageBackend,
+		B::Error: std::fmt::Debug,
+	{
+		let block = block_on(chain, parent, to, amount);
+		let hash = block.header.hash();
+		let previous_best = chain.best_block().1;
+		let report = chain.import_block(block).expect("valid block");
+		assert!(report.all_succeeded());
+		(hash, chain.tree_route(previous_best, chain.best_block().1).unwrap())
+	}
+
+	#[test]
+	fn longest_fork_becomes_the_best_chain() {
+		let mut chain = new_chain();
+		let genesis_hash = chain.canonical_hash(0).unwrap();
+
+		// Fork A pays Bob, and is imported first.
+		let (a1, route) = extend(&mut chain, genesis_hash, "bob", 10);
+		assert_eq!(route, TreeRoute { retracted: vec![], common_ancestor: genesis_hash, enacted: vec![a1] });
+		let (a2, _) = extend(&mut chain, a1, "bob", 10);
+		assert_eq!(chain.best_block(), (2, a2));
+
+		// Fork B pays Charlie. It is as long as fork A at block 2, so fork A stays the best chain.
+		let (b1, route) = extend(&mut chain, genesis_hash, "charlie", 5);
+		assert!(route.enacted.is_empty());
+		let (b2, route) = extend(&mut chain, b1, "charlie", 5);
+		assert!(route.enacted.is_empty());
+		assert_eq!(chain.best_block(), (2, a2));
+		assert!(chain.is_known(&b2));
+		assert_eq!(chain.canonical_block(2).map(|block| block.header.hash()), Some(a2));
+
+		// Once fork B is longer, the state rolls back to the genesis, and fork B is executed again.
+		let (b3, route) = extend(&mut chain, b2, "charlie", 5);
+		assert_eq!(route, TreeRoute { retracted: vec![a2, a1], common_ancestor: genesis_hash, enacted: vec![b1, b2, b3] });
+		assert_eq!(chain.best_block(), (3, b3));
+		assert_eq!(chain.runtime().balance_of(&dev_account("bob")), 0);
+		assert_eq!(chain.runtime().balance_of(&dev_account("charlie")), 15);
+		assert_eq!((1..=3).map(|number| chain.canonical_hash(number)).collect::<Vec<_>>(), [Some(b1), Some(b2), Some(b3)]);
+		let stored = (1..=4).map(|number| chain.canonical_block(number).map(|block| block.header.hash()));
+		assert_eq!(stored.collect::<Vec<_>>(), [Some(b1), Some(b2), Some(b3), None]);
+
+		// The blocks of fork A are kept, and their state is rebuilt from the genesis.
+		assert_eq!(chain.state_at(&a2).unwrap().balance_of(&dev_account("bob")), 20);
+		assert_eq!(chain.tree_route(b3, a1).map(|route| route.enacted), Some(vec![a1]));
+	}
+
+	#[test]
+	fn blocks_need_a_known_parent_and_are_imported_once() {
+		let mut chain = new_chain();
+		let block_1 = block_on(&chain, chain.canonical_hash(0).unwrap(), "bob", 10);
+
+		let mut orphan = block_1.clone();
+		orphan.header.parent_hash = [7; 32];
+		assert_eq!(chain.import_block(orphan).err(), Some(ImportError::UnknownParent { parent_hash: [7; 32] }));
+		let mut invalid = block_1.clone();
+		invalid.header.state_root = [0; 32];
+		let invalid = sealed(chain.runtime(), invalid);
+		assert_eq!(chain.import_block(invalid).err(), Some(ImportError::StateRootMismatch));
+		assert!(chain.import_block(block_1.clone()).is_ok());
+		assert_eq!(chain.import_block(block_1).err(), Some(ImportError::AlreadyKnown));
+		assert_eq!(chain.best_block().0, 1);
+	}
+
+	#[test]
+	fn finalizing_a_shorter_fork_moves_the_best_block_to_it() {
+		let mut chain = new_chain();
+		let genesis_hash = chain.canonical_hash(0).unwrap();
+		let (a1, _) = extend(&mut chain, genesis_hash, "bob", 10);
+		let (a2, _) = extend(&mut chain, a1, "bob", 10);
+		let (b1, _) = extend(&mut chain, genesis_hash, "charlie", 5);
+
+		let route = chain.finalize_block(b1).unwrap();
+		assert_eq!(route, Some(TreeRoute { retracted: vec![a2, a1], common_ancestor: genesis_hash, enacted: vec![b1] }));
+		assert_eq!((chain.best_block(), chain.finalized_head()), ((1, b1), (1, b1)));
+		assert_eq!(chain.runtime().balance_of(&dev_account("bob")), 0);
+		assert!(chain.canonical_block(2).is_none());
+
+		// Fork A is gone, and can't come back even if it gets longer.
+		let block_3 = block_on(&chain, b1, "bob", 1);
+		assert!(chain.block(&a2).is_none() && chain.state_at(&a1).is_none());
+		assert_eq!(chain.finalize_block(a2).err(), Some(FinalityError::UnknownBlock(a2)));
+		assert!(chain.import_block(block_3).is_ok());
+	}
+
+	#[test]
+	fn chain_resumes_from_its_stored_best_chain() {
+		let path = std::env::temp_dir().join(format!("pallets-chain-{}", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		let open = || Chain::new(genesis(), ChainStore::new(FileBackend::open(&path).unwrap()));
+
+		let mut chain = open().expect("empty store");
+		let genesis_hash = chain.canonical_hash(0).unwrap();
+		let (a1, _) = extend(&mut chain, genesis_hash, "bob", 10);
+		let (b1, _) = extend(&mut chain, genesis_hash, "charlie", 5);
+		let (b2, _) = extend(&mut chain, b1, "charlie", 5);
+		let best = chain.runtime().clone();
+		drop(chain);
+
+		// The node restarts, and finds the state it had before it stopped. Only the best chain was
+		// stored, so fork A is gone.
+		let mut chain = open().expect("stored blocks execute");
+		assert_eq!(chain.best_block(), (2, b2));
+		assert!(chain.runtime().diff(&best).is_empty());
+		assert!(!chain.is_known(&a1));
+
+		// It then carries on from the best block.
+		extend(&mut chain, b2, "bob", 10);
+		assert_eq!(chain.runtime().balance_of(&dev_account("bob")), 10);
+		std::fs::remove_file(&path).unwrap();
+	}
+}
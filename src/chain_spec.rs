@@ -0,0 +1,577 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::crypto::Public;
+use crate::support::{DispatchError, Weight};
+use crate::{system, types, Runtime, RuntimeGenesisConfig};
+
+/// A chain specification, which names a chain and describes its genesis state, including the
+/// parameters of the runtime which can differ from one network to another.
+///
+/// Chain specs are written in JSON, with a section for the genesis config of each pallet. Every
+/// section, and every field of a section, can be left out, in which case it is empty, or the
+/// parameter keeps the value of the runtime's `Config`. Accounts are given as SS58 addresses with
+/// the prefix of the runtime:
+///
+/// ```json
+/// {
+///   "name": "Development",
+///   "genesis": {
+///     "system": { "public_keys": { "<alice>": "<32 bytes in hex>" }, "max_block_weight": 100000 },
+///     "balances": { "balances": { "<alice>": 100 }, "existential_deposit": 1 },
+///     "proof_of_existence": { "claims": { "<alice>": ["my_document"] } },
+///     "identity": { "names": { "<alice>": "Alice" } },
+///     "sudo": { "key": "<alice>" },
+///     "collective": { "members": ["<alice>", "<bob>"] },
+///     "aura": { "authorities": ["<alice>", "<bob>"] }
+///   }
+/// }
+/// ```
+pub struct ChainSpec {
+	/// The name of the chain, which the node shows with its state.
+	pub name: String,
+	/// The genesis state of the chain, which is built into a new runtime before the first block.
+	pub genesis: RuntimeGenesisConfig,
+}
+
+/// The errors which can occur when loading a chain spec.
+#[derive(Debug)]
+pub enum Error {
+	/// The chain spec file couldn't be read.
+	Io(std::io::Error),
+	/// The chain spec is not valid JSON, or uses JSON which is not supported, at this byte offset.
+	Json(usize),
+	/// The chain spec is valid JSON, but doesn't describe a chain.
+	Invalid(&'static str),
+	/// The chain spec describes a genesis state which a pallet refuses, like a balance below the
+	/// existential deposit.
+	Genesis(DispatchError),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Io(error) => write!(f, "cannot read chain spec: {error}"),
+			Error::Json(offset) => write!(f, "invalid JSON at byte {offset}"),
+			Error::Invalid(reason) => write!(f, "invalid chain spec: {reason}"),
+			Error::Genesis(error) => write!(f, "invalid genesis state: {error}"),
+		}
+	}
+}
+
+impl ChainSpec {
+	/// Load a chain spec from the JSON file at `path`.
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+		Self::from_json(&std::fs::read_to_string(path).map_err(Error::Io)?)
+	}
+
+	/// Parse a chain spec from JSON. Unknown sections or fields are refused, so a typo can't
+	/// silently leave part of the genesis state empty.
+	pub fn from_json(json: &str) -> Result<Self, Error> {
+		let mut spec = Json::parse(json)?.into_object("the chain spec must be an object")?;
+		let name = spec
+			.remove("name")
+			.ok_or(Error::Invalid("missing name"))?
+			.into_string("the name must be a string")?;
+		let mut sections = match spec.remove("genesis") {
+			Some(genesis) => genesis.into_object("the genesis must be an object")?,
+			None => BTreeMap::new(),
+		};
+		ensure_no_unknown_field(&spec)?;
+
+		let mut genesis = RuntimeGenesisConfig::default();
+		let mut system = section(&mut sections, "system")?;
+		for (who, public) in field(&mut system, "public_keys")? {
+			let public = public.into_string("public keys must be strings")?;
+			let public = decode_hex(&public).ok_or(Error::Invalid("public keys must be 32 bytes in hex"))?;
+			genesis.system.public_keys.push((account(&who)?, Public(public)));
+		}
+		if let Some(weight) = system.remove("max_block_weight") {
+			let weight = weight.into_number("the maximum block weight must be a number")?;
+			let weight = Weight::try_from(weight).map_err(|_| Error::Invalid("the maximum block weight is too large"))?;
+			genesis.system.max_block_weight = Some(weight);
+		}
+		ensure_no_unknown_field(&system)?;
+
+		let mut balances = section(&mut sections, "balances")?;
+		for (who, balance) in field(&mut balances, "balances")? {
+			let balance = balance.into_number("balances must be numbers")?;
+			genesis.balances.balances.push((account(&who)?, balance));
+		}
+		if let Some(deposit) = balances.remove("existential_deposit") {
+			genesis.balances.existential_deposit = Some(deposit.into_number("the existential deposit must be a number")?);
+		}
+		ensure_no_unknown_field(&balances)?;
+
+		let mut proof_of_existence = section(&mut sections, "proof_of_existence")?;
+		for (owner, contents) in field(&mut proof_of_existence, "claims")? {
+			let contents = contents.into_array("the claims of an account must be an array")?;
+			let owner = account(&owner)?;
+			for content in contents {
+				let content = content.into_string("claimed contents must be strings")?;
+				genesis.proof_of_existence.claims.push((owner.clone(), content.into_bytes()));
+			}
+		}
+		ensure_no_unknown_field(&proof_of_existence)?;
+
+		let mut identity = section(&mut sections, "identity")?;
+		for (who, name) in field(&mut identity, "names")? {
+			genesis.identity.names.push((account(&who)?, name.into_string("names must be strings")?));
+		}
+		ensure_no_unknown_field(&identity)?;
+
+		let mut sudo = section(&mut sections, "sudo")?;
+		if let Some(key) = sudo.remove("key") {
+			genesis.sudo.key = Some(account(&key.into_string("the sudo key must be a string")?)?);
+		}
+		ensure_no_unknown_field(&sudo)?;
+
+		let mut collective = section(&mut sections, "collective")?;
+		if let Some(members) = collective.remove("members") {
+			for member in members.into_array("the members must be an array")? {
+				genesis.collective.members.push(account(&member.into_string("members must be strings")?)?);
+			}
+		}
+		ensure_no_unknown_field(&collective)?;
+
+		let mut aura = section(&mut sections, "aura")?;
+		if let Some(authorities) = aura.remove("authorities") {
+			for authority in authorities.into_array("the authorities must be an array")? {
+				genesis.aura.authorities.push(account(&authority.into_string("authorities must be strings")?)?);
+			}
+		}
+		ensure_no_unknown_field(&aura)?;
+		ensure_no_unknown_field(&sections)?;
+
+		Ok(Self { name, genesis })
+	}
+}
+
+impl Runtime {
+	/// Load the chain spec at `path`, and build a new runtime with its genesis state.
+	pub fn from_chain_spec(path: impl AsRef<Path>) -> Result<Self, Error> {
+		let mut runtime = Runtime::new();
+		ChainSpec::load(path)?.genesis.build(&mut runtime).map_err(Error::Genesis)?;
+		Ok(runtime)
+	}
+}
+
+/// Parse the SS58 `address` of an account, which must have the SS58 prefix of the runtime.
+fn account(address: &str) -> Result<types::AccountId, Error> {
+	match types::AccountId::from_ss58check_with_prefix(address) {
+		Ok((account, prefix)) if prefix == <Runtime as system::Config>::SS58_PREFIX => Ok(account),
+		Ok(_) => Err(Error::Invalid("accounts must have the SS58 prefix of the chain")),
+		Err(_) => Err(Error::Invalid("accounts must be SS58 addresses")),
+	}
+}
+
+/// Take the section of the genesis config of `pallet`, which is empty if it is left out.
+fn section(sections: &mut BTreeMap<String, Json>, pallet: &str) -> Result<BTreeMap<String, Json>, Error> {
+	match sections.remove(pallet) {
+		Some(section) => section.into_object("genesis sections must be objects"),
+		None => Ok(BTreeMap::new()),
+	}
+}
+
+/// Take a field of a genesis section, which maps accounts to values. It is empty if it is left out.
+fn field(section: &mut BTreeMap<String, Json>, name: &str) -> Result<BTreeMap<String, Json>, Error> {
+	match section.remove(name) {
+		Some(field) => field.into_object("genesis fields must map accounts to values"),
+		None => Ok(BTreeMap::new()),
+	}
+}
+
+fn ensure_no_unknown_field(object: &BTreeMap<String, Json>) -> Result<(), Error> {
+	if !object.is_empty() {
+		return Err(Error::Invalid("unknown field"));
+	}
+	Ok(())
+}
+
+/// Decode 32 bytes from 64 hexadecimal digits.
+fn decode_hex(hex: &str) -> Option<[u8; 32]> {
+	let digits = hex.as_bytes();
+	if digits.len() != 64 {
+		return None;
+	}
+	let mut bytes = [0u8; 32];
+	for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+		*byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+	}
+	Some(bytes)
+}
+
+/// A JSON value. Only the JSON which chain specs and the RPC server need is supported: numbers must
+/// be integers which fit in a `u128`.
+///
+/// Values are formatted as compact JSON with `Display`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+	Null,
+	Bool(bool),
+	Number(u128),
+	String(String),
+	Array(Vec<Json>),
+	Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+	/// Parse a JSON document, which must hold a single value.
+	pub fn parse(json: &str) -> Result<Json, Error> {
+		let mut parser = Parser { input: json.as_bytes(), position: 0 };
+		let value = parser.value()?;
+		parser.skip_whitespace();
+		if parser.position != parser.input.len() {
+			return Err(Error::Json(parser.position));
+		}
+		Ok(value)
+	}
+
+	fn into_object(self, reason: &'static str) -> Result<BTreeMap<String, Json>, Error> {
+		match self {
+			Json::Object(object) => Ok(object),
+			_ => Err(Error::Invalid(reason)),
+		}
+	}
+
+	fn into_string(self, reason: &'static str) -> Result<String, Error> {
+		match self {
+			Json::String(string) => Ok(string),
+			_ => Err(Error::Invalid(reason)),
+		}
+	}
+
+	fn into_array(self, reason: &'static str) -> Result<Vec<Json>, Error> {
+		match self {
+			Json::Array(array) => Ok(array),
+			_ => Err(Error::Invalid(reason)),
+		}
+	}
+
+	fn into_number(self, reason: &'static str) -> Result<u128, Error> {
+		match self {
+			Json::Number(number) => Ok(number),
+			_ => Err(Error::Invalid(reason)),
+		}
+	}
+}
+
+impl fmt::Display for Json {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Json::Null => write!(f, "null"),
+			Json::Bool(value) => write!(f, "{value}"),
+			Json::Number(number) => write!(f, "{number}"),
+			Json::String(string) => write_json_string(f, string),
+			Json::Array(array) => {
+				write!(f, "[")?;
+				for (i, value) in array.iter().enumerate() {
+					if i > 0 {
+						write!(f, ",")?;
+					}
+					write!(f, "{value}")?;
+				}
+				write!(f, "]")
+			},
+			Json::Object(object) => {
+				write!(f, "{{")?;
+				for (i, (key, value)) in object.iter().enumerate() {
+					if i > 0 {
+						write!(f, ",")?;
+					}
+					write_json_string(f, key)?;
+					write!(f, ":{value}")?;
+				}
+				write!(f, "}}")
+			},
+		}
+	}
+}
+
+/// Write `string` as a JSON string, escaping the characters JSON doesn't allow in strings.
+fn write_json_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+	write!(f, "\"")?;
+	for c in string.chars() {
+		match c {
+			'"' => write!(f, "\\\"")?,
+			'\\' => write!(f, "\\\\")?,
+			'\n' => write!(f, "\\n")?,
+			'\r' => write!(f, "\\r")?,
+			'\t' => write!(f, "\\t")?,
+			c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+			c => write!(f, "{c}")?,
+		}
+	}
+	write!(f, "\"")
+}
+
+/// A recursive descent parser over the bytes of a JSON document.
+struct Parser<'a> {
+	input: &'a [u8],
+	/// The offset of the next byte to parse.
+	position: usize,
+}
+
+impl Parser<'_> {
+	fn value(&mut self) -> Result<Json, Error> {
+		self.skip_whitespace();
+		match self.peek() {
+			Some(b'{') => self.object(),
+			Some(b'[') => self.array(),
+			Some(b'"') => self.string().map(Json::String),
+			Some(b'0'..=b'9') => self.number(),
+			Some(b't') => self.keyword("true", Json::Bool(true)),
+			Some(b'f') => self.keyword("false", Json::Bool(false)),
+			Some(b'n') => self.keyword("null", Json::Null),
+			_ => Err(Error::Json(self.position)),
+		}
+	}
+
+	fn object(&mut self) -> Result<Json, Error> {
+		self.expect(b'{')?;
+		let mut object = BTreeMap::new();
+		self.skip_whitespace();
+		if self.peek() == Some(b'}') {
+			self.position += 1;
+			return Ok(Json::Object(object));
+		}
+		loop {
+			self.skip_whitespace();
+			let key_position = self.position;
+			let key = self.string()?;
+			self.skip_whitespace();
+			self.expect(b':')?;
+			let value = self.value()?;
+			// A key which appears twice is ambiguous, so it is refused.
+			if object.insert(key, value).is_some() {
+				return Err(Error::Json(key_position));
+			}
+			self.skip_whitespace();
+			match self.next() {
+				Some(b',') => continue,
+				Some(b'}') => return Ok(Json::Object(object)),
+				_ => return Err(Error::Json(self.position - 1)),
+			}
+		}
+	}
+
+	fn array(&mut self) -> Result<Json, Error> {
+		self.expect(b'[')?;
+		let mut array = Vec::new();
+		self.skip_whitespace();
+		if self.peek() == Some(b']') {
+			self.position += 1;
+			return Ok(Json::Array(array));
+		}
+		loop {
+			array.push(self.value()?);
+			self.skip_whitespace();
+			match self.next() {
+				Some(b',') => continue,
+				Some(b']') => return Ok(Json::Array(array)),
+				_ => return Err(Error::Json(self.position - 1)),
+			}
+		}
+	}
+
+	fn string(&mut self) -> Result<String, Error> {
+		self.expect(b'"')?;
+		let mut string = Vec::new();
+		loop {
+			match self.next() {
+				Some(b'"') => break,
+				Some(b'\\') => {
+					let escaped = match self.next() {
+						Some(b'"') => '"',
+						Some(b'\\') => '\\',
+						Some(b'/') => '/',
+						Some(b'b') => '\u{8}',
+						Some(b'f') => '\u{c}',
+						Some(b'n') => '\n',
+						Some(b'r') => '\r',
+						Some(b't') => '\t',
+						Some(b'u') => self.unicode_escape()?,
+						_ => return Err(Error::Json(self.position - 1)),
+					};
+					string.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+				},
+				Some(byte) if byte >= 0x20 => string.push(byte),
+				_ => return Err(Error::Json(self.position.saturating_sub(1))),
+			}
+		}
+		// The input is a `str`, and only whole characters were copied, so this can't fail.
+		String::from_utf8(string).map_err(|_| Error::Json(self.position))
+	}
+
+	/// Parse the 4 hexadecimal digits of a `\u` escape. Surrogate pairs are not supported.
+	fn unicode_escape(&mut self) -> Result<char, Error> {
+		let start = self.position;
+		let digits = self.input.get(start..start + 4).ok_or(Error::Json(start))?;
+		self.position += 4;
+		std::str::from_utf8(digits)
+			.ok()
+			.and_then(|digits| u32::from_str_radix(digits, 16).ok())
+			.and_then(char::from_u32)
+			.ok_or(Error::Json(start))
+	}
+
+	fn number(&mut self) -> Result<Json, Error> {
+		let start = self.position;
+		while matches!(self.peek(), Some(b'0'..=b'9')) {
+			self.position += 1;
+		}
+		// Leading zeros, fractions and exponents are not supported.
+		if (self.position - start > 1 && self.input[start] == b'0') ||
+			matches!(self.peek(), Some(b'.' | b'e' | b'E'))
+		{
+			return Err(Error::Json(start));
+		}
+		let digits = std::str::from_utf8(&self.input[start..self.position]).map_err(|_| Error::Json(start))?;
+		digits.parse().map(Json::Number).map_err(|_| Error::Json(start))
+	}
+
+	fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, Error> {
+		if !self.input[self.position..].starts_with(keyword.as_bytes()) {
+			return Err(Error::Json(self.position));
+		}
+		self.position += keyword.len();
+		Ok(value)
+	}
+
+	fn expect(&mut self, byte: u8) -> Result<(), Error> {
+		if self.peek() != Some(byte) {
+			return Err(Error::Json(self.position));
+		}
+		self.position += 1;
+		Ok(())
+	}
+
+	fn skip_whitespace(&mut self) {
+		while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+			self.position += 1;
+		}
+	}
+
+	fn peek(&self) -> Option<u8> {
+		self.input.get(self.position).copied()
+	}
+
+	fn next(&mut self) -> Option<u8> {
+		let byte = self.peek()?;
+		self.position += 1;
+		Some(byte)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ChainSpec, Error, Json};
+	use crate::{balances, dev_account, proof_of_existence, system, Runtime, RuntimeApi};
+
+	fn claim_of(content: &str) -> system::Hash {
+		proof_of_existence::Pallet::<Runtime>::claim_of(content.as_bytes())
+	}
+
+	#[test]
+	fn parse_json_values() {
+		let json = Json::parse(r#" { "a": [1, true, null], "b": "x\"é\n", "c": {} } "#).unwrap();
+		let mut object = json.into_object("").unwrap();
+		assert_eq!(object.remove("a"), Some(Json::Array(vec![Json::Number(1), Json::Bool(true), Json::Null])));
+		assert_eq!(object.remove("b"), Some(Json::String("x\"é\n".to_string())));
+		assert_eq!(object.remove("c"), Some(Json::Object(Default::default())));
+
+		// Formatted values parse back to themselves.
+		let json = Json::parse(r#"{"a":[1,true,null],"b":"x\"\\é\n\u0001","c":{}}"#).unwrap();
+		assert_eq!(json.to_string(), r#"{"a":[1,true,null],"b":"x\"\\é\n\u0001","c":{}}"#);
+		assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+
+		for (invalid, offset) in [("{\"a\": 1,}", 8), ("[1 2]", 3), ("1.5", 0), ("01", 0), ("{\"a\": 1, \"a\": 2}", 9), ("1 1", 2)] {
+			assert!(matches!(Json::parse(invalid), Err(Error::Json(at)) if at == offset), "{invalid}");
+		}
+	}
+
+	#[test]
+	fn chain_spec_builds_the_genesis_state() {
+		let public = "ab".repeat(32);
+		let [alice, bob] = ["alice", "bob"].map(dev_account);
+		let json = format!(
+			r#"{{
+				"name": "Testnet",
+				"genesis": {{
+					"system": {{ "public_keys": {{ "{alice}": "{public}" }}, "max_block_weight": 20000 }},
+					"balances": {{ "balances": {{ "{alice}": 100, "{bob}": 50 }}, "existential_deposit": 10 }},
+					"proof_of_existence": {{ "claims": {{ "{bob}": ["my_document", "my_photo"] }} }},
+					"identity": {{ "names": {{ "{bob}": "Bob" }} }},
+					"sudo": {{ "key": "{alice}" }},
+					"collective": {{ "members": ["{bob}", "{alice}"] }},
+					"aura": {{ "authorities": ["{bob}", "{alice}"] }}
+				}}
+			}}"#
+		);
+		let spec = ChainSpec::from_json(&json).expect("valid chain spec");
+		assert_eq!(spec.name, "Testnet");
+
+		let mut runtime = Runtime::new();
+		spec.genesis.build(&mut runtime).expect("valid genesis");
+		assert_eq!(runtime.system.public_key(&alice).map(|public| public.0), Some([0xab; 32]));
+		assert_eq!(runtime.balances.balance(&alice), 100);
+		assert_eq!(runtime.balances.total_issuance(), 150);
+		assert_eq!(runtime.identity.name_of(&bob), Some(&"Bob".to_string()));
+		assert_eq!(runtime.sudo.key(), Some(&alice));
+		assert_eq!(runtime.collective.members(), [alice.clone(), bob.clone()]);
+		assert_eq!(runtime.aura.authorities(), [bob.clone(), alice]);
+		assert_eq!(runtime.system.max_block_weight(), 20_000);
+		assert_eq!(runtime.balances.existential_deposit(), 10);
+		for content in ["my_document", "my_photo"] {
+			assert_eq!(runtime.claim_owner(&claim_of(content)), Some(bob.clone()));
+		}
+
+		// The genesis sections can be left out.
+		let spec = ChainSpec::from_json(r#"{ "name": "Empty" }"#).expect("valid chain spec");
+		assert!(spec.genesis.balances.balances.is_empty());
+		assert!(spec.genesis.sudo.key.is_none());
+		assert_eq!((spec.genesis.system.max_block_weight, spec.genesis.balances.existential_deposit), (None, None));
+	}
+
+	#[test]
+	fn runtime_from_chain_spec_file() {
+		let path = std::env::temp_dir().join(format!("pallets-chain-spec-{}.json", std::process::id()));
+		let alice = dev_account("alice");
+		std::fs::write(&path, format!(r#"{{ "name": "Test", "genesis": {{ "balances": {{ "balances": {{ "{alice}": 5 }} }} }} }}"#)).unwrap();
+		let runtime = Runtime::from_chain_spec(&path).expect("valid chain spec");
+		assert_eq!(runtime.balance_of(&alice), 5);
+		assert_eq!(runtime.balances.existential_deposit(), <Runtime as balances::Config>::EXISTENTIAL_DEPOSIT);
+
+		// The chain spec is valid, but Alice's balance is below its existential deposit.
+		let json = format!(r#"{{ "name": "Test", "genesis": {{ "balances": {{ "balances": {{ "{alice}": 5 }}, "existential_deposit": 10 }} }} }}"#);
+		std::fs::write(&path, json).unwrap();
+		let error = Runtime::from_chain_spec(&path).err();
+		assert!(matches!(error, Some(Error::Genesis(error)) if error == balances::Error::ExistentialDeposit.into()));
+		std::fs::remove_file(&path).unwrap();
+		assert!(matches!(Runtime::from_chain_spec(&path), Err(Error::Io(_))));
+	}
+
+	#[test]
+	fn invalid_chain_specs_are_refused() {
+		for (json, reason) in [
+			(r#"{ "genesis": {} }"#, "missing name"),
+			(r#"{ "name": "Test", "genesis": { "balance": {} } }"#, "unknown field"),
+			(r#"{ "name": "Test", "genesis": { "balances": { "balances": { "alice": "100" } } } }"#, "balances must be numbers"),
+			(r#"{ "name": "Test", "genesis": { "system": { "public_keys": { "alice": "ab" } } } }"#, "public keys must be 32 bytes in hex"),
+			(r#"{ "name": "Test", "genesis": { "sudo": { "key": 1 } } }"#, "the sudo key must be a string"),
+			(r#"{ "name": "Test", "genesis": { "collective": { "members": "alice" } } }"#, "the members must be an array"),
+			(r#"{ "name": "Test", "genesis": { "system": { "max_block_weight": 18446744073709551616 } } }"#, "the maximum block weight is too large"),
+			(r#"{ "name": "Test", "genesis": { "balances": { "existential_deposit": "1" } } }"#, "the existential deposit must be a number"),
+			(r#"{ "name": "Test", "genesis": { "proof_of_existence": { "claims": { "alice": "my_document" } } } }"#, "the claims of an account must be an array"),
+			(r#"{ "name": "Test", "genesis": { "sudo": { "key": "alice" } } }"#, "accounts must be SS58 addresses"),
+			(
+				// Alice's address on Polkadot, whose SS58 prefix is 0.
+				r#"{ "name": "Test", "genesis": { "sudo": { "key": "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5" } } }"#,
+				"accounts must have the SS58 prefix of the chain",
+			),
+		] {
+			assert!(matches!(ChainSpec::from_json(json), Err(Error::Invalid(found)) if found == reason), "{json}");
+		}
+	}
+}
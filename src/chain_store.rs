@@ -0,0 +1,154 @@
+use crate::codec::{Decode, Encode};
+use crate::storage::StorageBackend;
+use crate::types;
+
+/// The key of the number of the best block stored.
+const BEST_BLOCK_KEY: &[u8] = b"best_block";
+/// The key of the number of the last block finalized.
+const FINALIZED_BLOCK_KEY: &[u8] = b"finalized_block";
+
+/// The blocks of the chain, kept in a `StorageBackend`.
+///
+/// Only the blocks of the best chain are stored, by number. The state of the runtime is not
+/// stored: `Chain::new` executes the blocks again on top of the genesis state when the node
+/// restarts, which rebuilds the state left by the best block. Since the pallets are deterministic,
+/// this is the same state the node had before it stopped.
+pub struct ChainStore<B> {
+	backend: B,
+}
+
+impl<B: StorageBackend> ChainStore<B> {
+	/// Open the chain kept in `backend`, which is empty for a new chain.
+	pub fn new(backend: B) -> Self {
+		Self { backend }
+	}
+
+	/// The number of the last block stored, which is 0 before the first block.
+	pub fn best_block_number(&self) -> types::BlockNumber {
+		self.backend
+			.get(BEST_BLOCK_KEY)
+			.and_then(|bytes| types::BlockNumber::decode_all(&bytes).ok())
+			.unwrap_or(0)
+	}
+
+	/// The number of the last block finalized, which is 0 until a block is finalized.
+	pub fn finalized_block_number(&self) -> types::BlockNumber {
+		self.backend
+			.get(FINALIZED_BLOCK_KEY)
+			.and_then(|bytes| types::BlockNumber::decode_all(&bytes).ok())
+			.unwrap_or(0)
+	}
+
+	/// The block stored with `block_number`, if any.
+	pub fn block(&self, block_number: types::BlockNumber) -> Option<types::Block> {
+		self.backend
+			.get(&block_key(block_number))
+			.and_then(|bytes| types::Block::decode_all(&bytes).ok())
+	}
+
+	/// Store `blocks` as the best chain after the block with `ancestor`, in place of the blocks
+	/// stored after it, and commit them, like when the best block moves to another fork.
+	///
+	/// The blocks must follow each other from `ancestor`, and must have been executed on top of
+	/// the blocks stored up to `ancestor`, so that replaying them gives the same state.
+	pub fn set_best_chain<'b>(
+		&mut self,
+		ancestor: types::BlockNumber,
+		blocks: impl IntoIterator<Item = &'b types::Block>,
+	) -> Result<(), B::Error> {
+		let previous_best = self.best_block_number();
+		assert!(ancestor <= previous_best, "the common ancestor must be stored");
+		assert!(ancestor >= self.finalized_block_number(), "finalized blocks are never replaced");
+		let mut best = ancestor;
+		for block in blocks {
+			assert_eq!(block.header.block_number, best + 1, "blocks must be stored in order");
+			best = block.header.block_number;
+			self.backend.insert(block_key(best), block.encode());
+		}
+		for retracted in best + 1..=previous_best {
+			self.backend.remove(&block_key(retracted));
+		}
+		self.backend.insert(BEST_BLOCK_KEY.to_vec(), best.encode());
+		self.backend.commit()
+	}
+
+	/// Record that the stored block with `block_number` is finalized, and commit it.
+	pub fn set_finalized(&mut self, block_number: types::BlockNumber) -> Result<(), B::Error> {
+		assert!(block_number <= self.best_block_number(), "the finalized block must be stored");
+		self.backend.insert(FINALIZED_BLOCK_KEY.to_vec(), block_number.encode());
+		self.backend.commit()
+	}
+}
+
+/// The key of the block with `block_number`. Block numbers are big endian, so the blocks are
+/// ordered by number in the backend.
+fn block_key(block_number: types::BlockNumber) -> Vec<u8> {
+	[b"block:".as_slice(), &block_number.to_be_bytes()].concat()
+}
+
+#[cfg(test)]
+mod test {
+	use super::ChainStore;
+	use crate::chain::Chain;
+	use crate::codec::Encode;
+	use crate::storage::{InMemoryBackend, StorageBackend};
+	use crate::support::DispatchError;
+	use crate::{development_genesis, system, types, Runtime};
+
+	fn blocks(parent_hash: system::Hash, numbers: std::ops::RangeInclusive<types::BlockNumber>) -> Vec<types::Block> {
+		let mut parent_hash = parent_hash;
+		numbers
+			.map(|block_number| {
+				let block = types::Block::new(parent_hash, block_number, Vec::new());
+				parent_hash = block.header.hash();
+				block
+			})
+			.collect()
+	}
+
+	#[test]
+	fn best_chain_replaces_the_blocks_after_the_common_ancestor() {
+		let mut store = ChainStore::new(InMemoryBackend::new());
+		assert_eq!(store.best_block_number(), 0);
+		let fork_a = blocks([0; 32], 1..=3);
+		store.set_best_chain(0, &fork_a).expect("commit should succeed");
+		assert_eq!(store.best_block_number(), 3);
+
+		// Fork B leaves fork A after block 1, and is shorter, like when a shorter fork is finalized.
+		let fork_b = blocks(fork_a[0].header.hash(), 2..=2);
+		store.set_best_chain(1, &fork_b).expect("commit should succeed");
+		assert_eq!(store.best_block_number(), 2);
+		let stored = (1..=3).map(|block_number| store.block(block_number).map(|block| block.encode()));
+		assert_eq!(stored.collect::<Vec<_>>(), [Some(fork_a[0].encode()), Some(fork_b[0].encode()), None]);
+	}
+
+	#[test]
+	#[should_panic(expected = "finalized blocks are never replaced")]
+	fn finalized_blocks_are_never_replaced() {
+		let mut store = ChainStore::new(InMemoryBackend::new());
+		let fork_a = blocks([0; 32], 1..=2);
+		store.set_best_chain(0, &fork_a).expect("commit should succeed");
+		store.set_finalized(1).expect("commit should succeed");
+		assert_eq!(store.finalized_block_number(), 1);
+		let _ = store.set_best_chain(0, &blocks([1; 32], 1..=3));
+	}
+
+	#[test]
+	#[should_panic(expected = "blocks must be stored in order")]
+	fn blocks_must_follow_the_common_ancestor() {
+		let mut store = ChainStore::new(InMemoryBackend::new());
+		let _ = store.set_best_chain(0, &blocks([0; 32], 2..=2));
+	}
+
+	#[test]
+	fn chain_fails_to_open_on_a_missing_block() {
+		let mut backend = InMemoryBackend::new();
+		backend.insert(super::BEST_BLOCK_KEY.to_vec(), 1u32.encode());
+		let mut genesis = Runtime::new();
+		development_genesis().build(&mut genesis).unwrap();
+		assert_eq!(
+			Chain::new(genesis, ChainStore::new(backend)).err(),
+			Some(DispatchError::Other("missing or corrupt block in the chain store"))
+		);
+	}
+}
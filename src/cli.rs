@@ -0,0 +1,237 @@
+use std::path::PathBuf;
+
+use crate::bench::BlockKind;
+use crate::crypto::AccountId32;
+use crate::{dev_account, system, types, Runtime, DEV_ACCOUNTS, DEV_KEYSTORE};
+
+/// The usage of the node, shown when its command line is invalid.
+pub const USAGE: &str = "\
+usage: node [--chain <spec.json>] [--base-path <dir>] [--validator <account>] <command>
+
+commands:
+  transfer <from> <to> <amount>    submit a transfer to the transaction pool
+  claim create <owner> <content>   submit a claim on a content
+  claim revoke <owner> <content>   submit the revocation of a claim
+  block produce                    author and import a block out of the transaction pool
+  state dump                       show the chain, its best block and the balance of every account
+  metadata                         show the pallets of the runtime, with their calls and errors
+  benchmark <kind> <n> [<accounts>]
+                                   time the execution of blocks of n random transfers or claims, as
+                                   given by kind, on top of that many funded accounts (1000000 by
+                                   default); build with --release for meaningful timings
+  benchmark weights [<path>]       measure the weight of the calls of the pallets and write them
+                                   to the path (src/weights.rs by default); build with --release
+  rpc <address>                    serve the chain over JSON-RPC (requires the `rpc` feature)
+  network <address> [<peer>...]    listen for peers at the address, connect to the given peers, and
+                                   sync and gossip blocks with them (requires the `network` feature)
+
+Extrinsics are signed by development accounts, given by name, like `alice`. Other accounts can be
+given by name or by SS58 address. On the network, the node authors the blocks on the turns of the
+`--validator` account, which must be a development account.";
+
+/// The number of funded accounts the state is populated with by the `benchmark` command, when they
+/// aren't given.
+pub const DEFAULT_BENCHMARK_ACCOUNTS: u32 = 1_000_000;
+
+/// The file the `benchmark weights` command writes the weights of the calls to, when it isn't
+/// given, which is the `weights.rs` of the pallets from the root of the crate.
+pub const DEFAULT_WEIGHTS_PATH: &str = "src/weights.rs";
+
+/// The directory the node keeps its chain in, when `--base-path` isn't given.
+pub const DEFAULT_BASE_PATH: &str = "chain-data";
+
+/// The command line of the node: its options, and the command to run on the chain.
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+	/// The JSON chain spec of the chain, see `chain_spec`. The development chain is used when it
+	/// isn't given. The chain must always be run with the same chain spec, since its state is
+	/// rebuilt from the genesis.
+	pub chain_spec: Option<PathBuf>,
+	/// The directory holding the blocks of the chain and the extrinsics waiting in its pool.
+	pub base_path: PathBuf,
+	/// The development account whose key the node authors blocks with on the network, when it is
+	/// the turn of that account.
+	pub validator: Option<types::AccountId>,
+	pub command: Command,
+}
+
+/// A command of the node.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+	/// Submit a transfer of `amount` from `from` to `to`.
+	Transfer { from: types::AccountId, to: types::AccountId, amount: types::Balance },
+	/// Submit a claim of `owner` on `content`.
+	CreateClaim { owner: types::AccountId, content: String },
+	/// Submit the revocation of the claim of `owner` on `content`.
+	RevokeClaim { owner: types::AccountId, content: String },
+	/// Author a block out of the transaction pool, and import it.
+	ProduceBlock,
+	/// Show the name of the chain, its best block and the balance of every account.
+	DumpState,
+	/// Show the metadata of the runtime.
+	Metadata,
+	/// Time the execution of blocks of `extrinsics` random extrinsics of `kind`, on top of a state
+	/// with `accounts` funded accounts. It is run without the chain of the node.
+	Benchmark { kind: BlockKind, extrinsics: u32, accounts: u32 },
+	/// Measure the weight of the calls of the pallets, and write them to `output`. It is run
+	/// without the chain of the node.
+	BenchmarkWeights { output: PathBuf },
+	/// Serve the chain over JSON-RPC at `address`.
+	Rpc { address: String },
+	/// Listen for peers at `address`, connect to `peers`, and run the node on the network.
+	Network { address: String, peers: Vec<String> },
+}
+
+impl Cli {
+	/// Parse the arguments of the node, without the name of the program.
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+		let mut chain_spec = None;
+		let mut base_path = PathBuf::from(DEFAULT_BASE_PATH);
+		let mut validator = None;
+		let mut words = Vec::new();
+		let mut args = args.into_iter();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--chain" => chain_spec = Some(PathBuf::from(value_of(&mut args, "--chain")?)),
+				"--base-path" => base_path = PathBuf::from(value_of(&mut args, "--base-path")?),
+				"--validator" => validator = Some(signer(&value_of(&mut args, "--validator")?)?),
+				option if option.starts_with("--") => return Err(format!("unknown option {option}")),
+				_ => words.push(arg),
+			}
+		}
+
+		let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+		let command = match words.as_slice() {
+			["transfer", from, to, amount] => Command::Transfer {
+				from: signer(from)?,
+				to: account(to)?,
+				amount: amount.parse().map_err(|_| format!("invalid amount {amount}"))?,
+			},
+			["claim", "create", owner, content] => {
+				Command::CreateClaim { owner: signer(owner)?, content: content.to_string() }
+			},
+			["claim", "revoke", owner, content] => {
+				Command::RevokeClaim { owner: signer(owner)?, content: content.to_string() }
+			},
+			["block", "produce"] => Command::ProduceBlock,
+			["state", "dump"] => Command::DumpState,
+			["metadata"] => Command::Metadata,
+			["benchmark", "weights"] => Command::BenchmarkWeights { output: PathBuf::from(DEFAULT_WEIGHTS_PATH) },
+			["benchmark", "weights", output] => Command::BenchmarkWeights { output: PathBuf::from(output) },
+			["benchmark", kind, extrinsics, accounts @ ..] if accounts.len() <= 1 => Command::Benchmark {
+				kind: kind.parse()?,
+				extrinsics: extrinsics.parse().map_err(|_| format!("invalid number of extrinsics {extrinsics}"))?,
+				accounts: match accounts {
+					[accounts] => accounts.parse().map_err(|_| format!("invalid number of accounts {accounts}"))?,
+					_ => DEFAULT_BENCHMARK_ACCOUNTS,
+				},
+			},
+			["rpc", address] => Command::Rpc { address: address.to_string() },
+			["network", address, peers @ ..] => {
+				Command::Network { address: address.to_string(), peers: peers.iter().map(|peer| peer.to_string()).collect() }
+			},
+			[] => return Err("missing command".to_string()),
+			_ => return Err(format!("invalid command: {}", words.join(" "))),
+		};
+		Ok(Self { chain_spec, base_path, validator, command })
+	}
+}
+
+/// The value of `option`, which is the next argument.
+fn value_of(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+	args.next().ok_or_else(|| format!("{option} needs a value"))
+}
+
+/// An account which signs an extrinsic, which must be in the development keystore, since only the
+/// keys of the `DEV_ACCOUNTS` are known.
+fn signer(arg: &str) -> Result<types::AccountId, String> {
+	let who = account(arg)?;
+	if !DEV_KEYSTORE.with(|keystore| keystore.contains(&who)) {
+		return Err(format!("{arg} is not a development account, it can't sign extrinsics"));
+	}
+	Ok(who)
+}
+
+/// An account, given by the name of a development account or by its SS58 address.
+fn account(arg: &str) -> Result<types::AccountId, String> {
+	if DEV_ACCOUNTS.contains(&arg) {
+		return Ok(dev_account(arg));
+	}
+	match AccountId32::from_ss58check_with_prefix(arg) {
+		Ok((who, prefix)) if prefix == <Runtime as system::Config>::SS58_PREFIX => Ok(who),
+		Ok(_) => Err(format!("{arg} doesn't have the SS58 prefix of the chain")),
+		Err(_) => Err(format!("{arg} is neither a development account nor an address")),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Cli, Command, DEFAULT_BASE_PATH, DEFAULT_BENCHMARK_ACCOUNTS, DEFAULT_WEIGHTS_PATH};
+	use crate::bench::BlockKind;
+	use crate::dev_account;
+	use std::path::PathBuf;
+
+	fn parse(args: &str) -> Result<Cli, String> {
+		Cli::parse(args.split_whitespace().map(String::from))
+	}
+
+	#[test]
+	fn parse_commands_and_options() {
+		let cli = parse("transfer alice bob 30").unwrap();
+		assert_eq!(cli.chain_spec, None);
+		assert_eq!(cli.base_path, PathBuf::from(DEFAULT_BASE_PATH));
+		assert_eq!(cli.command, Command::Transfer { from: dev_account("alice"), to: dev_account("bob"), amount: 30 });
+
+		// Options can come anywhere, and accounts can be given by address.
+		let bob = dev_account("bob").to_string();
+		let cli = parse(&format!("--chain spec.json transfer alice {bob} 30 --base-path /tmp/node")).unwrap();
+		assert_eq!(cli.chain_spec, Some(PathBuf::from("spec.json")));
+		assert_eq!(cli.base_path, PathBuf::from("/tmp/node"));
+		assert_eq!(cli.command, Command::Transfer { from: dev_account("alice"), to: dev_account("bob"), amount: 30 });
+
+		let create = Command::CreateClaim { owner: dev_account("alice"), content: "my_doc".into() };
+		assert_eq!(parse("claim create alice my_doc").unwrap().command, create);
+		let revoke = Command::RevokeClaim { owner: dev_account("alice"), content: "my_doc".into() };
+		assert_eq!(parse("claim revoke alice my_doc").unwrap().command, revoke);
+		assert_eq!(parse("block produce").unwrap().command, Command::ProduceBlock);
+		assert_eq!(parse("state dump").unwrap().command, Command::DumpState);
+		assert_eq!(parse("metadata").unwrap().command, Command::Metadata);
+		let benchmark = Command::Benchmark { kind: BlockKind::Claims, extrinsics: 100, accounts: DEFAULT_BENCHMARK_ACCOUNTS };
+		assert_eq!(parse("benchmark claims 100").unwrap().command, benchmark);
+		let benchmark = Command::Benchmark { kind: BlockKind::Transfers, extrinsics: 50, accounts: 1_000 };
+		assert_eq!(parse("benchmark transfers 50 1000").unwrap().command, benchmark);
+		let weights = Command::BenchmarkWeights { output: PathBuf::from(DEFAULT_WEIGHTS_PATH) };
+		assert_eq!(parse("benchmark weights").unwrap().command, weights);
+		assert_eq!(parse("rpc 127.0.0.1:9933").unwrap().command, Command::Rpc { address: "127.0.0.1:9933".into() });
+
+		let cli = parse("--validator bob network 127.0.0.1:30333 127.0.0.1:30334 127.0.0.1:30335").unwrap();
+		assert_eq!(cli.validator, Some(dev_account("bob")));
+		let peers = vec!["127.0.0.1:30334".to_string(), "127.0.0.1:30335".to_string()];
+		assert_eq!(cli.command, Command::Network { address: "127.0.0.1:30333".into(), peers });
+		assert_eq!(parse("network 127.0.0.1:30333").unwrap().validator, None);
+	}
+
+	#[test]
+	fn invalid_command_lines_are_refused() {
+		// A valid address, but not of a development account.
+		let stranger = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+		for (args, error) in [
+			("", "missing command"),
+			("block", "invalid command: block"),
+			("transfer alice bob", "invalid command: transfer alice bob"),
+			("transfer alice bob lots", "invalid amount lots"),
+			("transfer alice zoe 1", "zoe is neither a development account nor an address"),
+			(&format!("claim create {stranger} my_doc"), &format!("{stranger} is not a development account, it can't sign extrinsics")),
+			("state dump --chain", "--chain needs a value"),
+			("network 127.0.0.1:30333 --validator zoe", "zoe is neither a development account nor an address"),
+			("state dump --verbose", "unknown option --verbose"),
+			("benchmark votes 10", "invalid kind of block votes, expected transfers or claims"),
+			("benchmark claims 10 1000 5", "invalid command: benchmark claims 10 1000 5"),
+		] {
+			assert_eq!(parse(args).err().as_deref(), Some(error), "{args}");
+		}
+		// An address of another network.
+		let polkadot = "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5";
+		assert_eq!(parse(&format!("transfer alice {polkadot} 1")).err(), Some(format!("{polkadot} doesn't have the SS58 prefix of the chain")));
+	}
+}
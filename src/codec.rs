@@ -0,0 +1,347 @@
+//! A minimal implementation of the SCALE codec, which is how blocks and extrinsics are serialized
+//! on Substrate based chains, so they can be hashed, stored and sent over the network.
+//!
+//! The encoding of each type follows SCALE:
+//! - integers are encoded in little endian, with their fixed size,
+//! - `bool` is a single byte, `0` or `1`,
+//! - `Option<T>` is a `0` byte for `None`, or a `1` byte followed by the value,
+//! - `Vec<T>`, `String` and `str` are prefixed by their length as a compact integer,
+//! - enums are encoded as the index of their variant as a single byte, followed by its fields,
+//! - structs are the concatenation of the encodings of their fields.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+
+/// The vector the values are encoded into. This is used by the code generated by
+/// `#[macros::call]`, so pallets don't need to import `Vec` themselves under `no_std`.
+pub use alloc::vec::Vec;
+
+/// The errors which can happen while decoding a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The input ended before the value was fully decoded.
+	UnexpectedEnd,
+	/// The input doesn't encode a value of the type, like an unknown enum variant.
+	Invalid,
+	/// The input has some bytes left after the value was decoded.
+	TrailingBytes,
+	/// The input was encoded for another version of the type, like an extrinsic encoded for
+	/// another transaction version of the runtime.
+	UnsupportedVersion,
+}
+
+/// The maximum number of calls nested in a decoded value, like a sudo call holding a batch of
+/// calls. Decoding a call recurses once per nested call, so without a limit, a few hundred
+/// kilobytes of nested calls would overflow the stack.
+pub const MAX_CALL_DEPTH: u32 = 256;
+
+/// A type which can be encoded into bytes.
+pub trait Encode {
+	/// Append the encoding of this value to `dest`.
+	fn encode_to(&self, dest: &mut Vec<u8>);
+
+	/// Encode this value into a new vector of bytes.
+	fn encode(&self) -> Vec<u8> {
+		let mut dest = Vec::new();
+		self.encode_to(&mut dest);
+		dest
+	}
+}
+
+/// A type which can be decoded from the bytes produced by its `Encode` implementation.
+pub trait Decode: Sized {
+	/// Decode a value from the start of `input`, advancing `input` past the decoded bytes.
+	fn decode(input: &mut &[u8]) -> Result<Self, Error>;
+
+	/// Decode a value from the start of `input`, like `decode`, with at most `depth` calls nested
+	/// in it. Only calls count towards the depth, so types which can't hold a call don't need to
+	/// implement this, and the containers of other values pass `depth` on to them.
+	fn decode_with_depth(input: &mut &[u8], depth: u32) -> Result<Self, Error> {
+		let _ = depth;
+		Self::decode(input)
+	}
+
+	/// Decode a value from `input`, which must contain exactly one encoded value.
+	fn decode_all(mut input: &[u8]) -> Result<Self, Error> {
+		let value = Self::decode(&mut input)?;
+		if !input.is_empty() {
+			return Err(Error::TrailingBytes);
+		}
+		Ok(value)
+	}
+}
+
+/// Take the first `len` bytes of `input`, advancing `input` past them.
+pub fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+	if input.len() < len {
+		return Err(Error::UnexpectedEnd);
+	}
+	let (bytes, rest) = input.split_at(len);
+	*input = rest;
+	Ok(bytes)
+}
+
+/// Append the compact encoding of `value` to `dest`. Small values, like most lengths, take fewer
+/// bytes than their fixed size encoding.
+pub fn encode_compact(value: u64, dest: &mut Vec<u8>) {
+	match value {
+		0..=0x3f => dest.push((value as u8) << 2),
+		0x40..=0x3fff => dest.extend_from_slice(&(((value as u16) << 2) | 0b01).to_le_bytes()),
+		0x4000..=0x3fff_ffff => dest.extend_from_slice(&(((value as u32) << 2) | 0b10).to_le_bytes()),
+		_ => {
+			let bytes = value.to_le_bytes();
+			let len = bytes.len() - bytes.iter().rev().take_while(|byte| **byte == 0).count();
+			dest.push((((len - 4) as u8) << 2) | 0b11);
+			dest.extend_from_slice(&bytes[..len]);
+		},
+	}
+}
+
+/// Decode a compact integer from the start of `input`, advancing `input` past it.
+pub fn decode_compact(input: &mut &[u8]) -> Result<u64, Error> {
+	let first = take(input, 1)?[0];
+	match first & 0b11 {
+		0b00 => Ok(u64::from(first >> 2)),
+		0b01 => {
+			let second = take(input, 1)?[0];
+			Ok(u64::from(u16::from_le_bytes([first, second]) >> 2))
+		},
+		0b10 => {
+			let rest = take(input, 3)?;
+			Ok(u64::from(u32::from_le_bytes([first, rest[0], rest[1], rest[2]]) >> 2))
+		},
+		_ => {
+			let len = usize::from(first >> 2) + 4;
+			if len > 8 {
+				return Err(Error::Invalid);
+			}
+			let mut bytes = [0u8; 8];
+			bytes[..len].copy_from_slice(take(input, len)?);
+			Ok(u64::from_le_bytes(bytes))
+		},
+	}
+}
+
+/// Decode a length prefix, which is a compact integer.
+fn decode_len(input: &mut &[u8]) -> Result<usize, Error> {
+	usize::try_from(decode_compact(input)?).map_err(|_| Error::Invalid)
+}
+
+/// Integers are encoded in little endian, with their fixed size.
+macro_rules! impl_codec_for_int {
+	($($int:ty),*) => {$(
+		impl Encode for $int {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				dest.extend_from_slice(&self.to_le_bytes());
+			}
+		}
+
+		impl Decode for $int {
+			fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+				let bytes = take(input, core::mem::size_of::<$int>())?;
+				Ok(<$int>::from_le_bytes(bytes.try_into().map_err(|_| Error::Invalid)?))
+			}
+		}
+	)*};
+}
+
+impl_codec_for_int!(u8, u16, u32, u64, u128);
+
+impl Encode for bool {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(u8::from(*self));
+	}
+}
+
+impl Decode for bool {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		match u8::decode(input)? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err(Error::Invalid),
+		}
+	}
+}
+
+/// Fixed size arrays, like hashes, are encoded without a length prefix.
+impl<const N: usize> Encode for [u8; N] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.extend_from_slice(self);
+	}
+}
+
+impl<const N: usize> Decode for [u8; N] {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		take(input, N)?.try_into().map_err(|_| Error::Invalid)
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			None => dest.push(0),
+			Some(value) => {
+				dest.push(1);
+				value.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		Self::decode_with_depth(input, MAX_CALL_DEPTH)
+	}
+
+	fn decode_with_depth(input: &mut &[u8], depth: u32) -> Result<Self, Error> {
+		match u8::decode(input)? {
+			0 => Ok(None),
+			1 => Ok(Some(T::decode_with_depth(input, depth)?)),
+			_ => Err(Error::Invalid),
+		}
+	}
+}
+
+/// Tuples are encoded like structs, as the concatenation of the encodings of their items.
+impl<A: Encode, B: Encode> Encode for (A, B) {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+		self.1.encode_to(dest);
+	}
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		Self::decode_with_depth(input, MAX_CALL_DEPTH)
+	}
+
+	fn decode_with_depth(input: &mut &[u8], depth: u32) -> Result<Self, Error> {
+		Ok((A::decode_with_depth(input, depth)?, B::decode_with_depth(input, depth)?))
+	}
+}
+
+impl<T: Encode> Encode for [T] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		encode_compact(self.len() as u64, dest);
+		for item in self {
+			item.encode_to(dest);
+		}
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_slice().encode_to(dest);
+	}
+}
+
+impl<T: Decode> Decode for Vec<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		Self::decode_with_depth(input, MAX_CALL_DEPTH)
+	}
+
+	fn decode_with_depth(input: &mut &[u8], depth: u32) -> Result<Self, Error> {
+		let len = decode_len(input)?;
+		// The length comes from the input, so it is not trusted to preallocate the vector.
+		let mut items = Vec::with_capacity(len.min(input.len()));
+		for _ in 0..len {
+			items.push(T::decode_with_depth(input, depth)?);
+		}
+		Ok(items)
+	}
+}
+
+impl Encode for str {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		encode_compact(self.len() as u64, dest);
+		dest.extend_from_slice(self.as_bytes());
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest);
+	}
+}
+
+/// A boxed value is encoded like the value itself, which lets recursive types like a call holding
+/// another call be encoded.
+impl<T: Encode + ?Sized> Encode for Box<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest);
+	}
+}
+
+impl<T: Decode> Decode for Box<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		T::decode(input).map(Box::new)
+	}
+
+	fn decode_with_depth(input: &mut &[u8], depth: u32) -> Result<Self, Error> {
+		T::decode_with_depth(input, depth).map(Box::new)
+	}
+}
+
+impl Encode for String {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_str().encode_to(dest);
+	}
+}
+
+impl Decode for String {
+	fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+		let len = decode_len(input)?;
+		let bytes = take(input, len)?;
+		String::from_utf8(bytes.to_vec()).map_err(|_| Error::Invalid)
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{decode_compact, encode_compact, Decode, Encode, Error};
+
+	#[test]
+	fn compact_integers_use_the_smallest_mode() {
+		let compact = |value| {
+			let mut dest = Vec::new();
+			encode_compact(value, &mut dest);
+			dest
+		};
+		assert_eq!(compact(0), [0x00]);
+		assert_eq!(compact(1), [0x04]);
+		assert_eq!(compact(63), [0xfc]);
+		assert_eq!(compact(64), [0x01, 0x01]);
+		assert_eq!(compact(16_383), [0xfd, 0xff]);
+		assert_eq!(compact(16_384), [0x02, 0x00, 0x01, 0x00]);
+		assert_eq!(compact(1 << 30), [0x03, 0x00, 0x00, 0x00, 0x40]);
+		assert_eq!(compact(u64::MAX), [0x13, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+
+		for value in [0, 1, 63, 64, 16_383, 16_384, 1 << 30, u64::MAX] {
+			assert_eq!(decode_compact(&mut compact(value).as_slice()), Ok(value));
+		}
+	}
+
+	#[test]
+	fn values_roundtrip() {
+		let value: (u32, Option<u128>, Vec<(String, u8)>) =
+			(7, Some(1_000), vec![("alice".to_string(), 1), ("bob".to_string(), 2)]);
+		let mut bytes = value.0.encode();
+		value.1.encode_to(&mut bytes);
+		value.2.encode_to(&mut bytes);
+		assert_eq!(bytes[..5], [7, 0, 0, 0, 1]);
+
+		let mut input = bytes.as_slice();
+		assert_eq!(u32::decode(&mut input), Ok(value.0));
+		assert_eq!(Option::<u128>::decode(&mut input), Ok(value.1));
+		assert_eq!(Vec::<(String, u8)>::decode(&mut input), Ok(value.2));
+		assert!(input.is_empty());
+	}
+
+	#[test]
+	fn decoding_rejects_invalid_input() {
+		assert_eq!(u32::decode_all(&[1, 2, 3]), Err(Error::UnexpectedEnd));
+		assert_eq!(u8::decode_all(&[1, 2]), Err(Error::TrailingBytes));
+		assert_eq!(bool::decode_all(&[2]), Err(Error::Invalid));
+		assert_eq!(String::decode_all(&[0x04, 0xff]), Err(Error::Invalid));
+	}
+}
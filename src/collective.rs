@@ -0,0 +1,392 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::codec::Encode;
+use crate::storage::{storage_key, storage_map_key, StorageBackend};
+use crate::support::{
+	ensure_root, ActualWeight, DispatchCall, DispatchError, DispatchResult, Events, OriginFor, RawOrigin,
+	StorageMap, StorageValue, Weight,
+};
+
+pub trait Config: crate::system::Config {
+	/// The calls which the members can propose, which is usually the `RuntimeCall` generated by
+	/// `#[macros::runtime]`.
+	type RuntimeCall: Clone;
+	/// The maximum number of members.
+	const MAX_MEMBERS: u32;
+	/// The maximum weight of a proposed call. The call is dispatched by the vote which approves it,
+	/// so every vote pays for this weight, and is refunded what the call didn't use.
+	const MAX_PROPOSAL_WEIGHT: Weight;
+	/// The weight of `call`.
+	fn call_weight(call: &Self::RuntimeCall) -> Weight;
+}
+
+/// A call proposed by a member, waiting for enough members to approve it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proposal<AccountId, Call> {
+	/// The call, which is dispatched with the collective origin once it is approved.
+	pub call: Call,
+	/// The number of members who must approve the call.
+	pub threshold: u32,
+	/// The members who approved the call, including the proposer.
+	pub ayes: Vec<AccountId>,
+	/// The members who voted against the call.
+	pub nays: Vec<AccountId>,
+}
+
+/// The events emitted by the collective pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId> {
+	/// A member proposed a call, which `threshold` members must approve.
+	Proposed { account: AccountId, proposal_index: u32, threshold: u32 },
+	/// A member voted on a proposal, which now has `yes` approvals and `no` rejections.
+	Voted { account: AccountId, proposal_index: u32, approve: bool, yes: u32, no: u32 },
+	/// A proposal was approved, and its call was dispatched with this result.
+	Executed { proposal_index: u32, result: DispatchResult },
+	/// A proposal was rejected by too many members to ever be approved.
+	Disapproved { proposal_index: u32 },
+	/// The members were replaced.
+	MembersChanged { members: Vec<AccountId> },
+}
+
+/// The errors returned by the collective pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The caller isn't a member.
+	NotMember,
+	/// There is no proposal with this index.
+	ProposalMissing,
+	/// The member already voted this way on the proposal.
+	DuplicateVote,
+	/// There are more than `Config::MAX_MEMBERS` members.
+	TooManyMembers,
+	/// The threshold is zero, or more than the number of members.
+	WrongThreshold,
+	/// The proposed call is heavier than `Config::MAX_PROPOSAL_WEIGHT`.
+	WrongProposalWeight,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::NotMember => "NotMember",
+			Error::ProposalMissing => "ProposalMissing",
+			Error::DuplicateVote => "DuplicateVote",
+			Error::TooManyMembers => "TooManyMembers",
+			Error::WrongThreshold => "WrongThreshold",
+			Error::WrongProposalWeight => "WrongProposalWeight",
+		};
+		DispatchError::Module { pallet: "collective", error }
+	}
+}
+
+/// The events of the collective pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// The runtime `T`, through which the approved calls are dispatched.
+pub type RuntimeOf<T> = dyn DispatchCall<OriginFor<T>, <T as Config>::RuntimeCall>;
+
+/// Check that `origin` is the collective origin of at least `n` out of every `d` members, or fail
+/// with `DispatchError::BadOrigin`.
+pub fn ensure_proportion_at_least<AccountId>(origin: RawOrigin<AccountId>, n: u32, d: u32) -> DispatchResult {
+	match origin {
+		RawOrigin::Collective { yes, members } if u64::from(yes) * u64::from(d) >= u64::from(n) * u64::from(members) => {
+			Ok(())
+		},
+		_ => Err(DispatchError::BadOrigin),
+	}
+}
+
+/// This is the Collective Module.
+/// It allows a set of members, like a council, to propose calls and vote on them. Once enough
+/// members approve a call, it is dispatched with `RawOrigin::Collective`, which tells the pallet
+/// of the call how many of the members approved it, see `ensure_proportion_at_least`.
+///
+/// Like the utility pallet, the calls are dispatched through the runtime itself, see
+/// `#[dispatch_context(self)]` in `#[macros::runtime]`.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The members, in order.
+	members: StorageValue<Vec<T::AccountId>>,
+	/// The index which will be assigned to the next proposal.
+	next_proposal_index: StorageValue<u32>,
+	/// The proposals which were neither approved nor rejected yet, keyed by their index.
+	proposals: StorageMap<u32, Proposal<T::AccountId, T::RuntimeCall>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Propose `call`, which is dispatched once `threshold` members approve it. The `caller`
+	/// approves it right away, so with a threshold of 1 it is dispatched at once.
+	/// The index of the proposal is returned.
+	/// This function will return an error if the caller isn't a member, if the threshold is zero
+	/// or more than the number of members, or if the call is too heavy.
+	#[call_index = 0]
+	#[weight = 5_000u64.saturating_add(T::call_weight(call))]
+	pub fn propose(
+		&mut self,
+		caller: T::AccountId,
+		threshold: u32,
+		call: Box<T::RuntimeCall>,
+		runtime: &mut RuntimeOf<T>,
+	) -> Result<u32, DispatchError> {
+		self.ensure_member(&caller)?;
+		if threshold == 0 || threshold > self.members.len() as u32 {
+			return Err(Error::WrongThreshold.into());
+		}
+		if T::call_weight(&call) > T::MAX_PROPOSAL_WEIGHT {
+			return Err(Error::WrongProposalWeight.into());
+		}
+		let proposal_index = *self.next_proposal_index;
+		self.next_proposal_index.set(proposal_index.checked_add(1).ok_or("Proposal index overflow")?);
+
+		let proposal = Proposal { call: *call, threshold, ayes: Vec::from([caller.clone()]), nays: Vec::new() };
+		self.proposals.insert(proposal_index, proposal);
+		self.deposit_event(Event::Proposed { account: caller, proposal_index, threshold });
+		self.try_close(proposal_index, runtime);
+		Ok(proposal_index)
+	}
+
+	/// Vote for the proposal `proposal_index` if `approve`, or against it otherwise, replacing any
+	/// vote the `caller` made before. The proposal is dispatched as soon as enough members approve
+	/// it, and dropped as soon as too many members reject it for it to ever be approved.
+	/// The weight of the call is refunded unless this vote dispatched it.
+	/// This function will return an error if the caller isn't a member, if the proposal does not
+	/// exist, or if the caller already voted the same way.
+	#[call_index = 1]
+	#[weight = 2_000u64.saturating_add(T::MAX_PROPOSAL_WEIGHT)]
+	pub fn vote(
+		&mut self,
+		caller: T::AccountId,
+		proposal_index: u32,
+		approve: bool,
+		runtime: &mut RuntimeOf<T>,
+	) -> Result<ActualWeight, DispatchError> {
+		self.ensure_member(&caller)?;
+		let proposal = self.proposals.get_mut(&proposal_index).ok_or(Error::ProposalMissing)?;
+		let (votes, other_votes) =
+			if approve { (&mut proposal.ayes, &mut proposal.nays) } else { (&mut proposal.nays, &mut proposal.ayes) };
+		if votes.contains(&caller) {
+			return Err(Error::DuplicateVote.into());
+		}
+		other_votes.retain(|who| *who != caller);
+		votes.push(caller.clone());
+
+		let (yes, no) = (proposal.ayes.len() as u32, proposal.nays.len() as u32);
+		self.deposit_event(Event::Voted { account: caller, proposal_index, approve, yes, no });
+		let call_weight = self.try_close(proposal_index, runtime);
+		Ok(ActualWeight(2_000u64.saturating_add(call_weight)))
+	}
+
+	/// Replace the members with `members`. The votes of the members who leave are kept on the
+	/// proposals they voted on. This can only be called by the root origin.
+	/// This function will return an error if there are too many members.
+	#[call_index = 2]
+	#[weight = 10_000]
+	pub fn set_members(&mut self, origin: OriginFor<T>, members: Vec<T::AccountId>) -> DispatchResult {
+		ensure_root(origin)?;
+		self.do_set_members(members)?;
+		self.deposit_event(Event::MembersChanged { members: (*self.members).clone() });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Collective Module, without members.
+	pub fn new() -> Self {
+		Self {
+			members: StorageValue::new(Vec::new()),
+			next_proposal_index: StorageValue::new(0),
+			proposals: StorageMap::new(),
+			events: Events::new(),
+		}
+	}
+
+	/// Get the members, in order.
+	pub fn members(&self) -> &[T::AccountId] {
+		&self.members
+	}
+
+	/// Get the proposal `proposal_index`, if it was neither approved nor rejected yet.
+	pub fn proposal(&self, proposal_index: u32) -> Option<&Proposal<T::AccountId, T::RuntimeCall>> {
+		self.proposals.get(&proposal_index)
+	}
+
+	/// Check that `who` is a member.
+	fn ensure_member(&self, who: &T::AccountId) -> Result<(), Error> {
+		if self.members.binary_search(who).is_err() {
+			return Err(Error::NotMember);
+		}
+		Ok(())
+	}
+
+	/// Replace the members with `members`, sorted and without duplicates.
+	fn do_set_members(&mut self, mut members: Vec<T::AccountId>) -> Result<(), Error> {
+		members.sort();
+		members.dedup();
+		if members.len() > T::MAX_MEMBERS as usize {
+			return Err(Error::TooManyMembers);
+		}
+		self.members.set(members);
+		Ok(())
+	}
+
+	/// Dispatch the call of the proposal `proposal_index` with the collective origin if enough
+	/// members approved it, or drop the proposal if too many members rejected it for it to ever be
+	/// approved. Returns the weight of the call if it was dispatched, and zero otherwise.
+	fn try_close(&mut self, proposal_index: u32, runtime: &mut RuntimeOf<T>) -> Weight {
+		let Some(proposal) = self.proposals.get(&proposal_index) else { return 0 };
+		let members = self.members.len() as u32;
+		let (yes, no) = (proposal.ayes.len() as u32, proposal.nays.len() as u32);
+
+		if yes >= proposal.threshold {
+			let Some(proposal) = self.proposals.remove(&proposal_index) else { return 0 };
+			let weight = T::call_weight(&proposal.call);
+			let result = runtime.dispatch_call(RawOrigin::Collective { yes, members }, proposal.call).map(|_| ());
+			self.deposit_event(Event::Executed { proposal_index, result });
+			weight
+		} else {
+			if members.saturating_sub(no) < proposal.threshold {
+				self.proposals.remove(&proposal_index);
+				self.deposit_event(Event::Disapproved { proposal_index });
+			}
+			0
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::RuntimeCall: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		state.insert(storage_key("collective", "members"), self.members.encode());
+		state.insert(storage_key("collective", "next_proposal_index"), self.next_proposal_index.encode());
+		for (index, proposal) in &self.proposals {
+			let value = ((&proposal.call, proposal.threshold), (&proposal.ayes, &proposal.nays)).encode();
+			state.insert(storage_map_key("collective", "proposals", index), value);
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The initial state of the Collective Module, see `RuntimeGenesisConfig` of `#[macros::runtime]`.
+pub struct GenesisConfig<T: Config> {
+	/// The members at genesis.
+	pub members: Vec<T::AccountId>,
+}
+
+impl<T: Config> GenesisConfig<T> {
+	/// Set the members, like `set_members` does. This fails if there are too many members.
+	pub fn build(&self, collective: &mut Pallet<T>) -> DispatchResult {
+		collective.do_set_members(self.members.clone())?;
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { members: Vec::new() }
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{ensure_proportion_at_least, Error, Event, GenesisConfig};
+	use crate::support::{DispatchCall, DispatchError, DispatchResult, DispatchResultWithOutput, RawOrigin};
+
+	/// A test runtime, which records the origins of the calls it dispatched.
+	#[derive(Default)]
+	struct TestRuntime {
+		dispatched: Vec<(RawOrigin<&'static str>, &'static str)>,
+	}
+
+	impl DispatchCall<RawOrigin<&'static str>, &'static str> for TestRuntime {
+		fn dispatch_call(&mut self, origin: RawOrigin<&'static str>, call: &'static str) -> DispatchResultWithOutput {
+			self.dispatched.push((origin, call));
+			Ok(None)
+		}
+
+		fn transactional(
+			&mut self,
+			f: &mut dyn FnMut(&mut dyn DispatchCall<RawOrigin<&'static str>, &'static str>) -> DispatchResult,
+		) -> DispatchResult {
+			f(self)
+		}
+	}
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		type RuntimeCall = &'static str;
+		const MAX_MEMBERS: u32 = 3;
+		const MAX_PROPOSAL_WEIGHT: crate::support::Weight = 1_000;
+		fn call_weight(_call: &Self::RuntimeCall) -> crate::support::Weight {
+			1_000
+		}
+	}
+
+	fn new_collective() -> super::Pallet<TestConfig> {
+		let mut collective = super::Pallet::new();
+		let genesis = GenesisConfig { members: vec!["charlie", "alice", "bob"] };
+		assert_eq!(genesis.build(&mut collective), Ok(()));
+		collective
+	}
+
+	#[test]
+	fn approved_proposals_dispatch_with_the_collective_origin() {
+		let mut collective = new_collective();
+		let mut runtime = TestRuntime::default();
+		assert_eq!(collective.members(), ["alice", "bob", "charlie"]);
+
+		assert_eq!(collective.propose("dave", 2, Box::new("spend"), &mut runtime), Err(Error::NotMember.into()));
+		assert_eq!(collective.propose("alice", 4, Box::new("spend"), &mut runtime), Err(Error::WrongThreshold.into()));
+		assert_eq!(collective.propose("alice", 2, Box::new("spend"), &mut runtime), Ok(0));
+		assert_eq!(collective.vote("alice", 0, true, &mut runtime), Err(Error::DuplicateVote.into()));
+		assert!(runtime.dispatched.is_empty());
+
+		assert!(collective.vote("bob", 0, true, &mut runtime).is_ok());
+		assert_eq!(runtime.dispatched, [(RawOrigin::Collective { yes: 2, members: 3 }, "spend")]);
+		assert_eq!(collective.proposal(0), None);
+		let executed = Event::Executed { proposal_index: 0, result: Ok(()) };
+		assert_eq!(collective.events().last().map(|record| &record.event), Some(&executed));
+
+		// The collective origin tells how many of the members approved the call.
+		let origin = || RawOrigin::<&str>::Collective { yes: 2, members: 3 };
+		assert_eq!(ensure_proportion_at_least(origin(), 1, 2), Ok(()));
+		assert_eq!(ensure_proportion_at_least(origin(), 3, 4), Err(DispatchError::BadOrigin));
+		assert_eq!(ensure_proportion_at_least(RawOrigin::<&str>::Root, 1, 2), Err(DispatchError::BadOrigin));
+	}
+
+	#[test]
+	fn proposals_which_cannot_pass_are_disapproved() {
+		let mut collective = new_collective();
+		let mut runtime = TestRuntime::default();
+
+		assert_eq!(collective.propose("alice", 3, Box::new("spend"), &mut runtime), Ok(0));
+		// A member can change their vote.
+		assert!(collective.vote("bob", 0, true, &mut runtime).is_ok());
+		assert!(collective.vote("bob", 0, false, &mut runtime).is_ok());
+		assert_eq!(collective.proposal(0), None);
+		assert_eq!(collective.events().last().map(|record| &record.event), Some(&Event::Disapproved { proposal_index: 0 }));
+		assert_eq!(collective.vote("charlie", 0, true, &mut runtime), Err(Error::ProposalMissing.into()));
+		assert!(runtime.dispatched.is_empty());
+
+		assert_eq!(collective.set_members(RawOrigin::Signed("alice"), vec!["alice"]), Err(DispatchError::BadOrigin));
+		let too_many = vec!["alice", "bob", "charlie", "dave"];
+		assert_eq!(collective.set_members(RawOrigin::Root, too_many), Err(Error::TooManyMembers.into()));
+		assert_eq!(collective.set_members(RawOrigin::Root, vec!["dave"]), Ok(()));
+		assert_eq!(collective.propose("alice", 1, Box::new("spend"), &mut runtime), Err(Error::NotMember.into()));
+	}
+}
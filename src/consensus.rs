@@ -0,0 +1,156 @@
+use std::fmt;
+
+use crate::codec::{Decode, Encode};
+use crate::crypto::Signature;
+use crate::keyring::Keystore;
+use crate::support::{ConsensusEngineId, DigestItem};
+use crate::{types, Runtime};
+
+/// The consensus engine id of Aura, which tags its digest items.
+pub const AURA_ENGINE_ID: ConsensusEngineId = *b"aura";
+
+/// The reasons a block can't be sealed, or its seal is refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsensusError {
+	/// The chain has no authorities, so nobody can author blocks.
+	NoAuthorities,
+	/// The key of the authority which must author the block isn't in the keystore.
+	MissingKey(types::AccountId),
+	/// The header doesn't name its author in a pre-runtime digest of Aura.
+	MissingAuthor,
+	/// The header names another author than the authority whose turn it is.
+	WrongAuthor { expected: types::AccountId, found: types::AccountId },
+	/// The header isn't sealed by Aura.
+	MissingSeal,
+	/// The seal isn't a signature of the header by its author.
+	BadSeal,
+}
+
+impl fmt::Display for ConsensusError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ConsensusError::NoAuthorities => write!(f, "the chain has no authorities"),
+			ConsensusError::MissingKey(author) => write!(f, "the key of the authority {author} is not in the keystore"),
+			ConsensusError::MissingAuthor => write!(f, "the header does not name its author"),
+			ConsensusError::WrongAuthor { expected, found } => {
+				write!(f, "the block must be authored by {expected}, not {found}")
+			},
+			ConsensusError::MissingSeal => write!(f, "the header is not sealed"),
+			ConsensusError::BadSeal => write!(f, "the seal is not signed by the author"),
+		}
+	}
+}
+
+/// The authority which must author the block with `block_number` on top of `parent`.
+///
+/// This is a round-robin proof of authority, like Aura with one slot per block: the authorities
+/// of the aura pallet in the state of the parent take turns, in order, by block number.
+pub fn slot_author(parent: &Runtime, block_number: types::BlockNumber) -> Result<types::AccountId, ConsensusError> {
+	let authorities = parent.aura.authorities();
+	if authorities.is_empty() {
+		return Err(ConsensusError::NoAuthorities);
+	}
+	Ok(authorities[block_number as usize % authorities.len()].clone())
+}
+
+/// Seal `block`, which was built on top of `parent`, with the key of the authority whose turn it
+/// is, taken from `keystore`.
+///
+/// The header gets a pre-runtime digest naming its author, then the seal, which is the signature
+/// of the author over the hash of the header with that digest.
+pub fn seal_block(parent: &Runtime, block: &mut types::Block, keystore: &Keystore) -> Result<(), ConsensusError> {
+	let author = slot_author(parent, block.header.block_number)?;
+	let mut header = block.header.clone();
+	header.digest.push(DigestItem::PreRuntime(AURA_ENGINE_ID, author.encode()));
+	let signature = keystore.sign(&author, &header.hash()).ok_or(ConsensusError::MissingKey(author))?;
+	header.digest.push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+	block.header = header;
+	Ok(())
+}
+
+/// Check that `header`, of a block built on top of `parent`, was sealed by the authority whose
+/// turn it is, and return that authority.
+pub fn verify_header(parent: &Runtime, header: &types::Header) -> Result<types::AccountId, ConsensusError> {
+	let mut pre_header = header.clone();
+	let Some(DigestItem::Seal(AURA_ENGINE_ID, seal)) = pre_header.digest.pop() else {
+		return Err(ConsensusError::MissingSeal);
+	};
+	let found = pre_header
+		.digest
+		.iter()
+		.find_map(|item| match item {
+			DigestItem::PreRuntime(AURA_ENGINE_ID, author) => types::AccountId::decode_all(author).ok(),
+			_ => None,
+		})
+		.ok_or(ConsensusError::MissingAuthor)?;
+	let expected = slot_author(parent, header.block_number)?;
+	if found != expected {
+		return Err(ConsensusError::WrongAuthor { expected, found });
+	}
+
+	let signature = Signature::decode_all(&seal).map_err(|_| ConsensusError::BadSeal)?;
+	let public = parent.system.public_key(&expected).ok_or(ConsensusError::BadSeal)?;
+	if !public.verify(&pre_header.hash(), &signature) {
+		return Err(ConsensusError::BadSeal);
+	}
+	Ok(expected)
+}
+
+#[cfg(test)]
+mod test {
+	use super::{seal_block, slot_author, verify_header, ConsensusError, AURA_ENGINE_ID};
+	use crate::block_builder::BlockBuilder;
+	use crate::codec::Encode;
+	use crate::keyring::Keystore;
+	use crate::support::DigestItem;
+	use crate::{dev_account, development_genesis, Runtime, DEV_ACCOUNTS};
+
+	fn genesis() -> Runtime {
+		let mut runtime = Runtime::new();
+		development_genesis().build(&mut runtime).unwrap();
+		runtime
+	}
+
+	#[test]
+	fn authorities_take_turns_by_block_number() {
+		let runtime = genesis();
+		let authors = (1..=4).map(|block_number| slot_author(&runtime, block_number).unwrap()).collect::<Vec<_>>();
+		assert_eq!(authors, ["bob", "charlie", "alice", "bob"].map(dev_account));
+		assert_eq!(slot_author(&Runtime::new(), 1), Err(ConsensusError::NoAuthorities));
+	}
+
+	#[test]
+	fn only_the_authority_whose_turn_it_is_can_seal() {
+		let runtime = genesis();
+		let block = BlockBuilder::new(&runtime, runtime.system.max_block_weight()).seal(vec![]).unwrap();
+		assert_eq!(verify_header(&runtime, &block.header), Err(ConsensusError::MissingSeal));
+
+		let mut sealed = block.clone();
+		seal_block(&runtime, &mut sealed, &Keystore::with_dev_accounts(DEV_ACCOUNTS)).unwrap();
+		assert_eq!(verify_header(&runtime, &sealed.header), Ok(dev_account("bob")));
+		// The block is executed like any other block.
+		assert!(runtime.clone().execute_block(&sealed).is_ok());
+
+		// Without Bob's key, the block can't be sealed.
+		let mut unsealed = block.clone();
+		let error = seal_block(&runtime, &mut unsealed, &Keystore::with_dev_accounts(["alice"]));
+		assert_eq!(error, Err(ConsensusError::MissingKey(dev_account("bob"))));
+		assert!(unsealed.header.digest.is_empty());
+
+		// Alice claims to author the block, and signs it with her own key.
+		let mut forged = block.clone();
+		forged.header.digest.push(DigestItem::PreRuntime(AURA_ENGINE_ID, dev_account("alice").encode()));
+		let signature = Keystore::with_dev_accounts(["alice"]).sign(&dev_account("alice"), &forged.header.hash()).unwrap();
+		forged.header.digest.push(DigestItem::Seal(AURA_ENGINE_ID, signature.encode()));
+		let expected = Err(ConsensusError::WrongAuthor { expected: dev_account("bob"), found: dev_account("alice") });
+		assert_eq!(verify_header(&runtime, &forged.header), expected);
+
+		// The seal covers the whole header.
+		let mut tampered = sealed.clone();
+		tampered.header.state_root = [0; 32];
+		assert_eq!(verify_header(&runtime, &tampered.header), Err(ConsensusError::BadSeal));
+		let mut anonymous = sealed.clone();
+		anonymous.header.digest.remove(0);
+		assert_eq!(verify_header(&runtime, &anonymous.header), Err(ConsensusError::MissingAuthor));
+	}
+}
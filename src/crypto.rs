@@ -0,0 +1,736 @@
+//! A minimal implementation of Ed25519 signatures, as specified by RFC 8032, which accounts use to
+//! sign their extrinsics, and of account ids made of public keys, shown in the SS58 format.
+//!
+//! The usual crates for Ed25519 can't be fetched in this build environment, so the curve
+//! arithmetic is implemented here on top of the SHA-512 of `sha2`. It is written for clarity
+//! rather than speed, and it is not constant time, so it must not be used to sign with secret keys
+//! which matter outside of this project.
+
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+
+use sha2::{Digest, Sha512};
+
+use crate::codec::{self, Decode, Encode, Vec};
+use crate::hashing::blake2b_512;
+
+/// The public key of an account, which is the encoding of a point of the curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Public(pub [u8; 32]);
+
+/// A signature of a message, made by the secret key matching a `Public` key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature(pub [u8; 64]);
+
+/// A key pair, which signs messages with its secret key.
+#[derive(Clone)]
+pub struct Pair {
+	/// The secret scalar derived from the seed.
+	secret: [u8; 32],
+	/// The second half of the hash of the seed, which is mixed into the nonce of each signature.
+	prefix: [u8; 32],
+	public: Public,
+}
+
+impl Pair {
+	/// Derive the key pair of a 32 bytes secret seed.
+	pub fn from_seed(seed: &[u8; 32]) -> Self {
+		let hash = Sha512::digest(seed);
+		let mut secret = [0u8; 32];
+		secret.copy_from_slice(&hash[..32]);
+		secret[0] &= 248;
+		secret[31] &= 127;
+		secret[31] |= 64;
+		let mut prefix = [0u8; 32];
+		prefix.copy_from_slice(&hash[32..]);
+
+		let curve = Curve::new();
+		let public = Public(curve.base.mul(&secret, &curve).encode());
+		Self { secret, prefix, public }
+	}
+
+	/// The public key of this pair.
+	pub fn public(&self) -> Public {
+		self.public
+	}
+
+	/// Sign `message` with the secret key of this pair.
+	pub fn sign(&self, message: &[u8]) -> Signature {
+		let curve = Curve::new();
+		let r = reduce_scalar(&Sha512::new().chain_update(self.prefix).chain_update(message).finalize());
+		let big_r = curve.base.mul(&scalar_to_bytes(&r), &curve).encode();
+		let k = challenge(&big_r, &self.public, message);
+		let s = mul_add_scalar(&k, &bytes_to_scalar(&self.secret), &r);
+
+		let mut signature = [0u8; 64];
+		signature[..32].copy_from_slice(&big_r);
+		signature[32..].copy_from_slice(&scalar_to_bytes(&s));
+		Signature(signature)
+	}
+}
+
+impl Public {
+	/// Check that `signature` is a signature of `message` by the secret key of this public key.
+	pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		let curve = Curve::new();
+		let mut big_r = [0u8; 32];
+		big_r.copy_from_slice(&signature.0[..32]);
+		let mut s = [0u8; 32];
+		s.copy_from_slice(&signature.0[32..]);
+		// The scalar of the signature must be reduced, otherwise signatures would be malleable.
+		if !is_canonical_scalar(&s) {
+			return false;
+		}
+		let (Some(r_point), Some(a_point)) = (Point::decode(&big_r, &curve), Point::decode(&self.0, &curve)) else {
+			return false;
+		};
+
+		let k = challenge(&big_r, self, message);
+		let expected = r_point.add(&a_point.mul(&scalar_to_bytes(&k), &curve), &curve);
+		curve.base.mul(&s, &curve).encode() == expected.encode()
+	}
+}
+
+impl Encode for Public {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+	}
+}
+
+impl Decode for Public {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		Ok(Self(Decode::decode(input)?))
+	}
+}
+
+impl Encode for Signature {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+	}
+}
+
+impl Decode for Signature {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		Ok(Self(Decode::decode(input)?))
+	}
+}
+
+/// The SS58 prefix of the accounts of generic chains, which is used when no other prefix is given.
+pub const DEFAULT_SS58_PREFIX: u16 = 42;
+
+/// The base58 alphabet of Bitcoin, used by SS58 addresses.
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// The id of an account, which is the 32 bytes of its public key.
+///
+/// Account ids are shown to users as SS58 addresses: the base58 encoding of a network prefix, the
+/// public key, and a checksum. The prefix tells which chain an address is meant for, so it can't be
+/// pasted into another chain by mistake.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountId32(pub [u8; 32]);
+
+/// The errors which can occur when parsing an SS58 address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ss58Error {
+	/// The address contains a character which is not in the base58 alphabet.
+	BadBase58,
+	/// The address doesn't decode to a prefix, 32 bytes and a checksum.
+	BadLength,
+	/// The prefix is one of the reserved prefixes, which no address uses.
+	InvalidPrefix,
+	/// The checksum doesn't match the prefix and the public key.
+	InvalidChecksum,
+}
+
+impl fmt::Display for Ss58Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let reason = match self {
+			Ss58Error::BadBase58 => "invalid base58 character",
+			Ss58Error::BadLength => "wrong length",
+			Ss58Error::InvalidPrefix => "invalid prefix",
+			Ss58Error::InvalidChecksum => "wrong checksum",
+		};
+		write!(f, "invalid SS58 address: {reason}")
+	}
+}
+
+impl AccountId32 {
+	/// The SS58 address of this account on the network with `prefix`, which must be below 16384.
+	pub fn to_ss58check_with_prefix(&self, prefix: u16) -> String {
+		// Prefixes below 64 take one byte, the others take two bytes with 14 bits of the prefix.
+		let mut data = match prefix {
+			0..=63 => alloc::vec![prefix as u8],
+			_ => alloc::vec![((prefix & 0b1111_1100) >> 2) as u8 | 0b0100_0000, (prefix >> 8) as u8 | ((prefix & 0b11) << 6) as u8],
+		};
+		data.extend_from_slice(&self.0);
+		let checksum = ss58_checksum(&data);
+		data.extend_from_slice(&checksum[..2]);
+		base58_encode(&data)
+	}
+
+	/// The SS58 address of this account with the `DEFAULT_SS58_PREFIX`.
+	pub fn to_ss58check(&self) -> String {
+		self.to_ss58check_with_prefix(DEFAULT_SS58_PREFIX)
+	}
+
+	/// Parse an SS58 address into its account and its network prefix.
+	pub fn from_ss58check_with_prefix(address: &str) -> Result<(Self, u16), Ss58Error> {
+		let data = base58_decode(address)?;
+		if !(35..=36).contains(&data.len()) {
+			return Err(Ss58Error::BadLength);
+		}
+		let (prefix, prefix_len) = match data[0] {
+			first @ 0..=63 => (first as u16, 1),
+			first @ 64..=127 => {
+				let lower = (first << 2) | (data[1] >> 6);
+				let upper = data[1] & 0b0011_1111;
+				(lower as u16 | ((upper as u16) << 8), 2)
+			},
+			_ => return Err(Ss58Error::InvalidPrefix),
+		};
+		if data.len() != prefix_len + 32 + 2 {
+			return Err(Ss58Error::BadLength);
+		}
+		let (body, checksum) = data.split_at(prefix_len + 32);
+		if ss58_checksum(body)[..2] != *checksum {
+			return Err(Ss58Error::InvalidChecksum);
+		}
+		let mut account = [0u8; 32];
+		account.copy_from_slice(&body[prefix_len..]);
+		Ok((Self(account), prefix))
+	}
+}
+
+impl From<[u8; 32]> for AccountId32 {
+	fn from(bytes: [u8; 32]) -> Self {
+		Self(bytes)
+	}
+}
+
+impl From<Public> for AccountId32 {
+	fn from(public: Public) -> Self {
+		Self(public.0)
+	}
+}
+
+impl From<AccountId32> for Public {
+	fn from(account: AccountId32) -> Self {
+		Public(account.0)
+	}
+}
+
+impl AsRef<[u8]> for AccountId32 {
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+/// Shows the SS58 address of the account with the `DEFAULT_SS58_PREFIX`.
+impl fmt::Display for AccountId32 {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.to_ss58check())
+	}
+}
+
+impl fmt::Debug for AccountId32 {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "AccountId32({self})")
+	}
+}
+
+/// Parses an SS58 address, whatever its network prefix.
+impl FromStr for AccountId32 {
+	type Err = Ss58Error;
+
+	fn from_str(address: &str) -> Result<Self, Self::Err> {
+		Self::from_ss58check_with_prefix(address).map(|(account, _)| account)
+	}
+}
+
+impl Encode for AccountId32 {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+	}
+}
+
+impl Decode for AccountId32 {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		Ok(Self(Decode::decode(input)?))
+	}
+}
+
+/// The checksum of the prefix and the public key of an SS58 address, of which the address keeps the
+/// first two bytes.
+fn ss58_checksum(data: &[u8]) -> [u8; 64] {
+	let mut preimage = b"SS58PRE".to_vec();
+	preimage.extend_from_slice(data);
+	blake2b_512(&preimage)
+}
+
+/// Encode `data` in base58, where every leading zero byte is a leading `1`.
+fn base58_encode(data: &[u8]) -> String {
+	let zeros = data.iter().take_while(|&&byte| byte == 0).count();
+	// The digits of `data` in base 58, least significant first.
+	let mut digits: Vec<u8> = Vec::new();
+	for &byte in &data[zeros..] {
+		let mut carry = byte as u32;
+		for digit in digits.iter_mut() {
+			carry += (*digit as u32) << 8;
+			*digit = (carry % 58) as u8;
+			carry /= 58;
+		}
+		while carry > 0 {
+			digits.push((carry % 58) as u8);
+			carry /= 58;
+		}
+	}
+	let ones = core::iter::repeat_n('1', zeros);
+	ones.chain(digits.iter().rev().map(|&digit| BASE58_ALPHABET[digit as usize] as char)).collect()
+}
+
+/// Decode base58 `text`, the inverse of `base58_encode`.
+fn base58_decode(text: &str) -> Result<Vec<u8>, Ss58Error> {
+	let ones = text.bytes().take_while(|&character| character == b'1').count();
+	// The bytes of the number, least significant first.
+	let mut bytes: Vec<u8> = Vec::new();
+	for character in text.bytes().skip(ones) {
+		let digit = BASE58_ALPHABET.iter().position(|&c| c == character).ok_or(Ss58Error::BadBase58)?;
+		let mut carry = digit as u32;
+		for byte in bytes.iter_mut() {
+			carry += *byte as u32 * 58;
+			*byte = carry as u8;
+			carry >>= 8;
+		}
+		while carry > 0 {
+			bytes.push(carry as u8);
+			carry >>= 8;
+		}
+	}
+	let mut data = alloc::vec![0u8; ones];
+	data.extend(bytes.iter().rev());
+	Ok(data)
+}
+
+/// The hash of the signature nonce, the public key and the message, as a scalar.
+fn challenge(big_r: &[u8; 32], public: &Public, message: &[u8]) -> [u64; 4] {
+	let hash = Sha512::new().chain_update(big_r).chain_update(public.0).chain_update(message).finalize();
+	reduce_scalar(&hash)
+}
+
+const MASK: u64 = (1 << 51) - 1;
+
+/// An element of the field of integers modulo `p = 2^255 - 19`, as 5 limbs of 51 bits.
+#[derive(Clone, Copy)]
+struct Fe([u64; 5]);
+
+impl Fe {
+	const ZERO: Fe = Fe([0; 5]);
+	const ONE: Fe = Fe([1, 0, 0, 0, 0]);
+
+	fn from_u64(value: u64) -> Fe {
+		Fe(Fe::carry([value & MASK, value >> 51, 0, 0, 0]))
+	}
+
+	/// Load the 255 low bits of a little endian number.
+	fn from_bytes(bytes: &[u8; 32]) -> Fe {
+		let load = |offset: usize| {
+			let start = offset / 8;
+			let end = (start + 8).min(32);
+			let mut word = [0u8; 8];
+			word[..end - start].copy_from_slice(&bytes[start..end]);
+			(u64::from_le_bytes(word) >> (offset % 8)) & MASK
+		};
+		Fe([load(0), load(51), load(102), load(153), load(204)])
+	}
+
+	/// The canonical little endian encoding of this element, fully reduced modulo `p`.
+	fn to_bytes(self) -> [u8; 32] {
+		let mut h = Fe::carry(Fe::carry(self.0));
+		// Subtract `p` once if the value is not below it, by adding 19 and dropping bit 255.
+		let mut q = (h[0] + 19) >> 51;
+		for limb in &h[1..] {
+			q = (limb + q) >> 51;
+		}
+		h[0] += 19 * q;
+		for i in 0..4 {
+			h[i + 1] += h[i] >> 51;
+			h[i] &= MASK;
+		}
+		h[4] &= MASK;
+
+		let mut bytes = [0u8; 32];
+		let (mut acc, mut bits, mut i) = (0u128, 0, 0);
+		for limb in h {
+			acc |= u128::from(limb) << bits;
+			bits += 51;
+			while bits >= 8 {
+				bytes[i] = acc as u8;
+				acc >>= 8;
+				bits -= 8;
+				i += 1;
+			}
+		}
+		bytes[i] = acc as u8;
+		bytes
+	}
+
+	/// Propagate the carries of the limbs, so each of them fits in 51 bits again, up to a small
+	/// excess in the first limb.
+	fn carry(mut h: [u64; 5]) -> [u64; 5] {
+		for i in 0..4 {
+			h[i + 1] += h[i] >> 51;
+			h[i] &= MASK;
+		}
+		h[0] += 19 * (h[4] >> 51);
+		h[4] &= MASK;
+		h
+	}
+
+	fn add(&self, other: &Fe) -> Fe {
+		let (a, b) = (self.0, other.0);
+		Fe(Fe::carry([a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3], a[4] + b[4]]))
+	}
+
+	fn sub(&self, other: &Fe) -> Fe {
+		// Add `2p` first, so the limbs don't underflow.
+		let (a, b) = (self.0, other.0);
+		Fe(Fe::carry([
+			(a[0] + 0xf_ffff_ffff_ffda) - b[0],
+			(a[1] + 0xf_ffff_ffff_fffe) - b[1],
+			(a[2] + 0xf_ffff_ffff_fffe) - b[2],
+			(a[3] + 0xf_ffff_ffff_fffe) - b[3],
+			(a[4] + 0xf_ffff_ffff_fffe) - b[4],
+		]))
+	}
+
+	fn neg(&self) -> Fe {
+		Fe::ZERO.sub(self)
+	}
+
+	fn mul(&self, other: &Fe) -> Fe {
+		let m = |x: u64, y: u64| u128::from(x) * u128::from(y);
+		let [a0, a1, a2, a3, a4] = self.0;
+		let [b0, b1, b2, b3, b4] = other.0;
+		// `2^255 = 19 (mod p)`, so the limbs which overflow the 5 limbs wrap around times 19.
+		let (b1_19, b2_19, b3_19, b4_19) = (19 * b1, 19 * b2, 19 * b3, 19 * b4);
+
+		let mut r = [
+			m(a0, b0) + m(a1, b4_19) + m(a2, b3_19) + m(a3, b2_19) + m(a4, b1_19),
+			m(a0, b1) + m(a1, b0) + m(a2, b4_19) + m(a3, b3_19) + m(a4, b2_19),
+			m(a0, b2) + m(a1, b1) + m(a2, b0) + m(a3, b4_19) + m(a4, b3_19),
+			m(a0, b3) + m(a1, b2) + m(a2, b1) + m(a3, b0) + m(a4, b4_19),
+			m(a0, b4) + m(a1, b3) + m(a2, b2) + m(a3, b1) + m(a4, b0),
+		];
+		for i in 0..4 {
+			r[i + 1] += r[i] >> 51;
+			r[i] &= u128::from(MASK);
+		}
+		r[0] += 19 * (r[4] >> 51);
+		r[4] &= u128::from(MASK);
+		r[1] += r[0] >> 51;
+		r[0] &= u128::from(MASK);
+
+		Fe(Fe::carry(r.map(|limb| limb as u64)))
+	}
+
+	fn square(&self) -> Fe {
+		self.mul(self)
+	}
+
+	/// Raise this element to the power `exponent`, given as a little endian number.
+	fn pow(&self, exponent: &[u8; 32]) -> Fe {
+		let mut result = Fe::ONE;
+		for i in (0..256).rev() {
+			result = result.square();
+			if (exponent[i / 8] >> (i % 8)) & 1 == 1 {
+				result = result.mul(self);
+			}
+		}
+		result
+	}
+
+	fn invert(&self) -> Fe {
+		// `p - 2`, since `x^(p - 2) = x^-1 (mod p)`.
+		let mut exponent = [0xff; 32];
+		exponent[0] = 0xeb;
+		exponent[31] = 0x7f;
+		self.pow(&exponent)
+	}
+
+	fn is_negative(&self) -> bool {
+		self.to_bytes()[0] & 1 == 1
+	}
+
+	fn equals(&self, other: &Fe) -> bool {
+		self.to_bytes() == other.to_bytes()
+	}
+}
+
+/// The constants of the curve, which are computed rather than written out.
+struct Curve {
+	/// The constant `d = -121665 / 121666` of the curve equation `-x^2 + y^2 = 1 + d x^2 y^2`.
+	d: Fe,
+	/// `2 * d`, used by the addition of points.
+	d2: Fe,
+	/// A square root of `-1`.
+	sqrt_m1: Fe,
+	/// The base point, whose `y` is `4 / 5` and whose `x` is even.
+	base: Point,
+}
+
+impl Curve {
+	fn new() -> Self {
+		let d = Fe::from_u64(121665).neg().mul(&Fe::from_u64(121666).invert());
+		// `2^((p - 1) / 4)`, which squares to `-1` because 2 is not a square modulo `p`.
+		let mut exponent = [0xff; 32];
+		exponent[0] = 0xfb;
+		exponent[31] = 0x1f;
+		let sqrt_m1 = Fe::from_u64(2).pow(&exponent);
+
+		let base_y = Fe::from_u64(4).mul(&Fe::from_u64(5).invert());
+		let mut curve = Curve { d, d2: d.add(&d), sqrt_m1, base: Point::IDENTITY };
+		curve.base = Point::decode(&base_y.to_bytes(), &curve).expect("the base point is on the curve");
+		curve
+	}
+}
+
+/// A point of the curve, in extended coordinates `(X : Y : Z : T)` with `x = X / Z`, `y = Y / Z`
+/// and `x * y = T / Z`.
+#[derive(Clone, Copy)]
+struct Point {
+	x: Fe,
+	y: Fe,
+	z: Fe,
+	t: Fe,
+}
+
+impl Point {
+	const IDENTITY: Point = Point { x: Fe::ZERO, y: Fe::ONE, z: Fe::ONE, t: Fe::ZERO };
+
+	/// Decode a point from its `y` coordinate and the sign of its `x` coordinate, or `None` if the
+	/// encoding is not canonical or not on the curve.
+	fn decode(bytes: &[u8; 32], curve: &Curve) -> Option<Point> {
+		let x_is_negative = bytes[31] >> 7 == 1;
+		let mut y_bytes = *bytes;
+		y_bytes[31] &= 0x7f;
+		let y = Fe::from_bytes(&y_bytes);
+		if y.to_bytes() != y_bytes {
+			return None;
+		}
+
+		// Recover `x` from `x^2 = (y^2 - 1) / (d y^2 + 1)`.
+		let y2 = y.square();
+		let u = y2.sub(&Fe::ONE);
+		let v = curve.d.mul(&y2).add(&Fe::ONE);
+		let v3 = v.square().mul(&v);
+		let v7 = v3.square().mul(&v);
+		// `(p - 5) / 8`.
+		let mut exponent = [0xff; 32];
+		exponent[0] = 0xfd;
+		exponent[31] = 0x0f;
+		let mut x = u.mul(&v3).mul(&u.mul(&v7).pow(&exponent));
+
+		let vx2 = v.mul(&x.square());
+		if vx2.equals(&u.neg()) {
+			x = x.mul(&curve.sqrt_m1);
+		} else if !vx2.equals(&u) {
+			return None;
+		}
+		if x.equals(&Fe::ZERO) && x_is_negative {
+			return None;
+		}
+		if x.is_negative() != x_is_negative {
+			x = x.neg();
+		}
+		Some(Point { x, y, z: Fe::ONE, t: x.mul(&y) })
+	}
+
+	fn encode(&self) -> [u8; 32] {
+		let z_inv = self.z.invert();
+		let x = self.x.mul(&z_inv);
+		let mut bytes = self.y.mul(&z_inv).to_bytes();
+		bytes[31] |= u8::from(x.is_negative()) << 7;
+		bytes
+	}
+
+	/// Add two points. The formula is complete, so it also doubles a point.
+	fn add(&self, other: &Point, curve: &Curve) -> Point {
+		let a = self.y.sub(&self.x).mul(&other.y.sub(&other.x));
+		let b = self.y.add(&self.x).mul(&other.y.add(&other.x));
+		let c = self.t.mul(&curve.d2).mul(&other.t);
+		let zz = self.z.mul(&other.z);
+		let d = zz.add(&zz);
+		let (e, f, g, h) = (b.sub(&a), d.sub(&c), d.add(&c), b.add(&a));
+		Point { x: e.mul(&f), y: g.mul(&h), z: f.mul(&g), t: e.mul(&h) }
+	}
+
+	/// Multiply this point by a scalar, given as a little endian number.
+	fn mul(&self, scalar: &[u8; 32], curve: &Curve) -> Point {
+		let mut result = Point::IDENTITY;
+		for i in (0..256).rev() {
+			result = result.add(&result, curve);
+			if (scalar[i / 8] >> (i % 8)) & 1 == 1 {
+				result = result.add(self, curve);
+			}
+		}
+		result
+	}
+}
+
+/// The order of the base point, `L = 2^252 + 27742317777372353535851937790883648493`, as 4 little
+/// endian limbs.
+const L: [u64; 4] = [0x5812_631a_5cf5_d3ed, 0x14de_f9de_a2f7_9cd6, 0, 0x1000_0000_0000_0000];
+
+/// Reduce a little endian number of any length modulo `L`, one bit at a time.
+fn reduce_scalar(bytes: &[u8]) -> [u64; 4] {
+	let mut r = [0u64; 4];
+	for i in (0..bytes.len() * 8).rev() {
+		// `r < L < 2^253`, so doubling it doesn't overflow.
+		r = [r[0] << 1, (r[1] << 1) | (r[0] >> 63), (r[2] << 1) | (r[1] >> 63), (r[3] << 1) | (r[2] >> 63)];
+		r[0] |= u64::from((bytes[i / 8] >> (i % 8)) & 1);
+		if !less_than(&r, &L) {
+			let mut borrow = false;
+			for (limb, l) in r.iter_mut().zip(L) {
+				let (diff, b1) = limb.overflowing_sub(l);
+				let (diff, b2) = diff.overflowing_sub(u64::from(borrow));
+				*limb = diff;
+				borrow = b1 || b2;
+			}
+		}
+	}
+	r
+}
+
+fn less_than(a: &[u64; 4], b: &[u64; 4]) -> bool {
+	a.iter().rev().cmp(b.iter().rev()) == core::cmp::Ordering::Less
+}
+
+/// `a * b + c (mod L)`.
+fn mul_add_scalar(a: &[u64; 4], b: &[u64; 4], c: &[u64; 4]) -> [u64; 4] {
+	let mut product = [0u64; 8];
+	for i in 0..4 {
+		let mut carry = 0u128;
+		for j in 0..4 {
+			let sum = u128::from(product[i + j]) + u128::from(a[i]) * u128::from(b[j]) + carry;
+			product[i + j] = sum as u64;
+			carry = sum >> 64;
+		}
+		product[i + 4] = carry as u64;
+	}
+	let mut carry = 0u128;
+	for (i, limb) in product.iter_mut().enumerate() {
+		let sum = u128::from(*limb) + u128::from(c.get(i).copied().unwrap_or(0)) + carry;
+		*limb = sum as u64;
+		carry = sum >> 64;
+	}
+
+	let bytes = product.iter().flat_map(|limb| limb.to_le_bytes()).collect::<Vec<_>>();
+	reduce_scalar(&bytes)
+}
+
+fn is_canonical_scalar(bytes: &[u8; 32]) -> bool {
+	less_than(&bytes_to_scalar(bytes), &L)
+}
+
+fn bytes_to_scalar(bytes: &[u8; 32]) -> [u64; 4] {
+	let mut limbs = [0u64; 4];
+	for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+		*limb = u64::from_le_bytes(chunk.try_into().expect("chunks of 8 bytes"));
+	}
+	limbs
+}
+
+fn scalar_to_bytes(scalar: &[u64; 4]) -> [u8; 32] {
+	let mut bytes = [0u8; 32];
+	for (chunk, limb) in bytes.chunks_mut(8).zip(scalar) {
+		chunk.copy_from_slice(&limb.to_le_bytes());
+	}
+	bytes
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{AccountId32, Pair, Public, Signature, Ss58Error};
+
+	fn hex<const N: usize>(hex: &str) -> [u8; N] {
+		let mut bytes = [0u8; N];
+		for (i, byte) in bytes.iter_mut().enumerate() {
+			*byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+		}
+		bytes
+	}
+
+	#[test]
+	fn signatures_match_the_rfc_8032_test_vectors() {
+		let pair = Pair::from_seed(&hex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"));
+		assert_eq!(pair.public(), Public(hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")));
+		let signature = pair.sign(b"");
+		assert_eq!(
+			signature,
+			Signature(hex(
+				"e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+			))
+		);
+		assert!(pair.public().verify(b"", &signature));
+
+		let pair = Pair::from_seed(&hex("4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb"));
+		assert_eq!(pair.public(), Public(hex("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")));
+		let signature = pair.sign(&[0x72]);
+		assert_eq!(
+			signature,
+			Signature(hex(
+				"92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"
+			))
+		);
+		assert!(pair.public().verify(&[0x72], &signature));
+	}
+
+	#[test]
+	fn verify_rejects_tampered_signatures() {
+		let alice = Pair::from_seed(&[1; 32]);
+		let bob = Pair::from_seed(&[2; 32]);
+		let signature = alice.sign(b"transfer 10 to bob");
+		assert!(alice.public().verify(b"transfer 10 to bob", &signature));
+
+		assert!(!alice.public().verify(b"transfer 99 to bob", &signature));
+		assert!(!bob.public().verify(b"transfer 10 to bob", &signature));
+		let mut tampered = signature;
+		tampered.0[40] ^= 1;
+		assert!(!alice.public().verify(b"transfer 10 to bob", &tampered));
+		// A scalar which is not reduced is refused, even if it would otherwise verify.
+		let mut unreduced = signature;
+		unreduced.0[63] |= 0xf0;
+		assert!(!alice.public().verify(b"transfer 10 to bob", &unreduced));
+	}
+
+	#[test]
+	fn accounts_round_trip_through_ss58() {
+		let alice = AccountId32(hex("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"));
+		for (prefix, address) in [
+			(42, "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"),
+			(0, "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5"),
+			(2, "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F"),
+			(64, "cEaNSpz4PxFcZ7nT1VEKrKewH67rfx6MfcM6yKojyyPz7qaqp"),
+			(16383, "yNa8JpqfFB3q8A29rCwSgxvdU94ufJw2yKKxDgznS5m1PoFvn"),
+		] {
+			assert_eq!(alice.to_ss58check_with_prefix(prefix), address);
+			assert_eq!(AccountId32::from_ss58check_with_prefix(address), Ok((alice.clone(), prefix)));
+		}
+		assert_eq!(alice.to_string(), "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+		assert_eq!("15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5".parse(), Ok(alice));
+		assert_eq!(AccountId32([0; 32]).to_string(), "5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM");
+	}
+
+	#[test]
+	fn invalid_ss58_addresses_are_refused() {
+		for (address, error) in [
+			("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQ0", Ss58Error::BadBase58),
+			("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQ", Ss58Error::BadLength),
+			("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQZ", Ss58Error::InvalidChecksum),
+			("", Ss58Error::BadLength),
+		] {
+			assert_eq!(AccountId32::from_ss58check_with_prefix(address), Err(error), "{address}");
+		}
+	}
+}
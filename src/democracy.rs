@@ -0,0 +1,366 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use num::traits::{One, Saturating, Zero};
+
+use crate::balances::{LockIdentifier, WithdrawReasons};
+use crate::codec::Encode;
+use crate::storage::{storage_key, storage_map_key, StorageBackend};
+use crate::support::{
+	ArithmeticError, DispatchError, DispatchResult, Events, HooksWithContext, RawOrigin, StorageMap,
+	StorageValue,
+};
+
+/// The identifier of the lock which holds the balance an account voted with in the balances pallet.
+pub const DEMOCRACY_ID: LockIdentifier = *b"democrac";
+
+pub trait Config: crate::balances::Config + crate::scheduler::Config {
+	/// The number of blocks a referendum is open for votes. It is closed at the end of its last
+	/// block.
+	const VOTING_PERIOD: Self::BlockNumber;
+}
+
+/// The index of a referendum, in the order they were proposed.
+pub type ReferendumIndex = u32;
+
+/// The vote of an account in a referendum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vote<Balance> {
+	/// Whether the account voted for the proposal.
+	pub aye: bool,
+	/// The free balance of the account when it voted, which is the weight of its vote.
+	pub balance: Balance,
+}
+
+/// The total weight of the votes for and against a proposal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tally<Balance> {
+	pub ayes: Balance,
+	pub nays: Balance,
+}
+
+/// A proposal which is open for votes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Referendum<AccountId, Balance, BlockNumber, Call> {
+	/// The account which proposed the call.
+	pub proposer: AccountId,
+	/// The call which is dispatched with the root origin if the referendum passes.
+	pub call: Box<Call>,
+	/// The last block of the voting period.
+	pub end: BlockNumber,
+	pub tally: Tally<Balance>,
+	/// The vote of each account which voted.
+	pub votes: BTreeMap<AccountId, Vote<Balance>>,
+}
+
+/// The events emitted by the democracy pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, Balance> {
+	/// A call was proposed, and a referendum was started for it.
+	Proposed { ref_index: ReferendumIndex, proposer: AccountId },
+	/// An account voted in a referendum, replacing its previous vote if any.
+	Voted { ref_index: ReferendumIndex, voter: AccountId, vote: Vote<Balance> },
+	/// A referendum passed, and its call was scheduled to be dispatched at the start of the next
+	/// block.
+	Passed { ref_index: ReferendumIndex },
+	/// A referendum did not pass.
+	NotPassed { ref_index: ReferendumIndex },
+	/// A referendum passed, but its call couldn't be scheduled.
+	EnactmentFailed { ref_index: ReferendumIndex, error: DispatchError },
+}
+
+/// The errors returned by the democracy pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// There is no open referendum with this index.
+	ReferendumDoesNotExist,
+	/// The account has no free balance to vote with.
+	NoVotingBalance,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::ReferendumDoesNotExist => "ReferendumDoesNotExist",
+			Error::NoVotingBalance => "NoVotingBalance",
+		};
+		DispatchError::Module { pallet: "democracy", error }
+	}
+}
+
+/// The events of the democracy pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> =
+	Event<<T as crate::system::Config>::AccountId, <T as crate::balances::Config>::Balance>;
+
+/// This is the Democracy Module.
+/// It allows accounts to propose calls, which are put to a referendum where the votes are weighted
+/// by the free balance of the voters. At the end of the voting period, the call of a referendum
+/// with more ayes than nays is scheduled in the scheduler pallet, to be dispatched with the root
+/// origin at the start of the next block.
+///
+/// The balance an account voted with is locked against transfers, so it can't vote again with the
+/// same funds from another account. The lock stays after the referendum closes, until the account
+/// calls `unlock`.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The referenda which are open for votes.
+	#[allow(clippy::type_complexity)]
+	referenda: StorageMap<ReferendumIndex, Referendum<T::AccountId, T::Balance, T::BlockNumber, T::RuntimeCall>>,
+	/// The number of referenda started so far, which is the index of the next one.
+	referendum_count: StorageValue<ReferendumIndex>,
+	/// The block being executed, which the voting period starts from.
+	block_number: T::BlockNumber,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId, T::Balance>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Propose `call`, starting a referendum which is open for votes for `Config::VOTING_PERIOD`
+	/// blocks. Returns the index of the referendum.
+	#[call_index = 0]
+	#[weight = 10_000u64.saturating_add(T::call_weight(call))]
+	pub fn propose(
+		&mut self,
+		caller: T::AccountId,
+		call: Box<T::RuntimeCall>,
+	) -> Result<ReferendumIndex, DispatchError> {
+		let ref_index = *self.referendum_count;
+		self.referendum_count.set(ref_index.checked_add(1).ok_or(ArithmeticError::Overflow)?);
+		let referendum = Referendum {
+			proposer: caller.clone(),
+			call,
+			end: self.block_number.saturating_add(T::VOTING_PERIOD),
+			tally: Tally { ayes: T::Balance::zero(), nays: T::Balance::zero() },
+			votes: BTreeMap::new(),
+		};
+		self.referenda.insert(ref_index, referendum);
+		self.deposit_event(Event::Proposed { ref_index, proposer: caller });
+		Ok(ref_index)
+	}
+
+	/// Vote for the proposal of the referendum `ref_index` if `aye`, or against it, with the whole
+	/// free balance of the `caller`, which is locked against transfers. A second vote in the same
+	/// referendum replaces the first one.
+	/// This function will return an error if the referendum is not open, or if the caller has no
+	/// free balance.
+	#[call_index = 1]
+	#[weight = 10_000]
+	pub fn vote(
+		&mut self,
+		caller: T::AccountId,
+		ref_index: ReferendumIndex,
+		aye: bool,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		let referendum = self.referenda.get_mut(&ref_index).ok_or(Error::ReferendumDoesNotExist)?;
+		let balance = balances.balance(&caller);
+		if balance.is_zero() {
+			return Err(Error::NoVotingBalance.into());
+		}
+
+		let vote = Vote { aye, balance };
+		if let Some(previous) = referendum.votes.insert(caller.clone(), vote) {
+			let side = if previous.aye { &mut referendum.tally.ayes } else { &mut referendum.tally.nays };
+			*side = side.saturating_sub(previous.balance);
+		}
+		let side = if aye { &mut referendum.tally.ayes } else { &mut referendum.tally.nays };
+		*side = side.saturating_add(balance);
+		balances.extend_lock(DEMOCRACY_ID, &caller, balance, WithdrawReasons::TRANSFER);
+		self.deposit_event(Event::Voted { ref_index, voter: caller, vote });
+		Ok(())
+	}
+
+	/// Lower the lock of the `caller` to the largest balance they voted with in the referenda which
+	/// are still open, or remove it if they are not voting in any.
+	#[call_index = 2]
+	#[weight = 5_000]
+	pub fn unlock(&mut self, caller: T::AccountId, balances: &mut crate::balances::Pallet<T>) -> DispatchResult {
+		let locked = self
+			.referenda
+			.values()
+			.filter_map(|referendum| referendum.votes.get(&caller))
+			.fold(T::Balance::zero(), |locked, vote| if vote.balance > locked { vote.balance } else { locked });
+		if locked.is_zero() {
+			balances.remove_lock(DEMOCRACY_ID, &caller);
+		} else {
+			balances.set_lock(DEMOCRACY_ID, &caller, locked, WithdrawReasons::TRANSFER);
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Democracy Module.
+	pub fn new() -> Self {
+		Self {
+			referenda: StorageMap::new(),
+			referendum_count: StorageValue::new(0),
+			block_number: T::BlockNumber::zero(),
+			events: Events::new(),
+		}
+	}
+
+	/// Get the referendum `ref_index`, if it is open.
+	#[allow(clippy::type_complexity)]
+	pub fn referendum(
+		&self,
+		ref_index: ReferendumIndex,
+	) -> Option<&Referendum<T::AccountId, T::Balance, T::BlockNumber, T::RuntimeCall>> {
+		self.referenda.get(&ref_index)
+	}
+}
+
+impl<T: Config> HooksWithContext<T::BlockNumber, crate::scheduler::Pallet<T>> for Pallet<T> {
+	/// Keep the number of the block, which the voting period of new referenda starts from.
+	fn on_initialize(&mut self, _scheduler: &mut crate::scheduler::Pallet<T>, block_number: T::BlockNumber) {
+		self.block_number = block_number;
+	}
+
+	/// Close the referenda whose voting period ends with `block_number`. The call of each one which
+	/// passed is scheduled with the root origin for the next block.
+	fn on_finalize(&mut self, scheduler: &mut crate::scheduler::Pallet<T>, block_number: T::BlockNumber) {
+		let closed = self
+			.referenda
+			.iter()
+			.filter(|(_, referendum)| referendum.end <= block_number)
+			.map(|(ref_index, _)| *ref_index)
+			.collect::<Vec<_>>();
+		for ref_index in closed {
+			let referendum = self.referenda.remove(&ref_index).expect("the referendum was just found");
+			if referendum.tally.ayes <= referendum.tally.nays {
+				self.deposit_event(Event::NotPassed { ref_index });
+				continue;
+			}
+			let when = block_number.saturating_add(T::BlockNumber::one());
+			match scheduler.schedule_with_origin(RawOrigin::Root, when, referendum.call, block_number) {
+				Ok(_) => self.deposit_event(Event::Passed { ref_index }),
+				Err(error) => self.deposit_event(Event::EnactmentFailed { ref_index, error }),
+			}
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+	T::RuntimeCall: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The block
+	/// number is part of the state of the system pallet, and the phase and the events are not
+	/// part of the state.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		state.insert(storage_key("democracy", "referendum_count"), self.referendum_count.encode());
+		for (ref_index, referendum) in &self.referenda {
+			let votes = referendum
+				.votes
+				.iter()
+				.map(|(who, vote)| (who, (vote.aye, vote.balance)))
+				.collect::<Vec<_>>();
+			let tally = (referendum.tally.ayes, referendum.tally.nays);
+			let value = (&referendum.proposer, (&referendum.call, (&referendum.end, (tally, votes)))).encode();
+			state.insert(storage_map_key("democracy", "referenda", ref_index), value);
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, Event, Tally, Vote, DEMOCRACY_ID};
+	use crate::balances::{self, WithdrawReasons};
+	use crate::support::{HooksWithContext, RawOrigin};
+	use crate::scheduler;
+
+	crate::construct_mock_runtime!(struct TestConfig: balances);
+
+	impl scheduler::Config for TestConfig {
+		type RuntimeCall = &'static str;
+		const MAX_SCHEDULED_PER_BLOCK: u32 = 10;
+		fn call_weight(_call: &Self::RuntimeCall) -> crate::support::Weight {
+			1_000
+		}
+	}
+
+	impl super::Config for TestConfig {
+		const VOTING_PERIOD: Self::BlockNumber = 2;
+	}
+
+	fn setup() -> (super::Pallet<TestConfig>, balances::Pallet<TestConfig>, scheduler::Pallet<TestConfig>) {
+		let mut balances = balances::Pallet::new();
+		balances.set_balance("alice", 100);
+		balances.set_balance("bob", 60);
+		balances.set_balance("charlie", 50);
+		let mut scheduler = scheduler::Pallet::new();
+		let mut democracy = super::Pallet::new();
+		democracy.on_initialize(&mut scheduler, 1);
+		(democracy, balances, scheduler)
+	}
+
+	#[test]
+	fn votes_are_weighted_by_balance_and_locked() {
+		let (mut democracy, mut balances, _) = setup();
+
+		assert_eq!(democracy.propose("alice", Box::new("set_balance")), Ok(0));
+		assert_eq!(democracy.vote("dave", 0, true, &mut balances), Err(Error::NoVotingBalance.into()));
+		assert_eq!(democracy.vote("bob", 1, true, &mut balances), Err(Error::ReferendumDoesNotExist.into()));
+		assert_eq!(democracy.vote("bob", 0, true, &mut balances), Ok(()));
+		assert_eq!(democracy.vote("charlie", 0, false, &mut balances), Ok(()));
+		assert_eq!(democracy.referendum(0).map(|referendum| referendum.tally), Some(Tally { ayes: 60, nays: 50 }));
+		assert_eq!(balances.locked_balance(&"bob", WithdrawReasons::TRANSFER), 60);
+		assert_eq!(balances.transfer("bob", "dave", 10), Err(balances::Error::BalanceLocked.into()));
+
+		// A second vote replaces the first one.
+		assert_eq!(democracy.vote("bob", 0, false, &mut balances), Ok(()));
+		assert_eq!(democracy.referendum(0).map(|referendum| referendum.tally), Some(Tally { ayes: 0, nays: 110 }));
+		assert_eq!(
+			democracy.take_events().last().map(|record| record.event.clone()),
+			Some(Event::Voted { ref_index: 0, voter: "bob", vote: Vote { aye: false, balance: 60 } })
+		);
+
+		// The lock stays while the referendum is open.
+		assert_eq!(democracy.unlock("bob", &mut balances), Ok(()));
+		assert_eq!(balances.locked_balance(&"bob", WithdrawReasons::TRANSFER), 60);
+		assert!(balances.locks(&"bob").any(|(id, _)| *id == DEMOCRACY_ID));
+	}
+
+	#[test]
+	fn passed_referenda_are_scheduled_with_root_origin() {
+		let (mut democracy, mut balances, mut scheduler) = setup();
+
+		assert_eq!(democracy.propose("alice", Box::new("set_balance")), Ok(0));
+		assert_eq!(democracy.propose("charlie", Box::new("slash")), Ok(1));
+		assert_eq!(democracy.vote("alice", 0, true, &mut balances), Ok(()));
+		assert_eq!(democracy.vote("bob", 0, false, &mut balances), Ok(()));
+		assert_eq!(democracy.vote("charlie", 1, true, &mut balances), Ok(()));
+		assert_eq!(democracy.vote("bob", 1, false, &mut balances), Ok(()));
+		democracy.take_events();
+
+		// The referenda are open until the end of block 1 + `VOTING_PERIOD`.
+		democracy.on_finalize(&mut scheduler, 2);
+		assert!(democracy.referendum(0).is_some());
+		democracy.on_finalize(&mut scheduler, 3);
+		assert!(democracy.referendum(0).is_none());
+		let events = democracy.take_events().into_iter().map(|record| record.event).collect::<Vec<_>>();
+		assert_eq!(events, [Event::Passed { ref_index: 0 }, Event::NotPassed { ref_index: 1 }]);
+
+		let task = scheduler.task((4, 0)).expect("the call of the referendum should be scheduled");
+		assert_eq!((&task.origin, *task.call), (&RawOrigin::Root, "set_balance"));
+		assert!(scheduler.task((4, 1)).is_none());
+
+		// Once the referenda are closed, the votes can be unlocked.
+		assert_eq!(democracy.unlock("bob", &mut balances), Ok(()));
+		assert_eq!(balances.locks(&"bob").count(), 0);
+		assert_eq!(democracy.vote("bob", 0, true, &mut balances), Err(Error::ReferendumDoesNotExist.into()));
+	}
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use crate::{system, types, Runtime};
+
+/// The differences between the states of two runtimes. Each entry maps a key which differs to its
+/// value in the first runtime, and its value in the second runtime.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RuntimeDiff {
+	/// The accounts with a different free balance.
+	pub balances: BTreeMap<types::AccountId, (types::Balance, types::Balance)>,
+	/// The accounts with a different nonce.
+	pub nonces: BTreeMap<types::AccountId, (types::Nonce, types::Nonce)>,
+	/// The claims with a different owner, where `None` means the content is not claimed.
+	pub claims: BTreeMap<system::Hash, (Option<types::AccountId>, Option<types::AccountId>)>,
+}
+
+impl RuntimeDiff {
+	/// Whether the two runtimes have the same state.
+	pub fn is_empty(&self) -> bool {
+		self.balances.is_empty() && self.nonces.is_empty() && self.claims.is_empty()
+	}
+}
+
+impl Runtime {
+	/// Compare the state of this runtime with the state of `other`.
+	///
+	/// Accounts missing from one of the runtimes are compared as if they had a zero balance and a
+	/// zero nonce, like the pallets do when they are queried.
+	pub fn diff(&self, other: &Runtime) -> RuntimeDiff {
+		let balances = |runtime: &Runtime| {
+			runtime.balances.iter().map(|(who, free)| (who.clone(), *free)).collect()
+		};
+		let nonces = |runtime: &Runtime| runtime.system.snapshot().nonces;
+		let claims = |runtime: &Runtime| {
+			runtime
+				.proof_of_existence
+				.claims()
+				.map(|(claim, owner)| (*claim, Some(owner.clone())))
+				.collect()
+		};
+
+		RuntimeDiff {
+			balances: changes(balances(self), balances(other), 0),
+			nonces: changes(nonces(self), nonces(other), 0),
+			claims: changes(claims(self), claims(other), None),
+		}
+	}
+}
+
+/// Get the keys whose value differs between `before` and `after`, with both values. Keys missing
+/// from one of the maps have the `missing` value in it.
+fn changes<K: Ord, V: PartialEq + Clone>(
+	before: BTreeMap<K, V>,
+	mut after: BTreeMap<K, V>,
+	missing: V,
+) -> BTreeMap<K, (V, V)> {
+	let mut changes = BTreeMap::new();
+	for (key, before_value) in before {
+		let after_value = after.remove(&key).unwrap_or_else(|| missing.clone());
+		if before_value != after_value {
+			changes.insert(key, (before_value, after_value));
+		}
+	}
+	for (key, after_value) in after {
+		if after_value != missing {
+			changes.insert(key, (missing.clone(), after_value));
+		}
+	}
+	changes
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::BTreeMap;
+
+	use crate::{dev_account, proof_of_existence, Runtime};
+
+	#[test]
+	fn diff_lists_the_accounts_changed_by_a_transfer() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(dev_account("alice"), 100);
+		runtime.balances.set_balance(dev_account("charlie"), 10);
+		runtime.system.inc_nonce(&dev_account("alice"));
+
+		let before = runtime.clone();
+		assert!(before.diff(&runtime).is_empty());
+
+		runtime.balances.transfer(dev_account("alice"), dev_account("bob"), 30).unwrap();
+		let diff = before.diff(&runtime);
+		assert_eq!(
+			diff.balances,
+			BTreeMap::from([(dev_account("alice"), (100, 70)), (dev_account("bob"), (0, 30))])
+		);
+		assert!(diff.nonces.is_empty());
+		assert!(diff.claims.is_empty());
+	}
+
+	#[test]
+	fn diff_lists_nonce_and_claim_changes() {
+		let mut runtime = Runtime::new();
+		runtime.system.inc_nonce(&dev_account("alice"));
+		runtime.balances.set_balance(dev_account("alice"), 100);
+
+		let before = runtime.clone();
+		runtime.system.inc_nonce(&dev_account("alice"));
+		runtime
+			.proof_of_existence
+			.create_claim(dev_account("alice"), "my_document".into(), &mut runtime.balances)
+			.unwrap();
+
+		let diff = before.diff(&runtime);
+		assert_eq!(diff.nonces, BTreeMap::from([(dev_account("alice"), (1, 2))]));
+		let claim = proof_of_existence::Pallet::<Runtime>::claim_of(b"my_document");
+		assert_eq!(diff.claims, BTreeMap::from([(claim, (None, Some(dev_account("alice"))))]));
+		// The claim fee was paid into the treasury.
+		assert_eq!(diff.balances.len(), 2);
+	}
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::chain::Chain;
+use crate::codec::{self, Decode, Encode};
+use crate::crypto::Signature;
+use crate::keyring::Keystore;
+use crate::storage::StorageBackend;
+use crate::{system, types, Runtime};
+
+/// The reasons a vote is refused, or a block can't be finalized.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinalityError {
+	/// The account which cast the vote isn't in the voter set.
+	NotAVoter(types::AccountId),
+	/// The key of the voter isn't in the keystore.
+	MissingKey(types::AccountId),
+	/// The vote isn't signed by its voter.
+	BadSignature,
+	/// The block with this hash isn't known to the chain, or doesn't have the number voted for.
+	UnknownBlock(system::Hash),
+	/// The block isn't built on top of the finalized block, so it can never be finalized.
+	ConflictsWithFinalized,
+	/// The best chain changed when the block was finalized, but it couldn't be stored.
+	Storage(String),
+}
+
+impl fmt::Display for FinalityError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FinalityError::NotAVoter(voter) => write!(f, "{voter} is not a voter"),
+			FinalityError::MissingKey(voter) => write!(f, "the key of the voter {voter} is not in the keystore"),
+			FinalityError::BadSignature => write!(f, "the vote is not signed by its voter"),
+			FinalityError::UnknownBlock(_) => write!(f, "the block is not known"),
+			FinalityError::ConflictsWithFinalized => write!(f, "the block is not built on top of the finalized block"),
+			FinalityError::Storage(error) => write!(f, "cannot store the best chain: {error}"),
+		}
+	}
+}
+
+/// A vote for a block, which is also a vote for every ancestor of the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vote {
+	pub target_hash: system::Hash,
+	pub target_number: types::BlockNumber,
+}
+
+impl Vote {
+	/// The message the voter signs.
+	fn payload(&self) -> Vec<u8> {
+		(self.target_hash, self.target_number).encode()
+	}
+}
+
+/// A vote with the voter who cast it, and their signature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedVote {
+	pub vote: Vote,
+	pub voter: types::AccountId,
+	pub signature: Signature,
+}
+
+impl SignedVote {
+	/// Cast `vote` as `voter`, signed with their key taken from `keystore`.
+	pub fn new(vote: Vote, voter: types::AccountId, keystore: &Keystore) -> Result<Self, FinalityError> {
+		let signature = keystore.sign(&voter, &vote.payload()).ok_or_else(|| FinalityError::MissingKey(voter.clone()))?;
+		Ok(Self { vote, voter, signature })
+	}
+}
+
+impl Encode for SignedVote {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.vote.target_hash.encode_to(dest);
+		self.vote.target_number.encode_to(dest);
+		self.voter.encode_to(dest);
+		self.signature.encode_to(dest);
+	}
+}
+
+impl Decode for SignedVote {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		let vote = Vote { target_hash: Decode::decode(input)?, target_number: Decode::decode(input)? };
+		Ok(Self { vote, voter: Decode::decode(input)?, signature: Decode::decode(input)? })
+	}
+}
+
+/// A simplified GRANDPA: a configured set of voters votes on the blocks of a `Chain`, and a block
+/// is finalized once more than two thirds of the voters voted for it or for one of its descendants.
+///
+/// Each voter has one vote, which a newer vote replaces, so two conflicting blocks can never both
+/// gather enough votes. There are no rounds: the highest block with enough votes is finalized as
+/// soon as a vote makes it so.
+pub struct FinalityGadget {
+	/// The accounts which vote.
+	voters: Vec<types::AccountId>,
+	/// The last vote of each voter.
+	votes: BTreeMap<types::AccountId, Vote>,
+}
+
+impl FinalityGadget {
+	pub fn new(voters: Vec<types::AccountId>) -> Self {
+		Self { voters, votes: BTreeMap::new() }
+	}
+
+	/// The voters of the chain of `runtime`, which are its Aura authorities, see `consensus`.
+	pub fn authorities(runtime: &Runtime) -> Self {
+		Self::new(runtime.aura.authorities().to_vec())
+	}
+
+	/// The last vote counted of `voter`, if any.
+	pub fn vote_of(&self, voter: &types::AccountId) -> Option<&Vote> {
+		self.votes.get(voter)
+	}
+
+	/// The number of votes a block needs to be finalized, which is more than two thirds of the
+	/// voters.
+	pub fn threshold(&self) -> usize {
+		self.voters.len() * 2 / 3 + 1
+	}
+
+	/// Count `signed` as the vote of its voter, then finalize the highest block of `chain` with
+	/// enough votes, if it is higher than the finalized block. Return the hash of the block
+	/// finalized, if any.
+	///
+	/// The vote is refused if the voter isn't in the voter set, if it isn't signed with the key the
+	/// voter has in the state of the finalized block, or if it is for a block which isn't built on
+	/// top of the finalized block.
+	pub fn import_vote<B: StorageBackend>(
+		&mut self,
+		chain: &mut Chain<B>,
+		signed: SignedVote,
+	) -> Result<Option<system::Hash>, FinalityError>
+	where
+		B::Error: fmt::Debug,
+	{
+		let SignedVote { vote, voter, signature } = signed;
+		if !self.voters.contains(&voter) {
+			return Err(FinalityError::NotAVoter(voter));
+		}
+		let (finalized_number, finalized_hash) = chain.finalized_head();
+		let public = chain.finalized_runtime().system.public_key(&voter);
+		if !public.is_some_and(|public| public.verify(&vote.payload(), &signature)) {
+			return Err(FinalityError::BadSignature);
+		}
+		match chain.block(&vote.target_hash) {
+			Some(block) if block.header.block_number == vote.target_number => {},
+			_ => return Err(FinalityError::UnknownBlock(vote.target_hash)),
+		}
+		if !chain.is_descendant(vote.target_hash, finalized_hash) {
+			return Err(FinalityError::ConflictsWithFinalized);
+		}
+		self.votes.insert(voter, vote);
+
+		// Count each vote for its target and every ancestor of it above the finalized block.
+		let mut counts = BTreeMap::<system::Hash, (types::BlockNumber, usize)>::new();
+		for vote in self.votes.values() {
+			let (mut hash, mut number) = (vote.target_hash, vote.target_number);
+			while number > finalized_number {
+				counts.entry(hash).or_insert((number, 0)).1 += 1;
+				hash = chain.block(&hash).expect("votes are for known blocks").header.parent_hash;
+				number -= 1;
+			}
+		}
+		let Some((&hash, _)) = counts
+			.iter()
+			.filter(|(_, (_, count))| *count >= self.threshold())
+			.max_by_key(|(_, (number, _))| *number)
+		else {
+			return Ok(None);
+		};
+		chain.finalize_block(hash)?;
+		// The votes for the forks which were dropped can no longer be counted.
+		self.votes.retain(|_, vote| chain.block(&vote.target_hash).is_some());
+		Ok(Some(hash))
+	}
+
+	/// Vote for the best block of `chain` with the key of every voter in `keystore` who didn't vote
+	/// for it yet, and count the votes, see `import_vote`. Returns the votes cast, to gossip them to
+	/// the other nodes, and the hash of the block they finalized, if any.
+	///
+	/// Nothing is voted for while the best block is the finalized block.
+	#[allow(clippy::type_complexity)]
+	pub fn vote_for_best_block<B: StorageBackend>(
+		&mut self,
+		chain: &mut Chain<B>,
+		keystore: &Keystore,
+	) -> Result<(Vec<SignedVote>, Option<system::Hash>), FinalityError>
+	where
+		B::Error: fmt::Debug,
+	{
+		let (target_number, target_hash) = chain.best_block();
+		if target_number == chain.finalized_head().0 {
+			return Ok((Vec::new(), None));
+		}
+		let vote = Vote { target_hash, target_number };
+		let (mut cast, mut finalized) = (Vec::new(), None);
+		for voter in self.voters.clone() {
+			if !keystore.contains(&voter) || self.vote_of(&voter) == Some(&vote) {
+				continue;
+			}
+			let signed = SignedVote::new(vote, voter, keystore)?;
+			finalized = self.import_vote(chain, signed.clone())?.or(finalized);
+			cast.push(signed);
+		}
+		Ok((cast, finalized))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{FinalityError, FinalityGadget, SignedVote, Vote};
+	use crate::block_builder::BlockBuilder;
+	use crate::chain::Chain;
+	use crate::chain_store::ChainStore;
+	use crate::import_queue::ImportError;
+	use crate::storage::InMemoryBackend;
+	use crate::{balances, consensus, dev_account, development_genesis, system, types, Runtime, RuntimeApi, RuntimeCall, DEV_KEYSTORE};
+
+	// Build a block on top of `parent` in `chain`, with a transfer of `amount` from Alice to Bob.
+	fn block_on(chain: &Chain<InMemoryBackend>, parent: system::Hash, amount: types::Balance) -> types::Block {
+		let state = chain.state_at(&parent).unwrap();
+		let call = RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount });
+		let extrinsic = types::ExtrinsicBuilder::new(dev_account("alice")).call(call).build();
+		let signed = state.sign(extrinsic, state.nonce_of(&dev_account("alice")));
+		let mut block = BlockBuilder::new(&state, state.system.max_block_weight()).seal(vec![signed]).unwrap();
+		DEV_KEYSTORE.with(|keystore| consensus::seal_block(&state, &mut block, keystore)).unwrap();
+		block
+	}
+
+	// Import a block built with `block_on`, returning its hash.
+	fn extend(chain: &mut Chain<InMemoryBackend>, parent: system::Hash, amount: types::Balance) -> Result<system::Hash, ImportError> {
+		let block = block_on(chain, parent, amount);
+		let hash = block.header.hash();
+		chain.import_block(block).map(|_| hash)
+	}
+
+	fn vote(voter: &str, target_hash: system::Hash, target_number: types::BlockNumber) -> SignedVote {
+		let vote = Vote { target_hash, target_number };
+		DEV_KEYSTORE.with(|keystore| SignedVote::new(vote, dev_account(voter), keystore)).unwrap()
+	}
+
+	fn chain() -> Chain<InMemoryBackend> {
+		let mut genesis = Runtime::new();
+		development_genesis().build(&mut genesis).unwrap();
+		Chain::new(genesis, ChainStore::new(InMemoryBackend::new())).unwrap()
+	}
+
+	#[test]
+	fn more_than_two_thirds_of_the_voters_finalize_a_block() {
+		let mut chain = chain();
+		let genesis_hash = chain.canonical_hash(0).unwrap();
+		let a1 = extend(&mut chain, genesis_hash, 10).unwrap();
+		let a2 = extend(&mut chain, a1, 10).unwrap();
+		let b1 = extend(&mut chain, genesis_hash, 20).unwrap();
+		let late = block_on(&chain, genesis_hash, 30);
+		let mut gadget = FinalityGadget::new(["alice", "bob", "charlie", "dave"].map(dev_account).to_vec());
+		assert_eq!(gadget.threshold(), 3);
+
+		// A vote for `a2` is a vote for `a1` too, so `a1` is the first block with three votes.
+		assert_eq!(gadget.import_vote(&mut chain, vote("alice", a2, 2)), Ok(None));
+		assert_eq!(gadget.import_vote(&mut chain, vote("bob", a2, 2)), Ok(None));
+		assert_eq!(gadget.import_vote(&mut chain, vote("dave", b1, 1)), Ok(None));
+		assert_eq!(gadget.import_vote(&mut chain, vote("charlie", a1, 1)), Ok(Some(a1)));
+		assert_eq!(chain.finalized_head(), (1, a1));
+		assert_eq!(chain.best_block(), (2, a2));
+
+		// Fork B is dropped, and nothing can be built next to the finalized block any more.
+		assert!(chain.block(&b1).is_none());
+		assert_eq!(chain.import_block(late).err(), Some(ImportError::ConflictsWithFinalized));
+		assert_eq!(gadget.import_vote(&mut chain, vote("dave", a1, 1)), Ok(None));
+
+		// Dave changes his vote, which finalizes `a2`.
+		assert_eq!(gadget.import_vote(&mut chain, vote("dave", a2, 2)), Ok(Some(a2)));
+		assert_eq!(chain.finalized_head(), (2, a2));
+		assert_eq!(chain.finalize_block(a1), Err(FinalityError::ConflictsWithFinalized));
+	}
+
+	#[test]
+	fn votes_must_be_signed_by_a_voter_for_a_known_block() {
+		let mut chain = chain();
+		let genesis_hash = chain.canonical_hash(0).unwrap();
+		let a1 = extend(&mut chain, genesis_hash, 10).unwrap();
+		let mut gadget = FinalityGadget::new(["alice", "bob", "charlie"].map(dev_account).to_vec());
+
+		assert_eq!(gadget.import_vote(&mut chain, vote("eve", a1, 1)), Err(FinalityError::NotAVoter(dev_account("eve"))));
+		let mut forged = vote("alice", a1, 1);
+		forged.voter = dev_account("bob");
+		assert_eq!(gadget.import_vote(&mut chain, forged), Err(FinalityError::BadSignature));
+		let mut misnumbered = vote("alice", a1, 1);
+		misnumbered.vote.target_number = 2;
+		assert_eq!(gadget.import_vote(&mut chain, misnumbered), Err(FinalityError::BadSignature));
+		assert_eq!(gadget.import_vote(&mut chain, vote("alice", [7; 32], 1)), Err(FinalityError::UnknownBlock([7; 32])));
+
+		for voter in ["alice", "bob"] {
+			assert_eq!(gadget.import_vote(&mut chain, vote(voter, a1, 1)), Ok(None));
+		}
+		assert_eq!(chain.finalized_head(), (0, genesis_hash));
+	}
+}
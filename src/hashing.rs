@@ -0,0 +1,310 @@
+//! The hash functions a runtime can pick from with `system::Config::Hashing`, like to turn the
+//! content of a document into the claim stored by the proof of existence pallet.
+//!
+//! Like the curve arithmetic of the `crypto` module, BLAKE2b and Keccak are implemented here
+//! because their usual crates can't be fetched in this build environment. SHA-256 comes from
+//! `sha2`.
+
+use sha2::Digest;
+
+use crate::system::Hash;
+
+/// A hash function with a 32 byte output.
+pub trait Hasher {
+	/// Hash `data`.
+	fn hash(data: &[u8]) -> Hash;
+}
+
+/// SHA-256, as specified by FIPS 180-4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sha256;
+
+impl Hasher for Sha256 {
+	fn hash(data: &[u8]) -> Hash {
+		sha2::Sha256::digest(data).into()
+	}
+}
+
+/// BLAKE2b with a 32 byte output and no key, as specified by RFC 7693.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blake2b256;
+
+/// The initialization vector of BLAKE2b, which is the one of SHA-512.
+const BLAKE2B_IV: [u64; 8] = [
+	0x6a09e667f3bcc908,
+	0xbb67ae8584caa73b,
+	0x3c6ef372fe94f82b,
+	0xa54ff53a5f1d36f1,
+	0x510e527fade682d1,
+	0x9b05688c2b3e6c1f,
+	0x1f83d9abfb41bd6b,
+	0x5be0cd19137e2179,
+];
+
+/// The order in which each round of BLAKE2b mixes the words of a block. The last two rounds reuse
+/// the first two permutations.
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+	[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+	[14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+	[11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+	[7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+	[9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+	[2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+	[12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+	[13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+	[6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+	[10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+const BLAKE2B_BLOCK: usize = 128;
+
+impl Blake2b256 {
+	/// Mix the words `x` and `y` of a block into four words of the working vector.
+	fn mix(v: &mut [u64; 16], [a, b, c, d]: [usize; 4], x: u64, y: u64) {
+		v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+		v[d] = (v[d] ^ v[a]).rotate_right(32);
+		v[c] = v[c].wrapping_add(v[d]);
+		v[b] = (v[b] ^ v[c]).rotate_right(24);
+		v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+		v[d] = (v[d] ^ v[a]).rotate_right(16);
+		v[c] = v[c].wrapping_add(v[d]);
+		v[b] = (v[b] ^ v[c]).rotate_right(63);
+	}
+
+	/// Compress a block into the state `h`, after `bytes` bytes of input in total.
+	fn compress(h: &mut [u64; 8], block: &[u8; BLAKE2B_BLOCK], bytes: u128, last: bool) {
+		let mut m = [0u64; 16];
+		for (word, chunk) in m.iter_mut().zip(block.chunks_exact(8)) {
+			*word = u64::from_le_bytes(chunk.try_into().expect("chunks of 8 bytes"));
+		}
+		let mut v = [0u64; 16];
+		v[..8].copy_from_slice(h);
+		v[8..].copy_from_slice(&BLAKE2B_IV);
+		v[12] ^= bytes as u64;
+		v[13] ^= (bytes >> 64) as u64;
+		if last {
+			v[14] = !v[14];
+		}
+
+		for round in 0..12 {
+			let s = &BLAKE2B_SIGMA[round % 10];
+			Self::mix(&mut v, [0, 4, 8, 12], m[s[0]], m[s[1]]);
+			Self::mix(&mut v, [1, 5, 9, 13], m[s[2]], m[s[3]]);
+			Self::mix(&mut v, [2, 6, 10, 14], m[s[4]], m[s[5]]);
+			Self::mix(&mut v, [3, 7, 11, 15], m[s[6]], m[s[7]]);
+			Self::mix(&mut v, [0, 5, 10, 15], m[s[8]], m[s[9]]);
+			Self::mix(&mut v, [1, 6, 11, 12], m[s[10]], m[s[11]]);
+			Self::mix(&mut v, [2, 7, 8, 13], m[s[12]], m[s[13]]);
+			Self::mix(&mut v, [3, 4, 9, 14], m[s[14]], m[s[15]]);
+		}
+		for (i, word) in h.iter_mut().enumerate() {
+			*word ^= v[i] ^ v[i + 8];
+		}
+	}
+}
+
+impl Blake2b256 {
+	/// Hash `data` into `N` bytes, at most 64.
+	fn digest<const N: usize>(data: &[u8]) -> [u8; N] {
+		let mut h = BLAKE2B_IV;
+		// The parameter block: a `N` bytes output, no key, and the sequential mode.
+		h[0] ^= 0x0101_0000 ^ N as u64;
+
+		// The last block is always compressed as the last one, even when it is full, and the input
+		// is a single empty block when there is no data.
+		let full_blocks = data.len().saturating_sub(1) / BLAKE2B_BLOCK;
+		let mut bytes = 0u128;
+		for chunk in data.chunks_exact(BLAKE2B_BLOCK).take(full_blocks) {
+			bytes += BLAKE2B_BLOCK as u128;
+			Self::compress(&mut h, chunk.try_into().expect("a full block"), bytes, false);
+		}
+		let rest = &data[full_blocks * BLAKE2B_BLOCK..];
+		let mut block = [0u8; BLAKE2B_BLOCK];
+		block[..rest.len()].copy_from_slice(rest);
+		Self::compress(&mut h, &block, bytes + rest.len() as u128, true);
+
+		let mut output = [0u8; 64];
+		for (chunk, word) in output.chunks_exact_mut(8).zip(h) {
+			chunk.copy_from_slice(&word.to_le_bytes());
+		}
+		let mut hash = [0u8; N];
+		hash.copy_from_slice(&output[..N]);
+		hash
+	}
+}
+
+impl Hasher for Blake2b256 {
+	fn hash(data: &[u8]) -> Hash {
+		Self::digest(data)
+	}
+}
+
+/// BLAKE2b with a 64 byte output and no key, like to compute the checksum of SS58 addresses.
+pub fn blake2b_512(data: &[u8]) -> [u8; 64] {
+	Blake2b256::digest(data)
+}
+
+/// Keccak-256, the hash of Ethereum. It is the Keccak submitted to the SHA-3 competition, which
+/// pads the input differently than the standardized SHA3-256.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keccak256;
+
+/// The constants added to the state by each round of Keccak-f[1600].
+const KECCAK_ROUND_CONSTANTS: [u64; 24] = [
+	0x0000000000000001,
+	0x0000000000008082,
+	0x800000000000808a,
+	0x8000000080008000,
+	0x000000000000808b,
+	0x0000000080000001,
+	0x8000000080008081,
+	0x8000000000008009,
+	0x000000000000008a,
+	0x0000000000000088,
+	0x0000000080008009,
+	0x000000008000000a,
+	0x000000008000808b,
+	0x800000000000008b,
+	0x8000000000008089,
+	0x8000000000008003,
+	0x8000000000008002,
+	0x8000000000000080,
+	0x000000000000800a,
+	0x800000008000000a,
+	0x8000000080008081,
+	0x8000000000008080,
+	0x0000000080000001,
+	0x8000000080008008,
+];
+
+/// The rotation of each lane, in the order the lanes are visited by the combined rho and pi steps.
+const KECCAK_ROTATIONS: [u32; 24] = [1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44];
+
+/// The lanes visited by the combined rho and pi steps, each moved to the position of the next.
+const KECCAK_PI_LANES: [usize; 24] = [10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1];
+
+/// The number of bytes absorbed per permutation, for a 256 bit capacity of the sponge.
+const KECCAK_RATE: usize = 136;
+
+impl Keccak256 {
+	/// Apply the Keccak-f[1600] permutation to the state.
+	fn permute(state: &mut [u64; 25]) {
+		for round_constant in KECCAK_ROUND_CONSTANTS {
+			// Theta: mix the parity of the columns into every lane.
+			let mut parity = [0u64; 5];
+			for (x, column) in parity.iter_mut().enumerate() {
+				*column = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+			}
+			for x in 0..5 {
+				let mix = parity[(x + 4) % 5] ^ parity[(x + 1) % 5].rotate_left(1);
+				for y in (0..25).step_by(5) {
+					state[y + x] ^= mix;
+				}
+			}
+
+			// Rho and pi: rotate the lanes and move them around.
+			let mut lane = state[1];
+			for (&position, rotation) in KECCAK_PI_LANES.iter().zip(KECCAK_ROTATIONS) {
+				let next = state[position];
+				state[position] = lane.rotate_left(rotation);
+				lane = next;
+			}
+
+			// Chi: mix each row non-linearly.
+			for y in (0..25).step_by(5) {
+				let row = [state[y], state[y + 1], state[y + 2], state[y + 3], state[y + 4]];
+				for x in 0..5 {
+					state[y + x] ^= !row[(x + 1) % 5] & row[(x + 2) % 5];
+				}
+			}
+
+			// Iota: break the symmetry of the rounds.
+			state[0] ^= round_constant;
+		}
+	}
+
+	/// Absorb a block of `KECCAK_RATE` bytes into the state, and permute it.
+	fn absorb(state: &mut [u64; 25], block: &[u8]) {
+		for (lane, chunk) in state.iter_mut().zip(block.chunks_exact(8)) {
+			*lane ^= u64::from_le_bytes(chunk.try_into().expect("chunks of 8 bytes"));
+		}
+		Self::permute(state);
+	}
+}
+
+impl Hasher for Keccak256 {
+	fn hash(data: &[u8]) -> Hash {
+		let mut state = [0u64; 25];
+		let mut blocks = data.chunks_exact(KECCAK_RATE);
+		for block in &mut blocks {
+			Self::absorb(&mut state, block);
+		}
+		// The padding always adds a last block, with a first and a last bit set.
+		let rest = blocks.remainder();
+		let mut block = [0u8; KECCAK_RATE];
+		block[..rest.len()].copy_from_slice(rest);
+		block[rest.len()] ^= 0x01;
+		block[KECCAK_RATE - 1] ^= 0x80;
+		Self::absorb(&mut state, &block);
+
+		let mut hash = [0u8; 32];
+		for (chunk, lane) in hash.chunks_exact_mut(8).zip(state) {
+			chunk.copy_from_slice(&lane.to_le_bytes());
+		}
+		hash
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{blake2b_512, Blake2b256, Hash, Hasher, Keccak256, Sha256};
+
+	type HashFn = fn(&[u8]) -> Hash;
+
+	fn hex(hash: Hash) -> String {
+		hash.iter().map(|byte| format!("{byte:02x}")).collect()
+	}
+
+	#[test]
+	fn hashers_match_their_test_vectors() {
+		let vectors: [(HashFn, &[u8], &str); 6] = [
+			(Sha256::hash, b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+			(Sha256::hash, b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+			(Blake2b256::hash, b"", "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"),
+			(Blake2b256::hash, b"abc", "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319"),
+			(Keccak256::hash, b"", "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+			(Keccak256::hash, b"abc", "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+		];
+		for (hash, data, expected) in vectors {
+			assert_eq!(hex(hash(data)), expected);
+		}
+	}
+
+	#[test]
+	fn hashers_handle_inputs_across_block_boundaries() {
+		// Inputs filling exactly one block, and spilling into the next, take different paths.
+		let vectors: [(HashFn, usize, &str); 7] = [
+			(Blake2b256::hash, 128, "ae2aa48507885c4c950fb809b2076f959cde9f8ea6da260d9a3587df33dac450"),
+			(Blake2b256::hash, 129, "2f64744a6de0d2c0b56e64cf6e29a5aaa255010d415d51c75ccc82f73dccd865"),
+			(Blake2b256::hash, 300, "3c1292de00a518e36823f9ff908ac2da46be38718c018713403461df077e15f6"),
+			(Keccak256::hash, 135, "34367dc248bbd832f4e3e69dfaac2f92638bd0bbd18f2912ba4ef454919cf446"),
+			(Keccak256::hash, 136, "a6c4d403279fe3e0af03729caada8374b5ca54d8065329a3ebcaeb4b60aa386e"),
+			(Keccak256::hash, 137, "d869f639c7046b4929fc92a4d988a8b22c55fbadb802c0c66ebcd484f1915f39"),
+			(Keccak256::hash, 300, "5b7e0e47a96f32a88b4f14ca177982790807c40e1a105742ba0fc1babe1ef826"),
+		];
+		for (hash, length, expected) in vectors {
+			assert_eq!(hex(hash(&vec![b'a'; length])), expected, "{length}");
+		}
+	}
+
+	#[test]
+	fn blake2b_512_matches_its_test_vector() {
+		let hash = blake2b_512(b"abc");
+		let hex = hash.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+		assert_eq!(
+			hex,
+			"ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+			 7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+		);
+	}
+}
@@ -0,0 +1,495 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use num::traits::{CheckedSub, Saturating, Zero};
+
+use crate::codec::{self, Decode, Encode};
+use crate::storage::{storage_key, storage_map_key, StorageBackend};
+use crate::support::{ensure_root, DispatchError, DispatchResult, Events, OriginFor, StorageMap, StorageValue};
+
+pub trait Config: crate::balances::Config {
+	/// The maximum length of a name, in bytes.
+	const MAX_NAME_LENGTH: usize;
+	/// The maximum length of each field of an identity, in bytes.
+	const MAX_FIELD_LENGTH: usize;
+	/// The deposit reserved for every identity.
+	const BASIC_DEPOSIT: Self::Balance;
+	/// The deposit reserved for every byte stored in the fields of an identity, on top of the basic
+	/// deposit.
+	const BYTE_DEPOSIT: Self::Balance;
+	/// The maximum number of registrars.
+	const MAX_REGISTRARS: u32;
+}
+
+/// The information an account publishes about itself.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IdentityInfo {
+	pub display: String,
+	pub email: String,
+	pub web: String,
+}
+
+impl IdentityInfo {
+	/// The number of bytes stored in the fields of the identity.
+	pub fn len(&self) -> usize {
+		self.display.len() + self.email.len() + self.web.len()
+	}
+
+	/// Whether all the fields of the identity are empty.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+impl Encode for IdentityInfo {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.display.encode_to(dest);
+		self.email.encode_to(dest);
+		self.web.encode_to(dest);
+	}
+}
+
+impl Decode for IdentityInfo {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		Ok(Self { display: String::decode(input)?, email: String::decode(input)?, web: String::decode(input)? })
+	}
+}
+
+/// The judgement of a registrar on the identity of an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+	/// The registrar can't tell whether the identity is accurate.
+	Unknown,
+	/// The identity looks accurate, but the registrar didn't check it in depth.
+	Reasonable,
+	/// The registrar checked that the identity is accurate.
+	KnownGood,
+	/// The identity is not accurate.
+	Erroneous,
+}
+
+impl Encode for Judgement {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		let index: u8 = match self {
+			Judgement::Unknown => 0,
+			Judgement::Reasonable => 1,
+			Judgement::KnownGood => 2,
+			Judgement::Erroneous => 3,
+		};
+		dest.push(index);
+	}
+}
+
+impl Decode for Judgement {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		match u8::decode(input)? {
+			0 => Ok(Judgement::Unknown),
+			1 => Ok(Judgement::Reasonable),
+			2 => Ok(Judgement::KnownGood),
+			3 => Ok(Judgement::Erroneous),
+			_ => Err(codec::Error::Invalid),
+		}
+	}
+}
+
+/// The identity of an account, with the deposit reserved for it and the judgements of registrars.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Registration<Balance> {
+	pub info: IdentityInfo,
+	/// The deposit reserved from the account, which is returned when the identity is cleared.
+	pub deposit: Balance,
+	/// The judgements given on the identity, keyed by the index of their registrar.
+	pub judgements: Vec<(u32, Judgement)>,
+}
+
+/// The events emitted by the identity pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, Balance> {
+	/// An account set its identity, with the deposit now reserved for it.
+	IdentitySet { who: AccountId, deposit: Balance },
+	/// An account cleared its identity, and its deposit was returned.
+	IdentityCleared { who: AccountId, deposit: Balance },
+	/// A registrar was added, with its index.
+	RegistrarAdded { registrar_index: u32 },
+	/// A registrar gave a judgement on the identity of an account.
+	JudgementGiven { target: AccountId, registrar_index: u32 },
+}
+
+/// The errors returned by the identity pallet for identities. Names return string errors.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// A field of the identity is longer than `Config::MAX_FIELD_LENGTH`.
+	FieldTooLong,
+	/// The account has no identity.
+	NoIdentity,
+	/// There are already `Config::MAX_REGISTRARS` registrars.
+	TooManyRegistrars,
+	/// The caller is not the registrar with this index.
+	InvalidRegistrar,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::FieldTooLong => "FieldTooLong",
+			Error::NoIdentity => "NoIdentity",
+			Error::TooManyRegistrars => "TooManyRegistrars",
+			Error::InvalidRegistrar => "InvalidRegistrar",
+		};
+		DispatchError::Module { pallet: "identity", error }
+	}
+}
+
+/// The events of the identity pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> =
+	Event<<T as crate::system::Config>::AccountId, <T as crate::balances::Config>::Balance>;
+
+/// This is the Identity Module.
+/// It allows accounts to register a human-readable name, which is unique across all accounts.
+///
+/// Accounts can also set an identity, for which a deposit proportional to its size is reserved in
+/// the balances pallet. Registrars, added by the root origin, give judgements on identities.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The name registered by each account.
+	names: StorageMap<T::AccountId, String>,
+	/// The account which registered each name, used for reverse lookups.
+	accounts: StorageMap<String, T::AccountId>,
+	/// The identity set by each account.
+	identities: StorageMap<T::AccountId, Registration<T::Balance>>,
+	/// The registrars, whose index is their position.
+	registrars: StorageValue<Vec<T::AccountId>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId, T::Balance>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Set the name of the `caller`, replacing the name they had before.
+	/// This function will return an error if the name is too long, or if another account already
+	/// has that name.
+	#[call_index = 0]
+	#[weight = 5_000]
+	pub fn set_name(&mut self, caller: T::AccountId, name: String) -> DispatchResult {
+		if name.len() > T::MAX_NAME_LENGTH {
+			return Err("name too long".into());
+		}
+		match self.accounts.get(&name) {
+			Some(owner) if owner != &caller => return Err("name taken".into()),
+			Some(_) => return Ok(()),
+			None => {},
+		}
+
+		if let Some(old_name) = self.names.insert(caller.clone(), name.clone()) {
+			self.accounts.remove(&old_name);
+		}
+		self.accounts.insert(name, caller);
+		Ok(())
+	}
+
+	/// Clear the name of the `caller`, so it can be registered by any account.
+	/// This function will return an error if the caller has no name.
+	#[call_index = 1]
+	#[weight = 5_000]
+	pub fn clear_name(&mut self, caller: T::AccountId) -> DispatchResult {
+		let name = self.names.remove(&caller).ok_or("Account has no name")?;
+		self.accounts.remove(&name);
+		Ok(())
+	}
+
+	/// Set the identity of the `caller`, replacing the identity they had before and its
+	/// judgements. The deposit reserved for the identity is adjusted to its size.
+	/// This function will return an error if a field is too long, or if the caller can't reserve
+	/// the deposit.
+	#[call_index = 2]
+	#[weight = 10_000]
+	pub fn set_identity(
+		&mut self,
+		caller: T::AccountId,
+		info: IdentityInfo,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		if [&info.display, &info.email, &info.web].iter().any(|field| field.len() > T::MAX_FIELD_LENGTH) {
+			return Err(Error::FieldTooLong.into());
+		}
+
+		let deposit = Self::deposit(&info);
+		let old_deposit = self.identities.get(&caller).map_or(T::Balance::zero(), |identity| identity.deposit);
+		match deposit.checked_sub(&old_deposit) {
+			Some(extra) => balances.reserve(&caller, extra)?,
+			None => {
+				balances.unreserve(&caller, old_deposit.checked_sub(&deposit).unwrap_or_else(Zero::zero));
+			},
+		}
+
+		self.identities.insert(caller.clone(), Registration { info, deposit, judgements: Vec::new() });
+		self.deposit_event(Event::IdentitySet { who: caller, deposit });
+		Ok(())
+	}
+
+	/// Clear the identity of the `caller`, returning its deposit.
+	/// This function will return an error if the caller has no identity.
+	#[call_index = 3]
+	#[weight = 5_000]
+	pub fn clear_identity(&mut self, caller: T::AccountId, balances: &mut crate::balances::Pallet<T>) -> DispatchResult {
+		let identity = self.identities.remove(&caller).ok_or(Error::NoIdentity)?;
+		balances.unreserve(&caller, identity.deposit);
+		self.deposit_event(Event::IdentityCleared { who: caller, deposit: identity.deposit });
+		Ok(())
+	}
+
+	/// Add `account` as a registrar, with the next index. This can only be called by the root
+	/// origin.
+	/// This function will return an error if there are too many registrars.
+	#[call_index = 4]
+	#[weight = 5_000]
+	pub fn add_registrar(&mut self, origin: OriginFor<T>, account: T::AccountId) -> DispatchResult {
+		ensure_root(origin)?;
+		if self.registrars.len() >= T::MAX_REGISTRARS as usize {
+			return Err(Error::TooManyRegistrars.into());
+		}
+
+		self.registrars.get_mut().push(account);
+		let registrar_index = self.registrars.len() as u32 - 1;
+		self.deposit_event(Event::RegistrarAdded { registrar_index });
+		Ok(())
+	}
+
+	/// Give `judgement` on the identity of `target`, as the registrar at `registrar_index`. This
+	/// replaces the judgement the registrar gave before.
+	/// This function will return an error if the caller is not that registrar, or if the target
+	/// has no identity.
+	#[call_index = 5]
+	#[weight = 5_000]
+	pub fn provide_judgement(
+		&mut self,
+		caller: T::AccountId,
+		registrar_index: u32,
+		target: T::AccountId,
+		judgement: Judgement,
+	) -> DispatchResult {
+		if self.registrars.get(registrar_index as usize) != Some(&caller) {
+			return Err(Error::InvalidRegistrar.into());
+		}
+		let identity = self.identities.get_mut(&target).ok_or(Error::NoIdentity)?;
+
+		match identity.judgements.iter_mut().find(|(index, _)| *index == registrar_index) {
+			Some((_, given)) => *given = judgement,
+			None => identity.judgements.push((registrar_index, judgement)),
+		}
+		self.deposit_event(Event::JudgementGiven { target, registrar_index });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Identity Module.
+	pub fn new() -> Self {
+		Self {
+			names: StorageMap::new(),
+			accounts: StorageMap::new(),
+			identities: StorageMap::new(),
+			registrars: StorageValue::new(Vec::new()),
+			events: Events::new(),
+		}
+	}
+
+	/// Get the name (if any) of an account.
+	pub fn name_of(&self, who: &T::AccountId) -> Option<&String> {
+		self.names.get(who)
+	}
+
+	/// Get the account (if any) which registered a name.
+	pub fn account_of_name(&self, name: &str) -> Option<&T::AccountId> {
+		self.accounts.get(name)
+	}
+
+	/// Get the identity (if any) of an account.
+	pub fn identity(&self, who: &T::AccountId) -> Option<&Registration<T::Balance>> {
+		self.identities.get(who)
+	}
+
+	/// Get the registrars, whose index is their position.
+	pub fn registrars(&self) -> &[T::AccountId] {
+		&self.registrars
+	}
+
+	/// The deposit reserved for `info`: the basic deposit, and the byte deposit for every byte of
+	/// its fields.
+	pub fn deposit(info: &IdentityInfo) -> T::Balance {
+		(0..info.len()).fold(T::BASIC_DEPOSIT, |deposit, _| deposit.saturating_add(T::BYTE_DEPOSIT))
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+{
+	/// Write the state of the Identity Module into `state`, so the runtime can commit to it. The
+	/// account of each name is left out, since it follows from the names of the accounts.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for (who, name) in &self.names {
+			state.insert(storage_map_key("identity", "names", who), name.encode());
+		}
+		for (who, identity) in &self.identities {
+			let value = (&identity.info, (identity.deposit, &identity.judgements)).encode();
+			state.insert(storage_map_key("identity", "identities", who), value);
+		}
+		state.insert(storage_key("identity", "registrars"), self.registrars.encode());
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The initial state of the Identity Module, see `RuntimeGenesisConfig` of `#[macros::runtime]`.
+pub struct GenesisConfig<T: Config> {
+	/// The name registered by each account at genesis.
+	pub names: Vec<(T::AccountId, String)>,
+}
+
+impl<T: Config> GenesisConfig<T> {
+	/// Register the name of each account, like `set_name` does. This fails if a name is too long,
+	/// or if two accounts have the same name.
+	pub fn build(&self, identity: &mut Pallet<T>) -> DispatchResult {
+		for (who, name) in &self.names {
+			identity.set_name(who.clone(), name.clone())?;
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { names: Vec::new() }
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, IdentityInfo, Judgement};
+	use crate::balances;
+	use crate::support::{DispatchError, RawOrigin};
+
+	crate::construct_mock_runtime!(struct TestConfig: balances);
+
+	impl super::Config for TestConfig {
+		const MAX_NAME_LENGTH: usize = 8;
+		const MAX_FIELD_LENGTH: usize = 16;
+		const BASIC_DEPOSIT: Self::Balance = 10;
+		const BYTE_DEPOSIT: Self::Balance = 1;
+		const MAX_REGISTRARS: u32 = 1;
+	}
+
+	fn info(display: &str, email: &str) -> IdentityInfo {
+		IdentityInfo { display: display.to_string(), email: email.to_string(), web: String::new() }
+	}
+
+	#[test]
+	fn set_name() {
+		let mut identity = super::Pallet::<TestConfig>::new();
+		assert_eq!(identity.name_of(&"alice"), None);
+
+		assert_eq!(identity.set_name("alice", "Alice".to_string()), Ok(()));
+		assert_eq!(identity.name_of(&"alice"), Some(&"Alice".to_string()));
+
+		// Setting a new name frees the old one.
+		assert_eq!(identity.set_name("alice", "Ally".to_string()), Ok(()));
+		assert_eq!(identity.name_of(&"alice"), Some(&"Ally".to_string()));
+		assert_eq!(identity.account_of_name("Alice"), None);
+
+		assert_eq!(identity.set_name("alice", "Alice Liddell".to_string()), Err("name too long".into()));
+		assert_eq!(identity.name_of(&"alice"), Some(&"Ally".to_string()));
+	}
+
+	#[test]
+	fn names_are_unique() {
+		let mut identity = super::Pallet::<TestConfig>::new();
+		assert_eq!(identity.set_name("alice", "Alice".to_string()), Ok(()));
+
+		assert_eq!(identity.set_name("bob", "Alice".to_string()), Err("name taken".into()));
+		assert_eq!(identity.name_of(&"bob"), None);
+		assert_eq!(identity.account_of_name("Alice"), Some(&"alice"));
+
+		// Setting the same name again is not a conflict.
+		assert_eq!(identity.set_name("alice", "Alice".to_string()), Ok(()));
+	}
+
+	#[test]
+	fn clear_name() {
+		let mut identity = super::Pallet::<TestConfig>::new();
+		assert_eq!(identity.clear_name("alice"), Err("Account has no name".into()));
+
+		assert_eq!(identity.set_name("alice", "Alice".to_string()), Ok(()));
+		assert_eq!(identity.clear_name("alice"), Ok(()));
+		assert_eq!(identity.name_of(&"alice"), None);
+		assert_eq!(identity.account_of_name("Alice"), None);
+
+		// A cleared name can be taken by another account.
+		assert_eq!(identity.set_name("bob", "Alice".to_string()), Ok(()));
+		assert_eq!(identity.account_of_name("Alice"), Some(&"bob"));
+	}
+
+	#[test]
+	fn account_of_name() {
+		let mut identity = super::Pallet::<TestConfig>::new();
+		assert_eq!(identity.set_name("alice", "Alice".to_string()), Ok(()));
+		assert_eq!(identity.set_name("bob", "Bob".to_string()), Ok(()));
+
+		assert_eq!(identity.account_of_name("Alice"), Some(&"alice"));
+		assert_eq!(identity.account_of_name("Bob"), Some(&"bob"));
+		assert_eq!(identity.account_of_name("Charlie"), None);
+	}
+
+	#[test]
+	fn identity_reserves_a_deposit_for_its_size() {
+		let mut identity = super::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		balances.set_balance("alice", 100);
+
+		let too_long = info("Alice", "alice@wonderland.example");
+		assert_eq!(identity.set_identity("alice", too_long, &mut balances), Err(Error::FieldTooLong.into()));
+		assert_eq!(identity.set_identity("alice", info("Alice", "a@b.c"), &mut balances), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 20);
+
+		// A smaller identity returns part of the deposit.
+		assert_eq!(identity.set_identity("alice", info("Al", ""), &mut balances), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 12);
+		assert_eq!(balances.balance(&"alice"), 88);
+
+		assert_eq!(identity.clear_identity("alice", &mut balances), Ok(()));
+		assert_eq!(identity.clear_identity("alice", &mut balances), Err(Error::NoIdentity.into()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(balances.balance(&"alice"), 100);
+	}
+
+	#[test]
+	fn registrars_provide_judgements() {
+		let mut identity = super::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		balances.set_balance("alice", 100);
+		assert_eq!(identity.set_identity("alice", info("Alice", ""), &mut balances), Ok(()));
+
+		assert_eq!(identity.add_registrar(RawOrigin::Signed("bob"), "bob"), Err(DispatchError::BadOrigin));
+		assert_eq!(identity.add_registrar(RawOrigin::Root, "bob"), Ok(()));
+		assert_eq!(identity.add_registrar(RawOrigin::Root, "charlie"), Err(Error::TooManyRegistrars.into()));
+
+		let result = identity.provide_judgement("charlie", 0, "alice", Judgement::KnownGood);
+		assert_eq!(result, Err(Error::InvalidRegistrar.into()));
+		let result = identity.provide_judgement("bob", 0, "dave", Judgement::KnownGood);
+		assert_eq!(result, Err(Error::NoIdentity.into()));
+		assert_eq!(identity.provide_judgement("bob", 0, "alice", Judgement::Reasonable), Ok(()));
+		assert_eq!(identity.provide_judgement("bob", 0, "alice", Judgement::KnownGood), Ok(()));
+		assert_eq!(identity.identity(&"alice").unwrap().judgements, [(0, Judgement::KnownGood)]);
+
+		// Setting the identity again drops its judgements.
+		assert_eq!(identity.set_identity("alice", info("Ally", ""), &mut balances), Ok(()));
+		assert!(identity.identity(&"alice").unwrap().judgements.is_empty());
+	}
+}
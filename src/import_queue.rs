@@ -0,0 +1,291 @@
+#[cfg(any(test, feature = "network"))]
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::consensus::{self, ConsensusError};
+use crate::support::{self, BlockExecutionReport, DispatchError};
+use crate::{system, types, Runtime};
+
+/// The error `execute_block` refuses a block with when the state it leaves doesn't match the state
+/// root of its header, which is the last check of the execution.
+const STATE_ROOT_MISMATCH: DispatchError = DispatchError::Other("state root does not match the state");
+
+/// The reasons a block is refused by `BlockImport::import_block`, by the step of the import which
+/// refused it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+	/// The block doesn't follow its parent.
+	UnexpectedBlockNumber { expected: types::BlockNumber, found: types::BlockNumber },
+	/// The parent of the block is not known.
+	UnknownParent { parent_hash: system::Hash },
+	/// The block was imported already.
+	AlreadyKnown,
+	/// The block is on a fork which doesn't include the finalized block, see `Chain`.
+	ConflictsWithFinalized,
+	/// The block wasn't sealed by the authority whose turn it is, see `consensus`.
+	Consensus(ConsensusError),
+	/// The extrinsics root of the header doesn't commit to the extrinsics of the block.
+	ExtrinsicsRootMismatch,
+	/// The extrinsic at `index` in the block isn't signed by its caller.
+	BadSignature { index: usize, error: DispatchError },
+	/// The block failed to execute, like when an inherent fails or the block is too heavy.
+	Execution(DispatchError),
+	/// The state left by the block doesn't match the state root of its header.
+	StateRootMismatch,
+	/// The block was executed, but it couldn't be stored.
+	Storage(String),
+}
+
+impl fmt::Display for ImportError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ImportError::UnexpectedBlockNumber { expected, found } => {
+				write!(f, "expected block #{expected}, found block #{found}")
+			},
+			ImportError::UnknownParent { .. } => write!(f, "the parent of the block is not known"),
+			ImportError::AlreadyKnown => write!(f, "the block was imported already"),
+			ImportError::ConflictsWithFinalized => write!(f, "the block is not built on top of the finalized block"),
+			ImportError::Consensus(error) => write!(f, "invalid seal: {error}"),
+			ImportError::ExtrinsicsRootMismatch => write!(f, "the extrinsics root does not match the extrinsics"),
+			ImportError::BadSignature { index, error } => write!(f, "extrinsic {index} has a bad signature: {error}"),
+			ImportError::Execution(error) => write!(f, "the block failed to execute: {error}"),
+			ImportError::StateRootMismatch => write!(f, "the state root does not match the state"),
+			ImportError::Storage(error) => write!(f, "cannot store the block: {error}"),
+		}
+	}
+}
+
+/// Something blocks can be imported into, on top of any block it knows, see `Chain`.
+///
+/// A block goes through these steps, and is refused at the first one which fails:
+/// 1. its header must link to its parent, be sealed by the authority whose turn it is, and commit
+///    to the extrinsics of the block,
+/// 2. every extrinsic must be signed by its caller, with the public keys known before the block,
+/// 3. it is executed on the state of its parent, and the state it leaves must match its state root,
+/// 4. it is stored, and only then it can become the best block.
+#[cfg(any(test, feature = "network"))]
+pub trait BlockImport {
+	/// Whether blocks can be imported on top of the block with `hash`.
+	fn is_known(&self, hash: &system::Hash) -> bool;
+
+	/// The number of the finalized block, which every block imported must be higher than.
+	fn finalized_number(&self) -> types::BlockNumber;
+
+	/// Verify `block`, then execute and store it. Nothing is changed when the block is refused.
+	fn import_block(&mut self, block: &types::Block) -> Result<BlockExecutionReport, ImportError>;
+}
+
+/// Verify `block` on top of the best block of `parent`, see `verify_block`, then execute it on
+/// `parent`. Returns the state left by the block, which must match its state root, and the outcome
+/// of its extrinsics.
+pub fn execute_verified(parent: Runtime, block: &types::Block) -> Result<(Runtime, BlockExecutionReport), ImportError> {
+	verify_block(&parent, block)?;
+	let mut state = parent;
+	let report = state.execute_block(block).map_err(|error| match error {
+		STATE_ROOT_MISMATCH => ImportError::StateRootMismatch,
+		error => ImportError::Execution(error),
+	})?;
+	Ok((state, report))
+}
+
+/// Check that `block` can be imported on top of the best block of `runtime`, without executing it:
+/// its header, its seal and the signatures of its extrinsics.
+pub fn verify_block(runtime: &Runtime, block: &types::Block) -> Result<(), ImportError> {
+	let expected = runtime.system.block_number() + 1;
+	if block.header.block_number != expected {
+		return Err(ImportError::UnexpectedBlockNumber { expected, found: block.header.block_number });
+	}
+	if block.header.parent_hash != runtime.system.best_block_hash() {
+		return Err(ImportError::UnknownParent { parent_hash: block.header.parent_hash });
+	}
+	consensus::verify_header(runtime, &block.header).map_err(ImportError::Consensus)?;
+	if block.header.extrinsics_root != support::extrinsics_root(&block.inherents, &block.extrinsics) {
+		return Err(ImportError::ExtrinsicsRootMismatch);
+	}
+	for (index, signed) in block.extrinsics.iter().enumerate() {
+		runtime.check_signature(signed).map_err(|error| ImportError::BadSignature { index, error })?;
+	}
+	Ok(())
+}
+
+/// Blocks waiting to be imported, which may arrive in any order.
+///
+/// Blocks are imported in the order of their block number, and blocks with the same number in the
+/// order they were queued, so the same blocks always give the same chain. A block whose parent
+/// isn't known yet waits for its ancestors.
+#[cfg(any(test, feature = "network"))]
+#[derive(Default)]
+pub struct ImportQueue {
+	blocks: BTreeMap<types::BlockNumber, Vec<types::Block>>,
+}
+
+#[cfg(any(test, feature = "network"))]
+impl ImportQueue {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Queue `block` to be imported by the next call to `import`.
+	pub fn push(&mut self, block: types::Block) {
+		self.blocks.entry(block.header.block_number).or_default().push(block);
+	}
+
+	/// Import the queued blocks whose parent is known to `import`, and the blocks built on them,
+	/// until every block left waits for a missing parent. Blocks on every fork are imported, and a
+	/// block which is refused is dropped. Blocks already known, and blocks which are not higher than
+	/// the finalized block, are dropped without being tried.
+	///
+	/// Returns the hash of each block tried, in order, with the outcome of its import.
+	#[allow(clippy::type_complexity)]
+	pub fn import(
+		&mut self,
+		import: &mut impl BlockImport,
+	) -> Vec<(system::Hash, Result<BlockExecutionReport, ImportError>)> {
+		let mut outcomes = Vec::new();
+		loop {
+			let finalized = import.finalized_number();
+			self.blocks.retain(|&block_number, blocks| {
+				blocks.retain(|block| !import.is_known(&block.header.hash()));
+				block_number > finalized && !blocks.is_empty()
+			});
+			let next = self.blocks.iter().find_map(|(&block_number, blocks)| {
+				let index = blocks.iter().position(|block| import.is_known(&block.header.parent_hash))?;
+				Some((block_number, index))
+			});
+			let Some((block_number, index)) = next else {
+				return outcomes;
+			};
+			let block = self.blocks.get_mut(&block_number).expect("the block is queued").remove(index);
+			let outcome = import.import_block(&block);
+			outcomes.push((block.header.hash(), outcome));
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ImportError, ImportQueue};
+	use crate::block_builder::BlockBuilder;
+	use crate::chain::Chain;
+	use crate::chain_store::ChainStore;
+	use crate::codec::Encode;
+	use crate::consensus::{self, ConsensusError};
+	use crate::storage::InMemoryBackend;
+	use crate::support::{self, DispatchError};
+	use crate::{balances, dev_account, development_genesis, timestamp, types, Runtime, RuntimeApi, RuntimeCall, DEV_KEYSTORE};
+
+	fn transfer(runtime: &Runtime, to: &str, amount: types::Balance) -> types::SignedExtrinsic {
+		let call = RuntimeCall::balances(balances::Call::transfer { to: dev_account(to), amount });
+		let extrinsic = types::ExtrinsicBuilder::new(dev_account("alice")).call(call).build();
+		runtime.sign(extrinsic, runtime.nonce_of(&dev_account("alice")))
+	}
+
+	fn genesis() -> Runtime {
+		let mut runtime = Runtime::new();
+		development_genesis().build(&mut runtime).unwrap();
+		runtime
+	}
+
+	fn new_chain() -> Chain<InMemoryBackend> {
+		Chain::new(genesis(), ChainStore::new(InMemoryBackend::new())).unwrap()
+	}
+
+	// Build and seal a block of `extrinsics` on top of `runtime`.
+	fn build(runtime: &Runtime, extrinsics: Vec<types::SignedExtrinsic>) -> types::Block {
+		let block = BlockBuilder::new(runtime, runtime.system.max_block_weight()).seal(extrinsics).unwrap();
+		resealed(runtime, block)
+	}
+
+	// Seal `block` again, once its header was changed.
+	fn resealed(parent: &Runtime, mut block: types::Block) -> types::Block {
+		block.header.digest.clear();
+		DEV_KEYSTORE.with(|keystore| consensus::seal_block(parent, &mut block, keystore)).unwrap();
+		block
+	}
+
+	#[test]
+	fn invalid_blocks_are_refused_at_each_step() {
+		let mut chain = new_chain();
+		let parent = chain.runtime().clone();
+		let block = build(&parent, vec![transfer(&parent, "bob", 10)]);
+
+		let mut wrong = block.clone();
+		wrong.header.block_number = 2;
+		assert_eq!(chain.import_block(wrong.clone()).err(), Some(ImportError::UnexpectedBlockNumber { expected: 1, found: 2 }));
+		let mut wrong = block.clone();
+		wrong.header.parent_hash = [1; 32];
+		assert_eq!(chain.import_block(wrong.clone()).err(), Some(ImportError::UnknownParent { parent_hash: [1; 32] }));
+		let mut wrong = block.clone();
+		wrong.header.digest.pop();
+		assert_eq!(chain.import_block(wrong.clone()).err(), Some(ImportError::Consensus(ConsensusError::MissingSeal)));
+		let mut wrong = block.clone();
+		wrong.extrinsics[0].extrinsic.tip += 1;
+		assert_eq!(chain.import_block(wrong.clone()).err(), Some(ImportError::ExtrinsicsRootMismatch));
+		// The extrinsics root is fixed up, but the signature no longer matches the extrinsic.
+		wrong.header.extrinsics_root = support::extrinsics_root(&wrong.inherents, &wrong.extrinsics);
+		let wrong = resealed(&parent, wrong);
+		assert!(matches!(chain.import_block(wrong.clone()), Err(ImportError::BadSignature { index: 0, .. })));
+		let mut wrong = block.clone();
+		wrong.header.state_root = [0; 32];
+		let wrong = resealed(&parent, wrong);
+		assert_eq!(chain.import_block(wrong.clone()).err(), Some(ImportError::StateRootMismatch));
+		// The timestamp can only be set once per block.
+		let mut wrong = block.clone();
+		wrong.inherents = vec![RuntimeCall::timestamp(timestamp::Call::set { now: 6_000 }); 2];
+		wrong.header.extrinsics_root = support::extrinsics_root(&wrong.inherents, &wrong.extrinsics);
+		let wrong = resealed(&parent, wrong);
+		assert!(matches!(chain.import_block(wrong.clone()), Err(ImportError::Execution(DispatchError::Module { .. }))));
+
+		// Nothing was changed by the refused blocks, so the valid block is imported.
+		assert_eq!(chain.best_block().0, 0);
+		let report = chain.import_block(block.clone()).expect("valid block");
+		assert!(report.all_succeeded());
+		assert_eq!(chain.runtime().balance_of(&dev_account("bob")), 10);
+		assert_eq!(chain.canonical_block(1).map(|stored| stored.encode()), Some(block.encode()));
+	}
+
+	#[test]
+	fn queued_blocks_are_imported_in_order() {
+		let genesis = genesis();
+		let mut author = genesis.clone();
+		let (mut blocks, mut parents) = (Vec::new(), Vec::new());
+		for amount in [10, 20, 30] {
+			let block = build(&author, vec![transfer(&author, "bob", amount)]);
+			parents.push(author.clone());
+			author.execute_block(&block).unwrap();
+			blocks.push(block);
+		}
+		// A competing block 2, which is refused since its state root is wrong.
+		let mut invalid = blocks[1].clone();
+		invalid.header.state_root = [0; 32];
+		let invalid = resealed(&parents[1], invalid);
+
+		let mut queue = ImportQueue::new();
+		for block in [blocks[2].clone(), invalid.clone(), blocks[1].clone()] {
+			queue.push(block);
+		}
+		let mut chain = new_chain();
+		// Block 1 is missing, so nothing can be imported yet.
+		assert!(queue.import(&mut chain).is_empty());
+		assert_eq!(queue.blocks.values().map(Vec::len).sum::<usize>(), 3);
+
+		queue.push(blocks[0].clone());
+		let outcomes = queue.import(&mut chain);
+		let tried = outcomes.iter().map(|(hash, outcome)| (*hash, outcome.is_ok())).collect::<Vec<_>>();
+		let expected = [(&blocks[0], true), (&invalid, false), (&blocks[1], true), (&blocks[2], true)];
+		assert_eq!(tried, expected.map(|(block, ok)| (block.header.hash(), ok)));
+		assert!(queue.blocks.is_empty());
+		assert_eq!(chain.best_block(), (3, blocks[2].header.hash()));
+		assert_eq!(chain.runtime().balance_of(&dev_account("bob")), 60);
+
+		// Blocks already known are dropped without being tried, while a block on another fork is
+		// imported next to the best chain.
+		let fork = build(&genesis, vec![transfer(&genesis, "charlie", 5)]);
+		queue.push(blocks[0].clone());
+		queue.push(fork.clone());
+		let outcomes = queue.import(&mut chain);
+		assert_eq!(outcomes.iter().map(|(hash, outcome)| (*hash, outcome.is_ok())).collect::<Vec<_>>(), [(fork.header.hash(), true)]);
+		assert!(queue.blocks.is_empty());
+		assert_eq!(chain.best_block(), (3, blocks[2].header.hash()));
+	}
+}
@@ -0,0 +1,175 @@
+//! The well-known development accounts, and a keystore which holds the key pairs of accounts to
+//! sign their extrinsics.
+//!
+//! The key pair of a development account is derived from the SHA-256 of its lowercase name, so
+//! anyone can derive it. They are only meant for development chains and for tests.
+
+use alloc::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+
+use crate::codec::Encode;
+use crate::crypto::{AccountId32, Pair, Public, Signature};
+use crate::support::{Extrinsic, SignedExtrinsic, TransactionVersion};
+use crate::system::Hash;
+
+/// The key pair of the development account called `name`.
+pub fn dev_pair(name: &str) -> Pair {
+	Pair::from_seed(&Sha256::digest(name.as_bytes()).into())
+}
+
+/// The usual development accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Keyring {
+	Alice,
+	Bob,
+	Charlie,
+	Dave,
+	Eve,
+	Ferdie,
+}
+
+impl Keyring {
+	/// Every development account, in order.
+	pub const ALL: [Keyring; 6] = [Keyring::Alice, Keyring::Bob, Keyring::Charlie, Keyring::Dave, Keyring::Eve, Keyring::Ferdie];
+
+	/// The lowercase name of the account, which its key pair is derived from.
+	pub fn name(self) -> &'static str {
+		match self {
+			Keyring::Alice => "alice",
+			Keyring::Bob => "bob",
+			Keyring::Charlie => "charlie",
+			Keyring::Dave => "dave",
+			Keyring::Eve => "eve",
+			Keyring::Ferdie => "ferdie",
+		}
+	}
+
+	/// The key pair of the account.
+	pub fn pair(self) -> Pair {
+		dev_pair(self.name())
+	}
+
+	/// The public key of the account.
+	pub fn public(self) -> Public {
+		self.pair().public()
+	}
+
+	/// The id of the account, which is its public key.
+	pub fn to_account_id(self) -> AccountId32 {
+		self.public().into()
+	}
+
+	/// The development account with the id `who`, if it is one.
+	pub fn from_account_id(who: &AccountId32) -> Option<Self> {
+		Self::ALL.into_iter().find(|keyring| keyring.to_account_id() == *who)
+	}
+}
+
+/// The key pairs of some accounts, which can sign on their behalf. The id of each account is the
+/// public key of its pair.
+#[derive(Clone, Default)]
+pub struct Keystore {
+	pairs: BTreeMap<AccountId32, Pair>,
+}
+
+impl Keystore {
+	/// Create an empty keystore.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a keystore with the key pairs of the development accounts called `names`.
+	pub fn with_dev_accounts<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+		let mut keystore = Self::new();
+		for name in names {
+			keystore.insert(dev_pair(name));
+		}
+		keystore
+	}
+
+	/// Add a key pair, returning the id of its account.
+	pub fn insert(&mut self, pair: Pair) -> AccountId32 {
+		let who = AccountId32::from(pair.public());
+		self.pairs.insert(who.clone(), pair);
+		who
+	}
+
+	/// Check whether the keystore can sign on behalf of `who`.
+	pub fn contains(&self, who: &AccountId32) -> bool {
+		self.pairs.contains_key(who)
+	}
+
+	/// Sign `payload` on behalf of `who`, if the keystore holds their key pair.
+	pub fn sign(&self, who: &AccountId32, payload: &[u8]) -> Option<Signature> {
+		self.pairs.get(who).map(|pair| pair.sign(payload))
+	}
+
+	/// Sign `extrinsic` on behalf of its caller for the given nonce, on the chain with
+	/// `genesis_hash`. Returns `None` if the keystore doesn't hold the key pair of the caller.
+	pub fn sign_extrinsic<Call, BlockNumber, Balance, Nonce>(
+		&self,
+		extrinsic: Extrinsic<AccountId32, Call, BlockNumber, Balance>,
+		nonce: Nonce,
+		genesis_hash: &Hash,
+	) -> Option<SignedExtrinsic<Extrinsic<AccountId32, Call, BlockNumber, Balance>, Nonce>>
+	where
+		Call: Encode + TransactionVersion,
+		BlockNumber: Encode,
+		Balance: Encode,
+		Nonce: Encode,
+	{
+		let pair = self.pairs.get(&extrinsic.caller)?;
+		Some(SignedExtrinsic::sign(extrinsic, nonce, genesis_hash, pair))
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{dev_pair, Keyring, Keystore};
+	use crate::codec::Encode;
+	use crate::support::{Extrinsic, SignedExtrinsic, TransactionVersion};
+
+	// A call which is just a number, for extrinsics which are signed but never dispatched.
+	struct TestCall(u32);
+
+	impl Encode for TestCall {
+		fn encode_to(&self, dest: &mut Vec<u8>) {
+			self.0.encode_to(dest);
+		}
+	}
+
+	impl TransactionVersion for TestCall {
+		const TRANSACTION_VERSION: u32 = 1;
+	}
+
+	#[test]
+	fn keyring_accounts_are_deterministic() {
+		for keyring in Keyring::ALL {
+			assert_eq!(keyring.public(), dev_pair(keyring.name()).public());
+			assert_eq!(Keyring::from_account_id(&keyring.to_account_id()), Some(keyring));
+		}
+		assert_eq!(Keyring::Alice.to_account_id().to_string(), "5Gtxt5zrvWDwr5i2QirqZMVGEmi6Tzi7KbatEYS4WeqGtGmA");
+		assert_ne!(Keyring::Alice.to_account_id(), Keyring::Bob.to_account_id());
+		assert_eq!(Keyring::from_account_id(&dev_pair("mallory").public().into()), None);
+	}
+
+	#[test]
+	fn keystore_signs_for_the_accounts_it_holds() {
+		let keystore = Keystore::with_dev_accounts(["alice", "charlie"]);
+		let alice = Keyring::Alice.to_account_id();
+		let bob = Keyring::Bob.to_account_id();
+		assert!(keystore.contains(&alice));
+		assert!(!keystore.contains(&bob));
+
+		let signature = keystore.sign(&alice, b"payload").expect("alice is in the keystore");
+		assert!(Keyring::Alice.public().verify(b"payload", &signature));
+		assert_eq!(keystore.sign(&bob, b"payload"), None);
+
+		let extrinsic = |caller| Extrinsic { caller, call: TestCall(7), valid_until: None::<u32>, tip: 0u128 };
+		let signed = keystore.sign_extrinsic(extrinsic(alice.clone()), 3u32, &[1; 32]).expect("alice signs");
+		let payload = SignedExtrinsic::signing_payload(&signed.extrinsic, &signed.nonce, &[1; 32]);
+		assert!(Keyring::Alice.public().verify(&payload, &signed.signature));
+		assert!(keystore.sign_extrinsic(extrinsic(bob), 0u32, &[1; 32]).is_none());
+	}
+}
@@ -0,0 +1,44 @@
+//! The pallets of our runtime, and the support code they share.
+//!
+//! The pallets only depend on `core` and `alloc`, so with the default `std` feature disabled they
+//! can be built for `no_std` targets, like the environments real runtimes are executed in. The
+//! runtime itself, which is put together in `main.rs`, requires `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod assets;
+pub mod aura;
+pub mod balances;
+#[cfg(feature = "std")]
+pub mod benchmarking;
+pub mod codec;
+pub mod collective;
+pub mod crypto;
+pub mod democracy;
+pub mod hashing;
+pub mod identity;
+pub mod keyring;
+pub mod metadata;
+pub mod migrations;
+pub mod multisig;
+pub mod proof_of_existence;
+pub mod proxy;
+pub mod randomness;
+pub mod scheduler;
+pub mod staking;
+pub mod storage;
+pub mod sudo;
+pub mod support;
+pub mod system;
+pub mod test_utils;
+pub mod timestamp;
+pub mod treasury;
+pub mod tx_pause;
+pub mod uniques;
+pub mod utility;
+pub mod vesting;
+pub mod weights;
+
+#[cfg(all(test, not(feature = "std")))]
+mod no_std_tests;
@@ -1,94 +1,2873 @@
-mod balances;
-mod system;
-mod support;
-mod proof_of_existence;
+mod bench;
+mod block_builder;
+mod chain;
+mod chain_spec;
+mod chain_store;
+mod cli;
+mod consensus;
+mod diff;
+mod finality;
+mod import_queue;
+#[cfg(feature = "network")]
+mod network;
+mod node;
+#[cfg(feature = "rpc")]
+mod rpc;
+#[cfg(feature = "network")]
+mod sync;
+mod tx_pool;
 
-use balances::Call;
-use support::Block;
-use types::{Extrinsic, Header};
+// The pallets live in the library, so they can also be built without `std`. They are brought in
+// under their usual names, which are also used by the code generated by `#[macros::runtime]`.
+use pallets::{
+    assets, aura, balances, benchmarking, codec, collective, crypto, democracy, hashing, identity, keyring, metadata,
+    migrations, multisig, proof_of_existence, proxy, scheduler, staking, storage, sudo, support, system, timestamp,
+    treasury, tx_pause, uniques, utility, vesting,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::support::Dispatch;
+use crate::chain_spec::ChainSpec;
+use crate::cli::{Cli, Command};
+use crate::keyring::Keystore;
+use crate::node::Node;
 use crate::support::DispatchResult;
 
-
 mod types {
     use crate::support;
 
-    pub type AccountId = String;
+    pub type AccountId = crate::crypto::AccountId32;
     pub type Balance = u128;
     pub type BlockNumber = u32;
     pub type Nonce = u32;
-    pub type Extrinsic = support::Extrinsic<AccountId, crate::RuntimeCall>;
+    pub type Extrinsic = support::Extrinsic<AccountId, crate::RuntimeCall, BlockNumber, Balance>;
+    pub type ExtrinsicBuilder =
+        support::ExtrinsicBuilder<AccountId, crate::RuntimeCall, BlockNumber, Balance>;
+    pub type SignedExtrinsic = support::SignedExtrinsic<Extrinsic, Nonce>;
     pub type Header = support::Header<BlockNumber>;
-    pub type Block = support::Block<Header, Extrinsic>;
-    pub type Content = &'static str;
+    pub type Block = support::Block<Header, SignedExtrinsic, crate::RuntimeCall>;
+}
+
+/// The fee charged to the caller of every extrinsic, whatever its call. Transaction fees are paid
+/// into the treasury pot, see `TREASURY_FEE_PERCENT`.
+const BASE_FEE: types::Balance = 1;
+/// The fee charged for every 1_000 of weight of the call of an extrinsic, on top of the base fee,
+/// so heavier calls cost more.
+const FEE_PER_KILO_WEIGHT: types::Balance = 1;
+/// The slice of every transaction fee which is paid into the treasury pot, in percent. The rest of
+/// the fee is burnt, while tips are paid into the pot in full.
+const TREASURY_FEE_PERCENT: types::Balance = 80;
+/// The expected time between two blocks, in milliseconds. The timestamp of a block must be at least
+/// half of it after the timestamp of the block before.
+const BLOCK_TIME: timestamp::Moment = 6_000;
+
+/// The names of the development accounts, whose key pairs are in the development keystore.
+const DEV_ACCOUNTS: [&str; 6] = ["alice", "bob", "charlie", "dave", "eve", "ferdie"];
+
+thread_local! {
+    /// The keystore of the development accounts, which `Runtime::sign` signs extrinsics with.
+    static DEV_KEYSTORE: Keystore = Keystore::with_dev_accounts(DEV_ACCOUNTS);
+}
+
+/// The account of `who`, whose id is the public key of their development key pair.
+fn dev_account(who: &str) -> types::AccountId {
+    keyring::dev_pair(who).public().into()
+}
+
+/// The account of a pallet which holds funds, like the treasury pot, named after the 8 bytes `id`
+/// of the pallet. Nobody holds a key for it.
+fn pallet_account(id: &[u8; 8]) -> types::AccountId {
+    let mut account = [0u8; 32];
+    account[..4].copy_from_slice(b"modl");
+    account[4..12].copy_from_slice(id);
+    account.into()
 }
 
 impl system::Config for Runtime {
     type AccountId = types::AccountId;
     type BlockNumber = types::BlockNumber;
     type Nonce = types::Nonce;
+    type RuntimeEvent = RuntimeEvent;
+    type Hashing = hashing::Blake2b256;
+    const MAX_BLOCK_WEIGHT: support::Weight = 100_000;
+    // Bump the transaction version whenever the encoding of `RuntimeCall` changes, so extrinsics
+    // encoded for the previous runtime are refused instead of dispatching another call.
+    const VERSION: system::RuntimeVersion =
+        system::RuntimeVersion { spec_version: 1, impl_version: 1, transaction_version: 1 };
+
+    // Account ids are public keys, so every account can sign extrinsics without setting its key.
+    fn account_public_key(who: &Self::AccountId) -> Option<crypto::Public> {
+        Some(who.clone().into())
+    }
 }
 
 impl balances::Config for Runtime {
     type Balance = types::Balance;
+    const MINIMUM_TRANSFER: Self::Balance = 1;
+    const MAX_TRANSFERS_PER_BLOCK: u32 = 10;
+    const EXISTENTIAL_DEPOSIT: Self::Balance = 1;
+
+    // The dust of reaped accounts is collected into the treasury pot, like transaction fees.
+    fn dust_destination() -> Option<Self::AccountId> {
+        Some(<Runtime as treasury::Config>::account_id())
+    }
 }
 
 impl proof_of_existence::Config for Runtime {
-    type Content = types::Content;
+    type Currency = balances::Pallet<Runtime>;
+    const CLAIM_FEE: Self::Balance = 5;
+    const CLAIM_DEPOSIT: Self::Balance = 2;
+    const MAX_METADATA_LENGTH: usize = 256;
+    const MAX_CLAIM_OWNERS: u32 = 16;
+
+    // Claim fees are collected into the treasury pot, like transaction fees.
+    fn claim_fee_destination() -> Self::AccountId {
+        <Runtime as treasury::Config>::account_id()
+    }
+}
+
+impl treasury::Config for Runtime {
+    fn account_id() -> Self::AccountId {
+        pallet_account(b"py/trsry")
+    }
+    const PROPOSAL_BOND: Self::Balance = 5;
+    const SPEND_PERIOD: Self::BlockNumber = 2;
+
+    // Spends are approved and rejected by root, or by at least half of the council.
+    fn ensure_spend_origin(origin: support::OriginFor<Self>) -> DispatchResult {
+        support::ensure_root(origin.clone()).or_else(|_| collective::ensure_proportion_at_least(origin, 1, 2))
+    }
+}
+
+impl identity::Config for Runtime {
+    const MAX_NAME_LENGTH: usize = 32;
+    const MAX_FIELD_LENGTH: usize = 64;
+    const BASIC_DEPOSIT: Self::Balance = 10;
+    const BYTE_DEPOSIT: Self::Balance = 1;
+    const MAX_REGISTRARS: u32 = 4;
+}
+
+impl scheduler::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    const MAX_SCHEDULED_PER_BLOCK: u32 = 10;
+
+    fn call_weight(call: &Self::RuntimeCall) -> support::Weight {
+        call.weight()
+    }
+}
+
+impl staking::Config for Runtime {
+    const BONDING_DURATION: Self::BlockNumber = 3;
+    const MAX_UNLOCKING_CHUNKS: u32 = 8;
+}
+
+impl democracy::Config for Runtime {
+    const VOTING_PERIOD: Self::BlockNumber = 3;
+}
+
+impl multisig::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    const DEPOSIT_BASE: Self::Balance = 10;
+    const DEPOSIT_FACTOR: Self::Balance = 1;
+    const MAX_SIGNATORIES: u32 = 10;
+
+    fn call_weight(call: &Self::RuntimeCall) -> support::Weight {
+        call.weight()
+    }
+
+    // Nobody holds a key for a multisig account, so its id is its hash.
+    fn multi_account_id(hash: &system::Hash) -> Self::AccountId {
+        (*hash).into()
+    }
+}
+
+impl proxy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    const MAX_PROXIES: u32 = 4;
+
+    fn call_weight(call: &Self::RuntimeCall) -> support::Weight {
+        call.weight()
+    }
+}
+
+impl utility::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    const MAX_CALLS: u32 = 16;
+
+    fn call_weight(call: &Self::RuntimeCall) -> support::Weight {
+        call.weight()
+    }
+}
+
+impl timestamp::Config for Runtime {
+    const MINIMUM_PERIOD: timestamp::Moment = BLOCK_TIME / 2;
+}
+
+impl vesting::Config for Runtime {
+    const MIN_VESTED_TRANSFER: Self::Balance = 10;
+    const MAX_VESTING_SCHEDULES: u32 = 3;
+}
+
+impl uniques::Config for Runtime {
+    const MAX_METADATA_LENGTH: usize = 256;
+}
+
+impl assets::Config for Runtime {
+    type AssetId = u32;
+    type AssetBalance = u128;
+}
+
+impl sudo::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+
+    fn call_weight(call: &Self::RuntimeCall) -> support::Weight {
+        call.weight()
+    }
+}
+
+impl collective::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    const MAX_MEMBERS: u32 = 16;
+    const MAX_PROPOSAL_WEIGHT: support::Weight = 20_000;
+
+    fn call_weight(call: &Self::RuntimeCall) -> support::Weight {
+        call.weight()
+    }
+}
+
+impl aura::Config for Runtime {
+    const MAX_AUTHORITIES: u32 = 32;
+}
+
+// The calls lifting a pause, and the sudo calls which make them, can't be paused themselves.
+impl tx_pause::Config for Runtime {
+    const UNPAUSABLE_PALLETS: &'static [&'static str] = &["sudo", "tx_pause"];
+}
+
+impl support::ChargeTransaction for Runtime {
+    type Caller = types::AccountId;
+    type Balance = types::Balance;
+
+    fn charge_transaction(
+        &mut self,
+        caller: &Self::Caller,
+        weight: support::Weight,
+        tip: Self::Balance,
+    ) -> DispatchResult {
+        let treasury = <Runtime as treasury::Config>::account_id();
+        let fee = Runtime::transaction_fee(weight);
+        let total = fee.checked_add(tip).ok_or(support::ArithmeticError::Overflow)?;
+        self.balances.pay_fee(caller.clone(), treasury.clone(), total)?;
+        // Only a slice of the fee is kept in the pot, the rest is burnt.
+        self.balances.slash(&treasury, fee - Runtime::treasury_fee(fee));
+        Ok(())
+    }
+
+    fn refund_transaction(
+        &mut self,
+        caller: &Self::Caller,
+        weight: support::Weight,
+        actual_weight: support::Weight,
+    ) {
+        let treasury = <Runtime as treasury::Config>::account_id();
+        let (fee, actual_fee) = (Runtime::transaction_fee(weight), Runtime::transaction_fee(actual_weight));
+        let treasury_refund = Runtime::treasury_fee(fee).saturating_sub(Runtime::treasury_fee(actual_fee));
+        if treasury_refund > 0 {
+            // The slice of the refund which was paid into the treasury pot comes out of it. If the
+            // call emptied the pot in the meantime, it is lost.
+            let _ = self.balances.pay_fee(treasury, caller.clone(), treasury_refund);
+        }
+        // The rest of the refund was burnt, so it is given back by undoing the burn.
+        let burnt_refund = fee.saturating_sub(actual_fee).saturating_sub(treasury_refund);
+        let balance = self.balances.balance(caller);
+        self.balances.set_balance(caller.clone(), balance.saturating_add(burnt_refund));
+    }
+}
+
+// The runtime doesn't trace the calls it dispatches.
+impl support::PreDispatch for Runtime {
+    type Origin = RuntimeOrigin;
+    type Call = RuntimeCall;
+}
+
+// The calls paused by the root origin through the transaction pause pallet are refused.
+impl support::CallFilter<RuntimeCall> for Runtime {
+    fn allows(&self, call: &RuntimeCall) -> bool {
+        self.tx_pause.allows(call)
+    }
+}
+
+// Reap an account from every pallet, for `sudo::Call::kill_account`. This is refused if any pallet
+// still holds state for them, like a balance, a claim, a name, bonded funds or a seat in the
+// council, in which case nothing is removed.
+impl support::KillAccount<types::AccountId> for Runtime {
+    fn kill_account(&mut self, who: &types::AccountId) -> DispatchResult {
+        if self.sudo.key() == Some(who) {
+            return Err(sudo::Error::CannotKillSudoKey.into());
+        }
+        if self.proof_of_existence.has_claims(who) {
+            return Err("account still holds claims".into());
+        }
+        if self.identity.name_of(who).is_some() || self.identity.identity(who).is_some() {
+            return Err("account still has a name".into());
+        }
+        if self.identity.registrars().contains(who) {
+            return Err("account is a registrar".into());
+        }
+        if self.staking.ledger(who).is_some() {
+            return Err("account still has bonded funds".into());
+        }
+        if !self.vesting.vesting(who).is_empty() {
+            return Err("account still has vesting schedules".into());
+        }
+        if !self.proxy.proxies(who).is_empty() {
+            return Err("account still has proxies".into());
+        }
+        if self.assets.has_assets(who) || self.uniques.has_items(who) {
+            return Err("account still holds assets".into());
+        }
+        if self.collective.members().contains(who) || self.aura.authorities().contains(who) {
+            return Err("account is a member of the council or an authority".into());
+        }
+        self.balances.reap_account(who)?;
+        self.system.kill_account(who);
+        Ok(())
+    }
+}
+
+impl Runtime {
+    // The transaction fee for a call with the given weight, not including the tip.
+    fn transaction_fee(weight: support::Weight) -> types::Balance {
+        let weight_fee = types::Balance::from(weight / 1_000).saturating_mul(FEE_PER_KILO_WEIGHT);
+        BASE_FEE.saturating_add(weight_fee)
+    }
+
+    // The slice of the transaction `fee` which is paid into the treasury pot, see
+    // `TREASURY_FEE_PERCENT`.
+    fn treasury_fee(fee: types::Balance) -> types::Balance {
+        fee.saturating_mul(TREASURY_FEE_PERCENT) / 100
+    }
+
+    // Sign `extrinsic` with the development key of its caller, for the given nonce. The caller
+    // must be one of the `DEV_ACCOUNTS`.
+    fn sign(&self, extrinsic: types::Extrinsic, nonce: types::Nonce) -> types::SignedExtrinsic {
+        let genesis_hash = self.system.genesis_hash();
+        DEV_KEYSTORE
+            .with(|keystore| keystore.sign_extrinsic(extrinsic, nonce, &genesis_hash))
+            .expect("the caller is not a development account")
+    }
+
+    // Render the balance of every account as a table, sorted by account. Accounts are shown as
+    // SS58 addresses with the prefix of the runtime.
+    fn ledger(&self) -> String {
+        let prefix = <Runtime as system::Config>::SS58_PREFIX;
+        let rows = self
+            .balances
+            .iter()
+            .map(|(who, free)| (support::fmt_account(&who.to_ss58check_with_prefix(prefix)), free.to_string()))
+            .collect::<Vec<_>>();
+
+        let (account_header, free_header) = ("account", "free");
+        let account_width = rows
+            .iter()
+            .map(|(who, _)| who.chars().count())
+            .fold(account_header.len(), usize::max);
+        let free_width = rows.iter().map(|(_, free)| free.len()).fold(free_header.len(), usize::max);
+
+        let mut ledger = format!("{:<account_width$}  {:>free_width$}\n", account_header, free_header);
+        for (who, free) in rows {
+            ledger.push_str(&format!("{:<account_width$}  {:>free_width$}\n", who, free));
+        }
+        ledger
+    }
+
+    fn print_ledger(&self) {
+        print!("{}", self.ledger());
+    }
 }
 
-#[derive(Debug)]
 #[macros::runtime]
+#[derive(Debug, Clone)]
 pub struct Runtime {
     system: system::Pallet<Runtime>,
+    #[pallet_index = 0]
+    #[events]
+    #[genesis]
+    #[hooks]
+    #[migrations]
+    #[state]
     balances: balances::Pallet<Runtime>,
+    #[pallet_index = 1]
+    #[events]
+    #[genesis]
+    #[hooks(balances)]
+    #[dispatch_context(balances)]
+    #[state]
     proof_of_existence: proof_of_existence::Pallet<Runtime>,
+    #[pallet_index = 2]
+    #[events]
+    #[hooks(balances)]
+    #[dispatch_context(balances)]
+    #[state]
+    treasury: treasury::Pallet<Runtime>,
+    #[pallet_index = 3]
+    #[events]
+    #[genesis]
+    #[dispatch_context(balances)]
+    #[state]
+    identity: identity::Pallet<Runtime>,
+    #[pallet_index = 4]
+    #[events]
+    #[hooks]
+    #[scheduler]
+    #[dispatch_context(system)]
+    #[state]
+    scheduler: scheduler::Pallet<Runtime>,
+    #[pallet_index = 5]
+    #[events]
+    #[hooks(balances)]
+    #[dispatch_context(balances)]
+    #[state]
+    staking: staking::Pallet<Runtime>,
+    #[pallet_index = 6]
+    #[events]
+    #[hooks(scheduler)]
+    #[dispatch_context(balances)]
+    #[state]
+    democracy: democracy::Pallet<Runtime>,
+    #[pallet_index = 7]
+    #[events]
+    #[scheduler]
+    #[dispatch_context(balances)]
+    #[state]
+    multisig: multisig::Pallet<Runtime>,
+    #[pallet_index = 8]
+    #[events]
+    #[scheduler]
+    #[state]
+    proxy: proxy::Pallet<Runtime>,
+    #[pallet_index = 9]
+    #[events]
+    #[dispatch_context(self)]
+    utility: utility::Pallet<Runtime>,
+    #[pallet_index = 10]
+    #[hooks]
+    #[state]
+    timestamp: timestamp::Pallet<Runtime>,
+    #[pallet_index = 11]
+    #[events]
+    #[hooks(balances)]
+    #[dispatch_context(balances)]
+    #[state]
+    vesting: vesting::Pallet<Runtime>,
+    #[pallet_index = 12]
+    #[events]
+    #[state]
+    uniques: uniques::Pallet<Runtime>,
+    #[pallet_index = 13]
+    #[events]
+    #[state]
+    assets: assets::Pallet<Runtime>,
+    #[pallet_index = 14]
+    #[events]
+    #[genesis]
+    #[dispatch_context(self)]
+    #[state]
+    sudo: sudo::Pallet<Runtime>,
+    #[pallet_index = 15]
+    #[events]
+    #[genesis]
+    #[dispatch_context(self)]
+    #[state]
+    collective: collective::Pallet<Runtime>,
+    #[pallet_index = 16]
+    #[events]
+    #[genesis]
+    #[state]
+    aura: aura::Pallet<Runtime>,
+    #[pallet_index = 17]
+    #[events]
+    #[state]
+    tx_pause: tx_pause::Pallet<Runtime>,
+}
+
+// The genesis state of the development chain, which `main` runs when it is not given a chain spec:
+// Alice, Bob and Charlie form the council and take turns to author blocks, and Alice is funded. Like every account, they sign
+// extrinsics with the key pair of their account id, see `DEV_ACCOUNTS`.
+fn development_genesis() -> RuntimeGenesisConfig {
+    let mut genesis = RuntimeGenesisConfig::default();
+    let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(dev_account);
+    genesis.balances.balances = vec![(alice.clone(), 100)];
+    genesis.sudo.key = Some(alice.clone());
+    genesis.aura.authorities = vec![alice.clone(), bob.clone(), charlie.clone()];
+    genesis.collective.members = vec![alice, bob, charlie];
+    genesis
+}
+
+// The inherents of the next block authored on top of `runtime`: its timestamp, which is the current
+// time, but at least one `BLOCK_TIME` after the block before.
+fn block_inherents(runtime: &Runtime) -> Vec<RuntimeCall> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as timestamp::Moment);
+    let now = now.max(runtime.timestamp.now().saturating_add(BLOCK_TIME));
+    vec![RuntimeCall::timestamp(timestamp::Call::set { now })]
 }
 
 fn main() {
-    let mut runtime = Runtime::new();
-    let alice: String = "alice".to_string();
-    let bob: String = "bob".to_string();
-    let charli: String = "charli".to_string();
+    let cli = Cli::parse(std::env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{error}\n\n{}", cli::USAGE);
+        std::process::exit(2);
+    });
+    if let Err(error) = run(cli) {
+        eprintln!("error: {error}");
+        std::process::exit(1);
+    }
+}
 
-    runtime.balances.set_balance(alice.clone(), 100);
+// Run the command of `cli` on the node kept in its base path, see `node::Node`.
+fn run(cli: Cli) -> Result<(), String> {
+    let ChainSpec { name: chain_name, genesis } = match &cli.chain_spec {
+        Some(path) => ChainSpec::load(path).map_err(|error| format!("{}: {error}", path.display()))?,
+        None => ChainSpec { name: "Development".to_string(), genesis: development_genesis() },
+    };
+    // Benchmarks run on a state of their own, so they don't need the chain of the node.
+    match cli.command {
+        Command::Benchmark { kind, extrinsics, accounts } => return bench::run(genesis, kind, extrinsics, accounts),
+        Command::BenchmarkWeights { output } => return bench::run_weights(genesis, &output),
+        _ => {},
+    }
+    let mut node = Node::open(&cli.base_path, &genesis)?;
 
-    let block_1 = types::Block {
-        header: support::Header { block_number: 1 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: (30) }),
-            },
-        
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer { to: charli.clone(), amount: (20) }),
-            },
-        ],
+    let (caller, call) = match cli.command {
+        Command::Transfer { from, to, amount } => (from, RuntimeCall::balances(balances::Call::transfer { to, amount })),
+        Command::CreateClaim { owner, content } => (
+            owner,
+            RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { content: content.into_bytes() }),
+        ),
+        Command::RevokeClaim { owner, content } => {
+            let claim = proof_of_existence::Pallet::<Runtime>::claim_of(content.as_bytes());
+            (owner, RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim { claim }))
+        },
+        Command::ProduceBlock => {
+            let (block, report) = node.produce_block()?;
+            println!(
+                "Block #{} {}: {} extrinsics applied, {} failed",
+                block.header.block_number,
+                fmt_hash(&block.header.hash()),
+                report.succeeded(),
+                report.failed()
+            );
+            for (i, error) in report.failures() {
+                println!("\tExtrinsic {i}: {error}");
+            }
+            return Ok(());
+        },
+        Command::DumpState => {
+            println!("Chain: {chain_name}");
+            let (best, best_hash) = node.chain.best_block();
+            let extrinsics = node.chain.canonical_block(best).map_or(0, |block| block.extrinsics.len());
+            println!("Best block: #{best} {} with {extrinsics} extrinsics", fmt_hash(&best_hash));
+            let (finalized, finalized_hash) = node.chain.finalized_head();
+            println!("Finalized block: #{finalized} {}", fmt_hash(&finalized_hash));
+            println!("Pending extrinsics: {}\n", node.pool.len());
+            node.chain.runtime().print_ledger();
+            return Ok(());
+        },
+        Command::Metadata => {
+            print!("{}", Runtime::metadata());
+            return Ok(());
+        },
+        Command::Benchmark { .. } | Command::BenchmarkWeights { .. } => {
+            unreachable!("benchmarks are run before the node is opened")
+        },
+        #[cfg(feature = "rpc")]
+        Command::Rpc { address } => {
+            let listener = std::net::TcpListener::bind(&address).map_err(|error| format!("{address}: {error}"))?;
+            println!("JSON-RPC server listening on {address}");
+            let Node { chain, pool, .. } = node;
+            return rpc::RpcServer::new(chain, pool).serve(listener).map_err(|error| error.to_string());
+        },
+        #[cfg(not(feature = "rpc"))]
+        Command::Rpc { .. } => return Err("the JSON-RPC server requires the `rpc` feature".into()),
+        #[cfg(feature = "network")]
+        Command::Network { address, peers } => {
+            let listener = std::net::TcpListener::bind(&address).map_err(|error| format!("{address}: {error}"))?;
+            println!("Listening for peers on {address}");
+            let Node { chain, pool, .. } = node;
+            let mut network = network::Network::new(chain, pool);
+            for peer in &peers {
+                network.connect(peer).map_err(|error| format!("{peer}: {error}"))?;
+            }
+            // The keystore only holds the key of the validator, so the node only authors on its turns.
+            let keystore = cli.validator.map(|validator| {
+                Keystore::with_dev_accounts(DEV_ACCOUNTS.into_iter().filter(|name| dev_account(name) == validator))
+            });
+            return network.run(listener, keystore.as_ref()).map_err(|error| error.to_string());
+        },
+        #[cfg(not(feature = "network"))]
+        Command::Network { .. } => return Err("the peer-to-peer network requires the `network` feature".into()),
+    };
+    node.submit(caller, call)?;
+    println!("Extrinsic submitted, {} waiting in the pool", node.pool.len());
+    Ok(())
+}
+
+// Format a hash as hexadecimal, prefixed with `0x`.
+fn fmt_hash(hash: &system::Hash) -> String {
+    hash.iter().fold("0x".to_string(), |hex, byte| hex + &format!("{byte:02x}"))
+}
 
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assets, balances, collective, democracy, identity, multisig, proof_of_existence, proxy, scheduler, staking, sudo,
+        support, system, timestamp, treasury, tx_pause, types, uniques, utility, vesting, Runtime, RuntimeApi,
+        RuntimeCall, RuntimeEvent,
     };
+    use pallets::weights;
+    use crate::benchmarking::Rng;
+    use crate::block_builder::BlockBuilder;
+    use crate::{dev_account, development_genesis, ChainSpec, DEV_ACCOUNTS};
+    use crate::keyring::Keyring;
+    use crate::support::{Dispatch, KillAccount};
+    use crate::support::RawOrigin::{Root, Signed};
+    use std::collections::BTreeMap;
 
-    runtime.execute_block(block_1).expect("wrong block execution");
+    // A minimal pallet with a callable function which returns a value and emits an event, and calls
+    // declared out of the order of their index, used to check the code generated by
+    // `#[macros::call]`.
+    mod registry {
+        use crate::support::{DispatchError, Events, StorageValue};
 
-    let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { claim: "my_document" }),
-            },
-        
-            support::Extrinsic {
+        pub trait Config: crate::system::Config {}
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Event<AccountId> {
+            // `who` was registered with the id `id`.
+            Registered { who: AccountId, id: u32 },
+        }
+
+        pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+        #[macros::error]
+        pub enum Error {}
+
+        #[macros::storage]
+        #[derive(Clone)]
+        pub struct Pallet<T: Config> {
+            pub registered: StorageValue<Vec<T::AccountId>>,
+            events: Events<EventOf<T>>,
+        }
+
+        #[macros::call]
+        impl<T: Config> Pallet<T> {
+            #[call_index = 7]
+            #[weight = 1]
+            pub fn register(
+                &mut self,
+                _caller: T::AccountId,
+                who: T::AccountId,
+            ) -> Result<u32, DispatchError> {
+                self.registered.get_mut().push(who.clone());
+                let id = self.registered.len() as u32 - 1;
+                self.deposit_event(Event::Registered { who, id });
+                Ok(id)
+            }
+
+            #[call_index = 3]
+            #[weight = 1]
+            pub fn clear(&mut self, _caller: T::AccountId) -> Result<(), DispatchError> {
+                self.registered.set(Vec::new());
+                Ok(())
+            }
+        }
+
+        impl<T: Config> Pallet<T> {
+            pub fn new() -> Self {
+                Self { registered: StorageValue::new(Vec::new()), events: Events::new() }
+            }
+        }
+    }
+
+    impl registry::Config for Runtime {}
+
+    // The claim on `content` in the proof of existence pallet.
+    fn claim_of(content: &str) -> system::Hash {
+        proof_of_existence::Pallet::<Runtime>::claim_of(content.as_bytes())
+    }
+
+    // Sign `extrinsic` with the current nonce of its caller in `runtime`.
+    fn sign_next(runtime: &Runtime, extrinsic: types::Extrinsic) -> types::SignedExtrinsic {
+        let nonce = runtime.system.get_nonce(&extrinsic.caller);
+        runtime.sign(extrinsic, nonce)
+    }
+
+    // Sign `extrinsics` in order, numbering the extrinsics of each caller from their current nonce
+    // in `runtime`, like they would be applied in a single block.
+    fn sign_all(runtime: &Runtime, extrinsics: Vec<types::Extrinsic>) -> Vec<types::SignedExtrinsic> {
+        let mut nonces = BTreeMap::new();
+        extrinsics
+            .into_iter()
+            .map(|extrinsic| {
+                let nonce = nonces
+                    .entry(extrinsic.caller.clone())
+                    .or_insert_with(|| runtime.system.get_nonce(&extrinsic.caller));
+                let signed = runtime.sign(extrinsic, *nonce);
+                *nonce += 1;
+                signed
+            })
+            .collect()
+    }
+
+    // The next block on top of the best block of `runtime`, with exactly `extrinsics` and the
+    // state root they leave. See `BlockBuilder::seal`.
+    fn next_block(runtime: &Runtime, extrinsics: Vec<types::SignedExtrinsic>) -> types::Block {
+        BlockBuilder::new(runtime, runtime.system.max_block_weight())
+            .seal(extrinsics)
+            .expect("wrong block building")
+    }
+
+    // Build a chain of blocks with the given extrinsics on top of the best block of `runtime`,
+    // each block built on the previous one by executing them on a copy of `runtime`.
+    fn build_chain(runtime: &Runtime, blocks: Vec<Vec<types::SignedExtrinsic>>) -> Vec<types::Block> {
+        let mut state = runtime.clone();
+        blocks
+            .into_iter()
+            .map(|extrinsics| {
+                let block = next_block(&state, extrinsics);
+                state.execute_block(&block).expect("wrong block execution");
+                block
+            })
+            .collect()
+    }
+
+    #[test]
+    fn call_macro_returns_dispatch_output() {
+        let mut registry = registry::Pallet::<Runtime>::new();
+
+        let output = registry
+            .dispatch(Signed(dev_account("alice")), registry::Call::register { who: dev_account("alice") })
+            .unwrap();
+        assert_eq!(output.unwrap().downcast_ref::<u32>(), Some(&0));
+        let output = registry
+            .dispatch(Signed(dev_account("alice")), registry::Call::register { who: dev_account("bob") })
+            .unwrap();
+        assert_eq!(output.unwrap().downcast_ref::<u32>(), Some(&1));
+        assert_eq!(*registry.registered, vec![dev_account("alice"), dev_account("bob")]);
+
+        let output = registry.dispatch(Signed(dev_account("alice")), registry::Call::clear {}).unwrap();
+        assert!(output.is_none());
+        assert!(registry.registered.is_empty());
+    }
+
+    #[test]
+    fn calls_are_encoded_with_their_declared_index() {
+        use crate::codec::{Decode, Encode};
+
+        assert_eq!(registry::Call::<Runtime>::clear {}.encode(), [3]);
+        let register = registry::Call::<Runtime>::register { who: dev_account("bob") }.encode();
+        assert_eq!(register[0], 7);
+        assert!(matches!(registry::Call::<Runtime>::decode_all(&register), Ok(registry::Call::register { .. })));
+        assert!(registry::Call::<Runtime>::decode_all(&[0]).is_err());
+
+        let metadata = registry::Call::<Runtime>::metadata();
+        let indices = metadata.iter().map(|function| (function.name, function.index)).collect::<Vec<_>>();
+        assert_eq!(indices, [("register", 7), ("clear", 3)]);
+    }
+
+    #[test]
+    fn treasury_collects_fees_and_pays_out_spends() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        let charlie = dev_account("charlie");
+
+        runtime.balances.set_balance(alice.clone(), 100);
+        let issuance = runtime.balances.total_issuance();
+
+        let block_1 = next_block(
+            &runtime,
+            sign_all(
+                &runtime,
+                vec![
+                    support::Extrinsic {
+                        caller: alice.clone(),
+                        call: RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 30 }),
+                        valid_until: None,
+                        tip: 0,
+                    },
+                    support::Extrinsic {
+                        caller: alice.clone(),
+                        call: RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 20 }),
+                        valid_until: None,
+                        tip: 0,
+                    },
+                    support::Extrinsic {
+                        caller: bob.clone(),
+                        call: RuntimeCall::balances(balances::Call::transfer { to: alice.clone(), amount: 10 }),
+                        valid_until: None,
+                        tip: 0,
+                    },
+                ],
+            ),
+        );
+        let results = runtime.execute_block(&block_1).expect("wrong block execution").results;
+        // Transfers do not return a value.
+        assert!(results.iter().all(|result| matches!(result, Ok(None))));
+
+        let transfer_fee = Runtime::transaction_fee(weights::balances::TRANSFER);
+        let treasury_fee = Runtime::treasury_fee(transfer_fee);
+        // Only a slice of each fee is paid into the pot, the rest is burnt.
+        assert!(treasury_fee < transfer_fee);
+        assert_eq!(runtime.treasury.pot(&runtime.balances), 3 * treasury_fee);
+        assert_eq!(runtime.balances.total_issuance(), issuance - 3 * (transfer_fee - treasury_fee));
+        assert_eq!(runtime.balances.balance(&alice), 100 - 50 + 10 - 2 * transfer_fee);
+        assert_eq!(runtime.balances.balance(&bob), 50 - 10 - transfer_fee);
+
+        let block_2 = next_block(
+            &runtime,
+            vec![sign_next(
+                &runtime,
+                support::Extrinsic {
+                    caller: alice.clone(),
+                    call: RuntimeCall::treasury(treasury::Call::propose_spend {
+                        beneficiary: charlie.clone(),
+                        amount: 4,
+                    }),
+                    valid_until: None,
+                    tip: 0,
+                },
+            )],
+        );
+        let results = runtime.execute_block(&block_2).expect("wrong block execution").results;
+        let pot = 3 * treasury_fee + Runtime::treasury_fee(Runtime::transaction_fee(weights::treasury::PROPOSE_SPEND));
+        assert_eq!(runtime.treasury.pot(&runtime.balances), pot);
+        // The proposer reserves a bond until the spend is paid out.
+        assert_eq!(runtime.balances.reserved_balance(&alice), 5);
+
+        // The id of the new proposal is returned in the execution results.
+        let output = results[0].as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(output.downcast_ref::<u32>(), Some(&0));
+
+        // The spend is approved by the root origin, and paid out at the end of the next spend
+        // period, which is block 4.
+        let approve_proposal = RuntimeCall::treasury(treasury::Call::approve_proposal { proposal_id: 0 });
+        assert!(runtime.dispatch(Root, approve_proposal).is_ok());
+        let block_3 = next_block(&runtime, vec![]);
+        runtime.execute_block(&block_3).expect("wrong block execution");
+        assert_eq!(runtime.balances.balance(&charlie), 0);
+        let block_4 = next_block(&runtime, vec![]);
+        runtime.execute_block(&block_4).expect("wrong block execution");
+        assert_eq!(runtime.treasury.pot(&runtime.balances), pot - 4);
+        assert_eq!(runtime.balances.balance(&charlie), 4);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+    }
+
+    #[test]
+    fn failed_calls_are_reverted_but_still_pay_their_fee() {
+        let mut runtime = Runtime::new();
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        // A transaction which fails after changing several pallets leaves none of its changes.
+        let result = support::with_transaction(&mut runtime, |runtime| {
+            runtime.balances.set_balance(bob.clone(), 50);
+            runtime.system.inc_nonce(&bob);
+            Err::<(), _>(support::DispatchError::Other("failed"))
+        });
+        assert_eq!(result, Err(support::DispatchError::Other("failed")));
+        assert_eq!(runtime.balances.balance(&bob), 0);
+        assert!(!runtime.system.account_exists(&bob));
+
+        // The extrinsic of a call which fails still pays its fee and uses up its nonce.
+        let transfer = types::ExtrinsicBuilder::new(alice.clone())
+            .call(RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 500 }))
+            .build();
+        let fee = Runtime::transaction_fee(transfer.call.weight());
+        assert_eq!(
+            runtime.apply_extrinsic(sign_next(&runtime, transfer)).err(),
+            Some(balances::Error::InsufficientBalance.into())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 100 - fee);
+        assert_eq!(runtime.system.get_nonce(&alice), 1);
+    }
+
+    #[test]
+    fn nested_transactions_are_undone_with_the_outer_transaction() {
+        let mut runtime = Runtime::new();
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.system.create_account(&alice);
+        runtime.system.create_account(&bob);
+        let before = runtime.clone();
+
+        // The inner transaction commits into the outer one, which then fails, so the calls of both
+        // are undone, in every pallet they wrote to.
+        let result = support::with_transaction(&mut runtime, |runtime| {
+            support::with_transaction(runtime, |runtime| {
+                let transfer = balances::Call::transfer { to: bob.clone(), amount: 30 };
+                runtime.dispatch(Signed(alice.clone()), RuntimeCall::balances(transfer))?;
+                let claim = proof_of_existence::Call::create_claim { content: b"my_document".to_vec() };
+                runtime.dispatch(Signed(bob.clone()), RuntimeCall::proof_of_existence(claim))
+            })?;
+            let collection = uniques::Call::create { collection: 0 };
+            runtime.dispatch(Signed(alice.clone()), RuntimeCall::uniques(collection))?;
+            runtime.system.inc_nonce(&alice);
+            Err::<(), _>(support::DispatchError::Other("failed"))
+        });
+        assert_eq!(result, Err(support::DispatchError::Other("failed")));
+        assert!(runtime.diff(&before).is_empty());
+        assert_eq!(runtime.uniques.collection(0), None);
+        assert!(runtime.proof_of_existence.take_events().is_empty());
+        assert_eq!(support::StateRoot::state_root(&runtime), support::StateRoot::state_root(&before));
+    }
+
+    #[test]
+    fn fees_scale_with_the_weight_of_the_call() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        let light_fee = Runtime::transaction_fee(weights::proof_of_existence::QUERY_CLAIM);
+        let heavy_fee = Runtime::transaction_fee(weights::balances::TRANSFER);
+        assert!(light_fee < heavy_fee);
+
+        // Alice can pay for both calls, while Bob has exactly enough for the light one.
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.balances.set_balance(bob.clone(), light_fee);
+
+        let query = |caller: &types::AccountId| {
+            types::ExtrinsicBuilder::new(caller.clone())
+                .call(RuntimeCall::proof_of_existence(proof_of_existence::Call::query_claim {
+                    claim: claim_of("my_document"),
+                }))
+                .build()
+        };
+        let transfer = |caller: &types::AccountId| {
+            types::ExtrinsicBuilder::new(caller.clone())
+                .call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("charlie"), amount: 1 }))
+                .build()
+        };
+
+        assert!(runtime.apply_extrinsic(sign_next(&runtime, query(&alice))).is_ok());
+        assert_eq!(runtime.balances.balance(&alice), 100 - light_fee);
+        assert!(runtime.apply_extrinsic(sign_next(&runtime, transfer(&alice))).is_ok());
+        assert_eq!(runtime.balances.balance(&alice), 100 - light_fee - heavy_fee - 1);
+        let (light_treasury_fee, heavy_treasury_fee) = (Runtime::treasury_fee(light_fee), Runtime::treasury_fee(heavy_fee));
+        assert_eq!(runtime.treasury.pot(&runtime.balances), light_treasury_fee + heavy_treasury_fee);
+
+        // An extrinsic whose fee can't be paid is invalid, so it doesn't use up the nonce of Bob.
+        assert_eq!(runtime.apply_extrinsic(sign_next(&runtime, transfer(&bob))).err(), Some(balances::Error::InsufficientBalance.into()));
+        assert_eq!(runtime.balances.balance(&bob), light_fee);
+        assert_eq!(runtime.system.get_nonce(&bob), 0);
+        assert!(runtime.apply_extrinsic(sign_next(&runtime, query(&bob))).is_ok());
+        assert_eq!(runtime.balances.balance(&bob), 0);
+        assert_eq!(runtime.treasury.pot(&runtime.balances), 2 * light_treasury_fee + heavy_treasury_fee);
+    }
+
+    #[test]
+    fn dev_chain_spec_matches_the_development_genesis() {
+        let spec = ChainSpec::from_json(include_str!("../dev_chain_spec.json")).expect("valid chain spec");
+        assert_eq!(spec.name, "Development");
+        let mut from_spec = Runtime::new();
+        spec.genesis.build(&mut from_spec).expect("valid genesis");
+        let mut development = Runtime::new();
+        development_genesis().build(&mut development).expect("valid genesis");
+
+        assert_eq!(from_spec.ledger(), development.ledger());
+        assert_eq!(from_spec.sudo.key(), development.sudo.key());
+        assert_eq!(from_spec.collective.members(), development.collective.members());
+        assert_eq!(from_spec.aura.authorities(), development.aura.authorities());
+        for who in ["alice", "bob", "charlie"] {
+            let who = dev_account(who);
+            assert_eq!(from_spec.system.public_key(&who), development.system.public_key(&who));
+            assert!(from_spec.system.public_key(&who).is_some());
+        }
+    }
+
+    #[test]
+    fn genesis_is_refused_if_a_pallet_refuses_it() {
+        let mut genesis = development_genesis();
+        genesis.identity.names = vec![(dev_account("alice"), "Al".to_string()), (dev_account("bob"), "Al".to_string())];
+        assert_eq!(genesis.build(&mut Runtime::new()), Err("name taken".into()));
+
+        let mut genesis = development_genesis();
+        genesis.balances.balances.push((dev_account("bob"), 0));
+        assert_eq!(genesis.build(&mut Runtime::new()), Err(balances::Error::ExistentialDeposit.into()));
+    }
+
+    #[test]
+    fn unused_weight_is_refunded() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        // The transfer to Alice herself doesn't move any funds, so only the weight of the transfer
+        // to Bob is paid for in the end.
+        let targets = vec![(dev_account("bob"), 10), (alice.clone(), 10)];
+        let call = balances::Call::transfer_multi { targets };
+        assert_eq!(call.weight(), 2 * weights::balances::TRANSFER_MULTI_PER_TARGET);
+        let extrinsic = types::ExtrinsicBuilder::new(alice.clone())
+            .call(RuntimeCall::balances(call))
+            .tip(1)
+            .build();
+        let output = runtime
+            .apply_extrinsic(sign_next(&runtime, extrinsic))
+            .expect("extrinsic should succeed")
+            .expect("transfer_multi returns its actual weight");
+        assert_eq!(output.downcast_ref::<support::ActualWeight>(), Some(&support::ActualWeight(weights::balances::TRANSFER_MULTI_PER_TARGET)));
+
+        // The tip is not refunded, and is paid into the treasury pot in full.
+        let fee = Runtime::transaction_fee(weights::balances::TRANSFER_MULTI_PER_TARGET);
+        assert_eq!(runtime.balances.balance(&alice), 100 - 10 - fee - 1);
+        assert_eq!(runtime.treasury.pot(&runtime.balances), Runtime::treasury_fee(fee) + 1);
+        assert_eq!(runtime.balances.total_issuance(), 100 - (fee - Runtime::treasury_fee(fee)));
+    }
+
+    #[test]
+    fn blocks_heavier_than_the_maximum_weight_are_rejected() {
+        let mut runtime = Runtime::new();
+        for who in DEV_ACCOUNTS {
+            runtime.balances.set_balance(dev_account(who), 1_000);
+        }
+
+        // Only so many transfers fit in a block. They are made by every development account in
+        // turn, so none of them exceeds the transfers an account can make in a block.
+        let fit = (runtime.system.max_block_weight() / weights::balances::TRANSFER) as usize;
+        let transfer = |index: usize| {
+            types::ExtrinsicBuilder::new(dev_account(DEV_ACCOUNTS[index % DEV_ACCOUNTS.len()]))
+                .call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 1 }))
+                .build()
+        };
+        let heavy = sign_all(&runtime, (0..fit + 1).map(transfer).collect());
+        let block = next_block(&runtime, heavy);
+        assert_eq!(
+            runtime.clone().execute_block(&block).err(),
+            Some(support::DispatchError::Other("block exceeds the maximum weight"))
+        );
+
+        let full = sign_all(&runtime, (0..fit).map(transfer).collect());
+        let block = next_block(&runtime, full);
+        let report = runtime.execute_block(&block).expect("wrong block execution");
+        assert!(report.all_succeeded());
+    }
+
+    #[test]
+    fn apply_extrinsic_without_a_block() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let transfer = || {
+            types::ExtrinsicBuilder::new(alice.clone())
+                .call(RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 10 }))
+                .build()
+        };
+        let fee = Runtime::transaction_fee(weights::balances::TRANSFER);
+
+        assert!(matches!(runtime.apply_extrinsic(sign_next(&runtime, transfer())), Ok(None)));
+        assert_eq!(runtime.balances.balance(&alice), 100 - 10 - fee);
+        assert_eq!(runtime.system.get_nonce(&alice), 1);
+
+        assert!(matches!(runtime.apply_extrinsic(sign_next(&runtime, transfer())), Ok(None)));
+        assert_eq!(runtime.balances.balance(&alice), 100 - 2 * (10 + fee));
+        assert_eq!(runtime.balances.balance(&bob), 20);
+        assert_eq!(runtime.system.get_nonce(&alice), 2);
+        assert_eq!(runtime.system.block_number(), 0);
+    }
+
+    #[test]
+    fn system_snapshot_tracks_nonce_of_caller() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+
+        runtime.balances.set_balance(alice.clone(), 100);
+        let before = runtime.system.snapshot();
+
+        let block_1 = next_block(
+            &runtime,
+            vec![sign_next(
+                &runtime,
+                support::Extrinsic {
+                    caller: alice.clone(),
+                    call: RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 30 }),
+                    valid_until: None,
+                    tip: 0,
+                },
+            )],
+        );
+        runtime.execute_block(&block_1).expect("wrong block execution");
+        let after = runtime.system.snapshot();
+
+        assert_eq!(after.block_number - before.block_number, 1);
+        let nonce_of = |snapshot: &crate::system::SystemSnapshot<_, _, u32>, who: &types::AccountId| {
+            snapshot.nonces.get(who).copied().unwrap_or(0)
+        };
+        assert_eq!(nonce_of(&after, &alice) - nonce_of(&before, &alice), 1);
+        assert_eq!(nonce_of(&after, &bob), nonce_of(&before, &bob));
+    }
+
+    #[test]
+    fn execute_blocks_reports_block_number_gap() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        // Each block holds the next transfer of alice.
+        let transfer = |nonce: u32| {
+            let transfer = support::Extrinsic {
                 caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { claim: "bobs_document" }),
-            },
-        ],
+                call: RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 10 }),
+                valid_until: None,
+                tip: 0,
+            };
+            runtime.sign(transfer, nonce)
+        };
+        let blocks = build_chain(&runtime, (0..3).map(|nonce| vec![transfer(nonce)]).collect());
+        // Block 4 is missing, so block 5 is refused before it is executed.
+        let block_5 = types::Block::new(blocks[2].header.hash(), 5, vec![transfer(4)]);
+        let block_6 = types::Block::new(block_5.header.hash(), 6, vec![transfer(5)]);
+        let gap_blocks = vec![block_5, block_6];
 
-    };
+        assert_eq!(runtime.execute_blocks(&blocks), Ok(()));
+        assert_eq!(runtime.system.block_number(), 3);
+        assert_eq!(runtime.balances.balance(&bob), 30);
+
+        assert_eq!(
+            runtime.execute_blocks(&gap_blocks),
+            Err((5, support::DispatchError::Other("block number does not match what is expected")))
+        );
+        assert_eq!(runtime.balances.balance(&bob), 30);
+        // The block refused left the block number as it was, so block 4 still follows block 3.
+        assert_eq!(runtime.system.block_number(), 3);
+        let block_4 = next_block(&runtime, vec![]);
+        assert!(runtime.execute_block(&block_4).is_ok());
+    }
+
+    #[test]
+    fn dispatch_routes_calls_to_their_pallet() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+
+        // A caller with no prior state is refused: accounts are created by `execute_block`.
+        let call = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            content: "my_document".into(),
+        });
+        assert_eq!(runtime.dispatch(Signed(alice.clone()), call).err(), Some(support::DispatchError::BadOrigin));
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim_of("my_document")), None);
+
+        runtime.system.inc_nonce(&alice);
+        runtime.balances.set_balance(alice.clone(), 10);
+        let call = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+            content: "my_document".into(),
+        });
+        assert!(matches!(runtime.dispatch(Signed(alice.clone()), call), Ok(None)));
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim_of("my_document")), Some(&alice));
+        // The claim fee is paid into the treasury pot, and the claim deposit is reserved.
+        assert_eq!(runtime.balances.balance(&alice), 3);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 2);
+        assert_eq!(runtime.treasury.pot(&runtime.balances), 5);
+    }
+
+    #[test]
+    fn claims_change_hands_without_being_revoked() {
+        let mut runtime = Runtime::new();
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.balances.set_balance(bob.clone(), 100);
+
+        let claim_call = |caller: &types::AccountId, call| {
+            types::ExtrinsicBuilder::new(caller.clone()).call(RuntimeCall::proof_of_existence(call)).build()
+        };
+        let extrinsics = vec![
+            claim_call(&alice, proof_of_existence::Call::create_claim { content: "my_document".into() }),
+            claim_call(&alice, proof_of_existence::Call::transfer_claim { claim: claim_of("my_document"), new_owner: bob.clone() }),
+            claim_call(&alice, proof_of_existence::Call::renounce_claim { claim: claim_of("my_document") }),
+            claim_call(&bob, proof_of_existence::Call::renounce_claim { claim: claim_of("my_document") }),
+            claim_call(&alice, proof_of_existence::Call::create_claim { content: "my_document".into() }),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert!(results[..2].iter().all(Result::is_ok));
+        assert_eq!(results[2].as_ref().err(), Some(&proof_of_existence::Error::NotClaimOwner.into()));
+        assert!(results[3].is_ok());
+        // Once renounced, the content can't be claimed again, not even by its first owner.
+        assert_eq!(results[4].as_ref().err(), Some(&proof_of_existence::Error::ClaimAlreadyExists.into()));
+        assert_eq!(runtime.proof_of_existence.claim_history(&claim_of("my_document")), [alice.clone(), bob.clone()]);
+        assert!(runtime.proof_of_existence.is_renounced(&claim_of("my_document")));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(runtime.balances.reserved_balance(&bob), 0);
+    }
+
+    #[test]
+    fn claims_record_the_block_they_were_created_in() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let claim_call = |call| {
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::proof_of_existence(call)).build()
+        };
+        let create = claim_call(proof_of_existence::Call::create_claim { content: "my_document".into() });
+        let metadata = proof_of_existence::Call::set_claim_metadata { claim: claim_of("my_document"), metadata: b"pdf".to_vec() };
+        let blocks = build_chain(&runtime, vec![vec![], vec![runtime.sign(create, 0), runtime.sign(claim_call(metadata), 1)]]);
+
+        runtime.execute_blocks(&blocks).expect("wrong block execution");
+        let details = runtime.proof_of_existence.get_claim_details(&claim_of("my_document")).expect("claim was created");
+        assert_eq!((&details.owner, details.block_number, &details.metadata[..]), (&alice, 2, &b"pdf"[..]));
+    }
+
+    #[test]
+    fn expired_claims_are_purged_at_the_end_of_their_block() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let create = proof_of_existence::Call::create_claim_with_expiry { content: "my_document".into(), ttl_blocks: 1 };
+        let create = types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::proof_of_existence(create)).build();
+        let blocks = build_chain(&runtime, vec![vec![sign_next(&runtime, create)], vec![]]);
+
+        runtime.execute_blocks(&blocks[..1]).expect("wrong block execution");
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim_of("my_document")), Some(&alice));
+        runtime.execute_blocks(&blocks[1..]).expect("wrong block execution");
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim_of("my_document")), None);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        let expired = RuntimeEvent::proof_of_existence(proof_of_existence::Event::ClaimExpired {
+            claim: claim_of("my_document"),
+            owner: alice,
+        });
+        assert!(runtime.system.events(2).iter().any(|record| record.event == expired));
+    }
+
+    #[test]
+    fn shared_claims_are_revoked_by_a_threshold_of_owners() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(dev_account);
+        for who in [&alice, &bob, &charlie] {
+            runtime.balances.set_balance(who.clone(), 100);
+        }
+
+        let claim_call = |caller: &types::AccountId, call| {
+            types::ExtrinsicBuilder::new(caller.clone()).call(RuntimeCall::proof_of_existence(call)).build()
+        };
+        let owners = vec![alice.clone(), bob.clone(), charlie.clone()];
+        let approve = || proof_of_existence::Call::approve_revoke { claim: claim_of("deed") };
+        let extrinsics = vec![
+            claim_call(&alice, proof_of_existence::Call::create_shared_claim { content: "deed".into(), owners, threshold: 2 }),
+            claim_call(&bob, approve()),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+        runtime.execute_block(&block).expect("wrong block execution");
+        // Bob co-owns the claim, so his account can't be removed.
+        assert_eq!(runtime.kill_account(&bob), Err("account still holds claims".into()));
+
+        let block = next_block(&runtime, sign_all(&runtime, vec![claim_call(&charlie, approve())]));
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert!(results[0].is_ok());
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim_of("deed")), None);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+    }
+
+    #[test]
+    fn privileged_calls_require_the_root_origin() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let mint = |amount| RuntimeCall::balances(balances::Call::mint_into { who: dev_account("alice"), amount });
+
+        // The root origin doesn't need an account in the system pallet.
+        assert!(matches!(runtime.dispatch(Root, mint(100)), Ok(None)));
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert_eq!(runtime.balances.total_issuance(), 100);
+
+        // Extrinsics are dispatched with a signed origin, so they can't make privileged calls.
+        let extrinsic = types::ExtrinsicBuilder::new(alice.clone()).call(mint(1_000)).build();
+        assert_eq!(
+            runtime.apply_extrinsic(sign_next(&runtime, extrinsic)).err(),
+            Some(support::DispatchError::BadOrigin)
+        );
+        let fee = Runtime::transaction_fee(weights::balances::MINT_INTO);
+        assert_eq!(runtime.balances.balance(&alice), 100 - fee);
+
+        // Signed calls can't be made by the root origin, which has no account to act for.
+        let transfer = RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 10 });
+        assert_eq!(runtime.dispatch(Root, transfer).err(), Some(support::DispatchError::BadOrigin));
+    }
+
+    #[test]
+    fn runtime_api_queries_the_state() {
+        let mut runtime = Runtime::new();
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(alice.clone())
+                .call(RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 30 }))
+                .build(),
+            types::ExtrinsicBuilder::new(alice.clone())
+                .call(RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                    content: "my_document".into(),
+                }))
+                .build(),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+        runtime.execute_block(&block).expect("wrong block execution");
+
+        // The queries only need a shared reference to the runtime.
+        let api: &dyn RuntimeApi = &runtime;
+        assert_eq!(api.block_number(), 1);
+        assert_eq!((api.nonce_of(&alice), api.nonce_of(&bob)), (2, 0));
+        assert_eq!(api.balance_of(&bob), 30);
+        assert_eq!(api.balance_of(&alice), runtime.balances.balance(&alice));
+        assert_eq!(api.claim_owner(&claim_of("my_document")), Some(alice));
+        assert_eq!(api.claim_owner(&claim_of("other_document")), None);
+    }
+
+    #[test]
+    fn ledger_lists_accounts_in_sorted_order() {
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(dev_account("charlie"), 5);
+        runtime.balances.set_balance(dev_account("alice"), 100);
+        runtime.balances.set_balance(dev_account("bob"), 30);
+
+        // Charlie's public key comes first, then Alice's and Bob's. Their SS58 addresses are
+        // shortened to line up.
+        let ledger = runtime.ledger();
+        let lines = ledger.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "account        free",
+                "5GbvDm…nCqd2J     5",
+                "5Gtxt5…qGtGmA   100",
+                "5HR8he…W7ccyg    30",
+            ]
+        );
+    }
+
+    #[test]
+    fn fmt_account_shortens_long_ids() {
+        assert_eq!(support::fmt_account(&"alice"), "alice");
+        assert_eq!(
+            support::fmt_account(&"5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"),
+            "5Grwva…GKutQY"
+        );
+    }
+
+    #[test]
+    fn expired_extrinsic_is_rejected() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let mortal_transfer = || support::Extrinsic {
+            caller: alice.clone(),
+            call: RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 10 }),
+            valid_until: Some(1),
+            tip: 0,
+        };
+
+        let block_1 = next_block(
+            &runtime,
+            vec![sign_next(&runtime, mortal_transfer())],
+        );
+        let results = runtime.execute_block(&block_1).expect("wrong block execution").results;
+        assert!(matches!(results[0], Ok(None)));
+        assert_eq!(runtime.balances.balance(&bob), 10);
+        assert_eq!(runtime.system.get_nonce(&alice), 1);
+
+        let block_2 = next_block(
+            &runtime,
+            vec![sign_next(&runtime, mortal_transfer())],
+        );
+        let results = runtime.execute_block(&block_2).expect("wrong block execution").results;
+        assert_eq!(results[0].as_ref().err(), Some(&support::DispatchError::Other("extrinsic expired")));
+        assert_eq!(runtime.balances.balance(&bob), 10);
+        assert_eq!(runtime.system.get_nonce(&alice), 1);
+    }
+
+    #[test]
+    fn extrinsics_with_an_invalid_signature_are_rejected() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let transfer = |caller: &str| {
+            types::ExtrinsicBuilder::new(dev_account(caller))
+                .call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 10 }))
+                .build()
+        };
+        let genesis_hash = runtime.system.genesis_hash();
+        // Bob signs an extrinsic on behalf of alice.
+        let forged = support::SignedExtrinsic::sign(transfer("alice"), 0, &genesis_hash, &Keyring::Bob.pair());
+        // The tip is raised after alice signed the extrinsic.
+        let mut tampered = runtime.sign(transfer("alice"), 0);
+        tampered.extrinsic.tip = 50;
+        // Alice signs the extrinsic for another chain.
+        let other_chain = support::SignedExtrinsic::sign(transfer("alice"), 0, &[1; 32], &Keyring::Alice.pair());
+        // Dave's signatures are checked with the key set in the system pallet rather than with the
+        // key of his account id, so his own key is refused.
+        runtime.system.set_public_key(dev_account("dave"), Keyring::Eve.public());
+        let replaced_key = runtime.sign(transfer("dave"), 0);
+
+        let block_1 = next_block(
+            &runtime,
+            vec![forged, tampered, other_chain, replaced_key, runtime.sign(transfer("alice"), 0)],
+        );
+        let results = runtime.execute_block(&block_1).expect("wrong block execution").results;
+        let errors = results.iter().map(|result| result.as_ref().err()).collect::<Vec<_>>();
+        let invalid = support::DispatchError::Other("invalid signature");
+        assert_eq!(errors, vec![Some(&invalid), Some(&invalid), Some(&invalid), Some(&invalid), None]);
+
+        // Only the extrinsic signed by alice was applied.
+        assert_eq!(runtime.system.get_nonce(&alice), 1);
+        assert_eq!(runtime.balances.balance(&alice), 100 - 10 - Runtime::transaction_fee(weights::balances::TRANSFER));
+    }
+
+    #[test]
+    fn replayed_extrinsics_are_rejected() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let transfer = || {
+            types::ExtrinsicBuilder::new(alice.clone())
+                .call(RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 10 }))
+                .build()
+        };
+        let first = runtime.sign(transfer(), 0);
+        let block_1 = next_block(
+            &runtime,
+            vec![first.clone(), first.clone(), runtime.sign(transfer(), 2)],
+        );
+        let results = runtime.execute_block(&block_1).expect("wrong block execution").results;
+        assert!(matches!(results[0], Ok(None)));
+        assert_eq!(results[1].as_ref().err(), Some(&support::DispatchError::Other("stale nonce")));
+        assert_eq!(results[2].as_ref().err(), Some(&support::DispatchError::Other("future nonce")));
+        assert_eq!(runtime.system.get_nonce(&alice), 1);
+
+        // The extrinsic can't be replayed in a later block either.
+        let block_2 = next_block(&runtime, vec![first]);
+        let results = runtime.execute_block(&block_2).expect("wrong block execution").results;
+        assert_eq!(results[0].as_ref().err(), Some(&support::DispatchError::Other("stale nonce")));
+        assert_eq!(runtime.system.get_nonce(&alice), 1);
+        assert_eq!(runtime.balances.balance(&bob), 10);
+    }
+
+    #[test]
+    fn blocks_must_extend_the_best_block() {
+        let mut runtime = Runtime::new();
+        let genesis_hash = runtime.system.best_block_hash();
+        let block_1 = next_block(&runtime, vec![]);
+        runtime.execute_block(&block_1).expect("wrong block execution");
+        assert_eq!(runtime.system.block_hash(1), Some(block_1.header.hash()));
+        assert_eq!(runtime.system.best_block_hash(), block_1.header.hash());
+        assert_ne!(block_1.header.hash(), genesis_hash);
+
+        // A block built on the genesis block instead of block 1 is refused.
+        let fork = types::Block::new(genesis_hash, 2, vec![]);
+        assert_eq!(
+            runtime.clone().execute_block(&fork).err(),
+            Some(support::DispatchError::Other("parent hash does not match the best block"))
+        );
+
+        // So is a block whose extrinsics were swapped after its header was built.
+        let mut swapped = types::Block::new(block_1.header.hash(), 2, vec![]);
+        swapped.extrinsics.push(runtime.sign(
+            types::ExtrinsicBuilder::new(dev_account("alice"))
+                .call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 10 }))
+                .build(),
+            0,
+        ));
+        assert_eq!(
+            runtime.clone().execute_block(&swapped).err(),
+            Some(support::DispatchError::Other("extrinsics root does not match the extrinsics"))
+        );
+
+        let block_2 = next_block(&runtime, vec![]);
+        assert_eq!(block_2.header.parent_hash, block_1.header.hash());
+        runtime.execute_block(&block_2).expect("wrong block execution");
+        assert_eq!(runtime.system.block_hash(2), Some(block_2.header.hash()));
+    }
+
+    #[test]
+    fn blocks_must_commit_to_the_state_they_leave() {
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(dev_account("alice"), 100);
+        let transfer = sign_next(
+            &runtime,
+            types::ExtrinsicBuilder::new(dev_account("alice"))
+                .call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 10 }))
+                .build(),
+        );
+        let block = next_block(&runtime, vec![transfer.clone()]);
+        assert_ne!(block.header.state_root, support::StateRoot::state_root(&runtime));
+
+        // A block whose header doesn't commit to the state left by its extrinsics is refused.
+        let forged = types::Block::new(block.header.parent_hash, 1, vec![transfer]);
+        assert_eq!(
+            runtime.clone().execute_block(&forged).err(),
+            Some(support::DispatchError::Other("state root does not match the state"))
+        );
+
+        let report = runtime.execute_block(&block).expect("wrong block execution");
+        assert!(report.all_succeeded());
+        assert_eq!(runtime.balances.balance(&dev_account("bob")), 10);
+    }
+
+    #[test]
+    fn finality_lags_behind_the_best_block() {
+        let mut runtime = Runtime::new();
+        let blocks = build_chain(&runtime, vec![vec![]; 3]);
+        runtime.execute_blocks(&blocks).expect("wrong blocks execution");
+
+        assert_eq!(runtime.system.finalize_up_to(2), Ok(()));
+        assert_eq!(runtime.system.finalized_block(), 2);
+        assert_eq!(runtime.system.block_number(), 3);
+
+        assert_eq!(runtime.system.finalize_up_to(5), Err("cannot finalize beyond the best block".into()));
+        assert_eq!(runtime.system.finalize_up_to(1), Err("block is already finalized".into()));
+        assert_eq!(runtime.system.finalized_block(), 2);
+    }
+
+    #[test]
+    fn escrow_is_released_when_its_block_is_finalized() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let escrow = support::Extrinsic {
+            caller: alice.clone(),
+            call: RuntimeCall::balances(balances::Call::create_escrow {
+                to: bob.clone(),
+                amount: 40,
+                release_block: 2,
+            }),
+            valid_until: None,
+            tip: 0,
+        };
+        let block_1 = next_block(
+            &runtime,
+            vec![sign_next(&runtime, escrow)],
+        );
+        runtime.execute_block(&block_1).expect("wrong block execution");
+        assert_eq!(runtime.balances.reserved_balance(&alice), 40);
+        assert_eq!(runtime.balances.balance(&bob), 0);
+
+        let block_2 = next_block(&runtime, vec![]);
+        runtime.execute_block(&block_2).expect("wrong block execution");
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(runtime.balances.balance(&bob), 40);
+    }
+
+    #[test]
+    fn borrowed_block_executes_the_same_on_two_runtimes() {
+        let setup = || {
+            let mut runtime = Runtime::new();
+            runtime.balances.set_balance(dev_account("alice"), 100);
+            runtime
+        };
+        let mut first = setup();
+        let mut second = setup();
+
+        let block_1 = next_block(
+            &first,
+            sign_all(
+                &first,
+                vec![
+                    types::ExtrinsicBuilder::new(dev_account("alice"))
+                        .call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 30 }))
+                        .build(),
+                    types::ExtrinsicBuilder::new(dev_account("alice"))
+                        .call(RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                            content: "my_document".into(),
+                        }))
+                        .build(),
+                    types::ExtrinsicBuilder::new(dev_account("bob"))
+                        .call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("alice"), amount: 500 }))
+                        .build(),
+                ],
+            ),
+        );
+
+        let first_results = first.execute_block(&block_1).expect("wrong block execution").results;
+        let second_results = second.execute_block(&block_1).expect("wrong block execution").results;
+
+        // The block is still usable after both executions, and produced the same results and state.
+        assert_eq!(block_1.extrinsics.len(), 3);
+        let outcome = |results: &[support::DispatchResultWithOutput]| {
+            results.iter().map(|result| result.as_ref().map(|_| ()).map_err(|e| *e)).collect::<Vec<_>>()
+        };
+        assert_eq!(outcome(&first_results), outcome(&second_results));
+        assert_eq!(outcome(&first_results)[2], Err(balances::Error::InsufficientBalance.into()));
+        assert!(first.diff(&second).is_empty());
+        assert_eq!(first.balances.balance(&dev_account("bob")), 30 - Runtime::transaction_fee(weights::balances::TRANSFER));
+    }
+
+    #[test]
+    fn kill_account_is_refused_while_a_pallet_holds_state() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let claim_fee = <Runtime as proof_of_existence::Config>::CLAIM_FEE;
+        let claim_deposit = <Runtime as proof_of_existence::Config>::CLAIM_DEPOSIT;
+
+        runtime.system.inc_nonce(&alice);
+        runtime.balances.set_balance(alice.clone(), claim_fee + claim_deposit + 1);
+        assert_eq!(runtime.kill_account(&alice), Err(balances::Error::AccountHasBalance.into()));
+
+        // Claiming a document spends everything but the deposit and 1, which is then sent away.
+        runtime
+            .proof_of_existence
+            .create_claim(alice.clone(), "my_document".into(), &mut runtime.balances)
+            .unwrap();
+        runtime.balances.do_transfer(alice.clone(), dev_account("bob"), 1).unwrap();
+        assert_eq!(runtime.balances.total_balance(&alice), claim_deposit);
+        assert_eq!(runtime.kill_account(&alice), Err("account still holds claims".into()));
+        assert!(runtime.system.account_exists(&alice));
+
+        // Revoking the claim returns the deposit, which is sent away too.
+        runtime.proof_of_existence.revoke_claim(alice.clone(), claim_of("my_document"), &mut runtime.balances).unwrap();
+        runtime.balances.do_transfer(alice.clone(), dev_account("bob"), claim_deposit).unwrap();
+        assert_eq!(runtime.kill_account(&alice), Ok(()));
+
+        assert!(!runtime.system.account_exists(&alice));
+        assert_eq!(runtime.system.get_nonce(&alice), 0);
+        assert!(runtime.balances.iter().all(|(who, _)| who != &alice));
+        assert!(!runtime.proof_of_existence.has_claims(&alice));
+        assert_eq!(runtime.identity.name_of(&alice), None);
+    }
+
+    #[test]
+    fn failed_extrinsics_are_reported_without_stopping_the_block() {
+        let mut runtime = Runtime::new();
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let transfer = |amount| {
+            types::ExtrinsicBuilder::new(alice.clone())
+                .call(RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount }))
+                .build()
+        };
+        // The second transfer is more than Alice has left, but the third one is still applied.
+        let block = next_block(&runtime, sign_all(&runtime, vec![transfer(30), transfer(500), transfer(20)]));
+        let report = runtime.execute_block(&block).expect("wrong block execution");
+
+        assert_eq!((report.succeeded(), report.failed()), (2, 1));
+        assert!(!report.all_succeeded());
+        let insufficient_balance = support::DispatchError::from(balances::Error::InsufficientBalance);
+        assert_eq!(report.failures().collect::<Vec<_>>(), [(1, &insufficient_balance)]);
+        assert_eq!(runtime.balances.balance(&bob), 50);
+
+        let outcomes = runtime
+            .system
+            .events(1)
+            .iter()
+            .filter(|record| matches!(record.event, RuntimeEvent::system(_)))
+            .map(|record| (record.phase, record.event.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            [
+                (support::Phase::ApplyExtrinsic(0), RuntimeEvent::system(system::Event::ExtrinsicSuccess)),
+                (
+                    support::Phase::ApplyExtrinsic(1),
+                    RuntimeEvent::system(system::Event::ExtrinsicFailed { error: insufficient_balance }),
+                ),
+                (support::Phase::ApplyExtrinsic(2), RuntimeEvent::system(system::Event::ExtrinsicSuccess)),
+            ]
+        );
+    }
+
+    #[test]
+    fn state_built_by_another_spec_version_is_migrated_before_the_next_block() {
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(dev_account("alice"), 100);
+        let version = <Runtime as system::Config>::VERSION;
+        runtime.system.set_version(system::RuntimeVersion { spec_version: version.spec_version - 1, ..version });
+
+        // The block author migrates the state too, so the block commits to the migrated state.
+        let block = next_block(&runtime, vec![]);
+        runtime.execute_block(&block).expect("wrong block execution");
+        assert_eq!(runtime.system.version(), version);
+        let upgraded = RuntimeEvent::system(system::Event::RuntimeUpgraded { spec_version: version.spec_version, weight: 0 });
+        assert_eq!(
+            runtime.system.events(1).first().map(|record| (record.phase, record.event.clone())),
+            Some((support::Phase::Initialization, upgraded.clone()))
+        );
+
+        // The state is only migrated once.
+        let block = next_block(&runtime, vec![]);
+        runtime.execute_block(&block).expect("wrong block execution");
+        assert!(runtime.system.events(2).iter().all(|record| record.event != upgraded));
+    }
+
+    #[test]
+    fn events_are_recorded_with_the_index_of_their_extrinsic() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let block_1 = next_block(
+            &runtime,
+            sign_all(
+                &runtime,
+                vec![
+                    types::ExtrinsicBuilder::new(alice.clone())
+                        .call(RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 30 }))
+                        .build(),
+                    types::ExtrinsicBuilder::new(bob.clone())
+                        .call(RuntimeCall::proof_of_existence(proof_of_existence::Call::query_claim {
+                            claim: claim_of("my_document"),
+                        }))
+                        .build(),
+                ],
+            ),
+        );
+        runtime.execute_block(&block_1).expect("wrong block execution");
+
+        // Both extrinsics pay their fee, then the first one makes a transfer and the second one
+        // emits the result of its query. The system pallet reports the success of each of them.
+        let events = runtime.system.events(1);
+        let phases = events.iter().map(|record| record.phase).collect::<Vec<_>>();
+        assert_eq!(phases, [0, 0, 0, 1, 1, 1].map(support::Phase::ApplyExtrinsic));
+        assert_eq!(events[2].event, RuntimeEvent::system(system::Event::ExtrinsicSuccess));
+        assert_eq!(events[5].event, RuntimeEvent::system(system::Event::ExtrinsicSuccess));
+        assert_eq!(
+            events[4].event,
+            RuntimeEvent::proof_of_existence(proof_of_existence::Event::ClaimQueried {
+                claim: claim_of("my_document"),
+                owner: None,
+            })
+        );
+    }
+
+    #[test]
+    fn events_are_collected_per_block() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let claim_call = |call, nonce| {
+            runtime.sign(
+                types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::proof_of_existence(call)).build(),
+                nonce,
+            )
+        };
+        let blocks = build_chain(
+            &runtime,
+            vec![
+                vec![claim_call(proof_of_existence::Call::create_claim { content: "my_document".into() }, 0)],
+                vec![claim_call(proof_of_existence::Call::revoke_claim { claim: claim_of("my_document") }, 1)],
+            ],
+        );
+        runtime.execute_blocks(&blocks).expect("wrong blocks execution");
+
+        let claim_events = |block_number| {
+            runtime
+                .system
+                .events(block_number)
+                .iter()
+                .filter(|record| matches!(record.event, RuntimeEvent::proof_of_existence(_)))
+                .map(|record| record.event.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            claim_events(1),
+            [RuntimeEvent::proof_of_existence(proof_of_existence::Event::ClaimCreated {
+                claim: claim_of("my_document"),
+                owner: alice.clone(),
+            })]
+        );
+        assert_eq!(
+            claim_events(2),
+            [RuntimeEvent::proof_of_existence(proof_of_existence::Event::ClaimRevoked {
+                claim: claim_of("my_document"),
+                owner: alice.clone(),
+            })]
+        );
+
+        // The fee and claim fee of the first block are both transfers out of alice's account.
+        let transfers = runtime
+            .system
+            .events(1)
+            .iter()
+            .filter(|record| matches!(record.event, RuntimeEvent::balances(balances::Event::Transferred { .. })))
+            .count();
+        assert_eq!(transfers, 2);
+        // The events were moved out of the pallets.
+        assert!(runtime.balances.events().is_empty());
+        assert!(runtime.proof_of_existence.events().is_empty());
+    }
+
+    #[test]
+    fn scheduled_transfer_is_dispatched_at_its_block() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let transfer = RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 30 });
+        let schedule = types::ExtrinsicBuilder::new(alice.clone())
+            .call(RuntimeCall::scheduler(scheduler::Call::schedule { when: 3, call: Box::new(transfer) }))
+            .build();
+        // Scheduling pays for the weight of the transfer too.
+        assert_eq!(schedule.call.weight(), 10_000 + weights::balances::TRANSFER);
+        let blocks = build_chain(&runtime, vec![vec![sign_next(&runtime, schedule)], vec![], vec![]]);
+
+        runtime.execute_blocks(&blocks[..2]).expect("wrong blocks execution");
+        assert_eq!(runtime.balances.balance(&bob), 0);
+        assert!(runtime.scheduler.task((3, 0)).is_some());
+
+        runtime.execute_block(&blocks[2]).expect("wrong block execution");
+        assert_eq!(runtime.balances.balance(&bob), 30);
+        assert!(runtime.scheduler.task((3, 0)).is_none());
+        let dispatched = runtime
+            .system
+            .events(3)
+            .iter()
+            .find(|record| matches!(record.event, RuntimeEvent::scheduler(_)))
+            .cloned();
+        assert_eq!(
+            dispatched,
+            Some(support::EventRecord {
+                phase: support::Phase::Initialization,
+                event: RuntimeEvent::scheduler(scheduler::Event::Dispatched { task: (3, 0), id: None, result: Ok(()) }),
+            })
+        );
+    }
+
+    #[test]
+    fn named_schedule_can_be_canceled_before_it_is_due() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.proof_of_existence.create_claim(alice.clone(), "my_document".into(), &mut runtime.balances).unwrap();
+
+        let scheduler_call = |call| {
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::scheduler(call)).build()
+        };
+        let revoke = RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim { claim: claim_of("my_document") });
+        let schedule = scheduler_call(scheduler::Call::schedule_named {
+            id: "revoke".to_string(),
+            when: 3,
+            call: Box::new(revoke),
+        });
+        let cancel = scheduler_call(scheduler::Call::cancel_named { id: "revoke".to_string() });
+        let blocks = build_chain(
+            &runtime,
+            vec![
+                vec![sign_next(&runtime, schedule)],
+                vec![runtime.sign(cancel, 1)],
+                vec![],
+            ],
+        );
+
+        runtime.execute_blocks(&blocks[..1]).expect("wrong block execution");
+        assert_eq!(runtime.scheduler.named_task(&alice, "revoke"), Some((3, 0)));
+        runtime.execute_blocks(&blocks[1..]).expect("wrong blocks execution");
+        assert_eq!(runtime.scheduler.named_task(&alice, "revoke"), None);
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim_of("my_document")), Some(&alice));
+    }
+
+    #[test]
+    fn unbonded_funds_are_released_at_the_end_of_the_bonding_duration() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let staking_call = |call| {
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::staking(call)).build()
+        };
+        let blocks = build_chain(
+            &runtime,
+            vec![
+                vec![runtime.sign(staking_call(staking::Call::bond { amount: 60 }), 0)],
+                vec![runtime.sign(staking_call(staking::Call::unbond { amount: 60 }), 1)],
+            ],
+        );
+        runtime.execute_blocks(&blocks).expect("wrong blocks execution");
+        let locked = |runtime: &Runtime| runtime.balances.locked_balance(&alice, balances::WithdrawReasons::TRANSFER);
+        assert_eq!(locked(&runtime), 60);
+        assert_eq!(runtime.kill_account(&alice), Err("account still has bonded funds".into()));
+
+        // The funds unbonded in block 2 are released at the end of block 2 + `BONDING_DURATION`.
+        let unlock_block = 2 + <Runtime as staking::Config>::BONDING_DURATION;
+        while runtime.system.block_number() < unlock_block - 1 {
+            let block = next_block(&runtime, vec![]);
+            runtime.execute_block(&block).expect("wrong block execution");
+        }
+        assert_eq!(locked(&runtime), 60);
+        let block = next_block(&runtime, vec![]);
+        runtime.execute_block(&block).expect("wrong block execution");
+        assert_eq!(locked(&runtime), 0);
+        assert_eq!(runtime.staking.ledger(&alice), None);
+    }
+
+    #[test]
+    fn passed_referendum_is_dispatched_with_root_origin() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.balances.set_balance(bob.clone(), 50);
+
+        // Only the root origin can set a balance, so Alice can't make the call herself.
+        let set_balance = RuntimeCall::balances(balances::Call::force_set_balance { who: bob.clone(), amount: 1_000 });
+        let democracy_call = |caller: &types::AccountId, call| {
+            types::ExtrinsicBuilder::new(caller.clone()).call(RuntimeCall::democracy(call)).build()
+        };
+        let propose = democracy_call(&alice, democracy::Call::propose { call: Box::new(set_balance) });
+        let votes = vec![
+            runtime.sign(democracy_call(&alice, democracy::Call::vote { ref_index: 0, aye: true }), 1),
+            runtime.sign(democracy_call(&bob, democracy::Call::vote { ref_index: 0, aye: false }), 0),
+        ];
+        let blocks = build_chain(&runtime, vec![vec![sign_next(&runtime, propose)], votes]);
+        runtime.execute_blocks(&blocks).expect("wrong blocks execution");
+        let tally = runtime.democracy.referendum(0).map(|referendum| referendum.tally);
+        assert!(tally.is_some_and(|tally| tally.ayes > tally.nays));
+
+        // The referendum proposed in block 1 is closed at the end of block 1 + `VOTING_PERIOD`, and
+        // its call is dispatched at the start of the next block.
+        let end = 1 + <Runtime as democracy::Config>::VOTING_PERIOD;
+        let blocks = build_chain(&runtime, vec![vec![]; (end - runtime.system.block_number()) as usize]);
+        runtime.execute_blocks(&blocks).expect("wrong blocks execution");
+        assert!(runtime.democracy.referendum(0).is_none());
+        assert!(runtime.system.events(end).iter().any(|record| {
+            record.event == RuntimeEvent::democracy(democracy::Event::Passed { ref_index: 0 })
+        }));
+        assert_ne!(runtime.balances.balance(&bob), 1_000);
+
+        let block = next_block(&runtime, vec![]);
+        runtime.execute_block(&block).expect("wrong block execution");
+        assert_eq!(runtime.balances.balance(&bob), 1_000);
+    }
+
+    #[test]
+    fn multisig_call_is_dispatched_with_its_last_approval() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie, dave] = ["alice", "bob", "charlie", "dave"].map(dev_account);
+        let account = multisig::Pallet::<Runtime>::multi_account_id(&[alice.clone(), bob.clone(), charlie.clone()], 2);
+        for who in [&alice, &bob, &account] {
+            runtime.balances.set_balance(who.clone(), 100);
+        }
+
+        let transfer = RuntimeCall::balances(balances::Call::transfer { to: dave.clone(), amount: 30 });
+        let as_multi = |caller: &types::AccountId, others: [&types::AccountId; 2]| {
+            let call = multisig::Call::as_multi {
+                other_signatories: others.map(types::AccountId::clone).to_vec(),
+                threshold: 2,
+                call: Box::new(transfer.clone()),
+            };
+            types::ExtrinsicBuilder::new(caller.clone()).call(RuntimeCall::multisig(call)).build()
+        };
+        let extrinsics = vec![as_multi(&alice, [&bob, &charlie]), as_multi(&bob, [&charlie, &alice])];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let report = runtime.execute_block(&block).expect("wrong block execution");
+        assert!(report.all_succeeded());
+        assert_eq!(runtime.balances.balance(&dave), 30);
+        assert_eq!(runtime.balances.balance(&account), 70);
+        // The deposit of Alice was returned once the call was executed.
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        let call_hash = multisig::Pallet::<Runtime>::call_hash(&transfer);
+        let executed = support::EventRecord {
+            phase: support::Phase::ApplyExtrinsic(1),
+            event: RuntimeEvent::multisig(multisig::Event::MultisigExecuted {
+                multisig: account.clone(),
+                call_hash,
+                result: Ok(()),
+            }),
+        };
+        assert!(runtime.system.events(1).contains(&executed));
+    }
+
+    #[test]
+    fn proxy_calls_are_dispatched_on_behalf_of_the_real_account() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.balances.set_balance(bob.clone(), 100);
+
+        let add_proxy = proxy::Call::add_proxy { delegate: bob.clone(), proxy_type: proxy::ProxyType::BalancesOnly };
+        let proxy_call = |call| {
+            let call = proxy::Call::proxy { real: alice.clone(), call: Box::new(call) };
+            types::ExtrinsicBuilder::new(bob.clone()).call(RuntimeCall::proxy(call)).build()
+        };
+        let transfer = RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 30 });
+        let claim = RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { content: "my_document".into() });
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::proxy(add_proxy)).build(),
+            proxy_call(transfer),
+            proxy_call(claim),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert!(results[..2].iter().all(Result::is_ok));
+        // A balances proxy can't make a claim for Alice.
+        assert_eq!(results[2].as_ref().err(), Some(&proxy::Error::Unproxyable.into()));
+        assert_eq!(runtime.balances.balance(&charlie), 30);
+        assert_eq!(runtime.proof_of_existence.get_claim(&claim_of("my_document")), None);
+        let executed = RuntimeEvent::proxy(proxy::Event::ProxyExecuted { real: alice, delegate: bob, result: Ok(()) });
+        assert!(runtime.system.events(1).iter().any(|record| record.event == executed));
+    }
+
+    #[test]
+    fn batch_all_reverts_its_calls_when_one_fails() {
+        let mut runtime = Runtime::new();
+        let [alice, charlie] = ["alice", "charlie"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+
+        let transfer = |amount| RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount });
+        let calls = vec![transfer(30), transfer(1_000)];
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(alice.clone())
+                .call(RuntimeCall::utility(utility::Call::batch_all { calls: calls.clone() }))
+                .build(),
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::utility(utility::Call::batch { calls })).build(),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        // The first transfer of the atomic batch was reverted with the second, which failed.
+        assert!(results[0].is_err());
+        assert!(results[1].is_ok());
+        assert_eq!(runtime.balances.balance(&charlie), 30);
+        let failed = RuntimeEvent::utility(utility::Event::BatchCompleted { who: Some(alice), failed: 1 });
+        assert!(runtime.system.events(1).iter().any(|record| record.event == failed));
+    }
+
+    #[test]
+    fn batches_nested_in_a_sudo_call_in_a_batch_are_refused() {
+        let mut runtime = Runtime::new();
+        development_genesis().build(&mut runtime).expect("valid genesis");
+        let [alice, charlie] = ["alice", "charlie"].map(dev_account);
+
+        // The inner batch would be dispatched while the utility pallet is taken out of the runtime
+        // by the outer batch, so it is refused instead, and the sudo call reports its error.
+        let transfer = RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 30 });
+        let inner = RuntimeCall::utility(utility::Call::batch { calls: vec![transfer] });
+        let sudo = RuntimeCall::sudo(sudo::Call::sudo { call: Box::new(inner) });
+        let outer = RuntimeCall::utility(utility::Call::batch { calls: vec![sudo] });
+        let block = next_block(&runtime, sign_all(&runtime, vec![types::ExtrinsicBuilder::new(alice.clone()).call(outer).build()]));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert!(results[0].is_ok());
+        assert_eq!(runtime.balances.balance(&charlie), 0);
+        let events = runtime.system.events(1).iter().map(|record| record.event.clone()).collect::<Vec<_>>();
+        let sudid = RuntimeEvent::sudo(sudo::Event::Sudid { result: Err(support::DispatchError::Reentrancy("utility")) });
+        assert!(events.contains(&sudid));
+        // The events of the outer batch are kept.
+        assert!(events.contains(&RuntimeEvent::utility(utility::Event::BatchCompleted { who: Some(alice), failed: 0 })));
+    }
+
+    #[test]
+    fn sudo_calls_nested_in_a_batch_in_a_sudo_call_are_refused() {
+        let mut runtime = Runtime::new();
+        development_genesis().build(&mut runtime).expect("valid genesis");
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+
+        // The sudo pallet is taken out of the runtime by the outer sudo call, so the inner one
+        // would be checked against the empty pallet standing in for it. It is refused instead.
+        let force_set_balance = RuntimeCall::balances(balances::Call::force_set_balance { who: bob.clone(), amount: 500 });
+        let inner = RuntimeCall::sudo(sudo::Call::sudo { call: Box::new(force_set_balance) });
+        let batch = RuntimeCall::utility(utility::Call::batch { calls: vec![inner] });
+        let outer = RuntimeCall::sudo(sudo::Call::sudo { call: Box::new(batch) });
+        let block = next_block(&runtime, sign_all(&runtime, vec![types::ExtrinsicBuilder::new(alice.clone()).call(outer).build()]));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert!(results[0].is_ok());
+        assert_eq!(runtime.balances.balance(&bob), 0);
+        assert_eq!(runtime.sudo.key(), Some(&alice));
+        let failed = RuntimeEvent::utility(utility::Event::ItemFailed {
+            index: 0,
+            error: support::DispatchError::Reentrancy("sudo"),
+        });
+        assert!(runtime.system.events(1).iter().any(|record| record.event == failed));
+    }
+
+    #[test]
+    fn timestamp_is_set_by_an_inherent_once_per_block() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(alice.clone(), 100);
+        let set = |now| RuntimeCall::timestamp(timestamp::Call::set { now });
+        fn seal_inherents(
+            runtime: &Runtime,
+            inherents: Vec<RuntimeCall>,
+        ) -> Result<types::Block, support::DispatchError> {
+            BlockBuilder::new(runtime, runtime.system.max_block_weight()).with_inherents(inherents).seal(vec![])
+        }
+
+        let block_1 = seal_inherents(&runtime, vec![set(6_000)]).expect("wrong block building");
+        runtime.execute_block(&block_1).expect("wrong block execution");
+        assert_eq!(runtime.timestamp.now(), 6_000);
+
+        // A block which sets its timestamp twice, or too early, is refused.
+        let twice = seal_inherents(&runtime, vec![set(12_000), set(18_000)]);
+        assert_eq!(twice.err(), Some(timestamp::Error::AlreadySet.into()));
+        let twice = types::Block::new(block_1.header.hash(), 2, vec![]).with_inherents(vec![set(12_000), set(18_000)]);
+        assert_eq!(runtime.clone().execute_block(&twice).err(), Some(timestamp::Error::AlreadySet.into()));
+        let too_early = types::Block::new(block_1.header.hash(), 2, vec![]).with_inherents(vec![set(7_000)]);
+        assert_eq!(runtime.clone().execute_block(&too_early).err(), Some(timestamp::Error::TooEarly.into()));
+
+        // The timestamp can't be set by a signed extrinsic.
+        let extrinsic = types::ExtrinsicBuilder::new(alice).call(set(12_000)).build();
+        let block_2 = next_block(&runtime, vec![sign_next(&runtime, extrinsic)]);
+        let results = runtime.execute_block(&block_2).expect("wrong block execution").results;
+        assert_eq!(results[0].as_ref().err(), Some(&support::DispatchError::BadOrigin));
+        assert_eq!(runtime.timestamp.now(), 6_000);
+    }
+
+    #[test]
+    fn vested_transfers_unlock_at_the_start_of_each_block() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.balances.set_balance(bob.clone(), 50);
+
+        let schedule = vesting::VestingInfo { locked: 40, per_block: 10, starting_block: 1 };
+        let vested_transfer = vesting::Call::vested_transfer { target: bob.clone(), schedule };
+        let transfer = || {
+            let call = RuntimeCall::balances(balances::Call::transfer { to: charlie.clone(), amount: 20 });
+            types::ExtrinsicBuilder::new(bob.clone()).call(call).build()
+        };
+        let blocks = build_chain(
+            &runtime,
+            vec![
+                vec![runtime.sign(types::ExtrinsicBuilder::new(alice).call(RuntimeCall::vesting(vested_transfer)).build(), 0)],
+                vec![],
+                vec![runtime.sign(transfer(), 0)],
+            ],
+        );
+
+        runtime.execute_blocks(&blocks).expect("wrong block execution");
+        // The funds which vested are unlocked, so Bob can transfer them.
+        assert_eq!(runtime.balances.balance(&charlie), 20);
+        // Two blocks after the start of the schedule, 20 is still vesting.
+        assert_eq!(runtime.vesting.vesting_balance(&bob), 20);
+        assert_eq!(runtime.balances.locked_balance(&bob, balances::WithdrawReasons::TRANSFER), 20);
+    }
+
+    #[test]
+    fn identities_reserve_a_deposit_and_are_judged_by_registrars() {
+        let mut runtime = Runtime::new();
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.balances.set_balance(bob.clone(), 100);
+        let add_registrar = identity::Call::add_registrar { account: bob.clone() };
+        assert!(runtime.dispatch(Root, RuntimeCall::identity(add_registrar)).is_ok());
+
+        let info = identity::IdentityInfo { display: "Alice".to_string(), ..Default::default() };
+        let set_identity = identity::Call::set_identity { info };
+        let judgement = identity::Call::provide_judgement {
+            registrar_index: 0,
+            target: alice.clone(),
+            judgement: identity::Judgement::KnownGood,
+        };
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::identity(set_identity)).build(),
+            types::ExtrinsicBuilder::new(bob).call(RuntimeCall::identity(judgement)).build(),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let report = runtime.execute_block(&block).expect("wrong block execution");
+        assert!(report.all_succeeded());
+        // The basic deposit, and one for each of the 5 bytes of the display name.
+        assert_eq!(runtime.balances.reserved_balance(&alice), 15);
+        let registration = runtime.identity.identity(&alice).expect("alice has an identity");
+        assert_eq!(registration.judgements, [(0, identity::Judgement::KnownGood)]);
+    }
+
+    #[test]
+    fn unique_items_are_minted_and_transferred_by_extrinsics() {
+        let mut runtime = Runtime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.balances.set_balance(bob.clone(), 100);
+
+        let mint = uniques::Call::mint { collection: 0, item: 7, owner: bob.clone() };
+        let transfer = uniques::Call::transfer { collection: 0, item: 7, to: charlie.clone() };
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::uniques(uniques::Call::create { collection: 0 })).build(),
+            types::ExtrinsicBuilder::new(alice).call(RuntimeCall::uniques(mint)).build(),
+            types::ExtrinsicBuilder::new(bob.clone()).call(RuntimeCall::uniques(transfer)).build(),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let report = runtime.execute_block(&block).expect("wrong block execution");
+        assert!(report.all_succeeded());
+        assert_eq!(runtime.uniques.owner(0, 7), Some(&charlie));
+        assert_eq!(runtime.uniques.owned(&charlie).collect::<Vec<_>>(), [(0, 7)]);
+        assert_eq!(runtime.uniques.owned(&bob).count(), 0);
+    }
+
+    #[test]
+    fn assets_are_minted_by_their_admin_and_transferred_apart_from_native_balances() {
+        let mut runtime = Runtime::new();
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+        runtime.balances.set_balance(alice.clone(), 100);
+        runtime.balances.set_balance(bob.clone(), 100);
+
+        let mint = assets::Call::mint { asset_id: 0, beneficiary: bob.clone(), amount: 1_000 };
+        let transfer = assets::Call::transfer { asset_id: 0, to: alice.clone(), amount: 400 };
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::assets(assets::Call::create { asset_id: 0 })).build(),
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::assets(mint)).build(),
+            types::ExtrinsicBuilder::new(bob.clone()).call(RuntimeCall::assets(transfer)).build(),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let report = runtime.execute_block(&block).expect("wrong block execution");
+        assert!(report.all_succeeded());
+        assert_eq!(runtime.assets.balance(0, &alice), 400);
+        assert_eq!(runtime.assets.balance(0, &bob), 600);
+        // Only the fees were paid with the native balance.
+        assert!(runtime.balances.balance(&bob) > 90);
+    }
+
+    #[test]
+    fn only_the_sudo_account_dispatches_with_the_root_origin() {
+        let mut runtime = Runtime::new();
+        development_genesis().build(&mut runtime).expect("valid genesis");
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+        runtime.balances.set_balance(bob.clone(), 100);
+
+        let sudo = |amount| {
+            let force_set_balance = RuntimeCall::balances(balances::Call::force_set_balance { who: bob.clone(), amount });
+            RuntimeCall::sudo(sudo::Call::sudo { call: Box::new(force_set_balance) })
+        };
+        let set_key = RuntimeCall::sudo(sudo::Call::set_key { new: bob.clone() });
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(bob.clone()).call(sudo(1_000)).build(),
+            types::ExtrinsicBuilder::new(alice.clone()).call(sudo(500)).build(),
+            types::ExtrinsicBuilder::new(alice).call(set_key).build(),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert_eq!(results[0].as_ref().err(), Some(&sudo::Error::RequireSudo.into()));
+        assert!(results[1..].iter().all(Result::is_ok));
+        assert_eq!(runtime.balances.balance(&bob), 500);
+        assert_eq!(runtime.sudo.key(), Some(&bob));
+    }
+
+    #[test]
+    fn the_sudo_account_kills_accounts_no_pallet_holds_state_for() {
+        let mut runtime = Runtime::new();
+        development_genesis().build(&mut runtime).expect("valid genesis");
+        let [alice, bob, dave] = ["alice", "bob", "dave"].map(dev_account);
+        runtime.system.inc_nonce(&dave);
+
+        let kill = |who: &types::AccountId| {
+            let call = RuntimeCall::sudo(sudo::Call::kill_account { who: who.clone() });
+            types::ExtrinsicBuilder::new(alice.clone()).call(call).build()
+        };
+        let block = next_block(&runtime, sign_all(&runtime, vec![kill(&bob), kill(&alice), kill(&dave)]));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        // Bob sits in the council, and Alice holds the sudo key.
+        assert_eq!(results[0].as_ref().err(), Some(&"account is a member of the council or an authority".into()));
+        assert_eq!(results[1].as_ref().err(), Some(&sudo::Error::CannotKillSudoKey.into()));
+        assert!(results[2].is_ok());
+        assert!(!runtime.system.account_exists(&dave));
+        assert_eq!(runtime.kill_account(&alice), Err(sudo::Error::CannotKillSudoKey.into()));
+    }
+
+    #[test]
+    fn paused_calls_are_refused_until_they_are_unpaused() {
+        let mut runtime = Runtime::new();
+        development_genesis().build(&mut runtime).expect("valid genesis");
+        let [alice, bob] = ["alice", "bob"].map(dev_account);
+
+        let sudo = |call| RuntimeCall::sudo(sudo::Call::sudo { call: Box::new(RuntimeCall::tx_pause(call)) });
+        let pause = sudo(tx_pause::Call::pause { pallet: "balances".into(), function: Some("transfer".into()) });
+        let transfer = RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 10 });
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(alice.clone()).call(pause).build(),
+            types::ExtrinsicBuilder::new(alice.clone()).call(transfer.clone()).build(),
+            // The calls dispatched by other pallets are filtered too.
+            types::ExtrinsicBuilder::new(alice.clone())
+                .call(RuntimeCall::utility(utility::Call::batch_all { calls: vec![transfer.clone()] }))
+                .build(),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert!(results[0].is_ok());
+        assert_eq!(results[1].as_ref().err(), Some(&support::DispatchError::CallFiltered));
+        assert_eq!(results[2].as_ref().err(), Some(&support::DispatchError::CallFiltered));
+        assert_eq!(runtime.balances.balance(&bob), 0);
+
+        // The root origin isn't filtered, and lifts the pause.
+        let unpause = tx_pause::Call::unpause { pallet: "balances".into(), function: Some("transfer".into()) };
+        assert!(runtime.dispatch(Root, RuntimeCall::tx_pause(unpause)).is_ok());
+        let extrinsics = vec![types::ExtrinsicBuilder::new(alice).call(transfer).build()];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert!(results[0].is_ok());
+        assert_eq!(runtime.balances.balance(&bob), 10);
+    }
+
+    #[test]
+    fn half_of_the_council_can_approve_treasury_spends() {
+        let mut runtime = Runtime::new();
+        development_genesis().build(&mut runtime).expect("valid genesis");
+        let [alice, bob, dave] = ["alice", "bob", "dave"].map(dev_account);
+        runtime.balances.set_balance(bob.clone(), 100);
+        runtime.balances.set_balance(dave.clone(), 100);
+
+        let propose = |threshold, call| {
+            let call = collective::Call::propose { threshold, call: Box::new(RuntimeCall::treasury(call)) };
+            types::ExtrinsicBuilder::new(alice.clone()).call(RuntimeCall::collective(call)).build()
+        };
+        let spend = treasury::Call::propose_spend { beneficiary: dave.clone(), amount: 10 };
+        let vote = || RuntimeCall::collective(collective::Call::vote { proposal_index: 1, approve: true });
+        let extrinsics = vec![
+            types::ExtrinsicBuilder::new(dave.clone()).call(RuntimeCall::treasury(spend)).build(),
+            // A single member out of three is not enough to reject the spend.
+            propose(1, treasury::Call::reject_proposal { proposal_id: 0 }),
+            propose(2, treasury::Call::approve_proposal { proposal_id: 0 }),
+            types::ExtrinsicBuilder::new(bob).call(vote()).build(),
+            types::ExtrinsicBuilder::new(dave).call(vote()).build(),
+        ];
+        let block = next_block(&runtime, sign_all(&runtime, extrinsics));
+
+        let results = runtime.execute_block(&block).expect("wrong block execution").results;
+        assert!(results[..4].iter().all(Result::is_ok));
+        assert_eq!(results[4].as_ref().err(), Some(&collective::Error::NotMember.into()));
+        assert_eq!(runtime.treasury.approvals(), [0]);
+        let rejected = RuntimeEvent::collective(collective::Event::Executed {
+            proposal_index: 0,
+            result: Err(support::DispatchError::BadOrigin),
+        });
+        assert!(runtime.system.events(1).iter().any(|record| record.event == rejected));
+    }
+
+    #[test]
+    fn random_transfer_blocks_conserve_issuance() {
+        let accounts = ["alice", "bob", "charlie", "dave"].map(dev_account);
+        let treasury = <Runtime as treasury::Config>::account_id();
+
+        for seed in 1..=20 {
+            let mut rng = Rng(seed);
+            let mut runtime = Runtime::new();
+            for who in &accounts {
+                runtime.balances.set_balance(who.clone(), 100);
+            }
+            let issuance = runtime.balances.total_issuance();
+
+            for block_number in 1..=20 {
+                // At most 10 transfers, which is as many as fit in a block. A quarter of them are
+                // made by an account to itself, through each of the transfer calls.
+                let extrinsics = (0..rng.below(11))
+                    .map(|_| {
+                        let from = &accounts[rng.below(4) as usize];
+                        let to = accounts[rng.below(4) as usize].clone();
+                        let amount = rng.below(80).into();
+                        let call = match rng.below(4) {
+                            0 => balances::Call::transfer { to, amount },
+                            1 => balances::Call::transfer_some { to, amount },
+                            2 => balances::Call::transfer_keep_alive { to, amount },
+                            _ => balances::Call::transfer_all { to, keep_alive: rng.below(2) == 0 },
+                        };
+                        types::ExtrinsicBuilder::new(from.clone())
+                            .call(RuntimeCall::balances(call))
+                            .tip(rng.below(3).into())
+                            .build()
+                    })
+                    .collect();
+                let extrinsics = sign_all(&runtime, extrinsics);
+                let nonces_before = accounts.clone().map(|who| runtime.system.get_nonce(&who));
+                let issuance_before = runtime.balances.total_issuance();
+                runtime
+                    .execute_block(&next_block(&runtime, extrinsics))
+                    .expect("wrong block execution");
+
+                // Fees and tips only move funds into the treasury or burn them, so nothing is
+                // created, and the total issuance follows what is burnt.
+                let held = accounts
+                    .iter()
+                    .chain([&treasury])
+                    .map(|who| runtime.balances.total_balance(who))
+                    .sum::<types::Balance>();
+                assert_eq!(held, runtime.balances.total_issuance(), "seed {seed}, block {block_number}");
+                assert!(runtime.balances.total_issuance() <= issuance_before, "seed {seed}, block {block_number}");
+
+                // Balances are unsigned, so an underflow would show up as a huge balance.
+                assert!(runtime.balances.iter().all(|(_, free)| *free <= issuance));
+
+                let nonces_after = accounts.clone().map(|who| runtime.system.get_nonce(&who));
+                assert!(nonces_before.iter().zip(&nonces_after).all(|(before, after)| before <= after));
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_roundtrip_through_the_codec() {
+        use crate::codec::{Decode, Encode};
+
+        let setup = || {
+            let mut runtime = Runtime::new();
+            runtime.balances.set_balance(dev_account("alice"), 100);
+            runtime
+        };
+        let (mut original, mut copy) = (setup(), setup());
+
+        let block_1 = next_block(
+            &original,
+            sign_all(
+                &original,
+                vec![
+                    types::ExtrinsicBuilder::new(dev_account("alice"))
+                        .call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 30 }))
+                        .valid_until(5)
+                        .tip(2)
+                        .build(),
+                    types::ExtrinsicBuilder::new(dev_account("alice"))
+                        .call(RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                            content: "my_document".into(),
+                        }))
+                        .build(),
+                ],
+            ),
+        );
+        let bytes = block_1.encode();
+        let decoded = types::Block::decode_all(&bytes).expect("block must decode");
+        assert_eq!(decoded.encode(), bytes);
+        assert_eq!(decoded.header.block_number, 1);
+        assert_eq!(decoded.extrinsics[0].extrinsic.valid_until, Some(5));
+        assert_eq!(decoded.extrinsics[1].nonce, 1);
+        assert_eq!(decoded.extrinsics[1].extrinsic.call.name(), ("proof_of_existence", "create_claim"));
+
+        // The decoded block is executed exactly like the original one.
+        original.execute_block(&block_1).expect("wrong block execution");
+        copy.execute_block(&decoded).expect("wrong block execution");
+        assert!(original.diff(&copy).is_empty());
+
+        // The first byte of a runtime call is the index of its pallet, and there are only 18.
+        assert_eq!(RuntimeCall::decode_all(&[18, 0]).err(), Some(crate::codec::Error::Invalid));
+        assert!(types::Block::decode_all(&bytes[..bytes.len() - 1]).is_err());
+
+        // The call of an extrinsic follows the transaction version of the runtime, after the 32
+        // bytes of the caller, so extrinsics encoded for another version of the calls are refused.
+        let extrinsic = decoded.extrinsics[0].extrinsic.encode();
+        assert_eq!(extrinsic[32..36], <Runtime as system::Config>::VERSION.transaction_version.to_le_bytes());
+        let mut outdated = extrinsic.clone();
+        outdated[32] += 1;
+        assert_eq!(types::Extrinsic::decode_all(&outdated).err(), Some(crate::codec::Error::UnsupportedVersion));
+    }
+
+    #[test]
+    fn deeply_nested_calls_are_refused_by_the_codec() {
+        use crate::codec::{Decode, Encode, MAX_CALL_DEPTH};
+
+        // A sudo call is the index of the sudo pallet and of its call, followed by the call it holds.
+        let sudo = [14, 0];
+        let nested = |depth: u32| {
+            let transfer = RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 10 });
+            let mut bytes = sudo.repeat(depth as usize - 1);
+            bytes.extend(transfer.encode());
+            bytes
+        };
+        let decoded = RuntimeCall::decode_all(&nested(MAX_CALL_DEPTH)).expect("call must decode");
+        assert_eq!(decoded.encode(), nested(MAX_CALL_DEPTH));
+        assert_eq!(RuntimeCall::decode_all(&nested(MAX_CALL_DEPTH + 1)).err(), Some(crate::codec::Error::Invalid));
+
+        // Hundreds of kilobytes of nested calls are refused instead of overflowing the stack.
+        let bytes = sudo.repeat(200_000);
+        assert_eq!(RuntimeCall::decode_all(&bytes).err(), Some(crate::codec::Error::Invalid));
+    }
+
+    #[test]
+    fn calls_can_be_encoded_from_the_metadata() {
+        use crate::codec::{Decode, Encode};
+
+        let metadata = Runtime::metadata();
+        assert_eq!(metadata.version, <Runtime as system::Config>::VERSION);
+        assert_eq!(metadata.pallets.len(), 18);
+        assert_eq!(metadata.pallet("aura").map(|pallet| pallet.index), Some(16));
+
+        let pallet = metadata.pallet("balances").unwrap();
+        let transfer = pallet.call("transfer").unwrap();
+        let args = transfer.args.iter().map(|arg| (arg.name, arg.ty)).collect::<Vec<_>>();
+        assert_eq!(args, [("to", "T::AccountId"), ("amount", "T::Balance")]);
+        let error = pallet.errors.iter().find(|error| error.name == "InsufficientBalance").unwrap();
+        assert_eq!(error.docs, ["The free balance of the account is lower than the amount."]);
+
+        // A transfer encoded out of the metadata alone decodes into the same `RuntimeCall`.
+        let mut bytes = vec![pallet.index, transfer.index];
+        dev_account("bob").encode_to(&mut bytes);
+        10u128.encode_to(&mut bytes);
+        assert!(matches!(
+            RuntimeCall::decode_all(&bytes),
+            Ok(RuntimeCall::balances(balances::Call::transfer { to, amount: 10 })) if to == dev_account("bob")
+        ));
+    }
+
+    #[test]
+    fn extrinsic_builder_matches_hand_constructed_extrinsic() {
+        let transfer = || RuntimeCall::balances(balances::Call::transfer { to: dev_account("bob"), amount: 10 });
+        let expected: types::Extrinsic = support::Extrinsic {
+            caller: dev_account("alice"),
+            call: transfer(),
+            valid_until: Some(5),
+            tip: 2,
+        };
+
+        let built = types::ExtrinsicBuilder::new(dev_account("alice"))
+            .call(transfer())
+            .valid_until(5)
+            .tip(2)
+            .build();
+        assert_eq!(built.caller, expected.caller);
+        assert_eq!(built.valid_until, expected.valid_until);
+        assert_eq!(built.tip, expected.tip);
+        assert!(matches!(
+            (built.call, expected.call),
+            (
+                RuntimeCall::balances(balances::Call::transfer { to: built_to, amount: 10 }),
+                RuntimeCall::balances(balances::Call::transfer { to: expected_to, amount: 10 }),
+            ) if built_to == expected_to
+        ));
+
+        // The optional fields default to an immortal extrinsic without a tip.
+        let built = types::ExtrinsicBuilder::new(dev_account("alice")).call(transfer()).build();
+        assert_eq!(built.valid_until, None);
+        assert_eq!(built.tip, 0);
+    }
+
+    // A second runtime which records a trace of the calls it dispatches, used to check the calls
+    // observed through `support::PreDispatch`, and which includes the registry pallet, so its calls
+    // are checked through `execute_block`.
+    mod traced {
+        use std::cell::RefCell;
+
+        use super::registry;
+        use crate::support::{DispatchResult, DispatchResultWithOutput, RawOrigin};
+        use crate::{balances, support, system};
+
+        pub mod types {
+            pub type AccountId = String;
+            pub type Balance = u128;
+            pub type BlockNumber = u32;
+            pub type Nonce = u32;
+            pub type Extrinsic =
+                crate::support::Extrinsic<AccountId, super::RuntimeCall, BlockNumber, Balance>;
+            pub type SignedExtrinsic = crate::support::SignedExtrinsic<Extrinsic, Nonce>;
+            pub type Block =
+                crate::support::Block<crate::support::Header<BlockNumber>, SignedExtrinsic, super::RuntimeCall>;
+        }
+
+        thread_local! {
+            pub static TRACE: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+        }
+
+        impl system::Config for TracedRuntime {
+            type AccountId = types::AccountId;
+            type BlockNumber = types::BlockNumber;
+            type Nonce = types::Nonce;
+            type RuntimeEvent = RuntimeEvent;
+            type Hashing = crate::hashing::Blake2b256;
+        }
+
+        impl balances::Config for TracedRuntime {
+            type Balance = types::Balance;
+            const MINIMUM_TRANSFER: Self::Balance = 1;
+            const MAX_TRANSFERS_PER_BLOCK: u32 = 10;
+            const EXISTENTIAL_DEPOSIT: Self::Balance = 1;
+        }
+
+        impl support::ChargeTransaction for TracedRuntime {
+            type Caller = types::AccountId;
+            type Balance = types::Balance;
+
+            fn charge_transaction(
+                &mut self,
+                _caller: &Self::Caller,
+                _weight: support::Weight,
+                _tip: Self::Balance,
+            ) -> DispatchResult {
+                Ok(())
+            }
+
+            fn refund_transaction(
+                &mut self,
+                _caller: &Self::Caller,
+                _weight: support::Weight,
+                _actual_weight: support::Weight,
+            ) {
+            }
+        }
+
+        // The traced runtime doesn't commit to its state, so the state root of its blocks is left
+        // empty.
+        impl support::StateRoot for TracedRuntime {
+            fn state_root(&self) -> system::Hash {
+                [0; 32]
+            }
+        }
+
+        impl support::PreDispatch for TracedRuntime {
+            type Origin = RuntimeOrigin;
+            type Call = RuntimeCall;
+
+            fn pre_dispatch(&self, origin: &Self::Origin, call: &Self::Call) {
+                let (pallet, function) = call.name();
+                let caller = match origin {
+                    RawOrigin::Signed(who) => who.as_str(),
+                    RawOrigin::Root => "root",
+                    RawOrigin::None => "none",
+                    RawOrigin::Collective { .. } => "collective",
+                };
+                TRACE.with(|trace| trace.borrow_mut().push(format!("{caller} calls {pallet}::{function}")));
+            }
+
+            fn post_dispatch(&self, result: &DispatchResultWithOutput) {
+                let outcome = match result {
+                    Ok(_) => "ok".to_string(),
+                    Err(e) => format!("error: {e}"),
+                };
+                TRACE.with(|trace| trace.borrow_mut().push(outcome));
+            }
+        }
+
+        impl support::CallFilter<RuntimeCall> for TracedRuntime {}
+
+        impl registry::Config for TracedRuntime {}
+
+        #[macros::runtime]
+        #[derive(Clone)]
+        pub struct TracedRuntime {
+            pub system: system::Pallet<TracedRuntime>,
+            #[pallet_index = 9]
+            #[hooks]
+            pub balances: balances::Pallet<TracedRuntime>,
+            #[events]
+            pub registry: registry::Pallet<TracedRuntime>,
+        }
+
+        #[test]
+        fn calls_are_encoded_with_the_declared_pallet_index() {
+            use crate::codec::{Decode, Encode};
+
+            let call = RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount: 1 });
+            let bytes = call.encode();
+            assert_eq!(bytes[..2], [9, 0]);
+            assert!(RuntimeCall::decode_all(&bytes).is_ok());
+            assert_eq!(TracedRuntime::metadata().pallet("balances").map(|pallet| pallet.index), Some(9));
+        }
+
+        #[test]
+        fn pre_dispatch_observes_every_call() {
+            let mut runtime = TracedRuntime::new();
+            runtime.balances.set_balance("alice".to_string(), 100);
+            for who in ["alice", "charlie"] {
+                runtime.system.set_public_key(who.to_string(), crate::keyring::dev_pair(who).public());
+            }
+
+            let transfer = |caller: &str, amount| {
+                let extrinsic = support::Extrinsic {
+                    caller: caller.to_string(),
+                    call: RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount }),
+                    valid_until: None,
+                    tip: 0,
+                };
+                support::SignedExtrinsic::sign(extrinsic, 0, &runtime.system.genesis_hash(), &crate::keyring::dev_pair(caller))
+            };
+            let block = types::Block::new(
+                runtime.system.best_block_hash(),
+                1,
+                vec![transfer("alice", 30), transfer("charlie", 10)],
+            );
+            runtime.execute_block(&block).expect("wrong block execution");
+
+            let trace = TRACE.with(|trace| trace.take());
+            assert_eq!(
+                trace,
+                vec![
+                    "alice calls balances::transfer",
+                    "ok",
+                    "charlie calls balances::transfer",
+                    "error: balances::InsufficientBalance",
+                ]
+            );
+        }
+
+        #[test]
+        fn register_extrinsic_returns_its_id_through_execute_block() {
+            let mut runtime = TracedRuntime::new();
+            runtime.system.set_public_key("alice".to_string(), crate::keyring::dev_pair("alice").public());
+
+            let register = |who: &str, nonce| {
+                let extrinsic = support::Extrinsic {
+                    caller: "alice".to_string(),
+                    call: RuntimeCall::registry(registry::Call::register { who: who.to_string() }),
+                    valid_until: None,
+                    tip: 0,
+                };
+                support::SignedExtrinsic::sign(extrinsic, nonce, &runtime.system.genesis_hash(), &crate::keyring::dev_pair("alice"))
+            };
+            let block = types::Block::new(runtime.system.best_block_hash(), 1, vec![register("bob", 0), register("charlie", 1)]);
+            let results = runtime.execute_block(&block).expect("wrong block execution").results;
+
+            // The id returned by each call shows up in the execution results, in order.
+            let ids = results
+                .iter()
+                .map(|result| result.as_ref().unwrap().as_ref().unwrap().downcast_ref::<u32>().copied())
+                .collect::<Vec<_>>();
+            assert_eq!(ids, [Some(0), Some(1)]);
+            assert_eq!(*runtime.registry.registered, ["bob", "charlie"]);
+
+            let events = runtime.system.events(1).iter().map(|record| (record.phase, record.event.clone())).collect::<Vec<_>>();
+            let registered = |who: &str, id| RuntimeEvent::registry(registry::Event::Registered { who: who.to_string(), id });
+            assert!(events.contains(&(support::Phase::ApplyExtrinsic(0), registered("bob", 0))));
+            assert!(events.contains(&(support::Phase::ApplyExtrinsic(1), registered("charlie", 1))));
+            assert!(events.contains(&(support::Phase::ApplyExtrinsic(1), RuntimeEvent::system(system::Event::ExtrinsicSuccess))));
+        }
+    }
+
+    // A runtime with two instances of the balances pallet, one for each of two tokens, used to check
+    // the code generated by `#[macros::runtime]` for instances of a pallet.
+    mod tokens {
+        use crate::support::{self, Instance1, RawOrigin};
+        use crate::{balances, system};
+
+        pub mod types {
+            pub type AccountId = String;
+            pub type Balance = u128;
+            pub type BlockNumber = u32;
+            pub type Nonce = u32;
+            // The code generated by `#[macros::runtime]` names these types, even if these tests
+            // never build a block.
+            #[allow(dead_code)]
+            pub type Extrinsic =
+                crate::support::Extrinsic<AccountId, super::RuntimeCall, BlockNumber, Balance>;
+            #[allow(dead_code)]
+            pub type SignedExtrinsic = crate::support::SignedExtrinsic<Extrinsic, Nonce>;
+            #[allow(dead_code)]
+            pub type Block =
+                crate::support::Block<crate::support::Header<BlockNumber>, SignedExtrinsic, super::RuntimeCall>;
+        }
+
+        impl system::Config for TokensRuntime {
+            type AccountId = types::AccountId;
+            type BlockNumber = types::BlockNumber;
+            type Nonce = types::Nonce;
+            type RuntimeEvent = RuntimeEvent;
+            type Hashing = crate::hashing::Blake2b256;
+        }
+
+        impl balances::Config for TokensRuntime {
+            type Balance = types::Balance;
+            const MINIMUM_TRANSFER: Self::Balance = 1;
+            const MAX_TRANSFERS_PER_BLOCK: u32 = 10;
+            const EXISTENTIAL_DEPOSIT: Self::Balance = 1;
+        }
+
+        impl balances::Config<Instance1> for TokensRuntime {
+            type Balance = u64;
+            const MINIMUM_TRANSFER: Self::Balance = 5;
+            const MAX_TRANSFERS_PER_BLOCK: u32 = 10;
+            const EXISTENTIAL_DEPOSIT: Self::Balance = 1;
+        }
+
+        impl support::ChargeTransaction for TokensRuntime {
+            type Caller = types::AccountId;
+            type Balance = types::Balance;
+
+            fn charge_transaction(
+                &mut self,
+                _caller: &Self::Caller,
+                _weight: support::Weight,
+                _tip: Self::Balance,
+            ) -> support::DispatchResult {
+                Ok(())
+            }
+
+            fn refund_transaction(
+                &mut self,
+                _caller: &Self::Caller,
+                _weight: support::Weight,
+                _actual_weight: support::Weight,
+            ) {
+            }
+        }
+
+        impl support::StateRoot for TokensRuntime {
+            fn state_root(&self) -> system::Hash {
+                [0; 32]
+            }
+        }
+
+        impl support::PreDispatch for TokensRuntime {
+            type Origin = RuntimeOrigin;
+            type Call = RuntimeCall;
+        }
+
+        impl support::CallFilter<RuntimeCall> for TokensRuntime {}
+
+        #[macros::runtime]
+        #[derive(Clone)]
+        pub struct TokensRuntime {
+            pub system: system::Pallet<TokensRuntime>,
+            #[events]
+            #[genesis]
+            #[hooks]
+            pub balances: balances::Pallet<TokensRuntime>,
+            #[events]
+            #[genesis]
+            #[hooks]
+            pub tokens: balances::Pallet<TokensRuntime, Instance1>,
+        }
+
+        #[test]
+        fn instances_of_a_pallet_have_their_own_calls() {
+            use crate::codec::{Decode, Encode};
+
+            let (alice, bob) = ("alice".to_string(), "bob".to_string());
+            let mut genesis = RuntimeGenesisConfig::default();
+            genesis.balances.balances = vec![(alice.clone(), 100)];
+            genesis.tokens.balances = vec![(alice.clone(), 50)];
+            let mut runtime = TokensRuntime::new();
+            genesis.build(&mut runtime).unwrap();
+            runtime.system.create_account(&alice);
+
+            let transfer = RuntimeCall::tokens(balances::Call::transfer { to: bob.clone(), amount: 20 });
+            assert_eq!(transfer.name(), ("tokens", "transfer"));
+            let bytes = transfer.encode();
+            assert_eq!(bytes[..2], [1, 0]);
+            assert!(matches!(RuntimeCall::decode_all(&bytes), Ok(RuntimeCall::tokens(_))));
+            assert!(support::Dispatch::dispatch(&mut runtime, RawOrigin::Signed(alice.clone()), transfer).is_ok());
+            assert_eq!((runtime.balances.balance(&bob), runtime.tokens.balance(&bob)), (0, 20));
+
+            // Each instance checks the transfers against its own config.
+            let below_minimum = RuntimeCall::tokens(balances::Call::transfer { to: bob.clone(), amount: 1 });
+            assert_eq!(
+                support::Dispatch::dispatch(&mut runtime, RawOrigin::Signed(alice.clone()), below_minimum).err(),
+                Some(balances::Error::BelowMinimumTransfer.into())
+            );
+            let transfer = RuntimeCall::balances(balances::Call::transfer { to: bob.clone(), amount: 1 });
+            assert!(support::Dispatch::dispatch(&mut runtime, RawOrigin::Signed(alice.clone()), transfer).is_ok());
 
-    runtime.execute_block(block_2).expect("wrong block execution");
+            runtime.collect_events();
+            let transferred = |event: &RuntimeEvent| match event {
+                RuntimeEvent::balances(balances::Event::Transferred { amount, .. }) => Some(("balances", *amount)),
+                RuntimeEvent::tokens(balances::Event::Transferred { amount, .. }) => Some(("tokens", u128::from(*amount))),
+                _ => None,
+            };
+            let events = runtime.system.events(0).iter().filter_map(|record| transferred(&record.event)).collect::<Vec<_>>();
+            assert_eq!(events, [("balances", 1), ("tokens", 20)]);
 
-    println!("{:#?}", runtime);
+            let metadata = TokensRuntime::metadata();
+            let pallets = metadata.pallets.iter().map(|pallet| (pallet.name, pallet.index)).collect::<Vec<_>>();
+            assert_eq!(pallets, [("balances", 0), ("tokens", 1)]);
+        }
+    }
 }
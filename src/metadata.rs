@@ -0,0 +1,106 @@
+//! The metadata of a runtime, which describes its pallets, their calls and their errors, so tools
+//! outside of the runtime, like CLIs and RPC clients, can build calls without hardcoding the layout
+//! of `RuntimeCall`.
+//!
+//! It is generated by `#[macros::runtime]` as `Runtime::metadata()`, out of the metadata generated
+//! by `#[macros::call]` for the calls of each pallet, and by `#[macros::error]` for its errors.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::system::RuntimeVersion;
+
+/// The metadata of a runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeMetadata {
+	/// The version of the runtime, whose transaction version the calls are encoded for.
+	pub version: RuntimeVersion,
+	/// The pallets of the runtime, in the order they are declared. The system pallet isn't
+	/// included, since it has no calls.
+	pub pallets: Vec<PalletMetadata>,
+}
+
+impl RuntimeMetadata {
+	/// The pallet named `name`.
+	pub fn pallet(&self, name: &str) -> Option<&PalletMetadata> {
+		self.pallets.iter().find(|pallet| pallet.name == name)
+	}
+}
+
+// The metadata is formatted as a listing of the pallets with their index, followed by their calls
+// with their index and arguments, and by their errors with the first line of their documentation.
+impl fmt::Display for RuntimeMetadata {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let RuntimeVersion { spec_version, impl_version, transaction_version } = self.version;
+		writeln!(f, "spec version {spec_version}, impl version {impl_version}, transaction version {transaction_version}")?;
+		for pallet in &self.pallets {
+			writeln!(f, "\n{} #{}", pallet.name, pallet.index)?;
+			for call in &pallet.calls {
+				write!(f, "  {} {}(", call.index, call.name)?;
+				for (i, arg) in call.args.iter().enumerate() {
+					let separator = if i == 0 { "" } else { ", " };
+					write!(f, "{separator}{}: {}", arg.name, arg.ty)?;
+				}
+				writeln!(f, ")")?;
+			}
+			for error in &pallet.errors {
+				match error.docs.first() {
+					Some(doc) => writeln!(f, "  error {}: {doc}", error.name)?,
+					None => writeln!(f, "  error {}", error.name)?,
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// The metadata of a pallet of a runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PalletMetadata {
+	/// The name of the field of the pallet in the runtime, like `balances`.
+	pub name: &'static str,
+	/// The index of the pallet, which is the first byte of the encoding of its calls in a
+	/// `RuntimeCall`.
+	pub index: u8,
+	/// The functions which can be called, in the order they are declared.
+	pub calls: Vec<FunctionMetadata>,
+	/// The errors the pallet returns, in the order they are declared.
+	pub errors: Vec<ErrorMetadata>,
+}
+
+impl PalletMetadata {
+	/// The function named `name`.
+	pub fn call(&self, name: &str) -> Option<&FunctionMetadata> {
+		self.calls.iter().find(|call| call.name == name)
+	}
+}
+
+/// The metadata of a function of a pallet which can be called.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionMetadata {
+	/// The name of the function, like `transfer`.
+	pub name: &'static str,
+	/// The index of the function, which is the first byte of the encoding of its calls, after the
+	/// index of the pallet.
+	pub index: u8,
+	/// The arguments of the calls, which are encoded after the index of the function, in order.
+	pub args: Vec<ArgMetadata>,
+}
+
+/// The metadata of an argument of a call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgMetadata {
+	/// The name of the argument, like `amount`.
+	pub name: &'static str,
+	/// The type of the argument as written in the pallet, like `T::Balance`.
+	pub ty: &'static str,
+}
+
+/// The metadata of an error of a pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorMetadata {
+	/// The name of the error, which is the `error` of its `DispatchError::Module`.
+	pub name: &'static str,
+	/// The lines of the documentation of the error.
+	pub docs: Vec<&'static str>,
+}
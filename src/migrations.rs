@@ -0,0 +1,92 @@
+//! Migrations of the state of the pallets, for when a new version of the runtime changes how a
+//! pallet keeps its state, like splitting the balances of the accounts into free and reserved
+//! balances.
+//!
+//! The system pallet records the `system::RuntimeVersion` of the runtime which built the state.
+//! When a runtime executes a block on top of a state built by another spec version, it first runs
+//! the migration of each of its pallets declared with `#[migrations]`, see `#[macros::runtime]`.
+
+use alloc::vec::Vec;
+
+use crate::support::{DispatchError, Weight};
+
+/// The migration of the state of a pallet to the current version of the runtime.
+///
+/// Every function does nothing by default, so a pallet only implements what its migration needs.
+/// The checks run around every migration, so they must stay cheap.
+pub trait OnRuntimeUpgrade {
+	/// Check the state before the migration, and return what `post_upgrade` needs to check the
+	/// migrated state, like the total issuance of a currency.
+	fn pre_upgrade(&self) -> Result<Vec<u8>, DispatchError> {
+		Ok(Vec::new())
+	}
+
+	/// Migrate the state to the layout of the current version of the runtime, and return the
+	/// weight used.
+	fn on_runtime_upgrade(&mut self) -> Weight {
+		0
+	}
+
+	/// Check the migrated state, with the value returned by `pre_upgrade`.
+	fn post_upgrade(&self, _state: Vec<u8>) -> Result<(), DispatchError> {
+		Ok(())
+	}
+}
+
+/// Migrate `pallet`, checking its state before and after the migration. Return the weight of the
+/// migration, or the error of the first check which fails, in which case the migrated state must
+/// be discarded.
+pub fn migrate<P: OnRuntimeUpgrade>(pallet: &mut P) -> Result<Weight, DispatchError> {
+	let state = pallet.pre_upgrade()?;
+	let weight = pallet.on_runtime_upgrade();
+	pallet.post_upgrade(state)?;
+	Ok(weight)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{migrate, OnRuntimeUpgrade};
+	use crate::codec::{Decode, Encode};
+	use crate::support::{DispatchError, Weight};
+
+	// A pallet whose amounts were kept in cents, and are now kept in units, dropping the cents.
+	struct AmountsPallet {
+		amounts: Vec<u64>,
+		// Whether the migration loses an amount, which its checks must catch.
+		lossy: bool,
+	}
+
+	impl OnRuntimeUpgrade for AmountsPallet {
+		fn pre_upgrade(&self) -> Result<Vec<u8>, DispatchError> {
+			Ok((self.amounts.len() as u32).encode())
+		}
+
+		fn on_runtime_upgrade(&mut self) -> Weight {
+			for amount in &mut self.amounts {
+				*amount /= 100;
+			}
+			if self.lossy {
+				self.amounts.pop();
+			}
+			self.amounts.len() as Weight * 10
+		}
+
+		fn post_upgrade(&self, state: Vec<u8>) -> Result<(), DispatchError> {
+			let count = u32::decode_all(&state).map_err(|_| "invalid pre-upgrade state")?;
+			if count as usize != self.amounts.len() {
+				return Err("amounts were lost by the migration".into());
+			}
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn migrations_are_checked_before_and_after() {
+		let mut pallet = AmountsPallet { amounts: vec![1_250, 300], lossy: false };
+		assert_eq!(migrate(&mut pallet), Ok(20));
+		assert_eq!(pallet.amounts, vec![12, 3]);
+
+		let mut pallet = AmountsPallet { amounts: vec![1_250, 300], lossy: true };
+		assert_eq!(migrate(&mut pallet), Err("amounts were lost by the migration".into()));
+	}
+}
@@ -0,0 +1,427 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use num::traits::Saturating;
+use sha2::{Digest, Sha256};
+
+use crate::codec::Encode;
+use crate::storage::{storage_map_key, StorageBackend};
+use crate::support::{DispatchError, DispatchResult, Events, RawOrigin, StorageMap, StorageValue, Weight};
+use crate::system::Hash;
+
+pub trait Config: crate::balances::Config<AccountId: Encode> {
+	/// The calls which multisigs can make, which is usually the `RuntimeCall` generated by
+	/// `#[macros::runtime]`. They are approved by their hash, see `Pallet::call_hash`.
+	type RuntimeCall: Clone + Encode;
+	/// The deposit reserved from the signatory which opens an operation, until it is executed or
+	/// canceled.
+	const DEPOSIT_BASE: Self::Balance;
+	/// The deposit reserved on top of `DEPOSIT_BASE` for each approval the operation needs.
+	const DEPOSIT_FACTOR: Self::Balance;
+	/// The maximum number of signatories of a multisig.
+	const MAX_SIGNATORIES: u32;
+	/// The weight of `call`. The call of an operation is dispatched without an extrinsic, so its
+	/// weight is paid for with the approval which executes it.
+	fn call_weight(call: &Self::RuntimeCall) -> Weight;
+	/// The account of the multisig whose signatories and threshold hash to `hash`.
+	fn multi_account_id(hash: &Hash) -> Self::AccountId;
+}
+
+/// The hash of a call, which its approvals refer to.
+pub type CallHash = Hash;
+
+/// An operation of a multisig, waiting for enough approvals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multisig<AccountId, Balance> {
+	/// The signatory which opened the operation, and paid its deposit.
+	pub depositor: AccountId,
+	/// The deposit reserved from the depositor.
+	pub deposit: Balance,
+	/// The signatories which approved the operation, in order.
+	pub approvals: Vec<AccountId>,
+}
+
+/// The call of an operation which got enough approvals, to be dispatched by the runtime with the
+/// signed origin of the multisig.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation<AccountId, Call> {
+	/// The account of the multisig.
+	pub address: AccountId,
+	/// The hash of the call.
+	pub id: CallHash,
+	pub origin: RawOrigin<AccountId>,
+	pub call: Box<Call>,
+}
+
+/// The events emitted by the multisig pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId> {
+	/// A signatory opened an operation of a multisig, with their approval.
+	NewMultisig { approving: AccountId, multisig: AccountId, call_hash: CallHash },
+	/// A signatory approved an operation of a multisig.
+	MultisigApproval { approving: AccountId, multisig: AccountId, call_hash: CallHash },
+	/// The call of an operation was dispatched from its multisig, with its result.
+	MultisigExecuted { multisig: AccountId, call_hash: CallHash, result: DispatchResult },
+	/// An operation was canceled by its depositor.
+	MultisigCancelled { cancelling: AccountId, multisig: AccountId, call_hash: CallHash },
+}
+
+/// The errors returned by the multisig pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The threshold must be at least 2, since a single signatory can make the call themselves.
+	MinimumThreshold,
+	/// There are fewer signatories than the threshold.
+	TooFewSignatories,
+	/// There are more than `Config::MAX_SIGNATORIES` signatories.
+	TooManySignatories,
+	/// A signatory appears more than once, or the caller is one of the other signatories.
+	DuplicateSignatories,
+	/// The caller already approved the operation.
+	AlreadyApproved,
+	/// The multisig has no operation with this call hash.
+	NotFound,
+	/// Only the depositor of an operation can cancel it.
+	NotOwner,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::MinimumThreshold => "MinimumThreshold",
+			Error::TooFewSignatories => "TooFewSignatories",
+			Error::TooManySignatories => "TooManySignatories",
+			Error::DuplicateSignatories => "DuplicateSignatories",
+			Error::AlreadyApproved => "AlreadyApproved",
+			Error::NotFound => "NotFound",
+			Error::NotOwner => "NotOwner",
+		};
+		DispatchError::Module { pallet: "multisig", error }
+	}
+}
+
+/// The events of the multisig pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// This is the Multisig Module.
+/// It allows a group of signatories to make calls from an account derived from them and from a
+/// threshold, once enough of them approved the call.
+///
+/// The first approval of a call opens an operation, which reserves a deposit from its signatory.
+/// Approvals refer to the call by its hash, and the approval which reaches the threshold must give
+/// the call itself, which is then dispatched by the runtime right after the extrinsic, see
+/// `#[scheduler]` in `#[macros::runtime]`. The deposit is returned once the operation is executed
+/// or canceled.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The operations waiting for approvals, keyed by their multisig and the hash of their call.
+	#[allow(clippy::type_complexity)]
+	multisigs: StorageMap<(T::AccountId, CallHash), Multisig<T::AccountId, T::Balance>>,
+	/// The calls of the operations which got enough approvals, until the runtime dispatches them.
+	due: StorageValue<Vec<Operation<T::AccountId, T::RuntimeCall>>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Approve `call` as the `caller`, on behalf of the multisig of the `caller`, the
+	/// `other_signatories` and the `threshold`. If this is the first approval, an operation is
+	/// opened and its deposit is reserved from the caller. If the approvals reach the threshold,
+	/// the call is dispatched from the multisig.
+	/// This function will return an error if the signatories or the threshold are invalid, if the
+	/// caller can't pay the deposit, or if they already approved the call and this approval doesn't
+	/// execute it.
+	#[call_index = 0]
+	#[weight = 10_000u64.saturating_add(T::call_weight(call))]
+	pub fn as_multi(
+		&mut self,
+		caller: T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u32,
+		call: Box<T::RuntimeCall>,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		let call_hash = Self::call_hash(&call);
+		self.approve(caller, other_signatories, threshold, call_hash, Some(call), balances)
+	}
+
+	/// Approve the call whose hash is `call_hash` like `as_multi`, without giving the call. The
+	/// call is not dispatched, even if the approvals reach the threshold: a last approval must
+	/// give it with `as_multi`.
+	#[call_index = 1]
+	#[weight = 10_000]
+	pub fn approve_as_multi(
+		&mut self,
+		caller: T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u32,
+		call_hash: CallHash,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		self.approve(caller, other_signatories, threshold, call_hash, None, balances)
+	}
+
+	/// Cancel the operation of the call whose hash is `call_hash`, returning its deposit.
+	/// This function will return an error if there is no such operation, or if the caller is not
+	/// its depositor.
+	#[call_index = 2]
+	#[weight = 5_000]
+	pub fn cancel_as_multi(
+		&mut self,
+		caller: T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u32,
+		call_hash: CallHash,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		let multisig = Self::checked_multi_account_id(&caller, other_signatories, threshold)?;
+		let key = (multisig.clone(), call_hash);
+		let operation = self.multisigs.get(&key).ok_or(Error::NotFound)?;
+		if operation.depositor != caller {
+			return Err(Error::NotOwner.into());
+		}
+
+		balances.unreserve(&caller, operation.deposit);
+		self.multisigs.remove(&key);
+		self.deposit_event(Event::MultisigCancelled { cancelling: caller, multisig, call_hash });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Multisig Module.
+	pub fn new() -> Self {
+		Self { multisigs: StorageMap::new(), due: StorageValue::new(Vec::new()), events: Events::new() }
+	}
+
+	/// The hash of `call`, which approvals refer to: the SHA-256 hash of its SCALE encoding.
+	pub fn call_hash(call: &T::RuntimeCall) -> CallHash {
+		Sha256::digest(call.encode()).into()
+	}
+
+	/// The account of the multisig of `signatories` and `threshold`. The order of the signatories
+	/// doesn't matter.
+	pub fn multi_account_id(signatories: &[T::AccountId], threshold: u32) -> T::AccountId {
+		let mut signatories = signatories.to_vec();
+		signatories.sort();
+		let hash = Sha256::new()
+			.chain_update(b"multisig")
+			.chain_update((signatories, threshold).encode())
+			.finalize()
+			.into();
+		T::multi_account_id(&hash)
+	}
+
+	/// Get the operation (if any) of `multisig` for the call whose hash is `call_hash`.
+	pub fn multisig(
+		&self,
+		multisig: &T::AccountId,
+		call_hash: &CallHash,
+	) -> Option<&Multisig<T::AccountId, T::Balance>> {
+		self.multisigs.get(&(multisig.clone(), *call_hash))
+	}
+
+	/// Take the calls of the operations which got enough approvals, so the runtime can dispatch
+	/// them.
+	pub fn take_due(&mut self) -> Vec<Operation<T::AccountId, T::RuntimeCall>> {
+		core::mem::take(self.due.get_mut())
+	}
+
+	/// Record the `result` of the call whose hash is `call_hash`, which was dispatched by the
+	/// runtime from `multisig`.
+	pub fn note_dispatched(&mut self, multisig: T::AccountId, call_hash: CallHash, result: DispatchResult) {
+		self.deposit_event(Event::MultisigExecuted { multisig, call_hash, result });
+	}
+
+	/// Check the signatories and the threshold of a multisig, and return its account.
+	fn checked_multi_account_id(
+		caller: &T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u32,
+	) -> Result<T::AccountId, DispatchError> {
+		if threshold < 2 {
+			return Err(Error::MinimumThreshold.into());
+		}
+		let mut signatories = other_signatories;
+		signatories.push(caller.clone());
+		signatories.sort();
+		if signatories.windows(2).any(|pair| pair[0] == pair[1]) {
+			return Err(Error::DuplicateSignatories.into());
+		}
+		if signatories.len() > T::MAX_SIGNATORIES as usize {
+			return Err(Error::TooManySignatories.into());
+		}
+		if signatories.len() < threshold as usize {
+			return Err(Error::TooFewSignatories.into());
+		}
+		Ok(Self::multi_account_id(&signatories, threshold))
+	}
+
+	/// Approve the call whose hash is `call_hash` as the `caller`, and execute it if `call` is
+	/// given and the approvals reach the threshold.
+	fn approve(
+		&mut self,
+		caller: T::AccountId,
+		other_signatories: Vec<T::AccountId>,
+		threshold: u32,
+		call_hash: CallHash,
+		call: Option<Box<T::RuntimeCall>>,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		let multisig = Self::checked_multi_account_id(&caller, other_signatories, threshold)?;
+		let key = (multisig.clone(), call_hash);
+
+		let Some(operation) = self.multisigs.get_mut(&key) else {
+			// The threshold is at most `MAX_SIGNATORIES`, so this adds up a bounded number of factors.
+			let deposit = (0..threshold).fold(T::DEPOSIT_BASE, |deposit, _| deposit.saturating_add(T::DEPOSIT_FACTOR));
+			balances.reserve(&caller, deposit)?;
+			let operation = Multisig { depositor: caller.clone(), deposit, approvals: Vec::from([caller.clone()]) };
+			self.multisigs.insert(key, operation);
+			self.deposit_event(Event::NewMultisig { approving: caller, multisig, call_hash });
+			return Ok(());
+		};
+
+		let approved = operation.approvals.contains(&caller);
+		let approvals = operation.approvals.len() + usize::from(!approved);
+		match call {
+			Some(call) if approvals >= threshold as usize => {
+				let operation = self.multisigs.remove(&key).expect("the operation was just found");
+				balances.unreserve(&operation.depositor, operation.deposit);
+				let origin = RawOrigin::Signed(multisig.clone());
+				self.due.get_mut().push(Operation { address: multisig, id: call_hash, origin, call });
+				Ok(())
+			},
+			_ if approved => Err(Error::AlreadyApproved.into()),
+			_ => {
+				operation.approvals.push(caller.clone());
+				self.deposit_event(Event::MultisigApproval { approving: caller, multisig, call_hash });
+				Ok(())
+			},
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::Balance: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The calls which
+	/// are due are dispatched before the state is committed to, so they are left out with the phase
+	/// and the events.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for ((multisig, call_hash), operation) in &self.multisigs {
+			let value = (&operation.depositor, (operation.deposit, &operation.approvals)).encode();
+			state.insert(storage_map_key("multisig", "multisigs", &(multisig, call_hash)), value);
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, Event};
+	use crate::balances;
+	use crate::support::RawOrigin;
+
+	crate::construct_mock_runtime!(struct TestConfig: balances);
+
+	impl super::Config for TestConfig {
+		type RuntimeCall = &'static str;
+		const DEPOSIT_BASE: Self::Balance = 10;
+		const DEPOSIT_FACTOR: Self::Balance = 1;
+		const MAX_SIGNATORIES: u32 = 3;
+		fn call_weight(_call: &Self::RuntimeCall) -> crate::support::Weight {
+			1_000
+		}
+		fn multi_account_id(hash: &crate::system::Hash) -> Self::AccountId {
+			// Test accounts are static strings, so the multisigs of the tests are told apart by the
+			// first byte of their hash.
+			["multisig_a", "multisig_b"][usize::from(hash[0] % 2)]
+		}
+	}
+
+	type Multisig = super::Pallet<TestConfig>;
+
+	fn setup() -> (Multisig, balances::Pallet<TestConfig>) {
+		let mut balances = balances::Pallet::new();
+		for who in ["alice", "bob", "charlie"] {
+			balances.set_balance(who, 100);
+		}
+		(Multisig::new(), balances)
+	}
+
+	#[test]
+	fn call_is_due_once_the_threshold_is_reached() {
+		let (mut multisig, mut balances) = setup();
+		let call = || Box::new("transfer");
+		let call_hash = Multisig::call_hash(&call());
+		let account = Multisig::multi_account_id(&["alice", "bob", "charlie"], 2);
+		// The order of the signatories doesn't change the account.
+		assert_eq!(Multisig::multi_account_id(&["charlie", "alice", "bob"], 2), account);
+
+		for (others, threshold, error) in [
+			(vec!["bob", "charlie"], 1, Error::MinimumThreshold),
+			(vec!["bob"], 3, Error::TooFewSignatories),
+			(vec!["bob", "charlie", "dave"], 2, Error::TooManySignatories),
+			(vec!["alice", "bob"], 2, Error::DuplicateSignatories),
+		] {
+			assert_eq!(multisig.as_multi("alice", others, threshold, call(), &mut balances), Err(error.into()));
+		}
+
+		// The first approval only gives the hash, and reserves the deposit.
+		assert_eq!(
+			multisig.approve_as_multi("alice", vec!["charlie", "bob"], 2, call_hash, &mut balances),
+			Ok(())
+		);
+		assert_eq!(balances.reserved_balance(&"alice"), 12);
+		let approvals = multisig.multisig(&account, &call_hash).map(|operation| operation.approvals.clone());
+		assert_eq!(approvals, Some(vec!["alice"]));
+		assert!(multisig.take_due().is_empty());
+		assert_eq!(
+			multisig.approve_as_multi("alice", vec!["bob", "charlie"], 2, call_hash, &mut balances),
+			Err(Error::AlreadyApproved.into())
+		);
+
+		assert_eq!(multisig.as_multi("bob", vec!["alice", "charlie"], 2, call(), &mut balances), Ok(()));
+		assert_eq!(multisig.multisig(&account, &call_hash), None);
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		let due = multisig.take_due();
+		let due = due.iter().map(|operation| (operation.address, operation.id, &operation.origin, *operation.call));
+		assert_eq!(due.collect::<Vec<_>>(), [(account, call_hash, &RawOrigin::Signed(account), "transfer")]);
+
+		multisig.note_dispatched(account, call_hash, Ok(()));
+		assert_eq!(
+			multisig.take_events().last().map(|record| record.event.clone()),
+			Some(Event::MultisigExecuted { multisig: account, call_hash, result: Ok(()) })
+		);
+	}
+
+	#[test]
+	fn operation_can_be_cancelled_by_its_depositor() {
+		let (mut multisig, mut balances) = setup();
+		let call_hash = Multisig::call_hash(&"transfer");
+
+		assert_eq!(multisig.approve_as_multi("alice", vec!["bob"], 2, call_hash, &mut balances), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 12);
+		assert_eq!(
+			multisig.cancel_as_multi("bob", vec!["alice"], 2, call_hash, &mut balances),
+			Err(Error::NotOwner.into())
+		);
+		assert_eq!(
+			multisig.cancel_as_multi("alice", vec!["bob"], 2, [0; 32], &mut balances),
+			Err(Error::NotFound.into())
+		);
+		assert_eq!(multisig.cancel_as_multi("alice", vec!["bob"], 2, call_hash, &mut balances), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(multisig.approve_as_multi("bob", vec!["alice"], 2, call_hash, &mut balances), Ok(()));
+		assert_eq!(balances.reserved_balance(&"bob"), 12);
+	}
+}
@@ -0,0 +1,711 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use crate::block_builder::BlockBuilder;
+use crate::chain::Chain;
+use crate::codec::{self, Decode, Encode};
+use crate::consensus;
+use crate::finality::{FinalityError, FinalityGadget, SignedVote};
+use crate::import_queue::{ImportError, ImportQueue};
+use crate::keyring::Keystore;
+use crate::storage::StorageBackend;
+use crate::support::BlockExecutionReport;
+use crate::sync::{Body, ChainSync, SyncError, SyncProgress, SyncRequest, MAX_BODIES_PER_REQUEST, MAX_HEADERS_PER_REQUEST};
+use crate::tx_pool::TransactionPool;
+use crate::{block_inherents, system, types, RuntimeApi, BLOCK_TIME};
+
+/// The largest message accepted from a peer.
+const MAX_MESSAGE_LEN: usize = 16 << 20;
+/// How long `Network::run` waits between two polls of the peers.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The outcome of the import of each block tried, with its hash, see `ImportQueue::import`.
+pub type ImportOutcomes = Vec<(system::Hash, Result<BlockExecutionReport, ImportError>)>;
+
+/// A message between two nodes. On the wire, every message is prefixed with the length of its
+/// encoding, as a little endian `u32`.
+#[derive(Clone)]
+pub enum Message {
+	/// The best block of the sender, which is the first message sent on a connection.
+	Status { best_number: types::BlockNumber, best_hash: system::Hash },
+	/// A block the sender authored or imported.
+	Block(types::Block),
+	/// An extrinsic the sender added to its pool.
+	Extrinsic(types::SignedExtrinsic),
+	/// A request for at most `count` headers of the best chain of the receiver, from `from` on.
+	HeaderRequest { from: types::BlockNumber, count: u32 },
+	/// The headers requested, in order. There are fewer of them when the sender doesn't have them
+	/// all.
+	HeaderResponse(Vec<types::Header>),
+	/// A request for at most `count` bodies of the blocks of the best chain of the receiver, from
+	/// `from` on.
+	BodyRequest { from: types::BlockNumber, count: u32 },
+	/// The bodies requested, in order. There are fewer of them when the sender doesn't have them
+	/// all.
+	BodyResponse(Vec<Body>),
+	/// A vote of a voter of the finality gadget, which the sender cast or counted.
+	Vote(SignedVote),
+}
+
+impl Encode for Message {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Message::Status { best_number, best_hash } => {
+				dest.push(0);
+				best_number.encode_to(dest);
+				best_hash.encode_to(dest);
+			},
+			Message::Block(block) => {
+				dest.push(1);
+				block.encode_to(dest);
+			},
+			Message::Extrinsic(signed) => {
+				dest.push(2);
+				signed.encode_to(dest);
+			},
+			Message::HeaderRequest { from, count } => {
+				dest.push(3);
+				from.encode_to(dest);
+				count.encode_to(dest);
+			},
+			Message::HeaderResponse(headers) => {
+				dest.push(4);
+				headers.encode_to(dest);
+			},
+			Message::BodyRequest { from, count } => {
+				dest.push(5);
+				from.encode_to(dest);
+				count.encode_to(dest);
+			},
+			Message::BodyResponse(bodies) => {
+				dest.push(6);
+				bodies.encode_to(dest);
+			},
+			Message::Vote(signed) => {
+				dest.push(7);
+				signed.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl Decode for Message {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		match u8::decode(input)? {
+			0 => Ok(Message::Status { best_number: Decode::decode(input)?, best_hash: Decode::decode(input)? }),
+			1 => Ok(Message::Block(Decode::decode(input)?)),
+			2 => Ok(Message::Extrinsic(Decode::decode(input)?)),
+			3 => Ok(Message::HeaderRequest { from: Decode::decode(input)?, count: Decode::decode(input)? }),
+			4 => Ok(Message::HeaderResponse(Decode::decode(input)?)),
+			5 => Ok(Message::BodyRequest { from: Decode::decode(input)?, count: Decode::decode(input)? }),
+			6 => Ok(Message::BodyResponse(Decode::decode(input)?)),
+			7 => Ok(Message::Vote(Decode::decode(input)?)),
+			_ => Err(codec::Error::Invalid),
+		}
+	}
+}
+
+/// A node connected to this one.
+struct Peer {
+	stream: TcpStream,
+	address: SocketAddr,
+	/// The bytes received which don't make a whole message yet.
+	received: Vec<u8>,
+	/// The number of the best block the peer announced.
+	best_number: types::BlockNumber,
+	/// Whether a request of the sync sent to the peer is waiting for its response.
+	requesting: bool,
+	/// Set once the connection failed, or the peer misbehaved. The peer is dropped at the end of
+	/// the poll.
+	disconnected: bool,
+}
+
+impl Peer {
+	fn new(stream: TcpStream) -> io::Result<Self> {
+		stream.set_nodelay(true)?;
+		let address = stream.peer_addr()?;
+		Ok(Self { stream, address, received: Vec::new(), best_number: 0, requesting: false, disconnected: false })
+	}
+
+	fn send(&mut self, message: &Message) {
+		let encoded = message.encode();
+		let mut frame = (encoded.len() as u32).to_le_bytes().to_vec();
+		frame.extend(encoded);
+		if let Err(error) = self.stream.write_all(&frame) {
+			self.disconnect(error);
+		}
+	}
+
+	/// Read the messages the peer sent since the last call, without waiting for more.
+	fn receive(&mut self) -> Vec<Message> {
+		if let Err(error) = self.read_available() {
+			self.disconnect(error);
+		}
+		let mut messages = Vec::new();
+		while let Some(prefix) = self.received.first_chunk::<4>() {
+			let len = u32::from_le_bytes(*prefix) as usize;
+			if len > MAX_MESSAGE_LEN {
+				self.disconnect("message too large");
+				break;
+			}
+			if self.received.len() < 4 + len {
+				break;
+			}
+			let frame = self.received.drain(..4 + len).skip(4).collect::<Vec<_>>();
+			// Calls nested deeper than `codec::MAX_CALL_DEPTH` are refused like any invalid message,
+			// so a frame of nested calls can't overflow the stack of the node.
+			match Message::decode_all(&frame) {
+				Ok(message) => messages.push(message),
+				Err(_) => {
+					self.disconnect("invalid message");
+					break;
+				},
+			}
+		}
+		messages
+	}
+
+	/// Move the bytes waiting on the connection to `received`.
+	fn read_available(&mut self) -> io::Result<()> {
+		self.stream.set_nonblocking(true)?;
+		let mut buffer = [0; 4096];
+		let result = loop {
+			match self.stream.read(&mut buffer) {
+				Ok(0) => break Err(io::ErrorKind::UnexpectedEof.into()),
+				Ok(len) => self.received.extend_from_slice(&buffer[..len]),
+				Err(error) if error.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+				Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+				Err(error) => break Err(error),
+			}
+		};
+		self.stream.set_nonblocking(false)?;
+		result
+	}
+
+	fn disconnect(&mut self, reason: impl fmt::Display) {
+		if !self.disconnected {
+			eprintln!("Peer {} disconnected: {reason}", self.address);
+			self.disconnected = true;
+		}
+	}
+}
+
+/// The peer-to-peer layer of a node: it connects to other nodes over TCP, gossips the blocks and
+/// extrinsics of the node to them, and imports theirs.
+///
+/// When a peer announces a best block higher than the best block of the node, the missing blocks
+/// are downloaded from it by the `ChainSync`, headers first. Every block received goes through the
+/// `ImportQueue`, so blocks can arrive in any order, and is imported into the `Chain` like a block
+/// authored locally, on whichever fork it is. A peer which sends a block which fails to import, or
+/// an invalid response to the sync, is disconnected.
+///
+/// The votes of the authorities are gossiped too, and counted by the `FinalityGadget`, which
+/// finalizes the blocks of the chain: no block can be imported, nor can the best chain reorg, below
+/// the finalized block.
+///
+/// The node is polled, see `poll`, so it doesn't need threads: reading from the peers never waits.
+pub struct Network<B> {
+	chain: Chain<B>,
+	pool: TransactionPool,
+	/// The votes of the authorities, which finalize the blocks of the chain.
+	finality: FinalityGadget,
+	queue: ImportQueue,
+	sync: ChainSync,
+	peers: Vec<Peer>,
+}
+
+impl<B: StorageBackend> Network<B>
+where
+	B::Error: fmt::Debug,
+{
+	/// Run the node of `chain`, with the extrinsics waiting in `pool`.
+	pub fn new(chain: Chain<B>, pool: TransactionPool) -> Self {
+		let finality = FinalityGadget::authorities(chain.finalized_runtime());
+		Self { chain, pool, finality, queue: ImportQueue::new(), sync: ChainSync::new(), peers: Vec::new() }
+	}
+
+	/// How far the node caught up with its peers, while some of them have a higher best block.
+	pub fn sync_progress(&self) -> Option<SyncProgress> {
+		self.sync.progress(self.chain.best_block().0)
+	}
+
+	/// The number of peers connected.
+	pub fn peer_count(&self) -> usize {
+		self.peers.len()
+	}
+
+	/// Connect to the node listening at `address`.
+	pub fn connect(&mut self, address: impl ToSocketAddrs) -> io::Result<()> {
+		let stream = TcpStream::connect(address)?;
+		self.add_peer(stream)
+	}
+
+	/// Accept the connections waiting on `listener`, without waiting for more.
+	pub fn accept(&mut self, listener: &TcpListener) -> io::Result<()> {
+		listener.set_nonblocking(true)?;
+		loop {
+			match listener.accept() {
+				Ok((stream, _)) => {
+					stream.set_nonblocking(false)?;
+					self.add_peer(stream)?;
+				},
+				Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+				Err(error) => return Err(error),
+			}
+		}
+	}
+
+	fn add_peer(&mut self, stream: TcpStream) -> io::Result<()> {
+		let mut peer = Peer::new(stream)?;
+		let (best_number, best_hash) = self.chain.best_block();
+		peer.send(&Message::Status { best_number, best_hash });
+		// The extrinsics submitted to the node before the peer connected are gossiped to it too.
+		for signed in self.pool.pending() {
+			peer.send(&Message::Extrinsic(signed.clone()));
+		}
+		self.peers.push(peer);
+		Ok(())
+	}
+
+	/// Author the next block out of the pool, seal it with the key of the authority whose turn it
+	/// is, taken from `keystore`, import it, then gossip it to every peer.
+	pub fn author_block(&mut self, keystore: &Keystore) -> Result<(types::Block, BlockExecutionReport), String> {
+		let runtime = self.chain.runtime();
+		let mut block = BlockBuilder::new(runtime, runtime.system.max_block_weight())
+			.with_inherents(block_inherents(runtime))
+			.build_from_pool(&mut self.pool)
+			.map_err(|error| format!("cannot build the block: {error}"))?;
+		consensus::seal_block(runtime, &mut block, keystore).map_err(|error| format!("cannot seal the block: {error}"))?;
+		let report = self
+			.chain
+			.import_block(block.clone())
+			.map_err(|error| format!("cannot import the block: {error}"))?;
+		self.broadcast(&Message::Block(block.clone()), None);
+		Ok((block, report))
+	}
+
+	/// Handle the messages received from every peer since the last poll, and drop the peers which
+	/// disconnected. Returns the outcome of the import of the blocks received.
+	pub fn poll(&mut self) -> ImportOutcomes {
+		let mut outcomes = Vec::new();
+		for index in 0..self.peers.len() {
+			for message in self.peers[index].receive() {
+				outcomes.extend(self.handle(index, message));
+			}
+		}
+		if self.peers.iter().any(|peer| peer.disconnected && peer.requesting) {
+			self.sync.cancel();
+		}
+		self.peers.retain(|peer| !peer.disconnected);
+
+		// Catch up with the peers whose best block is higher, one request at a time.
+		let (best, best_hash) = self.chain.best_block();
+		if !self.peers.iter().any(|peer| peer.requesting) {
+			if let Some(peer) = self.peers.iter_mut().find(|peer| peer.best_number > best) {
+				self.sync.set_target(peer.best_number);
+				let request = match self.sync.next_request(best, best_hash) {
+					Some(SyncRequest::Headers { from, count }) => Message::HeaderRequest { from, count },
+					Some(SyncRequest::Bodies { from, count }) => Message::BodyRequest { from, count },
+					None => return outcomes,
+				};
+				peer.send(&request);
+				peer.requesting = true;
+			}
+		}
+		outcomes
+	}
+
+	/// Accept the peers connecting to `listener`, and poll them, forever. When `keystore` holds the
+	/// key of the authority whose turn it is, a block is authored once `BLOCK_TIME` elapsed since
+	/// the best block was authored or imported, and the voters in `keystore` vote for every new best
+	/// block.
+	pub fn run(&mut self, listener: TcpListener, keystore: Option<&Keystore>) -> io::Result<()> {
+		let mut last_block = Instant::now();
+		let mut peer_count = 0;
+		loop {
+			self.accept(&listener)?;
+			let best = self.chain.best_block();
+			for (hash, outcome) in self.poll() {
+				if let Err(error) = outcome {
+					println!("Block {} refused: {error}", crate::fmt_hash(&hash));
+				}
+			}
+			if self.peer_count() != peer_count {
+				peer_count = self.peer_count();
+				println!("Connected to {peer_count} peers");
+			}
+			if self.chain.best_block() != best {
+				match self.sync_progress() {
+					Some(progress) => println!("Syncing: {progress}"),
+					None => println!("Imported up to block #{}", self.chain.best_block().0),
+				}
+				last_block = Instant::now();
+			}
+
+			let runtime = self.chain.runtime();
+			let author = consensus::slot_author(runtime, runtime.block_number() + 1).ok();
+			let is_our_turn = keystore.zip(author).is_some_and(|(keystore, author)| keystore.contains(&author));
+			if is_our_turn && last_block.elapsed() >= Duration::from_millis(BLOCK_TIME) {
+				match self.author_block(keystore.expect("it is our turn")) {
+					Ok((block, report)) => println!(
+						"Authored block #{} {} with {} extrinsics",
+						block.header.block_number,
+						crate::fmt_hash(&block.header.hash()),
+						report.succeeded() + report.failed()
+					),
+					Err(error) => println!("{error}"),
+				}
+				last_block = Instant::now();
+			}
+			if let Some(keystore) = keystore {
+				match self.vote(keystore) {
+					Ok(Some(hash)) => println!("Finalized block {}", crate::fmt_hash(&hash)),
+					Ok(None) => {},
+					Err(error) => println!("Cannot vote: {error}"),
+				}
+			}
+			std::thread::sleep(POLL_INTERVAL);
+		}
+	}
+
+	fn handle(&mut self, from: usize, message: Message) -> ImportOutcomes {
+		match message {
+			Message::Status { best_number, .. } => {
+				self.peers[from].best_number = best_number;
+				Vec::new()
+			},
+			Message::Block(block) => {
+				let peer = &mut self.peers[from];
+				peer.best_number = peer.best_number.max(block.header.block_number);
+				// A block gossiped back by another peer was imported already.
+				if self.chain.is_known(&block.header.hash()) {
+					return Vec::new();
+				}
+				self.queue.push(block);
+				self.import_queued(from)
+			},
+			Message::Extrinsic(signed) => {
+				// An extrinsic already in the pool is refused, so it isn't gossiped again.
+				if self.pool.submit_extrinsic(self.chain.runtime(), signed.clone()).is_ok() {
+					self.broadcast(&Message::Extrinsic(signed), Some(from));
+				}
+				Vec::new()
+			},
+			Message::HeaderRequest { from: first, count } => {
+				let headers = self.stored_blocks(first, count.min(MAX_HEADERS_PER_REQUEST)).map(|block| block.header).collect();
+				self.peers[from].send(&Message::HeaderResponse(headers));
+				Vec::new()
+			},
+			Message::BodyRequest { from: first, count } => {
+				let bodies = self
+					.stored_blocks(first, count.min(MAX_BODIES_PER_REQUEST))
+					.map(|block| (block.inherents, block.extrinsics))
+					.collect();
+				self.peers[from].send(&Message::BodyResponse(bodies));
+				Vec::new()
+			},
+			Message::HeaderResponse(headers) => {
+				self.peers[from].requesting = false;
+				let result = self.sync.on_headers(headers);
+				self.sync_failed(from, result.err());
+				Vec::new()
+			},
+			Message::BodyResponse(bodies) => {
+				self.peers[from].requesting = false;
+				match self.sync.on_bodies(bodies) {
+					Ok(blocks) => {
+						for block in blocks {
+							self.queue.push(block);
+						}
+						self.import_queued(from)
+					},
+					Err(error) => {
+						self.sync_failed(from, Some(error));
+						Vec::new()
+					},
+				}
+			},
+			Message::Vote(signed) => {
+				self.import_vote(from, signed);
+				Vec::new()
+			},
+		}
+	}
+
+	/// The blocks of the best chain from `first` on, at most `count` of them.
+	fn stored_blocks(&self, first: types::BlockNumber, count: u32) -> impl Iterator<Item = types::Block> + '_ {
+		(first..first.saturating_add(count)).map_while(|block_number| self.chain.canonical_block(block_number))
+	}
+
+	/// Drop what the sync downloaded from `from` after it sent an invalid response. A peer which
+	/// doesn't have the blocks it announced isn't synced from any more, and any other error
+	/// disconnects it.
+	fn sync_failed(&mut self, from: usize, error: Option<SyncError>) {
+		let Some(error) = error else {
+			return;
+		};
+		let best = self.chain.best_block().0;
+		self.sync.reset(best);
+		match error {
+			SyncError::Empty => self.peers[from].best_number = best,
+			error => self.peers[from].disconnect(error),
+		}
+	}
+
+	/// Import the blocks of the queue whose parent is known, after `from` sent a block, then gossip
+	/// the blocks imported to the other peers.
+	fn import_queued(&mut self, from: usize) -> ImportOutcomes {
+		let best = self.chain.best_block();
+		let outcomes = self.queue.import(&mut self.chain);
+		if outcomes.iter().any(|(_, outcome)| outcome.is_err()) {
+			self.peers[from].disconnect("sent a block which failed to import");
+		}
+		for (hash, _) in outcomes.iter().filter(|(_, outcome)| outcome.is_ok()) {
+			let block = self.chain.block(hash).expect("the block was imported").clone();
+			self.broadcast(&Message::Block(block), Some(from));
+		}
+		self.revalidate_pool(best.1);
+		outcomes
+	}
+
+	/// Check the extrinsics of the pool again when the best block is no longer `previous_best`.
+	/// The extrinsics of the blocks rolled back by a reorg are checked too, on top of the new best
+	/// block, which drops the ones the new best chain included.
+	fn revalidate_pool(&mut self, previous_best: system::Hash) {
+		let best = self.chain.best_block().1;
+		if best == previous_best {
+			return;
+		}
+		// Finalizing a fork prunes the other ones, whose extrinsics are only checked again when the
+		// best block was kept.
+		let retracted = match self.chain.tree_route(previous_best, best) {
+			Some(route) => route
+				.retracted
+				.iter()
+				.rev()
+				.flat_map(|hash| self.chain.block(hash).expect("retracted blocks are kept").extrinsics.clone())
+				.collect(),
+			None => Vec::new(),
+		};
+		for signed in retracted.into_iter().chain(self.pool.drain()) {
+			let _ = self.pool.submit_extrinsic(self.chain.runtime(), signed);
+		}
+	}
+
+	/// Vote for the best block with the keys of the voters in `keystore`, then gossip the votes to
+	/// every peer.
+	pub fn vote(&mut self, keystore: &Keystore) -> Result<Option<system::Hash>, FinalityError> {
+		let best = self.chain.best_block().1;
+		let (votes, finalized) = self.finality.vote_for_best_block(&mut self.chain, keystore)?;
+		for signed in votes {
+			self.broadcast(&Message::Vote(signed), None);
+		}
+		self.revalidate_pool(best);
+		Ok(finalized)
+	}
+
+	/// Count the vote `from` sent, then gossip it to the other peers. A peer sending a vote which
+	/// isn't cast by a voter is disconnected, while a vote for a block which isn't known, or which
+	/// can no longer be finalized, is only dropped.
+	fn import_vote(&mut self, from: usize, signed: SignedVote) {
+		// A vote gossiped back by another peer was counted already.
+		if self.finality.vote_of(&signed.voter) == Some(&signed.vote) {
+			return;
+		}
+		let best = self.chain.best_block().1;
+		match self.finality.import_vote(&mut self.chain, signed.clone()) {
+			Ok(_) => self.broadcast(&Message::Vote(signed), Some(from)),
+			Err(error @ (FinalityError::NotAVoter(_) | FinalityError::BadSignature)) => self.peers[from].disconnect(error),
+			Err(_) => {},
+		}
+		self.revalidate_pool(best);
+	}
+
+	/// Send `message` to every peer, but the peer at index `except`.
+	fn broadcast(&mut self, message: &Message, except: Option<usize>) {
+		for (index, peer) in self.peers.iter_mut().enumerate() {
+			if Some(index) != except {
+				peer.send(message);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Message, Network};
+	use crate::chain::Chain;
+	use crate::chain_store::ChainStore;
+	use crate::codec::{Decode, Encode};
+	use crate::finality::{SignedVote, Vote};
+	use crate::storage::InMemoryBackend;
+	use crate::tx_pool::TransactionPool;
+	use crate::{balances, dev_account, development_genesis, types, Runtime, RuntimeApi, RuntimeCall, DEV_KEYSTORE};
+	use std::io::Write;
+	use std::net::{TcpListener, TcpStream};
+
+	fn new_network() -> Network<InMemoryBackend> {
+		let mut runtime = Runtime::new();
+		development_genesis().build(&mut runtime).expect("invalid genesis");
+		Network::new(Chain::new(runtime, ChainStore::new(InMemoryBackend::new())).unwrap(), TransactionPool::new())
+	}
+
+	fn transfer(network: &Network<InMemoryBackend>, to: &str, amount: types::Balance) -> types::SignedExtrinsic {
+		let call = RuntimeCall::balances(balances::Call::transfer { to: dev_account(to), amount });
+		let extrinsic = types::ExtrinsicBuilder::new(dev_account("alice")).call(call).build();
+		let alice = dev_account("alice");
+		let nonce = network.chain.runtime().nonce_of(&alice) + network.pool.pending_of(&alice) as types::Nonce;
+		network.chain.runtime().sign(extrinsic, nonce)
+	}
+
+	// Poll every network until `done` holds, or panic after a while.
+	fn poll_until(networks: &mut [&mut Network<InMemoryBackend>], done: impl Fn(&[&mut Network<InMemoryBackend>]) -> bool) {
+		for _ in 0..500 {
+			for network in networks.iter_mut() {
+				assert!(network.poll().iter().all(|(_, outcome)| outcome.is_ok()));
+			}
+			if done(networks) {
+				return;
+			}
+			std::thread::sleep(std::time::Duration::from_millis(5));
+		}
+		panic!("the networks didn't converge");
+	}
+
+	#[test]
+	fn messages_roundtrip_through_the_codec() {
+		let network = new_network();
+		let status = Message::Status { best_number: 3, best_hash: [7; 32] };
+		let extrinsic = Message::Extrinsic(transfer(&network, "bob", 10));
+		let request = Message::HeaderRequest { from: 1, count: 2 };
+		let vote = Vote { target_hash: [7; 32], target_number: 3 };
+		let vote = Message::Vote(DEV_KEYSTORE.with(|keystore| SignedVote::new(vote, dev_account("alice"), keystore)).unwrap());
+		for message in [status, extrinsic, request, Message::HeaderResponse(vec![]), Message::BodyResponse(vec![(vec![], vec![])]), vote] {
+			let bytes = message.encode();
+			assert_eq!(Message::decode_all(&bytes).map(|decoded| decoded.encode()), Ok(bytes));
+		}
+		assert!(Message::decode_all(&[8]).is_err());
+	}
+
+	#[test]
+	fn nodes_sync_and_gossip_blocks_and_extrinsics() {
+		let (mut alice, mut bob, mut charlie) = (new_network(), new_network(), new_network());
+		let signed = transfer(&charlie, "charlie", 20);
+		charlie.pool.submit_extrinsic(charlie.chain.runtime(), signed).unwrap();
+		for _ in 0..2 {
+			DEV_KEYSTORE.with(|keystore| alice.author_block(keystore)).unwrap();
+		}
+
+		// Bob connects to Alice, and syncs the blocks he missed, headers first.
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		bob.connect(listener.local_addr().unwrap()).unwrap();
+		alice.accept(&listener).unwrap();
+		poll_until(&mut [&mut alice, &mut bob], |networks| networks[1].chain.runtime().block_number() == 2);
+		assert_eq!(bob.chain.runtime().system.best_block_hash(), alice.chain.runtime().system.best_block_hash());
+
+		// Charlie connects to Bob. The extrinsic waiting in the pool of Charlie is sent to Bob, and
+		// reaches Alice through Bob.
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		charlie.connect(listener.local_addr().unwrap()).unwrap();
+		bob.accept(&listener).unwrap();
+		poll_until(&mut [&mut alice, &mut bob, &mut charlie], |networks| {
+			networks[2].chain.runtime().block_number() == 2 && networks[0].pool.len() == 1
+		});
+
+		// The block Alice authors with it reaches Charlie through Bob, and empties every pool.
+		DEV_KEYSTORE.with(|keystore| alice.author_block(keystore)).unwrap();
+		poll_until(&mut [&mut alice, &mut bob, &mut charlie], |networks| networks[2].chain.runtime().block_number() == 3);
+		assert_eq!(charlie.chain.runtime().balance_of(&dev_account("charlie")), 20);
+		assert!(alice.pool.is_empty() && bob.pool.is_empty() && charlie.pool.is_empty());
+		assert_eq!((alice.peer_count(), bob.peer_count(), charlie.peer_count()), (1, 2, 1));
+	}
+
+	#[test]
+	fn nodes_switch_to_a_longer_fork_gossiped_to_them() {
+		let (mut alice, mut bob) = (new_network(), new_network());
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		bob.connect(listener.local_addr().unwrap()).unwrap();
+		alice.accept(&listener).unwrap();
+
+		// Alice and Bob author blocks at the same heights before they hear of each other's blocks.
+		let signed = transfer(&alice, "bob", 10);
+		alice.pool.submit_extrinsic(alice.chain.runtime(), signed).unwrap();
+		let (a1, _) = DEV_KEYSTORE.with(|keystore| alice.author_block(keystore)).unwrap();
+		for _ in 0..2 {
+			DEV_KEYSTORE.with(|keystore| bob.author_block(keystore)).unwrap();
+		}
+		let bob_best = bob.chain.best_block();
+
+		// Bob's fork is longer, so Alice rolls back her block and executes Bob's blocks instead. Her
+		// transfer goes back to her pool, while Bob only adds her block to his chain as a fork.
+		poll_until(&mut [&mut alice, &mut bob], |networks| networks[0].chain.best_block() == bob_best);
+		assert!(alice.chain.is_known(&bob_best.1) && bob.chain.is_known(&a1.header.hash()));
+		assert_eq!(bob.chain.best_block(), bob_best);
+		assert_eq!(alice.chain.runtime().balance_of(&dev_account("bob")), 0);
+		assert_eq!(alice.pool.len(), 1);
+	}
+
+	#[test]
+	fn votes_are_gossiped_and_finalize_blocks_on_every_node() {
+		let (mut alice, mut bob) = (new_network(), new_network());
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		bob.connect(listener.local_addr().unwrap()).unwrap();
+		alice.accept(&listener).unwrap();
+		let (block, _) = DEV_KEYSTORE.with(|keystore| alice.author_block(keystore)).unwrap();
+		poll_until(&mut [&mut alice, &mut bob], |networks| networks[1].chain.runtime().block_number() == 1);
+
+		// Alice holds the keys of every authority, so her votes finalize the block on both nodes.
+		let finalized = DEV_KEYSTORE.with(|keystore| alice.vote(keystore)).unwrap();
+		assert_eq!(finalized, Some(block.header.hash()));
+		poll_until(&mut [&mut alice, &mut bob], |networks| networks[1].chain.finalized_head().0 == 1);
+		assert_eq!(bob.chain.finalized_head(), alice.chain.finalized_head());
+		// Nothing is left to vote for.
+		assert_eq!(DEV_KEYSTORE.with(|keystore| alice.vote(keystore)), Ok(None));
+	}
+
+	#[test]
+	fn peers_sending_invalid_messages_are_dropped() {
+		let mut network = new_network();
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+		network.accept(&listener).unwrap();
+		assert_eq!(network.peer_count(), 1);
+
+		stream.write_all(&[1, 0, 0, 0, 9]).unwrap();
+		for _ in 0..100 {
+			network.poll();
+			if network.peer_count() == 0 {
+				return;
+			}
+			std::thread::sleep(std::time::Duration::from_millis(5));
+		}
+		panic!("the peer wasn't dropped");
+	}
+
+	#[test]
+	fn peers_sending_deeply_nested_calls_are_dropped() {
+		let mut network = new_network();
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+		network.accept(&listener).unwrap();
+
+		// The call of an extrinsic follows its caller and the transaction version. Wrapping it in
+		// 200_000 sudo calls makes a frame of 400 KB, well below the largest message accepted.
+		let mut message = Message::Extrinsic(transfer(&network, "bob", 10)).encode();
+		message.splice(1 + 32 + 4..1 + 32 + 4, [14, 0].repeat(200_000));
+		let mut frame = (message.len() as u32).to_le_bytes().to_vec();
+		frame.extend(message);
+		stream.write_all(&frame).unwrap();
+		for _ in 0..100 {
+			network.poll();
+			if network.peer_count() == 0 {
+				assert!(network.pool.is_empty());
+				return;
+			}
+			std::thread::sleep(std::time::Duration::from_millis(5));
+		}
+		panic!("the peer wasn't dropped");
+	}
+}
@@ -0,0 +1,73 @@
+//! Smoke tests which are only compiled with `--no-default-features`, to check that the pallets
+//! still work without `std`. Like the pallets, they may only use `core` and `alloc`.
+
+use crate::support::{Dispatch, DispatchWithContext, HooksWithContext, RawOrigin::{Root, Signed}};
+use crate::{balances, proof_of_existence, system, treasury};
+
+struct TestConfig;
+
+impl system::Config for TestConfig {
+	type AccountId = &'static str;
+	type BlockNumber = u32;
+	type Nonce = u32;
+	type RuntimeEvent = ();
+	type Hashing = crate::hashing::Sha256;
+}
+
+impl balances::Config for TestConfig {
+	type Balance = u128;
+	const MINIMUM_TRANSFER: Self::Balance = 1;
+	const MAX_TRANSFERS_PER_BLOCK: u32 = u32::MAX;
+	const EXISTENTIAL_DEPOSIT: Self::Balance = 0;
+}
+
+impl proof_of_existence::Config for TestConfig {
+	type Currency = balances::Pallet<Self>;
+	const CLAIM_FEE: Self::Balance = 5;
+	const CLAIM_DEPOSIT: Self::Balance = 1;
+	const MAX_METADATA_LENGTH: usize = 32;
+	const MAX_CLAIM_OWNERS: u32 = 4;
+	fn claim_fee_destination() -> Self::AccountId {
+		"treasury"
+	}
+}
+
+impl treasury::Config for TestConfig {
+	fn account_id() -> Self::AccountId {
+		"treasury"
+	}
+	const PROPOSAL_BOND: Self::Balance = 1;
+	const SPEND_PERIOD: Self::BlockNumber = 1;
+}
+
+#[test]
+fn pallets_work_without_std() {
+	let mut system = system::Pallet::<TestConfig>::new();
+	let mut balances = balances::Pallet::<TestConfig>::new();
+	let mut poe = proof_of_existence::Pallet::<TestConfig>::new();
+	let mut treasury = treasury::Pallet::<TestConfig>::new();
+
+	assert_eq!(system.inc_block_number(), Ok(()));
+	system.inc_nonce(&"alice");
+	assert_eq!(system.block_number(), 1);
+	assert_eq!(system.get_nonce(&"alice"), 1);
+
+	balances.set_balance("alice", 100);
+	let call = balances::Call::transfer { to: "bob", amount: 30 };
+	assert!(balances.dispatch(Signed("alice"), call).is_ok());
+	assert_eq!(balances.balance(&"bob"), 30);
+
+	assert_eq!(poe.create_claim("alice", b"my_document".to_vec(), &mut balances), Ok(()));
+	let claim = proof_of_existence::Pallet::<TestConfig>::claim_of(b"my_document");
+	assert_eq!(poe.claim_history(&claim), &["alice"]);
+	assert_eq!(balances.balance(&"alice"), 64);
+	assert_eq!(balances.reserved_balance(&"alice"), 1);
+
+	let propose_spend = treasury::Call::propose_spend { beneficiary: "bob", amount: 5 };
+	let output = DispatchWithContext::dispatch(&mut treasury, &mut balances, Signed("bob"), propose_spend);
+	assert_eq!(output.unwrap().unwrap().downcast_ref::<u32>(), Some(&0));
+	let approve_proposal = treasury::Call::approve_proposal { proposal_id: 0 };
+	assert!(DispatchWithContext::dispatch(&mut treasury, &mut balances, Root, approve_proposal).is_ok());
+	treasury.on_finalize(&mut balances, 1);
+	assert_eq!(treasury.pot(&balances), 0);
+}
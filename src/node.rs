@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use crate::block_builder::BlockBuilder;
+use crate::chain::Chain;
+use crate::chain_store::ChainStore;
+use crate::codec::{Decode, Encode};
+use crate::consensus;
+use crate::finality::FinalityGadget;
+use crate::storage::{FileBackend, StorageBackend};
+use crate::support::BlockExecutionReport;
+use crate::tx_pool::TransactionPool;
+use crate::{block_inherents, types, Runtime, RuntimeApi, RuntimeCall, RuntimeGenesisConfig, DEV_KEYSTORE};
+
+/// The key of the extrinsics waiting in the pool, in the pool file.
+const POOL_KEY: &[u8] = b"pool";
+
+/// A node of the chain whose state is kept in a directory, so it is found again by the next
+/// invocation of the node.
+///
+/// The blocks of the best chain are kept in `chain.db`, with a `ChainStore`, and the state of the
+/// runtime is rebuilt from them when the node is opened, see `Chain`. The extrinsics waiting in
+/// the transaction pool are kept in `pool.db`.
+///
+/// The node holds the development keys of every authority, so every block it produces is voted for
+/// by all of them, which finalizes it at once, see `finality`.
+pub struct Node {
+	pub chain: Chain<FileBackend>,
+	pub pool: TransactionPool,
+	finality: FinalityGadget,
+	/// The file of the pool, which is written every time the pool changes.
+	pool_backend: FileBackend,
+}
+
+impl Node {
+	/// Open the node kept in `base_path`, which is created if needed, for the chain starting from
+	/// `genesis`.
+	///
+	/// The extrinsics kept in the pool are submitted again on top of the state of the chain, and
+	/// the ones which are no longer valid are dropped.
+	pub fn open(base_path: &Path, genesis: &RuntimeGenesisConfig) -> Result<Self, String> {
+		std::fs::create_dir_all(base_path).map_err(|error| format!("{}: {error}", base_path.display()))?;
+		let open = |name: &str| {
+			let path = base_path.join(name);
+			FileBackend::open(&path).map_err(|error| format!("{}: {error:?}", path.display()))
+		};
+		let mut runtime = Runtime::new();
+		genesis.build(&mut runtime).map_err(|error| format!("invalid genesis: {error}"))?;
+		let store = ChainStore::new(open("chain.db")?);
+		let chain = Chain::new(runtime, store).map_err(|error| format!("cannot resume the chain: {error}"))?;
+		let pool_backend = open("pool.db")?;
+
+		let mut pool = TransactionPool::new();
+		let pending = match pool_backend.get(POOL_KEY) {
+			Some(bytes) => Vec::<types::SignedExtrinsic>::decode_all(&bytes).map_err(|_| "corrupt pool file".to_string())?,
+			None => Vec::new(),
+		};
+		for signed in pending {
+			let _ = pool.submit_extrinsic(chain.runtime(), signed);
+		}
+		let finality = FinalityGadget::authorities(chain.finalized_runtime());
+		Ok(Self { chain, pool, finality, pool_backend })
+	}
+
+	/// Sign `call` with the development key of `caller`, and submit it to the pool. Its nonce
+	/// follows the extrinsics of the caller already waiting in the pool.
+	pub fn submit(&mut self, caller: types::AccountId, call: RuntimeCall) -> Result<(), String> {
+		let runtime = self.chain.runtime();
+		let nonce = runtime.nonce_of(&caller) + self.pool.pending_of(&caller) as types::Nonce;
+		let extrinsic = types::ExtrinsicBuilder::new(caller).call(call).build();
+		self.pool
+			.submit_extrinsic(runtime, runtime.sign(extrinsic, nonce))
+			.map_err(|error| format!("extrinsic refused: {error}"))?;
+		self.save_pool()
+	}
+
+	/// Author the next block out of the pool, seal it with the development key of the authority
+	/// whose turn it is, import it, then finalize it with the votes of the authorities. The
+	/// extrinsics which don't fit in the block are left in the pool.
+	pub fn produce_block(&mut self) -> Result<(types::Block, BlockExecutionReport), String> {
+		let runtime = self.chain.runtime();
+		let mut block = BlockBuilder::new(runtime, runtime.system.max_block_weight())
+			.with_inherents(block_inherents(runtime))
+			.build_from_pool(&mut self.pool)
+			.map_err(|error| format!("cannot build the block: {error}"))?;
+		DEV_KEYSTORE
+			.with(|keystore| consensus::seal_block(runtime, &mut block, keystore))
+			.map_err(|error| format!("cannot seal the block: {error}"))?;
+		let report = self
+			.chain
+			.import_block(block.clone())
+			.map_err(|error| format!("cannot import the block: {error}"))?;
+		DEV_KEYSTORE
+			.with(|keystore| self.finality.vote_for_best_block(&mut self.chain, keystore))
+			.map_err(|error| format!("cannot finalize the block: {error}"))?;
+		self.save_pool()?;
+		Ok((block, report))
+	}
+
+	/// Write the extrinsics waiting in the pool to the pool file, which is left empty once the pool
+	/// is.
+	fn save_pool(&mut self) -> Result<(), String> {
+		if self.pool.is_empty() {
+			self.pool_backend.remove(POOL_KEY);
+		} else {
+			let pending = self.pool.pending().cloned().collect::<Vec<_>>();
+			self.pool_backend.insert(POOL_KEY.to_vec(), pending.encode());
+		}
+		self.pool_backend.commit().map_err(|error| format!("cannot store the pool: {error:?}"))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Node, POOL_KEY};
+	use crate::storage::StorageBackend;
+	use crate::{balances, dev_account, development_genesis, RuntimeApi, RuntimeCall};
+
+	#[test]
+	fn node_keeps_its_pool_and_blocks_across_invocations() {
+		let base_path = std::env::temp_dir().join(format!("pallets-node-{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&base_path);
+		let transfer = |to: &str, amount| RuntimeCall::balances(balances::Call::transfer { to: dev_account(to), amount });
+
+		let mut node = Node::open(&base_path, &development_genesis()).unwrap();
+		node.submit(dev_account("alice"), transfer("bob", 30)).unwrap();
+		drop(node);
+
+		// The next invocation finds the transfer in the pool, and numbers the next one after it.
+		let mut node = Node::open(&base_path, &development_genesis()).unwrap();
+		assert_eq!(node.pool.len(), 1);
+		node.submit(dev_account("alice"), transfer("charlie", 20)).unwrap();
+		drop(node);
+
+		let mut node = Node::open(&base_path, &development_genesis()).unwrap();
+		let (block, report) = node.produce_block().unwrap();
+		assert_eq!(block.header.block_number, 1);
+		assert_eq!((report.succeeded(), report.failed()), (2, 0));
+		assert!(node.pool.is_empty() && node.pool_backend.get(POOL_KEY).is_none());
+		drop(node);
+
+		// The block was finalized when it was produced, and stays finalized.
+		let node = Node::open(&base_path, &development_genesis()).unwrap();
+		assert_eq!(node.chain.finalized_head(), (1, block.header.hash()));
+		let runtime = node.chain.runtime();
+		assert_eq!(runtime.block_number(), 1);
+		assert_eq!((runtime.balance_of(&dev_account("bob")), runtime.balance_of(&dev_account("charlie"))), (30, 20));
+		assert_eq!(runtime.nonce_of(&dev_account("alice")), 2);
+		assert!(node.pool.is_empty());
+		std::fs::remove_dir_all(&base_path).unwrap();
+	}
+}
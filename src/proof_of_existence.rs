@@ -1,58 +1,403 @@
-use core::fmt::Debug;
-use std::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
 
-use crate::support::DispatchResult;
+use num::traits::{CheckedAdd, Zero};
 
-pub trait Config: crate::system::Config {
-	/// The type which represents the content that can be claimed using this pallet.
-	/// Could be the content directly as bytes, or better yet the hash of that content.
-	/// We leave that decision to the runtime developer.
-	type Content: Debug + Ord;
+use crate::codec::Encode;
+use crate::hashing::Hasher;
+use crate::storage::{storage_map_key, StorageBackend};
+use crate::support::{Currency, DispatchError, DispatchResult, Events, HooksWithContext, StorageMap, StorageSet};
+use crate::system::Hash;
+use crate::weights::proof_of_existence as weights;
+
+pub trait Config: crate::balances::Config {
+	/// The currency in which claim fees and deposits are paid, which is usually the balances pallet.
+	type Currency: Currency<Self::AccountId, Balance = Self::Balance>;
+	/// The fee paid by the caller to create a claim, to discourage claim spam.
+	const CLAIM_FEE: Self::Balance;
+	/// The deposit reserved from the owner of a claim for as long as they own it, so the claims
+	/// stored on chain are paid for. It is returned when the claim is revoked.
+	const CLAIM_DEPOSIT: Self::Balance;
+	/// The maximum length in bytes of the metadata of a claim.
+	const MAX_METADATA_LENGTH: usize;
+	/// The maximum number of owners of a shared claim.
+	const MAX_CLAIM_OWNERS: u32;
+	/// The account receiving the claim fees, like a treasury or an unusable burn address.
+	fn claim_fee_destination() -> Self::AccountId;
+}
+
+/// A claim on some content, with the block it was created in and metadata set by its owner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClaimDetails<AccountId, BlockNumber> {
+	/// The current owner of the claim.
+	pub owner: AccountId,
+	/// The block in which the claim was created, which is when the content was proven to exist.
+	pub block_number: BlockNumber,
+	/// Free-form data about the content, like its name or media type. It is empty until the owner
+	/// sets it.
+	pub metadata: Vec<u8>,
+	/// The block at the end of which the claim expires, if it was created with an expiry.
+	pub expires_at: Option<BlockNumber>,
+}
+
+/// The owners of a shared claim, and how many of them must approve its revocation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedClaim<AccountId> {
+	/// The owners, in order. The account which created the claim is one of them, and keeps the
+	/// deposit reserved.
+	pub owners: Vec<AccountId>,
+	/// The number of owners who must call `approve_revoke` for the claim to be revoked.
+	pub threshold: u32,
+}
+
+/// The events emitted by the proof of existence pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, Content> {
+	/// A claim was created by its owner.
+	ClaimCreated { claim: Content, owner: AccountId },
+	/// A claim was revoked by its owner.
+	ClaimRevoked { claim: Content, owner: AccountId },
+	/// A claim was transferred from its owner to a new owner.
+	ClaimTransferred { claim: Content, from: AccountId, to: AccountId },
+	/// A claim was renounced by its owner, so it stays claimed but nobody owns it anymore.
+	ClaimRenounced { claim: Content, owner: AccountId },
+	/// The owner of a claim set its metadata.
+	ClaimMetadataSet { claim: Content },
+	/// A claim shared by several owners was created.
+	SharedClaimCreated { claim: Content, owners: Vec<AccountId>, threshold: u32 },
+	/// An owner of a shared claim approved its revocation, which now has `approvals` approvals.
+	RevokeApproved { claim: Content, owner: AccountId, approvals: u32 },
+	/// A claim expired, so its deposit was returned to its owner and the content can be claimed
+	/// again.
+	ClaimExpired { claim: Content, owner: AccountId },
+	/// A claim was queried through `query_claim`, with its owner at the time of the query.
+	ClaimQueried { claim: Content, owner: Option<AccountId> },
+}
+
+/// The errors returned by the proof of existence pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// Someone already owns a claim on this content.
+	ClaimAlreadyExists,
+	/// Nobody owns a claim on this content.
+	ClaimDoesNotExist,
+	/// The caller is not the owner of the claim.
+	NotClaimOwner,
+	/// The metadata is longer than `Config::MAX_METADATA_LENGTH`.
+	MetadataTooLong,
+	/// A claim can't expire in the block in which it is created.
+	ZeroExpiry,
+	/// The claim is shared, so it can only be revoked with `approve_revoke`.
+	SharedClaim,
+	/// The claim is not shared.
+	NotSharedClaim,
+	/// The threshold is zero, or more than the number of owners.
+	WrongThreshold,
+	/// There are more than `Config::MAX_CLAIM_OWNERS` owners.
+	TooManyOwners,
+	/// The owner already approved the revocation of the claim.
+	AlreadyApproved,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::ClaimAlreadyExists => "ClaimAlreadyExists",
+			Error::ClaimDoesNotExist => "ClaimDoesNotExist",
+			Error::NotClaimOwner => "NotClaimOwner",
+			Error::MetadataTooLong => "MetadataTooLong",
+			Error::ZeroExpiry => "ZeroExpiry",
+			Error::SharedClaim => "SharedClaim",
+			Error::NotSharedClaim => "NotSharedClaim",
+			Error::WrongThreshold => "WrongThreshold",
+			Error::TooManyOwners => "TooManyOwners",
+			Error::AlreadyApproved => "AlreadyApproved",
+		};
+		DispatchError::Module { pallet: "proof_of_existence", error }
+	}
 }
 
+/// The events of the proof of existence pallet for the runtime `T`, as collected by
+/// `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId, Hash>;
+
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
-#[derive(Debug)]
+#[macros::storage]
+#[derive(Debug, Clone)]
 pub struct Pallet<T: Config> {
-	/// A simple storage map from content to the owner of that content.
+	/// A simple storage map from the hash of some content to the details of its claim, including
+	/// its owner.
 	/// Accounts can make multiple different claims, but each claim can only have one owner.
-	/* TODO: Add a field `claims` which is a `BTreeMap` fom `T::Content` to `T::AccountId`. */
-    claims: BTreeMap<T::Content, T::AccountId>,
+	/* TODO: Add a field `claims` which is a `BTreeMap` fom `Hash` to `T::AccountId`. */
+	#[getter(get_claim_details, iter)]
+    claims: StorageMap<Hash, ClaimDetails<T::AccountId, T::BlockNumber>>,
+	/// The claims of each account, so they can be listed without going through every claim. The
+	/// owners of a shared claim are all indexed.
+	claims_by_owner: StorageMap<T::AccountId, BTreeSet<Hash>>,
+	/// Every owner a claim has had, in order, starting with the account which created it.
+	/// The history is kept when a claim is revoked, for auditing.
+	claim_history: StorageMap<Hash, Vec<T::AccountId>>,
+	/// The deposit reserved from the owner of each claim, which is the `CLAIM_DEPOSIT` at the time
+	/// the claim was created. It is zero for the claims which don't exist.
+	#[getter(deposit, default = Zero::zero())]
+	deposits: StorageMap<Hash, T::Balance>,
+	/// The claims renounced by their owner. Their content stays claimed, so nobody can claim it
+	/// again, but nobody owns them, so they can't be transferred or revoked either.
+	renounced: StorageSet<Hash>,
+	/// The owners of the shared claims. The owner in the details of a shared claim is the account
+	/// which created it.
+	shared_claims: StorageMap<Hash, SharedClaim<T::AccountId>>,
+	/// The owners of each shared claim who approved its revocation so far.
+	revoke_approvals: StorageMap<Hash, Vec<T::AccountId>>,
+	/// The claims which expire at the end of each block. A claim is left here when it is revoked
+	/// or renounced before it expires, so its details are checked before it is purged.
+	expiries: StorageMap<T::BlockNumber, Vec<Hash>>,
+	/// The block being executed, which new claims are recorded with.
+	block_number: T::BlockNumber,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId, Hash>>,
 }
 
 #[macros::call]
 impl<T: Config> Pallet<T> {
 	
-	/// Create a new  on behalf of the `caller`.
-	/// This function will return an error if someone already has claimed that content.
-	pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		/* TODO: Check that a `claim` does not already exist. If so, return an error. */
-		match self.get_claim(&claim) {
-            Some(_) => Err("Claim already exists"),
-            None => {
-                self.claims.insert(claim, caller);
-                Ok(())
-            }
-        }
-        /* TODO: `insert` the claim on behalf of `caller`. */
+	/// Create a new claim on `content` on behalf of the `caller`, who pays the claim fee and
+	/// reserves the claim deposit. Only the hash of the content is stored, which is the claim.
+	/// This function will return an error if someone already has claimed that content, or if the
+	/// caller cannot pay the fee and the deposit.
+	#[call_index = 0]
+	#[weight = weights::CREATE_CLAIM.saturating_add(content.len() as u64)]
+	pub fn create_claim(
+		&mut self,
+		caller: T::AccountId,
+		content: Vec<u8>,
+		currency: &mut T::Currency,
+	) -> DispatchResult {
+		self.do_create_claim(caller, Self::claim_of(&content), None, currency)
+	}
 
+	/// Create a new claim on `content` like `create_claim`, which expires `ttl_blocks` blocks after the current
+	/// block. At the end of that block the claim is purged and its deposit returned, so the content
+	/// can be claimed again.
+	/// This function will return an error if someone already has claimed that content, if the
+	/// caller cannot pay the fee and the deposit, or if `ttl_blocks` is zero.
+	#[call_index = 1]
+	#[weight = weights::CREATE_CLAIM_WITH_EXPIRY.saturating_add(content.len() as u64)]
+	pub fn create_claim_with_expiry(
+		&mut self,
+		caller: T::AccountId,
+		content: Vec<u8>,
+		ttl_blocks: T::BlockNumber,
+		currency: &mut T::Currency,
+	) -> DispatchResult {
+		if ttl_blocks.is_zero() {
+			return Err(Error::ZeroExpiry.into());
+		}
+		let expires_at = self.block_number.checked_add(&ttl_blocks).ok_or("Expiry block overflow")?;
+		self.do_create_claim(caller, Self::claim_of(&content), Some(expires_at), currency)
 	}
 
-	/// Revoke an existing claim on some content.
+	/// Revoke an existing claim on some content, returning its deposit to the owner.
 	/// This function should only succeed if the caller is the owner of an existing claim.
-	/// It will return an error if the claim does not exist, or if the caller is not the owner.
-	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+	/// It will return an error if the claim does not exist, if the caller is not the owner, or if
+	/// the claim is shared.
+	#[call_index = 2]
+	#[weight = weights::REVOKE_CLAIM]
+	pub fn revoke_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: Hash,
+		currency: &mut T::Currency,
+	) -> DispatchResult {
 		/* TODO: Get the owner of the `claim` to be revoked. */
-        let claim_owner = self.get_claim(&claim).ok_or("Claim does not exist.")?;
+        self.ensure_sole_owner(&claim, &caller)?;
 
-        if claim_owner != &caller {
-            return Err("Caller is not the owner of the claim");
+        self.claims.remove(&claim);
+        self.unindex_claim(&caller, &claim);
+        if let Some(deposit) = self.deposits.remove(&claim) {
+            currency.unreserve(&caller, deposit);
         }
-        self.claims.remove(&claim);		
+		self.deposit_event(Event::ClaimRevoked { claim, owner: caller });
         /* TODO: Check that the `owner` matches the `caller`. */
 		/* TODO: If all checks pass, then `remove` the `claim`. */
 		Ok(())
 	}
+
+	/// Transfer an existing claim on some content to a `new_owner`, who reserves its deposit in
+	/// place of the `caller`.
+	/// This function should only succeed if the caller is the owner of an existing claim.
+	/// It will return an error if the claim does not exist, if the caller is not the owner, if the
+	/// claim is shared, or if the new owner cannot reserve the deposit.
+	#[call_index = 3]
+	#[weight = weights::TRANSFER_CLAIM]
+	pub fn transfer_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: Hash,
+		new_owner: T::AccountId,
+		currency: &mut T::Currency,
+	) -> DispatchResult {
+		self.ensure_sole_owner(&claim, &caller)?;
+		let deposit = self.deposit(&claim);
+		if !deposit.is_zero() {
+			currency.reserve(&new_owner, deposit)?;
+			currency.unreserve(&caller, deposit);
+		}
+
+		if let Some(details) = self.claims.get_mut(&claim) {
+			details.owner = new_owner.clone();
+		}
+		self.unindex_claim(&caller, &claim);
+		self.claims_by_owner.entry(new_owner.clone()).or_default().insert(claim);
+		self.claim_history.entry(claim).or_default().push(new_owner.clone());
+		self.deposit_event(Event::ClaimTransferred { claim, from: caller, to: new_owner });
+		Ok(())
+	}
+
+	/// Renounce an existing claim on some content, returning its deposit to the owner. Unlike a
+	/// revoked claim, the content stays claimed forever, so nobody can claim it in place of the
+	/// owner, but nobody owns it anymore.
+	/// This function should only succeed if the caller is the owner of an existing claim.
+	/// It will return an error if the claim does not exist, if the caller is not the owner, or if
+	/// the claim is shared.
+	#[call_index = 4]
+	#[weight = weights::RENOUNCE_CLAIM]
+	pub fn renounce_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: Hash,
+		currency: &mut T::Currency,
+	) -> DispatchResult {
+		self.ensure_sole_owner(&claim, &caller)?;
+
+		self.claims.remove(&claim);
+		self.unindex_claim(&caller, &claim);
+		if let Some(deposit) = self.deposits.remove(&claim) {
+			currency.unreserve(&caller, deposit);
+		}
+		self.renounced.insert(claim);
+		self.deposit_event(Event::ClaimRenounced { claim, owner: caller });
+		Ok(())
+	}
+
+	/// Set the metadata of an existing claim, replacing its previous metadata. Any owner of a shared
+	/// claim can set it.
+	/// This function should only succeed if the caller is the owner of an existing claim.
+	/// It will return an error if the claim does not exist, if the caller is not the owner, or if
+	/// the metadata is too long.
+	#[call_index = 5]
+	#[weight = weights::SET_CLAIM_METADATA]
+	pub fn set_claim_metadata(&mut self, caller: T::AccountId, claim: Hash, metadata: Vec<u8>) -> DispatchResult {
+		if !self.is_owner(&claim, &caller) {
+			let error = if self.claims.contains_key(&claim) { Error::NotClaimOwner } else { Error::ClaimDoesNotExist };
+			return Err(error.into());
+		}
+		let details = self.claims.get_mut(&claim).ok_or(Error::ClaimDoesNotExist)?;
+		if metadata.len() > T::MAX_METADATA_LENGTH {
+			return Err(Error::MetadataTooLong.into());
+		}
+
+		details.metadata = metadata;
+		self.deposit_event(Event::ClaimMetadataSet { claim });
+		Ok(())
+	}
+
+	/// Create a new claim on `content` on behalf of `owners`, which `threshold` of them must approve to revoke
+	/// with `approve_revoke`. The `caller` must be one of the owners, and pays the claim fee and
+	/// reserves the claim deposit. A shared claim can't be transferred or renounced.
+	/// This function will return an error if someone already has claimed that content, if the
+	/// caller is not one of the owners, if there are too many owners, if the threshold is zero or
+	/// more than the number of owners, or if the caller cannot pay the fee and the deposit.
+	#[call_index = 6]
+	#[weight = weights::CREATE_SHARED_CLAIM.saturating_add(content.len() as u64)]
+	pub fn create_shared_claim(
+		&mut self,
+		caller: T::AccountId,
+		content: Vec<u8>,
+		mut owners: Vec<T::AccountId>,
+		threshold: u32,
+		currency: &mut T::Currency,
+	) -> DispatchResult {
+		owners.sort();
+		owners.dedup();
+		if owners.len() > T::MAX_CLAIM_OWNERS as usize {
+			return Err(Error::TooManyOwners.into());
+		}
+		if owners.binary_search(&caller).is_err() {
+			return Err(Error::NotClaimOwner.into());
+		}
+		if threshold == 0 || threshold as usize > owners.len() {
+			return Err(Error::WrongThreshold.into());
+		}
+
+		let claim = Self::claim_of(&content);
+		self.do_create_claim(caller, claim, None, currency)?;
+		for owner in &owners {
+			self.claims_by_owner.entry(owner.clone()).or_default().insert(claim);
+		}
+		self.shared_claims.insert(claim, SharedClaim { owners: owners.clone(), threshold });
+		self.deposit_event(Event::SharedClaimCreated { claim, owners, threshold });
+		Ok(())
+	}
+
+	/// Approve the revocation of a shared claim on behalf of the `caller`, one of its owners. Once
+	/// `threshold` owners approved it, the claim is revoked and its deposit returned to the owner
+	/// who created it.
+	/// This function will return an error if the claim does not exist or is not shared, if the
+	/// caller is not one of its owners, or if they already approved its revocation.
+	#[call_index = 7]
+	#[weight = weights::APPROVE_REVOKE]
+	pub fn approve_revoke(
+		&mut self,
+		caller: T::AccountId,
+		claim: Hash,
+		currency: &mut T::Currency,
+	) -> DispatchResult {
+		if !self.claims.contains_key(&claim) {
+			return Err(Error::ClaimDoesNotExist.into());
+		}
+		let shared = self.shared_claims.get(&claim).ok_or(Error::NotSharedClaim)?;
+		if shared.owners.binary_search(&caller).is_err() {
+			return Err(Error::NotClaimOwner.into());
+		}
+		let threshold = shared.threshold;
+		let approvals = self.revoke_approvals.entry(claim).or_default();
+		if approvals.contains(&caller) {
+			return Err(Error::AlreadyApproved.into());
+		}
+		approvals.push(caller.clone());
+		let count = approvals.len() as u32;
+		self.deposit_event(Event::RevokeApproved { claim, owner: caller, approvals: count });
+
+		if count >= threshold {
+			if let Some(shared) = self.shared_claims.remove(&claim) {
+				for owner in &shared.owners {
+					self.unindex_claim(owner, &claim);
+				}
+			}
+			self.revoke_approvals.remove(&claim);
+			if let Some(details) = self.claims.remove(&claim) {
+				if let Some(deposit) = self.deposits.remove(&claim) {
+					currency.unreserve(&details.owner, deposit);
+				}
+				self.deposit_event(Event::ClaimRevoked { claim, owner: details.owner });
+			}
+		}
+		Ok(())
+	}
+
+	/// Query the owner of a claim, which is emitted in a `ClaimQueried` event, so reads can go
+	/// through the dispatch pipeline like any other call.
+	/// This function doesn't modify any claim, and succeeds even if the claim does not exist.
+	#[call_index = 8]
+	#[weight = weights::QUERY_CLAIM]
+	pub fn query_claim(&mut self, _caller: T::AccountId, claim: Hash) -> DispatchResult {
+		let owner = self.get_claim(&claim).cloned();
+		self.deposit_event(Event::ClaimQueried { claim, owner });
+		Ok(())
+	}
 }
 
 
@@ -60,47 +405,592 @@ impl<T: Config> Pallet<T> {
 	/// Create a new instance of the Proof of Existence Module.
 	pub fn new() -> Self {
         Self {
-            claims: BTreeMap::new(),
+            claims: StorageMap::new(),
+            claims_by_owner: StorageMap::new(),
+            claim_history: StorageMap::new(),
+            deposits: StorageMap::new(),
+            renounced: StorageSet::new(),
+            shared_claims: StorageMap::new(),
+            revoke_approvals: StorageMap::new(),
+            expiries: StorageMap::new(),
+            block_number: T::BlockNumber::zero(),
+            events: Events::new(),
         }
 		/* TODO: Return a new instance of the `Pallet` struct. */
 	}
 
+	/// Get the claim on `content`, which is its hash with the `Hashing` of the runtime.
+	pub fn claim_of(content: &[u8]) -> Hash {
+		T::Hashing::hash(content)
+	}
+
     /// Get the owner (if any) of a claim.
-	pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
+	pub fn get_claim(&self, claim: &Hash) -> Option<&T::AccountId> {
 		/* TODO: `get` the `claim` */
-		self.claims.get(claim)
+		self.claims.get(claim).map(|details| &details.owner)
+	}
+
+	/// Check whether a claim was renounced by its owner, so it is claimed but has no owner.
+	pub fn is_renounced(&self, claim: &Hash) -> bool {
+		self.renounced.contains(claim)
+	}
+
+	/// Iterate over all the claims and their owners, sorted by claim.
+	pub fn claims(&self) -> impl Iterator<Item = (&Hash, &T::AccountId)> {
+		self.claims.iter().map(|(claim, details)| (claim, &details.owner))
+	}
+
+	/// Get the number of claims, not counting the renounced claims.
+	pub fn claim_count(&self) -> usize {
+		self.claims.len()
+	}
+
+	/// Get the claims owned by `who`, alone or with the other owners of a shared claim, sorted by
+	/// claim.
+	pub fn claims_of(&self, who: &T::AccountId) -> Vec<&Hash> {
+		self.claims_by_owner.get(who).map(|claims| claims.iter().collect()).unwrap_or_default()
+	}
+
+	/// Check whether `who` owns any claim.
+	pub fn has_claims(&self, who: &T::AccountId) -> bool {
+		self.claims_by_owner.contains_key(who)
+	}
+
+	/// Get the claims from `start` (included) to `end` (excluded) and their owners, sorted by
+	/// claim. The range is empty if `start` is not before `end`.
+	pub fn claims_in_range(
+		&self,
+		start: &Hash,
+		end: &Hash,
+	) -> Vec<(&Hash, &T::AccountId)> {
+		if start >= end {
+			return Vec::new();
+		}
+		self.claims.range(*start..*end).map(|(claim, details)| (claim, &details.owner)).collect()
+	}
+
+	/// Get every owner a claim has had, from the account which created it to the current owner.
+	pub fn claim_history(&self, claim: &Hash) -> &[T::AccountId] {
+		self.claim_history.get(claim).map(Vec::as_slice).unwrap_or_default()
+	}
+
+	/// Check that `caller` is the only owner of the existing claim `claim`.
+	fn ensure_sole_owner(&self, claim: &Hash, caller: &T::AccountId) -> Result<(), Error> {
+		let claim_owner = self.get_claim(claim).ok_or(Error::ClaimDoesNotExist)?;
+		if self.shared_claims.contains_key(claim) {
+			return Err(Error::SharedClaim);
+		}
+		if claim_owner != caller {
+			return Err(Error::NotClaimOwner);
+		}
+		Ok(())
+	}
+
+	/// Check whether `who` owns the existing claim `claim`, alone or with the other owners of a
+	/// shared claim.
+	pub fn is_owner(&self, claim: &Hash, who: &T::AccountId) -> bool {
+		match self.shared_claims.get(claim) {
+			Some(shared) => shared.owners.binary_search(who).is_ok(),
+			None => self.get_claim(claim) == Some(who),
+		}
+	}
+
+	/// Get the owners of a shared claim and its revocation threshold, if the claim is shared.
+	pub fn shared_claim(&self, claim: &Hash) -> Option<&SharedClaim<T::AccountId>> {
+		self.shared_claims.get(claim)
+	}
+
+	/// Create a claim on `claim` for `caller`, which expires at the end of `expires_at` if set.
+	fn do_create_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: Hash,
+		expires_at: Option<T::BlockNumber>,
+		currency: &mut T::Currency,
+	) -> DispatchResult {
+		/* TODO: Check that a `claim` does not already exist. If so, return an error. */
+		match self.get_claim(&claim) {
+            Some(_) => Err(Error::ClaimAlreadyExists.into()),
+            None if self.renounced.contains(&claim) => Err(Error::ClaimAlreadyExists.into()),
+            None => {
+                // The claim is only recorded once the fee is paid and the deposit reserved. The
+                // deposit is reserved first, so the fee can't be paid without it.
+                if !T::CLAIM_DEPOSIT.is_zero() {
+                    currency.reserve(&caller, T::CLAIM_DEPOSIT)?;
+                }
+                if !T::CLAIM_FEE.is_zero() {
+                    if let Err(error) = currency.transfer(&caller, &T::claim_fee_destination(), T::CLAIM_FEE) {
+                        currency.unreserve(&caller, T::CLAIM_DEPOSIT);
+                        return Err(error);
+                    }
+                }
+                let block_number = self.block_number;
+                let details = ClaimDetails { owner: caller.clone(), block_number, metadata: Vec::new(), expires_at };
+                self.claims.insert(claim, details);
+                self.claims_by_owner.entry(caller.clone()).or_default().insert(claim);
+                if let Some(expires_at) = expires_at {
+                    self.expiries.entry(expires_at).or_default().push(claim);
+                }
+                self.deposits.insert(claim, T::CLAIM_DEPOSIT);
+                self.claim_history.entry(claim).or_default().push(caller.clone());
+                self.deposit_event(Event::ClaimCreated { claim, owner: caller });
+                Ok(())
+            }
+        }
+        /* TODO: `insert` the claim on behalf of `caller`. */
+	}
+
+
+	/// Remove `claim` from the claims indexed for `who`.
+	fn unindex_claim(&mut self, who: &T::AccountId, claim: &Hash) {
+		if let Some(claims) = self.claims_by_owner.get_mut(who) {
+			claims.remove(claim);
+			if claims.is_empty() {
+				self.claims_by_owner.remove(who);
+			}
+		}
+	}
+
+	/// Purge the claims which expire at the end of `block_number`, returning their deposit to their
+	/// owner. Claims which were revoked or renounced since are already gone.
+	fn purge_expired_claims(&mut self, block_number: T::BlockNumber, currency: &mut T::Currency) {
+		let Some(claims) = self.expiries.remove(&block_number) else { return };
+		for claim in claims {
+			// The claim may have been revoked, and claimed again with another expiry.
+			match self.claims.get(&claim) {
+				Some(details) if details.expires_at == Some(block_number) => {},
+				_ => continue,
+			}
+			let Some(details) = self.claims.remove(&claim) else { continue };
+			self.unindex_claim(&details.owner, &claim);
+			if let Some(deposit) = self.deposits.remove(&claim) {
+				currency.unreserve(&details.owner, deposit);
+			}
+			self.deposit_event(Event::ClaimExpired { claim, owner: details.owner });
+		}
 	}
 }
 
-#[cfg(test)]
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The phase and
+	/// the events are not part of the state.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for (claim, details) in &self.claims {
+			let value = ((&details.owner, details.block_number), (&details.metadata, details.expires_at)).encode();
+			state.insert(storage_map_key("proof_of_existence", "claims", claim), value);
+		}
+		for (claim, owners) in &self.claim_history {
+			state.insert(storage_map_key("proof_of_existence", "claim_history", claim), owners.encode());
+		}
+		for (claim, deposit) in &self.deposits {
+			state.insert(storage_map_key("proof_of_existence", "deposits", claim), deposit.encode());
+		}
+		for (claim, shared) in &self.shared_claims {
+			let value = (&shared.owners, shared.threshold).encode();
+			state.insert(storage_map_key("proof_of_existence", "shared_claims", claim), value);
+		}
+		for (claim, approvals) in &self.revoke_approvals {
+			state.insert(storage_map_key("proof_of_existence", "revoke_approvals", claim), approvals.encode());
+		}
+		for claim in &self.renounced {
+			state.insert(storage_map_key("proof_of_existence", "renounced", claim), true.encode());
+		}
+	}
+}
+
+impl<T: Config> HooksWithContext<T::BlockNumber, T::Currency> for Pallet<T> {
+	/// Keep the number of the block, which new claims are recorded with.
+	fn on_initialize(&mut self, _currency: &mut T::Currency, block_number: T::BlockNumber) {
+		self.block_number = block_number;
+	}
+
+	/// Purge the claims which expire at the end of this block.
+	fn on_finalize(&mut self, currency: &mut T::Currency, block_number: T::BlockNumber) {
+		self.purge_expired_claims(block_number, currency);
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The initial state of the proof of existence pallet, see `RuntimeGenesisConfig` of
+/// `#[macros::runtime]`.
+pub struct GenesisConfig<T: Config> {
+	/// The claims which exist from the genesis block, with their owner and the content they are on.
+	/// Unlike the claims created by extrinsics, no fee or deposit is paid for them.
+	pub claims: Vec<(T::AccountId, Vec<u8>)>,
+}
+
+impl<T: Config> GenesisConfig<T> {
+	/// Record each claim in block zero. This fails if two claims are on the same content.
+	pub fn build(&self, pallet: &mut Pallet<T>) -> DispatchResult {
+		for (owner, content) in &self.claims {
+			let claim = Pallet::<T>::claim_of(content);
+			if pallet.claims.contains_key(&claim) {
+				return Err(Error::ClaimAlreadyExists.into());
+			}
+			let details = ClaimDetails { owner: owner.clone(), block_number: T::BlockNumber::zero(), metadata: Vec::new(), expires_at: None };
+			pallet.claims.insert(claim, details);
+			pallet.claims_by_owner.entry(owner.clone()).or_default().insert(claim);
+			pallet.claim_history.entry(claim).or_default().push(owner.clone());
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { claims: Vec::new() }
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
 mod test {
-	struct TestConfig;
+	use crate::balances;
+	use crate::hashing::{Hasher, Sha256};
+	use crate::system::Hash;
+	use super::Error;
 
-	impl super::Config for TestConfig {
-		type Content = &'static str;
+	/// The claim on `content`, hashed like the test configurations do.
+	fn claim(content: &str) -> Hash {
+		Sha256::hash(content.as_bytes())
 	}
 
-	impl crate::system::Config for TestConfig {
-		type AccountId = &'static str;
-		type BlockNumber = u32;
-		type Nonce = u32;
+	/// The claims on `contents`, in the order the pallet sorts them.
+	fn sorted_claims(contents: &[&str]) -> Vec<Hash> {
+		let mut claims = contents.iter().map(|content| claim(content)).collect::<Vec<_>>();
+		claims.sort();
+		claims
 	}
 
+	crate::construct_mock_runtime!(struct TestConfig: balances, proof_of_existence);
+
 	#[test]
 	fn basic_proof_of_existence() {
         let mut poe = super::Pallet::<TestConfig>::new();
+        let mut balances = balances::Pallet::<TestConfig>::new();
+
+        assert_eq!(poe.get_claim(&claim("my_document")), None);
+
+        assert_eq!(poe.create_claim("alice", "my_document".into(), &mut balances), Ok(()));
+        assert_eq!(poe.get_claim(&claim("my_document")), Some(&"alice"));
+        assert_eq!(super::Pallet::<TestConfig>::claim_of(b"my_document"), claim("my_document"));
+        assert_eq!(poe.create_claim("bob", "my_document".into(), &mut balances), Err(Error::ClaimAlreadyExists.into()));
+
+        assert_eq!(poe.revoke_claim("bob", claim("my_document"), &mut balances), Err(Error::NotClaimOwner.into()));
+        assert_eq!(poe.revoke_claim("alice", claim("my_document"), &mut balances), Ok(()));
+        assert_eq!(poe.get_claim(&claim("my_document")), None);
+        assert_eq!(poe.revoke_claim("alice", claim("my_document"), &mut balances), Err(Error::ClaimDoesNotExist.into()));
+	}
+
+	#[test]
+	fn genesis_claims_exist_from_block_zero() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let genesis = super::GenesisConfig::<TestConfig> { claims: vec![("alice", "my_document".into())] };
+		assert_eq!(genesis.build(&mut poe), Ok(()));
+		assert_eq!(poe.get_claim(&claim("my_document")), Some(&"alice"));
+		assert_eq!(poe.claim_history(&claim("my_document")), ["alice"]);
+
+		// No deposit was reserved for the claim, so none is returned when it is revoked.
+		assert_eq!(poe.revoke_claim("alice", claim("my_document"), &mut balances), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+
+		let genesis = super::GenesisConfig::<TestConfig> {
+			claims: vec![("alice", "my_photo".into()), ("bob", "my_photo".into())],
+		};
+		assert_eq!(genesis.build(&mut super::Pallet::new()), Err(Error::ClaimAlreadyExists.into()));
+	}
+
+	#[test]
+	fn claim_ownership_history() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		assert!(poe.claim_history(&claim("my_document")).is_empty());
+
+		assert_eq!(poe.create_claim("alice", "my_document".into(), &mut balances), Ok(()));
+		assert_eq!(
+			poe.transfer_claim("bob", claim("my_document"), "charlie", &mut balances),
+			Err(Error::NotClaimOwner.into())
+		);
+		assert_eq!(poe.transfer_claim("alice", claim("my_document"), "bob", &mut balances), Ok(()));
+		assert_eq!(poe.transfer_claim("bob", claim("my_document"), "charlie", &mut balances), Ok(()));
+		assert_eq!(poe.get_claim(&claim("my_document")), Some(&"charlie"));
+		assert_eq!(poe.claim_history(&claim("my_document")), ["alice", "bob", "charlie"]);
+
+		// The history is kept for auditing after the claim is revoked.
+		assert_eq!(poe.revoke_claim("charlie", claim("my_document"), &mut balances), Ok(()));
+		assert_eq!(poe.get_claim(&claim("my_document")), None);
+		assert_eq!(poe.claim_history(&claim("my_document")), ["alice", "bob", "charlie"]);
+		assert_eq!(
+			poe.transfer_claim("charlie", claim("my_document"), "alice", &mut balances),
+			Err(Error::ClaimDoesNotExist.into())
+		);
+	}
+
+	#[test]
+	fn renounced_claims_have_no_owner_and_cannot_be_claimed_again() {
+		let mut poe = super::Pallet::<ClaimFeeConfig>::new();
+		let mut balances = balances::Pallet::<ClaimFeeConfig>::new();
+		balances.set_balance("alice", 30);
+		balances.set_balance("bob", 30);
+		assert_eq!(poe.create_claim("alice", "my_document".into(), &mut balances), Ok(()));
+
+		assert_eq!(poe.renounce_claim("bob", claim("my_document"), &mut balances), Err(Error::NotClaimOwner.into()));
+		assert_eq!(poe.renounce_claim("alice", claim("my_document"), &mut balances), Ok(()));
+		assert_eq!(poe.get_claim(&claim("my_document")), None);
+		assert!(poe.is_renounced(&claim("my_document")));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		let renounced = super::Event::ClaimRenounced { claim: claim("my_document"), owner: "alice" };
+		assert_eq!(poe.events().last().map(|record| &record.event), Some(&renounced));
+
+		// Nobody can claim, transfer or revoke the content anymore.
+		assert_eq!(poe.create_claim("bob", "my_document".into(), &mut balances), Err(Error::ClaimAlreadyExists.into()));
+		assert_eq!(
+			poe.transfer_claim("alice", claim("my_document"), "bob", &mut balances),
+			Err(Error::ClaimDoesNotExist.into())
+		);
+		assert_eq!(poe.revoke_claim("alice", claim("my_document"), &mut balances), Err(Error::ClaimDoesNotExist.into()));
+		assert_eq!(poe.renounce_claim("alice", claim("my_document"), &mut balances), Err(Error::ClaimDoesNotExist.into()));
+		assert_eq!(balances.balance(&"bob"), 30);
+	}
+
+	#[test]
+	fn claim_details_record_the_block_and_metadata() {
+		use crate::support::HooksWithContext;
+
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		poe.on_initialize(&mut balances, 7);
+		assert_eq!(poe.create_claim("alice", "my_document".into(), &mut balances), Ok(()));
 
-        let _ = poe.create_claim("alice", "my_document");
-        assert_eq!(poe.get_claim(&"alice"), Some(&"my_document"));
+		assert_eq!(poe.set_claim_metadata("bob", claim("my_document"), b"pdf".to_vec()), Err(Error::NotClaimOwner.into()));
+		assert_eq!(poe.set_claim_metadata("alice", claim("my_document"), b"text/pdf".to_vec()), Ok(()));
+		assert_eq!(
+			poe.set_claim_metadata("alice", claim("my_document"), b"much too long".to_vec()),
+			Err(Error::MetadataTooLong.into())
+		);
+		assert_eq!(poe.set_claim_metadata("alice", claim("other_document"), b"pdf".to_vec()), Err(Error::ClaimDoesNotExist.into()));
 
-        let _ = poe.revoke_claim("bob", "my_document");
+		// The creation block and the metadata are kept when the claim changes hands.
+		poe.on_initialize(&mut balances, 9);
+		assert_eq!(poe.transfer_claim("alice", claim("my_document"), "bob", &mut balances), Ok(()));
+		let details = super::ClaimDetails { owner: "bob", block_number: 7, metadata: b"text/pdf".to_vec(), expires_at: None };
+		assert_eq!(poe.get_claim_details(&claim("my_document")), Some(&details));
+		assert_eq!(poe.get_claim_details(&claim("other_document")), None);
+	}
+
+	#[test]
+	fn expired_claims_are_purged_and_can_be_claimed_again() {
+		use crate::support::HooksWithContext;
+
+		let mut poe = super::Pallet::<ClaimFeeConfig>::new();
+		let mut balances = balances::Pallet::<ClaimFeeConfig>::new();
+		balances.set_balance("alice", 30);
+		balances.set_balance("bob", 30);
+		poe.on_initialize(&mut balances, 1);
+		assert_eq!(poe.create_claim_with_expiry("alice", "my_document".into(), 0, &mut balances), Err(Error::ZeroExpiry.into()));
+		assert_eq!(poe.create_claim_with_expiry("alice", "my_document".into(), 2, &mut balances), Ok(()));
+		assert_eq!(poe.create_claim_with_expiry("alice", "my_image".into(), 2, &mut balances), Ok(()));
+		assert_eq!(poe.get_claim_details(&claim("my_document")).and_then(|details| details.expires_at), Some(3));
+
+		// A claim revoked and claimed again without expiry is not purged.
+		assert_eq!(poe.revoke_claim("alice", claim("my_image"), &mut balances), Ok(()));
+		assert_eq!(poe.create_claim("bob", "my_image".into(), &mut balances), Ok(()));
+
+		poe.on_finalize(&mut balances, 2);
+		assert_eq!(poe.get_claim(&claim("my_document")), Some(&"alice"));
+		poe.on_finalize(&mut balances, 3);
+		assert_eq!(poe.get_claim(&claim("my_document")), None);
+		assert_eq!(poe.get_claim(&claim("my_image")), Some(&"bob"));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		let expired = super::Event::ClaimExpired { claim: claim("my_document"), owner: "alice" };
+		assert_eq!(poe.events().last().map(|record| &record.event), Some(&expired));
+
+		assert_eq!(poe.create_claim("bob", "my_document".into(), &mut balances), Ok(()));
+	}
+
+	#[test]
+	fn shared_claims_are_revoked_once_enough_owners_approve() {
+		let mut poe = super::Pallet::<ClaimFeeConfig>::new();
+		let mut balances = balances::Pallet::<ClaimFeeConfig>::new();
+		balances.set_balance("alice", 30);
+		let shared = |owners: &[&'static str], threshold| (owners.to_vec(), threshold);
+
+		for ((owners, threshold), error) in [
+			(shared(&["bob", "charlie"], 1), Error::NotClaimOwner),
+			(shared(&["alice", "bob"], 3), Error::WrongThreshold),
+			(shared(&["alice", "bob", "charlie", "dave"], 2), Error::TooManyOwners),
+		] {
+			assert_eq!(poe.create_shared_claim("alice", "deed".into(), owners, threshold, &mut balances), Err(error.into()));
+		}
+		let owners = vec!["charlie", "alice", "bob", "alice"];
+		assert_eq!(poe.create_shared_claim("alice", "deed".into(), owners, 2, &mut balances), Ok(()));
+		assert_eq!(poe.shared_claim(&claim("deed")).map(|shared| &shared.owners[..]), Some(&["alice", "bob", "charlie"][..]));
+		assert!(poe.is_owner(&claim("deed"), &"bob"));
+		assert_eq!(balances.reserved_balance(&"alice"), 3);
+
+		// A shared claim can't be revoked, transferred or renounced by a single owner.
+		assert_eq!(poe.revoke_claim("alice", claim("deed"), &mut balances), Err(Error::SharedClaim.into()));
+		assert_eq!(poe.transfer_claim("alice", claim("deed"), "dave", &mut balances), Err(Error::SharedClaim.into()));
+		assert_eq!(poe.renounce_claim("alice", claim("deed"), &mut balances), Err(Error::SharedClaim.into()));
+		assert_eq!(poe.set_claim_metadata("bob", claim("deed"), b"house".to_vec()), Ok(()));
+
+		assert_eq!(poe.approve_revoke("dave", claim("deed"), &mut balances), Err(Error::NotClaimOwner.into()));
+		assert_eq!(poe.approve_revoke("bob", claim("deed"), &mut balances), Ok(()));
+		assert_eq!(poe.approve_revoke("bob", claim("deed"), &mut balances), Err(Error::AlreadyApproved.into()));
+		assert_eq!(poe.get_claim(&claim("deed")), Some(&"alice"));
+		assert_eq!(poe.approve_revoke("charlie", claim("deed"), &mut balances), Ok(()));
+		assert_eq!(poe.get_claim(&claim("deed")), None);
+		assert_eq!(poe.shared_claim(&claim("deed")), None);
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(poe.approve_revoke("charlie", claim("deed"), &mut balances), Err(Error::ClaimDoesNotExist.into()));
+
+		assert_eq!(poe.create_claim("alice", "deed".into(), &mut balances), Ok(()));
+		assert_eq!(poe.approve_revoke("alice", claim("deed"), &mut balances), Err(Error::NotSharedClaim.into()));
+	}
+
+	#[test]
+	fn claims_are_indexed_by_owner() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		for (caller, content) in [("alice", "doc_b"), ("bob", "doc_c"), ("alice", "doc_a")] {
+			assert_eq!(poe.create_claim(caller, content.into(), &mut balances), Ok(()));
+		}
+		assert_eq!(poe.create_shared_claim("bob", "deed".into(), vec!["alice", "bob"], 2, &mut balances), Ok(()));
+		let claims_of = |poe: &super::Pallet<TestConfig>, who| poe.claims_of(&who).into_iter().copied().collect::<Vec<_>>();
+		assert_eq!(claims_of(&poe, "alice"), sorted_claims(&["deed", "doc_a", "doc_b"]));
+		assert_eq!(poe.claim_count(), 4);
+
+		assert_eq!(poe.transfer_claim("alice", claim("doc_a"), "charlie", &mut balances), Ok(()));
+		assert_eq!(poe.revoke_claim("bob", claim("doc_c"), &mut balances), Ok(()));
+		assert_eq!(claims_of(&poe, "alice"), sorted_claims(&["deed", "doc_b"]));
+		assert_eq!(claims_of(&poe, "bob"), [claim("deed")]);
+		assert_eq!(claims_of(&poe, "charlie"), [claim("doc_a")]);
+
+		assert_eq!(poe.approve_revoke("alice", claim("deed"), &mut balances), Ok(()));
+		assert_eq!(poe.approve_revoke("bob", claim("deed"), &mut balances), Ok(()));
+		assert!(!poe.has_claims(&"bob"));
+		assert!(poe.claims_of(&"dave").is_empty());
+		let owners = poe.iter_claims().map(|(claim, details)| (*claim, details.owner)).collect::<Vec<_>>();
+		let mut expected = vec![(claim("doc_a"), "charlie"), (claim("doc_b"), "alice")];
+		expected.sort();
+		assert_eq!(owners, expected);
+	}
+
+	#[test]
+	fn query_claim_emits_the_owner() {
+		use crate::support::{DispatchWithContext, RawOrigin::Signed};
+
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		assert_eq!(poe.create_claim("alice", "my_document".into(), &mut balances), Ok(()));
+
+		let query = |content| super::Call::query_claim { claim: claim(content) };
+		assert!(poe.dispatch(&mut balances, Signed("bob"), query("my_document")).is_ok());
+		assert!(poe.dispatch(&mut balances, Signed("bob"), query("unknown_document")).is_ok());
+		let events = poe.events().iter().map(|record| record.event.clone()).collect::<Vec<_>>();
+		assert_eq!(
+			events,
+			[
+				super::Event::ClaimCreated { claim: claim("my_document"), owner: "alice" },
+				super::Event::ClaimQueried { claim: claim("my_document"), owner: Some("alice") },
+				super::Event::ClaimQueried { claim: claim("unknown_document"), owner: None },
+			]
+		);
+		// Queries don't modify the claims.
+		assert_eq!(poe.get_claim(&claim("my_document")), Some(&"alice"));
+		assert_eq!(poe.get_claim(&claim("unknown_document")), None);
+	}
+
+	#[test]
+	fn claims_in_range() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		for content in ["doc_c", "doc_a", "img_a", "doc_b"] {
+			assert_eq!(poe.create_claim("alice", content.into(), &mut balances), Ok(()));
+		}
+		// The claims are sorted by hash, not by content.
+		let claims = sorted_claims(&["doc_c", "doc_a", "img_a", "doc_b"]);
 
-		/*
-			TODO:
-			Create an end to end test verifying the basic functionality of this pallet.
-				- Check the initial state is as you expect.
-				- Check that all functions work successfully.
-				- Check that all error conditions error as expected.
-		*/
+		assert_eq!(
+			poe.claims_in_range(&claims[0], &claims[2]),
+			vec![(&claims[0], &"alice"), (&claims[1], &"alice")]
+		);
+		assert_eq!(poe.claims_in_range(&[0; 32], &[0xff; 32]).len(), 4);
+		assert_eq!(poe.claims_in_range(&claims[2], &claims[2]), vec![]);
+		assert_eq!(poe.claims_in_range(&claims[3], &claims[0]), vec![]);
 	}
-}
\ No newline at end of file
+
+	crate::construct_mock_runtime!(struct ClaimFeeConfig: balances);
+
+	impl super::Config for ClaimFeeConfig {
+		type Currency = balances::Pallet<Self>;
+		const CLAIM_FEE: Self::Balance = 10;
+		const CLAIM_DEPOSIT: Self::Balance = 3;
+		const MAX_METADATA_LENGTH: usize = 8;
+		const MAX_CLAIM_OWNERS: u32 = 3;
+		fn claim_fee_destination() -> Self::AccountId {
+			"treasury"
+		}
+	}
+
+	#[test]
+	fn create_claim_charges_fee() {
+		let mut poe = super::Pallet::<ClaimFeeConfig>::new();
+		let mut balances = balances::Pallet::<ClaimFeeConfig>::new();
+		balances.set_balance("alice", 18);
+		balances.set_balance("bob", 12);
+
+		// Bob can reserve the deposit, but not pay the fee on top of it.
+		assert_eq!(poe.create_claim("bob", "my_document".into(), &mut balances), Err(balances::Error::InsufficientBalance.into()));
+		assert_eq!(poe.get_claim(&claim("my_document")), None);
+		assert_eq!(balances.balance(&"bob"), 12);
+		assert_eq!(balances.reserved_balance(&"bob"), 0);
+
+		assert_eq!(poe.create_claim("alice", "my_document".into(), &mut balances), Ok(()));
+		assert_eq!(poe.get_claim(&claim("my_document")), Some(&"alice"));
+		assert_eq!(balances.balance(&"alice"), 5);
+		assert_eq!(balances.reserved_balance(&"alice"), 3);
+		assert_eq!(balances.balance(&"treasury"), 10);
+
+		// No fee is charged when the claim already exists.
+		assert_eq!(poe.create_claim("alice", "my_document".into(), &mut balances), Err(Error::ClaimAlreadyExists.into()));
+		assert_eq!(balances.balance(&"alice"), 5);
+	}
+
+	#[test]
+	fn claim_deposit_follows_the_owner() {
+		let mut poe = super::Pallet::<ClaimFeeConfig>::new();
+		let mut balances = balances::Pallet::<ClaimFeeConfig>::new();
+		balances.set_balance("alice", 13);
+		balances.set_balance("bob", 2);
+		balances.set_balance("charlie", 3);
+		assert_eq!(poe.create_claim("alice", "my_document".into(), &mut balances), Ok(()));
+		assert_eq!(poe.deposit(&claim("my_document")), 3);
+
+		// The new owner must be able to reserve the deposit.
+		assert_eq!(
+			poe.transfer_claim("alice", claim("my_document"), "bob", &mut balances),
+			Err(balances::Error::InsufficientBalance.into())
+		);
+		assert_eq!(poe.transfer_claim("alice", claim("my_document"), "charlie", &mut balances), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(balances.balance(&"alice"), 3);
+		assert_eq!(balances.reserved_balance(&"charlie"), 3);
+
+		// The deposit is returned to the owner who revokes the claim.
+		assert_eq!(poe.revoke_claim("charlie", claim("my_document"), &mut balances), Ok(()));
+		assert_eq!(balances.reserved_balance(&"charlie"), 0);
+		assert_eq!(balances.balance(&"charlie"), 3);
+		assert_eq!(poe.deposit(&claim("my_document")), 0);
+	}
+}
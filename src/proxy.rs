@@ -0,0 +1,346 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::codec::{self, Decode, Encode};
+use crate::storage::{storage_map_key, StorageBackend};
+use crate::support::{
+	CallMetadata, DispatchError, DispatchResult, Events, InstanceFilter, RawOrigin, StorageMap, StorageValue,
+	Weight,
+};
+
+pub trait Config: crate::system::Config {
+	/// The calls which proxies can make, which is usually the `RuntimeCall` generated by
+	/// `#[macros::runtime]`.
+	type RuntimeCall: Clone + CallMetadata;
+	/// The maximum number of proxies an account can have.
+	const MAX_PROXIES: u32;
+	/// The weight of `call`. The call of a proxy is dispatched without an extrinsic, so its weight is
+	/// paid for with the `proxy` call.
+	fn call_weight(call: &Self::RuntimeCall) -> Weight;
+}
+
+/// What a proxy is allowed to call on behalf of the account which registered it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProxyType {
+	/// Any call.
+	Any,
+	/// The calls of the balances pallet.
+	BalancesOnly,
+	/// The calls of the proof of existence pallet.
+	PoEOnly,
+}
+
+impl<Call: CallMetadata> InstanceFilter<Call> for ProxyType {
+	fn filter(&self, call: &Call) -> bool {
+		match self {
+			ProxyType::Any => true,
+			ProxyType::BalancesOnly => call.pallet_name() == "balances",
+			ProxyType::PoEOnly => call.pallet_name() == "proof_of_existence",
+		}
+	}
+}
+
+impl Encode for ProxyType {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		let index: u8 = match self {
+			ProxyType::Any => 0,
+			ProxyType::BalancesOnly => 1,
+			ProxyType::PoEOnly => 2,
+		};
+		dest.push(index);
+	}
+}
+
+impl Decode for ProxyType {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		match u8::decode(input)? {
+			0 => Ok(ProxyType::Any),
+			1 => Ok(ProxyType::BalancesOnly),
+			2 => Ok(ProxyType::PoEOnly),
+			_ => Err(codec::Error::Invalid),
+		}
+	}
+}
+
+/// A proxy of an account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyDefinition<AccountId> {
+	/// The account which can make calls on behalf of the account.
+	pub delegate: AccountId,
+	pub proxy_type: ProxyType,
+}
+
+/// A call made by a proxy, to be dispatched by the runtime with the signed origin of the account it
+/// was made on behalf of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyCall<AccountId, Call> {
+	/// The account the call is made on behalf of.
+	pub address: AccountId,
+	/// The proxy which made the call.
+	pub id: AccountId,
+	pub origin: RawOrigin<AccountId>,
+	pub call: Box<Call>,
+}
+
+/// The events emitted by the proxy pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId> {
+	/// An account registered a proxy.
+	ProxyAdded { delegator: AccountId, delegate: AccountId, proxy_type: ProxyType },
+	/// An account removed a proxy.
+	ProxyRemoved { delegator: AccountId, delegate: AccountId, proxy_type: ProxyType },
+	/// A call made by a proxy was dispatched on behalf of the `real` account, with its result.
+	ProxyExecuted { real: AccountId, delegate: AccountId, result: DispatchResult },
+}
+
+/// The errors returned by the proxy pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The account already has `Config::MAX_PROXIES` proxies.
+	TooMany,
+	/// The account already has this proxy.
+	Duplicate,
+	/// An account can't be its own proxy.
+	NoSelfProxy,
+	/// The account has no such proxy.
+	NotFound,
+	/// The caller is not a proxy of the account.
+	NotProxy,
+	/// None of the proxy types of the caller allows the call.
+	Unproxyable,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::TooMany => "TooMany",
+			Error::Duplicate => "Duplicate",
+			Error::NoSelfProxy => "NoSelfProxy",
+			Error::NotFound => "NotFound",
+			Error::NotProxy => "NotProxy",
+			Error::Unproxyable => "Unproxyable",
+		};
+		DispatchError::Module { pallet: "proxy", error }
+	}
+}
+
+/// The events of the proxy pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// This is the Proxy Module.
+/// It allows accounts to register proxies, which can make calls on their behalf. The type of a
+/// proxy limits the calls it can make, see `ProxyType`.
+///
+/// The pallet only checks the call of a proxy: the runtime dispatches it right after the extrinsic,
+/// see `#[scheduler]` in `#[macros::runtime]`.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The proxies of each account, in the order they were added.
+	proxies: StorageMap<T::AccountId, Vec<ProxyDefinition<T::AccountId>>>,
+	/// The calls made by proxies, until the runtime dispatches them.
+	due: StorageValue<Vec<ProxyCall<T::AccountId, T::RuntimeCall>>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Register `delegate` as a proxy of the `caller`, which can make the calls allowed by
+	/// `proxy_type` on their behalf. An account can have several proxy types for the same
+	/// delegate.
+	/// This function will return an error if the caller already has this proxy, or too many
+	/// proxies.
+	#[call_index = 0]
+	#[weight = 5_000]
+	pub fn add_proxy(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: ProxyType,
+	) -> DispatchResult {
+		if delegate == caller {
+			return Err(Error::NoSelfProxy.into());
+		}
+		let proxies = self.proxies.entry(caller.clone()).or_default();
+		let definition = ProxyDefinition { delegate: delegate.clone(), proxy_type };
+		if proxies.contains(&definition) {
+			return Err(Error::Duplicate.into());
+		}
+		if proxies.len() >= T::MAX_PROXIES as usize {
+			return Err(Error::TooMany.into());
+		}
+
+		proxies.push(definition);
+		self.deposit_event(Event::ProxyAdded { delegator: caller, delegate, proxy_type });
+		Ok(())
+	}
+
+	/// Remove the proxy of the `caller` registered with `delegate` and `proxy_type`.
+	/// This function will return an error if the caller has no such proxy.
+	#[call_index = 1]
+	#[weight = 5_000]
+	pub fn remove_proxy(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: ProxyType,
+	) -> DispatchResult {
+		let proxies = self.proxies.get_mut(&caller).ok_or(Error::NotFound)?;
+		let definition = ProxyDefinition { delegate: delegate.clone(), proxy_type };
+		let index = proxies.iter().position(|proxy| *proxy == definition).ok_or(Error::NotFound)?;
+
+		proxies.remove(index);
+		if proxies.is_empty() {
+			self.proxies.remove(&caller);
+		}
+		self.deposit_event(Event::ProxyRemoved { delegator: caller, delegate, proxy_type });
+		Ok(())
+	}
+
+	/// Make `call` on behalf of the `real` account, as one of its proxies. The call is dispatched
+	/// with the signed origin of the real account.
+	/// This function will return an error if the caller is not a proxy of the real account, or if
+	/// none of their proxy types allows the call.
+	#[call_index = 2]
+	#[weight = 10_000u64.saturating_add(T::call_weight(call))]
+	pub fn proxy(
+		&mut self,
+		caller: T::AccountId,
+		real: T::AccountId,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		let mut proxy_types = self
+			.proxies
+			.get(&real)
+			.into_iter()
+			.flatten()
+			.filter(|proxy| proxy.delegate == caller)
+			.map(|proxy| proxy.proxy_type)
+			.peekable();
+		if proxy_types.peek().is_none() {
+			return Err(Error::NotProxy.into());
+		}
+		if !proxy_types.any(|proxy_type| proxy_type.filter(&*call)) {
+			return Err(Error::Unproxyable.into());
+		}
+
+		let origin = RawOrigin::Signed(real.clone());
+		self.due.get_mut().push(ProxyCall { address: real, id: caller, origin, call });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Proxy Module.
+	pub fn new() -> Self {
+		Self { proxies: StorageMap::new(), due: StorageValue::new(Vec::new()), events: Events::new() }
+	}
+
+	/// Get the proxies of `who`, in the order they were added.
+	pub fn proxies(&self, who: &T::AccountId) -> &[ProxyDefinition<T::AccountId>] {
+		self.proxies.get(who).map_or(&[], Vec::as_slice)
+	}
+
+	/// Take the calls made by proxies, so the runtime can dispatch them.
+	pub fn take_due(&mut self) -> Vec<ProxyCall<T::AccountId, T::RuntimeCall>> {
+		core::mem::take(self.due.get_mut())
+	}
+
+	/// Record the `result` of the call made by the proxy `delegate`, which was dispatched by the
+	/// runtime on behalf of `real`.
+	pub fn note_dispatched(&mut self, real: T::AccountId, delegate: T::AccountId, result: DispatchResult) {
+		self.deposit_event(Event::ProxyExecuted { real, delegate, result });
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The calls which
+	/// are due are dispatched before the state is committed to, so they are left out with the phase
+	/// and the events.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for (who, proxies) in &self.proxies {
+			let proxies = proxies.iter().map(|proxy| (&proxy.delegate, proxy.proxy_type)).collect::<Vec<_>>();
+			state.insert(storage_map_key("proxy", "proxies", who), proxies.encode());
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, ProxyType};
+	use crate::support::{CallMetadata, InstanceFilter, RawOrigin};
+
+	/// A call of a test runtime, made of the names of its pallet and function.
+	#[derive(Debug, Clone, PartialEq)]
+	struct TestCall(&'static str, &'static str);
+
+	impl CallMetadata for TestCall {
+		fn pallet_name(&self) -> &'static str {
+			self.0
+		}
+
+		fn function_name(&self) -> &'static str {
+			self.1
+		}
+	}
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		type RuntimeCall = TestCall;
+		const MAX_PROXIES: u32 = 2;
+		fn call_weight(_call: &Self::RuntimeCall) -> crate::support::Weight {
+			1_000
+		}
+	}
+
+	#[test]
+	fn proxy_types_filter_calls() {
+		let transfer = TestCall("balances", "transfer");
+		let create_claim = TestCall("proof_of_existence", "create_claim");
+		let bond = TestCall("staking", "bond");
+		assert!([&transfer, &create_claim, &bond].iter().all(|call| ProxyType::Any.filter(*call)));
+		assert!(ProxyType::BalancesOnly.filter(&transfer));
+		assert!(!ProxyType::BalancesOnly.filter(&create_claim));
+		assert!(ProxyType::PoEOnly.filter(&create_claim));
+		assert!(!ProxyType::PoEOnly.filter(&bond));
+	}
+
+	#[test]
+	fn proxies_make_the_calls_their_type_allows() {
+		let mut proxy = super::Pallet::<TestConfig>::new();
+		let transfer = || Box::new(TestCall("balances", "transfer"));
+
+		assert_eq!(proxy.add_proxy("alice", "alice", ProxyType::Any), Err(Error::NoSelfProxy.into()));
+		assert_eq!(proxy.add_proxy("alice", "bob", ProxyType::PoEOnly), Ok(()));
+		assert_eq!(proxy.add_proxy("alice", "bob", ProxyType::PoEOnly), Err(Error::Duplicate.into()));
+		assert_eq!(proxy.add_proxy("alice", "charlie", ProxyType::Any), Ok(()));
+		assert_eq!(proxy.add_proxy("alice", "dave", ProxyType::Any), Err(Error::TooMany.into()));
+
+		assert_eq!(proxy.proxy("dave", "alice", transfer()), Err(Error::NotProxy.into()));
+		assert_eq!(proxy.proxy("bob", "alice", transfer()), Err(Error::Unproxyable.into()));
+		assert!(proxy.take_due().is_empty());
+		// A delegate can have several proxy types, and any of them can allow the call.
+		assert_eq!(proxy.add_proxy("alice", "bob", ProxyType::BalancesOnly), Err(Error::TooMany.into()));
+		assert_eq!(proxy.remove_proxy("alice", "charlie", ProxyType::Any), Ok(()));
+		assert_eq!(proxy.remove_proxy("alice", "charlie", ProxyType::Any), Err(Error::NotFound.into()));
+		assert_eq!(proxy.add_proxy("alice", "bob", ProxyType::BalancesOnly), Ok(()));
+		assert_eq!(proxy.proxy("bob", "alice", transfer()), Ok(()));
+
+		let due = proxy.take_due();
+		let due = due.iter().map(|call| (call.address, call.id, &call.origin, &*call.call)).collect::<Vec<_>>();
+		assert_eq!(due, [("alice", "bob", &RawOrigin::Signed("alice"), &*transfer())]);
+		assert_eq!(proxy.proxies(&"alice").len(), 2);
+	}
+}
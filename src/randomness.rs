@@ -0,0 +1,75 @@
+//! Deterministic on-chain randomness, for things like picking block authors or running lotteries.
+//!
+//! The randomness is derived from the hashes of recent blocks, so every node executing the same
+//! chain gets the same random values. It can be predicted, and to some extent influenced, by
+//! block authors, so it must not be used where that matters.
+
+use sha2::{Digest, Sha256};
+
+use crate::system;
+
+/// The number of recent block hashes mixed into the seed of the randomness.
+pub const RECENT_BLOCKS: usize = 16;
+
+/// Get a random number for `subject` at `block_number`.
+///
+/// This mixes the hashes of the `RECENT_BLOCKS` most recent blocks up to `block_number`, stored
+/// in the system pallet, with the block number and the subject. The same inputs always produce
+/// the same number, while different subjects, like different lotteries, get independent numbers.
+pub fn random<T: system::Config>(
+	system: &system::Pallet<T>,
+	block_number: T::BlockNumber,
+	subject: &[u8],
+) -> u64
+where
+	T::BlockNumber: Into<u64>,
+{
+	let mut hasher = Sha256::new();
+	for hash in system.recent_block_hashes(block_number, RECENT_BLOCKS) {
+		hasher.update(hash);
+	}
+	hasher.update(block_number.into().to_le_bytes());
+	hasher.update(subject);
+
+	let digest = hasher.finalize();
+	let mut bytes = [0u8; 8];
+	bytes.copy_from_slice(&digest[..8]);
+	u64::from_le_bytes(bytes)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use crate::system;
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	fn system_with_hashes() -> system::Pallet<TestConfig> {
+		let mut system = system::Pallet::<TestConfig>::new();
+		for block_number in 1..=20u32 {
+			system.set_block_hash(block_number, [block_number as u8; 32]);
+		}
+		system
+	}
+
+	#[test]
+	fn random_is_deterministic() {
+		let system = system_with_hashes();
+		let first = super::random(&system, 20, b"lottery");
+		assert_eq!(super::random(&system, 20, b"lottery"), first);
+
+		// Another node with the same chain gets the same number.
+		assert_eq!(super::random(&system_with_hashes(), 20, b"lottery"), first);
+	}
+
+	#[test]
+	fn random_depends_on_subject_and_block() {
+		let system = system_with_hashes();
+		let lottery = super::random(&system, 20, b"lottery");
+		assert_ne!(super::random(&system, 20, b"author"), lottery);
+		assert_ne!(super::random(&system, 19, b"lottery"), lottery);
+
+		let mut other = system_with_hashes();
+		other.set_block_hash(20, [0; 32]);
+		assert_ne!(super::random(&other, 20, b"lottery"), lottery);
+	}
+}
@@ -0,0 +1,730 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::block_builder::BlockBuilder;
+use crate::chain::Chain;
+use crate::chain_spec::Json;
+use crate::codec::{self, Decode, Encode};
+use crate::consensus;
+use crate::crypto::AccountId32;
+use crate::finality::FinalityGadget;
+use crate::hashing::{Blake2b256, Hasher};
+use crate::metadata::RuntimeMetadata;
+use crate::storage::StorageBackend;
+use crate::tx_pool::TransactionPool;
+use crate::{block_inherents, system, types, Runtime, RuntimeApi, DEV_KEYSTORE};
+
+/// The request is not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// The request is JSON, but not a JSON-RPC request.
+const INVALID_REQUEST: i64 = -32600;
+/// There is no method with the name of the request.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The parameters of the request don't match the method.
+const INVALID_PARAMS: i64 = -32602;
+/// The server failed to handle a valid request.
+const INTERNAL_ERROR: i64 = -32603;
+/// The extrinsic submitted is refused by the transaction pool.
+const INVALID_TRANSACTION: i64 = 1010;
+
+/// The largest body of an HTTP request, or message of a WebSocket, which is handled.
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+/// The string appended to the key of a WebSocket handshake before it is hashed, see RFC 6455.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The error of a JSON-RPC request, with its code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+	pub code: i64,
+	pub message: String,
+}
+
+impl RpcError {
+	fn new(code: i64, message: impl fmt::Display) -> Self {
+		Self { code, message: message.to_string() }
+	}
+}
+
+/// A JSON-RPC 2.0 server for the chain, which external tools can query and submit extrinsics to,
+/// over HTTP or WebSocket.
+///
+/// The methods are:
+/// - `chain_getBlockHash([number])` - the hash of a block, of the best block by default.
+/// - `chain_getBlock([number])` - a block with its header and its encoded extrinsics.
+/// - `chain_getFinalizedHead()` - the hash of the last block finalized.
+/// - `state_getBalance(address)` - the free balance of an account.
+/// - `state_getClaimOwner(claim)` - the owner of the hash of a claimed content, if any.
+/// - `system_accountNextIndex(address)` - the nonce of the next extrinsic of an account, after the
+///   extrinsics it has waiting in the pool.
+/// - `author_submitExtrinsic(extrinsic)` - submit an encoded signed extrinsic to the transaction
+///   pool, returning its hash.
+/// - `engine_createBlock()` - author a block out of the pool, import it and finalize it, returning
+///   its number and hash.
+///
+/// Accounts are SS58 addresses, and hashes and encoded values are hexadecimal strings prefixed
+/// with `0x`.
+pub struct RpcServer<B> {
+	chain: Chain<B>,
+	pool: TransactionPool,
+	/// The votes of the authorities, whose development keys finalize the blocks created.
+	finality: FinalityGadget,
+}
+
+impl<B: StorageBackend> RpcServer<B>
+where
+	B::Error: fmt::Debug,
+{
+	/// Serve `chain`, with the extrinsics waiting in `pool`.
+	pub fn new(chain: Chain<B>, pool: TransactionPool) -> Self {
+		let finality = FinalityGadget::authorities(chain.finalized_runtime());
+		Self { chain, pool, finality }
+	}
+
+	/// Serve the connections accepted by `listener`, one after the other. A connection which fails
+	/// is dropped without stopping the server.
+	pub fn serve(&mut self, listener: TcpListener) -> io::Result<()> {
+		for stream in listener.incoming() {
+			if let Err(error) = self.serve_connection(stream?) {
+				eprintln!("RPC connection error: {error}");
+			}
+		}
+		Ok(())
+	}
+
+	/// Serve a single connection. An HTTP request is answered with the response to its body, and
+	/// the connection is closed. A WebSocket handshake upgrades the connection, whose text messages
+	/// are then answered until it is closed.
+	pub fn serve_connection(&mut self, stream: TcpStream) -> io::Result<()> {
+		let mut reader = BufReader::new(stream.try_clone()?);
+		let mut writer = stream;
+		let (method, headers) = read_http_head(&mut reader)?;
+		let header = |name: &str| headers.get(name).map(String::as_str).unwrap_or_default();
+
+		if header("upgrade").eq_ignore_ascii_case("websocket") {
+			let accept = websocket_accept(header("sec-websocket-key"));
+			write!(
+				writer,
+				"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+				 Sec-WebSocket-Accept: {accept}\r\n\r\n"
+			)?;
+			return self.serve_websocket(&mut reader, &mut writer)
+		}
+
+		if method != "POST" {
+			return write!(writer, "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+		}
+		let len = header("content-length")
+			.parse::<usize>()
+			.ok()
+			.filter(|len| *len <= MAX_MESSAGE_LEN)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid content length"))?;
+		let mut body = vec![0; len];
+		reader.read_exact(&mut body)?;
+		let response = self.handle(&String::from_utf8_lossy(&body));
+		write!(
+			writer,
+			"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			response.len(),
+			response
+		)
+	}
+
+	/// Answer the text messages of a WebSocket until it is closed. Messages may be split over
+	/// several frames.
+	fn serve_websocket(&mut self, reader: &mut impl Read, writer: &mut impl Write) -> io::Result<()> {
+		let mut message = Vec::new();
+		loop {
+			let (fin, opcode, payload) = read_frame(reader)?;
+			match opcode {
+				OPCODE_CONTINUATION | OPCODE_TEXT => {
+					message.extend_from_slice(&payload);
+					if message.len() > MAX_MESSAGE_LEN {
+						return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"))
+					}
+					if fin {
+						let response = self.handle(&String::from_utf8_lossy(&std::mem::take(&mut message)));
+						write_frame(writer, OPCODE_TEXT, response.as_bytes())?;
+					}
+				},
+				OPCODE_PING => write_frame(writer, OPCODE_PONG, &payload)?,
+				OPCODE_CLOSE => return write_frame(writer, OPCODE_CLOSE, &payload),
+				// Binary messages and pongs are ignored.
+				_ => {},
+			}
+		}
+	}
+
+	/// Handle a JSON-RPC request, and return its response.
+	pub fn handle(&mut self, request: &str) -> String {
+		let Ok(request) = Json::parse(request) else {
+			return response(Json::Null, Err(RpcError::new(PARSE_ERROR, "parse error")))
+		};
+		let Json::Object(mut request) = request else {
+			return response(Json::Null, Err(RpcError::new(INVALID_REQUEST, "the request must be an object")))
+		};
+		let id = request.remove("id").unwrap_or(Json::Null);
+		if request.remove("jsonrpc") != Some(Json::String("2.0".into())) {
+			return response(id, Err(RpcError::new(INVALID_REQUEST, "the request must be JSON-RPC 2.0")))
+		}
+		let Some(Json::String(method)) = request.remove("method") else {
+			return response(id, Err(RpcError::new(INVALID_REQUEST, "the method must be a string")))
+		};
+		let params = match request.remove("params") {
+			None => Vec::new(),
+			Some(Json::Array(params)) => params,
+			Some(_) => return response(id, Err(RpcError::new(INVALID_PARAMS, "the params must be an array"))),
+		};
+		response(id, self.call(&method, Params(params.into_iter())))
+	}
+
+	/// Call `method` with `params`.
+	fn call(&mut self, method: &str, mut params: Params) -> Result<Json, RpcError> {
+		let result = match method {
+			"chain_getBlockHash" => {
+				let block_number = params.block_number()?.unwrap_or(self.chain.best_block().0);
+				self.chain.canonical_hash(block_number).map_or(Json::Null, |hash| hex(&hash))
+			},
+			"chain_getBlock" => {
+				let block_number = params.block_number()?.unwrap_or(self.chain.best_block().0);
+				self.chain.canonical_block(block_number).map_or(Json::Null, |block| block_json(&block))
+			},
+			"chain_getFinalizedHead" => hex(&self.chain.finalized_head().1),
+			"state_getBalance" => Json::Number(self.chain.runtime().balance_of(&params.account()?)),
+			"state_getClaimOwner" => {
+				let claim = params.bytes()?;
+				let claim = system::Hash::try_from(claim).map_err(|_| RpcError::new(INVALID_PARAMS, "claims are 32 bytes"))?;
+				self.chain.runtime().claim_owner(&claim).map_or(Json::Null, |owner| address(&owner))
+			},
+			"state_getRuntimeVersion" => version_json(self.chain.runtime().system.version()),
+			"state_getMetadata" => metadata_json(&Runtime::metadata()),
+			"system_accountNextIndex" => {
+				let who = params.account()?;
+				let nonce = self.chain.runtime().nonce_of(&who) as u128 + self.pool.pending_of(&who) as u128;
+				Json::Number(nonce)
+			},
+			"author_submitExtrinsic" => {
+				let bytes = params.bytes()?;
+				// Calls nested deeper than `codec::MAX_CALL_DEPTH` are refused as an invalid encoding,
+				// so a client can't overflow the stack of the server with them.
+				let signed = types::SignedExtrinsic::decode_all(&bytes).map_err(|error| match error {
+					codec::Error::UnsupportedVersion =>
+						RpcError::new(INVALID_PARAMS, "the extrinsic is encoded for another transaction version"),
+					_ => RpcError::new(INVALID_PARAMS, "invalid extrinsic encoding"),
+				})?;
+				self.pool
+					.submit_extrinsic(self.chain.runtime(), signed)
+					.map_err(|error| RpcError::new(INVALID_TRANSACTION, error))?;
+				hex(&Blake2b256::hash(&bytes))
+			},
+			"engine_createBlock" => self.create_block()?,
+			_ => return Err(RpcError::new(METHOD_NOT_FOUND, format!("method not found: {method}"))),
+		};
+		params.finish()?;
+		Ok(result)
+	}
+
+	/// Author the next block out of the extrinsics waiting in the pool, seal it with the
+	/// development key of the authority whose turn it is, import it, then finalize it with the
+	/// votes of the authorities.
+	fn create_block(&mut self) -> Result<Json, RpcError> {
+		let runtime = self.chain.runtime();
+		let mut block = BlockBuilder::new(runtime, runtime.system.max_block_weight())
+			.with_inherents(block_inherents(runtime))
+			.build_from_pool(&mut self.pool)
+			.map_err(|error| RpcError::new(INTERNAL_ERROR, error))?;
+		DEV_KEYSTORE
+			.with(|keystore| consensus::seal_block(runtime, &mut block, keystore))
+			.map_err(|error| RpcError::new(INTERNAL_ERROR, error))?;
+		self.chain
+			.import_block(block.clone())
+			.map_err(|error| RpcError::new(INTERNAL_ERROR, error))?;
+		DEV_KEYSTORE
+			.with(|keystore| self.finality.vote_for_best_block(&mut self.chain, keystore))
+			.map_err(|error| RpcError::new(INTERNAL_ERROR, error))?;
+		Ok(object([("hash", hex(&block.header.hash())), ("number", Json::Number(block.header.block_number.into()))]))
+	}
+}
+
+/// The parameters of a request, taken in order by the method.
+struct Params(std::vec::IntoIter<Json>);
+
+impl Params {
+	/// An optional block number, which is `None` when it is null or left out.
+	fn block_number(&mut self) -> Result<Option<types::BlockNumber>, RpcError> {
+		match self.0.next() {
+			None | Some(Json::Null) => Ok(None),
+			Some(Json::Number(number)) => types::BlockNumber::try_from(number)
+				.map(Some)
+				.map_err(|_| RpcError::new(INVALID_PARAMS, "block number too large")),
+			Some(_) => Err(RpcError::new(INVALID_PARAMS, "block numbers must be numbers")),
+		}
+	}
+
+	fn string(&mut self) -> Result<String, RpcError> {
+		match self.0.next() {
+			Some(Json::String(string)) => Ok(string),
+			_ => Err(RpcError::new(INVALID_PARAMS, "expected a string")),
+		}
+	}
+
+	/// An account, given as an SS58 address with the prefix of the runtime.
+	fn account(&mut self) -> Result<types::AccountId, RpcError> {
+		let (account, prefix) = AccountId32::from_ss58check_with_prefix(&self.string()?)
+			.map_err(|_| RpcError::new(INVALID_PARAMS, "accounts must be SS58 addresses"))?;
+		if prefix != <Runtime as system::Config>::SS58_PREFIX {
+			return Err(RpcError::new(INVALID_PARAMS, "accounts must have the SS58 prefix of the chain"))
+		}
+		Ok(account)
+	}
+
+	/// Bytes, given as a hexadecimal string prefixed with `0x`.
+	fn bytes(&mut self) -> Result<Vec<u8>, RpcError> {
+		let string = self.string()?;
+		let digits = string
+			.strip_prefix("0x")
+			.filter(|digits| digits.len() % 2 == 0)
+			.ok_or_else(|| RpcError::new(INVALID_PARAMS, "expected hexadecimal prefixed with 0x"))?;
+		(0..digits.len())
+			.step_by(2)
+			.map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+			.collect::<Result<_, _>>()
+			.map_err(|_| RpcError::new(INVALID_PARAMS, "expected hexadecimal prefixed with 0x"))
+	}
+
+	/// Check that every parameter was used.
+	fn finish(mut self) -> Result<(), RpcError> {
+		match self.0.next() {
+			None => Ok(()),
+			Some(_) => Err(RpcError::new(INVALID_PARAMS, "too many params")),
+		}
+	}
+}
+
+/// The JSON-RPC response to the request `id`.
+fn response(id: Json, result: Result<Json, RpcError>) -> String {
+	match result {
+		Ok(result) => format!(r#"{{"jsonrpc":"2.0","result":{result},"id":{id}}}"#),
+		Err(RpcError { code, message }) => {
+			format!(r#"{{"jsonrpc":"2.0","error":{{"code":{code},"message":{}}},"id":{id}}}"#, Json::String(message))
+		},
+	}
+}
+
+fn object<const N: usize>(fields: [(&str, Json); N]) -> Json {
+	Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+fn hex(bytes: &[u8]) -> Json {
+	Json::String(bytes.iter().fold("0x".to_string(), |hex, byte| hex + &format!("{byte:02x}")))
+}
+
+fn address(who: &types::AccountId) -> Json {
+	Json::String(who.to_ss58check_with_prefix(<Runtime as system::Config>::SS58_PREFIX))
+}
+
+fn version_json(version: system::RuntimeVersion) -> Json {
+	object([
+		("specVersion", Json::Number(version.spec_version.into())),
+		("implVersion", Json::Number(version.impl_version.into())),
+		("transactionVersion", Json::Number(version.transaction_version.into())),
+	])
+}
+
+/// The metadata of the runtime, with the index, the calls and the errors of each pallet.
+fn metadata_json(metadata: &RuntimeMetadata) -> Json {
+	let string = |value: &str| Json::String(value.to_string());
+	let pallets = metadata.pallets.iter().map(|pallet| {
+		let calls = pallet.calls.iter().map(|call| {
+			let args = call.args.iter().map(|arg| object([("name", string(arg.name)), ("type", string(arg.ty))]));
+			object([
+				("name", string(call.name)),
+				("index", Json::Number(call.index.into())),
+				("args", Json::Array(args.collect())),
+			])
+		});
+		let errors = pallet.errors.iter().map(|error| {
+			let docs = error.docs.iter().map(|doc| string(doc));
+			object([("name", string(error.name)), ("docs", Json::Array(docs.collect()))])
+		});
+		object([
+			("name", string(pallet.name)),
+			("index", Json::Number(pallet.index.into())),
+			("calls", Json::Array(calls.collect())),
+			("errors", Json::Array(errors.collect())),
+		])
+	});
+	object([("version", version_json(metadata.version)), ("pallets", Json::Array(pallets.collect()))])
+}
+
+fn block_json(block: &types::Block) -> Json {
+	let header = &block.header;
+	object([
+		("hash", hex(&header.hash())),
+		("number", Json::Number(header.block_number.into())),
+		("parentHash", hex(&header.parent_hash)),
+		("stateRoot", hex(&header.state_root)),
+		("extrinsicsRoot", hex(&header.extrinsics_root)),
+		("digest", Json::Array(header.digest.iter().map(|item| hex(&item.encode())).collect())),
+		("inherents", Json::Array(block.inherents.iter().map(|call| hex(&call.encode())).collect())),
+		("extrinsics", Json::Array(block.extrinsics.iter().map(|signed| hex(&signed.encode())).collect())),
+	])
+}
+
+/// Read the request line and the headers of an HTTP request, returning its method and its headers
+/// keyed by their lowercase name.
+fn read_http_head(reader: &mut impl BufRead) -> io::Result<(String, BTreeMap<String, String>)> {
+	let mut line = String::new();
+	reader.read_line(&mut line)?;
+	let method = line.split_whitespace().next().unwrap_or_default().to_string();
+	let mut headers = BTreeMap::new();
+	loop {
+		line.clear();
+		if reader.read_line(&mut line)? == 0 {
+			return Err(io::ErrorKind::UnexpectedEof.into())
+		}
+		let header = line.trim_end();
+		if header.is_empty() {
+			return Ok((method, headers))
+		}
+		if let Some((name, value)) = header.split_once(':') {
+			headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+		}
+	}
+}
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// Read a WebSocket frame sent by a client, returning whether it is the last frame of its message,
+/// its opcode and its unmasked payload.
+fn read_frame(reader: &mut impl Read) -> io::Result<(bool, u8, Vec<u8>)> {
+	let mut head = [0; 2];
+	reader.read_exact(&mut head)?;
+	let (fin, opcode, masked) = (head[0] & 0x80 != 0, head[0] & 0x0f, head[1] & 0x80 != 0);
+	let len = match head[1] & 0x7f {
+		126 => {
+			let mut len = [0; 2];
+			reader.read_exact(&mut len)?;
+			u16::from_be_bytes(len) as u64
+		},
+		127 => {
+			let mut len = [0; 8];
+			reader.read_exact(&mut len)?;
+			u64::from_be_bytes(len)
+		},
+		len => len as u64,
+	};
+	// Clients must mask their frames.
+	if !masked || len > MAX_MESSAGE_LEN as u64 {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid WebSocket frame"))
+	}
+	let mut mask = [0; 4];
+	reader.read_exact(&mut mask)?;
+	let mut payload = vec![0; len as usize];
+	reader.read_exact(&mut payload)?;
+	for (i, byte) in payload.iter_mut().enumerate() {
+		*byte ^= mask[i % 4];
+	}
+	Ok((fin, opcode, payload))
+}
+
+/// Write a WebSocket frame holding a whole message. Frames sent by servers are not masked.
+fn write_frame(writer: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+	let mut frame = vec![0x80 | opcode];
+	match payload.len() {
+		len @ 0..=125 => frame.push(len as u8),
+		len @ 126..=0xffff => {
+			frame.push(126);
+			frame.extend_from_slice(&(len as u16).to_be_bytes());
+		},
+		len => {
+			frame.push(127);
+			frame.extend_from_slice(&(len as u64).to_be_bytes());
+		},
+	}
+	frame.extend_from_slice(payload);
+	writer.write_all(&frame)
+}
+
+/// The `Sec-WebSocket-Accept` answering the `Sec-WebSocket-Key` of a handshake.
+fn websocket_accept(key: &str) -> String {
+	base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()))
+}
+
+/// The SHA-1 hash of `data`, which the WebSocket handshake uses. It is not used for anything else,
+/// since SHA-1 is broken.
+fn sha1(data: &[u8]) -> [u8; 20] {
+	let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+	let mut message = data.to_vec();
+	message.push(0x80);
+	while message.len() % 64 != 56 {
+		message.push(0);
+	}
+	message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+	for block in message.chunks(64) {
+		let mut words = [0u32; 80];
+		for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+			*word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+		}
+		for i in 16..80 {
+			words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+		}
+		let [mut a, mut b, mut c, mut d, mut e] = state;
+		for (i, word) in words.iter().enumerate() {
+			let (f, k) = match i {
+				0..=19 => ((b & c) | (!b & d), 0x5a827999),
+				20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+				40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+				_ => (b ^ c ^ d, 0xca62c1d6),
+			};
+			let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+			(a, b, c, d, e) = (temp, a, b.rotate_left(30), c, d);
+		}
+		for (word, value) in state.iter_mut().zip([a, b, c, d, e]) {
+			*word = word.wrapping_add(value);
+		}
+	}
+
+	let mut digest = [0; 20];
+	for (bytes, word) in digest.chunks_mut(4).zip(state) {
+		bytes.copy_from_slice(&word.to_be_bytes());
+	}
+	digest
+}
+
+/// Encode `bytes` in standard base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+	let mut encoded = String::new();
+	for chunk in bytes.chunks(3) {
+		let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+		for i in 0..4 {
+			if i <= chunk.len() {
+				encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+			} else {
+				encoded.push('=');
+			}
+		}
+	}
+	encoded
+}
+
+#[cfg(test)]
+mod test {
+	use super::{base64, sha1, websocket_accept, RpcServer};
+	use crate::chain::Chain;
+	use crate::chain_spec::Json;
+	use crate::chain_store::ChainStore;
+	use crate::codec::Encode;
+	use crate::storage::InMemoryBackend;
+	use crate::tx_pool::TransactionPool;
+	use crate::{balances, dev_account, development_genesis, types, Runtime, RuntimeCall};
+	use std::io::{Read, Write};
+	use std::net::{TcpListener, TcpStream};
+
+	fn new_server() -> RpcServer<InMemoryBackend> {
+		let mut runtime = Runtime::new();
+		development_genesis().build(&mut runtime).expect("invalid genesis");
+		let chain = Chain::new(runtime, ChainStore::new(InMemoryBackend::new())).expect("empty store");
+		RpcServer::new(chain, TransactionPool::new())
+	}
+
+	fn request(method: &str, params: &str) -> String {
+		format!(r#"{{"jsonrpc":"2.0","id":7,"method":"{method}","params":{params}}}"#)
+	}
+
+	/// Call `method` with `params` on `server`, and return the result of the response.
+	fn call(server: &mut RpcServer<InMemoryBackend>, method: &str, params: &str) -> Json {
+		let response = server.handle(&request(method, params));
+		let Ok(Json::Object(mut response)) = Json::parse(&response) else { panic!("not a result: {response}") };
+		assert_eq!(response.remove("id"), Some(Json::Number(7)));
+		response.remove("result").expect("no result")
+	}
+
+	/// Call `method` with `params` on `server`, and return the code and the message of the error of
+	/// the response. Error codes are negative, which `Json` doesn't parse, so they are read from the
+	/// response itself.
+	fn call_error(server: &mut RpcServer<InMemoryBackend>, method: &str, params: &str) -> (i64, String) {
+		error_of(&server.handle(&request(method, params)))
+	}
+
+	fn error_of(response: &str) -> (i64, String) {
+		let error = response.strip_prefix(r#"{"jsonrpc":"2.0","error":{"code":"#).expect("not an error");
+		let (code, rest) = error.split_once(r#","message":"#).unwrap();
+		let message = &rest[..rest.rfind(r#"},"id":"#).unwrap()];
+		let Ok(Json::String(message)) = Json::parse(message) else { panic!("invalid message: {message}") };
+		(code.parse().unwrap(), message)
+	}
+
+	fn hex_string(bytes: &[u8]) -> String {
+		bytes.iter().fold("0x".to_string(), |hex, byte| hex + &format!("{byte:02x}"))
+	}
+
+	fn transfer(server: &RpcServer<InMemoryBackend>, to: &str, amount: types::Balance, nonce: types::Nonce) -> String {
+		let extrinsic = types::ExtrinsicBuilder::new(dev_account("alice"))
+			.call(RuntimeCall::balances(balances::Call::transfer { to: dev_account(to), amount }))
+			.build();
+		hex_string(&server.chain.runtime().sign(extrinsic, nonce).encode())
+	}
+
+	#[test]
+	fn websocket_handshake_hashes_the_key() {
+		assert_eq!(hex_string(&sha1(b"abc")), "0xa9993e364706816aba3e25717850c26c9cd0d89d");
+		assert_eq!(hex_string(&sha1(&[b'a'; 64])), "0x0098ba824b5c16427bd7a1122a5a442a25ec644d");
+		assert_eq!(base64(b"foob"), "Zm9vYg==");
+		assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+		assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+		// The example of RFC 6455.
+		assert_eq!(websocket_accept("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+	}
+
+	#[test]
+	fn rpc_methods_drive_the_chain() {
+		let mut server = new_server();
+		let alice = format!(r#"["{}"]"#, dev_account("alice"));
+		let bob = format!(r#"["{}"]"#, dev_account("bob"));
+
+		for nonce in 0..2 {
+			let extrinsic = transfer(&server, "bob", 10, nonce);
+			assert!(matches!(call(&mut server, "author_submitExtrinsic", &format!(r#"["{extrinsic}"]"#)), Json::String(_)));
+		}
+		// The next nonce of Alice follows her extrinsics waiting in the pool.
+		assert_eq!(call(&mut server, "system_accountNextIndex", &alice), Json::Number(2));
+		assert_eq!(call(&mut server, "state_getBalance", &bob), Json::Number(0));
+
+		let genesis_hash = call(&mut server, "chain_getBlockHash", "[0]");
+		assert_eq!(call(&mut server, "chain_getFinalizedHead", "[]"), genesis_hash);
+		let Json::Object(created) = call(&mut server, "engine_createBlock", "[]") else { panic!("no block created") };
+		assert_eq!(created["number"], Json::Number(1));
+		// The block is finalized by the development keys of the authorities as soon as it is created.
+		assert_eq!(call(&mut server, "chain_getFinalizedHead", "[]"), created["hash"]);
+		assert_eq!(call(&mut server, "state_getBalance", &bob), Json::Number(20));
+		assert_eq!(call(&mut server, "system_accountNextIndex", &alice), Json::Number(2));
+
+		// The best block is returned by default.
+		assert_eq!(call(&mut server, "chain_getBlockHash", "[]"), created["hash"]);
+		assert_eq!(call(&mut server, "chain_getBlockHash", "[1]"), created["hash"]);
+		let Json::Object(block) = call(&mut server, "chain_getBlock", "[]") else { panic!("no block") };
+		assert_eq!(block["hash"], created["hash"]);
+		let extrinsics = [0, 1].map(|nonce| Json::String(transfer(&server, "bob", 10, nonce)));
+		assert_eq!(block["extrinsics"], Json::Array(extrinsics.to_vec()));
+		assert_eq!(call(&mut server, "chain_getBlock", "[2]"), Json::Null);
+
+		let claim = format!(r#"["{}"]"#, hex_string(&[0; 32]));
+		assert_eq!(call(&mut server, "state_getClaimOwner", &claim), Json::Null);
+
+		let Json::Object(version) = call(&mut server, "state_getRuntimeVersion", "[]") else { panic!("no version") };
+		assert_eq!(version["specVersion"], Json::Number(1));
+		assert_eq!(version["transactionVersion"], Json::Number(1));
+
+		let Json::Object(metadata) = call(&mut server, "state_getMetadata", "[]") else { panic!("no metadata") };
+		let Json::Array(pallets) = &metadata["pallets"] else { panic!("no pallets") };
+		let Json::Object(balances) = &pallets[0] else { panic!("no balances pallet") };
+		assert_eq!(balances["name"], Json::String("balances".into()));
+		let Json::Array(calls) = &balances["calls"] else { panic!("no calls") };
+		let Json::Object(transfer) = &calls[0] else { panic!("no transfer") };
+		assert_eq!(transfer["name"], Json::String("transfer".into()));
+		assert_eq!(
+			transfer["args"],
+			Json::parse(r#"[{"name":"to","type":"T::AccountId"},{"name":"amount","type":"T::Balance"}]"#).unwrap()
+		);
+	}
+
+	#[test]
+	fn invalid_requests_are_answered_with_errors() {
+		let mut server = new_server();
+		let code = |response: String| error_of(&response).0;
+
+		assert_eq!(code(server.handle("{")), -32700);
+		assert_eq!(code(server.handle(r#"{"id":1,"method":"chain_getBlock"}"#)), -32600);
+		assert_eq!(call_error(&mut server, "chain_getStorage", "[]").0, -32601);
+		assert_eq!(call_error(&mut server, "chain_getBlock", r#"["one"]"#).0, -32602);
+		assert_eq!(call_error(&mut server, "chain_getBlock", "[1, 2]").0, -32602);
+		assert_eq!(call_error(&mut server, "state_getBalance", r#"["alice"]"#).0, -32602);
+		assert_eq!(call_error(&mut server, "author_submitExtrinsic", r#"["0x00"]"#).0, -32602);
+
+		// Extrinsics encoded for another transaction version are refused, the version following
+		// the 32 bytes of the caller.
+		let mut outdated = transfer(&server, "bob", 10, 0);
+		outdated.replace_range(2 + 64..2 + 66, "02");
+		assert_eq!(
+			call_error(&mut server, "author_submitExtrinsic", &format!(r#"["{outdated}"]"#)),
+			(-32602, "the extrinsic is encoded for another transaction version".to_string())
+		);
+
+		// An extrinsic whose call is wrapped in 200_000 sudo calls is refused, after the caller and
+		// the transaction version, without overflowing the stack of the server.
+		let mut nested = transfer(&server, "bob", 10, 0);
+		nested.insert_str(2 + 72, &"0e00".repeat(200_000));
+		assert_eq!(
+			call_error(&mut server, "author_submitExtrinsic", &format!(r#"["{nested}"]"#)),
+			(-32602, "invalid extrinsic encoding".to_string())
+		);
+
+		// The pool refuses extrinsics whose nonce doesn't follow the nonce of their caller.
+		let future = transfer(&server, "bob", 10, 1);
+		assert_eq!(
+			call_error(&mut server, "author_submitExtrinsic", &format!(r#"["{future}"]"#)),
+			(1010, "future nonce".to_string())
+		);
+		assert!(server.pool.is_empty());
+	}
+
+	#[test]
+	fn serve_requests_over_http_and_websocket() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address = listener.local_addr().unwrap();
+		let server = std::thread::spawn(move || {
+			let mut server = new_server();
+			for _ in 0..2 {
+				let (stream, _) = listener.accept().unwrap();
+				server.serve_connection(stream).unwrap();
+			}
+		});
+		let request = r#"{"jsonrpc":"2.0","id":1,"method":"chain_getBlock","params":[]}"#;
+		let expected = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+
+		let mut http = TcpStream::connect(address).unwrap();
+		write!(http, "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{request}", request.len()).unwrap();
+		let mut response = String::new();
+		http.read_to_string(&mut response).unwrap();
+		assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+		assert!(response.ends_with(expected), "{response}");
+
+		let mut websocket = TcpStream::connect(address).unwrap();
+		write!(
+			websocket,
+			"GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+			 Sec-WebSocket-Version: 13\r\n\r\n"
+		)
+		.unwrap();
+		// The request is sent as a masked text frame, split in two.
+		let mask = [1, 2, 3, 4];
+		let (first, second) = request.as_bytes().split_at(10);
+		for (head, part) in [(0x01, first), (0x80, second)] {
+			let mut frame = vec![head, 0x80 | part.len() as u8];
+			frame.extend_from_slice(&mask);
+			frame.extend(part.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+			websocket.write_all(&frame).unwrap();
+		}
+		websocket.write_all(&[0x88, 0x80, 0, 0, 0, 0]).unwrap();
+
+		let mut response = Vec::new();
+		websocket.read_to_end(&mut response).unwrap();
+		let handshake = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+			Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n";
+		assert!(response.starts_with(handshake));
+		let frames = &response[handshake.len()..];
+		assert_eq!(&frames[..2], &[0x81, expected.len() as u8]);
+		assert_eq!(&frames[2..2 + expected.len()], expected.as_bytes());
+		assert_eq!(&frames[2 + expected.len()..], &[0x88, 0]);
+		server.join().unwrap();
+	}
+}
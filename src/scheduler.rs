@@ -0,0 +1,414 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::codec::Encode;
+use crate::storage::{storage_map_key, StorageBackend};
+use crate::support::{
+	DispatchError, DispatchResult, Events, Hooks, OriginFor, RawOrigin, StorageMap, StorageValue, Weight,
+};
+
+pub trait Config: crate::system::Config {
+	/// The calls which can be scheduled, which is usually the `RuntimeCall` generated by
+	/// `#[macros::runtime]`.
+	type RuntimeCall: Clone;
+	/// The maximum number of tasks which can be scheduled for the same block, including the tasks
+	/// which were canceled.
+	const MAX_SCHEDULED_PER_BLOCK: u32;
+	/// The weight of `call`. Scheduled calls are dispatched without an extrinsic, so their weight
+	/// is paid for when they are scheduled.
+	fn call_weight(call: &Self::RuntimeCall) -> Weight;
+}
+
+/// The block a task is scheduled for, and the index of the task in the agenda of that block.
+pub type TaskAddress<BlockNumber> = (BlockNumber, u32);
+
+/// A call scheduled to be dispatched at the start of a future block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task<AccountId, BlockNumber, Call> {
+	/// Where the task is in the agenda.
+	pub address: TaskAddress<BlockNumber>,
+	/// The name of the task, if it was scheduled with `schedule_named`.
+	pub id: Option<String>,
+	/// The origin the call is dispatched with. This is the signed origin of the account which
+	/// scheduled the task, unless another pallet scheduled it, see `schedule_with_origin`.
+	pub origin: RawOrigin<AccountId>,
+	/// The call to dispatch.
+	pub call: Box<Call>,
+}
+
+/// The tasks of the scheduler pallet for the runtime `T`.
+pub type TaskOf<T> = Task<
+	<T as crate::system::Config>::AccountId,
+	<T as crate::system::Config>::BlockNumber,
+	<T as Config>::RuntimeCall,
+>;
+
+/// The events emitted by the scheduler pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, BlockNumber> {
+	/// A task was scheduled, to be dispatched with `origin`.
+	Scheduled { task: TaskAddress<BlockNumber>, id: Option<String>, origin: RawOrigin<AccountId> },
+	/// A task was canceled before it was due.
+	Canceled { task: TaskAddress<BlockNumber>, id: Option<String> },
+	/// The call of a task was dispatched, with its result.
+	Dispatched { task: TaskAddress<BlockNumber>, id: Option<String>, result: DispatchResult },
+}
+
+/// The errors returned by the scheduler pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// Tasks can only be scheduled for a block after the current block.
+	TargetBlockNumberInPast,
+	/// The agenda of the block is full.
+	AgendaFull,
+	/// No task is scheduled at this address or under this name.
+	TaskDoesNotExist,
+	/// The caller is not the account which scheduled the task.
+	NotTaskOwner,
+	/// The caller already has a task with this name.
+	TaskNameTaken,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::TargetBlockNumberInPast => "TargetBlockNumberInPast",
+			Error::AgendaFull => "AgendaFull",
+			Error::TaskDoesNotExist => "TaskDoesNotExist",
+			Error::NotTaskOwner => "NotTaskOwner",
+			Error::TaskNameTaken => "TaskNameTaken",
+		};
+		DispatchError::Module { pallet: "scheduler", error }
+	}
+}
+
+/// The events of the scheduler pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> =
+	Event<<T as crate::system::Config>::AccountId, <T as crate::system::Config>::BlockNumber>;
+
+/// This is the Scheduler Module.
+/// It allows accounts to schedule calls, which are dispatched on their behalf at the start of a
+/// future block, like a transfer or the revocation of a claim.
+///
+/// The pallet only keeps the agenda: the runtime dispatches the calls which are due, see
+/// `#[scheduler]` in `#[macros::runtime]`.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The tasks scheduled for each block, in the order they were scheduled. A canceled task leaves
+	/// an empty slot, so the address of the other tasks doesn't change.
+	#[allow(clippy::type_complexity)]
+	agenda: StorageMap<T::BlockNumber, Vec<Option<Task<T::AccountId, T::BlockNumber, T::RuntimeCall>>>>,
+	/// The address of each named task, keyed by the account which scheduled it and its name.
+	lookup: StorageMap<(T::AccountId, String), TaskAddress<T::BlockNumber>>,
+	/// The tasks which are due, taken out of the agenda by `on_initialize`, until the runtime
+	/// dispatches them.
+	#[allow(clippy::type_complexity)]
+	due: StorageValue<Vec<Task<T::AccountId, T::BlockNumber, T::RuntimeCall>>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId, T::BlockNumber>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Schedule `call` to be dispatched at the start of block `when`, with the signed origin of the
+	/// `caller`. Returns the index of the task in the agenda of `when`, which is needed to cancel
+	/// it.
+	/// This function will return an error if `when` is not after the current block, or if the
+	/// agenda of `when` is full.
+	#[call_index = 0]
+	#[weight = 10_000u64.saturating_add(T::call_weight(call))]
+	pub fn schedule(
+		&mut self,
+		caller: T::AccountId,
+		when: T::BlockNumber,
+		call: Box<T::RuntimeCall>,
+		system: &mut crate::system::Pallet<T>,
+	) -> Result<u32, DispatchError> {
+		let (_, index) = self.do_schedule(RawOrigin::Signed(caller), None, when, call, system.block_number())?;
+		Ok(index)
+	}
+
+	/// Cancel the task at `index` in the agenda of `when`, before it is dispatched.
+	/// This function will return an error if there is no such task, or if the caller didn't
+	/// schedule it.
+	#[call_index = 1]
+	#[weight = 5_000]
+	pub fn cancel(&mut self, caller: T::AccountId, when: T::BlockNumber, index: u32) -> DispatchResult {
+		self.do_cancel(caller, (when, index))
+	}
+
+	/// Schedule `call` like `schedule`, under the name `id`, so the `caller` can cancel it by name.
+	/// The name can be used again once the task is dispatched or canceled.
+	/// This function will also return an error if the caller already has a task with that name.
+	#[call_index = 2]
+	#[weight = 10_000u64.saturating_add(T::call_weight(call))]
+	pub fn schedule_named(
+		&mut self,
+		caller: T::AccountId,
+		id: String,
+		when: T::BlockNumber,
+		call: Box<T::RuntimeCall>,
+		system: &mut crate::system::Pallet<T>,
+	) -> DispatchResult {
+		if self.lookup.contains_key(&(caller.clone(), id.clone())) {
+			return Err(Error::TaskNameTaken.into());
+		}
+		let origin = RawOrigin::Signed(caller.clone());
+		let address = self.do_schedule(origin, Some(id.clone()), when, call, system.block_number())?;
+		self.lookup.insert((caller, id), address);
+		Ok(())
+	}
+
+	/// Cancel the task the `caller` scheduled under the name `id`, before it is dispatched.
+	/// This function will return an error if the caller has no task with that name.
+	#[call_index = 3]
+	#[weight = 5_000]
+	pub fn cancel_named(&mut self, caller: T::AccountId, id: String) -> DispatchResult {
+		let address = *self.lookup.get(&(caller.clone(), id)).ok_or(Error::TaskDoesNotExist)?;
+		self.do_cancel(caller, address)
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Scheduler Module.
+	pub fn new() -> Self {
+		Self {
+			agenda: StorageMap::new(),
+			lookup: StorageMap::new(),
+			due: StorageValue::new(Vec::new()),
+			events: Events::new(),
+		}
+	}
+
+	/// Get the task (if any) scheduled at `address`.
+	pub fn task(&self, address: TaskAddress<T::BlockNumber>) -> Option<&TaskOf<T>> {
+		let (when, index) = address;
+		self.agenda.get(&when)?.get(index as usize)?.as_ref()
+	}
+
+	/// Get the address (if any) of the task `who` scheduled under the name `id`.
+	pub fn named_task(&self, who: &T::AccountId, id: &str) -> Option<TaskAddress<T::BlockNumber>> {
+		self.lookup.get(&(who.clone(), String::from(id))).copied()
+	}
+
+	/// Take the tasks which are due, so the runtime can dispatch them. They were taken out of the
+	/// agenda by `on_initialize`.
+	pub fn take_due(&mut self) -> Vec<TaskOf<T>> {
+		core::mem::take(self.due.get_mut())
+	}
+
+	/// Record the `result` of the call of the task at `address`, which was dispatched by the
+	/// runtime.
+	pub fn note_dispatched(
+		&mut self,
+		address: TaskAddress<T::BlockNumber>,
+		id: Option<String>,
+		result: DispatchResult,
+	) {
+		self.deposit_event(Event::Dispatched { task: address, id, result });
+	}
+
+	/// Schedule `call` to be dispatched at the start of block `when` with `origin`, like the root
+	/// origin. `now` is the current block, which `when` must come after.
+	/// This is how other pallets schedule calls, like the enactment of a referendum, so it is not a
+	/// call of the pallet. The task has no name and can't be canceled.
+	pub fn schedule_with_origin(
+		&mut self,
+		origin: OriginFor<T>,
+		when: T::BlockNumber,
+		call: Box<T::RuntimeCall>,
+		now: T::BlockNumber,
+	) -> Result<TaskAddress<T::BlockNumber>, DispatchError> {
+		self.do_schedule(origin, None, when, call, now)
+	}
+
+	/// Add a task for `call` at the end of the agenda of `when`, to be dispatched with `origin`.
+	/// `now` is the current block, which `when` must come after.
+	fn do_schedule(
+		&mut self,
+		origin: OriginFor<T>,
+		id: Option<String>,
+		when: T::BlockNumber,
+		call: Box<T::RuntimeCall>,
+		now: T::BlockNumber,
+	) -> Result<TaskAddress<T::BlockNumber>, DispatchError> {
+		if when <= now {
+			return Err(Error::TargetBlockNumberInPast.into());
+		}
+		let scheduled = self.agenda.get(&when).map_or(0, Vec::len);
+		if scheduled >= T::MAX_SCHEDULED_PER_BLOCK as usize {
+			return Err(Error::AgendaFull.into());
+		}
+
+		let address = (when, scheduled as u32);
+		let task = Task { address, id: id.clone(), origin: origin.clone(), call };
+		self.agenda.entry(when).or_default().push(Some(task));
+		self.deposit_event(Event::Scheduled { task: address, id, origin });
+		Ok(address)
+	}
+
+	/// Cancel the task at `address`, which must have been scheduled by the `caller`.
+	fn do_cancel(&mut self, caller: T::AccountId, address: TaskAddress<T::BlockNumber>) -> DispatchResult {
+		let (when, index) = address;
+		let slot = self
+			.agenda
+			.get_mut(&when)
+			.and_then(|agenda| agenda.get_mut(index as usize))
+			.ok_or(Error::TaskDoesNotExist)?;
+		match slot {
+			Some(task) if task.origin != RawOrigin::Signed(caller.clone()) =>
+				return Err(Error::NotTaskOwner.into()),
+			Some(_) => {},
+			None => return Err(Error::TaskDoesNotExist.into()),
+		}
+
+		let task = slot.take().expect("the slot was just checked");
+		if let Some(id) = &task.id {
+			self.lookup.remove(&(caller, id.clone()));
+		}
+		self.deposit_event(Event::Canceled { task: address, id: task.id });
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	/// Take the tasks scheduled up to `block_number` out of the agenda, so the runtime dispatches
+	/// them after the hooks, see `take_due`. The names of those tasks can be used again.
+	fn on_initialize(&mut self, block_number: T::BlockNumber) {
+		while let Some(when) = self.agenda.keys().next().copied() {
+			if when > block_number {
+				break;
+			}
+			let agenda = self.agenda.remove(&when).unwrap_or_default();
+			for task in agenda.into_iter().flatten() {
+				if let (Some(id), RawOrigin::Signed(who)) = (&task.id, &task.origin) {
+					self.lookup.remove(&(who.clone(), id.clone()));
+				}
+				self.due.get_mut().push(task);
+			}
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::BlockNumber: Encode,
+	T::RuntimeCall: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The address of
+	/// the named tasks follows from the agenda, and the tasks which are due are dispatched before
+	/// the state is committed to, so they are left out with the phase and the events.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for (when, agenda) in &self.agenda {
+			let tasks = agenda
+				.iter()
+				.map(|slot| slot.as_ref().map(|task| (&task.id, (&task.origin, &task.call))))
+				.collect::<Vec<_>>();
+			state.insert(storage_map_key("scheduler", "agenda", when), tasks.encode());
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, Event};
+	use crate::support::{EventRecord, Hooks, Phase, RawOrigin};
+	use crate::system;
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		type RuntimeCall = &'static str;
+		const MAX_SCHEDULED_PER_BLOCK: u32 = 2;
+		fn call_weight(_call: &Self::RuntimeCall) -> crate::support::Weight {
+			1_000
+		}
+	}
+
+	fn setup() -> (super::Pallet<TestConfig>, system::Pallet<TestConfig>) {
+		let mut system = system::Pallet::<TestConfig>::new();
+		system.inc_block_number().unwrap();
+		(super::Pallet::new(), system)
+	}
+
+	#[test]
+	fn tasks_are_due_at_their_block() {
+		let (mut scheduler, mut system) = setup();
+		let call = || Box::new("transfer");
+
+		assert_eq!(
+			scheduler.schedule("alice", 1, call(), &mut system),
+			Err(Error::TargetBlockNumberInPast.into())
+		);
+		assert_eq!(scheduler.schedule("alice", 3, call(), &mut system), Ok(0));
+		assert_eq!(scheduler.schedule("bob", 3, call(), &mut system), Ok(1));
+		assert_eq!(scheduler.schedule("bob", 3, call(), &mut system), Err(Error::AgendaFull.into()));
+		assert_eq!(scheduler.schedule("bob", 4, call(), &mut system), Ok(0));
+		assert_eq!(scheduler.task((3, 1)).map(|task| &task.origin), Some(&RawOrigin::Signed("bob")));
+		// The weight of scheduling includes the weight of the scheduled call.
+		assert_eq!(super::Call::<TestConfig>::schedule { when: 3, call: call() }.weight(), 11_000);
+
+		scheduler.on_initialize(2);
+		assert!(scheduler.take_due().is_empty());
+		scheduler.on_initialize(3);
+		let due = scheduler.take_due();
+		let due = due.iter().map(|task| (task.address, task.origin.clone())).collect::<Vec<_>>();
+		assert_eq!(due, [((3, 0), RawOrigin::Signed("alice")), ((3, 1), RawOrigin::Signed("bob"))]);
+		assert!(scheduler.take_due().is_empty());
+		assert_eq!(scheduler.task((3, 0)), None);
+		assert!(scheduler.task((4, 0)).is_some());
+
+		scheduler.set_phase(Phase::Initialization);
+		scheduler.note_dispatched((3, 0), None, Ok(()));
+		let dispatched = Event::Dispatched { task: (3, 0), id: None, result: Ok(()) };
+		assert_eq!(
+			scheduler.take_events().last(),
+			Some(&EventRecord { phase: Phase::Initialization, event: dispatched })
+		);
+	}
+
+	#[test]
+	fn tasks_can_be_canceled_by_their_owner() {
+		let (mut scheduler, mut system) = setup();
+		let call = || Box::new("revoke_claim");
+
+		assert_eq!(scheduler.schedule("alice", 3, call(), &mut system), Ok(0));
+		assert_eq!(scheduler.schedule("alice", 3, call(), &mut system), Ok(1));
+		assert_eq!(scheduler.cancel("bob", 3, 0), Err(Error::NotTaskOwner.into()));
+		assert_eq!(scheduler.cancel("alice", 3, 0), Ok(()));
+		assert_eq!(scheduler.cancel("alice", 3, 0), Err(Error::TaskDoesNotExist.into()));
+		// The slot of a canceled task is not reused, so the address of the next task is kept.
+		assert_eq!(scheduler.task((3, 1)).map(|task| &task.origin), Some(&RawOrigin::Signed("alice")));
+		assert_eq!(scheduler.schedule("alice", 3, call(), &mut system), Err(Error::AgendaFull.into()));
+
+		let id = || "revoke".to_string();
+		assert_eq!(scheduler.schedule_named("alice", id(), 4, call(), &mut system), Ok(()));
+		assert_eq!(
+			scheduler.schedule_named("alice", id(), 5, call(), &mut system),
+			Err(Error::TaskNameTaken.into())
+		);
+		// Names are per account.
+		assert_eq!(scheduler.schedule_named("bob", id(), 4, call(), &mut system), Ok(()));
+		assert_eq!(scheduler.named_task(&"alice", "revoke"), Some((4, 0)));
+		assert_eq!(scheduler.cancel_named("alice", id()), Ok(()));
+		assert_eq!(scheduler.cancel_named("alice", id()), Err(Error::TaskDoesNotExist.into()));
+		assert_eq!(scheduler.named_task(&"alice", "revoke"), None);
+		assert_eq!(scheduler.schedule_named("alice", id(), 5, call(), &mut system), Ok(()));
+
+		// Once a named task is due, its name can be used again.
+		scheduler.on_initialize(4);
+		assert_eq!(scheduler.take_due().len(), 2);
+		assert_eq!(scheduler.named_task(&"bob", "revoke"), None);
+		assert_eq!(scheduler.schedule_named("bob", id(), 6, call(), &mut system), Ok(()));
+	}
+}
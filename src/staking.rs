@@ -0,0 +1,414 @@
+use alloc::vec::Vec;
+
+use num::traits::{CheckedAdd, Saturating, Zero};
+
+use crate::balances::{LockIdentifier, WithdrawReasons};
+use crate::codec::Encode;
+use crate::storage::{storage_map_key, StorageBackend};
+use crate::support::{
+	ensure_root, ArithmeticError, DispatchError, DispatchResult, Events, HooksWithContext, OriginFor,
+	StorageMap,
+};
+
+/// The identifier of the lock which holds the bonded funds of an account in the balances pallet.
+pub const STAKING_ID: LockIdentifier = *b"staking ";
+
+pub trait Config: crate::balances::Config {
+	/// The number of blocks unbonded funds stay locked before they are released.
+	const BONDING_DURATION: Self::BlockNumber;
+	/// The maximum number of chunks an account can be unbonding at the same time.
+	const MAX_UNLOCKING_CHUNKS: u32;
+}
+
+/// Funds which were unbonded, and are released at the end of `unlock_block`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnlockChunk<Balance, BlockNumber> {
+	/// The amount which is released.
+	pub value: Balance,
+	/// The block at the end of which the funds are released.
+	pub unlock_block: BlockNumber,
+}
+
+/// The funds an account bonded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakingLedger<Balance, BlockNumber> {
+	/// The amount locked in the balances pallet, which is the active amount and the amount which
+	/// is unlocking.
+	pub total: Balance,
+	/// The amount which is bonded, and not unlocking.
+	pub active: Balance,
+	/// The funds which are unlocking, oldest first.
+	pub unlocking: Vec<UnlockChunk<Balance, BlockNumber>>,
+}
+
+/// The events emitted by the staking pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, Balance> {
+	/// Funds were bonded by an account.
+	Bonded { who: AccountId, amount: Balance },
+	/// Funds were unbonded by an account, and start unlocking.
+	Unbonded { who: AccountId, amount: Balance },
+	/// Unlocked funds were released from the lock of an account.
+	Withdrawn { who: AccountId, amount: Balance },
+	/// The bonded funds of an account were slashed, and the amount was burnt.
+	Slashed { who: AccountId, amount: Balance },
+}
+
+/// The errors returned by the staking pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The account has no bonded funds.
+	NotBonded,
+	/// The free balance of the account doesn't cover what it bonded and the new amount.
+	InsufficientBalance,
+	/// The account has less active funds than the amount to unbond.
+	InsufficientBond,
+	/// The account is already unbonding `Config::MAX_UNLOCKING_CHUNKS` chunks.
+	NoMoreChunks,
+	/// None of the chunks of the account are unlocked yet.
+	NothingToWithdraw,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::NotBonded => "NotBonded",
+			Error::InsufficientBalance => "InsufficientBalance",
+			Error::InsufficientBond => "InsufficientBond",
+			Error::NoMoreChunks => "NoMoreChunks",
+			Error::NothingToWithdraw => "NothingToWithdraw",
+		};
+		DispatchError::Module { pallet: "staking", error }
+	}
+}
+
+/// The events of the staking pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> =
+	Event<<T as crate::system::Config>::AccountId, <T as crate::balances::Config>::Balance>;
+
+/// This is the Staking Module.
+/// It allows accounts to bond some of their funds, which are locked in the balances pallet until
+/// they are unbonded and the bonding duration has passed. Bonded funds can be slashed as a penalty.
+///
+/// The lock only forbids transfers, so an account which bonded all its funds can still pay the fees
+/// of its extrinsics, like to unbond.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The funds bonded by each account.
+	ledgers: StorageMap<T::AccountId, StakingLedger<T::Balance, T::BlockNumber>>,
+	/// The block being executed, which unbonding starts from.
+	block_number: T::BlockNumber,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId, T::Balance>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Bond `amount` more of the free balance of the `caller`, which is locked until it is
+	/// unbonded.
+	/// This function will return an error if the free balance of the caller doesn't cover the
+	/// funds they already bonded and `amount`.
+	#[call_index = 0]
+	#[weight = 10_000]
+	pub fn bond(
+		&mut self,
+		caller: T::AccountId,
+		amount: T::Balance,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		let ledger = self.ledgers.get(&caller);
+		let total = ledger.map_or(T::Balance::zero(), |ledger| ledger.total);
+		let new_total = total.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+		if balances.balance(&caller) < new_total {
+			return Err(Error::InsufficientBalance.into());
+		}
+
+		let active = ledger.map_or(T::Balance::zero(), |ledger| ledger.active).saturating_add(amount);
+		let ledger = self.ledgers.entry(caller.clone()).or_insert_with(|| StakingLedger {
+			total: T::Balance::zero(),
+			active: T::Balance::zero(),
+			unlocking: Vec::new(),
+		});
+		ledger.total = new_total;
+		ledger.active = active;
+		balances.set_lock(STAKING_ID, &caller, new_total, WithdrawReasons::TRANSFER);
+		self.deposit_event(Event::Bonded { who: caller, amount });
+		Ok(())
+	}
+
+	/// Unbond `amount` of the active funds of the `caller`. The funds stay locked for
+	/// `Config::BONDING_DURATION` blocks, and can still be slashed until they are released.
+	/// This function will return an error if the caller has less active funds than `amount`, or if
+	/// they are already unbonding too many chunks.
+	#[call_index = 1]
+	#[weight = 10_000]
+	pub fn unbond(&mut self, caller: T::AccountId, amount: T::Balance) -> DispatchResult {
+		let unlock_block = self.block_number.saturating_add(T::BONDING_DURATION);
+		let ledger = self.ledgers.get_mut(&caller).ok_or(Error::NotBonded)?;
+		if ledger.active < amount {
+			return Err(Error::InsufficientBond.into());
+		}
+		if ledger.unlocking.len() >= T::MAX_UNLOCKING_CHUNKS as usize {
+			return Err(Error::NoMoreChunks.into());
+		}
+
+		ledger.active = ledger.active.saturating_sub(amount);
+		ledger.unlocking.push(UnlockChunk { value: amount, unlock_block });
+		self.deposit_event(Event::Unbonded { who: caller, amount });
+		Ok(())
+	}
+
+	/// Release the chunks of the `caller` which are unlocked, without waiting for the end of the
+	/// block. The chunks of every account are also released at the end of the block they unlock
+	/// in, so this is only needed to use the funds within that block.
+	/// This function will return an error if none of the chunks of the caller are unlocked.
+	#[call_index = 2]
+	#[weight = 10_000]
+	pub fn withdraw_unbonded(
+		&mut self,
+		caller: T::AccountId,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		if !self.ledgers.contains_key(&caller) {
+			return Err(Error::NotBonded.into());
+		}
+		if !self.release(balances, &caller, self.block_number) {
+			return Err(Error::NothingToWithdraw.into());
+		}
+		Ok(())
+	}
+
+	/// Slash up to `amount` of the funds bonded by `who`, including the funds which are unlocking,
+	/// as a penalty. The active funds are slashed first, then the chunks which unlock last. The
+	/// slashed funds are burnt. This can only be called by the root origin.
+	#[call_index = 3]
+	#[weight = 10_000]
+	pub fn slash(
+		&mut self,
+		origin: OriginFor<T>,
+		who: T::AccountId,
+		amount: T::Balance,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		ensure_root(origin)?;
+		let ledger = self.ledgers.get_mut(&who).ok_or(Error::NotBonded)?;
+		let amount = if amount < ledger.total { amount } else { ledger.total };
+
+		let mut remaining = amount;
+		for value in core::iter::once(&mut ledger.active)
+			.chain(ledger.unlocking.iter_mut().rev().map(|chunk| &mut chunk.value))
+		{
+			let slashed = if remaining < *value { remaining } else { *value };
+			*value = value.saturating_sub(slashed);
+			remaining = remaining.saturating_sub(slashed);
+		}
+		ledger.unlocking.retain(|chunk| !chunk.value.is_zero());
+		ledger.total = ledger.total.saturating_sub(amount);
+
+		// Fees may have been paid out of the bonded funds, so less than `amount` may be left.
+		let slashed = balances.slash(&who, amount);
+		self.update_lock(balances, &who);
+		self.deposit_event(Event::Slashed { who, amount: slashed });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Staking Module.
+	pub fn new() -> Self {
+		Self {
+			ledgers: StorageMap::new(),
+			block_number: T::BlockNumber::zero(),
+			events: Events::new(),
+		}
+	}
+
+	/// Get the funds bonded by `who`, if any.
+	pub fn ledger(&self, who: &T::AccountId) -> Option<&StakingLedger<T::Balance, T::BlockNumber>> {
+		self.ledgers.get(who)
+	}
+
+	/// Release the chunks of `who` which unlock at or before `block_number`, lowering their lock.
+	/// Returns whether any chunk was released.
+	fn release(
+		&mut self,
+		balances: &mut crate::balances::Pallet<T>,
+		who: &T::AccountId,
+		block_number: T::BlockNumber,
+	) -> bool {
+		let Some(ledger) = self.ledgers.get_mut(who) else { return false };
+		let (released, unlocking): (Vec<_>, Vec<_>) = core::mem::take(&mut ledger.unlocking)
+			.into_iter()
+			.partition(|chunk| chunk.unlock_block <= block_number);
+		ledger.unlocking = unlocking;
+		if released.is_empty() {
+			return false;
+		}
+
+		let amount = released
+			.iter()
+			.fold(T::Balance::zero(), |amount, chunk| amount.saturating_add(chunk.value));
+		ledger.total = ledger.total.saturating_sub(amount);
+		self.update_lock(balances, who);
+		self.deposit_event(Event::Withdrawn { who: who.clone(), amount });
+		true
+	}
+
+	/// Set the lock of `who` to the total of their ledger. The ledger and the lock are removed once
+	/// nothing is bonded or unlocking.
+	fn update_lock(&mut self, balances: &mut crate::balances::Pallet<T>, who: &T::AccountId) {
+		match self.ledgers.get(who) {
+			Some(ledger) if !ledger.total.is_zero() => {
+				balances.set_lock(STAKING_ID, who, ledger.total, WithdrawReasons::TRANSFER);
+			},
+			_ => {
+				self.ledgers.remove(who);
+				balances.remove_lock(STAKING_ID, who);
+			},
+		}
+	}
+}
+
+impl<T: Config> HooksWithContext<T::BlockNumber, crate::balances::Pallet<T>> for Pallet<T> {
+	/// Keep the number of the block, which unbonding starts from.
+	fn on_initialize(&mut self, _balances: &mut crate::balances::Pallet<T>, block_number: T::BlockNumber) {
+		self.block_number = block_number;
+	}
+
+	/// Release the chunks of every account which unlock at the end of `block_number`.
+	fn on_finalize(&mut self, balances: &mut crate::balances::Pallet<T>, block_number: T::BlockNumber) {
+		let unlocked = self
+			.ledgers
+			.iter()
+			.filter(|(_, ledger)| {
+				ledger.unlocking.iter().any(|chunk| chunk.unlock_block <= block_number)
+			})
+			.map(|(who, _)| who.clone())
+			.collect::<Vec<_>>();
+		for who in unlocked {
+			self.release(balances, &who, block_number);
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The block
+	/// number is part of the state of the system pallet, and the phase and the events are not
+	/// part of the state.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for (who, ledger) in &self.ledgers {
+			let unlocking =
+				ledger.unlocking.iter().map(|chunk| (chunk.value, chunk.unlock_block)).collect::<Vec<_>>();
+			let value = (ledger.total, (ledger.active, unlocking)).encode();
+			state.insert(storage_map_key("staking", "ledgers", who), value);
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, UnlockChunk, STAKING_ID};
+	use crate::balances::{self, WithdrawReasons};
+	use crate::support::{DispatchError, HooksWithContext, RawOrigin};
+
+	crate::construct_mock_runtime!(struct TestConfig: balances);
+
+	impl super::Config for TestConfig {
+		const BONDING_DURATION: Self::BlockNumber = 3;
+		const MAX_UNLOCKING_CHUNKS: u32 = 2;
+	}
+
+	fn setup() -> (super::Pallet<TestConfig>, balances::Pallet<TestConfig>) {
+		let mut balances = balances::Pallet::new();
+		balances.set_balance("alice", 100);
+		let mut staking = super::Pallet::new();
+		staking.on_initialize(&mut balances, 1);
+		(staking, balances)
+	}
+
+	#[test]
+	fn bonded_funds_are_locked_until_released() {
+		let (mut staking, mut balances) = setup();
+
+		assert_eq!(staking.bond("alice", 60, &mut balances), Ok(()));
+		assert_eq!(staking.bond("alice", 50, &mut balances), Err(Error::InsufficientBalance.into()));
+		assert_eq!(staking.bond("alice", 20, &mut balances), Ok(()));
+		assert_eq!(balances.locked_balance(&"alice", WithdrawReasons::TRANSFER), 80);
+		assert_eq!(balances.transfer("alice", "bob", 30), Err(balances::Error::BalanceLocked.into()));
+
+		assert_eq!(staking.unbond("alice", 90), Err(Error::InsufficientBond.into()));
+		assert_eq!(staking.unbond("alice", 30), Ok(()));
+		staking.on_initialize(&mut balances, 2);
+		assert_eq!(staking.unbond("alice", 10), Ok(()));
+		assert_eq!(staking.unbond("alice", 10), Err(Error::NoMoreChunks.into()));
+		let ledger = staking.ledger(&"alice").unwrap();
+		assert_eq!((ledger.total, ledger.active), (80, 40));
+		assert_eq!(
+			ledger.unlocking,
+			[UnlockChunk { value: 30, unlock_block: 4 }, UnlockChunk { value: 10, unlock_block: 5 }]
+		);
+		assert_eq!(
+			staking.withdraw_unbonded("alice", &mut balances),
+			Err(Error::NothingToWithdraw.into())
+		);
+
+		// Unbonded funds stay locked until the end of the block they unlock in.
+		staking.on_finalize(&mut balances, 3);
+		assert_eq!(balances.locked_balance(&"alice", WithdrawReasons::TRANSFER), 80);
+		staking.on_finalize(&mut balances, 4);
+		assert_eq!(balances.locked_balance(&"alice", WithdrawReasons::TRANSFER), 50);
+		// They can also be withdrawn within the block they unlock in.
+		staking.on_initialize(&mut balances, 5);
+		assert_eq!(staking.withdraw_unbonded("alice", &mut balances), Ok(()));
+		assert_eq!(balances.locked_balance(&"alice", WithdrawReasons::TRANSFER), 40);
+
+		assert_eq!(staking.unbond("alice", 40), Ok(()));
+		staking.on_finalize(&mut balances, 8);
+		assert_eq!(staking.ledger(&"alice"), None);
+		assert_eq!(balances.locks(&"alice").count(), 0);
+		assert_eq!(staking.unbond("alice", 10), Err(Error::NotBonded.into()));
+	}
+
+	#[test]
+	fn slash_takes_active_funds_first() {
+		let (mut staking, mut balances) = setup();
+		assert_eq!(staking.bond("alice", 80, &mut balances), Ok(()));
+		assert_eq!(staking.unbond("alice", 20), Ok(()));
+		assert_eq!(staking.unbond("alice", 20), Ok(()));
+
+		assert_eq!(
+			staking.slash(RawOrigin::Signed("bob"), "alice", 10, &mut balances),
+			Err(DispatchError::BadOrigin)
+		);
+		assert_eq!(staking.slash(RawOrigin::Root, "alice", 50, &mut balances), Ok(()));
+		let ledger = staking.ledger(&"alice").unwrap();
+		assert_eq!((ledger.total, ledger.active), (30, 0));
+		assert_eq!(
+			ledger.unlocking,
+			[UnlockChunk { value: 20, unlock_block: 4 }, UnlockChunk { value: 10, unlock_block: 4 }]
+		);
+		assert_eq!(balances.balance(&"alice"), 50);
+		assert_eq!(balances.total_issuance(), 50);
+		let lock = balances.locks(&"alice").next().map(|(id, lock)| (*id, lock.amount));
+		assert_eq!(lock, Some((STAKING_ID, 30)));
+
+		// Slashing more than what is bonded only slashes the bonded funds.
+		assert_eq!(staking.slash(RawOrigin::Root, "alice", 100, &mut balances), Ok(()));
+		assert_eq!(staking.ledger(&"alice"), None);
+		assert_eq!(balances.balance(&"alice"), 20);
+		assert_eq!(balances.locks(&"alice").count(), 0);
+	}
+}
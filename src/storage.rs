@@ -0,0 +1,358 @@
+//! Key-value storage backends, which keep data as bytes, like the encoding of blocks produced by
+//! the `codec` module.
+//!
+//! `InMemoryBackend` is lost when the program exits, while `FileBackend` (which requires the `std`
+//! feature) keeps its data in a file, so a node can restart and find it again.
+//!
+//! A node only persists its blocks, in the `ChainStore` of the chain, and the transaction pool:
+//! the pallets keep their state in memory, and it is rebuilt when the node restarts by executing
+//! the stored blocks again. Pallets write their state into an `InMemoryBackend` under the keys
+//! built by `storage_key`, `storage_map_key` and `storage_double_map_key` only to compute the root
+//! of the state with `InMemoryBackend::root`.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use sha2::{Digest, Sha256};
+
+use crate::codec::Encode;
+#[cfg(feature = "std")]
+use crate::codec::{self, Decode};
+use crate::system::Hash;
+
+/// The key under which `pallet` stores the single value of its `item`, like a counter.
+///
+/// The names are encoded with their length, so the keys of different items can't collide.
+pub fn storage_key(pallet: &str, item: &str) -> Vec<u8> {
+	(pallet, item).encode()
+}
+
+/// The key under which `pallet` stores the value of `key` in its `item`, which is a map. The keys of
+/// a map follow the key of the map itself, so they are ordered together in a backend.
+pub fn storage_map_key<K: Encode + ?Sized>(pallet: &str, item: &str, key: &K) -> Vec<u8> {
+	let mut storage_key = storage_key(pallet, item);
+	key.encode_to(&mut storage_key);
+	storage_key
+}
+
+/// The key under which `pallet` stores the value of `(key1, key2)` in its `item`, which is a map with
+/// two keys, like the items of each collection. The keys sharing the same `key1` are ordered
+/// together in a backend, after the key of the map.
+pub fn storage_double_map_key<K1: Encode + ?Sized, K2: Encode + ?Sized>(
+	pallet: &str,
+	item: &str,
+	key1: &K1,
+	key2: &K2,
+) -> Vec<u8> {
+	let mut storage_key = storage_map_key(pallet, item, key1);
+	key2.encode_to(&mut storage_key);
+	storage_key
+}
+
+/// A key-value store of bytes.
+///
+/// Writes are visible to reads right away, but they are only guaranteed to survive a restart once
+/// they are committed.
+pub trait StorageBackend {
+	/// The error returned when the writes can't be committed.
+	type Error;
+
+	/// The value stored under `key`, if any.
+	fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+
+	/// Store `value` under `key`, replacing the value stored there before.
+	fn insert(&mut self, key: Vec<u8>, value: Vec<u8>);
+
+	/// Remove the value stored under `key`, if any.
+	fn remove(&mut self, key: &[u8]);
+
+	/// Make every write so far durable.
+	fn commit(&mut self) -> Result<(), Self::Error>;
+}
+
+/// A storage backend which keeps its entries in memory. Committing does nothing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InMemoryBackend {
+	entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl InMemoryBackend {
+	/// Create an empty backend.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The number of entries stored.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// The root of a binary Merkle tree over the entries, in the order of their keys, which
+	/// commits to all of them: changing, adding or removing any entry changes the root.
+	///
+	/// Each leaf is the SHA-256 hash of the encoding of an entry, and each node is the hash of its
+	/// two children. Leaves and nodes are hashed with a different prefix byte, so a node can't be
+	/// passed off as a leaf. A node without a right child is carried up to the next level as it is.
+	/// The root of an empty backend is all zeros.
+	pub fn root(&self) -> Hash {
+		let mut level = self
+			.entries
+			.iter()
+			.map(|entry| Sha256::new().chain_update([0]).chain_update(entry.encode()).finalize().into())
+			.collect::<Vec<Hash>>();
+		while level.len() > 1 {
+			level = level
+				.chunks(2)
+				.map(|pair| match pair {
+					[left, right] =>
+						Sha256::new().chain_update([1]).chain_update(left).chain_update(right).finalize().into(),
+					_ => pair[0],
+				})
+				.collect();
+		}
+		level.first().copied().unwrap_or_default()
+	}
+}
+
+impl StorageBackend for InMemoryBackend {
+	type Error = core::convert::Infallible;
+
+	fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+		self.entries.get(key).cloned()
+	}
+
+	fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+		self.entries.insert(key, value);
+	}
+
+	fn remove(&mut self, key: &[u8]) {
+		self.entries.remove(key);
+	}
+
+	fn commit(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+
+/// The errors which can happen while opening or committing a `FileBackend`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum FileError {
+	/// The file couldn't be read or written.
+	Io(std::io::Error),
+	/// The file doesn't hold the entries of a backend.
+	Corrupt(codec::Error),
+}
+
+/// A storage backend which keeps its entries in a file.
+///
+/// The file is a log of the commits: each commit appends the entries written or removed since the
+/// previous one, so its cost depends on the changes and not on the size of the backend. The log is
+/// replayed in memory when the backend is opened. A commit interrupted by a crash leaves a partial
+/// record at the end of the file, which is ignored, so the entries of the previous commits are
+/// intact; the next commit overwrites it.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct FileBackend {
+	path: std::path::PathBuf,
+	entries: InMemoryBackend,
+	/// The entries written, or removed with `None`, since the last commit.
+	changes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+	/// The length of the complete commits at the start of the file.
+	committed_len: u64,
+}
+
+#[cfg(feature = "std")]
+impl FileBackend {
+	/// Open the backend kept in the file at `path`. The backend is empty if the file doesn't exist
+	/// yet, and the file is only created by the first commit.
+	pub fn open(path: impl Into<std::path::PathBuf>) -> Result<Self, FileError> {
+		let path = path.into();
+		let bytes = match std::fs::read(&path) {
+			Ok(bytes) => bytes,
+			Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+			Err(error) => return Err(FileError::Io(error)),
+		};
+
+		let mut entries = InMemoryBackend::new();
+		let mut input = &bytes[..];
+		while !input.is_empty() {
+			let mut rest = input;
+			let commit = match Vec::<(Vec<u8>, Option<Vec<u8>>)>::decode(&mut rest) {
+				Ok(commit) => commit,
+				// The last commit was interrupted before it was fully written.
+				Err(codec::Error::UnexpectedEnd) => break,
+				Err(error) => return Err(FileError::Corrupt(error)),
+			};
+			for (key, value) in commit {
+				match value {
+					Some(value) => entries.insert(key, value),
+					None => entries.remove(&key),
+				}
+			}
+			input = rest;
+		}
+		let committed_len = (bytes.len() - input.len()) as u64;
+		Ok(Self { path, entries, changes: BTreeMap::new(), committed_len })
+	}
+}
+
+#[cfg(feature = "std")]
+impl StorageBackend for FileBackend {
+	type Error = FileError;
+
+	fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+		self.entries.get(key)
+	}
+
+	fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+		self.changes.insert(key.clone(), Some(value.clone()));
+		self.entries.insert(key, value);
+	}
+
+	fn remove(&mut self, key: &[u8]) {
+		self.changes.insert(key.to_vec(), None);
+		self.entries.remove(key);
+	}
+
+	fn commit(&mut self) -> Result<(), Self::Error> {
+		use std::io::{Seek, Write};
+
+		if self.changes.is_empty() {
+			return Ok(());
+		}
+		let bytes = self.changes.iter().collect::<Vec<_>>().encode();
+		let mut file = std::fs::OpenOptions::new()
+			.create(true)
+			.truncate(false)
+			.write(true)
+			.open(&self.path)
+			.map_err(FileError::Io)?;
+		// Drop the partial record of an interrupted commit, if any, then append this one.
+		file.set_len(self.committed_len).map_err(FileError::Io)?;
+		file.seek(std::io::SeekFrom::End(0)).map_err(FileError::Io)?;
+		file.write_all(&bytes).and_then(|()| file.sync_data()).map_err(FileError::Io)?;
+		self.committed_len += bytes.len() as u64;
+		self.changes.clear();
+		Ok(())
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use crate::codec::Encode;
+
+	use super::{
+		storage_double_map_key, storage_key, storage_map_key, FileBackend, FileError, InMemoryBackend, StorageBackend,
+	};
+
+	#[test]
+	fn in_memory_backend_reads_its_writes() {
+		let mut backend = InMemoryBackend::new();
+		backend.insert(b"key".to_vec(), b"value".to_vec());
+		backend.insert(b"other".to_vec(), b"1".to_vec());
+		backend.insert(b"other".to_vec(), b"2".to_vec());
+		assert_eq!(backend.get(b"key"), Some(b"value".to_vec()));
+		assert_eq!(backend.get(b"other"), Some(b"2".to_vec()));
+		assert_eq!(backend.len(), 2);
+
+		backend.remove(b"key");
+		assert_eq!(backend.get(b"key"), None);
+		assert_eq!(backend.len(), 1);
+	}
+
+	#[test]
+	fn root_commits_to_every_entry() {
+		let mut backend = InMemoryBackend::new();
+		assert_eq!(backend.root(), [0; 32]);
+
+		let mut roots = vec![backend.root()];
+		for (who, balance) in [("alice", 100u128), ("bob", 50), ("charlie", 10)] {
+			backend.insert(storage_map_key("balances", "accounts", who), balance.encode());
+			roots.push(backend.root());
+		}
+		backend.insert(storage_map_key("balances", "accounts", "bob"), 51u128.encode());
+		roots.push(backend.root());
+		backend.remove(&storage_map_key("balances", "accounts", "charlie"));
+		roots.push(backend.root());
+		// Every change gave a new root.
+		let distinct = roots.iter().collect::<std::collections::BTreeSet<_>>();
+		assert_eq!(distinct.len(), roots.len());
+
+		// The root only depends on the entries, not on the order they were written in.
+		let mut other = InMemoryBackend::new();
+		other.insert(storage_map_key("balances", "accounts", "bob"), 51u128.encode());
+		other.insert(storage_map_key("balances", "accounts", "alice"), 100u128.encode());
+		assert_eq!(other.root(), backend.root());
+
+		// The names of the pallet and of the item are part of the key.
+		assert_ne!(storage_key("ab", "c"), storage_key("a", "bc"));
+		// The entries of a double map sharing their first key follow the key of the map with it.
+		let prefix = storage_map_key("uniques", "items", &1u32);
+		assert!(storage_double_map_key("uniques", "items", &1u32, &2u32).starts_with(&prefix));
+	}
+
+	#[test]
+	fn file_backend_keeps_committed_writes() {
+		let path = std::env::temp_dir().join(format!("pallets-storage-{}", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+
+		let mut backend = FileBackend::open(&path).expect("a missing file is an empty backend");
+		backend.insert(b"key".to_vec(), b"value".to_vec());
+		backend.insert(b"removed".to_vec(), b"value".to_vec());
+		backend.remove(b"removed");
+		backend.commit().expect("commit should succeed");
+		// Writes which are not committed are lost when the backend is opened again.
+		backend.insert(b"uncommitted".to_vec(), b"value".to_vec());
+
+		let reopened = FileBackend::open(&path).expect("the file should be readable");
+		assert_eq!(reopened.get(b"key"), Some(b"value".to_vec()));
+		assert_eq!(reopened.get(b"removed"), None);
+		assert_eq!(reopened.get(b"uncommitted"), None);
+
+		std::fs::write(&path, vec![(b"key".to_vec(), 2u8)].encode()).unwrap();
+		assert!(matches!(FileBackend::open(&path), Err(FileError::Corrupt(_))));
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn file_backend_appends_the_changes_of_each_commit() {
+		let path = std::env::temp_dir().join(format!("pallets-storage-log-{}", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		let file_len = || std::fs::metadata(&path).unwrap().len();
+
+		let mut backend = FileBackend::open(&path).unwrap();
+		for index in 0u32..100 {
+			backend.insert(index.encode(), vec![0; 100]);
+		}
+		backend.commit().unwrap();
+		let len = file_len();
+		// A commit without changes writes nothing, and one with a change only appends it.
+		backend.commit().unwrap();
+		assert_eq!(file_len(), len);
+		backend.insert(b"key".to_vec(), b"value".to_vec());
+		backend.remove(&0u32.encode());
+		backend.commit().unwrap();
+		let change = vec![(0u32.encode(), None), (b"key".to_vec(), Some(b"value".to_vec()))].encode();
+		assert_eq!(file_len(), len + change.len() as u64);
+
+		// A commit interrupted by a crash is ignored, and overwritten by the next commit.
+		let mut bytes = std::fs::read(&path).unwrap();
+		bytes.extend_from_slice(&change[..change.len() - 1]);
+		std::fs::write(&path, bytes).unwrap();
+		let mut reopened = FileBackend::open(&path).unwrap();
+		assert_eq!(reopened.get(&0u32.encode()), None);
+		assert_eq!(reopened.get(&1u32.encode()), Some(vec![0; 100]));
+		assert_eq!(reopened.get(b"key"), Some(b"value".to_vec()));
+		reopened.insert(b"other".to_vec(), b"value".to_vec());
+		reopened.commit().unwrap();
+		let reopened = FileBackend::open(&path).unwrap();
+		assert_eq!(reopened.get(b"other"), Some(b"value".to_vec()));
+		assert_eq!(reopened.get(b"key"), Some(b"value".to_vec()));
+		std::fs::remove_file(&path).unwrap();
+	}
+}
@@ -0,0 +1,287 @@
+use alloc::boxed::Box;
+
+use crate::codec::Encode;
+use crate::storage::{storage_key, StorageBackend};
+use crate::support::{
+	DispatchCall, DispatchError, DispatchResult, Events, KillAccount, OriginFor, RawOrigin, StorageValue, Weight,
+};
+
+pub trait Config: crate::system::Config {
+	/// The calls which the sudo account can dispatch, which is usually the `RuntimeCall` generated
+	/// by `#[macros::runtime]`.
+	type RuntimeCall: Clone;
+	/// The weight of `call`. The call is dispatched without an extrinsic, so its weight is paid for
+	/// with the `sudo` call.
+	fn call_weight(call: &Self::RuntimeCall) -> Weight;
+}
+
+/// The events emitted by the sudo pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId> {
+	/// A call was dispatched with the root origin by the sudo account, with this result.
+	Sudid { result: DispatchResult },
+	/// The sudo account was changed from `old` to `new`.
+	KeyChanged { old: AccountId, new: AccountId },
+	/// The account of `who` was reaped from every pallet by the sudo account.
+	AccountKilled { who: AccountId },
+}
+
+/// The errors returned by the sudo pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The caller isn't the sudo account.
+	RequireSudo,
+	/// The sudo account can't reap its own account, since it holds the sudo key.
+	CannotKillSudoKey,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::RequireSudo => "RequireSudo",
+			Error::CannotKillSudoKey => "CannotKillSudoKey",
+		};
+		DispatchError::Module { pallet: "sudo", error }
+	}
+}
+
+/// The events of the sudo pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// The runtime `T`, through which the calls of the sudo account are dispatched, and accounts are
+/// reaped.
+pub trait SudoRuntime<T: Config>: DispatchCall<OriginFor<T>, T::RuntimeCall> + KillAccount<T::AccountId> {}
+
+impl<T: Config, R> SudoRuntime<T> for R where R: DispatchCall<OriginFor<T>, T::RuntimeCall> + KillAccount<T::AccountId> + ?Sized {}
+
+/// The runtime `T`, as the sudo pallet sees it.
+pub type RuntimeOf<T> = dyn SudoRuntime<T>;
+
+/// This is the Sudo Module.
+/// It lets a single super-user account, the sudo key, dispatch any call with the root origin, like
+/// `force_set_balance`, so privileged calls are only reachable through a signed extrinsic of that
+/// account. The key is set at genesis, and only the sudo account can hand it over.
+///
+/// Like the utility pallet, the calls are dispatched through the runtime itself, see
+/// `#[dispatch_context(self)]` in `#[macros::runtime]`. The pallet is taken out of the runtime
+/// meanwhile, so a sudo call nested in the call, like in a batch, is refused with
+/// `DispatchError::Reentrancy` instead of being checked against an empty pallet.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The sudo account, if there is one.
+	key: StorageValue<Option<T::AccountId>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Dispatch `call` with the root origin. Only the sudo account can call this. The result of
+	/// `call` is emitted with `Event::Sudid`, so this succeeds even when `call` fails.
+	/// This function will return an error if the caller isn't the sudo account.
+	#[call_index = 0]
+	#[weight = 1_000u64.saturating_add(T::call_weight(call))]
+	#[allow(clippy::boxed_local)]
+	pub fn sudo(
+		&mut self,
+		caller: T::AccountId,
+		call: Box<T::RuntimeCall>,
+		runtime: &mut RuntimeOf<T>,
+	) -> DispatchResult {
+		self.ensure_sudo(&caller)?;
+
+		let result = runtime.dispatch_call(RawOrigin::Root, *call).map(|_| ());
+		self.deposit_event(Event::Sudid { result });
+		Ok(())
+	}
+
+	/// Make `new` the sudo account in place of the `caller`.
+	/// This function will return an error if the caller isn't the sudo account.
+	#[call_index = 1]
+	#[weight = 1_000]
+	pub fn set_key(&mut self, caller: T::AccountId, new: T::AccountId) -> DispatchResult {
+		self.ensure_sudo(&caller)?;
+
+		self.key.set(Some(new.clone()));
+		self.deposit_event(Event::KeyChanged { old: caller, new });
+		Ok(())
+	}
+
+	/// Reap the account of `who` from every pallet of the runtime, and reset its nonce, see
+	/// `support::KillAccount`. Only the sudo account can call this.
+	/// This function will return an error if the caller isn't the sudo account, if `who` is the
+	/// sudo account, or if a pallet still holds state for `who`.
+	#[call_index = 2]
+	#[weight = 1_000]
+	pub fn kill_account(&mut self, caller: T::AccountId, who: T::AccountId, runtime: &mut RuntimeOf<T>) -> DispatchResult {
+		self.ensure_sudo(&caller)?;
+		if who == caller {
+			return Err(Error::CannotKillSudoKey.into());
+		}
+
+		runtime.kill_account(&who)?;
+		self.deposit_event(Event::AccountKilled { who });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Sudo Module, without a sudo account.
+	pub fn new() -> Self {
+		Self { key: StorageValue::new(None), events: Events::new() }
+	}
+
+	/// Get the sudo account, if there is one.
+	pub fn key(&self) -> Option<&T::AccountId> {
+		self.key.as_ref()
+	}
+
+	/// Check that `caller` is the sudo account.
+	fn ensure_sudo(&self, caller: &T::AccountId) -> Result<(), Error> {
+		match &*self.key {
+			Some(key) if key == caller => Ok(()),
+			_ => Err(Error::RequireSudo),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		if let Some(key) = &*self.key {
+			state.insert(storage_key("sudo", "key"), key.encode());
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The initial state of the Sudo Module, see `RuntimeGenesisConfig` of `#[macros::runtime]`.
+pub struct GenesisConfig<T: Config> {
+	/// The sudo account at genesis, if there is one.
+	pub key: Option<T::AccountId>,
+}
+
+impl<T: Config> GenesisConfig<T> {
+	/// Set the sudo account.
+	pub fn build(&self, sudo: &mut Pallet<T>) -> DispatchResult {
+		sudo.key.set(self.key.clone());
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+	fn default() -> Self {
+		Self { key: None }
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, Event, GenesisConfig};
+	use crate::support::{DispatchCall, DispatchError, DispatchResult, DispatchResultWithOutput, KillAccount, RawOrigin};
+
+	/// A test runtime, which records the origins of the calls it dispatched, and the accounts it
+	/// reaped. Calls named `fail` fail, and the account `busy` can't be reaped.
+	#[derive(Default)]
+	struct TestRuntime {
+		dispatched: Vec<(RawOrigin<&'static str>, &'static str)>,
+		killed: Vec<&'static str>,
+	}
+
+	impl KillAccount<&'static str> for TestRuntime {
+		fn kill_account(&mut self, who: &&'static str) -> DispatchResult {
+			if *who == "busy" {
+				return Err(DispatchError::Other("account still holds claims"));
+			}
+			self.killed.push(who);
+			Ok(())
+		}
+	}
+
+	impl DispatchCall<RawOrigin<&'static str>, &'static str> for TestRuntime {
+		fn dispatch_call(&mut self, origin: RawOrigin<&'static str>, call: &'static str) -> DispatchResultWithOutput {
+			if call == "fail" {
+				return Err(DispatchError::Other("failed"));
+			}
+			self.dispatched.push((origin, call));
+			Ok(None)
+		}
+
+		fn transactional(
+			&mut self,
+			f: &mut dyn FnMut(&mut dyn DispatchCall<RawOrigin<&'static str>, &'static str>) -> DispatchResult,
+		) -> DispatchResult {
+			f(self)
+		}
+	}
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		type RuntimeCall = &'static str;
+		fn call_weight(_call: &Self::RuntimeCall) -> crate::support::Weight {
+			1_000
+		}
+	}
+
+	fn new_sudo(key: &'static str) -> super::Pallet<TestConfig> {
+		let mut sudo = super::Pallet::new();
+		assert_eq!(GenesisConfig { key: Some(key) }.build(&mut sudo), Ok(()));
+		sudo
+	}
+
+	#[test]
+	fn sudo_dispatches_with_the_root_origin() {
+		let mut sudo = new_sudo("alice");
+		let mut runtime = TestRuntime::default();
+
+		assert_eq!(sudo.sudo("bob", Box::new("set_balance"), &mut runtime), Err(Error::RequireSudo.into()));
+		assert_eq!(sudo.sudo("alice", Box::new("set_balance"), &mut runtime), Ok(()));
+		assert_eq!(runtime.dispatched, [(RawOrigin::Root, "set_balance")]);
+
+		// A failing call is reported in the event, not by `sudo` itself.
+		assert_eq!(sudo.sudo("alice", Box::new("fail"), &mut runtime), Ok(()));
+		let events = sudo.take_events().into_iter().map(|record| record.event).collect::<Vec<_>>();
+		assert_eq!(
+			events,
+			[Event::Sudid { result: Ok(()) }, Event::Sudid { result: Err(DispatchError::Other("failed")) }]
+		);
+	}
+
+	#[test]
+	fn only_the_sudo_account_can_set_the_key() {
+		let mut sudo = new_sudo("alice");
+		assert_eq!(sudo.set_key("bob", "bob"), Err(Error::RequireSudo.into()));
+		assert_eq!(sudo.set_key("alice", "bob"), Ok(()));
+		assert_eq!(sudo.key(), Some(&"bob"));
+		assert_eq!(sudo.set_key("alice", "alice"), Err(Error::RequireSudo.into()));
+
+		// Without a sudo account, nobody can dispatch with the root origin.
+		let mut sudo = super::Pallet::<TestConfig>::new();
+		assert_eq!(sudo.sudo("alice", Box::new("set_balance"), &mut TestRuntime::default()), Err(Error::RequireSudo.into()));
+	}
+
+	#[test]
+	fn only_the_sudo_account_can_kill_accounts() {
+		let mut sudo = new_sudo("alice");
+		let mut runtime = TestRuntime::default();
+
+		assert_eq!(sudo.kill_account("bob", "charlie", &mut runtime), Err(Error::RequireSudo.into()));
+		assert_eq!(sudo.kill_account("alice", "alice", &mut runtime), Err(Error::CannotKillSudoKey.into()));
+		assert_eq!(sudo.kill_account("alice", "busy", &mut runtime), Err(DispatchError::Other("account still holds claims")));
+		assert_eq!(sudo.kill_account("alice", "charlie", &mut runtime), Ok(()));
+		assert_eq!(runtime.killed, ["charlie"]);
+		let events = sudo.take_events().into_iter().map(|record| record.event).collect::<Vec<_>>();
+		assert_eq!(events, [Event::AccountKilled { who: "charlie" }]);
+	}
+}
@@ -1,42 +1,1254 @@
+use alloc::boxed::Box;
+use alloc::collections::btree_map::{self, BTreeMap};
+use alloc::collections::btree_set::{self, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use core::borrow::Borrow;
+
+use num::traits::Zero;
+use sha2::{Digest, Sha256};
+
+use crate::codec::{self, Decode, Encode};
+use crate::crypto::{Pair, Signature};
+use crate::system::Hash;
+
 /// The most primitive representation of a Blockchain block.
-pub struct Block<Header, Extrinsic> {
+#[derive(Clone)]
+pub struct Block<Header, Extrinsic, Inherent> {
 	/// The block header contains metadata about the block.
 	pub header: Header,
+	/// The inherents are unsigned calls provided by the block author, like the time of the block.
+	/// They are applied with the none origin before the extrinsics, and the block is refused if
+	/// any of them fails.
+	pub inherents: Vec<Inherent>,
 	/// The extrinsics represent the state transitions to be executed in this block.
 	pub extrinsics: Vec<Extrinsic>,
 }
 
-/// We are using an extremely simplified header which only contains the current block number.
-/// On a real blockchain, you would expect to also find:
-/// - parent block hash
-/// - state root
-/// - extrinsics root
-/// - etc...
+/// The header of a block, which links it to the block it was built on.
+///
+/// The hash of a block is the SHA-256 hash of the encoding of its header, see `Header::hash`.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Header<BlockNumber> {
+	/// The hash of the previous block, which the block is built on.
+	pub parent_hash: Hash,
 	pub block_number: BlockNumber,
+	/// The root of the state after executing the block, see `StateRoot`. The block author
+	/// computes it by executing the block, and the block is refused if executing it leaves another
+	/// state.
+	pub state_root: Hash,
+	/// The hash of the inherents and extrinsics of the block, see `extrinsics_root`.
+	pub extrinsics_root: Hash,
+	/// What the consensus engine needs to verify who authored the block, which the runtime
+	/// doesn't execute. It is empty until the block author seals the block.
+	pub digest: Vec<DigestItem>,
+}
+
+/// The identifier of a consensus engine, like `*b"aura"`, which tags its digest items.
+pub type ConsensusEngineId = [u8; 4];
+
+/// An item of the digest of a header.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DigestItem {
+	/// Data put in the header by the block author before it is sealed, like who authored it.
+	PreRuntime(ConsensusEngineId, Vec<u8>),
+	/// The signature of the block author over the hash of the header without its seal, which is
+	/// the last item of the digest.
+	Seal(ConsensusEngineId, Vec<u8>),
+}
+
+impl<BlockNumber: Encode> Header<BlockNumber> {
+	/// The hash of the block with this header.
+	pub fn hash(&self) -> Hash {
+		Sha256::digest(self.encode()).into()
+	}
+}
+
+/// The hash of the encoding of the inherents and extrinsics of a block, which commits the header to
+/// them.
+pub fn extrinsics_root<Inherent: Encode, Extrinsic: Encode>(inherents: &[Inherent], extrinsics: &[Extrinsic]) -> Hash {
+	let mut encoded = inherents.encode();
+	extrinsics.encode_to(&mut encoded);
+	Sha256::digest(encoded).into()
+}
+
+impl<BlockNumber, Extrinsic: Encode, Inherent: Encode> Block<Header<BlockNumber>, Extrinsic, Inherent> {
+	/// Build a block of `extrinsics` on top of the block whose hash is `parent_hash`, with the
+	/// matching extrinsics root. The block has no inherents, see `with_inherents`. The state root is
+	/// left empty, for the block author to fill in once they executed the block.
+	pub fn new(parent_hash: Hash, block_number: BlockNumber, extrinsics: Vec<Extrinsic>) -> Self {
+		let extrinsics_root = extrinsics_root::<Inherent, _>(&[], &extrinsics);
+		let header = Header { parent_hash, block_number, state_root: [0; 32], extrinsics_root, digest: Vec::new() };
+		Self { header, inherents: Vec::new(), extrinsics }
+	}
+
+	/// Set the inherents of the block, updating its extrinsics root to match.
+	pub fn with_inherents(mut self, inherents: Vec<Inherent>) -> Self {
+		self.header.extrinsics_root = extrinsics_root(&inherents, &self.extrinsics);
+		self.inherents = inherents;
+		self
+	}
 }
 
 /// This is an "extrinsic": literally an external message from outside of the blockchain.
 /// This simplified version of an extrinsic tells us who is making the call, and which call they are
 /// making.
-pub struct Extrinsic<Caller, Call> {
+#[derive(Clone)]
+pub struct Extrinsic<Caller, Call, BlockNumber, Balance> {
 	pub caller: Caller,
 	pub call: Call,
+	/// The last block number in which this extrinsic can be included, so it can't be replayed
+	/// much later. `None` means the extrinsic is immortal.
+	pub valid_until: Option<BlockNumber>,
+	/// An extra amount paid on top of the transaction fee, to get the extrinsic included in a
+	/// block before others.
+	pub tip: Balance,
+}
+
+impl<Header: Encode, Extrinsic: Encode, Inherent: Encode> Encode for Block<Header, Extrinsic, Inherent> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.header.encode_to(dest);
+		self.inherents.encode_to(dest);
+		self.extrinsics.encode_to(dest);
+	}
+}
+
+impl<Header: Decode, Extrinsic: Decode, Inherent: Decode> Decode for Block<Header, Extrinsic, Inherent> {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		Ok(Self { header: Header::decode(input)?, inherents: Vec::decode(input)?, extrinsics: Vec::decode(input)? })
+	}
+}
+
+impl<BlockNumber: Encode> Encode for Header<BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.parent_hash.encode_to(dest);
+		self.block_number.encode_to(dest);
+		self.state_root.encode_to(dest);
+		self.extrinsics_root.encode_to(dest);
+		self.digest.encode_to(dest);
+	}
+}
+
+impl<BlockNumber: Decode> Decode for Header<BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		Ok(Self {
+			parent_hash: Decode::decode(input)?,
+			block_number: BlockNumber::decode(input)?,
+			state_root: Decode::decode(input)?,
+			extrinsics_root: Decode::decode(input)?,
+			digest: Decode::decode(input)?,
+		})
+	}
+}
+
+impl Encode for DigestItem {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		let (index, engine, data) = match self {
+			DigestItem::PreRuntime(engine, data) => (0u8, engine, data),
+			DigestItem::Seal(engine, data) => (1u8, engine, data),
+		};
+		dest.push(index);
+		engine.encode_to(dest);
+		data.encode_to(dest);
+	}
+}
+
+impl Decode for DigestItem {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		let index = u8::decode(input)?;
+		let (engine, data) = (Decode::decode(input)?, Decode::decode(input)?);
+		match index {
+			0 => Ok(DigestItem::PreRuntime(engine, data)),
+			1 => Ok(DigestItem::Seal(engine, data)),
+			_ => Err(codec::Error::Invalid),
+		}
+	}
+}
+
+/// The calls of a runtime, which are encoded for its transaction version, see
+/// `system::RuntimeVersion`. `#[macros::runtime]` implements it for `RuntimeCall`.
+pub trait TransactionVersion {
+	const TRANSACTION_VERSION: u32;
+}
+
+// The call of an extrinsic is prefixed with the transaction version it was encoded for, so an
+// extrinsic encoded for another version of the calls is refused instead of being decoded into
+// another call. Since the caller signs the encoding of the extrinsic, the version is part of the
+// signed payload too.
+impl<Caller, Call, BlockNumber, Balance> Encode for Extrinsic<Caller, Call, BlockNumber, Balance>
+where
+	Caller: Encode,
+	Call: Encode + TransactionVersion,
+	BlockNumber: Encode,
+	Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.caller.encode_to(dest);
+		Call::TRANSACTION_VERSION.encode_to(dest);
+		self.call.encode_to(dest);
+		self.valid_until.encode_to(dest);
+		self.tip.encode_to(dest);
+	}
+}
+
+impl<Caller, Call, BlockNumber, Balance> Decode for Extrinsic<Caller, Call, BlockNumber, Balance>
+where
+	Caller: Decode,
+	Call: Decode + TransactionVersion,
+	BlockNumber: Decode,
+	Balance: Decode,
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		let caller = Caller::decode(input)?;
+		if u32::decode(input)? != Call::TRANSACTION_VERSION {
+			return Err(codec::Error::UnsupportedVersion);
+		}
+		Ok(Self {
+			caller,
+			call: Call::decode(input)?,
+			valid_until: Option::decode(input)?,
+			tip: Balance::decode(input)?,
+		})
+	}
+}
+
+/// An extrinsic signed by its caller, which proves the caller agreed to make it.
+///
+/// The signature covers the encoding of the extrinsic, the nonce of the caller and the genesis
+/// hash of the chain, so it can't be replayed on another chain, or changed by whoever relays it,
+/// like raising its tip. The encoding of the extrinsic includes the transaction version of the
+/// runtime, so the signature doesn't carry over to a runtime whose calls are encoded differently.
+#[derive(Clone)]
+pub struct SignedExtrinsic<Extrinsic, Nonce> {
+	pub extrinsic: Extrinsic,
+	/// The number of extrinsics the caller applied before this one, which must match their nonce
+	/// in the system pallet, so each signed extrinsic can only be applied once.
+	pub nonce: Nonce,
+	pub signature: Signature,
+}
+
+impl<Extrinsic: Encode, Nonce: Encode> SignedExtrinsic<Extrinsic, Nonce> {
+	/// The message signed by the caller of `extrinsic`.
+	pub fn signing_payload(extrinsic: &Extrinsic, nonce: &Nonce, genesis_hash: &Hash) -> Vec<u8> {
+		let mut payload = extrinsic.encode();
+		nonce.encode_to(&mut payload);
+		genesis_hash.encode_to(&mut payload);
+		payload
+	}
+
+	/// Sign `extrinsic` with the key pair of its caller.
+	pub fn sign(extrinsic: Extrinsic, nonce: Nonce, genesis_hash: &Hash, pair: &Pair) -> Self {
+		let signature = pair.sign(&Self::signing_payload(&extrinsic, &nonce, genesis_hash));
+		Self { extrinsic, nonce, signature }
+	}
+}
+
+impl<Extrinsic: Encode, Nonce: Encode> Encode for SignedExtrinsic<Extrinsic, Nonce> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.extrinsic.encode_to(dest);
+		self.nonce.encode_to(dest);
+		self.signature.encode_to(dest);
+	}
+}
+
+impl<Extrinsic: Decode, Nonce: Decode> Decode for SignedExtrinsic<Extrinsic, Nonce> {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		Ok(Self {
+			extrinsic: Extrinsic::decode(input)?,
+			nonce: Nonce::decode(input)?,
+			signature: Signature::decode(input)?,
+		})
+	}
+}
+
+/// A builder for an `Extrinsic`, like `ExtrinsicBuilder::new(alice).call(call).tip(5).build()`,
+/// which fills in the optional fields with their defaults: the extrinsic is immortal and has no tip.
+pub struct ExtrinsicBuilder<Caller, Call, BlockNumber, Balance> {
+	caller: Caller,
+	call: Option<Call>,
+	valid_until: Option<BlockNumber>,
+	tip: Balance,
+}
+
+impl<Caller, Call, BlockNumber, Balance: Zero> ExtrinsicBuilder<Caller, Call, BlockNumber, Balance> {
+	/// Start building an extrinsic made by `caller`.
+	pub fn new(caller: Caller) -> Self {
+		Self { caller, call: None, valid_until: None, tip: Balance::zero() }
+	}
+
+	/// Set the call made by the extrinsic. This is required before building it.
+	pub fn call(mut self, call: Call) -> Self {
+		self.call = Some(call);
+		self
+	}
+
+	/// Make the extrinsic mortal, so it can only be included up to `block_number`.
+	pub fn valid_until(mut self, block_number: BlockNumber) -> Self {
+		self.valid_until = Some(block_number);
+		self
+	}
+
+	/// Set the tip paid on top of the transaction fee.
+	pub fn tip(mut self, tip: Balance) -> Self {
+		self.tip = tip;
+		self
+	}
+
+	/// Build the extrinsic.
+	///
+	/// This panics if the call of the extrinsic was not set.
+	pub fn build(self) -> Extrinsic<Caller, Call, BlockNumber, Balance> {
+		Extrinsic {
+			caller: self.caller,
+			call: self.call.expect("the call of an extrinsic must be set before building it"),
+			valid_until: self.valid_until,
+			tip: self.tip,
+		}
+	}
+}
+
+/// The error type for our runtime, returned by calls and by the execution of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchError {
+	/// An error of a pallet, identified by the name of the pallet and the name of the error.
+	Module { pallet: &'static str, error: &'static str },
+	/// An arithmetic operation overflowed or underflowed.
+	Arithmetic(ArithmeticError),
+	/// The caller is not allowed to make the call.
+	BadOrigin,
+	/// The call was refused by the `CallFilter` of the runtime, like a call paused by the
+	/// transaction pause pallet.
+	CallFiltered,
+	/// The call is a call of this pallet, made by a call the pallet is dispatching through the
+	/// runtime, like a batch in a sudo call in a batch. See `Dispatching`.
+	Reentrancy(&'static str),
+	/// Any other error, described by a static error message.
+	Other(&'static str),
+}
+
+/// The arithmetic errors which can happen while dispatching a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticError {
+	/// The result of the operation is larger than the largest value of its type.
+	Overflow,
+	/// The result of the operation is smaller than the smallest value of its type.
+	Underflow,
+}
+
+impl From<ArithmeticError> for DispatchError {
+	fn from(error: ArithmeticError) -> Self {
+		DispatchError::Arithmetic(error)
+	}
+}
+
+impl From<&'static str> for DispatchError {
+	fn from(message: &'static str) -> Self {
+		DispatchError::Other(message)
+	}
+}
+
+impl core::fmt::Display for DispatchError {
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		match self {
+			DispatchError::Module { pallet, error } => write!(f, "{pallet}::{error}"),
+			DispatchError::Arithmetic(ArithmeticError::Overflow) => f.write_str("arithmetic overflow"),
+			DispatchError::Arithmetic(ArithmeticError::Underflow) => f.write_str("arithmetic underflow"),
+			DispatchError::BadOrigin => f.write_str("bad origin"),
+			DispatchError::CallFiltered => f.write_str("call filtered"),
+			DispatchError::Reentrancy(pallet) => write!(f, "reentrant call of the {pallet} pallet"),
+			DispatchError::Other(message) => f.write_str(message),
+		}
+	}
 }
 
 /// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
-/// otherwise return a static error message.
-pub type DispatchResult = Result<(), &'static str>;
+/// otherwise return a `DispatchError`.
+pub type DispatchResult = Result<(), DispatchError>;
+
+/// The origin of a call, which tells who is allowed to make it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawOrigin<AccountId> {
+	/// The call was made by an account, which signed the extrinsic.
+	Signed(AccountId),
+	/// The call was made by the runtime itself, with the highest privileges.
+	Root,
+	/// The call was made without any account, like an inherent included by the block author.
+	None,
+	/// The call was approved by `yes` of the `members` of a collective, like a council, see the
+	/// collective pallet.
+	Collective { yes: u32, members: u32 },
+}
+
+impl<AccountId: Encode> Encode for RawOrigin<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			RawOrigin::Signed(who) => {
+				dest.push(0);
+				who.encode_to(dest);
+			},
+			RawOrigin::Root => dest.push(1),
+			RawOrigin::None => dest.push(2),
+			RawOrigin::Collective { yes, members } => {
+				dest.push(3);
+				yes.encode_to(dest);
+				members.encode_to(dest);
+			},
+		}
+	}
+}
+
+/// The origin of the calls of the pallets of the runtime `T`.
+pub type OriginFor<T> = RawOrigin<<T as crate::system::Config>::AccountId>;
+
+/// Check that `origin` is a signed origin, and return the account which signed it.
+pub fn ensure_signed<AccountId>(origin: RawOrigin<AccountId>) -> Result<AccountId, DispatchError> {
+	match origin {
+		RawOrigin::Signed(who) => Ok(who),
+		_ => Err(DispatchError::BadOrigin),
+	}
+}
+
+/// Check that `origin` is the root origin.
+pub fn ensure_root<AccountId>(origin: RawOrigin<AccountId>) -> DispatchResult {
+	match origin {
+		RawOrigin::Root => Ok(()),
+		_ => Err(DispatchError::BadOrigin),
+	}
+}
+
+/// Check that `origin` is the none origin.
+pub fn ensure_none<AccountId>(origin: RawOrigin<AccountId>) -> DispatchResult {
+	match origin {
+		RawOrigin::None => Ok(()),
+		_ => Err(DispatchError::BadOrigin),
+	}
+}
+
+/// The outcome of executing a block, returned by `execute_block`. Extrinsics which fail don't
+/// stop the execution of the block, so the result of each extrinsic is recorded, in the order of
+/// the extrinsics of the block.
+#[derive(Debug, Default)]
+pub struct BlockExecutionReport {
+	/// The result of each extrinsic of the block. Inherents are not included.
+	pub results: Vec<DispatchResultWithOutput>,
+}
+
+impl BlockExecutionReport {
+	/// The number of extrinsics which were applied successfully.
+	pub fn succeeded(&self) -> usize {
+		self.results.iter().filter(|result| result.is_ok()).count()
+	}
+
+	/// The number of extrinsics which failed.
+	pub fn failed(&self) -> usize {
+		self.results.len() - self.succeeded()
+	}
+
+	/// Whether every extrinsic of the block was applied successfully.
+	pub fn all_succeeded(&self) -> bool {
+		self.results.iter().all(Result::is_ok)
+	}
+
+	/// The index and the error of each extrinsic which failed.
+	pub fn failures(&self) -> impl Iterator<Item = (usize, &DispatchError)> {
+		self.results.iter().enumerate().filter_map(|(i, result)| Some((i, result.as_ref().err()?)))
+	}
+}
+
+/// A trait which allows us to dispatch an incoming extrinsic to a pallet whose state transition
+/// functions need access to another part of the runtime, like the balances pallet.
+pub trait DispatchWithContext<Context: ?Sized> {
+	/// The type used to identify the origin of the call.
+	type Origin;
+	/// The state transition function call the origin is trying to access.
+	type Call;
+
+	/// This function takes the `context` needed by the pallet, the `origin` of the call and the
+	/// `call` itself, and returns a `Result` based on the outcome of that function call, including
+	/// any value it returned.
+	fn dispatch(
+		&mut self,
+		context: &mut Context,
+		origin: Self::Origin,
+		call: Self::Call,
+	) -> DispatchResultWithOutput;
+}
+
+/// State which can be changed in a transaction, see `with_transaction`. Transactions can be nested:
+/// the changes of a transaction which is committed become part of the transaction around it, and
+/// are undone with it.
+///
+/// The storage of a pallet keeps what each of its writes replaced while a transaction is open, see
+/// `StorageMap`, `StorageSet` and `StorageValue`, so undoing a transaction only costs as much as the
+/// writes it made. `#[macros::storage]` implements this trait for the pallets, and
+/// `#[macros::runtime]` for the runtime, which starts, commits or undoes a transaction in all its
+/// pallets at once.
+pub trait Transactional {
+	/// Start a transaction, nested in the transactions which are already open.
+	fn start_transaction(&mut self);
+
+	/// Keep the changes made since the innermost open transaction was started, and close it.
+	fn commit_transaction(&mut self);
+
+	/// Undo the changes made since the innermost open transaction was started, and close it.
+	fn rollback_transaction(&mut self);
+}
+
+/// Run `f` on `state` as a transaction: when `f` fails, every change it made to `state` is
+/// discarded, so it either happens as a whole or not at all.
+///
+/// The runtime wraps the dispatch of each extrinsic in a transaction, so calls which fail halfway
+/// don't leave partial changes behind.
+pub fn with_transaction<S: Transactional + ?Sized, R, E>(
+	state: &mut S,
+	f: impl FnOnce(&mut S) -> Result<R, E>,
+) -> Result<R, E> {
+	state.start_transaction();
+	let result = f(state);
+	match result {
+		Ok(_) => state.commit_transaction(),
+		Err(_) => state.rollback_transaction(),
+	}
+	result
+}
+
+/// A map in the storage of a pallet. It reads like the `BTreeMap` it dereferences to, but can only
+/// be changed through its own functions, which record the values they replace while a transaction
+/// is open, so the writes can be undone, see `Transactional`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageMap<K, V> {
+	map: BTreeMap<K, V>,
+	// The values replaced by the writes of each open transaction, innermost last, where `None`
+	// means the key was missing. Undoing a transaction puts them back in reverse order.
+	journal: Vec<Vec<(K, Option<V>)>>,
+}
+
+impl<K: Ord + Clone, V: Clone> StorageMap<K, V> {
+	/// Create an empty map.
+	pub fn new() -> Self {
+		Self { map: BTreeMap::new(), journal: Vec::new() }
+	}
+
+	/// Insert `value` under `key`, returning the value which was there before, like
+	/// `BTreeMap::insert`.
+	pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+		self.record(&key);
+		self.map.insert(key, value)
+	}
+
+	/// Remove the value under `key`, returning it, like `BTreeMap::remove`.
+	pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+	{
+		if self.journal.is_empty() {
+			return self.map.remove(key);
+		}
+		let (key, _) = self.map.get_key_value(key)?;
+		let key = key.clone();
+		self.record(&key);
+		self.map.remove::<K>(&key)
+	}
+
+	/// A mutable reference to the value under `key`, like `BTreeMap::get_mut`.
+	pub fn get_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+	where
+		K: Borrow<Q>,
+	{
+		if self.journal.is_empty() {
+			return self.map.get_mut(key);
+		}
+		let (key, _) = self.map.get_key_value(key)?;
+		let key = key.clone();
+		self.record(&key);
+		self.map.get_mut::<K>(&key)
+	}
+
+	/// The entry of `key`, to be changed in place, like `BTreeMap::entry`.
+	pub fn entry(&mut self, key: K) -> btree_map::Entry<'_, K, V> {
+		self.record(&key);
+		self.map.entry(key)
+	}
+
+	/// Keep only the entries for which `f` returns `true`, like `BTreeMap::retain`.
+	pub fn retain(&mut self, mut f: impl FnMut(&K, &V) -> bool) {
+		let removed = self.map.iter().filter(|(key, value)| !f(key, value)).map(|(key, _)| key.clone());
+		for key in removed.collect::<Vec<_>>() {
+			self.remove(&key);
+		}
+	}
+
+	/// Remove every entry, like `BTreeMap::clear`.
+	pub fn clear(&mut self) {
+		if let Some(writes) = self.journal.last_mut() {
+			writes.extend(self.map.iter().map(|(key, value)| (key.clone(), Some(value.clone()))));
+		}
+		self.map.clear();
+	}
+
+	// Record the value under `key` before it is written, if a transaction is open.
+	fn record(&mut self, key: &K) {
+		if let Some(writes) = self.journal.last_mut() {
+			writes.push((key.clone(), self.map.get(key).cloned()));
+		}
+	}
+}
+
+impl<K: Ord, V> Transactional for StorageMap<K, V> {
+	fn start_transaction(&mut self) {
+		self.journal.push(Vec::new());
+	}
+
+	fn commit_transaction(&mut self) {
+		let writes = self.journal.pop().unwrap_or_default();
+		if let Some(outer) = self.journal.last_mut() {
+			outer.extend(writes);
+		}
+	}
+
+	fn rollback_transaction(&mut self) {
+		for (key, value) in self.journal.pop().unwrap_or_default().into_iter().rev() {
+			match value {
+				Some(value) => self.map.insert(key, value),
+				None => self.map.remove(&key),
+			};
+		}
+	}
+}
+
+impl<K, V> core::ops::Deref for StorageMap<K, V> {
+	type Target = BTreeMap<K, V>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.map
+	}
+}
+
+impl<'a, K, V> IntoIterator for &'a StorageMap<K, V> {
+	type Item = (&'a K, &'a V);
+	type IntoIter = btree_map::Iter<'a, K, V>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.map.iter()
+	}
+}
+
+impl<K: Ord + Clone, V: Clone> Default for StorageMap<K, V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// A set in the storage of a pallet. It reads like the `BTreeSet` it dereferences to, but can only
+/// be changed through its own functions, which record what they change while a transaction is
+/// open, so the writes can be undone, see `Transactional`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageSet<T> {
+	set: BTreeSet<T>,
+	// The values inserted or removed by each open transaction, innermost last, with whether they
+	// were in the set before. Undoing a transaction puts them back in reverse order.
+	journal: Vec<Vec<(T, bool)>>,
+}
+
+impl<T: Ord + Clone> StorageSet<T> {
+	/// Create an empty set.
+	pub fn new() -> Self {
+		Self { set: BTreeSet::new(), journal: Vec::new() }
+	}
+
+	/// Insert `value`, returning whether it was missing, like `BTreeSet::insert`.
+	pub fn insert(&mut self, value: T) -> bool {
+		let inserted = self.set.insert(value.clone());
+		if let (true, Some(writes)) = (inserted, self.journal.last_mut()) {
+			writes.push((value, false));
+		}
+		inserted
+	}
+
+	/// Remove `value`, returning whether it was in the set, like `BTreeSet::remove`.
+	pub fn remove(&mut self, value: &T) -> bool {
+		let removed = self.set.remove(value);
+		if let (true, Some(writes)) = (removed, self.journal.last_mut()) {
+			writes.push((value.clone(), true));
+		}
+		removed
+	}
+}
+
+impl<T: Ord> Transactional for StorageSet<T> {
+	fn start_transaction(&mut self) {
+		self.journal.push(Vec::new());
+	}
+
+	fn commit_transaction(&mut self) {
+		let writes = self.journal.pop().unwrap_or_default();
+		if let Some(outer) = self.journal.last_mut() {
+			outer.extend(writes);
+		}
+	}
+
+	fn rollback_transaction(&mut self) {
+		for (value, present) in self.journal.pop().unwrap_or_default().into_iter().rev() {
+			if present {
+				self.set.insert(value);
+			} else {
+				self.set.remove(&value);
+			}
+		}
+	}
+}
+
+impl<T> core::ops::Deref for StorageSet<T> {
+	type Target = BTreeSet<T>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.set
+	}
+}
+
+impl<'a, T> IntoIterator for &'a StorageSet<T> {
+	type Item = &'a T;
+	type IntoIter = btree_set::Iter<'a, T>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.set.iter()
+	}
+}
+
+impl<T: Ord + Clone> Default for StorageSet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// A single value in the storage of a pallet, like a counter. It reads like the value it
+/// dereferences to, but can only be changed through its own functions, which record the value
+/// they replace while a transaction is open, so the writes can be undone, see `Transactional`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StorageValue<T> {
+	value: T,
+	// The value at the start of each open transaction, innermost last, recorded by the first write
+	// in the transaction. `None` means the transaction didn't write the value.
+	journal: Vec<Option<T>>,
+}
+
+impl<T: Clone> StorageValue<T> {
+	/// Create a storage value holding `value`.
+	pub fn new(value: T) -> Self {
+		Self { value, journal: Vec::new() }
+	}
+
+	/// Replace the value with `value`.
+	pub fn set(&mut self, value: T) {
+		self.record();
+		self.value = value;
+	}
+
+	/// A mutable reference to the value, to be changed in place.
+	pub fn get_mut(&mut self) -> &mut T {
+		self.record();
+		&mut self.value
+	}
+
+	// Record the value before it is first written in the innermost open transaction.
+	fn record(&mut self) {
+		if let Some(start @ None) = self.journal.last_mut() {
+			*start = Some(self.value.clone());
+		}
+	}
+}
+
+impl<T> Transactional for StorageValue<T> {
+	fn start_transaction(&mut self) {
+		self.journal.push(None);
+	}
+
+	fn commit_transaction(&mut self) {
+		// The transaction around it keeps the value from its own start, if it wrote it already.
+		if let (Some(Some(start)), Some(outer @ None)) = (self.journal.pop(), self.journal.last_mut()) {
+			*outer = Some(start);
+		}
+	}
+
+	fn rollback_transaction(&mut self) {
+		if let Some(Some(start)) = self.journal.pop() {
+			self.value = start;
+		}
+	}
+}
+
+impl<T> core::ops::Deref for StorageValue<T> {
+	type Target = T;
+
+	fn deref(&self) -> &Self::Target {
+		&self.value
+	}
+}
+
+/// A trait which allows a pallet to dispatch calls of the runtime itself, like the calls of a
+/// batch. `#[macros::runtime]` implements it for the runtime, which is the context of the pallets
+/// declared with `#[dispatch_context(self)]`.
+pub trait DispatchCall<Origin, Call> {
+	/// Dispatch `call` with `origin`, like `Dispatch::dispatch`.
+	fn dispatch_call(&mut self, origin: Origin, call: Call) -> DispatchResultWithOutput;
+
+	/// Run `f` as a transaction: when it fails, every change it made to the runtime is reverted,
+	/// so it either happens as a whole or not at all.
+	fn transactional(
+		&mut self,
+		f: &mut dyn FnMut(&mut dyn DispatchCall<Origin, Call>) -> DispatchResult,
+	) -> DispatchResult;
+}
+
+/// A trait which lets the sudo account reap an account from every pallet of the runtime at once,
+/// see `sudo::Pallet::kill_account`. The runtime implements it, since only the runtime knows which
+/// of its pallets can hold state for an account.
+pub trait KillAccount<AccountId> {
+	/// Remove every trace of `who` from the runtime, and reset their nonce. This is refused, with
+	/// nothing removed, while any pallet still holds state for them.
+	fn kill_account(&mut self, who: &AccountId) -> DispatchResult;
+}
+
+/// The pallets of a runtime which are dispatching a call through the runtime, like a batch of the
+/// utility pallet. `#[macros::runtime]` keeps it for the pallets declared with
+/// `#[dispatch_context(self)]`, which are taken out of the runtime while their call is dispatched:
+/// a call of such a pallet made by its own call would reach the `Default` standing in for it, so it
+/// is refused with `DispatchError::Reentrancy` instead.
+#[derive(Debug, Clone, Default)]
+pub struct Dispatching {
+	pallets: Vec<&'static str>,
+}
+
+impl Dispatching {
+	/// Record that `pallet` is dispatching a call. This fails if it already is.
+	pub fn enter(&mut self, pallet: &'static str) -> DispatchResult {
+		if self.pallets.contains(&pallet) {
+			return Err(DispatchError::Reentrancy(pallet));
+		}
+		self.pallets.push(pallet);
+		Ok(())
+	}
+
+	/// Record that `pallet` is done dispatching its call.
+	pub fn exit(&mut self, pallet: &'static str) {
+		self.pallets.retain(|other| *other != pallet);
+	}
+}
+
+/// The weight of a call, which is an abstract measure of the resources needed to execute it.
+pub type Weight = u64;
+
+/// The weight of a nanosecond of execution, which converts the times measured by the benchmarks
+/// of the calls into the weights of `weights`.
+pub const WEIGHT_PER_NANOS: Weight = 10;
+
+/// The weight a call actually used, which calls can return when they used less than the weight
+/// declared by their `#[weight]` attribute. The part of the fee paid for the unused weight is
+/// refunded to the caller, see `ChargeTransaction::refund_transaction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActualWeight(pub Weight);
+
+/// The value produced by a successfully dispatched call. Calls which return `DispatchResult`
+/// produce `None`, while calls which return `Result<R, DispatchError>` produce their boxed `R`.
+pub type DispatchOutput = Option<Box<dyn core::any::Any>>;
+
+/// Box the value returned by a call into its `DispatchOutput`. This is used by the code generated
+/// by `#[macros::call]`, so pallets don't need to import `Box` themselves under `no_std`.
+pub fn dispatch_output<R: 'static>(value: R) -> DispatchOutput {
+	Some(Box::new(value))
+}
+
+/// The Result type of dispatching a call, which includes the value produced by the call.
+pub type DispatchResultWithOutput = Result<DispatchOutput, DispatchError>;
 
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
 pub trait Dispatch {
-	/// The type used to identify the caller of the function.
+	/// The type used to identify the origin of the call.
+	type Origin;
+	/// The state transition function call the origin is trying to access.
+	type Call;
+
+	/// This function takes the `origin` of a call and the `call` itself, and returns a `Result`
+	/// based on the outcome of that function call, including any value it returned.
+	fn dispatch(&mut self, origin: Self::Origin, call: Self::Call) -> DispatchResultWithOutput;
+}
+
+/// A trait which allows the runtime to charge a fee to the caller of an extrinsic, before the call
+/// is dispatched. If the fee cannot be paid, the call is not dispatched.
+pub trait ChargeTransaction {
+	/// The type used to identify the caller paying the fee.
 	type Caller;
-	/// The state transition function call the caller is trying to access.
+	/// The type used to represent the tip paid on top of the fee.
+	type Balance;
+
+	/// This function takes the `caller` of an extrinsic, the `weight` of its call and the `tip`
+	/// they offered, and returns an error if they cannot pay the transaction fee plus the tip.
+	fn charge_transaction(
+		&mut self,
+		caller: &Self::Caller,
+		weight: Weight,
+		tip: Self::Balance,
+	) -> DispatchResult;
+
+	/// This function is called after the call of an extrinsic is dispatched, when the call
+	/// returned an `ActualWeight` lower than its declared `weight`. It refunds the `caller` the
+	/// part of the fee which was paid for the unused weight. The tip is never refunded.
+	fn refund_transaction(&mut self, caller: &Self::Caller, weight: Weight, actual_weight: Weight);
+}
+
+/// A trait for a currency held by accounts, of which they can reserve part as a deposit. It is
+/// implemented by the balances pallet, so other pallets can move funds and take deposits without
+/// depending on how balances are stored.
+pub trait Currency<AccountId> {
+	/// The type used to represent an amount of the currency.
+	type Balance: Zero + Copy + PartialOrd;
+
+	/// The balance of `who` which is free to be transferred or reserved.
+	fn free_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// The balance of `who` which is reserved as deposits.
+	fn reserved_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// Move `amount` from the free balance of `from` to the free balance of `to`, or return an error
+	/// if `from` cannot pay it.
+	fn transfer(&mut self, from: &AccountId, to: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Move `amount` from the free balance of `who` to their reserved balance, or return an error if
+	/// they cannot pay it.
+	fn reserve(&mut self, who: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Move up to `amount` from the reserved balance of `who` back to their free balance, and return
+	/// the amount which was actually unreserved.
+	fn unreserve(&mut self, who: &AccountId, amount: Self::Balance) -> Self::Balance;
+}
+
+/// A trait which allows the runtime to let its pallets act at the end of a block, after every
+/// extrinsic of the block is applied. It is implemented by `#[macros::runtime]`, which calls
+/// `Hooks::on_finalize` on each pallet declared with `#[hooks]`.
+pub trait FinalizeBlock {
+	/// This function is called once at the end of every block.
+	fn finalize_block(&mut self);
+}
+
+/// A trait which allows the runtime to commit to its state, so the header of a block can commit to
+/// the state left by the block.
+pub trait StateRoot {
+	/// The root of the current state of the runtime, which is usually the root of the state of
+	/// its pallets, see `storage::InMemoryBackend::root`.
+	fn state_root(&self) -> Hash;
+}
+
+/// A trait which allows the runtime to observe every call it dispatches, like to build a trace of
+/// the calls in a block. Both functions do nothing by default, so a runtime which doesn't need
+/// them only has to implement the trait without overriding them.
+pub trait PreDispatch {
+	/// The type used to identify the origin of the call.
+	type Origin;
+	/// The call which is being dispatched.
 	type Call;
 
-	/// This function takes a `caller` and the `call` they want to make, and returns a `Result`
-	/// based on the outcome of that function call.
-	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
-}
\ No newline at end of file
+	/// This function is called with the `origin` and the `call` it wants to make, before the call
+	/// is routed to its pallet.
+	fn pre_dispatch(&self, _origin: &Self::Origin, _call: &Self::Call) {}
+
+	/// This function is called with the `result` of the call, after it was dispatched.
+	fn post_dispatch(&self, _result: &DispatchResultWithOutput) {}
+}
+
+/// A trait which allows the runtime to refuse calls before they are dispatched, like the calls
+/// paused by the transaction pause pallet. Every call passes by default, so a runtime which doesn't
+/// filter calls only has to implement the trait without overriding `allows`.
+pub trait CallFilter<Call> {
+	/// Whether `call` may be dispatched. The calls of the root origin are dispatched without
+	/// consulting the filter.
+	fn allows(&self, _call: &Call) -> bool {
+		true
+	}
+}
+
+/// The pallets with callable functions, which `#[macros::call]` implements this trait for.
+/// `#[macros::runtime]` requires it of every pallet besides the system pallet, so a pallet missing
+/// its callable functions is reported where it is included in the runtime.
+#[diagnostic::on_unimplemented(
+	message = "`{Self}` has no callable functions",
+	label = "this pallet needs an `impl` block with `#[macros::call]`"
+)]
+pub trait Callable {
+	/// The calls of the pallet, which is the `Call` generated by `#[macros::call]`.
+	type Call;
+}
+
+/// A trait which tells which pallet and which function a call is for. It is implemented by
+/// `#[macros::runtime]` for its `RuntimeCall`, so pallets can filter the calls of the runtime
+/// without knowing its other pallets, like the calls a proxy is allowed to make.
+pub trait CallMetadata {
+	/// The name of the field of the pallet in the runtime, like `balances`.
+	fn pallet_name(&self) -> &'static str;
+
+	/// The name of the function of the pallet, like `transfer`.
+	fn function_name(&self) -> &'static str;
+}
+
+/// A filter on calls, like the calls a type of proxy is allowed to make.
+pub trait InstanceFilter<Call> {
+	/// Whether `call` passes the filter.
+	fn filter(&self, call: &Call) -> bool;
+}
+
+/// An instance of a pallet which can be included several times in a runtime, like a balances
+/// pallet for each of two tokens. Such a pallet has a `Config<I>` generic over its instance, which
+/// is `()` for a pallet included once, and keeps the state of each instance under its own keys.
+pub trait Instance: 'static {
+	/// The prefix added to the name of the pallet in the storage keys of this instance. It is empty
+	/// for `()`, so a pallet included once keeps the keys it had before it was instantiable.
+	const PREFIX: &'static str;
+}
+
+impl Instance for () {
+	const PREFIX: &'static str = "";
+}
+
+/// The first instance of a pallet besides `()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instance1;
+
+impl Instance for Instance1 {
+	const PREFIX: &'static str = "Instance1";
+}
+
+/// The second instance of a pallet besides `()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instance2;
+
+impl Instance for Instance2 {
+	const PREFIX: &'static str = "Instance2";
+}
+
+/// A trait which allows a pallet to act at the start and at the end of every block, like to expire
+/// what was due in the block. Both functions do nothing by default.
+///
+/// The hooks of the pallets declared with `#[hooks]` in the runtime are called by the
+/// `InitializeBlock` and `FinalizeBlock` implementations generated by `#[macros::runtime]`, in the
+/// order the pallets are declared.
+pub trait Hooks<BlockNumber> {
+	/// This function is called at the start of block `block_number`, after the block number is
+	/// incremented and before any extrinsic of the block is applied.
+	fn on_initialize(&mut self, _block_number: BlockNumber) {}
+
+	/// This function is called at the end of block `block_number`, after every extrinsic of the
+	/// block is applied.
+	fn on_finalize(&mut self, _block_number: BlockNumber) {}
+}
+
+/// A trait like `Hooks`, for pallets whose hooks need access to another part of the runtime, like
+/// the balances pallet. The runtime provides that context to the pallets declared with
+/// `#[hooks(field)]`.
+pub trait HooksWithContext<BlockNumber, Context> {
+	/// This function is called with the `context` of the pallet at the start of block
+	/// `block_number`, like `Hooks::on_initialize`.
+	fn on_initialize(&mut self, _context: &mut Context, _block_number: BlockNumber) {}
+
+	/// This function is called with the `context` of the pallet at the end of block
+	/// `block_number`, like `Hooks::on_finalize`.
+	fn on_finalize(&mut self, _context: &mut Context, _block_number: BlockNumber) {}
+}
+
+/// A trait which allows the runtime to prepare its pallets for a new block, after the block number
+/// is incremented and before any extrinsic of the block is applied. It is implemented by
+/// `#[macros::runtime]`, which calls `Hooks::on_initialize` on each pallet declared with `#[hooks]`.
+pub trait InitializeBlock {
+	/// This function is called once at the start of every block.
+	fn initialize_block(&mut self);
+}
+
+/// The phase of the execution of a block in which an event was emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+	/// The block is being initialized, before any extrinsic is applied.
+	Initialization,
+	/// The extrinsic at this index of the block is being applied.
+	ApplyExtrinsic(u32),
+	/// The block is being finalized, after every extrinsic was applied.
+	Finalization,
+}
+
+/// An event, together with the phase of the block in which it was emitted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRecord<Event> {
+	pub phase: Phase,
+	pub event: Event,
+}
+
+/// The events emitted by a pallet, with the phase of the block being executed, which every event is
+/// recorded with. Pallets keep it in a field declared in `#[macros::storage]`, which generates the
+/// functions the runtime uses to move the events to the system pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Events<Event> {
+	phase: Phase,
+	records: Vec<EventRecord<Event>>,
+	// The number of events at the start of each open transaction, innermost last. Events are only
+	// recorded at the end, so undoing a transaction forgets the events recorded after it started.
+	journal: Vec<usize>,
+}
+
+impl<Event> Events<Event> {
+	/// Create an empty list of events, recorded in the initialization phase until another phase is
+	/// set.
+	pub fn new() -> Self {
+		Self { phase: Phase::Initialization, records: Vec::new(), journal: Vec::new() }
+	}
+
+	/// The events recorded so far with the phase they were emitted in, oldest first.
+	pub fn records(&self) -> &[EventRecord<Event>] {
+		&self.records
+	}
+
+	/// Take the events recorded since they were last taken.
+	pub fn take(&mut self) -> Vec<EventRecord<Event>> {
+		core::mem::take(&mut self.records)
+	}
+
+	/// Set the phase of the block being executed, which the next events are recorded with.
+	pub fn set_phase(&mut self, phase: Phase) {
+		self.phase = phase;
+	}
+
+	/// Record `event` with the current phase.
+	pub fn deposit(&mut self, event: Event) {
+		self.records.push(EventRecord { phase: self.phase, event });
+	}
+}
+
+impl<Event> Transactional for Events<Event> {
+	fn start_transaction(&mut self) {
+		self.journal.push(self.records.len());
+	}
+
+	fn commit_transaction(&mut self) {
+		self.journal.pop();
+	}
+
+	fn rollback_transaction(&mut self) {
+		if let Some(len) = self.journal.pop() {
+			self.records.truncate(len);
+		}
+	}
+}
+
+impl<Event> Default for Events<Event> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// A trait which allows the runtime to tell its pallets which phase of the block is being executed,
+/// so the events they emit are recorded with that phase.
+pub trait EnterPhase {
+	/// This function is called before the block is initialized, before each of its extrinsics is
+	/// applied, and before the block is finalized.
+	fn enter_phase(&mut self, phase: Phase);
+}
+
+/// Format an account id to be shown to users. Long ids, like public keys, are shortened to their
+/// first and last characters so they can be lined up in tables.
+pub fn fmt_account(id: &impl core::fmt::Display) -> String {
+	const MAX_LEN: usize = 16;
+	const EDGE_LEN: usize = 6;
+
+	let id = id.to_string();
+	let chars = id.chars().collect::<Vec<_>>();
+	if chars.len() <= MAX_LEN {
+		return id
+	}
+	let start = chars[..EDGE_LEN].iter().collect::<String>();
+	let end = chars[chars.len() - EDGE_LEN..].iter().collect::<String>();
+	alloc::format!("{start}…{end}")
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{with_transaction, Events, StorageMap, StorageSet, StorageValue};
+
+	#[test]
+	fn storage_maps_undo_the_writes_of_a_failed_transaction() {
+		let mut map = StorageMap::new();
+		map.insert("alice", 10);
+		map.insert("bob", 20);
+		let before = map.clone();
+
+		let result = with_transaction(&mut map, |map| {
+			map.insert("alice", 11);
+			map.remove(&"bob");
+			map.insert("charlie", 30);
+			*map.entry("charlie").or_default() += 1;
+			map.retain(|_, value| *value > 10);
+			Err::<(), _>("failed")
+		});
+		assert_eq!(result, Err("failed"));
+		assert_eq!(*map, *before);
+
+		assert_eq!(with_transaction(&mut map, |map| Ok::<_, ()>(map.insert("bob", 21))), Ok(Some(20)));
+		assert_eq!(map.get("bob"), Some(&21));
+	}
+
+	#[test]
+	fn nested_transactions_commit_into_the_outer_transaction() {
+		let mut value = StorageValue::new(1);
+
+		// The inner transaction is undone on its own when it fails.
+		let result = with_transaction(&mut value, |value| {
+			value.set(2);
+			let inner = with_transaction(value, |value| {
+				value.set(3);
+				Err::<(), _>("failed")
+			});
+			assert_eq!((inner, **value), (Err("failed"), 2));
+			Ok::<_, &str>(())
+		});
+		assert_eq!((result, *value), (Ok(()), 2));
+
+		// Once committed, the writes of the inner transaction are undone with the outer one.
+		let result = with_transaction(&mut value, |value| {
+			with_transaction(value, |value| {
+				value.set(4);
+				*value.get_mut() += 1;
+				Ok(())
+			})?;
+			Err::<(), _>("failed")
+		});
+		assert_eq!((result, *value), (Err("failed"), 2));
+	}
+
+	#[test]
+	fn storage_sets_undo_the_values_a_transaction_inserted_or_removed() {
+		let mut set = StorageSet::new();
+		set.insert("alice");
+
+		let result = with_transaction(&mut set, |set| {
+			// Inserting a value which is already there changes nothing, so it is not undone.
+			assert!(!set.insert("alice"));
+			with_transaction(set, |set| {
+				set.remove(&"alice");
+				set.insert("bob");
+				Ok(())
+			})?;
+			Err::<(), _>("failed")
+		});
+		assert_eq!(result, Err("failed"));
+		assert_eq!(set.iter().copied().collect::<Vec<_>>(), ["alice"]);
+	}
+
+	#[test]
+	fn events_of_a_failed_transaction_are_dropped() {
+		let mut events = Events::new();
+		events.deposit("kept");
+		let result = with_transaction(&mut events, |events| {
+			events.deposit("dropped");
+			Err::<(), _>("failed")
+		});
+		assert_eq!(result, Err("failed"));
+		assert_eq!(events.take().into_iter().map(|record| record.event).collect::<Vec<_>>(), ["kept"]);
+	}
+}
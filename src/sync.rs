@@ -0,0 +1,290 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::support;
+use crate::{system, types, RuntimeCall};
+
+/// The most headers asked for by a single request.
+pub const MAX_HEADERS_PER_REQUEST: u32 = 128;
+/// The most bodies asked for by a single request, which is the size of the batches of blocks
+/// handed to the import queue.
+pub const MAX_BODIES_PER_REQUEST: u32 = 8;
+
+/// The body of a block: its inherents and its extrinsics, which the header commits to with its
+/// extrinsics root.
+pub type Body = (Vec<RuntimeCall>, Vec<types::SignedExtrinsic>);
+
+/// A request of `ChainSync` to the peer it syncs from, for the headers or the bodies of the blocks
+/// from `from` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncRequest {
+	Headers { from: types::BlockNumber, count: u32 },
+	Bodies { from: types::BlockNumber, count: u32 },
+}
+
+/// The reasons the response of a peer is refused. The peer is not synced from any more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncError {
+	/// There was no request waiting for this response.
+	Unexpected,
+	/// The peer didn't send any of the headers or bodies requested.
+	Empty,
+	/// The peer sent more headers or bodies than requested.
+	TooMany,
+	/// The header with this number doesn't follow the header before it.
+	UnlinkedHeader(types::BlockNumber),
+	/// The body of the block with this number doesn't match the extrinsics root of its header.
+	BodyMismatch(types::BlockNumber),
+}
+
+impl fmt::Display for SyncError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SyncError::Unexpected => write!(f, "unexpected response"),
+			SyncError::Empty => write!(f, "none of the blocks requested were sent"),
+			SyncError::TooMany => write!(f, "more blocks were sent than requested"),
+			SyncError::UnlinkedHeader(number) => write!(f, "header #{number} does not follow the header before it"),
+			SyncError::BodyMismatch(number) => write!(f, "the body of block #{number} does not match its header"),
+		}
+	}
+}
+
+/// How far a sync went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncProgress {
+	/// The number of the best block imported.
+	pub best: types::BlockNumber,
+	/// The number of the block the sync aims for, which is the best block of the peer.
+	pub target: types::BlockNumber,
+	/// The number of headers downloaded whose block wasn't imported yet.
+	pub headers: usize,
+}
+
+impl fmt::Display for SyncProgress {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "#{} of #{}, {} headers ahead", self.best, self.target, self.headers)
+	}
+}
+
+/// Catches a node up with a peer whose best block is higher, like a fresh node joining a chain.
+///
+/// The headers of the missing blocks are downloaded first, up to the best block of the peer, and
+/// each one must link to the one before it, so the peer can't send blocks of an unrelated chain.
+/// Then the bodies are downloaded in batches of `MAX_BODIES_PER_REQUEST`, in order, and each body
+/// must match the extrinsics root of its header. The blocks of a batch are handed to the import
+/// queue, which executes them.
+///
+/// `ChainSync` doesn't know about the network: it tells which request to send next, and is given
+/// the responses. There is at most one request waiting at a time.
+#[derive(Default)]
+pub struct ChainSync {
+	/// The number of the best block of the peer synced from.
+	target: types::BlockNumber,
+	/// The headers downloaded whose body wasn't downloaded yet, in order.
+	headers: VecDeque<types::Header>,
+	/// The request waiting for its response, with the number and hash of the header which the
+	/// headers requested must follow.
+	pending: Option<(SyncRequest, types::BlockNumber, system::Hash)>,
+}
+
+impl ChainSync {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Whether blocks are missing up to the target, see `set_target`, after the best block `best`.
+	pub fn is_syncing(&self, best: types::BlockNumber) -> bool {
+		self.target > best
+	}
+
+	/// How far the sync went, with the best block `best`, if it is syncing.
+	pub fn progress(&self, best: types::BlockNumber) -> Option<SyncProgress> {
+		self.is_syncing(best).then_some(SyncProgress { best, target: self.target, headers: self.headers.len() })
+	}
+
+	/// Sync up to the block with number `target`, which a peer announced. A lower target than the
+	/// current one is ignored.
+	pub fn set_target(&mut self, target: types::BlockNumber) {
+		self.target = self.target.max(target);
+	}
+
+	/// The next request to send to the peer, after the best block with `best_number` and
+	/// `best_hash`, if no request is waiting and blocks are missing.
+	///
+	/// Headers are requested until the target is reached, then bodies.
+	pub fn next_request(&mut self, best_number: types::BlockNumber, best_hash: system::Hash) -> Option<SyncRequest> {
+		if self.pending.is_some() {
+			return None;
+		}
+		// The blocks imported meanwhile, like the ones gossiped, don't need a body any more.
+		while self.headers.front().is_some_and(|header| header.block_number <= best_number) {
+			self.headers.pop_front();
+		}
+		let (last_number, last_hash) =
+			self.headers.back().map_or((best_number, best_hash), |header| (header.block_number, header.hash()));
+		let request = if last_number < self.target {
+			let count = (self.target - last_number).min(MAX_HEADERS_PER_REQUEST);
+			SyncRequest::Headers { from: last_number + 1, count }
+		} else {
+			let first = self.headers.front()?;
+			let count = (self.headers.len() as u32).min(MAX_BODIES_PER_REQUEST);
+			SyncRequest::Bodies { from: first.block_number, count }
+		};
+		self.pending = Some((request, last_number, last_hash));
+		Some(request)
+	}
+
+	/// Forget the request waiting, whose peer disconnected, so it is sent again to another peer.
+	pub fn cancel(&mut self) {
+		self.pending = None;
+	}
+
+	/// Drop everything downloaded, after a peer sent an invalid response, and aim for the best
+	/// block `best` until another peer announces a higher one.
+	pub fn reset(&mut self, best: types::BlockNumber) {
+		*self = Self { target: best, ..Self::default() };
+	}
+
+	/// Add the headers sent in response to a `SyncRequest::Headers`. They must follow the last
+	/// header downloaded, or the best block, in order.
+	pub fn on_headers(&mut self, headers: Vec<types::Header>) -> Result<(), SyncError> {
+		let Some((SyncRequest::Headers { count, .. }, mut number, mut hash)) = self.pending.take() else {
+			return Err(SyncError::Unexpected);
+		};
+		if headers.is_empty() {
+			return Err(SyncError::Empty);
+		}
+		if headers.len() > count as usize {
+			return Err(SyncError::TooMany);
+		}
+		for header in &headers {
+			if header.block_number != number + 1 || header.parent_hash != hash {
+				return Err(SyncError::UnlinkedHeader(header.block_number));
+			}
+			(number, hash) = (header.block_number, header.hash());
+		}
+		self.headers.extend(headers);
+		Ok(())
+	}
+
+	/// Match the bodies sent in response to a `SyncRequest::Bodies` with their headers, in order,
+	/// and return the blocks they make, for the import queue.
+	pub fn on_bodies(&mut self, bodies: Vec<Body>) -> Result<Vec<types::Block>, SyncError> {
+		let Some((SyncRequest::Bodies { from, count }, ..)) = self.pending.take() else {
+			return Err(SyncError::Unexpected);
+		};
+		if bodies.is_empty() {
+			return Err(SyncError::Empty);
+		}
+		if bodies.len() > count as usize {
+			return Err(SyncError::TooMany);
+		}
+		// The headers requested are still the first ones, since only this response removes them.
+		debug_assert_eq!(self.headers.front().map(|header| header.block_number), Some(from));
+		for (header, (inherents, extrinsics)) in self.headers.iter().zip(&bodies) {
+			if header.extrinsics_root != support::extrinsics_root(inherents, extrinsics) {
+				return Err(SyncError::BodyMismatch(header.block_number));
+			}
+		}
+		let headers = self.headers.drain(..bodies.len());
+		Ok(headers
+			.zip(bodies)
+			.map(|(header, (inherents, extrinsics))| types::Block { header, inherents, extrinsics })
+			.collect())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ChainSync, SyncError, SyncProgress, SyncRequest};
+	use crate::block_builder::BlockBuilder;
+	use crate::chain::Chain;
+	use crate::chain_store::ChainStore;
+	use crate::import_queue::ImportQueue;
+	use crate::storage::InMemoryBackend;
+	use crate::{consensus, development_genesis, timestamp, types, Runtime, RuntimeCall, DEV_KEYSTORE};
+
+	fn genesis() -> Runtime {
+		let mut runtime = Runtime::new();
+		development_genesis().build(&mut runtime).unwrap();
+		runtime
+	}
+
+	// The blocks of a chain of `count` empty blocks on top of the genesis.
+	fn chain_of(count: usize) -> Vec<types::Block> {
+		let mut runtime = genesis();
+		(0..count)
+			.map(|_| {
+				let mut block = BlockBuilder::new(&runtime, runtime.system.max_block_weight()).seal(vec![]).unwrap();
+				DEV_KEYSTORE.with(|keystore| consensus::seal_block(&runtime, &mut block, keystore)).unwrap();
+				runtime.execute_block(&block).unwrap();
+				block
+			})
+			.collect()
+	}
+
+	#[test]
+	fn headers_are_downloaded_first_then_bodies_in_batches() {
+		let blocks = chain_of(10);
+		let mut chain = Chain::new(genesis(), ChainStore::new(InMemoryBackend::new())).unwrap();
+		let genesis_hash = chain.canonical_hash(0).unwrap();
+		let mut sync = ChainSync::new();
+		assert_eq!(sync.next_request(0, genesis_hash), None);
+		sync.set_target(10);
+
+		assert_eq!(sync.next_request(0, genesis_hash), Some(SyncRequest::Headers { from: 1, count: 10 }));
+		// Only one request is waiting at a time.
+		assert_eq!(sync.next_request(0, genesis_hash), None);
+		let headers = blocks.iter().map(|block| block.header.clone()).collect();
+		assert_eq!(sync.on_headers(headers), Ok(()));
+		assert_eq!(sync.progress(0), Some(SyncProgress { best: 0, target: 10, headers: 10 }));
+
+		let mut queue = ImportQueue::new();
+		for (from, count) in [(1, 8), (9, 2)] {
+			let (best_number, best_hash) = chain.best_block();
+			assert_eq!(sync.next_request(best_number, best_hash), Some(SyncRequest::Bodies { from, count }));
+			let range = from as usize - 1..(from + count) as usize - 1;
+			let bodies = blocks[range].iter().map(|block| (block.inherents.clone(), block.extrinsics.clone())).collect();
+			for block in sync.on_bodies(bodies).unwrap() {
+				queue.push(block);
+			}
+			assert!(queue.import(&mut chain).iter().all(|(_, outcome)| outcome.is_ok()));
+			assert_eq!(chain.best_block().0, from + count - 1);
+		}
+		assert_eq!(chain.best_block(), (10, blocks[9].header.hash()));
+		assert_eq!(sync.progress(10), None);
+		assert_eq!(sync.next_request(10, blocks[9].header.hash()), None);
+	}
+
+	#[test]
+	fn invalid_responses_are_refused() {
+		let blocks = chain_of(3);
+		let genesis_hash = genesis().system.best_block_hash();
+		let mut sync = ChainSync::new();
+		sync.set_target(3);
+		assert_eq!(sync.on_headers(vec![]), Err(SyncError::Unexpected));
+
+		// The headers must follow the best block, one after the other.
+		let headers = |indices: &[usize]| indices.iter().map(|&i| blocks[i].header.clone()).collect::<Vec<_>>();
+		for (sent, error) in [
+			(headers(&[]), SyncError::Empty),
+			(headers(&[0, 1, 2, 0]), SyncError::TooMany),
+			(headers(&[1, 2]), SyncError::UnlinkedHeader(2)),
+			(headers(&[0, 2]), SyncError::UnlinkedHeader(3)),
+		] {
+			sync.next_request(0, genesis_hash).unwrap();
+			assert_eq!(sync.on_headers(sent), Err(error));
+		}
+
+		// A body must match its header.
+		assert_eq!(sync.next_request(0, genesis_hash), Some(SyncRequest::Headers { from: 1, count: 3 }));
+		sync.on_headers(headers(&[0, 1, 2])).unwrap();
+		assert_eq!(sync.next_request(0, genesis_hash), Some(SyncRequest::Bodies { from: 1, count: 3 }));
+		let mut body = (blocks[0].inherents.clone(), blocks[0].extrinsics.clone());
+		body.0.push(RuntimeCall::timestamp(timestamp::Call::set { now: 6_000 }));
+		assert_eq!(sync.on_bodies(vec![body]).err(), Some(SyncError::BodyMismatch(1)));
+
+		sync.reset(0);
+		assert_eq!(sync.next_request(0, genesis_hash), None);
+	}
+}
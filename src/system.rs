@@ -1,24 +1,134 @@
-use std::{collections::BTreeMap, ops::AddAssign};
-use num::traits::{Zero, One};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use num::traits::{CheckedAdd, One, Saturating, Zero};
+
+use crate::codec::{self, Decode, Encode};
+use crate::crypto::Public;
+use crate::storage::{storage_key, storage_map_key, StorageBackend};
+use crate::support::{ArithmeticError, DispatchError, DispatchResult, EventRecord, StorageMap, Weight};
 
 pub trait Config {
     type AccountId: Ord + Clone;
-    type BlockNumber: Zero + One + AddAssign + Copy; 
+    type BlockNumber: Zero + One + CheckedAdd + Saturating + Copy + Ord;
     type Nonce: Zero + One + Copy;
+    /// The events of every pallet of the runtime, which is usually the `RuntimeEvent` generated by
+    /// `#[macros::runtime]`.
+    type RuntimeEvent;
+    /// The hash function of the runtime, like to hash the content claimed with the proof of
+    /// existence pallet.
+    type Hashing: crate::hashing::Hasher;
+    /// The SS58 prefix of the network, which addresses are shown and parsed with when the account
+    /// ids are public keys.
+    const SS58_PREFIX: u16 = crate::crypto::DEFAULT_SS58_PREFIX;
+    /// The public key which checks the signatures of `who` when none was set with
+    /// `set_public_key`, like when account ids are public keys themselves. By default there is none,
+    /// so the key of every account must be set.
+    fn account_public_key(_who: &Self::AccountId) -> Option<Public> {
+        None
+    }
+    /// Whether the block number stays at its maximum value instead of failing when it overflows.
+    const SATURATING_BLOCK_NUMBER: bool = false;
+    /// The maximum total weight of the calls of the extrinsics of a block. Blocks which are
+    /// heavier are refused. The genesis config of a chain can set another limit, see
+    /// `Pallet::max_block_weight`.
+    const MAX_BLOCK_WEIGHT: Weight = Weight::MAX;
+    /// The version of the runtime, which a new release of the runtime bumps, see `RuntimeVersion`.
+    const VERSION: RuntimeVersion = RuntimeVersion { spec_version: 1, impl_version: 1, transaction_version: 1 };
+}
+
+/// The version of a runtime, which the system pallet stores so a chain knows which runtime built
+/// its state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeVersion {
+    /// The version of the behaviour of the runtime, bumped whenever a block executes differently.
+    pub spec_version: u32,
+    /// The version of the implementation of the runtime, bumped by changes which don't change its
+    /// behaviour, like optimizations.
+    pub impl_version: u32,
+    /// The version of the calls of the runtime, bumped whenever their encoding changes, like when a
+    /// call is added, removed or reordered. Extrinsics encoded for another transaction version are
+    /// refused, see `support::TransactionVersion`.
+    pub transaction_version: u32,
+}
+
+impl Encode for RuntimeVersion {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.spec_version.encode_to(dest);
+        self.impl_version.encode_to(dest);
+        self.transaction_version.encode_to(dest);
+    }
 }
 
-#[derive(Debug)]
-pub struct Pallet<T: Config> { 
+impl Decode for RuntimeVersion {
+    fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+        Ok(Self {
+            spec_version: u32::decode(input)?,
+            impl_version: u32::decode(input)?,
+            transaction_version: u32::decode(input)?,
+        })
+    }
+}
+
+/// The hash of a block.
+pub type Hash = [u8; 32];
+
+/// The events of the system pallet, which `execute_block` deposits after each extrinsic of a
+/// block with the outcome of the extrinsic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// An extrinsic was applied successfully.
+    ExtrinsicSuccess,
+    /// An extrinsic failed. Its changes were discarded, but it stays in the block.
+    ExtrinsicFailed { error: DispatchError },
+    /// The state was built by another spec version of the runtime, and was migrated to this one
+    /// before the block was initialized. Includes the total weight of the migrations.
+    RuntimeUpgraded { spec_version: u32, weight: Weight },
+}
+
+/// A copy of the state of the system pallet at some point in time, used to compare the state
+/// before and after executing a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemSnapshot<BlockNumber, AccountId, Nonce> {
+    pub block_number: BlockNumber,
+    pub nonces: BTreeMap<AccountId, Nonce>,
+}
+
+// The nonces and the public keys of the accounts record their writes, so they can be changed in a
+// transaction, see `support::Transactional`. The rest of the state is only changed while a block is
+// executed, outside of the calls of its extrinsics.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+    // The best block, which is advanced by executing blocks.
     block_number: T::BlockNumber,
-    nonce: BTreeMap<T::AccountId, T::Nonce>,
+    // The last finalized block, which lags behind the best block.
+    finalized_block: T::BlockNumber,
+    // The hashes of the blocks which were executed, keyed by their block number.
+    block_hashes: BTreeMap<T::BlockNumber, Hash>,
+    nonce: StorageMap<T::AccountId, T::Nonce>,
+    // The public keys which check the signatures of the extrinsics of each account.
+    public_keys: StorageMap<T::AccountId, Public>,
+    // The events emitted by the pallets of the runtime, keyed by the block they were emitted in.
+    events: BTreeMap<T::BlockNumber, Vec<EventRecord<T::RuntimeEvent>>>,
+    // The maximum total weight of the calls of the extrinsics of a block, which is
+    // `Config::MAX_BLOCK_WEIGHT` unless the genesis config sets another one.
+    max_block_weight: Weight,
+    // The version of the runtime which built the state, which is `Config::VERSION` once the state
+    // is migrated, see `migrations`.
+    version: RuntimeVersion,
 }
 
-impl<T: Config> Pallet<T>{
+impl<T: Config> Pallet<T> {
     pub fn new() -> Self {
         Self {
-
             block_number: T::BlockNumber::zero(),
-            nonce: BTreeMap::new()
+            finalized_block: T::BlockNumber::zero(),
+            block_hashes: BTreeMap::new(),
+            nonce: StorageMap::new(),
+            public_keys: StorageMap::new(),
+            events: BTreeMap::new(),
+            max_block_weight: T::MAX_BLOCK_WEIGHT,
+            version: T::VERSION,
         }
     }
 
@@ -26,8 +136,81 @@ impl<T: Config> Pallet<T>{
         self.block_number
     }
 
-    pub fn inc_block_number(&mut self) {
-        self.block_number += T::BlockNumber::one();//avoid overflows
+    // The maximum total weight of the calls of the extrinsics of a block.
+    pub fn max_block_weight(&self) -> Weight {
+        self.max_block_weight
+    }
+
+    // The version of the runtime which built the state.
+    pub fn version(&self) -> RuntimeVersion {
+        self.version
+    }
+
+    // Record that the state was built by `version` of the runtime, once it is migrated to it.
+    pub fn set_version(&mut self, version: RuntimeVersion) {
+        self.version = version;
+    }
+
+    pub fn finalized_block(&self) -> T::BlockNumber {
+        self.finalized_block
+    }
+
+    // Finalize all the blocks up to `block_number`. Finality can't go past the best block, and
+    // can't go back to an earlier block.
+    pub fn finalize_up_to(&mut self, block_number: T::BlockNumber) -> DispatchResult {
+        if block_number > self.block_number {
+            return Err("cannot finalize beyond the best block".into());
+        }
+        if block_number < self.finalized_block {
+            return Err("block is already finalized".into());
+        }
+        self.finalized_block = block_number;
+        Ok(())
+    }
+
+    // The number of the next block, which `inc_block_number` moves to. When it overflows, this
+    // either fails or is the maximum value, depending on `Config::SATURATING_BLOCK_NUMBER`.
+    pub fn next_block_number(&self) -> Result<T::BlockNumber, DispatchError> {
+        if T::SATURATING_BLOCK_NUMBER {
+            Ok(self.block_number.saturating_add(T::BlockNumber::one()))
+        } else {
+            Ok(self.block_number.checked_add(&T::BlockNumber::one()).ok_or(ArithmeticError::Overflow)?)
+        }
+    }
+
+    // Increment the block number, see `next_block_number`.
+    pub fn inc_block_number(&mut self) -> DispatchResult {
+        self.block_number = self.next_block_number()?;
+        Ok(())
+    }
+
+    pub fn set_block_hash(&mut self, block_number: T::BlockNumber, hash: Hash) {
+        self.block_hashes.insert(block_number, hash);
+    }
+
+    pub fn block_hash(&self, block_number: T::BlockNumber) -> Option<Hash> {
+        self.block_hashes.get(&block_number).copied()
+    }
+
+    // The hash of the best block, which the next block must be built on. Before the first block,
+    // this is the genesis hash.
+    pub fn best_block_hash(&self) -> Hash {
+        self.block_hash(self.block_number).unwrap_or_else(|| self.genesis_hash())
+    }
+
+    // The hash of the genesis block, which extrinsics are signed with so they are only valid on
+    // this chain. It is all zeros until the hash of block 0 is set.
+    pub fn genesis_hash(&self) -> Hash {
+        self.block_hash(T::BlockNumber::zero()).unwrap_or_default()
+    }
+
+    // The hashes of the `count` most recent blocks up to `block_number`, newest first.
+    pub fn recent_block_hashes(
+        &self,
+        block_number: T::BlockNumber,
+        count: usize,
+    ) -> impl Iterator<Item = &Hash> {
+        self.block_hashes.range(..=block_number).rev().take(count).map(|(_, hash)| hash)
     }
 
     pub fn inc_nonce(&mut self, who: &T::AccountId) {
@@ -38,35 +221,183 @@ impl<T: Config> Pallet<T>{
     pub fn get_nonce(&self, who: &T::AccountId) -> T::Nonce {
         *self.nonce.get(who).unwrap_or(&T::Nonce::zero())
     }
+
+    // An account exists once it has a nonce, which is set when it submits its first extrinsic.
+    pub fn account_exists(&self, who: &T::AccountId) -> bool {
+        self.nonce.contains_key(who)
+    }
+
+    // Create the account of `who` with a nonce of zero, if it doesn't exist yet. This is for
+    // accounts which can't sign extrinsics, like the accounts of multisigs.
+    pub fn create_account(&mut self, who: &T::AccountId) {
+        self.nonce.entry(who.clone()).or_insert_with(T::Nonce::zero);
+    }
+
+    // Set the public key which checks the signatures of the extrinsics of `who`.
+    pub fn set_public_key(&mut self, who: T::AccountId, public: Public) {
+        self.public_keys.insert(who, public);
+    }
+
+    // The public key which checks the signatures of `who`: the key set for them, or else the key
+    // derived from their account id by the runtime.
+    pub fn public_key(&self, who: &T::AccountId) -> Option<Public> {
+        self.public_keys.get(who).copied().or_else(|| T::account_public_key(who))
+    }
+
+    // Forget the account of `who`, resetting their nonce. The runtime must first make sure no other
+    // pallet still holds state for them.
+    pub fn kill_account(&mut self, who: &T::AccountId) {
+        self.nonce.remove(who);
+    }
+
+    // Record an event in the current block.
+    pub fn deposit_event(&mut self, record: EventRecord<T::RuntimeEvent>) {
+        self.events.entry(self.block_number).or_default().push(record);
+    }
+
+    // The events emitted in `block_number`, oldest first.
+    pub fn events(&self, block_number: T::BlockNumber) -> &[EventRecord<T::RuntimeEvent>] {
+        self.events.get(&block_number).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn snapshot(&self) -> SystemSnapshot<T::BlockNumber, T::AccountId, T::Nonce> {
+        SystemSnapshot {
+            block_number: self.block_number,
+            nonces: (*self.nonce).clone(),
+        }
+    }
+}
+
+impl<T: Config> Pallet<T>
+where
+    T::AccountId: Encode,
+    T::BlockNumber: Encode,
+    T::Nonce: Encode,
+{
+    // Write the state of the pallet into `state`, so the runtime can commit to it. The events are
+    // not part of the state.
+    pub fn store_state(&self, state: &mut impl StorageBackend) {
+        state.insert(storage_key("system", "block_number"), self.block_number.encode());
+        state.insert(storage_key("system", "finalized_block"), self.finalized_block.encode());
+        for (block_number, hash) in &self.block_hashes {
+            state.insert(storage_map_key("system", "block_hashes", block_number), hash.encode());
+        }
+        for (who, nonce) in &self.nonce {
+            state.insert(storage_map_key("system", "nonce", who), nonce.encode());
+        }
+        for (who, public) in &self.public_keys {
+            state.insert(storage_map_key("system", "public_keys", who), public.encode());
+        }
+        state.insert(storage_key("system", "max_block_weight"), self.max_block_weight.encode());
+        state.insert(storage_key("system", "version"), self.version.encode());
+    }
 }
 
-#[cfg(test)]
+impl<T: Config> Default for Pallet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The initial state of the system pallet, see `RuntimeGenesisConfig` of `#[macros::runtime]`.
+pub struct GenesisConfig<T: Config> {
+    // The public key of each account which can sign extrinsics from the genesis block.
+    pub public_keys: Vec<(T::AccountId, Public)>,
+    // The maximum total weight of a block on this chain, in place of `Config::MAX_BLOCK_WEIGHT`.
+    pub max_block_weight: Option<Weight>,
+}
+
+impl<T: Config> GenesisConfig<T> {
+    pub fn build(&self, system: &mut Pallet<T>) -> DispatchResult {
+        for (who, public) in &self.public_keys {
+            system.set_public_key(who.clone(), *public);
+        }
+        if let Some(max_block_weight) = self.max_block_weight {
+            system.max_block_weight = max_block_weight;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { public_keys: Vec::new(), max_block_weight: None }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
 mod test {
-    struct  TestConfig;
-    
-    impl super::Config for TestConfig{
-         type  AccountId = String;
-         type BlockNumber = u32;
-         type Nonce = u32;
-    
+    struct TestConfig;
+
+    impl super::Config for TestConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type Hashing = crate::hashing::Sha256;
     }
 
     #[test]
-    fn init_system(){
+    fn init_system() {
         let system: super::Pallet<TestConfig> = super::Pallet::new();
         assert_eq!(system.block_number(), 0);
     }
 
     #[test]
-    fn inc_block_number(){
-        let mut system:super::Pallet<TestConfig> = super::Pallet::new();
-        system.inc_block_number();
+    fn inc_block_number() {
+        let mut system: super::Pallet<TestConfig> = super::Pallet::new();
+        assert_eq!(system.inc_block_number(), Ok(()));
         assert_eq!(system.block_number(), 1);
     }
 
+    struct U8BlockNumberConfig;
+
+    impl super::Config for U8BlockNumberConfig {
+        type AccountId = String;
+        type BlockNumber = u8;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type Hashing = crate::hashing::Sha256;
+    }
+
+    struct SaturatingConfig;
+
+    impl super::Config for SaturatingConfig {
+        type AccountId = String;
+        type BlockNumber = u8;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type Hashing = crate::hashing::Sha256;
+        const SATURATING_BLOCK_NUMBER: bool = true;
+    }
+
+    #[test]
+    fn inc_block_number_fails_on_overflow() {
+        let mut system: super::Pallet<U8BlockNumberConfig> = super::Pallet::new();
+        for _ in 0..255 {
+            assert_eq!(system.inc_block_number(), Ok(()));
+        }
+        assert_eq!(system.block_number(), 255);
+
+        assert_eq!(system.inc_block_number(), Err(crate::support::ArithmeticError::Overflow.into()));
+        assert_eq!(system.block_number(), 255);
+    }
+
+    #[test]
+    fn inc_block_number_saturates() {
+        let mut system: super::Pallet<SaturatingConfig> = super::Pallet::new();
+        for _ in 0..255 {
+            assert_eq!(system.inc_block_number(), Ok(()));
+        }
+        assert_eq!(system.block_number(), 255);
+
+        assert_eq!(system.inc_block_number(), Ok(()));
+        assert_eq!(system.block_number(), 255);
+    }
+
     #[test]
-    fn inc_nonce(){
-        let alice: String =String::from("alice");
+    fn inc_nonce() {
+        let alice: String = String::from("alice");
         let mut system: super::Pallet<TestConfig> = super::Pallet::new();
         system.inc_nonce(&alice.clone());
         system.inc_nonce(&alice.clone());
@@ -74,4 +405,41 @@ mod test {
         assert_eq!(system.get_nonce(&alice), 2);
     }
 
+    #[test]
+    fn account_exists() {
+        let alice: String = String::from("alice");
+        let mut system: super::Pallet<TestConfig> = super::Pallet::new();
+        assert!(!system.account_exists(&alice));
+
+        system.inc_nonce(&alice);
+        assert!(system.account_exists(&alice));
+    }
+
+    struct VersionedConfig;
+
+    impl super::Config for VersionedConfig {
+        type AccountId = String;
+        type BlockNumber = u32;
+        type Nonce = u32;
+        type RuntimeEvent = ();
+        type Hashing = crate::hashing::Sha256;
+        const VERSION: super::RuntimeVersion =
+            super::RuntimeVersion { spec_version: 3, impl_version: 1, transaction_version: 2 };
+    }
+
+    #[test]
+    fn version_is_stored_in_the_state() {
+        use crate::codec::Decode;
+        use crate::storage::{storage_key, InMemoryBackend, StorageBackend};
+
+        let system: super::Pallet<VersionedConfig> = super::Pallet::new();
+        assert_eq!(system.version(), <VersionedConfig as super::Config>::VERSION);
+        assert_eq!(super::Pallet::<TestConfig>::new().version().spec_version, 1);
+
+        let mut state = InMemoryBackend::new();
+        system.store_state(&mut state);
+        let stored = state.get(&storage_key("system", "version")).unwrap();
+        assert_eq!(super::RuntimeVersion::decode_all(&stored), Ok(<VersionedConfig as super::Config>::VERSION));
+    }
+
 }
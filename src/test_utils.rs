@@ -0,0 +1,231 @@
+//! Helpers for the tests of the pallets: `construct_mock_runtime!` declares the configuration of a
+//! test runtime in a line, and `MockRuntimeBuilder` puts together the system, balances and proof of
+//! existence pallets on top of it, for the tests which go through several pallets.
+
+use alloc::vec::Vec;
+
+use crate::support::{Hooks, HooksWithContext};
+use crate::{balances, proof_of_existence, system};
+
+/// Declare the configuration of a test runtime, named `$name`, and implement the `Config` of the
+/// system pallet for it, with `&'static str` account ids, `u32` block numbers and nonces, no
+/// events and the `Sha256` hash function. Another type of account id can be given in brackets,
+/// like `struct TestConfig<AccountId = String>`.
+///
+/// The `Config` of the balances and proof of existence pallets are implemented as well when they
+/// are listed after a colon, with `u128` balances and without any fee, deposit or limit on the
+/// transfers. The claim fees of the proof of existence pallet are paid to the `"treasury"`
+/// account, so its account id must be made from a `&'static str`.
+///
+/// The constants of the balances pallet can be overridden in braces after it, like
+/// `struct TestConfig: balances { EXISTENTIAL_DEPOSIT = 10, MAX_TRANSFERS_PER_BLOCK = 2 }`, followed
+/// by a semicolon and the functions of its `Config` to override.
+#[macro_export]
+macro_rules! construct_mock_runtime {
+	($vis:vis struct $name:ident $(: $($pallet:ident $({ $($overrides:tt)* })?),+)?) => {
+		$crate::construct_mock_runtime!(
+			$vis struct $name<AccountId = &'static str> $(: $($pallet $({ $($overrides)* })?),+)?
+		);
+	};
+	($vis:vis struct $name:ident<AccountId = $account:ty> $(: $($pallet:ident $({ $($overrides:tt)* })?),+)?) => {
+		#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+		$vis struct $name;
+
+		impl $crate::system::Config for $name {
+			type AccountId = $account;
+			type BlockNumber = u32;
+			type Nonce = u32;
+			type RuntimeEvent = ();
+			type Hashing = $crate::hashing::Sha256;
+		}
+
+		$($($crate::construct_mock_runtime!(@pallet $pallet $name { $($($overrides)*)? });)+)?
+	};
+	(@pallet balances $name:ident { $($constant:ident = $value:expr),* $(; $($function:item)*)? }) => {
+		impl $crate::balances::Config for $name {
+			type Balance = u128;
+			const MINIMUM_TRANSFER: Self::Balance =
+				$crate::construct_mock_runtime!(@constant MINIMUM_TRANSFER, 0, $($constant = $value),*);
+			const MAX_TRANSFERS_PER_BLOCK: u32 =
+				$crate::construct_mock_runtime!(@constant MAX_TRANSFERS_PER_BLOCK, u32::MAX, $($constant = $value),*);
+			const EXISTENTIAL_DEPOSIT: Self::Balance =
+				$crate::construct_mock_runtime!(@constant EXISTENTIAL_DEPOSIT, 0, $($constant = $value),*);
+			$($($function)*)?
+		}
+
+		// Refuse the overrides of constants the balances pallet doesn't have.
+		$(const _: () = { let _ = <$name as $crate::balances::Config>::$constant; };)*
+	};
+	(@pallet proof_of_existence $name:ident {}) => {
+		impl $crate::proof_of_existence::Config for $name {
+			type Currency = $crate::balances::Pallet<Self>;
+			const CLAIM_FEE: Self::Balance = 0;
+			const CLAIM_DEPOSIT: Self::Balance = 0;
+			const MAX_METADATA_LENGTH: usize = 8;
+			const MAX_CLAIM_OWNERS: u32 = 3;
+			fn claim_fee_destination() -> Self::AccountId {
+				::core::convert::From::from("treasury")
+			}
+		}
+	};
+	// The value of `$constant` among the overrides, or `$default` if it isn't overridden.
+	(@constant MINIMUM_TRANSFER, $default:expr, MINIMUM_TRANSFER = $value:expr $(, $($rest:tt)*)?) => { $value };
+	(@constant MAX_TRANSFERS_PER_BLOCK, $default:expr, MAX_TRANSFERS_PER_BLOCK = $value:expr $(, $($rest:tt)*)?) => {
+		$value
+	};
+	(@constant EXISTENTIAL_DEPOSIT, $default:expr, EXISTENTIAL_DEPOSIT = $value:expr $(, $($rest:tt)*)?) => { $value };
+	(@constant $constant:ident, $default:expr, $other:ident = $value:expr $(, $($rest:tt)*)?) => {
+		$crate::construct_mock_runtime!(@constant $constant, $default, $($($rest)*)?)
+	};
+	(@constant $constant:ident, $default:expr,) => { $default };
+}
+
+/// The pallets of a test runtime built by `MockRuntimeBuilder`. Unlike the runtime of the node,
+/// calls are made on the pallets directly, and blocks are moved to with `run_to_block`.
+pub struct MockRuntime<T: proof_of_existence::Config<Currency = balances::Pallet<T>>> {
+	/// The system pallet, which keeps the number of the current block.
+	pub system: system::Pallet<T>,
+	/// The balances pallet, which is also the currency of the proof of existence pallet.
+	pub balances: balances::Pallet<T>,
+	/// The proof of existence pallet.
+	pub proof_of_existence: proof_of_existence::Pallet<T>,
+}
+
+impl<T: proof_of_existence::Config<Currency = balances::Pallet<T>>> MockRuntime<T> {
+	/// Finalize the current block and initialize the next ones, until `block_number` is the
+	/// current block, running the hooks of the balances then proof of existence pallets, in the
+	/// order of the runtime. Nothing happens if
+	/// the current block is `block_number` or later.
+	///
+	/// # Panics
+	///
+	/// Panics if the block number overflows.
+	pub fn run_to_block(&mut self, block_number: T::BlockNumber) {
+		while self.system.block_number() < block_number {
+			let current = self.system.block_number();
+			Hooks::on_finalize(&mut self.balances, current);
+			self.proof_of_existence.on_finalize(&mut self.balances, current);
+			self.system.inc_block_number().expect("the block number of a mock runtime overflowed");
+			let next = self.system.block_number();
+			Hooks::on_initialize(&mut self.balances, next);
+			self.proof_of_existence.on_initialize(&mut self.balances, next);
+		}
+	}
+}
+
+/// A builder of `MockRuntime`, with the genesis balances and claims of the runtime, and the block
+/// it starts at.
+pub struct MockRuntimeBuilder<T: proof_of_existence::Config<Currency = balances::Pallet<T>>> {
+	balances: Vec<(T::AccountId, T::Balance)>,
+	claims: Vec<(T::AccountId, Vec<u8>)>,
+	block_number: T::BlockNumber,
+}
+
+impl<T: proof_of_existence::Config<Currency = balances::Pallet<T>>> MockRuntimeBuilder<T> {
+	/// Create a builder of a runtime without balances nor claims, which starts at block zero.
+	pub fn new() -> Self {
+		Self { balances: Vec::new(), claims: Vec::new(), block_number: num::Zero::zero() }
+	}
+
+	/// Give `amount` to `who` at genesis.
+	pub fn balance(mut self, who: T::AccountId, amount: T::Balance) -> Self {
+		self.balances.push((who, amount));
+		self
+	}
+
+	/// Give each account its amount at genesis.
+	pub fn balances(mut self, balances: impl IntoIterator<Item = (T::AccountId, T::Balance)>) -> Self {
+		self.balances.extend(balances);
+		self
+	}
+
+	/// Claim `content` for `owner` at genesis, without any fee or deposit.
+	pub fn claim(mut self, owner: T::AccountId, content: &[u8]) -> Self {
+		self.claims.push((owner, content.to_vec()));
+		self
+	}
+
+	/// Start the runtime at `block_number`, after the blocks before it were run.
+	pub fn block_number(mut self, block_number: T::BlockNumber) -> Self {
+		self.block_number = block_number;
+		self
+	}
+
+	/// Build the genesis of the runtime, then run it to its first block.
+	///
+	/// # Panics
+	///
+	/// Panics if the genesis is invalid, like when the same content is claimed twice.
+	pub fn build(self) -> MockRuntime<T> {
+		let mut runtime = MockRuntime {
+			system: system::Pallet::new(),
+			balances: balances::Pallet::new(),
+			proof_of_existence: proof_of_existence::Pallet::new(),
+		};
+		let mut balances = balances::GenesisConfig::<T>::default();
+		balances.balances = self.balances;
+		balances.build(&mut runtime.balances).expect("invalid genesis balances");
+		let claims = proof_of_existence::GenesisConfig::<T> { claims: self.claims };
+		claims.build(&mut runtime.proof_of_existence).expect("invalid genesis claims");
+		runtime.run_to_block(self.block_number);
+		runtime
+	}
+}
+
+impl<T: proof_of_existence::Config<Currency = balances::Pallet<T>>> Default for MockRuntimeBuilder<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::MockRuntimeBuilder;
+	use crate::proof_of_existence::{self, Error};
+
+	crate::construct_mock_runtime!(struct TestConfig: balances, proof_of_existence);
+
+	#[test]
+	fn claims_expire_as_the_mock_runtime_runs_blocks() {
+		let mut runtime = MockRuntimeBuilder::<TestConfig>::new()
+			.balances([("alice", 100), ("bob", 50)])
+			.claim("alice", b"my_document")
+			.block_number(2)
+			.build();
+		assert_eq!(runtime.system.block_number(), 2);
+		assert_eq!(runtime.balances.total_issuance(), 150);
+		let my_document = proof_of_existence::Pallet::<TestConfig>::claim_of(b"my_document");
+		assert_eq!(runtime.proof_of_existence.get_claim(&my_document), Some(&"alice"));
+
+		let (poe, balances) = (&mut runtime.proof_of_existence, &mut runtime.balances);
+		assert_eq!(poe.create_claim("bob", b"my_document".to_vec(), balances), Err(Error::ClaimAlreadyExists.into()));
+		assert_eq!(poe.create_claim_with_expiry("bob", b"my_photo".to_vec(), 3, balances), Ok(()));
+		let my_photo = proof_of_existence::Pallet::<TestConfig>::claim_of(b"my_photo");
+
+		// The claim expires at the end of block 5, so it is purged once block 6 is reached.
+		runtime.run_to_block(5);
+		assert_eq!(runtime.proof_of_existence.get_claim(&my_photo), Some(&"bob"));
+		runtime.run_to_block(6);
+		assert_eq!(runtime.proof_of_existence.get_claim(&my_photo), None);
+		assert_eq!(runtime.proof_of_existence.get_claim(&my_document), Some(&"alice"));
+	}
+
+	#[test]
+	fn the_mock_runtime_runs_the_hooks_of_the_balances_pallet() {
+		let mut runtime = MockRuntimeBuilder::<TestConfig>::new().balance("alice", 100).build();
+		assert_eq!(runtime.balances.create_escrow("alice", "bob", 30, 2), Ok(0));
+
+		// The escrow is released at the end of block 2, by the hook of the balances pallet.
+		runtime.run_to_block(2);
+		assert_eq!(runtime.balances.balance(&"bob"), 0);
+		runtime.run_to_block(3);
+		assert_eq!(runtime.balances.balance(&"bob"), 30);
+		assert_eq!(runtime.balances.balance(&"alice"), 70);
+	}
+
+	#[test]
+	#[should_panic(expected = "invalid genesis claims")]
+	fn mock_runtimes_refuse_an_invalid_genesis() {
+		MockRuntimeBuilder::<TestConfig>::new().claim("alice", b"my_document").claim("bob", b"my_document").build();
+	}
+}
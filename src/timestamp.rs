@@ -0,0 +1,126 @@
+use crate::codec::Encode;
+use crate::storage::{storage_key, StorageBackend};
+use crate::support::{ensure_none, DispatchError, DispatchResult, Hooks, OriginFor, StorageValue};
+
+/// A point in time, as the number of milliseconds since the Unix epoch.
+pub type Moment = u64;
+
+pub trait Config: crate::system::Config {
+	/// The minimum time between the timestamps of two blocks.
+	const MINIMUM_PERIOD: Moment;
+}
+
+/// The errors returned by the timestamp pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The timestamp was already set in this block.
+	AlreadySet,
+	/// The timestamp is less than `Config::MINIMUM_PERIOD` after the timestamp of the last block.
+	TooEarly,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::AlreadySet => "AlreadySet",
+			Error::TooEarly => "TooEarly",
+		};
+		DispatchError::Module { pallet: "timestamp", error }
+	}
+}
+
+/// This is the Timestamp Module.
+/// It keeps the wall-clock time of the current block, which the block author sets with an inherent,
+/// so other pallets can use `now` instead of block numbers.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The timestamp of the current block, or of the last block which set one.
+	now: StorageValue<Moment>,
+	/// Whether the timestamp was set in the current block.
+	did_update: StorageValue<bool>,
+	_config: core::marker::PhantomData<T>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Set the timestamp of the current block to `now`. This is an inherent: it can only be called
+	/// with the none origin, by the block author, once per block.
+	/// This function will return an error if the timestamp was already set in this block, or if
+	/// `now` is too early after the timestamp of the last block.
+	#[call_index = 0]
+	#[weight = 1_000]
+	pub fn set(&mut self, origin: OriginFor<T>, now: Moment) -> DispatchResult {
+		ensure_none(origin)?;
+		if *self.did_update {
+			return Err(Error::AlreadySet.into());
+		}
+		if *self.now != 0 && now < self.now.saturating_add(T::MINIMUM_PERIOD) {
+			return Err(Error::TooEarly.into());
+		}
+
+		self.now.set(now);
+		self.did_update.set(true);
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Timestamp Module.
+	pub fn new() -> Self {
+		Self { now: StorageValue::new(0), did_update: StorageValue::new(false), _config: core::marker::PhantomData }
+	}
+
+	/// Get the timestamp of the current block, or of the last block which set one. This is zero
+	/// until a block sets it.
+	pub fn now(&self) -> Moment {
+		*self.now
+	}
+
+	/// Write the state of the pallet into `state`, so the runtime can commit to it.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		state.insert(storage_key("timestamp", "now"), self.now.encode());
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	/// The next block can set its timestamp again.
+	fn on_finalize(&mut self, _block_number: T::BlockNumber) {
+		self.did_update.set(false);
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::Error;
+	use crate::support::{DispatchError, Hooks, RawOrigin};
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		const MINIMUM_PERIOD: super::Moment = 3_000;
+	}
+
+	#[test]
+	fn timestamp_is_set_once_per_block() {
+		let mut timestamp = super::Pallet::<TestConfig>::new();
+		assert_eq!(timestamp.now(), 0);
+
+		assert_eq!(timestamp.set(RawOrigin::Signed("alice"), 6_000), Err(DispatchError::BadOrigin));
+		assert_eq!(timestamp.set(RawOrigin::None, 6_000), Ok(()));
+		assert_eq!(timestamp.set(RawOrigin::None, 12_000), Err(Error::AlreadySet.into()));
+		assert_eq!(timestamp.now(), 6_000);
+
+		timestamp.on_finalize(1);
+		assert_eq!(timestamp.set(RawOrigin::None, 8_000), Err(Error::TooEarly.into()));
+		assert_eq!(timestamp.set(RawOrigin::None, 12_000), Ok(()));
+		assert_eq!(timestamp.now(), 12_000);
+	}
+}
@@ -0,0 +1,345 @@
+use alloc::vec::Vec;
+
+use num::traits::Zero;
+
+use crate::codec::Encode;
+use crate::storage::{storage_key, storage_map_key, StorageBackend};
+use crate::support::{
+	ensure_root, DispatchError, DispatchResult, Events, HooksWithContext, OriginFor, StorageMap, StorageValue,
+};
+use crate::weights::treasury as weights;
+
+pub trait Config:
+	crate::balances::Config<BlockNumber: core::ops::Rem<Output = <Self as crate::system::Config>::BlockNumber>>
+{
+	/// The account which holds the treasury pot.
+	/// Transaction fees are collected into this account by the runtime.
+	fn account_id() -> Self::AccountId;
+	/// The deposit reserved from the proposer of a spend, which is returned when the spend is paid
+	/// out and lost to the pot when it is rejected.
+	const PROPOSAL_BOND: Self::Balance;
+	/// The number of blocks between two payouts of the approved spends. A period of zero disables
+	/// the payouts.
+	const SPEND_PERIOD: Self::BlockNumber;
+
+	/// Check that `origin` can approve and reject spends. By default only the root origin can.
+	fn ensure_spend_origin(origin: OriginFor<Self>) -> DispatchResult {
+		ensure_root(origin)
+	}
+}
+
+/// A request to spend some of the treasury pot, waiting for approval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proposal<AccountId, Balance> {
+	/// The account which made the proposal.
+	pub proposer: AccountId,
+	/// The account which will receive the funds if the proposal is approved.
+	pub beneficiary: AccountId,
+	/// The amount to be paid out of the pot.
+	pub amount: Balance,
+	/// The deposit reserved from the proposer.
+	pub bond: Balance,
+}
+
+/// The events emitted by the treasury pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, Balance> {
+	/// A spend was proposed.
+	Proposed { proposal_id: u32 },
+	/// A spend was approved, and will be paid out at the next payout which the pot can fund.
+	Approved { proposal_id: u32 },
+	/// A spend was rejected, and the bond of its proposer was slashed into the pot.
+	Rejected { proposal_id: u32, slashed: Balance },
+	/// An approved spend was paid out of the pot to its beneficiary.
+	Awarded { proposal_id: u32, award: Balance, account: AccountId },
+}
+
+/// The errors returned by the treasury pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// There is no spend proposal waiting for approval with this id.
+	InvalidIndex,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::InvalidIndex => "InvalidIndex",
+		};
+		DispatchError::Module { pallet: "treasury", error }
+	}
+}
+
+/// The events of the treasury pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId, <T as crate::balances::Config>::Balance>;
+
+/// This is the Treasury Module.
+/// It allows accounts to propose spending the funds collected in the treasury pot, against a bond.
+/// The root origin approves or rejects the proposals, and the approved ones are paid out every
+/// `Config::SPEND_PERIOD` blocks, as long as the pot can fund them.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The id which will be assigned to the next spend proposal.
+	next_proposal_id: StorageValue<u32>,
+	/// All the spend proposals which have not been paid out or rejected yet, keyed by their id.
+	proposals: StorageMap<u32, Proposal<T::AccountId, T::Balance>>,
+	/// The ids of the approved proposals, in the order they are paid out.
+	approvals: StorageValue<Vec<u32>>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId, T::Balance>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Propose to pay `amount` from the treasury pot to the `beneficiary`, reserving
+	/// `Config::PROPOSAL_BOND` from the `caller`.
+	/// The proposal is stored under the next available proposal id until it is approved, and that
+	/// id is returned.
+	/// This function will return an error if the caller can't reserve the bond.
+	#[call_index = 0]
+	#[weight = weights::PROPOSE_SPEND]
+	pub fn propose_spend(
+		&mut self,
+		caller: T::AccountId,
+		beneficiary: T::AccountId,
+		amount: T::Balance,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> Result<u32, DispatchError> {
+		let proposal_id = *self.next_proposal_id;
+		let next_proposal_id = proposal_id.checked_add(1).ok_or("Proposal id overflow")?;
+		balances.reserve(&caller, T::PROPOSAL_BOND)?;
+
+		self.next_proposal_id.set(next_proposal_id);
+		let proposal = Proposal { proposer: caller, beneficiary, amount, bond: T::PROPOSAL_BOND };
+		self.proposals.insert(proposal_id, proposal);
+		self.deposit_event(Event::Proposed { proposal_id });
+		Ok(proposal_id)
+	}
+
+	/// Approve the spend proposal `proposal_id`, so it is paid out at the next payout which the
+	/// pot can fund. This can only be called by the origin of `Config::ensure_spend_origin`.
+	/// This function will return an error if the proposal does not exist or is already approved.
+	#[call_index = 1]
+	#[weight = weights::APPROVE_PROPOSAL]
+	pub fn approve_proposal(&mut self, origin: OriginFor<T>, proposal_id: u32) -> DispatchResult {
+		T::ensure_spend_origin(origin)?;
+		if !self.proposals.contains_key(&proposal_id) || self.approvals.contains(&proposal_id) {
+			return Err(Error::InvalidIndex.into());
+		}
+
+		self.approvals.get_mut().push(proposal_id);
+		self.deposit_event(Event::Approved { proposal_id });
+		Ok(())
+	}
+
+	/// Reject the spend proposal `proposal_id`, slashing the bond of its proposer into the pot.
+	/// This can only be called by the origin of `Config::ensure_spend_origin`.
+	/// This function will return an error if the proposal does not exist or is already approved.
+	#[call_index = 2]
+	#[weight = weights::REJECT_PROPOSAL]
+	pub fn reject_proposal(
+		&mut self,
+		origin: OriginFor<T>,
+		proposal_id: u32,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		T::ensure_spend_origin(origin)?;
+		if self.approvals.contains(&proposal_id) {
+			return Err(Error::InvalidIndex.into());
+		}
+		let proposal = self.proposals.remove(&proposal_id).ok_or(Error::InvalidIndex)?;
+
+		let slashed = balances.repatriate_reserved(&proposal.proposer, T::account_id(), proposal.bond);
+		self.deposit_event(Event::Rejected { proposal_id, slashed });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Treasury Module.
+	pub fn new() -> Self {
+		Self {
+			next_proposal_id: StorageValue::new(0),
+			proposals: StorageMap::new(),
+			approvals: StorageValue::new(Vec::new()),
+			events: Events::new(),
+		}
+	}
+
+	/// Get a spend proposal which is waiting to be paid out or rejected.
+	pub fn proposal(&self, proposal_id: u32) -> Option<&Proposal<T::AccountId, T::Balance>> {
+		self.proposals.get(&proposal_id)
+	}
+
+	/// Get the ids of the approved proposals, in the order they are paid out.
+	pub fn approvals(&self) -> &[u32] {
+		&self.approvals
+	}
+
+	/// Get the amount of funds currently held in the treasury pot.
+	pub fn pot(&self, balances: &crate::balances::Pallet<T>) -> T::Balance {
+		balances.balance(&T::account_id())
+	}
+
+	/// Pay out the approved proposals in order, returning the bonds of their proposers. A proposal
+	/// which the pot can't fund is kept for the next payout, but the ones after it can still be
+	/// paid out.
+	fn spend_funds(&mut self, balances: &mut crate::balances::Pallet<T>) {
+		let approvals = core::mem::take(self.approvals.get_mut());
+		for proposal_id in approvals {
+			let Some(proposal) = self.proposals.get(&proposal_id) else { continue };
+			if balances.do_transfer(T::account_id(), proposal.beneficiary.clone(), proposal.amount).is_err() {
+				self.approvals.get_mut().push(proposal_id);
+				continue;
+			}
+
+			balances.unreserve(&proposal.proposer, proposal.bond);
+			let award = proposal.amount;
+			let account = proposal.beneficiary.clone();
+			self.proposals.remove(&proposal_id);
+			self.deposit_event(Event::Awarded { proposal_id, award, account });
+		}
+	}
+}
+
+impl<T: Config> HooksWithContext<T::BlockNumber, crate::balances::Pallet<T>> for Pallet<T> {
+	/// Pay out the approved proposals at the end of every `Config::SPEND_PERIOD` blocks, unless the
+	/// period is zero.
+	fn on_finalize(&mut self, balances: &mut crate::balances::Pallet<T>, block_number: T::BlockNumber) {
+		if !T::SPEND_PERIOD.is_zero() && (block_number % T::SPEND_PERIOD).is_zero() {
+			self.spend_funds(balances);
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The pot is
+	/// held by the balances pallet, so it is part of its state.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		state.insert(storage_key("treasury", "next_proposal_id"), self.next_proposal_id.encode());
+		for (id, proposal) in &self.proposals {
+			let value = ((&proposal.proposer, &proposal.beneficiary), (&proposal.amount, &proposal.bond)).encode();
+			state.insert(storage_map_key("treasury", "proposals", id), value);
+		}
+		state.insert(storage_key("treasury", "approvals"), self.approvals.encode());
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, Event};
+	use crate::balances;
+	use crate::support::{DispatchError, HooksWithContext, RawOrigin};
+
+	crate::construct_mock_runtime!(struct TestConfig: balances);
+
+	impl super::Config for TestConfig {
+		fn account_id() -> Self::AccountId {
+			"treasury"
+		}
+		const PROPOSAL_BOND: Self::Balance = 5;
+		const SPEND_PERIOD: Self::BlockNumber = 2;
+	}
+
+	#[test]
+	fn approved_spends_are_paid_out_every_spend_period() {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let mut treasury = super::Pallet::<TestConfig>::new();
+		balances.set_balance("treasury", 100);
+		balances.set_balance("alice", 10);
+
+		assert_eq!(treasury.propose_spend("alice", "bob", 40, &mut balances), Ok(0));
+		assert_eq!(
+			treasury.proposal(0),
+			Some(&super::Proposal { proposer: "alice", beneficiary: "bob", amount: 40, bond: 5 })
+		);
+		assert_eq!(balances.reserved_balance(&"alice"), 5);
+
+		assert_eq!(treasury.approve_proposal(RawOrigin::Signed("alice"), 0), Err(DispatchError::BadOrigin));
+		assert_eq!(treasury.approve_proposal(RawOrigin::Root, 1), Err(Error::InvalidIndex.into()));
+		assert_eq!(treasury.approve_proposal(RawOrigin::Root, 0), Ok(()));
+		assert_eq!(treasury.approve_proposal(RawOrigin::Root, 0), Err(Error::InvalidIndex.into()));
+
+		// Nothing is paid out until the end of the spend period.
+		treasury.on_finalize(&mut balances, 1);
+		assert_eq!(balances.balance(&"bob"), 0);
+		treasury.on_finalize(&mut balances, 2);
+		assert_eq!(balances.balance(&"bob"), 40);
+		assert_eq!(treasury.pot(&balances), 60);
+		assert_eq!(treasury.proposal(0), None);
+		// The bond is returned once the spend is paid out.
+		assert_eq!(balances.balance(&"alice"), 10);
+		let award = Event::Awarded { proposal_id: 0, award: 40, account: "bob" };
+		assert_eq!(treasury.events().last().map(|record| &record.event), Some(&award));
+	}
+
+	#[test]
+	fn unfunded_spends_wait_and_rejected_spends_lose_their_bond() {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let mut treasury = super::Pallet::<TestConfig>::new();
+		balances.set_balance("treasury", 10);
+		balances.set_balance("alice", 10);
+
+		assert_eq!(treasury.propose_spend("alice", "bob", 40, &mut balances), Ok(0));
+		assert_eq!(treasury.propose_spend("alice", "charlie", 5, &mut balances), Ok(1));
+		assert!(treasury.propose_spend("alice", "charlie", 5, &mut balances).is_err());
+		assert_eq!(treasury.approve_proposal(RawOrigin::Root, 0), Ok(()));
+		assert_eq!(treasury.approve_proposal(RawOrigin::Root, 1), Ok(()));
+
+		// The pot can't fund the first spend, which waits for the next payout, but it can fund the
+		// second one.
+		treasury.on_finalize(&mut balances, 2);
+		assert_eq!(balances.balance(&"bob"), 0);
+		assert_eq!(balances.balance(&"charlie"), 5);
+		assert_eq!(treasury.approvals(), [0]);
+
+		balances.set_balance("treasury", 40);
+		treasury.on_finalize(&mut balances, 4);
+		assert_eq!(balances.balance(&"bob"), 40);
+		assert!(treasury.approvals().is_empty());
+
+		assert_eq!(treasury.propose_spend("alice", "bob", 1, &mut balances), Ok(2));
+		assert_eq!(treasury.reject_proposal(RawOrigin::Root, 2, &mut balances), Ok(()));
+		assert_eq!(treasury.reject_proposal(RawOrigin::Root, 2, &mut balances), Err(Error::InvalidIndex.into()));
+		assert_eq!(balances.total_balance(&"alice"), 5);
+		assert_eq!(treasury.pot(&balances), 5);
+	}
+
+	crate::construct_mock_runtime!(struct NoPayoutConfig: balances);
+
+	impl super::Config for NoPayoutConfig {
+		fn account_id() -> Self::AccountId {
+			"treasury"
+		}
+		const PROPOSAL_BOND: Self::Balance = 5;
+		const SPEND_PERIOD: Self::BlockNumber = 0;
+	}
+
+	#[test]
+	fn a_spend_period_of_zero_disables_the_payouts() {
+		let mut balances = balances::Pallet::<NoPayoutConfig>::new();
+		let mut treasury = super::Pallet::<NoPayoutConfig>::new();
+		balances.set_balance("treasury", 100);
+		balances.set_balance("alice", 10);
+		assert_eq!(treasury.propose_spend("alice", "bob", 40, &mut balances), Ok(0));
+		assert_eq!(treasury.approve_proposal(RawOrigin::Root, 0), Ok(()));
+
+		for block_number in 0..4 {
+			treasury.on_finalize(&mut balances, block_number);
+		}
+		assert_eq!(balances.balance(&"bob"), 0);
+		assert_eq!(treasury.approvals(), [0]);
+	}
+}
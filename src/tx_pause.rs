@@ -0,0 +1,201 @@
+use alloc::string::String;
+
+use crate::codec::Encode;
+use crate::storage::{storage_map_key, StorageBackend};
+use crate::support::{
+	ensure_root, CallFilter, CallMetadata, DispatchError, DispatchResult, Events, OriginFor, StorageSet,
+};
+
+pub trait Config: crate::system::Config {
+	/// The pallets whose calls can't be paused, like the sudo pallet, so the calls lifting a pause
+	/// can always be made.
+	const UNPAUSABLE_PALLETS: &'static [&'static str];
+}
+
+/// The events emitted by the transaction pause pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+	/// The calls of `function` of `pallet`, or of the whole pallet if there is no function, were
+	/// paused.
+	CallPaused { pallet: String, function: Option<String> },
+	/// The calls of `function` of `pallet`, or of the whole pallet if there is no function, were
+	/// unpaused.
+	CallUnpaused { pallet: String, function: Option<String> },
+}
+
+/// The errors returned by the transaction pause pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The calls are paused already.
+	IsPaused,
+	/// The calls aren't paused.
+	IsNotPaused,
+	/// The pallet is one of `Config::UNPAUSABLE_PALLETS`.
+	Unpausable,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::IsPaused => "IsPaused",
+			Error::IsNotPaused => "IsNotPaused",
+			Error::Unpausable => "Unpausable",
+		};
+		DispatchError::Module { pallet: "tx_pause", error }
+	}
+}
+
+/// The events of the transaction pause pallet for the runtime `T`, as collected by
+/// `#[macros::runtime]`.
+pub type EventOf<T> = <Pallet<T> as EventsOf>::Event;
+
+/// The events of the pallet, which don't depend on the runtime, so `EventOf` can still be generic
+/// over it like the events of the other pallets.
+pub trait EventsOf {
+	type Event;
+}
+
+impl<T: Config> EventsOf for Pallet<T> {
+	type Event = Event;
+}
+
+/// This is the Transaction Pause Module.
+/// It lets the root origin pause the calls of a function, like `balances::transfer`, or of a whole
+/// pallet, in an emergency. The runtime consults it as its `support::CallFilter`, so paused calls
+/// fail with `DispatchError::CallFiltered`, whether they are made by an extrinsic or dispatched by
+/// another pallet, like a batch of calls. Calls of the root origin are never filtered.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The paused calls, as the name of their pallet and of their function. A whole pallet is
+	/// paused when there is no function.
+	paused: StorageSet<(String, Option<String>)>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event>,
+	_config: core::marker::PhantomData<T>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Pause the calls of `function` of `pallet`, or of the whole pallet if there is no function.
+	/// This can only be called by the root origin.
+	/// This function will return an error if the calls are paused already, or if the pallet is
+	/// one of `Config::UNPAUSABLE_PALLETS`.
+	#[call_index = 0]
+	#[weight = 1_000]
+	pub fn pause(&mut self, origin: OriginFor<T>, pallet: String, function: Option<String>) -> DispatchResult {
+		ensure_root(origin)?;
+		if T::UNPAUSABLE_PALLETS.contains(&pallet.as_str()) {
+			return Err(Error::Unpausable.into());
+		}
+		if !self.paused.insert((pallet.clone(), function.clone())) {
+			return Err(Error::IsPaused.into());
+		}
+		self.deposit_event(Event::CallPaused { pallet, function });
+		Ok(())
+	}
+
+	/// Unpause the calls paused by `pause` with the same `pallet` and `function`. Pausing a whole
+	/// pallet and unpausing one of its functions leaves the function paused.
+	/// This can only be called by the root origin.
+	/// This function will return an error if the calls aren't paused.
+	#[call_index = 1]
+	#[weight = 1_000]
+	pub fn unpause(&mut self, origin: OriginFor<T>, pallet: String, function: Option<String>) -> DispatchResult {
+		ensure_root(origin)?;
+		if !self.paused.remove(&(pallet.clone(), function.clone())) {
+			return Err(Error::IsNotPaused.into());
+		}
+		self.deposit_event(Event::CallUnpaused { pallet, function });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Transaction Pause Module, without paused calls.
+	pub fn new() -> Self {
+		Self { paused: StorageSet::new(), events: Events::new(), _config: core::marker::PhantomData }
+	}
+
+	/// Check whether the calls of `function` of `pallet` are paused, on their own or with their
+	/// whole pallet.
+	pub fn is_paused(&self, pallet: &str, function: &str) -> bool {
+		self.paused.iter().any(|(paused_pallet, paused_function)| {
+			paused_pallet == pallet && paused_function.as_deref().is_none_or(|paused| paused == function)
+		})
+	}
+}
+
+// The calls which are paused are filtered out, by the names of their pallet and function.
+impl<T: Config, Call: CallMetadata> CallFilter<Call> for Pallet<T> {
+	fn allows(&self, call: &Call) -> bool {
+		!self.is_paused(call.pallet_name(), call.function_name())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Write the state of the pallet into `state`, so the runtime can commit to it.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for paused in &self.paused {
+			state.insert(storage_map_key("tx_pause", "paused", paused), true.encode());
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, Event};
+	use crate::support::{CallFilter, CallMetadata, DispatchError, RawOrigin};
+
+	/// A call of a test runtime, made of the names of its pallet and function.
+	struct TestCall(&'static str, &'static str);
+
+	impl CallMetadata for TestCall {
+		fn pallet_name(&self) -> &'static str {
+			self.0
+		}
+
+		fn function_name(&self) -> &'static str {
+			self.1
+		}
+	}
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		const UNPAUSABLE_PALLETS: &'static [&'static str] = &["sudo"];
+	}
+
+	#[test]
+	fn paused_calls_are_filtered_out() {
+		let mut tx_pause = super::Pallet::<TestConfig>::new();
+		let (transfer, burn, claim) =
+			(TestCall("balances", "transfer"), TestCall("balances", "burn"), TestCall("proof_of_existence", "create_claim"));
+		let pause = |tx_pause: &mut super::Pallet<TestConfig>, pallet: &str, function: Option<&str>| {
+			tx_pause.pause(RawOrigin::Root, pallet.to_string(), function.map(str::to_string))
+		};
+
+		assert_eq!(tx_pause.pause(RawOrigin::Signed("alice"), "balances".into(), None), Err(DispatchError::BadOrigin));
+		assert_eq!(pause(&mut tx_pause, "sudo", None), Err(Error::Unpausable.into()));
+		assert_eq!(pause(&mut tx_pause, "balances", Some("transfer")), Ok(()));
+		assert_eq!(pause(&mut tx_pause, "balances", Some("transfer")), Err(Error::IsPaused.into()));
+		assert!(!tx_pause.allows(&transfer));
+		assert!(tx_pause.allows(&burn) && tx_pause.allows(&claim));
+
+		// Pausing the whole pallet pauses every function, until the pallet is unpaused.
+		assert_eq!(pause(&mut tx_pause, "balances", None), Ok(()));
+		assert!(!tx_pause.allows(&burn) && tx_pause.allows(&claim));
+		assert_eq!(tx_pause.unpause(RawOrigin::Root, "balances".into(), None), Ok(()));
+		assert!(tx_pause.allows(&burn) && !tx_pause.allows(&transfer));
+		assert_eq!(tx_pause.unpause(RawOrigin::Root, "balances".into(), None), Err(Error::IsNotPaused.into()));
+		let unpaused = Event::CallUnpaused { pallet: "balances".into(), function: None };
+		assert_eq!(tx_pause.events().last().map(|record| &record.event), Some(&unpaused));
+	}
+}
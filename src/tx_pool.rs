@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::balances::WithdrawReasons;
+use crate::support::DispatchError;
+use crate::{balances, types, Runtime, RuntimeApi};
+
+/// An extrinsic waiting in the pool.
+struct PoolEntry {
+	/// The order the extrinsic was submitted in, which breaks ties between equal priorities.
+	submitted: u64,
+	/// The fee paid by the extrinsic, including its tip.
+	fee: types::Balance,
+	signed: types::SignedExtrinsic,
+}
+
+/// The pool of extrinsics submitted by users, which wait there until a block author drains them
+/// into a block.
+///
+/// Extrinsics are validated against the state of the runtime when they are submitted, taking into
+/// account the extrinsics of the same caller already waiting in the pool.
+#[derive(Default)]
+pub struct TransactionPool {
+	/// The extrinsics of each caller, in the order of their nonces.
+	pending: BTreeMap<types::AccountId, VecDeque<PoolEntry>>,
+	/// The number of extrinsics submitted so far.
+	submitted: u64,
+}
+
+impl TransactionPool {
+	/// Create an empty pool.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The number of extrinsics waiting in the pool.
+	pub fn len(&self) -> usize {
+		self.pending.values().map(VecDeque::len).sum()
+	}
+
+	/// Whether no extrinsic is waiting in the pool.
+	pub fn is_empty(&self) -> bool {
+		self.pending.is_empty()
+	}
+
+	/// The number of extrinsics of `who` waiting in the pool. Their next extrinsic must have the
+	/// nonce after them.
+	pub fn pending_of(&self, who: &types::AccountId) -> usize {
+		self.pending.get(who).map_or(0, VecDeque::len)
+	}
+
+	/// The extrinsics waiting in the pool, grouped by caller in the order of their nonces. They can
+	/// be submitted again in this order, like to a new pool.
+	pub fn pending(&self) -> impl Iterator<Item = &types::SignedExtrinsic> {
+		self.pending.values().flatten().map(|entry| &entry.signed)
+	}
+
+	/// Validate `signed` against the current state of `runtime`, and add it to the pool.
+	///
+	/// The extrinsic is refused if its signature is invalid, if it expired before the next block,
+	/// or if its nonce doesn't follow the nonce of its caller and their extrinsics already in the
+	/// pool. Its caller must also be able to pay its fee on top of the fees of those extrinsics.
+	pub fn submit_extrinsic(
+		&mut self,
+		runtime: &Runtime,
+		signed: types::SignedExtrinsic,
+	) -> Result<(), DispatchError> {
+		runtime.check_signature(&signed)?;
+		let extrinsic = &signed.extrinsic;
+		let next_block = runtime.block_number().saturating_add(1);
+		if extrinsic.valid_until.is_some_and(|valid_until| next_block > valid_until) {
+			return Err(DispatchError::Other("extrinsic expired"));
+		}
+
+		let queue = self.pending.get(&extrinsic.caller);
+		let (queued, queued_fees) = queue.map_or((0, 0), |queue| {
+			(queue.len() as types::Nonce, queue.iter().map(|entry| entry.fee).sum::<types::Balance>())
+		});
+		let nonce = runtime.nonce_of(&extrinsic.caller);
+		if signed.nonce < nonce {
+			return Err(DispatchError::Other("stale nonce"));
+		}
+		if signed.nonce < nonce + queued {
+			return Err(DispatchError::Other("extrinsic already in the pool"));
+		}
+		if signed.nonce > nonce + queued {
+			return Err(DispatchError::Other("future nonce"));
+		}
+
+		let fee = Runtime::transaction_fee(extrinsic.call.weight()).saturating_add(extrinsic.tip);
+		let spendable = runtime
+			.balance_of(&extrinsic.caller)
+			.saturating_sub(runtime.balances.locked_balance(&extrinsic.caller, WithdrawReasons::FEE));
+		if queued_fees.saturating_add(fee) > spendable {
+			return Err(balances::Error::InsufficientBalance.into());
+		}
+
+		let entry = PoolEntry { submitted: self.submitted, fee, signed };
+		self.submitted += 1;
+		self.pending.entry(entry.signed.extrinsic.caller.clone()).or_default().push_back(entry);
+		Ok(())
+	}
+
+	/// Remove every extrinsic from the pool, in priority order.
+	///
+	/// Extrinsics with a higher tip come first, and extrinsics with the same tip come in the order
+	/// they were submitted. The extrinsics of a caller always stay in the order of their nonces, so
+	/// a high tip can't jump ahead of an earlier extrinsic of the same caller.
+	pub fn drain(&mut self) -> Vec<types::SignedExtrinsic> {
+		let mut drained = Vec::with_capacity(self.len());
+		while let Some(caller) = self
+			.pending
+			.iter()
+			.filter_map(|(caller, queue)| queue.front().map(|entry| (caller, entry)))
+			.max_by_key(|(_, entry)| (entry.signed.extrinsic.tip, core::cmp::Reverse(entry.submitted)))
+			.map(|(caller, _)| caller.clone())
+		{
+			let queue = self.pending.get_mut(&caller).expect("the caller was just found in the pool");
+			drained.extend(queue.pop_front().map(|entry| entry.signed));
+			if queue.is_empty() {
+				self.pending.remove(&caller);
+			}
+		}
+		drained
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::TransactionPool;
+	use crate::support::DispatchError;
+	use crate::block_builder::BlockBuilder;
+	use crate::{balances, dev_account, types, Runtime, RuntimeCall};
+
+	fn transfer(caller: &str, amount: types::Balance, tip: types::Balance) -> types::Extrinsic {
+		types::ExtrinsicBuilder::new(dev_account(caller))
+			.call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("charlie"), amount }))
+			.tip(tip)
+			.build()
+	}
+
+	fn setup() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(dev_account("alice"), 100);
+		runtime.balances.set_balance(dev_account("bob"), 100);
+		runtime
+	}
+
+	#[test]
+	fn submit_validates_nonces_signatures_and_fees() {
+		let runtime = setup();
+		let mut pool = TransactionPool::new();
+
+		assert_eq!(pool.submit_extrinsic(&runtime, runtime.sign(transfer("alice", 10, 0), 0)), Ok(()));
+		assert_eq!(
+			pool.submit_extrinsic(&runtime, runtime.sign(transfer("alice", 10, 0), 0)).err(),
+			Some(DispatchError::Other("extrinsic already in the pool"))
+		);
+		assert_eq!(
+			pool.submit_extrinsic(&runtime, runtime.sign(transfer("alice", 10, 0), 2)).err(),
+			Some(DispatchError::Other("future nonce"))
+		);
+		assert_eq!(pool.submit_extrinsic(&runtime, runtime.sign(transfer("alice", 10, 0), 1)), Ok(()));
+
+		let mut tampered = runtime.sign(transfer("bob", 10, 0), 0);
+		tampered.extrinsic.tip = 1;
+		assert_eq!(
+			pool.submit_extrinsic(&runtime, tampered).err(),
+			Some(DispatchError::Other("invalid signature"))
+		);
+		let expired = types::ExtrinsicBuilder::new(dev_account("bob"))
+			.call(RuntimeCall::balances(balances::Call::transfer { to: dev_account("charlie"), amount: 10 }))
+			.valid_until(0)
+			.build();
+		assert_eq!(
+			pool.submit_extrinsic(&runtime, runtime.sign(expired, 0)).err(),
+			Some(DispatchError::Other("extrinsic expired"))
+		);
+
+		// The fees of Alice's two transfers in the pool count towards what she can pay for the third.
+		let fee = Runtime::transaction_fee(pallets::weights::balances::TRANSFER);
+		assert_eq!(
+			pool.submit_extrinsic(&runtime, runtime.sign(transfer("alice", 10, 100 - 2 * fee), 2)).err(),
+			Some(balances::Error::InsufficientBalance.into())
+		);
+		assert_eq!(pool.submit_extrinsic(&runtime, runtime.sign(transfer("alice", 10, 100 - 3 * fee), 2)), Ok(()));
+		assert_eq!(pool.len(), 3);
+	}
+
+	#[test]
+	fn drain_orders_by_tip_and_keeps_nonces_in_order() {
+		let mut runtime = setup();
+		let mut pool = TransactionPool::new();
+		let submitted = [("alice", 1, 0), ("alice", 9, 1), ("bob", 5, 0), ("bob", 1, 1)];
+		for (caller, tip, nonce) in submitted {
+			pool.submit_extrinsic(&runtime, runtime.sign(transfer(caller, 10, tip), nonce)).unwrap();
+		}
+
+		let drained = pool.drain();
+		assert!(pool.is_empty());
+		let order = drained
+			.iter()
+			.map(|signed| (signed.extrinsic.caller.clone(), signed.extrinsic.tip))
+			.collect::<Vec<_>>();
+		// Alice's tip of 9 has to wait for her extrinsic with a tip of 1, which comes after Bob's tip
+		// of 5. Then her tip of 9 comes before Bob's tip of 1.
+		assert_eq!(order, vec![(dev_account("bob"), 5), (dev_account("alice"), 1), (dev_account("alice"), 9), (dev_account("bob"), 1)]);
+
+		let block = BlockBuilder::new(&runtime, runtime.system.max_block_weight())
+			.seal(drained)
+			.expect("wrong block building");
+		let report = runtime.execute_block(&block).expect("wrong block execution");
+		assert!(report.all_succeeded());
+		assert_eq!(runtime.balances.balance(&dev_account("charlie")), 40);
+	}
+}
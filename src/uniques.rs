@@ -0,0 +1,335 @@
+use alloc::vec::Vec;
+
+use crate::codec::Encode;
+use crate::storage::{storage_double_map_key, storage_map_key, StorageBackend};
+use crate::support::{DispatchError, DispatchResult, Events, StorageMap, StorageSet};
+
+/// The identifier of a collection of items.
+pub type CollectionId = u32;
+/// The identifier of an item, unique within its collection.
+pub type ItemId = u32;
+
+pub trait Config: crate::system::Config {
+	/// The maximum length of the metadata of an item, in bytes.
+	const MAX_METADATA_LENGTH: usize;
+}
+
+/// A collection of items, which only its owner can mint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionDetails<AccountId> {
+	pub owner: AccountId,
+	/// The number of items in the collection.
+	pub items: u32,
+}
+
+/// The events emitted by the uniques pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId> {
+	/// A collection was created.
+	Created { collection: CollectionId, owner: AccountId },
+	/// An item was minted.
+	Issued { collection: CollectionId, item: ItemId, owner: AccountId },
+	/// An item was transferred.
+	Transferred { collection: CollectionId, item: ItemId, from: AccountId, to: AccountId },
+	/// An item was burnt.
+	Burned { collection: CollectionId, item: ItemId, owner: AccountId },
+	/// The metadata of an item was set.
+	MetadataSet { collection: CollectionId, item: ItemId },
+}
+
+/// The errors returned by the uniques pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The collection already exists.
+	InUse,
+	/// The collection does not exist.
+	UnknownCollection,
+	/// The item already exists in the collection.
+	AlreadyExists,
+	/// The item does not exist in the collection.
+	UnknownItem,
+	/// The caller doesn't own the collection or the item.
+	NoPermission,
+	/// The metadata is longer than `Config::MAX_METADATA_LENGTH`.
+	MetadataTooLong,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::InUse => "InUse",
+			Error::UnknownCollection => "UnknownCollection",
+			Error::AlreadyExists => "AlreadyExists",
+			Error::UnknownItem => "UnknownItem",
+			Error::NoPermission => "NoPermission",
+			Error::MetadataTooLong => "MetadataTooLong",
+		};
+		DispatchError::Module { pallet: "uniques", error }
+	}
+}
+
+/// The events of the uniques pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// This is the Uniques Module.
+/// It allows accounts to create collections of unique items, like NFTs, which the owner of the
+/// collection mints to other accounts, and which their owners can transfer or burn.
+///
+/// Items are keyed by their collection and their id, and each account's items are indexed by
+/// owner, so `owned` doesn't need to go through every item.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The collections, keyed by their id.
+	collections: StorageMap<CollectionId, CollectionDetails<T::AccountId>>,
+	/// The owner of each item, keyed by its collection and its id.
+	items: StorageMap<(CollectionId, ItemId), T::AccountId>,
+	/// The metadata of each item which has some, keyed like `items`.
+	metadata: StorageMap<(CollectionId, ItemId), Vec<u8>>,
+	/// The items owned by each account, so they are ordered by owner.
+	owned: StorageSet<(T::AccountId, CollectionId, ItemId)>,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Create the collection `collection`, owned by the `caller`.
+	/// This function will return an error if the collection already exists.
+	#[call_index = 0]
+	#[weight = 5_000]
+	pub fn create(&mut self, caller: T::AccountId, collection: CollectionId) -> DispatchResult {
+		if self.collections.contains_key(&collection) {
+			return Err(Error::InUse.into());
+		}
+
+		self.collections.insert(collection, CollectionDetails { owner: caller.clone(), items: 0 });
+		self.deposit_event(Event::Created { collection, owner: caller });
+		Ok(())
+	}
+
+	/// Mint `item` in `collection`, owned by `owner`. Only the owner of the collection can mint.
+	/// This function will return an error if the collection does not exist, if the caller doesn't
+	/// own it, or if the item already exists.
+	#[call_index = 1]
+	#[weight = 5_000]
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		collection: CollectionId,
+		item: ItemId,
+		owner: T::AccountId,
+	) -> DispatchResult {
+		let details = self.collections.get_mut(&collection).ok_or(Error::UnknownCollection)?;
+		if details.owner != caller {
+			return Err(Error::NoPermission.into());
+		}
+		if self.items.contains_key(&(collection, item)) {
+			return Err(Error::AlreadyExists.into());
+		}
+
+		details.items += 1;
+		self.items.insert((collection, item), owner.clone());
+		self.owned.insert((owner.clone(), collection, item));
+		self.deposit_event(Event::Issued { collection, item, owner });
+		Ok(())
+	}
+
+	/// Transfer `item` of `collection` from the `caller` to `to`.
+	/// This function will return an error if the item does not exist, or if the caller doesn't own
+	/// it.
+	#[call_index = 2]
+	#[weight = 5_000]
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: CollectionId,
+		item: ItemId,
+		to: T::AccountId,
+	) -> DispatchResult {
+		let owner = self.items.get_mut(&(collection, item)).ok_or(Error::UnknownItem)?;
+		if *owner != caller {
+			return Err(Error::NoPermission.into());
+		}
+
+		*owner = to.clone();
+		self.owned.remove(&(caller.clone(), collection, item));
+		self.owned.insert((to.clone(), collection, item));
+		self.deposit_event(Event::Transferred { collection, item, from: caller, to });
+		Ok(())
+	}
+
+	/// Burn `item` of `collection`, with its metadata. The owner of the item or of the collection
+	/// can burn it.
+	/// This function will return an error if the item does not exist, or if the caller owns
+	/// neither the item nor the collection.
+	#[call_index = 3]
+	#[weight = 5_000]
+	pub fn burn(&mut self, caller: T::AccountId, collection: CollectionId, item: ItemId) -> DispatchResult {
+		let owner = self.items.get(&(collection, item)).ok_or(Error::UnknownItem)?;
+		let details = self.collections.get_mut(&collection).ok_or(Error::UnknownCollection)?;
+		if *owner != caller && details.owner != caller {
+			return Err(Error::NoPermission.into());
+		}
+
+		details.items -= 1;
+		let owner = self.items.remove(&(collection, item)).ok_or(Error::UnknownItem)?;
+		self.metadata.remove(&(collection, item));
+		self.owned.remove(&(owner.clone(), collection, item));
+		self.deposit_event(Event::Burned { collection, item, owner });
+		Ok(())
+	}
+
+	/// Set the metadata of `item` of `collection`, replacing the metadata it had before. Only the
+	/// owner of the collection can set metadata.
+	/// This function will return an error if the metadata is too long, if the item does not exist,
+	/// or if the caller doesn't own the collection.
+	#[call_index = 4]
+	#[weight = 5_000]
+	pub fn set_metadata(
+		&mut self,
+		caller: T::AccountId,
+		collection: CollectionId,
+		item: ItemId,
+		data: Vec<u8>,
+	) -> DispatchResult {
+		if data.len() > T::MAX_METADATA_LENGTH {
+			return Err(Error::MetadataTooLong.into());
+		}
+		let details = self.collections.get(&collection).ok_or(Error::UnknownCollection)?;
+		if details.owner != caller {
+			return Err(Error::NoPermission.into());
+		}
+		if !self.items.contains_key(&(collection, item)) {
+			return Err(Error::UnknownItem.into());
+		}
+
+		self.metadata.insert((collection, item), data);
+		self.deposit_event(Event::MetadataSet { collection, item });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Uniques Module.
+	pub fn new() -> Self {
+		Self {
+			collections: StorageMap::new(),
+			items: StorageMap::new(),
+			metadata: StorageMap::new(),
+			owned: StorageSet::new(),
+			events: Events::new(),
+		}
+	}
+
+	/// Get the collection `collection`, if it exists.
+	pub fn collection(&self, collection: CollectionId) -> Option<&CollectionDetails<T::AccountId>> {
+		self.collections.get(&collection)
+	}
+
+	/// Get the owner of `item` of `collection`, if it exists.
+	pub fn owner(&self, collection: CollectionId, item: ItemId) -> Option<&T::AccountId> {
+		self.items.get(&(collection, item))
+	}
+
+	/// Get the metadata of `item` of `collection`, if it has some.
+	pub fn metadata(&self, collection: CollectionId, item: ItemId) -> Option<&[u8]> {
+		self.metadata.get(&(collection, item)).map(Vec::as_slice)
+	}
+
+	/// Get the items owned by `who`, as their collection and their id, in order.
+	pub fn owned(&self, who: &T::AccountId) -> impl Iterator<Item = (CollectionId, ItemId)> + '_ {
+		let start = (who.clone(), CollectionId::MIN, ItemId::MIN);
+		let end = (who.clone(), CollectionId::MAX, ItemId::MAX);
+		self.owned.range(start..=end).map(|(_, collection, item)| (*collection, *item))
+	}
+
+	/// Whether `who` owns a collection, or an item.
+	pub fn has_items(&self, who: &T::AccountId) -> bool {
+		self.owned(who).next().is_some() || self.collections.values().any(|details| details.owner == *who)
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The items owned
+	/// by each account are left out, since they follow from the owners of the items.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for (collection, details) in &self.collections {
+			let value = (&details.owner, details.items).encode();
+			state.insert(storage_map_key("uniques", "collections", collection), value);
+		}
+		for ((collection, item), owner) in &self.items {
+			state.insert(storage_double_map_key("uniques", "items", collection, item), owner.encode());
+		}
+		for ((collection, item), data) in &self.metadata {
+			state.insert(storage_double_map_key("uniques", "metadata", collection, item), data.encode());
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::Error;
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		const MAX_METADATA_LENGTH: usize = 4;
+	}
+
+	#[test]
+	fn collection_owners_mint_items_which_owners_transfer() {
+		let mut uniques = super::Pallet::<TestConfig>::new();
+		assert_eq!(uniques.mint("alice", 0, 1, "bob"), Err(Error::UnknownCollection.into()));
+		assert_eq!(uniques.create("alice", 0), Ok(()));
+		assert_eq!(uniques.create("bob", 0), Err(Error::InUse.into()));
+		assert_eq!(uniques.create("bob", 1), Ok(()));
+
+		assert_eq!(uniques.mint("bob", 0, 1, "bob"), Err(Error::NoPermission.into()));
+		assert_eq!(uniques.mint("alice", 0, 1, "bob"), Ok(()));
+		assert_eq!(uniques.mint("alice", 0, 1, "bob"), Err(Error::AlreadyExists.into()));
+		assert_eq!(uniques.mint("alice", 0, 2, "charlie"), Ok(()));
+		assert_eq!(uniques.mint("bob", 1, 1, "bob"), Ok(()));
+		assert_eq!(uniques.collection(0).map(|details| details.items), Some(2));
+
+		assert_eq!(uniques.transfer("alice", 0, 1, "charlie"), Err(Error::NoPermission.into()));
+		assert_eq!(uniques.transfer("bob", 0, 3, "charlie"), Err(Error::UnknownItem.into()));
+		assert_eq!(uniques.transfer("bob", 0, 1, "charlie"), Ok(()));
+		assert_eq!(uniques.owner(0, 1), Some(&"charlie"));
+		assert_eq!(uniques.owned(&"charlie").collect::<Vec<_>>(), [(0, 1), (0, 2)]);
+		assert_eq!(uniques.owned(&"bob").collect::<Vec<_>>(), [(1, 1)]);
+	}
+
+	#[test]
+	fn burn_removes_the_item_and_its_metadata() {
+		let mut uniques = super::Pallet::<TestConfig>::new();
+		assert_eq!(uniques.create("alice", 0), Ok(()));
+		assert_eq!(uniques.mint("alice", 0, 1, "bob"), Ok(()));
+		assert_eq!(uniques.mint("alice", 0, 2, "bob"), Ok(()));
+
+		assert_eq!(uniques.set_metadata("alice", 0, 1, b"too long".to_vec()), Err(Error::MetadataTooLong.into()));
+		assert_eq!(uniques.set_metadata("bob", 0, 1, b"art".to_vec()), Err(Error::NoPermission.into()));
+		assert_eq!(uniques.set_metadata("alice", 0, 3, b"art".to_vec()), Err(Error::UnknownItem.into()));
+		assert_eq!(uniques.set_metadata("alice", 0, 1, b"art".to_vec()), Ok(()));
+		assert_eq!(uniques.metadata(0, 1), Some(&b"art"[..]));
+
+		// Both the owner of the item and the owner of the collection can burn it.
+		assert_eq!(uniques.burn("charlie", 0, 1), Err(Error::NoPermission.into()));
+		assert_eq!(uniques.burn("bob", 0, 1), Ok(()));
+		assert_eq!(uniques.burn("alice", 0, 2), Ok(()));
+		assert_eq!(uniques.burn("alice", 0, 2), Err(Error::UnknownItem.into()));
+		assert_eq!(uniques.metadata(0, 1), None);
+		assert_eq!(uniques.owned(&"bob").count(), 0);
+		assert_eq!(uniques.collection(0).map(|details| details.items), Some(0));
+	}
+}
@@ -0,0 +1,272 @@
+use alloc::vec::Vec;
+
+use crate::support::{
+	CallMetadata, DispatchCall, DispatchError, DispatchResult, Events, OriginFor, RawOrigin, Weight,
+};
+
+pub trait Config: crate::system::Config {
+	/// The calls which can be batched, which is usually the `RuntimeCall` generated by
+	/// `#[macros::runtime]`.
+	type RuntimeCall: Clone + CallMetadata;
+	/// The maximum number of calls in a batch.
+	const MAX_CALLS: u32;
+	/// The weight of `call`. The calls of a batch are dispatched without an extrinsic, so their
+	/// weight is paid for with the batch.
+	fn call_weight(call: &Self::RuntimeCall) -> Weight;
+}
+
+/// The events emitted by the utility pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId> {
+	/// The call at `index` of a batch was dispatched successfully.
+	ItemCompleted { index: u32 },
+	/// The call at `index` of a batch failed with `error`.
+	ItemFailed { index: u32, error: DispatchError },
+	/// All the calls of a batch were dispatched, `failed` of them failing. `who` is the caller of
+	/// the batch, if it was dispatched with a signed origin.
+	BatchCompleted { who: Option<AccountId>, failed: u32 },
+}
+
+/// The errors returned by the utility pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The batch has more than `Config::MAX_CALLS` calls.
+	TooManyCalls,
+	/// A batch can't contain calls of the utility pallet itself.
+	NestedBatch,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::TooManyCalls => "TooManyCalls",
+			Error::NestedBatch => "NestedBatch",
+		};
+		DispatchError::Module { pallet: "utility", error }
+	}
+}
+
+/// The events of the utility pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> = Event<<T as crate::system::Config>::AccountId>;
+
+/// The runtime `T`, through which the calls of a batch are dispatched.
+pub type RuntimeOf<T> = dyn DispatchCall<OriginFor<T>, <T as Config>::RuntimeCall>;
+
+/// This is the Utility Module.
+/// It allows an account to dispatch a batch of calls with a single extrinsic, either independently
+/// of each other with `batch`, or as a whole with `batch_all`.
+///
+/// The calls are dispatched through the runtime itself, which is the context of this pallet, see
+/// `#[dispatch_context(self)]` in `#[macros::runtime]`. The pallet is taken out of the runtime
+/// while it dispatches a batch, so a batch can't contain calls of this pallet, and the runtime
+/// refuses the calls of this pallet nested deeper in a batch, like a batch in a sudo call.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Dispatch each of `calls` with the origin of the batch, in order, like the signed origin of
+	/// its caller, or the root origin of a sudo call. A call which fails doesn't stop the batch: its
+	/// error is emitted with `Event::ItemFailed`, and the next calls are still dispatched.
+	/// This function will return an error if the batch has too many calls, or calls of this
+	/// pallet.
+	#[call_index = 0]
+	#[weight = calls.iter().fold(10_000u64, |weight, call| weight.saturating_add(T::call_weight(call)))]
+	pub fn batch(
+		&mut self,
+		origin: OriginFor<T>,
+		calls: Vec<T::RuntimeCall>,
+		runtime: &mut RuntimeOf<T>,
+	) -> DispatchResult {
+		Self::ensure_batchable(&calls)?;
+
+		let mut failed = 0u32;
+		for (index, call) in (0u32..).zip(calls) {
+			match runtime.dispatch_call(origin.clone(), call) {
+				Ok(_) => self.deposit_event(Event::ItemCompleted { index }),
+				Err(error) => {
+					failed += 1;
+					self.deposit_event(Event::ItemFailed { index, error });
+				},
+			}
+		}
+		self.deposit_event(Event::BatchCompleted { who: Self::caller(origin), failed });
+		Ok(())
+	}
+
+	/// Dispatch each of `calls` with the origin of the batch, in order, as a whole: when one of them
+	/// fails, the calls dispatched before it are reverted, and the batch fails with its error.
+	/// This function will return an error if the batch has too many calls, or calls of this
+	/// pallet.
+	#[call_index = 1]
+	#[weight = calls.iter().fold(10_000u64, |weight, call| weight.saturating_add(T::call_weight(call)))]
+	pub fn batch_all(
+		&mut self,
+		origin: OriginFor<T>,
+		calls: Vec<T::RuntimeCall>,
+		runtime: &mut RuntimeOf<T>,
+	) -> DispatchResult {
+		Self::ensure_batchable(&calls)?;
+
+		runtime.transactional(&mut |runtime| {
+			for call in &calls {
+				runtime.dispatch_call(origin.clone(), call.clone())?;
+			}
+			Ok(())
+		})?;
+
+		for index in 0..calls.len() as u32 {
+			self.deposit_event(Event::ItemCompleted { index });
+		}
+		self.deposit_event(Event::BatchCompleted { who: Self::caller(origin), failed: 0 });
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Utility Module.
+	pub fn new() -> Self {
+		Self { events: Events::new() }
+	}
+
+	/// The caller of a batch dispatched with `origin`, if it is signed.
+	fn caller(origin: OriginFor<T>) -> Option<T::AccountId> {
+		match origin {
+			RawOrigin::Signed(who) => Some(who),
+			_ => None,
+		}
+	}
+
+	/// Check that `calls` can be dispatched as a batch.
+	fn ensure_batchable(calls: &[T::RuntimeCall]) -> DispatchResult {
+		if calls.len() > T::MAX_CALLS as usize {
+			return Err(Error::TooManyCalls.into());
+		}
+		if calls.iter().any(|call| call.pallet_name() == "utility") {
+			return Err(Error::NestedBatch.into());
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, Event};
+	use crate::support::{
+		CallMetadata, DispatchCall, DispatchError, DispatchResult, DispatchResultWithOutput, RawOrigin,
+	};
+
+	/// A call of a test runtime, made of the names of its pallet and function. Calls whose function
+	/// is `fail` fail.
+	#[derive(Debug, Clone, PartialEq)]
+	struct TestCall(&'static str, &'static str);
+
+	impl CallMetadata for TestCall {
+		fn pallet_name(&self) -> &'static str {
+			self.0
+		}
+
+		fn function_name(&self) -> &'static str {
+			self.1
+		}
+	}
+
+	/// A test runtime, which records the calls it dispatched.
+	#[derive(Default)]
+	struct TestRuntime {
+		dispatched: Vec<(RawOrigin<&'static str>, TestCall)>,
+	}
+
+	impl DispatchCall<RawOrigin<&'static str>, TestCall> for TestRuntime {
+		fn dispatch_call(&mut self, origin: RawOrigin<&'static str>, call: TestCall) -> DispatchResultWithOutput {
+			if call.1 == "fail" {
+				return Err(DispatchError::Other("failed"));
+			}
+			self.dispatched.push((origin, call));
+			Ok(None)
+		}
+
+		fn transactional(
+			&mut self,
+			f: &mut dyn FnMut(&mut dyn DispatchCall<RawOrigin<&'static str>, TestCall>) -> DispatchResult,
+		) -> DispatchResult {
+			let snapshot = self.dispatched.clone();
+			let result = f(self);
+			if result.is_err() {
+				self.dispatched = snapshot;
+			}
+			result
+		}
+	}
+
+	crate::construct_mock_runtime!(struct TestConfig);
+
+	impl super::Config for TestConfig {
+		type RuntimeCall = TestCall;
+		const MAX_CALLS: u32 = 3;
+		fn call_weight(_call: &Self::RuntimeCall) -> crate::support::Weight {
+			1_000
+		}
+	}
+
+	#[test]
+	fn batch_dispatches_every_call() {
+		let mut utility = super::Pallet::<TestConfig>::new();
+		let mut runtime = TestRuntime::default();
+		let transfer = TestCall("balances", "transfer");
+		let fail = TestCall("balances", "fail");
+
+		let too_many = vec![transfer.clone(); 4];
+		assert_eq!(utility.batch(RawOrigin::Signed("alice"), too_many, &mut runtime), Err(Error::TooManyCalls.into()));
+		let nested = vec![TestCall("utility", "batch")];
+		assert_eq!(utility.batch(RawOrigin::Signed("alice"), nested, &mut runtime), Err(Error::NestedBatch.into()));
+
+		let calls = vec![transfer.clone(), fail, transfer.clone()];
+		assert_eq!(utility.batch(RawOrigin::Signed("alice"), calls, &mut runtime), Ok(()));
+		let signed = (RawOrigin::Signed("alice"), transfer);
+		assert_eq!(runtime.dispatched, [signed.clone(), signed]);
+		let events = utility.take_events().into_iter().map(|record| record.event).collect::<Vec<_>>();
+		assert_eq!(
+			events,
+			[
+				Event::ItemCompleted { index: 0 },
+				Event::ItemFailed { index: 1, error: DispatchError::Other("failed") },
+				Event::ItemCompleted { index: 2 },
+				Event::BatchCompleted { who: Some("alice"), failed: 1 },
+			]
+		);
+	}
+
+	#[test]
+	fn batch_all_reverts_every_call_when_one_fails() {
+		let mut utility = super::Pallet::<TestConfig>::new();
+		let mut runtime = TestRuntime::default();
+		let transfer = TestCall("balances", "transfer");
+		let fail = TestCall("balances", "fail");
+
+		let calls = vec![transfer.clone(), fail];
+		assert_eq!(utility.batch_all(RawOrigin::Signed("alice"), calls, &mut runtime), Err(DispatchError::Other("failed")));
+		assert!(runtime.dispatched.is_empty());
+		assert!(utility.events().is_empty());
+
+		let calls = vec![transfer.clone(), transfer.clone()];
+		assert_eq!(utility.batch_all(RawOrigin::Signed("alice"), calls, &mut runtime), Ok(()));
+		assert_eq!(runtime.dispatched.len(), 2);
+		assert_eq!(utility.events().len(), 3);
+
+		// A batch of the root origin, like in a sudo call, dispatches its calls with the root origin.
+		assert_eq!(utility.batch_all(RawOrigin::Root, vec![transfer.clone()], &mut runtime), Ok(()));
+		assert_eq!(runtime.dispatched.last(), Some(&(RawOrigin::Root, transfer)));
+	}
+}
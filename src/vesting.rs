@@ -0,0 +1,309 @@
+use alloc::vec::Vec;
+
+use num::traits::{Saturating, SaturatingMul, Zero};
+
+use crate::balances::{LockIdentifier, WithdrawReasons};
+use crate::codec::{self, Decode, Encode};
+use crate::storage::{storage_map_key, StorageBackend};
+use crate::support::{DispatchError, DispatchResult, Events, HooksWithContext, StorageMap};
+
+/// The identifier of the lock which holds the vesting funds of an account in the balances pallet.
+pub const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config:
+	crate::balances::Config<Balance: SaturatingMul, BlockNumber: Into<<Self as crate::balances::Config>::Balance>>
+{
+	/// The minimum amount of a vested transfer.
+	const MIN_VESTED_TRANSFER: Self::Balance;
+	/// The maximum number of vesting schedules an account can have at the same time.
+	const MAX_VESTING_SCHEDULES: u32;
+}
+
+/// Funds which unlock linearly: `locked` is locked until `starting_block`, then `per_block` of it
+/// unlocks at every block until nothing is left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VestingInfo<Balance, BlockNumber> {
+	/// The amount which is locked before the schedule starts.
+	pub locked: Balance,
+	/// The amount which unlocks at every block once the schedule started.
+	pub per_block: Balance,
+	pub starting_block: BlockNumber,
+}
+
+impl<Balance, BlockNumber> VestingInfo<Balance, BlockNumber>
+where
+	Balance: Copy + Saturating + SaturatingMul + Zero,
+	BlockNumber: Copy + Into<Balance> + PartialOrd + Saturating,
+{
+	/// The amount which is still locked at `block_number`.
+	pub fn locked_at(&self, block_number: BlockNumber) -> Balance {
+		if block_number <= self.starting_block {
+			return self.locked;
+		}
+		let elapsed: Balance = block_number.saturating_sub(self.starting_block).into();
+		self.locked.saturating_sub(elapsed.saturating_mul(&self.per_block))
+	}
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for VestingInfo<Balance, BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.locked.encode_to(dest);
+		self.per_block.encode_to(dest);
+		self.starting_block.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for VestingInfo<Balance, BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, codec::Error> {
+		Ok(Self {
+			locked: Balance::decode(input)?,
+			per_block: Balance::decode(input)?,
+			starting_block: BlockNumber::decode(input)?,
+		})
+	}
+}
+
+/// The events emitted by the vesting pallet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<AccountId, Balance> {
+	/// Funds were transferred to an account with a vesting schedule.
+	VestingCreated { who: AccountId, locked: Balance },
+	/// The amount still vesting for an account was updated.
+	VestingUpdated { who: AccountId, unvested: Balance },
+	/// All the funds vesting for an account were unlocked.
+	VestingCompleted { who: AccountId },
+}
+
+/// The errors returned by the vesting pallet.
+#[macros::error]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The account has no vesting schedule.
+	NotVesting,
+	/// The amount of the vested transfer is less than `Config::MIN_VESTED_TRANSFER`.
+	AmountLow,
+	/// The schedule unlocks nothing per block.
+	InvalidScheduleParams,
+	/// The account already has `Config::MAX_VESTING_SCHEDULES` vesting schedules.
+	AtMaxVestingSchedules,
+}
+
+impl From<Error> for DispatchError {
+	fn from(error: Error) -> Self {
+		let error = match error {
+			Error::NotVesting => "NotVesting",
+			Error::AmountLow => "AmountLow",
+			Error::InvalidScheduleParams => "InvalidScheduleParams",
+			Error::AtMaxVestingSchedules => "AtMaxVestingSchedules",
+		};
+		DispatchError::Module { pallet: "vesting", error }
+	}
+}
+
+/// The events of the vesting pallet for the runtime `T`, as collected by `#[macros::runtime]`.
+pub type EventOf<T> =
+	Event<<T as crate::system::Config>::AccountId, <T as crate::balances::Config>::Balance>;
+
+/// This is the Vesting Module.
+/// It allows accounts to transfer funds which unlock linearly over time, see `VestingInfo`. The
+/// funds which are still vesting are locked in the balances pallet, and the lock is lowered at the
+/// start of every block.
+///
+/// Like for staking, the lock only forbids transfers, so vesting funds can still pay fees.
+#[macros::storage]
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The vesting schedules of each account, in the order they were created.
+	#[allow(clippy::type_complexity)]
+	vesting: StorageMap<T::AccountId, Vec<VestingInfo<T::Balance, T::BlockNumber>>>,
+	/// The block being executed, which the locks are computed at.
+	block_number: T::BlockNumber,
+	/// All the events emitted by this pallet with the phase they were emitted in, oldest first.
+	events: Events<Event<T::AccountId, T::Balance>>,
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Transfer `schedule.locked` from the `caller` to `target`, where it vests following
+	/// `schedule`.
+	/// This function will return an error if the amount is too low, if the schedule unlocks
+	/// nothing per block, if the target already has too many vesting schedules, or if the transfer
+	/// fails.
+	#[call_index = 0]
+	#[weight = 10_000]
+	pub fn vested_transfer(
+		&mut self,
+		caller: T::AccountId,
+		target: T::AccountId,
+		schedule: VestingInfo<T::Balance, T::BlockNumber>,
+		balances: &mut crate::balances::Pallet<T>,
+	) -> DispatchResult {
+		if schedule.locked < T::MIN_VESTED_TRANSFER {
+			return Err(Error::AmountLow.into());
+		}
+		if schedule.per_block.is_zero() {
+			return Err(Error::InvalidScheduleParams.into());
+		}
+		let schedules = self.vesting.get(&target).map_or(0, Vec::len);
+		if schedules >= T::MAX_VESTING_SCHEDULES as usize {
+			return Err(Error::AtMaxVestingSchedules.into());
+		}
+
+		balances.do_transfer(caller, target.clone(), schedule.locked)?;
+		self.vesting.entry(target.clone()).or_default().push(schedule);
+		self.deposit_event(Event::VestingCreated { who: target.clone(), locked: schedule.locked });
+		self.update_lock(balances, &target);
+		Ok(())
+	}
+
+	/// Unlock the funds of the `caller` which vested by the current block, without waiting for the
+	/// start of the next block.
+	/// This function will return an error if the caller has no vesting schedule.
+	#[call_index = 1]
+	#[weight = 5_000]
+	pub fn vest(&mut self, caller: T::AccountId, balances: &mut crate::balances::Pallet<T>) -> DispatchResult {
+		if !self.vesting.contains_key(&caller) {
+			return Err(Error::NotVesting.into());
+		}
+		self.update_lock(balances, &caller);
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Vesting Module.
+	pub fn new() -> Self {
+		Self {
+			vesting: StorageMap::new(),
+			block_number: T::BlockNumber::zero(),
+			events: Events::new(),
+		}
+	}
+
+	/// Get the vesting schedules of `who`, in the order they were created.
+	pub fn vesting(&self, who: &T::AccountId) -> &[VestingInfo<T::Balance, T::BlockNumber>] {
+		self.vesting.get(who).map_or(&[], Vec::as_slice)
+	}
+
+	/// Get the amount of `who` which is still vesting at the current block.
+	pub fn vesting_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.vesting(who)
+			.iter()
+			.fold(T::Balance::zero(), |locked, schedule| locked.saturating_add(schedule.locked_at(self.block_number)))
+	}
+
+	/// Set the lock of `who` to the amount still vesting at the current block. The schedules which
+	/// completed are removed, and the lock is removed once nothing is vesting anymore.
+	fn update_lock(&mut self, balances: &mut crate::balances::Pallet<T>, who: &T::AccountId) {
+		let unvested = self.vesting_balance(who);
+		let block_number = self.block_number;
+		if let Some(schedules) = self.vesting.get_mut(who) {
+			schedules.retain(|schedule| !schedule.locked_at(block_number).is_zero());
+		}
+
+		if unvested.is_zero() {
+			self.vesting.remove(who);
+			balances.remove_lock(VESTING_ID, who);
+			self.deposit_event(Event::VestingCompleted { who: who.clone() });
+		} else {
+			balances.set_lock(VESTING_ID, who, unvested, WithdrawReasons::TRANSFER);
+			self.deposit_event(Event::VestingUpdated { who: who.clone(), unvested });
+		}
+	}
+}
+
+impl<T: Config> HooksWithContext<T::BlockNumber, crate::balances::Pallet<T>> for Pallet<T> {
+	/// Keep the number of the block, and lower the lock of every account to what is still vesting
+	/// at that block.
+	fn on_initialize(&mut self, balances: &mut crate::balances::Pallet<T>, block_number: T::BlockNumber) {
+		self.block_number = block_number;
+		let vesting = self.vesting.keys().cloned().collect::<Vec<_>>();
+		for who in vesting {
+			self.update_lock(balances, &who);
+		}
+	}
+}
+
+impl<T: Config> Pallet<T>
+where
+	T::AccountId: Encode,
+	T::Balance: Encode,
+	T::BlockNumber: Encode,
+{
+	/// Write the state of the pallet into `state`, so the runtime can commit to it. The block
+	/// number is part of the state of the system pallet, and the phase and the events are not
+	/// part of the state.
+	pub fn store_state(&self, state: &mut impl StorageBackend) {
+		for (who, schedules) in &self.vesting {
+			state.insert(storage_map_key("vesting", "vesting", who), schedules.encode());
+		}
+	}
+}
+
+impl<T: Config> Default for Pallet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+	use super::{Error, VestingInfo, VESTING_ID};
+	use crate::balances::{self, WithdrawReasons};
+	use crate::support::HooksWithContext;
+
+	crate::construct_mock_runtime!(struct TestConfig: balances);
+
+	impl super::Config for TestConfig {
+		const MIN_VESTED_TRANSFER: Self::Balance = 10;
+		const MAX_VESTING_SCHEDULES: u32 = 1;
+	}
+
+	#[test]
+	fn vested_funds_unlock_linearly() {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		balances.set_balance("alice", 100);
+		let mut vesting = super::Pallet::<TestConfig>::new();
+		vesting.on_initialize(&mut balances, 1);
+		let schedule = VestingInfo { locked: 40, per_block: 10, starting_block: 2 };
+
+		let low = VestingInfo { locked: 5, ..schedule };
+		assert_eq!(vesting.vested_transfer("alice", "bob", low, &mut balances), Err(Error::AmountLow.into()));
+		let never = VestingInfo { per_block: 0, ..schedule };
+		let result = vesting.vested_transfer("alice", "bob", never, &mut balances);
+		assert_eq!(result, Err(Error::InvalidScheduleParams.into()));
+		assert_eq!(vesting.vested_transfer("alice", "bob", schedule, &mut balances), Ok(()));
+		let result = vesting.vested_transfer("alice", "bob", schedule, &mut balances);
+		assert_eq!(result, Err(Error::AtMaxVestingSchedules.into()));
+		assert_eq!(balances.balance(&"bob"), 40);
+		assert_eq!(balances.locked_balance(&"bob", WithdrawReasons::TRANSFER), 40);
+		assert_eq!(vesting.vest("alice", &mut balances), Err(Error::NotVesting.into()));
+
+		// Nothing unlocks before the starting block, then 10 unlocks at every block.
+		vesting.on_initialize(&mut balances, 2);
+		assert_eq!(vesting.vesting_balance(&"bob"), 40);
+		vesting.on_initialize(&mut balances, 4);
+		assert_eq!(balances.locked_balance(&"bob", WithdrawReasons::TRANSFER), 20);
+		assert_eq!(balances.transfer("bob", "charlie", 30), Err(balances::Error::BalanceLocked.into()));
+		assert_eq!(balances.transfer("bob", "charlie", 20), Ok(()));
+
+		vesting.on_initialize(&mut balances, 6);
+		assert!(vesting.vesting(&"bob").is_empty());
+		assert_eq!(balances.locks(&"bob").filter(|(id, _)| **id == VESTING_ID).count(), 0);
+	}
+
+	#[test]
+	fn vest_unlocks_the_funds_vested_by_the_current_block() {
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		balances.set_balance("alice", 100);
+		let mut vesting = super::Pallet::<TestConfig>::new();
+		vesting.on_initialize(&mut balances, 3);
+
+		// The schedule already started, so part of the transfer is unlocked right away.
+		let schedule = VestingInfo { locked: 40, per_block: 10, starting_block: 1 };
+		assert_eq!(vesting.vested_transfer("alice", "bob", schedule, &mut balances), Ok(()));
+		assert_eq!(balances.locked_balance(&"bob", WithdrawReasons::TRANSFER), 20);
+		assert_eq!(vesting.vest("bob", &mut balances), Ok(()));
+		assert_eq!(vesting.vesting(&"bob"), [schedule]);
+	}
+}
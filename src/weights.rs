@@ -0,0 +1,89 @@
+//! The weights of the calls of the pallets, which are the median time of their worst case as
+//! measured on the runtime of the node, converted with `support::WEIGHT_PER_NANOS`. The calls
+//! declare them with their `#[weight = ...]` attribute, so the fees follow what the calls cost.
+//!
+//! This file is generated by the `benchmark weights` command of the node, regenerate it with
+//! `cargo run --release -- benchmark weights` instead of editing it.
+
+use crate::support::Weight;
+
+/// The weights of the calls of the `balances` pallet.
+pub mod balances {
+	use super::Weight;
+
+	/// The weight of `transfer`.
+	pub const TRANSFER: Weight = 3_100;
+
+	/// The weight of `transfer_some`.
+	pub const TRANSFER_SOME: Weight = 3_620;
+
+	/// The weight of `transfer_keep_alive`.
+	pub const TRANSFER_KEEP_ALIVE: Weight = 2_190;
+
+	/// The weight of `transfer_all`.
+	pub const TRANSFER_ALL: Weight = 3_350;
+
+	/// The weight of `transfer_multi`, per unit.
+	pub const TRANSFER_MULTI_PER_TARGET: Weight = 1_536;
+
+	/// The weight of `create_escrow`.
+	pub const CREATE_ESCROW: Weight = 1_740;
+
+	/// The weight of `cancel_escrow`.
+	pub const CANCEL_ESCROW: Weight = 1_720;
+
+	/// The weight of `force_set_balance`.
+	pub const FORCE_SET_BALANCE: Weight = 1_460;
+
+	/// The weight of `mint_into`.
+	pub const MINT_INTO: Weight = 1_710;
+
+	/// The weight of `burn_from`.
+	pub const BURN_FROM: Weight = 2_550;
+}
+
+/// The weights of the calls of the `proof_of_existence` pallet.
+pub mod proof_of_existence {
+	use super::Weight;
+
+	/// The weight of `create_claim`.
+	pub const CREATE_CLAIM: Weight = 6_850;
+
+	/// The weight of `create_claim_with_expiry`.
+	pub const CREATE_CLAIM_WITH_EXPIRY: Weight = 6_930;
+
+	/// The weight of `revoke_claim`.
+	pub const REVOKE_CLAIM: Weight = 2_800;
+
+	/// The weight of `transfer_claim`.
+	pub const TRANSFER_CLAIM: Weight = 3_910;
+
+	/// The weight of `renounce_claim`.
+	pub const RENOUNCE_CLAIM: Weight = 2_790;
+
+	/// The weight of `set_claim_metadata`.
+	pub const SET_CLAIM_METADATA: Weight = 1_650;
+
+	/// The weight of `create_shared_claim`.
+	pub const CREATE_SHARED_CLAIM: Weight = 17_700;
+
+	/// The weight of `approve_revoke`.
+	pub const APPROVE_REVOKE: Weight = 14_650;
+
+	/// The weight of `query_claim`.
+	pub const QUERY_CLAIM: Weight = 1_570;
+}
+
+/// The weights of the calls of the `treasury` pallet.
+pub mod treasury {
+	use super::Weight;
+
+	/// The weight of `propose_spend`.
+	pub const PROPOSE_SPEND: Weight = 2_680;
+
+	/// The weight of `approve_proposal`.
+	pub const APPROVE_PROPOSAL: Weight = 2_090;
+
+	/// The weight of `reject_proposal`.
+	pub const REJECT_PROPOSAL: Weight = 3_000;
+}